@@ -5,6 +5,7 @@ use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use nostr::message::EventIdPrefix;
 use nostr::secp256k1::XOnlyPublicKey;
 use nostr::{EventId, SubscriptionFilter as SubscriptionFilterSdk, Timestamp};
 
@@ -44,12 +45,16 @@ impl SubscriptionFilter {
 
     pub fn id(self: Arc<Self>, id: String) -> Result<Arc<Self>> {
         let mut builder = unwrap_or_clone_arc(self);
-        builder.sub_filter = builder.sub_filter.id(id);
+        builder.sub_filter = builder.sub_filter.id(EventIdPrefix::new(id)?);
 
         Ok(Arc::new(builder))
     }
 
     pub fn ids(self: Arc<Self>, ids: Vec<String>) -> Result<Arc<Self>> {
+        let ids = ids
+            .into_iter()
+            .map(EventIdPrefix::new)
+            .collect::<Result<Vec<EventIdPrefix>, _>>()?;
         let mut builder = unwrap_or_clone_arc(self);
         builder.sub_filter = builder.sub_filter.ids(ids);
 
@@ -45,7 +45,8 @@ impl Client {
         for filter in filters.into_iter() {
             new_filters.push(filter.as_ref().deref().clone());
         }
-        Ok(self.client.lock().subscribe(new_filters)?)
+        self.client.lock().subscribe(new_filters)?;
+        Ok(())
     }
 
     pub fn send_event(&self, event: Arc<Event>) -> Result<()> {
@@ -0,0 +1,472 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Nostr CLI
+
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use clap::{Args, Parser, Subcommand};
+use nostr_sdk::nostr::nips::nip11::RelayInformationDocument;
+use nostr_sdk::prelude::*;
+use serde_json::{json, Value};
+
+#[derive(Debug, Parser)]
+#[command(name = "nostr-cli", version, about = "Nostr command line interface")]
+struct Cli {
+    /// Relay url (can be repeated)
+    #[arg(
+        short,
+        long = "relay",
+        global = true,
+        env = "NOSTR_RELAYS",
+        value_delimiter = ','
+    )]
+    relays: Vec<String>,
+    /// Secret key (hex or bech32)
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "NOSTR_SECRET_KEY",
+        hide_env_values = true
+    )]
+    secret_key: Option<String>,
+    /// Print JSON output, one value per line
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Keys management
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Publish text note or custom event
+    Publish(PublishArgs),
+    /// Sign unsigned event JSON read from stdin
+    ///
+    /// Accept `kind`, `content`, `tags` and optionally `created_at` (ex. NIP-07 event templates).
+    Sign,
+    /// Query events
+    Query(QueryArgs),
+    /// Direct messages
+    #[command(subcommand)]
+    Dm(DmCommand),
+    /// Relays
+    #[command(subcommand)]
+    Relay(RelayCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysCommand {
+    /// Generate new keys
+    Generate,
+    /// Convert key or event id between hex and bech32
+    Convert {
+        /// `npub`, `nsec`, `note` or hex string
+        value: String,
+        /// Interpret hex string as secret key
+        #[arg(long, conflicts_with = "note")]
+        secret: bool,
+        /// Interpret hex string as event id
+        #[arg(long)]
+        note: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+struct PublishArgs {
+    /// Content
+    content: String,
+    /// Kind
+    #[arg(short, long, default_value = "1")]
+    kind: u64,
+    /// Tag, as comma separated values (ex. `t,nostr`). Can be repeated
+    #[arg(short, long = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+struct QueryArgs {
+    /// Event id (can be repeated)
+    #[arg(long = "id")]
+    ids: Vec<String>,
+    /// Author public key (can be repeated)
+    #[arg(short, long = "author")]
+    authors: Vec<String>,
+    /// Kind (can be repeated)
+    #[arg(short, long = "kind")]
+    kinds: Vec<u64>,
+    /// Referenced public key (can be repeated)
+    #[arg(short, long = "pubkey")]
+    pubkeys: Vec<String>,
+    /// Hashtag (can be repeated)
+    #[arg(long = "hashtag")]
+    hashtags: Vec<String>,
+    /// Full text search (NIP-50)
+    #[arg(long)]
+    search: Option<String>,
+    /// Since (UNIX timestamp)
+    #[arg(long)]
+    since: Option<u64>,
+    /// Until (UNIX timestamp)
+    #[arg(long)]
+    until: Option<u64>,
+    /// Max number of events
+    #[arg(short, long)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Subcommand)]
+enum DmCommand {
+    /// Send direct message (NIP-04)
+    Send {
+        /// Receiver public key
+        receiver: String,
+        /// Message
+        message: String,
+    },
+    /// List conversations, or the messages of a conversation
+    List {
+        /// Show the messages exchanged with this public key
+        #[arg(long)]
+        with: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RelayCommand {
+    /// Get relay information document (NIP-11)
+    Info {
+        /// Relay url
+        url: String,
+    },
+    /// Check which relays have an event
+    Check {
+        /// Event id (`note` or hex)
+        event_id: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let output = Output { json: cli.json };
+
+    match &cli.command {
+        Command::Keys(cmd) => keys(cmd, &output),
+        Command::Sign => sign(&cli),
+        Command::Relay(RelayCommand::Info { url }) => {
+            let document = RelayInformationDocument::get(Url::parse(url)?, None).await?;
+            output.value(serde_json::to_value(document)?, None);
+            Ok(())
+        }
+        Command::Publish(args) => {
+            let client = connect(&cli, true).await?;
+            let tags: Vec<Tag> = args
+                .tags
+                .iter()
+                .map(|tag| Tag::parse(tag.split(',').collect()))
+                .collect::<Result<_, _>>()?;
+            let event_id = client
+                .send_event(
+                    EventBuilder::new(Kind::from(args.kind), &args.content, &tags)
+                        .to_event(&client.keys())?,
+                )
+                .await?
+                .value;
+            output.event_id(&event_id)?;
+            client.shutdown().await?;
+            Ok(())
+        }
+        Command::Query(args) => {
+            let client = connect(&cli, false).await?;
+            let events: Vec<Event> = client.get_events_of(vec![filter(args)?]).await?;
+            for event in events.iter() {
+                output.event(event)?;
+            }
+            client.shutdown().await?;
+            Ok(())
+        }
+        Command::Dm(cmd) => {
+            let client = connect(&cli, true).await?;
+            dm(&client, cmd, &output).await?;
+            client.shutdown().await?;
+            Ok(())
+        }
+        Command::Relay(RelayCommand::Check { event_id }) => {
+            let client = connect(&cli, false).await?;
+            let event_id: EventId = parse_event_id(event_id)?;
+            let report = client
+                .check_event_propagation::<String>(event_id, Vec::new())
+                .await?;
+            for url in report.found.iter() {
+                output.value(
+                    json!({"relay": url, "found": true}),
+                    Some(format!("found   {url}")),
+                );
+            }
+            for url in report.missing.iter() {
+                output.value(
+                    json!({"relay": url, "found": false}),
+                    Some(format!("missing {url}")),
+                );
+            }
+            for (url, e) in report.failed.iter() {
+                output.value(
+                    json!({"relay": url, "error": e}),
+                    Some(format!("failed  {url}: {e}")),
+                );
+            }
+            client.shutdown().await?;
+            Ok(())
+        }
+    }
+}
+
+fn secret_keys(cli: &Cli) -> Result<Keys> {
+    match &cli.secret_key {
+        Some(secret_key) => Ok(Keys::from_sk_str(secret_key)?),
+        None => Err("secret key required: use --secret-key or NOSTR_SECRET_KEY".into()),
+    }
+}
+
+/// Client connected to the relays of the command line
+///
+/// Read only commands don't need the secret key: random keys are used if missing.
+async fn connect(cli: &Cli, require_secret_key: bool) -> Result<Client> {
+    if cli.relays.is_empty() {
+        return Err("at least one relay required: use --relay or NOSTR_RELAYS".into());
+    }
+    let keys: Keys = match &cli.secret_key {
+        None if !require_secret_key => Keys::generate(),
+        _ => secret_keys(cli)?,
+    };
+    let client = Client::new(&keys);
+    for url in cli.relays.iter() {
+        client.add_relay(url.as_str(), None).await?;
+        client.connect_relay(url.as_str(), true).await?;
+    }
+    Ok(client)
+}
+
+fn keys(cmd: &KeysCommand, output: &Output) -> Result<()> {
+    match cmd {
+        KeysCommand::Generate => {
+            let keys = Keys::generate();
+            let public_key = keys.public_key();
+            let secret_key = keys.secret_key()?;
+            output.value(
+                json!({
+                    "public_key": public_key.to_string(),
+                    "npub": public_key.to_bech32()?,
+                    "secret_key": secret_key.display_secret().to_string(),
+                    "nsec": secret_key.to_bech32()?,
+                }),
+                Some(format!(
+                    "Public key: {}\n            {}\nSecret key: {}\n            {}",
+                    public_key,
+                    public_key.to_bech32()?,
+                    secret_key.display_secret(),
+                    secret_key.to_bech32()?
+                )),
+            );
+        }
+        KeysCommand::Convert {
+            value,
+            secret,
+            note,
+        } => {
+            let converted: String = if value.starts_with("npub") {
+                XOnlyPublicKey::from_bech32(value)?.to_string()
+            } else if value.starts_with("nsec") {
+                SecretKey::from_bech32(value)?.display_secret().to_string()
+            } else if value.starts_with("note") {
+                EventId::from_bech32(value)?.to_hex()
+            } else if *secret {
+                SecretKey::from_str(value)?.to_bech32()?
+            } else if *note {
+                EventId::from_hex(value)?.to_bech32()?
+            } else {
+                XOnlyPublicKey::from_str(value)?.to_bech32()?
+            };
+            output.value(json!(converted), Some(converted.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn sign(cli: &Cli) -> Result<()> {
+    let keys: Keys = secret_keys(cli)?;
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let template: Value = serde_json::from_str(&input)?;
+
+    let kind: Kind = Kind::from(template["kind"].as_u64().ok_or("missing kind")?);
+    let content: &str = template["content"].as_str().unwrap_or_default();
+    let tags: Vec<Tag> = match template.get("tags") {
+        Some(tags) => serde_json::from_value(tags.clone())?,
+        None => Vec::new(),
+    };
+
+    let mut unsigned = EventBuilder::new(kind, content, &tags).to_unsigned_event(keys.public_key());
+    if let Some(created_at) = template["created_at"].as_u64() {
+        unsigned.created_at = Timestamp::from(created_at);
+        unsigned.id = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &unsigned.tags,
+            &unsigned.content,
+        );
+    }
+    let event: Event = unsigned.sign(&keys)?;
+    // Signed events are always printed as JSON, to be piped to other tools
+    println!("{}", event.as_json()?);
+    Ok(())
+}
+
+fn filter(args: &QueryArgs) -> Result<SubscriptionFilter> {
+    let mut filter = SubscriptionFilter::new();
+    if !args.ids.is_empty() {
+        let ids: Vec<EventId> = args
+            .ids
+            .iter()
+            .map(|id| parse_event_id(id))
+            .collect::<Result<_>>()?;
+        filter = filter.ids(ids);
+    }
+    if !args.authors.is_empty() {
+        filter = filter.authors(parse_public_keys(&args.authors)?);
+    }
+    if !args.kinds.is_empty() {
+        filter = filter.kinds(args.kinds.iter().map(|k| Kind::from(*k)).collect());
+    }
+    if !args.pubkeys.is_empty() {
+        filter = filter.pubkeys(parse_public_keys(&args.pubkeys)?);
+    }
+    if !args.hashtags.is_empty() {
+        filter = filter.hashtags(args.hashtags.clone());
+    }
+    if let Some(search) = &args.search {
+        filter = filter.search(search);
+    }
+    if let Some(since) = args.since {
+        filter = filter.since(Timestamp::from(since));
+    }
+    if let Some(until) = args.until {
+        filter = filter.until(Timestamp::from(until));
+    }
+    if let Some(limit) = args.limit {
+        filter = filter.limit(limit);
+    }
+    Ok(filter)
+}
+
+async fn dm(client: &Client, cmd: &DmCommand, output: &Output) -> Result<()> {
+    match cmd {
+        DmCommand::Send { receiver, message } => {
+            let receiver = parse_public_key(receiver)?;
+            let event_id = client.send_direct_msg(receiver, message).await?;
+            output.event_id(&event_id)?;
+        }
+        DmCommand::List { with } => {
+            let inbox = Inbox::new(client);
+            inbox.sync().await?;
+            let identity: XOnlyPublicKey = client.keys().public_key();
+            match with {
+                Some(counterparty) => {
+                    let counterparty = parse_public_key(counterparty)?;
+                    for msg in inbox.messages(&identity, &counterparty).iter() {
+                        let direction: &str = if msg.outgoing { ">" } else { "<" };
+                        output.value(
+                            json!({
+                                "id": msg.id,
+                                "outgoing": msg.outgoing,
+                                "created_at": msg.created_at,
+                                "content": msg.content,
+                            }),
+                            Some(format!("{} {} {}", msg.created_at, direction, msg.content)),
+                        );
+                    }
+                }
+                None => {
+                    for conversation in inbox.conversations().iter() {
+                        let counterparty: String = conversation.counterparty.to_bech32()?;
+                        output.value(
+                            json!({
+                                "counterparty": counterparty,
+                                "unread": conversation.unread,
+                                "last_message": conversation.last_message.content,
+                                "created_at": conversation.last_message.created_at,
+                            }),
+                            Some(format!(
+                                "{} ({} unread): {}",
+                                counterparty,
+                                conversation.unread,
+                                conversation.last_message.content
+                            )),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_event_id(id: &str) -> Result<EventId> {
+    if id.starts_with("note") {
+        Ok(EventId::from_bech32(id)?)
+    } else {
+        Ok(EventId::from_hex(id)?)
+    }
+}
+
+fn parse_public_key(public_key: &str) -> Result<XOnlyPublicKey> {
+    Ok(Keys::from_pk_str(public_key)?.public_key())
+}
+
+fn parse_public_keys(public_keys: &[String]) -> Result<Vec<XOnlyPublicKey>> {
+    public_keys.iter().map(|pk| parse_public_key(pk)).collect()
+}
+
+/// Output mode
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    /// Print `value` as JSON or `text` (JSON if `None`)
+    fn value(&self, value: Value, text: Option<String>) {
+        match text {
+            Some(text) if !self.json => println!("{text}"),
+            _ => println!("{value}"),
+        }
+    }
+
+    fn event(&self, event: &Event) -> Result<()> {
+        if self.json {
+            println!("{}", event.as_json()?);
+        } else {
+            println!(
+                "{} {} kind:{} {}\n  {}",
+                event.created_at,
+                event.pubkey.to_bech32()?,
+                event.kind.as_u64(),
+                event.id.to_bech32()?,
+                event.content
+            );
+        }
+        Ok(())
+    }
+
+    fn event_id(&self, event_id: &EventId) -> Result<()> {
+        let note: String = event_id.to_bech32()?;
+        self.value(json!({"id": event_id, "note": note}), Some(note.clone()));
+        Ok(())
+    }
+}
@@ -14,9 +14,11 @@ async fn main() -> Result<()> {
 
     let opts = Options::new().wait_for_send(true);
 
-    let client = Client::new_with_opts(&my_keys, opts);
-    client.add_relay("wss://relay.nostr.info", None).await?;
-    client.add_relay("wss://relay.damus.io", None).await?;
+    let client = ClientBuilder::new(&my_keys)
+        .opts(opts)
+        .relay(Url::parse("wss://relay.nostr.info")?, None)
+        .relay(Url::parse("wss://relay.damus.io")?, None)
+        .build();
 
     client.connect().await;
 
@@ -0,0 +1,41 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr_sdk::prelude::*;
+use nostr_sdk::scenario::Step;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let my_keys = Keys::generate();
+    let note = EventBuilder::new_text_note("Scripted note", &[]).to_event(&my_keys)?;
+
+    let scenario = Scenario::new()
+        // Subscription sent on connection
+        .step(Step::Receive)
+        .respond_to_req(vec![note])
+        .reject_event("blocked: scripted rejection")
+        .disconnect();
+    let relay = ScenarioRelay::start(scenario)?;
+
+    let client = Client::new(&my_keys);
+    client.add_relay(relay.url().as_str(), None).await?;
+    client.connect_relay(relay.url().as_str(), true).await?;
+
+    let filter = SubscriptionFilter::new().kind(Kind::TextNote);
+    let events = client.get_events_of(vec![filter]).await?;
+    println!("Events: {events:?}");
+
+    client.publish_text_note("Hello", &[]).await?;
+
+    relay.wait_finished(Duration::from_secs(10)).await;
+    println!("Connections: {}", relay.connections());
+    for msg in relay.received() {
+        println!("Received: {}", msg.as_json());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,431 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Remote signer
+//!
+//! Accounts held by a NIP-46 remote signer (bunker), ex. a team account shared by many authors
+//! without sharing its secret key.
+
+use std::time::Duration;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip46::{BunkerURI, Request, Response};
+use nostr::{
+    ClientMessage, Event, EventBuilder, EventId, Kind, SubscriptionFilter, SubscriptionId,
+    Timestamp, UnsignedEvent,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::client::{Client, Error};
+use crate::relay::pool::RelayPoolNotification;
+
+/// Default timeout of requests: remote signers may wait for the approval of the owner
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Account held by a NIP-46 remote signer
+///
+/// Build with [`Client::connect_bunker`]. Requests are signed with the [`Client`] keys and
+/// exchanged on the relays of the [`BunkerURI`].
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: Client,
+    uri: BunkerURI,
+    public_key: XOnlyPublicKey,
+    subscription_id: SubscriptionId,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    /// Public key of the account
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.public_key
+    }
+
+    /// Connection URI
+    pub fn uri(&self) -> &BunkerURI {
+        &self.uri
+    }
+
+    /// Set timeout of requests (default: 60 secs)
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Send request and wait for the response of the remote signer
+    ///
+    /// Return the result, or [`Error::NIP46`] if the remote signer replied with an error.
+    pub async fn send_request(&self, request: Request) -> Result<String, Error> {
+        send_request(&self.client, &self.uri, request, self.timeout).await
+    }
+
+    /// Sign [`UnsignedEvent`] of the account
+    pub async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        let result: String = self.send_request(Request::sign_event(&unsigned)?).await?;
+        let event: Event = Event::from_json(result)?;
+        event.verify()?;
+        if event.pubkey != self.public_key || event.id != unsigned.id {
+            return Err(Error::NIP46(nostr::nips::nip46::Error::Response(
+                String::from("signed event doesn't match the request"),
+            )));
+        }
+        Ok(event)
+    }
+
+    /// Sign [`EventBuilder`] with the account and send it to the client relays
+    ///
+    /// The [`OutgoingHook`](crate::middleware::OutgoingHook)s of the client are applied before signing.
+    pub async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
+        let builder: EventBuilder = self.client.apply_outgoing_hooks(builder)?;
+        let event: Event = self
+            .sign_event(builder.to_unsigned_event(self.public_key))
+            .await?;
+        Ok(self.client.send_event(event).await?.value)
+    }
+
+    /// Ping remote signer
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.send_request(Request::ping()).await?;
+        Ok(())
+    }
+
+    /// Close subscription of the responses on the relays of the remote signer
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        for url in self.uri.relays.iter() {
+            let msg = ClientMessage::close(self.subscription_id.clone());
+            self.client.send_msg_to(url.to_string(), msg).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn send_request(
+    client: &Client,
+    uri: &BunkerURI,
+    request: Request,
+    timeout: Duration,
+) -> Result<String, Error> {
+    let keys = client.keys();
+    let event: Event = request.to_event(&keys, &uri.remote_signer)?;
+
+    // Listen before sending, to not miss fast responses
+    let mut notifications = client.notifications();
+    for url in uri.relays.iter() {
+        if let Err(e) = client.send_event_to(url.to_string(), event.clone()).await {
+            log::warn!("Impossible to send NIP-46 request to {}: {}", url, e);
+        }
+    }
+
+    let response = async {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotification::Event(_, event))
+                    if event.kind == Kind::NostrConnect && event.pubkey == uri.remote_signer =>
+                {
+                    match Response::from_event(&keys, &event) {
+                        Ok(response) if response.id == request.id => return Ok(response),
+                        Ok(_) => (),
+                        Err(e) => log::warn!("Invalid NIP-46 response: {}", e),
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => return Err(Error::Timeout),
+            }
+        }
+    };
+    let response: Response = tokio::time::timeout(timeout, response)
+        .await
+        .map_err(|_| Error::Timeout)??;
+    Ok(response.into_result()?)
+}
+
+impl Client {
+    /// Connect to the NIP-46 remote signer of `uri` (`bunker://...`)
+    ///
+    /// The relays of the URI are added and connected, then the handshake (`connect` with the
+    /// secret, if any, and `get_public_key`) is completed.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let signer = client
+    ///     .connect_bunker("bunker://b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a?relay=wss://relay.nsec.app&secret=abc")
+    ///     .await
+    ///     .unwrap();
+    /// signer
+    ///     .send_event_builder(EventBuilder::new_text_note("Hello from the team", &[]))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn connect_bunker<S>(&self, uri: S) -> Result<RemoteSigner, Error>
+    where
+        S: AsRef<str>,
+    {
+        let uri: BunkerURI = uri.as_ref().parse()?;
+        for url in uri.relays.iter() {
+            self.add_relay(url.to_string(), None).await?;
+            self.connect_relay(url.to_string(), true).await?;
+        }
+
+        let subscription_id = SubscriptionId::generate();
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::NostrConnect)
+            .pubkey(self.keys().public_key())
+            .since(Timestamp::now());
+        for url in uri.relays.iter() {
+            let msg = ClientMessage::new_req(subscription_id.clone(), vec![filter.clone()]);
+            self.send_msg_to(url.to_string(), msg).await?;
+        }
+
+        let connect = Request::connect(uri.remote_signer, uri.secret.clone());
+        send_request(self, &uri, connect, DEFAULT_TIMEOUT).await?;
+        let public_key: String =
+            send_request(self, &uri, Request::get_public_key(), DEFAULT_TIMEOUT).await?;
+
+        Ok(RemoteSigner {
+            client: self.clone(),
+            public_key: public_key.parse()?,
+            uri,
+            subscription_id,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::{SinkExt, StreamExt};
+    use nostr::nips::nip46::{self, Method};
+    use nostr::url::Url;
+    use nostr::{Keys, RelayMessage};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc::{self, UnboundedSender};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+    use crate::Options;
+
+    const SECRET: &str = "team-secret";
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    type Subscription = (usize, SubscriptionId, Vec<SubscriptionFilter>);
+
+    /// Events and subscriptions of [`start_relay`]
+    #[derive(Default)]
+    struct RelayState {
+        events: Vec<Event>,
+        subscriptions: Vec<(Subscription, UnboundedSender<RelayMessage>)>,
+    }
+
+    /// Local relay storing the events and sending them to the matching subscriptions
+    async fn start_relay() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        let state: Arc<Mutex<RelayState>> = Arc::default();
+        tokio::spawn(async move {
+            let mut connection: usize = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                connection += 1;
+                tokio::spawn(serve(connection, stream, state.clone()));
+            }
+        });
+        url
+    }
+
+    async fn serve(connection: usize, stream: TcpStream, state: Arc<Mutex<RelayState>>) {
+        let ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(_) => return,
+        };
+        let (mut ws_tx, mut ws_rx) = ws.split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<RelayMessage>();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if ws_tx.send(Message::Text(msg.as_json())).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(Ok(Message::Text(text))) = ws_rx.next().await {
+            let mut state = state.lock().unwrap();
+            match ClientMessage::from_json(text) {
+                Ok(ClientMessage::Req {
+                    subscription_id,
+                    filters,
+                }) => {
+                    for event in state.events.iter() {
+                        if filters.iter().any(|f| f.match_event(event)) {
+                            let msg =
+                                RelayMessage::new_event(subscription_id.clone(), event.clone());
+                            let _ = tx.send(msg);
+                        }
+                    }
+                    let _ = tx.send(RelayMessage::new_eose(subscription_id.clone()));
+                    state
+                        .subscriptions
+                        .retain(|((c, id, _), _)| *c != connection || id != &subscription_id);
+                    state
+                        .subscriptions
+                        .push(((connection, subscription_id, filters), tx.clone()));
+                }
+                Ok(ClientMessage::Close(subscription_id)) => state
+                    .subscriptions
+                    .retain(|((c, id, _), _)| *c != connection || id != &subscription_id),
+                Ok(ClientMessage::Event(event)) => {
+                    let _ = tx.send(RelayMessage::new_ok(event.id, true, ""));
+                    for ((_, id, filters), sender) in state.subscriptions.iter() {
+                        if filters.iter().any(|f| f.match_event(&event)) {
+                            let _ =
+                                sender.send(RelayMessage::new_event(id.clone(), *event.clone()));
+                        }
+                    }
+                    state.events.push(*event);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Remote signer of `account`, allowing only `connect` (with [`SECRET`]), `get_public_key`,
+    /// `sign_event` and `ping`
+    async fn start_bunker(url: &Url, signer: Keys, account: Keys) {
+        let client = Client::new_with_opts(&signer, Options::new().wait_for_connection(true));
+        client.add_relay(url.to_string(), None).await.unwrap();
+        client.connect().await;
+        let mut notifications = client.notifications();
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::NostrConnect)
+            .pubkey(signer.public_key());
+        client.subscribe(vec![filter]).await.unwrap();
+
+        tokio::spawn(async move {
+            while let Ok(notification) = notifications.recv().await {
+                let event = match notification {
+                    RelayPoolNotification::Event(_, event) if event.kind == Kind::NostrConnect => {
+                        event
+                    }
+                    _ => continue,
+                };
+                let request = match Request::from_event(&signer, &event) {
+                    Ok(request) => request,
+                    Err(_) => continue,
+                };
+                let result: Result<String, String> = match request.method {
+                    Method::Connect
+                        if request.params.get(1).map(|s| s.as_str()) == Some(SECRET) =>
+                    {
+                        Ok(String::from("ack"))
+                    }
+                    Method::Connect => Err(String::from("invalid secret")),
+                    Method::GetPublicKey => Ok(account.public_key().to_string()),
+                    Method::SignEvent => {
+                        let unsigned = UnsignedEvent::from_json(&request.params[0]).unwrap();
+                        if unsigned.pubkey == account.public_key() {
+                            Ok(unsigned.sign(&account).unwrap().as_json().unwrap())
+                        } else {
+                            Err(String::from("not the account"))
+                        }
+                    }
+                    Method::Ping => Ok(String::from("pong")),
+                    method => Err(format!("{method} not allowed")),
+                };
+                let response = match result {
+                    Ok(result) => Response::ok(request.id, result),
+                    Err(error) => Response {
+                        id: request.id,
+                        result: None,
+                        error: Some(error),
+                    },
+                };
+                let event = response.to_event(&signer, &event.pubkey).unwrap();
+                client.send_event(event).await.unwrap();
+            }
+        });
+    }
+
+    fn bunker_uri(url: &Url, signer: &Keys, secret: &str) -> String {
+        BunkerURI::new(signer.public_key(), vec![url.clone()])
+            .secret(secret)
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let url = start_relay().await;
+        let signer = Keys::generate();
+        let account = Keys::generate();
+        start_bunker(&url, signer.clone(), account.clone()).await;
+
+        let client = Client::new(&Keys::generate());
+        let remote = tokio::time::timeout(
+            TIMEOUT,
+            client.connect_bunker(bunker_uri(&url, &signer, SECRET)),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .timeout(TIMEOUT);
+        assert_eq!(remote.public_key(), account.public_key());
+        remote.ping().await.unwrap();
+
+        let unsigned = EventBuilder::new_text_note("Hello from the team", &[])
+            .to_unsigned_event(account.public_key());
+        let event = remote.sign_event(unsigned.clone()).await.unwrap();
+        assert_eq!(event.id, unsigned.id);
+        assert_eq!(event.pubkey, account.public_key());
+        event.verify().unwrap();
+
+        // Events of other authors are not signed
+        let unsigned = EventBuilder::new_text_note("Hello", &[])
+            .to_unsigned_event(Keys::generate().public_key());
+        assert!(matches!(
+            remote.sign_event(unsigned).await,
+            Err(Error::NIP46(nip46::Error::Response(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests() {
+        let url = start_relay().await;
+        let signer = Keys::generate();
+        start_bunker(&url, signer.clone(), Keys::generate()).await;
+        let client = Client::new(&Keys::generate());
+
+        // Wrong secret: the handshake fails
+        let res = tokio::time::timeout(
+            TIMEOUT,
+            client.connect_bunker(bunker_uri(&url, &signer, "wrong")),
+        )
+        .await
+        .unwrap();
+        assert!(
+            matches!(res, Err(Error::NIP46(nip46::Error::Response(e))) if e == "invalid secret")
+        );
+
+        // Methods not allowed by the remote signer
+        let remote = tokio::time::timeout(
+            TIMEOUT,
+            client.connect_bunker(bunker_uri(&url, &signer, SECRET)),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .timeout(TIMEOUT);
+        let request = Request::new(Method::Custom(String::from("nip04_decrypt")), Vec::new());
+        assert!(matches!(
+            remote.send_request(request).await,
+            Err(Error::NIP46(nip46::Error::Response(e))) if e == "nip04_decrypt not allowed"
+        ));
+        remote.ping().await.unwrap();
+    }
+}
@@ -5,17 +5,35 @@
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::time::Duration;
 
 use nostr::key::XOnlyPublicKey;
 use nostr::url::Url;
-use nostr::{ClientMessage, Contact, Event, EventId, Keys, Metadata, SubscriptionFilter, Tag};
-use tokio::sync::broadcast;
-
-use super::{Error, Options};
+use nostr::{
+    ClientMessage, Contact, ContactList, Event, EventId, Keys, Metadata, SubscriptionFilter, Tag,
+};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "encrypted-store")]
+use super::AccountImport;
+use super::{
+    ConnectionStrategy, Error, ImportReport, Options, PropagationReport, RelaySet, Thread,
+};
 use crate::client::Entity;
-use crate::relay::pool::RelayPoolNotification;
+use crate::display_name::DisplayName;
+use crate::notification_center::Notification;
+use crate::policy::EventPolicy;
+use crate::relay::pool::{Output, PoolStatus, RelayPoolNotification};
+use crate::relay::probe::RelayProbeReport;
+use crate::relay::stats::{BandwidthBudget, SentReq};
+use crate::relay::verifier::VerificationStats;
 use crate::relay::Relay;
+use crate::store::audit::AuditEntry;
+use crate::store::Store;
+#[cfg(feature = "encrypted-store")]
+use crate::store::StoreSecret;
 use crate::RUNTIME;
 
 #[derive(Debug, Clone)]
@@ -23,6 +41,12 @@ pub struct Client {
     client: super::Client,
 }
 
+impl From<super::Client> for Client {
+    fn from(client: super::Client) -> Self {
+        Self { client }
+    }
+}
+
 impl Client {
     pub fn new(keys: &Keys) -> Self {
         Self {
@@ -36,20 +60,207 @@ impl Client {
         }
     }
 
+    pub fn builder(keys: &Keys) -> super::ClientBuilder {
+        super::ClientBuilder::new(keys)
+    }
+
     /// Get current [`Keys`]
     pub fn keys(&self) -> Keys {
         self.client.keys()
     }
 
+    pub fn store(&self) -> Option<Store> {
+        self.client.store()
+    }
+
+    pub fn display_names(&self) -> Result<DisplayName, Error> {
+        self.client.display_names()
+    }
+
+    pub fn set_event_policy<T>(&self, policy: T)
+    where
+        T: EventPolicy + 'static,
+    {
+        self.client.set_event_policy(policy)
+    }
+
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.client.notifications()
     }
 
+    pub fn notifications_for(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> mpsc::Receiver<RelayPoolNotification> {
+        self.client.notifications_for(filters)
+    }
+
+    pub fn verification_stats(&self) -> VerificationStats {
+        self.client.verification_stats()
+    }
+
     /// Get relays
     pub fn relays(&self) -> HashMap<Url, Relay> {
         RUNTIME.block_on(async { self.client.relays().await })
     }
 
+    pub fn sent_reqs(&self) -> HashMap<Url, Vec<SentReq>> {
+        RUNTIME.block_on(async { self.client.sent_reqs().await })
+    }
+
+    pub fn pool_status(&self) -> PoolStatus {
+        RUNTIME.block_on(async { self.client.pool_status().await })
+    }
+
+    pub fn start_recording<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.client.start_recording(path)
+    }
+
+    pub fn stop_recording(&self) {
+        self.client.stop_recording()
+    }
+
+    pub fn replay<P>(&self, path: P) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        RUNTIME.block_on(async { self.client.replay(path).await })
+    }
+
+    pub fn seen_on(&self, event_id: EventId) -> Vec<Url> {
+        RUNTIME.block_on(async { self.client.seen_on(event_id).await })
+    }
+
+    #[cfg(feature = "encrypted-store")]
+    pub fn export_account<P>(
+        &self,
+        path: P,
+        include_secret: bool,
+        secret: &StoreSecret,
+    ) -> Result<usize, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        RUNTIME.block_on(async {
+            self.client
+                .export_account(path, include_secret, secret)
+                .await
+        })
+    }
+
+    #[cfg(feature = "encrypted-store")]
+    pub fn import_account<P>(&self, path: P, secret: &StoreSecret) -> Result<AccountImport, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        RUNTIME.block_on(async { self.client.import_account(path, secret).await })
+    }
+
+    pub fn import_backup<S>(&self, data: S, rebroadcast: bool) -> Result<ImportReport, Error>
+    where
+        S: AsRef<str>,
+    {
+        RUNTIME.block_on(async { self.client.import_backup(data, rebroadcast).await })
+    }
+
+    pub fn broadcast(&self, event: Event) -> Result<Vec<Url>, Error> {
+        RUNTIME.block_on(async { self.client.broadcast(event).await })
+    }
+
+    pub fn check_event_propagation<S>(
+        &self,
+        event_id: EventId,
+        relays: Vec<S>,
+    ) -> Result<PropagationReport, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.check_event_propagation(event_id, relays).await })
+    }
+
+    pub fn probe_relay<S>(&self, url: S) -> Result<RelayProbeReport, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.probe_relay(url).await })
+    }
+
+    pub fn get_thread(&self, event_id: EventId, depth: usize) -> Result<Thread, Error> {
+        RUNTIME.block_on(async { self.client.get_thread(event_id, depth).await })
+    }
+
+    pub fn notifications_feed(&self) -> Result<Vec<Notification>, Error> {
+        self.client.notifications_feed()
+    }
+
+    pub fn audit_log(&self, filters: Vec<SubscriptionFilter>) -> Result<Vec<AuditEntry>, Error> {
+        self.client.audit_log(filters)
+    }
+
+    pub fn create_relay_set<N, S>(&self, name: N, urls: Vec<S>) -> Result<(), Error>
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.create_relay_set(name, urls).await })
+    }
+
+    pub fn create_relay_set_with_strategy<N, S>(
+        &self,
+        name: N,
+        urls: Vec<S>,
+        strategy: ConnectionStrategy,
+    ) -> Result<(), Error>
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async {
+            self.client
+                .create_relay_set_with_strategy(name, urls, strategy)
+                .await
+        })
+    }
+
+    pub fn remove_relay_set(&self, name: &str) -> Option<RelaySet> {
+        RUNTIME.block_on(async { self.client.remove_relay_set(name).await })
+    }
+
+    pub fn relay_set(&self, name: &str) -> Option<RelaySet> {
+        RUNTIME.block_on(async { self.client.relay_set(name).await })
+    }
+
+    pub fn relay_sets(&self) -> Vec<RelaySet> {
+        RUNTIME.block_on(async { self.client.relay_sets().await })
+    }
+
+    pub fn connect_relay_set(&self, name: &str, wait_for_connection: bool) -> Result<(), Error> {
+        RUNTIME.block_on(async {
+            self.client
+                .connect_relay_set(name, wait_for_connection)
+                .await
+        })
+    }
+
+    pub fn disconnect_relay_set(&self, name: &str) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.disconnect_relay_set(name).await })
+    }
+
+    pub fn send_event_to_set(&self, name: &str, event: Event) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.send_event_to_set(name, event).await })
+    }
+
+    pub fn get_events_from_set(
+        &self,
+        name: &str,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<Vec<Event>, Error> {
+        RUNTIME.block_on(async { self.client.get_events_from_set(name, filters).await })
+    }
+
     /// Add multiple relays
     pub fn add_relays<S>(&self, relays: Vec<(S, Option<SocketAddr>)>) -> Result<(), Error>
     where
@@ -86,6 +297,28 @@ impl Client {
         RUNTIME.block_on(async { self.client.disconnect_relay(url).await })
     }
 
+    pub fn connect_relay_with_cancel<S>(
+        &self,
+        url: S,
+        token: &CancellationToken,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.connect_relay_with_cancel(url, token).await })
+    }
+
+    pub fn set_bandwidth_budget<S>(
+        &self,
+        url: S,
+        budget: Option<BandwidthBudget>,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.set_bandwidth_budget(url, budget).await })
+    }
+
     pub fn connect(&self) {
         RUNTIME.block_on(async {
             self.client.connect().await;
@@ -96,7 +329,15 @@ impl Client {
         RUNTIME.block_on(async { self.client.disconnect().await })
     }
 
-    pub fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<(), Error> {
+    pub fn graceful_disconnect(&self, timeout: Duration) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.graceful_disconnect(timeout).await })
+    }
+
+    pub fn flush(&self, timeout: Duration) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.flush(timeout).await })
+    }
+
+    pub fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<Output<()>, Error> {
         RUNTIME.block_on(async { self.client.subscribe(filters).await })
     }
 
@@ -104,6 +345,22 @@ impl Client {
         RUNTIME.block_on(async { self.client.get_events_of(filters).await })
     }
 
+    pub fn get_events_of_with_cancel(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        token: &CancellationToken,
+    ) -> Result<Vec<Event>, Error> {
+        RUNTIME.block_on(async { self.client.get_events_of_with_cancel(filters, token).await })
+    }
+
+    pub fn get_events_of_many(
+        &self,
+        queries: Vec<Vec<SubscriptionFilter>>,
+        concurrency: usize,
+    ) -> Vec<Result<Vec<Event>, Error>> {
+        RUNTIME.block_on(async { self.client.get_events_of_many(queries, concurrency).await })
+    }
+
     pub fn req_events_of(&self, filters: Vec<SubscriptionFilter>, timeout: Duration) {
         RUNTIME.block_on(async {
             self.client.req_events_of(filters, timeout).await;
@@ -116,7 +373,7 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_client_msg(msg, wait).await })
     }
 
-    pub fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
+    pub fn send_msg(&self, msg: ClientMessage) -> Result<Output<()>, Error> {
         RUNTIME.block_on(async { self.client.send_msg(msg).await })
     }
 
@@ -128,7 +385,7 @@ impl Client {
     }
 
     /// Send event
-    pub fn send_event(&self, event: Event) -> Result<EventId, Error> {
+    pub fn send_event(&self, event: Event) -> Result<Output<EventId>, Error> {
         RUNTIME.block_on(async { self.client.send_event(event).await })
     }
 
@@ -139,10 +396,40 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_event_to(url, event).await })
     }
 
+    pub fn auth<S>(&self, challenge: S, url: Url) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.auth(challenge, url).await })
+    }
+
     pub fn update_profile(&self, metadata: Metadata) -> Result<EventId, Error> {
         RUNTIME.block_on(async { self.client.update_profile(metadata).await })
     }
 
+    pub fn set_metadata(&self, metadata: Metadata) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.set_metadata(metadata).await })
+    }
+
+    pub fn edit_profile<F>(&self, f: F) -> Result<EventId, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        RUNTIME.block_on(async { self.client.edit_profile(f).await })
+    }
+
+    pub fn get_profile(&self, public_key: XOnlyPublicKey) -> Result<Option<Metadata>, Error> {
+        RUNTIME.block_on(async { self.client.get_profile(public_key).await })
+    }
+
+    pub fn sanitize_metadata(&self, metadata: Metadata) -> Metadata {
+        self.client.sanitize_metadata(metadata)
+    }
+
+    pub fn get_metadata(&self) -> Result<Option<Metadata>, Error> {
+        RUNTIME.block_on(async { self.client.get_metadata().await })
+    }
+
     pub fn publish_text_note<S>(&self, content: S, tags: &[Tag]) -> Result<EventId, Error>
     where
         S: Into<String>,
@@ -182,6 +469,22 @@ impl Client {
         RUNTIME.block_on(async { self.client.get_contact_list().await })
     }
 
+    pub fn get_contact_lists(
+        &self,
+        public_keys: Vec<XOnlyPublicKey>,
+    ) -> Result<HashMap<XOnlyPublicKey, ContactList>, Error> {
+        RUNTIME.block_on(async { self.client.get_contact_lists(public_keys).await })
+    }
+
+    #[cfg(feature = "nip26")]
+    pub fn migrate_identity(
+        &self,
+        old_keys: &Keys,
+        new_keys: &Keys,
+    ) -> Result<super::MigrationReport, Error> {
+        RUNTIME.block_on(async { self.client.migrate_identity(old_keys, new_keys).await })
+    }
+
     #[cfg(feature = "nip04")]
     pub fn send_direct_msg<S>(&self, receiver: XOnlyPublicKey, msg: S) -> Result<EventId, Error>
     where
@@ -190,6 +493,19 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_direct_msg(receiver, msg).await })
     }
 
+    #[cfg(feature = "attachment")]
+    pub fn send_attachment<S>(
+        &self,
+        receiver: XOnlyPublicKey,
+        data: &[u8],
+        mime_type: Option<S>,
+    ) -> Result<Vec<EventId>, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_attachment(receiver, data, mime_type).await })
+    }
+
     pub fn repost_event(
         &self,
         event_id: EventId,
@@ -297,4 +613,122 @@ impl Client {
     {
         RUNTIME.block_on(async { self.client.handle_notifications(func).await })
     }
+
+    #[cfg(feature = "nip96")]
+    pub fn upload_file(
+        &self,
+        server: Url,
+        data: Vec<u8>,
+        mime_type: Option<&str>,
+    ) -> Result<(Url, Vec<Tag>), Error> {
+        RUNTIME.block_on(async { self.client.upload_file(server, data, mime_type).await })
+    }
+
+    #[cfg(feature = "blossom")]
+    pub fn blossom_upload(
+        &self,
+        servers: Vec<Url>,
+        data: Vec<u8>,
+    ) -> Result<Vec<nostr::blossom::BlobDescriptor>, Error> {
+        RUNTIME.block_on(async { self.client.blossom_upload(servers, data).await })
+    }
+
+    #[cfg(feature = "nip05")]
+    pub fn verify_nip05(&self, public_key: XOnlyPublicKey, nip05: &str) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.verify_nip05(public_key, nip05).await })
+    }
+
+    #[cfg(feature = "nip05")]
+    pub fn follow_nip05(&self, nip05: &str, relay_set: Option<&str>) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.follow_nip05(nip05, relay_set).await })
+    }
+
+    #[cfg(feature = "nip32")]
+    pub fn get_relay_reviews<S>(&self, url: S) -> Result<nostr::nips::nip32::RelayRating, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.get_relay_reviews(url).await })
+    }
+
+    #[cfg(feature = "nip19")]
+    pub fn resolve<S>(&self, entity: S) -> Result<crate::client::Resolved, Error>
+    where
+        S: AsRef<str>,
+    {
+        RUNTIME.block_on(async { self.client.resolve(entity).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn join_group(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+        invite_code: Option<&str>,
+    ) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.join_group(group, invite_code).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn leave_group(&self, group: &nostr::nips::nip29::GroupId) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.leave_group(group).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn send_group_message<S>(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+        content: S,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_group_message(group, content).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn put_group_user(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+        public_key: XOnlyPublicKey,
+        roles: &[&str],
+    ) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.put_group_user(group, public_key, roles).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn remove_group_user(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+        public_key: XOnlyPublicKey,
+    ) -> Result<EventId, Error> {
+        RUNTIME.block_on(async { self.client.remove_group_user(group, public_key).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn get_group_metadata(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+    ) -> Result<nostr::nips::nip29::GroupMetadata, Error> {
+        RUNTIME.block_on(async { self.client.get_group_metadata(group).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn get_group_admins(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+    ) -> Result<Vec<nostr::nips::nip29::GroupAdmin>, Error> {
+        RUNTIME.block_on(async { self.client.get_group_admins(group).await })
+    }
+
+    #[cfg(feature = "nip29")]
+    pub fn get_group_members(
+        &self,
+        group: &nostr::nips::nip29::GroupId,
+    ) -> Result<Vec<XOnlyPublicKey>, Error> {
+        RUNTIME.block_on(async { self.client.get_group_members(group).await })
+    }
+
+    pub fn get_lnurl_pay_request(&self, lud: &str) -> Result<crate::lnurl::PayRequest, Error> {
+        RUNTIME.block_on(async { self.client.get_lnurl_pay_request(lud).await })
+    }
 }
@@ -0,0 +1,253 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Client builder
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use nostr::url::Url;
+use nostr::{Keys, Signer};
+use tokio::sync::RwLock;
+
+use super::{Client, Options, RelaySet};
+use crate::http;
+use crate::middleware::{Middleware, OutgoingHook};
+use crate::policy::EventPolicy;
+use crate::relay::pool::RelayPool;
+use crate::relay::trust::TrustPolicy;
+use crate::store::Store;
+
+/// [`Client`] builder
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    keys: Keys,
+    signer: Option<Arc<dyn Signer>>,
+    opts: Options,
+    relays: Vec<(Url, Option<SocketAddr>)>,
+    relay_sets: Vec<RelaySet>,
+    store: Option<Store>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl ClientBuilder {
+    /// New [`ClientBuilder`]
+    pub fn new(keys: &Keys) -> Self {
+        Self {
+            keys: keys.clone(),
+            signer: None,
+            opts: Options::default(),
+            relays: Vec::new(),
+            relay_sets: Vec::new(),
+            store: None,
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Set [`Signer`]
+    ///
+    /// Events built by the client are signed by `signer` (ex. a [`HardwareSigner`](nostr::HardwareSigner))
+    /// instead of the keys, that can be built from the public key only. The public key of the signer must
+    /// be the one of the keys. NIP-04 messages, NIP-98 and Blossom authorizations and account bundles still need the secret key.
+    pub fn signer<T>(self, signer: T) -> Self
+    where
+        T: Signer + 'static,
+    {
+        Self {
+            signer: Some(Arc::new(signer)),
+            ..self
+        }
+    }
+
+    /// Set [`Options`]
+    pub fn opts(self, opts: Options) -> Self {
+        Self { opts, ..self }
+    }
+
+    /// Set proxy (ex. Tor `127.0.0.1:9050`)
+    ///
+    /// See [`Options::proxy`].
+    pub fn proxy(self, proxy: SocketAddr) -> Self {
+        Self {
+            opts: self.opts.proxy(proxy),
+            ..self
+        }
+    }
+
+    /// Use the proxy of the system environment
+    ///
+    /// See [`Options::system_proxy`].
+    pub fn system_proxy(self, enabled: bool) -> Self {
+        Self {
+            opts: self.opts.system_proxy(enabled),
+            ..self
+        }
+    }
+
+    /// Resolve names of NIP-05 and LNURL lookups with DNS-over-HTTPS
+    ///
+    /// See [`Options::dns_over_https`].
+    pub fn dns_over_https(self, endpoint: Url) -> Self {
+        Self {
+            opts: self.opts.dns_over_https(endpoint),
+            ..self
+        }
+    }
+
+    /// Set [`TrustPolicy`]
+    pub fn trust_policy<T>(self, policy: T) -> Self
+    where
+        T: TrustPolicy + 'static,
+    {
+        Self {
+            opts: self.opts.trust_policy(policy),
+            ..self
+        }
+    }
+
+    /// Set [`EventPolicy`]
+    pub fn event_policy<T>(self, policy: T) -> Self
+    where
+        T: EventPolicy + 'static,
+    {
+        Self {
+            opts: self.opts.event_policy(policy),
+            ..self
+        }
+    }
+
+    /// Add ingestion [`Middleware`], run after the ones already added
+    ///
+    /// See [`crate::middleware`] for the stages of the pipeline.
+    pub fn middleware<T>(mut self, middleware: T) -> Self
+    where
+        T: Middleware + 'static,
+    {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Add [`OutgoingHook`], run after the ones already added
+    pub fn outgoing_hook<T>(self, hook: T) -> Self
+    where
+        T: OutgoingHook + 'static,
+    {
+        Self {
+            opts: self.opts.outgoing_hook(hook),
+            ..self
+        }
+    }
+
+    /// Add relay
+    pub fn relay(mut self, url: Url, proxy: Option<SocketAddr>) -> Self {
+        self.relays.push((url, proxy));
+        self
+    }
+
+    /// Add multiple relays
+    pub fn relays(mut self, relays: Vec<(Url, Option<SocketAddr>)>) -> Self {
+        self.relays.extend(relays);
+        self
+    }
+
+    /// Add [`RelaySet`], replacing the set with the same name
+    ///
+    /// The relays of the set are added to the pool and, like the other relays, are connected with
+    /// [`Client::connect`] (also the ones of [`ConnectionStrategy::OnDemand`](super::ConnectionStrategy::OnDemand) sets).
+    pub fn relay_set(mut self, set: RelaySet) -> Self {
+        self.relay_sets.retain(|s| s.name != set.name);
+        self.relay_sets.push(set);
+        self
+    }
+
+    /// Set [`Store`]
+    ///
+    /// Events received from relays are saved in the store. Expired events (NIP-40) are removed every minute.
+    pub fn store(self, store: Store) -> Self {
+        Self {
+            store: Some(store),
+            ..self
+        }
+    }
+
+    /// Build [`Client`]
+    ///
+    /// If the HTTP client can't be built with the proxy and DNS-over-HTTPS options,
+    /// NIP-05 and LNURL lookups return [`Error::HttpClient`](super::Error::HttpClient).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// let my_keys = Keys::generate();
+    /// let client = ClientBuilder::new(&my_keys)
+    ///     .opts(Options::new().wait_for_send(true))
+    ///     .relay(Url::parse("wss://relay.damus.io")?, None)
+    ///     .build();
+    /// client.connect().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build(self) -> Client {
+        let proxy: Option<SocketAddr> = self.opts.proxy;
+        // On error, HTTP requests fail instead of bypassing the proxy
+        let http = http::build_client(
+            proxy,
+            self.opts.dns_over_https.clone(),
+            self.opts.system_proxy,
+        )
+        .map_err(|e| {
+            log::error!("Impossible to build HTTP client: {}", e);
+            Arc::new(e)
+        });
+        let set_relays = self
+            .relay_sets
+            .iter()
+            .flat_map(|set| set.urls.iter().map(|url| (url.clone(), None)));
+        let relays = self
+            .relays
+            .into_iter()
+            .chain(set_relays)
+            .map(|(url, relay_proxy)| (url, relay_proxy.or(proxy)))
+            .collect();
+        let relay_sets = self
+            .relay_sets
+            .into_iter()
+            .map(|set| (set.name.clone(), set))
+            .collect();
+        let client = Client {
+            pool: RelayPool::with_relays(
+                relays,
+                self.opts.timeouts.clone(),
+                self.opts.event_policy.clone(),
+                self.opts.user_agent.as_ref().map(|ua| ua.to_string()),
+                self.opts.req_priority,
+                self.opts.query_cache,
+                self.opts.auto_narrowing,
+            ),
+            keys: self.keys,
+            signer: self.signer,
+            opts: self.opts,
+            http,
+            store: self.store,
+            relay_sets: Arc::new(RwLock::new(relay_sets)),
+        };
+        client
+            .pool
+            .set_subscription_id_generator(client.opts.subscription_ids.clone());
+        client.pool.set_jitter(client.opts.jitter);
+        client
+            .pool
+            .set_document_refresh(client.opts.document_refresh);
+        client.pool.set_system_proxy(client.opts.system_proxy);
+        for middleware in self.middlewares.into_iter() {
+            client.pool.add_middleware(middleware);
+        }
+        client.pool.set_store(client.store.clone());
+        client.handle_auth();
+        client.handle_store();
+        client
+    }
+}
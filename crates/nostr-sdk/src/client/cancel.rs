@@ -0,0 +1,38 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Cancellation
+
+use std::time::Duration;
+
+use futures_util::Future;
+use tokio_util::sync::CancellationToken;
+
+use super::Error;
+
+/// Run `future` until it completes, `token` is cancelled or `timeout` expires
+///
+/// When cancelled or timed out, `future` is dropped and [`Error::Cancelled`] or [`Error::Timeout`] is returned.
+/// [`Client`](super::Client) operations are cancel-safe: subscriptions opened on relays are closed
+/// and connections keep being established in background.
+pub async fn with_cancellation<F, T>(
+    token: &CancellationToken,
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    let future = async {
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| Error::Timeout)?,
+            None => future.await,
+        }
+    };
+    tokio::select! {
+        result = future => result,
+        _ = token.cancelled() => Err(Error::Cancelled),
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Conversation thread
+
+use std::collections::HashMap;
+
+use nostr::{Event, EventId};
+
+/// Node of a [`Thread`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// Event id
+    pub id: EventId,
+    /// Event, `None` if referenced but not found on relays
+    pub event: Option<Event>,
+    /// Parent (replied event), if known
+    pub parent: Option<EventId>,
+    /// Children (replies), oldest first
+    pub children: Vec<EventId>,
+}
+
+impl ThreadNode {
+    fn new(id: EventId, event: Option<Event>) -> Self {
+        Self {
+            id,
+            event,
+            parent: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Check if the event wasn't found on relays (placeholder)
+    pub fn is_missing(&self) -> bool {
+        self.event.is_none()
+    }
+}
+
+/// Conversation tree (NIP-10)
+///
+/// Contains the root, the ancestors of the target event and its replies.
+/// Events referenced but not found on relays are kept as placeholders (see [`ThreadNode::is_missing`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thread {
+    /// Root id
+    pub root: EventId,
+    /// Id of the event the thread was requested for
+    pub target: EventId,
+    /// Nodes
+    pub nodes: HashMap<EventId, ThreadNode>,
+}
+
+impl Thread {
+    /// Assemble thread from fetched `events`
+    ///
+    /// Replies are followed up to `depth` levels below the target.
+    pub(crate) fn new(target: EventId, events: Vec<Event>, depth: usize) -> Self {
+        let events: HashMap<EventId, Event> = events.into_iter().map(|e| (e.id, e)).collect();
+        let root: EventId = events
+            .get(&target)
+            .and_then(|e| e.root_id())
+            .unwrap_or(target);
+
+        let mut thread = Self {
+            root,
+            target,
+            nodes: HashMap::new(),
+        };
+        thread.insert(target, &events);
+
+        // Ancestors
+        let mut id: EventId = target;
+        while let Some(parent) = thread.parent_of(&id, &events) {
+            if thread.nodes.contains_key(&parent) {
+                // Loop
+                break;
+            }
+            thread.insert(parent, &events);
+            thread.link(parent, id);
+            id = parent;
+        }
+        if id != root {
+            // Gap between the top known ancestor and the root
+            thread.insert(root, &events);
+            thread.link(root, id);
+        }
+
+        // Replies
+        let mut frontier: Vec<EventId> = vec![target];
+        for _ in 0..depth {
+            let mut next: Vec<EventId> = Vec::new();
+            for event in events.values() {
+                if thread.nodes.contains_key(&event.id) {
+                    continue;
+                }
+                if let Some(parent) = event.reply_to_id() {
+                    if frontier.contains(&parent) {
+                        next.push(event.id);
+                    }
+                }
+            }
+            for id in next.iter() {
+                thread.insert(*id, &events);
+                if let Some(parent) = thread.parent_of(id, &events) {
+                    thread.link(parent, *id);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        thread.sort_children();
+        thread
+    }
+
+    fn parent_of(&self, id: &EventId, events: &HashMap<EventId, Event>) -> Option<EventId> {
+        events.get(id).and_then(|e| e.reply_to_id())
+    }
+
+    fn insert(&mut self, id: EventId, events: &HashMap<EventId, Event>) {
+        self.nodes
+            .entry(id)
+            .or_insert_with(|| ThreadNode::new(id, events.get(&id).cloned()));
+    }
+
+    fn link(&mut self, parent: EventId, child: EventId) {
+        if let Some(node) = self.nodes.get_mut(&child) {
+            node.parent = Some(parent);
+        }
+        if let Some(node) = self.nodes.get_mut(&parent) {
+            if !node.children.contains(&child) {
+                node.children.push(child);
+            }
+        }
+    }
+
+    fn sort_children(&mut self) {
+        let created_at: HashMap<EventId, u64> = self
+            .nodes
+            .values()
+            .map(|n| (n.id, n.event.as_ref().map_or(0, |e| e.created_at.as_u64())))
+            .collect();
+        for node in self.nodes.values_mut() {
+            node.children
+                .sort_by_key(|id| created_at.get(id).copied().unwrap_or_default());
+        }
+    }
+
+    /// Get node
+    pub fn get(&self, id: &EventId) -> Option<&ThreadNode> {
+        self.nodes.get(id)
+    }
+
+    /// Get root node
+    pub fn root(&self) -> Option<&ThreadNode> {
+        self.get(&self.root)
+    }
+
+    /// Get target node
+    pub fn target(&self) -> Option<&ThreadNode> {
+        self.get(&self.target)
+    }
+
+    /// Get ancestors of the target, root first
+    pub fn ancestors(&self) -> Vec<&ThreadNode> {
+        let mut ancestors: Vec<&ThreadNode> = Vec::new();
+        let mut node: Option<&ThreadNode> = self.target();
+        while let Some(parent) = node.and_then(|n| n.parent).and_then(|id| self.get(&id)) {
+            if ancestors.iter().any(|a| a.id == parent.id) {
+                break;
+            }
+            ancestors.push(parent);
+            node = Some(parent);
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
+    /// Get replies of `id`, oldest first
+    pub fn replies(&self, id: &EventId) -> Vec<&ThreadNode> {
+        self.get(id)
+            .map(|node| node.children.iter().filter_map(|c| self.get(c)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get ids of the events referenced but not found
+    pub fn missing(&self) -> Vec<EventId> {
+        self.nodes
+            .values()
+            .filter(|n| n.is_missing())
+            .map(|n| n.id)
+            .collect()
+    }
+}
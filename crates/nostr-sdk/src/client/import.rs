@@ -0,0 +1,37 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Backup import
+
+#[cfg(feature = "encrypted-store")]
+use nostr::EventId;
+
+#[cfg(feature = "encrypted-store")]
+use crate::store::AccountBundle;
+
+/// Backup import report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Events saved in the store
+    pub imported: usize,
+    /// Valid events not saved (already in the store, superseded, ephemeral or expired)
+    pub skipped: usize,
+    /// Events present more than once in the backup
+    pub duplicates: usize,
+    /// Invalid entries (malformed JSON, wrong id or invalid signature)
+    pub invalid: usize,
+    /// Imported events sent to at least one relay
+    pub rebroadcast: usize,
+}
+
+/// Account bundle import report
+#[cfg(feature = "encrypted-store")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountImport {
+    /// Imported bundle (see [`AccountBundle::keys`] to restore the keys)
+    pub bundle: AccountBundle,
+    /// Events sent to at least one relay
+    pub rebroadcast: usize,
+    /// Events not re-published (invalid signature or not sent to any relay)
+    pub failed: Vec<EventId>,
+}
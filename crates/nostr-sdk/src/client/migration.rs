@@ -0,0 +1,65 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Identity migration
+
+use nostr::EventId;
+
+use super::Error;
+
+/// Identity migration step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationStep {
+    /// Publish the profile of the new identity, delegated by the old one (NIP-26)
+    Profile,
+    /// Copy the contact list to the new identity
+    ContactList,
+    /// Publish the final profile of the old identity, pointing to the new one
+    Announcement,
+}
+
+/// Identity migration report
+///
+/// Steps are executed in order and the migration stops at the first failed step.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    steps: Vec<(MigrationStep, Result<Option<EventId>, Error>)>,
+}
+
+impl MigrationReport {
+    /// Record step result, return `true` if the step succeeded
+    pub(crate) fn record(
+        &mut self,
+        step: MigrationStep,
+        result: Result<Option<EventId>, Error>,
+    ) -> bool {
+        match &result {
+            Ok(Some(id)) => log::info!("Migration step {:?} completed: {}", step, id),
+            Ok(None) => log::info!("Migration step {:?} skipped: nothing to publish", step),
+            Err(e) => log::error!("Migration step {:?} failed: {}", step, e),
+        }
+        let success: bool = result.is_ok();
+        self.steps.push((step, result));
+        success
+    }
+
+    /// Get executed steps with their result
+    ///
+    /// `Ok(None)` means that there was nothing to publish for the step.
+    pub fn steps(&self) -> &[(MigrationStep, Result<Option<EventId>, Error>)] {
+        &self.steps
+    }
+
+    /// Get the [`EventId`] published by `step`
+    pub fn event_id(&self, step: MigrationStep) -> Option<EventId> {
+        self.steps.iter().find_map(|(s, res)| match res {
+            Ok(id) if *s == step => *id,
+            _ => None,
+        })
+    }
+
+    /// Check if all the steps completed successfully
+    pub fn is_complete(&self) -> bool {
+        self.steps.len() == 3 && self.steps.iter().all(|(_, res)| res.is_ok())
+    }
+}
@@ -3,27 +3,91 @@
 
 //! Client
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
+use futures_util::{future, stream, StreamExt};
 use nostr::event::builder::Error as EventBuilderError;
+use nostr::event::TagKind;
 use nostr::key::XOnlyPublicKey;
+#[cfg(feature = "nip19")]
+use nostr::nips::nip19::{FromBech32, Nip19};
+#[cfg(feature = "nip26")]
+use nostr::nips::nip26;
+#[cfg(feature = "nip29")]
+use nostr::nips::nip29::{self, GroupAdmin, GroupId, GroupMetadata};
+#[cfg(feature = "nip32")]
+use nostr::nips::nip32::RelayRating;
+use nostr::types::relay_url;
 use nostr::url::Url;
 use nostr::{
-    ClientMessage, Contact, Entity, Event, EventBuilder, EventId, Keys, Kind, Metadata,
-    SubscriptionFilter, Tag,
+    Backup, ClientMessage, Contact, ContactList, Coordinate, Entity, Event, EventBuilder, EventId,
+    Keys, Kind, Metadata, RelayMessage, Signer, SubscriptionFilter, Tag, UnsignedEvent,
 };
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
+mod builder;
+mod cancel;
+mod conversation;
+mod import;
+#[cfg(feature = "nip26")]
+mod migration;
 mod options;
+mod propagation;
+mod relay_set;
+#[cfg(feature = "nip19")]
+mod resolve;
 
-pub use self::options::Options;
-use crate::relay::pool::{Error as RelayPoolError, RelayPool, RelayPoolNotification};
-use crate::Relay;
+pub use self::builder::ClientBuilder;
+pub use self::cancel::with_cancellation;
+pub use self::conversation::{Thread, ThreadNode};
+#[cfg(feature = "encrypted-store")]
+pub use self::import::AccountImport;
+pub use self::import::ImportReport;
+#[cfg(feature = "nip26")]
+pub use self::migration::{MigrationReport, MigrationStep};
+pub use self::options::{Options, UserAgent};
+pub use self::propagation::PropagationReport;
+pub use self::relay_set::{ConnectionStrategy, RelaySet};
+#[cfg(feature = "nip19")]
+pub use self::resolve::Resolved;
+use crate::display_name::DisplayName;
+use crate::http;
+use crate::lnurl::{self, PayRequest};
+use crate::notification_center::{Notification, NotificationCenter};
+use crate::policy::EventPolicy;
+use crate::relay::pool::{
+    Error as RelayPoolError, Output, PoolStatus, RelayPool, RelayPoolNotification,
+};
+use crate::relay::probe::{self, RelayProbeReport};
+use crate::relay::recorder::Recording;
+use crate::relay::stats::{BandwidthBudget, SentReq};
+use crate::relay::trust::{self, RelayAction};
+use crate::relay::verifier::VerificationStats;
+use crate::store::audit::{AuditAction, AuditEntry};
+use crate::store::Store;
+#[cfg(feature = "encrypted-store")]
+use crate::store::{AccountBundle, StoreSecret};
+use crate::thread;
+use crate::{Relay, RelayStatus};
+
+/// Max number of authors per filter when fetching contact lists
+pub const CONTACT_LISTS_BATCH_SIZE: usize = 250;
+/// Interval between removals of expired events from the [`Store`]
+const STORE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// Max number of ancestors fetched by [`Client::get_thread`]
+const MAX_THREAD_ANCESTORS: usize = 64;
+/// Capacity of the channels of [`Client::notifications_for`]
+const ROUTED_NOTIFICATIONS_CAPACITY: usize = 1024;
 
 /// [`Client`] error
 #[derive(Debug, thiserror::Error)]
@@ -31,21 +95,125 @@ pub enum Error {
     /// Url parse error
     #[error("impossible to parse URL: {0}")]
     Url(#[from] nostr::url::ParseError),
+    /// Relay url error
+    #[error("invalid relay URL: {0}")]
+    RelayUrl(#[from] relay_url::Error),
     /// [`RelayPool`] error
     #[error("relay pool error: {0}")]
     RelayPool(#[from] RelayPoolError),
     /// Relay not found
     #[error("relay not found")]
     RelayNotFound,
+    /// HTTP client not available (invalid proxy or DNS-over-HTTPS options)
+    #[error("HTTP client not available: {0}")]
+    HttpClient(Arc<http::Error>),
+    /// Relay set not found
+    #[error("relay set not found: {0}")]
+    RelaySetNotFound(String),
+    /// Store not configured
+    #[error("store not configured")]
+    StoreNotConfigured,
+    /// No relay allowed by trust policy
+    #[error("no relay allowed by trust policy")]
+    NoTrustedRelay,
+    /// Operation cancelled
+    #[error("cancelled")]
+    Cancelled,
+    /// Operation timed out
+    #[error("timeout")]
+    Timeout,
+    /// Event vetoed by an [`OutgoingHook`](crate::middleware::OutgoingHook)
+    #[error("event vetoed: {0}")]
+    Vetoed(String),
     /// [`EventBuilder`] error
     #[error("event builder error: {0}")]
     EventBuilder(#[from] EventBuilderError),
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] nostr::event::Error),
+    /// Keys error
+    #[error("keys error: {0}")]
+    Keys(#[from] nostr::key::Error),
+    /// Signer error
+    #[error("signer error: {0}")]
+    Signer(#[from] nostr::signer::Error),
+    /// Unsigned event error
+    #[error("unsigned event error: {0}")]
+    UnsignedEvent(#[from] nostr::event::unsigned::Error),
     /// Secp256k1 error
     #[error("secp256k1 error: {0}")]
     Secp256k1(#[from] nostr::secp256k1::Error),
     /// Hex error
     #[error("hex decoding error: {0}")]
     Hex(#[from] nostr::hashes::hex::Error),
+    /// [`EventId`] error
+    #[error("event id error: {0}")]
+    EventId(#[from] nostr::event::id::Error),
+    /// Metadata error
+    #[error("metadata error: {0}")]
+    Metadata(#[from] nostr::types::metadata::Error),
+    /// Profile metadata not found on relays
+    #[error("metadata not found")]
+    MetadataNotFound,
+    /// LNURL error
+    #[error("lnurl error: {0}")]
+    Lnurl(#[from] lnurl::Error),
+    /// NIP05 error
+    #[cfg(feature = "nip05")]
+    #[error(transparent)]
+    NIP05(#[from] nostr::nips::nip05::Error),
+    /// NIP19 error
+    #[cfg(feature = "nip19")]
+    #[error(transparent)]
+    NIP19(#[from] nostr::nips::nip19::Error),
+    /// Secret keys can't be resolved
+    #[cfg(feature = "nip19")]
+    #[error("secret keys can't be resolved")]
+    SecretKeyEntity,
+    /// Entity not found on relays
+    #[cfg(feature = "nip19")]
+    #[error("entity not found")]
+    EntityNotFound,
+    /// NIP26 error
+    #[cfg(feature = "nip26")]
+    #[error(transparent)]
+    NIP26(#[from] nostr::nips::nip26::Error),
+    /// NIP29 error
+    #[cfg(feature = "nip29")]
+    #[error(transparent)]
+    NIP29(#[from] nostr::nips::nip29::Error),
+    /// Group state not found on the group relay
+    #[cfg(feature = "nip29")]
+    #[error("group not found")]
+    GroupNotFound,
+    /// NIP46 error
+    #[cfg(feature = "nip46")]
+    #[error(transparent)]
+    NIP46(#[from] nostr::nips::nip46::Error),
+    /// NIP96 error
+    #[cfg(feature = "nip96")]
+    #[error(transparent)]
+    NIP96(#[from] nostr::nips::nip96::Error),
+    /// Download URL not found in upload response
+    #[cfg(feature = "nip96")]
+    #[error("download URL not found")]
+    DownloadUrlNotFound,
+    /// Account bundle error
+    #[cfg(feature = "encrypted-store")]
+    #[error(transparent)]
+    AccountBundle(#[from] crate::store::bundle::Error),
+    /// Blossom error
+    #[cfg(feature = "blossom")]
+    #[error(transparent)]
+    Blossom(#[from] nostr::blossom::Error),
+    /// No Blossom server provided
+    #[cfg(feature = "blossom")]
+    #[error("no blossom server provided")]
+    NoBlossomServer,
+    /// Attachment error
+    #[cfg(feature = "attachment")]
+    #[error(transparent)]
+    Attachment(#[from] nostr::attachment::Error),
 }
 
 /// Nostr client
@@ -53,7 +221,11 @@ pub enum Error {
 pub struct Client {
     pool: RelayPool,
     keys: Keys,
+    signer: Option<Arc<dyn Signer>>,
     opts: Options,
+    http: Result<reqwest::Client, Arc<http::Error>>,
+    store: Option<Store>,
+    relay_sets: Arc<RwLock<HashMap<String, RelaySet>>>,
 }
 
 impl Client {
@@ -81,11 +253,76 @@ impl Client {
     /// let client = Client::new_with_opts(&my_keys, opts);
     /// ```
     pub fn new_with_opts(keys: &Keys, opts: Options) -> Self {
-        Self {
-            pool: RelayPool::new(),
-            keys: keys.clone(),
-            opts,
-        }
+        ClientBuilder::new(keys).opts(opts).build()
+    }
+
+    /// Create a new [`ClientBuilder`]
+    pub fn builder(keys: &Keys) -> ClientBuilder {
+        ClientBuilder::new(keys)
+    }
+
+    /// Automatically authenticate to relays allowed by the trust policy
+    fn handle_auth(&self) {
+        let client = self.clone();
+        thread::spawn(async move {
+            let mut notifications = client.notifications();
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Message(url, RelayMessage::Auth { challenge })) => {
+                        let policy = client.opts.get_trust_policy();
+                        if policy.is_allowed(&url, RelayAction::Authenticate) {
+                            if let Err(e) = client.auth(challenge, url.clone()).await {
+                                log::error!("Impossible to authenticate to {}: {}", url, e);
+                            }
+                        } else {
+                            log::debug!("Skipped authentication to {}: relay not trusted", url);
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => break,
+                    _ => (),
+                }
+            }
+            log::debug!("Exited from auth handler");
+        });
+    }
+
+    /// Save the `OK` of the audited events in the [`Store`], if any
+    ///
+    /// Received events are saved by the pool, before being notified.
+    fn handle_store(&self) {
+        let store: Store = match self.store.clone() {
+            Some(store) => store,
+            None => return,
+        };
+        store.start_sweeper(STORE_SWEEP_INTERVAL);
+        let mut notifications = self.notifications();
+        let opts: Options = self.opts.clone();
+        thread::spawn(async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Message(
+                        relay,
+                        RelayMessage::Ok {
+                            event_id,
+                            status,
+                            message,
+                        },
+                    )) if opts.get_audit_log() => {
+                        if let Some(event) = store.audited_event(&event_id) {
+                            let action = if status {
+                                AuditAction::Accepted { relay, message }
+                            } else {
+                                AuditAction::Rejected { relay, message }
+                            };
+                            store.append_audit(&event, action);
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => break,
+                    _ => (),
+                }
+            }
+            log::debug!("Exited from store handler");
+        });
     }
 
     /// Update default difficulty for new [`Event`]
@@ -95,9 +332,24 @@ impl Client {
 
     /// Update current [`Options`]
     pub fn update_opts(&self, new_opts: Options) {
+        self.pool
+            .set_subscription_id_generator(new_opts.subscription_ids.clone());
+        self.pool.set_jitter(new_opts.jitter);
+        self.pool.set_document_refresh(new_opts.document_refresh);
+        self.pool.set_system_proxy(new_opts.system_proxy);
         self.opts.update_opts(new_opts);
     }
 
+    /// Replace current [`EventPolicy`]
+    ///
+    /// Useful for policies that need the [`Client`] itself (ex. [`TrustScorePolicy`](crate::web_of_trust::TrustScorePolicy) with a [`WebOfTrust`](crate::web_of_trust::WebOfTrust) scorer).
+    pub fn set_event_policy<T>(&self, policy: T)
+    where
+        T: EventPolicy + 'static,
+    {
+        self.opts.update_event_policy(Arc::new(policy));
+    }
+
     /// Get current [`Keys`]
     pub fn keys(&self) -> Keys {
         self.keys.clone()
@@ -108,16 +360,244 @@ impl Client {
         Ok(self.pool.shutdown().await?)
     }
 
+    /// Get [`Store`]
+    pub fn store(&self) -> Option<Store> {
+        self.store.clone()
+    }
+
+    /// Get [`DisplayName`] resolver of the [`Store`], with the petnames of the contact list of current [`Keys`]
+    pub fn display_names(&self) -> Result<DisplayName, Error> {
+        let store: Store = self.store.clone().ok_or(Error::StoreNotConfigured)?;
+        Ok(DisplayName::new(store, self.keys.public_key()))
+    }
+
     /// Get new notification listener
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.pool.notifications()
     }
 
+    /// Get new listener of the [`RelayPoolNotification::Event`] notifications matching `filters`
+    ///
+    /// Events are routed client-side from the pool notifications, so that each module (ex. direct
+    /// messages, feed, zaps) receives only its events. Routing stops when the receiver is dropped.
+    pub fn notifications_for(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> mpsc::Receiver<RelayPoolNotification> {
+        let (tx, rx) = mpsc::channel(ROUTED_NOTIFICATIONS_CAPACITY);
+        let mut notifications = self.notifications();
+        thread::spawn(async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Event(url, event))
+                        if filters.iter().any(|f| f.match_event(&event)) =>
+                    {
+                        let notification = RelayPoolNotification::Event(url, event);
+                        if tx.send(notification).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!(
+                            "Notification router lagged: {} notifications skipped",
+                            skipped
+                        )
+                    }
+                    _ => (),
+                }
+            }
+            log::debug!("Exited from notification router");
+        });
+        rx
+    }
+
+    /// Get [`VerificationStats`] (signature verification queue and cache)
+    pub fn verification_stats(&self) -> VerificationStats {
+        self.pool.verification_stats()
+    }
+
+    /// Record the traffic of all relays in `path`, to attach to bug reports
+    ///
+    /// Relay url credentials and `AUTH` messages are removed. See [`RelayPool::start_recording`].
+    pub fn start_recording<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(self.pool.start_recording(path)?)
+    }
+
+    /// Stop recording
+    pub fn stop_recording(&self) {
+        self.pool.stop_recording()
+    }
+
+    /// Replay the messages received in the recording at `path`
+    ///
+    /// Return the number of replayed messages. See [`RelayPool::replay`].
+    pub async fn replay<P>(&self, path: P) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let recording = Recording::load(path).map_err(RelayPoolError::from)?;
+        Ok(self.pool.replay(&recording).await)
+    }
+
+    /// Get relays where the [`Event`] was seen on
+    pub async fn seen_on(&self, event_id: EventId) -> Vec<Url> {
+        self.pool.seen_on(&event_id).await
+    }
+
+    /// Check which relays have the [`Event`]
+    ///
+    /// Every relay is queried with an id filter, waiting for `EOSE` up to the relay `EOSE` timeout.
+    /// Check all the relays of the pool if `relays` is empty. Useful after publishing important events
+    /// (ex. key rotations or deletions) to verify their spread.
+    pub async fn check_event_propagation<S>(
+        &self,
+        event_id: EventId,
+        relays: Vec<S>,
+    ) -> Result<PropagationReport, Error>
+    where
+        S: Into<String>,
+    {
+        let pool_relays: HashMap<Url, Relay> = self.pool.relays().await;
+        let urls: Vec<Url> = if relays.is_empty() {
+            pool_relays.keys().cloned().collect()
+        } else {
+            relays
+                .into_iter()
+                .map(|url| relay_url::parse(url.into()))
+                .collect::<Result<_, _>>()?
+        };
+
+        let filter = SubscriptionFilter::new().id(event_id);
+        let results = future::join_all(urls.into_iter().map(|url| {
+            let relay: Option<Relay> = pool_relays.get(&url).cloned();
+            let filter = filter.clone();
+            async move {
+                let result = match relay {
+                    Some(relay) => {
+                        let timeout: Duration = relay.stats().await.eose_timeout;
+                        relay
+                            .get_events_of(vec![filter], timeout)
+                            .await
+                            .map(|events| events.iter().any(|e| e.id == event_id))
+                            .map_err(|e| e.to_string())
+                    }
+                    None => Err(Error::RelayNotFound.to_string()),
+                };
+                (url, result)
+            }
+        }))
+        .await;
+
+        let mut report = PropagationReport::default();
+        for (url, result) in results.into_iter() {
+            match result {
+                Ok(true) => report.found.push(url),
+                Ok(false) => report.missing.push(url),
+                Err(e) => report.failed.push((url, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Probe relay capabilities
+    ///
+    /// Open a dedicated connection (the relay doesn't need to be added to the client), fetch the
+    /// relay information document (NIP-11), test a `REQ`/`EVENT` round-trip with an ephemeral event
+    /// signed with random keys and check if authentication (NIP-42) or payment is required.
+    /// Every step waits up to the [`Options`] timeout.
+    pub async fn probe_relay<S>(&self, url: S) -> Result<RelayProbeReport, Error>
+    where
+        S: Into<String>,
+    {
+        let url = relay_url::parse(url.into())?;
+        Ok(probe::probe(
+            url,
+            self.opts.proxy,
+            self.opts.user_agent.as_ref().map(|ua| ua.to_string()),
+            self.opts.timeouts.timeout(),
+            self.pool.system_proxy(),
+        )
+        .await)
+    }
+
+    /// Get the conversation [`Thread`] of an event (NIP-10)
+    ///
+    /// Fetch the event, its ancestors up to the root and the replies up to `depth` levels below it.
+    /// Events referenced but not found on relays are kept as placeholders.
+    pub async fn get_thread(&self, event_id: EventId, depth: usize) -> Result<Thread, Error> {
+        let mut events: HashMap<EventId, Event> = HashMap::new();
+        let mut requested: HashSet<EventId> = HashSet::new();
+
+        // Target and ancestors
+        let mut pending: Vec<EventId> = vec![event_id];
+        for _ in 0..=MAX_THREAD_ANCESTORS {
+            pending.retain(|id| requested.insert(*id));
+            if pending.is_empty() {
+                break;
+            }
+            let filter = SubscriptionFilter::new().ids(pending.clone());
+            pending.clear();
+            for event in self.get_events_of(vec![filter]).await? {
+                pending.extend(event.root_id());
+                pending.extend(event.reply_to_id());
+                events.insert(event.id, event);
+            }
+        }
+
+        // Replies
+        let mut frontier: Vec<EventId> = vec![event_id];
+        for _ in 0..depth {
+            let filter = SubscriptionFilter::new()
+                .kind(Kind::TextNote)
+                .events(frontier.clone());
+            let mut next: Vec<EventId> = Vec::new();
+            for event in self.get_events_of(vec![filter]).await? {
+                if event
+                    .reply_to_id()
+                    .map_or(false, |parent| frontier.contains(&parent))
+                {
+                    next.push(event.id);
+                }
+                events.entry(event.id).or_insert(event);
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        Ok(Thread::new(event_id, events.into_values().collect(), depth))
+    }
+
+    /// Get notifications of the client public key from the [`Store`], from the newest
+    ///
+    /// See [`NotificationCenter`] to sync, subscribe and mark the notifications as read.
+    pub fn notifications_feed(&self) -> Result<Vec<Notification>, Error> {
+        NotificationCenter::new(self).feed()
+    }
+
     /// Get relays
     pub async fn relays(&self) -> HashMap<Url, Relay> {
         self.pool.relays().await
     }
 
+    /// Get the latest REQs sent to each relay
+    ///
+    /// Only the REQs actually written to the WebSocket are logged, with the filters as sent
+    /// (see [`SentReq::explain`]). Useful to debug why a feed is empty.
+    pub async fn sent_reqs(&self) -> HashMap<Url, Vec<SentReq>> {
+        self.pool.sent_reqs().await
+    }
+
+    /// Get [`PoolStatus`]: connected relays, active subscriptions, pending publishes, event rate and lagging listeners
+    pub async fn pool_status(&self) -> PoolStatus {
+        self.pool.status().await
+    }
+
     /// Add new relay
     ///
     /// # Example
@@ -142,8 +622,8 @@ impl Client {
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
-        self.pool.add_relay(url, proxy).await;
+        let url = relay_url::parse(url.into())?;
+        self.pool.add_relay(url, proxy.or(self.opts.proxy)).await;
         Ok(())
     }
 
@@ -164,7 +644,7 @@ impl Client {
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
+        let url = relay_url::parse(url.into())?;
         self.pool.remove_relay(url).await;
         Ok(())
     }
@@ -200,7 +680,7 @@ impl Client {
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
+        let url = relay_url::parse(url.into())?;
         if let Some(relay) = self.pool.relays().await.get(&url) {
             self.pool.connect_relay(relay, wait_for_connection).await;
             return Ok(());
@@ -208,6 +688,38 @@ impl Client {
         Err(Error::RelayNotFound)
     }
 
+    /// Connect relay and wait for the connection until `token` is cancelled
+    ///
+    /// The connection keeps being established in background after cancellation.
+    pub async fn connect_relay_with_cancel<S>(
+        &self,
+        url: S,
+        token: &CancellationToken,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = relay_url::parse(url.into())?;
+        let relay: Relay = self
+            .pool
+            .relays()
+            .await
+            .remove(&url)
+            .ok_or(Error::RelayNotFound)?;
+        let mut notifications = self.pool.notifications();
+        self.pool.connect_relay(&relay, false).await;
+        with_cancellation(token, None, async {
+            while relay.status().await != RelayStatus::Connected {
+                match notifications.recv().await {
+                    Ok(_) | Err(RecvError::Lagged(_)) => (),
+                    Err(RecvError::Closed) => return Err(Error::RelayNotFound),
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
     /// Disconnect relay
     ///
     /// # Example
@@ -228,7 +740,7 @@ impl Client {
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
+        let url = relay_url::parse(url.into())?;
         if let Some(relay) = self.pool.relays().await.get(&url) {
             self.pool.disconnect_relay(relay).await?;
             return Ok(());
@@ -236,6 +748,26 @@ impl Client {
         Err(Error::RelayNotFound)
     }
 
+    /// Set the [`BandwidthBudget`] of relay (`None` to remove it)
+    ///
+    /// Relays over budget are deprioritized for queries but still used for publishing.
+    /// Usage is reported in [`Relay::stats`].
+    pub async fn set_bandwidth_budget<S>(
+        &self,
+        url: S,
+        budget: Option<BandwidthBudget>,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = relay_url::parse(url.into())?;
+        if let Some(relay) = self.pool.relays().await.get(&url) {
+            relay.set_bandwidth_budget(budget).await;
+            return Ok(());
+        }
+        Err(Error::RelayNotFound)
+    }
+
     /// Connect to all added relays without waiting for connection and keep connection alive
     ///
     /// # Example
@@ -270,6 +802,36 @@ impl Client {
         Ok(self.pool.disconnect().await?)
     }
 
+    /// Wait until all the queued messages are transmitted to relays (or `timeout` expires) and then disconnect
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .graceful_disconnect(Duration::from_secs(10))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn graceful_disconnect(&self, timeout: Duration) -> Result<(), Error> {
+        if let Err(e) = self.pool.flush(timeout).await {
+            log::warn!("Impossible to flush queued messages: {}", e);
+        }
+        self.disconnect().await
+    }
+
+    /// Wait until all the queued messages are transmitted to relays
+    pub async fn flush(&self, timeout: Duration) -> Result<(), Error> {
+        Ok(self.pool.flush(timeout).await?)
+    }
+
     /// Subscribe to filters
     ///
     /// # Example
@@ -287,7 +849,7 @@ impl Client {
     /// client.subscribe(vec![subscription]).await.unwrap();
     /// # }
     /// ```
-    pub async fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<(), Error> {
+    pub async fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<Output<()>, Error> {
         Ok(self
             .pool
             .subscribe(filters, self.opts.get_wait_for_send())
@@ -318,6 +880,35 @@ impl Client {
         Ok(self.pool.get_events_of(filters).await?)
     }
 
+    /// Get events of filters, until `token` is cancelled
+    ///
+    /// Subscriptions are closed on relays when cancelled.
+    pub async fn get_events_of_with_cancel(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        token: &CancellationToken,
+    ) -> Result<Vec<Event>, Error> {
+        with_cancellation(token, None, self.get_events_of(filters)).await
+    }
+
+    /// Get events of many independent queries, running at most `concurrency` queries at a time
+    ///
+    /// The result at index `i` is the result of `queries[i]`.
+    pub async fn get_events_of_many(
+        &self,
+        queries: Vec<Vec<SubscriptionFilter>>,
+        concurrency: usize,
+    ) -> Vec<Result<Vec<Event>, Error>> {
+        stream::iter(
+            queries
+                .into_iter()
+                .map(|filters| self.get_events_of(filters)),
+        )
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+    }
+
     /// Request events of filters
     /// All events will be received on notification listener (`client.notifications()`)
     pub async fn req_events_of(&self, filters: Vec<SubscriptionFilter>, timeout: Duration) {
@@ -327,15 +918,29 @@ impl Client {
     /// Send client message
     #[deprecated]
     pub async fn send_client_msg(&self, msg: ClientMessage, wait: bool) -> Result<(), Error> {
-        Ok(self.pool.send_msg(msg, wait).await?)
+        self.pool.send_msg(msg, wait).await?;
+        Ok(())
     }
 
-    /// Send client message
-    pub async fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
-        Ok(self
+    /// Send client message to all relays
+    pub async fn send_msg(&self, msg: ClientMessage) -> Result<Output<()>, Error> {
+        let event: Option<Event> = match &msg {
+            ClientMessage::Event(event) if self.is_audit_enabled() => Some(event.as_ref().clone()),
+            _ => None,
+        };
+        let output = self
             .pool
             .send_msg(msg, self.opts.get_wait_for_send())
-            .await?)
+            .await?;
+        if let Some(event) = &event {
+            for url in output.success.iter() {
+                self.audit(event, send_action(url.clone(), Ok(())));
+            }
+            for (url, e) in output.failed.iter() {
+                self.audit(event, send_action(url.clone(), Err(e.to_string())));
+            }
+        }
+        Ok(output)
     }
 
     /// Send client message to a specific relay
@@ -343,18 +948,53 @@ impl Client {
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
-        Ok(self
+        let url = relay_url::parse(url.into())?;
+        Ok(self.send_msg_to_relay(url, msg).await?)
+    }
+
+    async fn send_msg_to_relay(&self, url: Url, msg: ClientMessage) -> Result<(), RelayPoolError> {
+        let event: Option<Event> = match &msg {
+            ClientMessage::Event(event) if self.is_audit_enabled() => Some(event.as_ref().clone()),
+            _ => None,
+        };
+        let result = self
             .pool
-            .send_msg_to(url, msg, self.opts.get_wait_for_send())
-            .await?)
+            .send_msg_to(url.clone(), msg, self.opts.get_wait_for_send())
+            .await;
+        if let Some(event) = &event {
+            let outcome = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+            self.audit(event, send_action(url, outcome));
+        }
+        result
+    }
+
+    fn is_audit_enabled(&self) -> bool {
+        self.store.is_some() && self.opts.get_audit_log()
+    }
+
+    fn audit(&self, event: &Event, action: AuditAction) {
+        if let Some(store) = &self.store {
+            if self.opts.get_audit_log() {
+                store.append_audit(event, action);
+            }
+        }
+    }
+
+    /// Get audit log entries of the events matching any of `filters` (all if `filters` is empty)
+    ///
+    /// Requires a [`Store`] and the audit log enabled with [`Options::audit_log`].
+    pub fn audit_log(&self, filters: Vec<SubscriptionFilter>) -> Result<Vec<AuditEntry>, Error> {
+        let store: &Store = self.store.as_ref().ok_or(Error::StoreNotConfigured)?;
+        Ok(store.audit_log(filters))
     }
 
-    /// Send event
-    pub async fn send_event(&self, event: Event) -> Result<EventId, Error> {
+    /// Send event to all relays
+    ///
+    /// The [`Output`] value is the event id.
+    pub async fn send_event(&self, event: Event) -> Result<Output<EventId>, Error> {
         let event_id = event.id;
-        self.send_msg(ClientMessage::new_event(event)).await?;
-        Ok(event_id)
+        let output = self.send_msg(ClientMessage::new_event(event)).await?;
+        Ok(output.map(|_| event_id))
     }
 
     /// Send event to specific relay
@@ -368,46 +1008,7 @@ impl Client {
         Ok(event_id)
     }
 
-    async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
-        let difficulty: u8 = self.opts.get_difficulty();
-        let event: Event = if difficulty > 0 {
-            builder.to_pow_event(&self.keys, difficulty)?
-        } else {
-            builder.to_event(&self.keys)?
-        };
-        self.send_event(event).await
-    }
-
-    /// Update profile metadata
-    ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
-    ///
-    /// # Example
-    /// ```rust,no_run
-    /// use nostr_sdk::prelude::*;
-    ///
-    /// # #[tokio::main]
-    /// # async fn main() {
-    /// #   let my_keys = Keys::generate();
-    /// #   let client = Client::new(&my_keys);
-    /// let metadata = Metadata::new()
-    ///     .name("username")
-    ///     .display_name("My Username")
-    ///     .about("Description")
-    ///     .picture(Url::parse("https://example.com/avatar.png").unwrap())
-    ///     .nip05("username@example.com");
-    ///
-    /// client.update_profile(metadata).await.unwrap();
-    /// # }
-    /// ```
-    pub async fn update_profile(&self, metadata: Metadata) -> Result<EventId, Error> {
-        let builder = EventBuilder::set_metadata(metadata)?;
-        self.send_event_builder(builder).await
-    }
-
-    /// Publish text note
-    ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    /// Create (or replace) relay set `name` with [`ConnectionStrategy::Persistent`]
     ///
     /// # Example
     /// ```rust,no_run
@@ -418,75 +1019,636 @@ impl Client {
     /// #   let my_keys = Keys::generate();
     /// #   let client = Client::new(&my_keys);
     /// client
-    ///     .publish_text_note("My first text note from Nostr SDK!", &[])
+    ///     .create_relay_set("search", vec!["wss://relay.nostr.band"])
+    ///     .await
+    ///     .unwrap();
+    /// let filter = SubscriptionFilter::new().search("nostr").limit(10);
+    /// let _events = client
+    ///     .get_events_from_set("search", vec![filter])
     ///     .await
     ///     .unwrap();
     /// # }
     /// ```
-    pub async fn publish_text_note<S>(&self, content: S, tags: &[Tag]) -> Result<EventId, Error>
+    pub async fn create_relay_set<N, S>(&self, name: N, urls: Vec<S>) -> Result<(), Error>
     where
+        N: Into<String>,
         S: Into<String>,
     {
-        let builder = EventBuilder::new_text_note(content, tags);
-        self.send_event_builder(builder).await
+        self.create_relay_set_with_strategy(name, urls, ConnectionStrategy::Persistent)
+            .await
     }
 
-    /// Publish POW text note
-    ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
-    ///
-    /// # Example
-    /// ```rust,no_run
-    /// use nostr_sdk::prelude::*;
+    /// Create (or replace) relay set `name` with [`ConnectionStrategy`]
     ///
-    /// # #[tokio::main]
-    /// # async fn main() {
-    /// #   let my_keys = Keys::generate();
-    /// #   let client = Client::new(&my_keys);
-    /// client
-    ///     .publish_pow_text_note("My first POW text note from Nostr SDK!", &[], 16)
-    ///     .await
-    ///     .unwrap();
-    /// # }
-    /// ```
-    #[cfg(feature = "nip13")]
-    pub async fn publish_pow_text_note<S>(
+    /// The relays are added to the pool. Note that [`Client::connect`] connects all the relays of the pool,
+    /// also the ones of [`ConnectionStrategy::OnDemand`] sets.
+    pub async fn create_relay_set_with_strategy<N, S>(
         &self,
-        content: S,
-        tags: &[Tag],
-        difficulty: u8,
-    ) -> Result<EventId, Error>
+        name: N,
+        urls: Vec<S>,
+        strategy: ConnectionStrategy,
+    ) -> Result<(), Error>
     where
+        N: Into<String>,
         S: Into<String>,
     {
-        let event: Event =
-            EventBuilder::new_text_note(content, tags).to_pow_event(&self.keys, difficulty)?;
-        self.send_event(event).await
+        let mut parsed: Vec<Url> = Vec::with_capacity(urls.len());
+        for url in urls.into_iter() {
+            parsed.push(relay_url::parse(url.into())?);
+        }
+        let set = RelaySet::new(name, parsed, strategy);
+        for url in set.urls.iter() {
+            self.pool.add_relay(url.clone(), self.opts.proxy).await;
+        }
+        if strategy == ConnectionStrategy::Persistent {
+            self.connect_relay_set_relays(&set, self.opts.get_wait_for_connection())
+                .await;
+        }
+        let mut relay_sets = self.relay_sets.write().await;
+        relay_sets.insert(set.name.clone(), set);
+        Ok(())
     }
 
-    /// Add recommended relay
-    ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    /// Remove relay set `name`
     ///
-    /// # Example
-    /// ```rust,no_run
-    /// use nostr_sdk::prelude::*;
-    ///
-    /// # #[tokio::main]
-    /// # async fn main() {
-    /// #   let my_keys = Keys::generate();
-    /// #   let client = Client::new(&my_keys);
-    /// client
-    ///     .add_recommended_relay("wss://relay.damus.io")
-    ///     .await
-    ///     .unwrap();
-    /// # }
-    /// ```
-    pub async fn add_recommended_relay<S>(&self, url: S) -> Result<EventId, Error>
-    where
+    /// Relays stay in the pool: use [`Client::remove_relay`] to remove them.
+    pub async fn remove_relay_set(&self, name: &str) -> Option<RelaySet> {
+        let mut relay_sets = self.relay_sets.write().await;
+        relay_sets.remove(name)
+    }
+
+    /// Get relay set `name`
+    pub async fn relay_set(&self, name: &str) -> Option<RelaySet> {
+        let relay_sets = self.relay_sets.read().await;
+        relay_sets.get(name).cloned()
+    }
+
+    /// Get relay sets
+    pub async fn relay_sets(&self) -> Vec<RelaySet> {
+        let relay_sets = self.relay_sets.read().await;
+        relay_sets.values().cloned().collect()
+    }
+
+    /// Connect the relays of set `name`
+    pub async fn connect_relay_set(
+        &self,
+        name: &str,
+        wait_for_connection: bool,
+    ) -> Result<(), Error> {
+        let set: RelaySet = self.get_relay_set(name).await?;
+        self.connect_relay_set_relays(&set, wait_for_connection)
+            .await;
+        Ok(())
+    }
+
+    /// Disconnect the relays of set `name`
+    pub async fn disconnect_relay_set(&self, name: &str) -> Result<(), Error> {
+        let set: RelaySet = self.get_relay_set(name).await?;
+        let relays = self.pool.relays().await;
+        for url in set.urls.iter() {
+            if let Some(relay) = relays.get(url) {
+                self.pool.disconnect_relay(relay).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send event to the relays of set `name`
+    pub async fn send_event_to_set(&self, name: &str, event: Event) -> Result<EventId, Error> {
+        let set: RelaySet = self.use_relay_set(name).await?;
+        let event_id = event.id;
+        let msg = ClientMessage::new_event(event);
+        let mut urls: Vec<Url> = set.urls;
+        self.pool.shuffle(&mut urls);
+        for (i, url) in urls.into_iter().enumerate() {
+            if i > 0 {
+                self.pool.wait_jitter().await;
+            }
+            if let Err(e) = self.send_msg_to_relay(url.clone(), msg.clone()).await {
+                log::error!("Impossible to send event to {}: {}", url, e);
+            }
+        }
+        Ok(event_id)
+    }
+
+    /// Get events of filters from the relays of set `name`
+    pub async fn get_events_from_set(
+        &self,
+        name: &str,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<Vec<Event>, Error> {
+        let set: RelaySet = self.use_relay_set(name).await?;
+        Ok(self.pool.get_events_from(&set.urls, filters).await?)
+    }
+
+    async fn get_relay_set(&self, name: &str) -> Result<RelaySet, Error> {
+        self.relay_set(name)
+            .await
+            .ok_or_else(|| Error::RelaySetNotFound(name.to_string()))
+    }
+
+    /// Get relay set `name`, connecting its relays if [`ConnectionStrategy::OnDemand`]
+    async fn use_relay_set(&self, name: &str) -> Result<RelaySet, Error> {
+        let set: RelaySet = self.get_relay_set(name).await?;
+        if set.strategy == ConnectionStrategy::OnDemand {
+            self.connect_relay_set_relays(&set, true).await;
+        }
+        Ok(set)
+    }
+
+    async fn connect_relay_set_relays(&self, set: &RelaySet, wait_for_connection: bool) {
+        let relays = self.pool.relays().await;
+        for url in set.urls.iter() {
+            if let Some(relay) = relays.get(url) {
+                self.pool.connect_relay(relay, wait_for_connection).await;
+            }
+        }
+    }
+
+    /// Import events from a backup of another client or relay into the store
+    ///
+    /// See [`Backup`] for the supported formats. Events are strictly validated, invalid entries
+    /// and duplicates are skipped. If `rebroadcast` is `true`, imported events are re-published
+    /// with [`Client::broadcast`].
+    pub async fn import_backup<S>(&self, data: S, rebroadcast: bool) -> Result<ImportReport, Error>
+    where
+        S: AsRef<str>,
+    {
+        let store: Store = self.store.clone().ok_or(Error::StoreNotConfigured)?;
+        let backup = Backup::parse(data);
+        let mut report = ImportReport {
+            duplicates: backup.duplicates,
+            invalid: backup.invalid,
+            ..Default::default()
+        };
+        for event in backup.events.into_iter() {
+            if !store.save_event(event.clone()) {
+                report.skipped += 1;
+                continue;
+            }
+            report.imported += 1;
+            if rebroadcast && !self.broadcast(event).await?.is_empty() {
+                report.rebroadcast += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Export the account to an encrypted [`AccountBundle`] file
+    ///
+    /// The latest events of [`ACCOUNT_KINDS`](crate::store::bundle::ACCOUNT_KINDS) are fetched from
+    /// the relays and from the store, if configured. The secret key is included if `include_secret`
+    /// is `true`. Use [`StoreSecret::Passphrase`] to import the bundle in another app.
+    /// Return the number of exported events.
+    #[cfg(feature = "encrypted-store")]
+    pub async fn export_account<P>(
+        &self,
+        path: P,
+        include_secret: bool,
+        secret: &StoreSecret,
+    ) -> Result<usize, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let filter = SubscriptionFilter::new()
+            .author(self.keys.public_key())
+            .kinds(
+                crate::store::bundle::ACCOUNT_KINDS
+                    .into_iter()
+                    .map(Kind::from)
+                    .collect(),
+            );
+        let mut events: Vec<Event> = self.get_events_of(vec![filter.clone()]).await?;
+        if let Some(store) = &self.store {
+            events.extend(store.query(vec![filter.clone()]));
+        }
+
+        // Keep the latest version of replaceable events
+        let latest = Store::new();
+        for event in events.into_iter() {
+            latest.save_event(event);
+        }
+        let bundle = AccountBundle::new(&self.keys, include_secret, latest.query(vec![filter]))?;
+        bundle.save(path, secret)?;
+        Ok(bundle.events.len())
+    }
+
+    /// Import an account exported with [`Client::export_account`]
+    ///
+    /// Events are saved in the store, if configured, and re-published with [`Client::broadcast`].
+    /// A failure to re-publish an event doesn't stop the import: see [`AccountImport::failed`].
+    #[cfg(feature = "encrypted-store")]
+    pub async fn import_account<P>(
+        &self,
+        path: P,
+        secret: &StoreSecret,
+    ) -> Result<AccountImport, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let bundle = AccountBundle::load(path, secret)?;
+        let mut rebroadcast: usize = 0;
+        let mut failed: Vec<EventId> = Vec::new();
+        for event in bundle.events.iter() {
+            if let Some(store) = &self.store {
+                store.save_event(event.clone());
+            }
+            match self.broadcast(event.clone()).await {
+                Ok(relays) if !relays.is_empty() => rebroadcast += 1,
+                Ok(..) => {
+                    log::warn!("Account event {} not re-published", event.id);
+                    failed.push(event.id);
+                }
+                Err(e) => {
+                    log::error!("Impossible to re-publish account event {}: {}", event.id, e);
+                    failed.push(event.id);
+                }
+            }
+        }
+        Ok(AccountImport {
+            bundle,
+            rebroadcast,
+            failed,
+        })
+    }
+
+    /// Re-publish an existing signed event to the relays of the pool
+    ///
+    /// The signature is verified before sending. Relays already known to have the event
+    /// (see [`Client::seen_on`]) are skipped. Return the relays the event was sent to.
+    pub async fn broadcast(&self, event: Event) -> Result<Vec<Url>, Error> {
+        event.verify()?;
+        let seen_on: Vec<Url> = self.pool.seen_on(&event.id).await;
+        let msg = ClientMessage::new_event(event);
+        let mut sent: Vec<Url> = Vec::new();
+        let mut urls: Vec<Url> = self.pool.relays().await.into_keys().collect();
+        self.pool.shuffle(&mut urls);
+        for url in urls.into_iter() {
+            if seen_on.contains(&url) {
+                log::debug!("Skipped broadcast to {}: event already seen", url);
+                continue;
+            }
+            if !sent.is_empty() {
+                self.pool.wait_jitter().await;
+            }
+            match self.send_msg_to_relay(url.clone(), msg.clone()).await {
+                Ok(()) => sent.push(url),
+                Err(e) => log::error!("Impossible to broadcast event to {}: {}", url, e),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Send event only to relays that allow [`RelayAction`]
+    async fn send_event_with_action(
+        &self,
+        event: Event,
+        action: RelayAction,
+    ) -> Result<EventId, Error> {
+        let event_id = event.id;
+        let policy = self.opts.get_trust_policy();
+        let mut urls: Vec<Url> = trust::allowed_relays(
+            policy.as_ref(),
+            self.pool.relays().await.into_keys(),
+            action,
+        );
+        if urls.is_empty() {
+            return Err(Error::NoTrustedRelay);
+        }
+        self.pool.shuffle(&mut urls);
+        let msg = ClientMessage::new_event(event);
+        for (i, url) in urls.into_iter().enumerate() {
+            if i > 0 {
+                self.pool.wait_jitter().await;
+            }
+            if let Err(e) = self.send_msg_to_relay(url.clone(), msg.clone()).await {
+                log::error!("Impossible to send msg to {}: {}", url, e);
+            }
+        }
+        Ok(event_id)
+    }
+
+    fn build_event(&self, builder: EventBuilder) -> Result<Event, Error> {
+        let builder = match &self.opts.user_agent {
+            Some(user_agent) if self.opts.get_client_tag() => builder.add_tags(&[Tag::Generic(
+                TagKind::Custom(String::from("client")),
+                vec![user_agent.name.clone()],
+            )]),
+            _ => builder,
+        };
+        let builder: EventBuilder = self.apply_outgoing_hooks(builder)?;
+        let event: Event = self.sign(builder, self.opts.get_difficulty())?;
+        self.audit(&event, AuditAction::Signed);
+        Ok(event)
+    }
+
+    /// Sign with the [`Signer`], if set, or with the keys
+    fn sign(&self, builder: EventBuilder, difficulty: u8) -> Result<Event, Error> {
+        match &self.signer {
+            Some(signer) => {
+                let public_key: XOnlyPublicKey = self.keys.public_key();
+                let unsigned: UnsignedEvent = if difficulty > 0 {
+                    builder.to_unsigned_pow_event(public_key, difficulty)
+                } else {
+                    builder.to_unsigned_event(public_key)
+                };
+                Ok(signer.sign_event(unsigned)?)
+            }
+            None if difficulty > 0 => Ok(builder.to_pow_event(&self.keys, difficulty)?),
+            None => Ok(builder.to_event(&self.keys)?),
+        }
+    }
+
+    pub(crate) fn apply_outgoing_hooks(
+        &self,
+        builder: EventBuilder,
+    ) -> Result<EventBuilder, Error> {
+        if self.opts.outgoing_hooks.is_empty() {
+            return Ok(builder);
+        }
+        let mut unsigned: UnsignedEvent = builder.to_unsigned_event(self.keys.public_key());
+        for hook in self.opts.outgoing_hooks.iter() {
+            hook.process(&mut unsigned).map_err(Error::Vetoed)?;
+        }
+        Ok(EventBuilder::new(
+            unsigned.kind,
+            unsigned.content,
+            &unsigned.tags,
+        ))
+    }
+
+    async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
+        let event: Event = self.build_event(builder)?;
+        Ok(self.send_event(event).await?.value)
+    }
+
+    /// Authenticate to relay
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    ///
+    /// Relays allowed by the trust policy are authenticated automatically.
+    pub async fn auth<S>(&self, challenge: S, url: Url) -> Result<(), Error>
+    where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
+        let event: Event = self.sign(EventBuilder::auth(challenge, url.clone()), 0)?;
+        self.audit(&event, AuditAction::Signed);
+        Ok(self
+            .pool
+            .send_msg_to(
+                url,
+                ClientMessage::new_auth(event),
+                self.opts.get_wait_for_send(),
+            )
+            .await?)
+    }
+
+    /// Update profile metadata
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// The latest published metadata is fetched from relays and the fields set in `metadata` are merged over it.
+    /// Fail with [`Error::MetadataNotFound`] if relays don't return any metadata, so that a profile that
+    /// couldn't be fetched is not overwritten: use [`Client::set_metadata`] for the first metadata of an account.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let metadata = Metadata::new()
+    ///     .name("username")
+    ///     .display_name("My Username")
+    ///     .about("Description")
+    ///     .picture(Url::parse("https://example.com/avatar.png").unwrap())
+    ///     .nip05("username@example.com");
+    ///
+    /// client.update_profile(metadata).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn update_profile(&self, metadata: Metadata) -> Result<EventId, Error> {
+        self.edit_profile(|current| current.merge(metadata)).await
+    }
+
+    /// Set profile metadata
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// Publish `metadata` as is, replacing the published one (ex. new account).
+    /// Use [`Client::update_profile`] or [`Client::edit_profile`] to keep the published fields.
+    pub async fn set_metadata(&self, metadata: Metadata) -> Result<EventId, Error> {
+        let builder = EventBuilder::set_metadata(metadata)?;
+        self.send_event_builder(builder).await
+    }
+
+    /// Edit profile metadata
+    ///
+    /// Fetch the latest published metadata, apply `f` and publish the result.
+    /// Fail with [`Error::MetadataNotFound`] if relays don't return any metadata.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .edit_profile(|metadata| metadata.about("New description"))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn edit_profile<F>(&self, f: F) -> Result<EventId, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        let current: Metadata = self.get_metadata().await?.ok_or(Error::MetadataNotFound)?;
+        let builder = EventBuilder::set_metadata(f(current))?;
+        self.send_event_builder(builder).await
+    }
+
+    /// Get latest profile metadata published by current [`Keys`]
+    pub async fn get_metadata(&self) -> Result<Option<Metadata>, Error> {
+        self.get_metadata_of(self.keys.public_key()).await
+    }
+
+    /// Get latest profile metadata of `public_key`, ready to be rendered
+    ///
+    /// See [`Client::sanitize_metadata`].
+    pub async fn get_profile(&self, public_key: XOnlyPublicKey) -> Result<Option<Metadata>, Error> {
+        Ok(self
+            .get_metadata_of(public_key)
+            .await?
+            .map(|metadata| self.sanitize_metadata(metadata)))
+    }
+
+    /// Sanitize picture and banner urls of [`Metadata`] before rendering
+    ///
+    /// Urls rejected by the image url policy are removed, the others are rewritten by the image proxy,
+    /// if configured (see [`Options::image_url_policy`] and [`Options::image_proxy`]).
+    /// Don't publish the sanitized metadata: fetch it with [`Client::get_metadata`] to update the profile.
+    pub fn sanitize_metadata(&self, metadata: Metadata) -> Metadata {
+        let metadata: Metadata = match &self.opts.image_url_policy {
+            Some(policy) => metadata.sanitize_images(policy),
+            None => metadata,
+        };
+        match &self.opts.image_proxy {
+            Some(proxy) => {
+                let rewrite = |url: Option<String>| -> Option<String> {
+                    let url: String = url?;
+                    match Url::parse(&url) {
+                        Ok(parsed) if parsed.scheme() != "data" => {
+                            Some(proxy.rewrite(&parsed).to_string())
+                        }
+                        _ => Some(url),
+                    }
+                };
+                Metadata {
+                    picture: rewrite(metadata.picture),
+                    banner: rewrite(metadata.banner),
+                    ..metadata
+                }
+            }
+            None => metadata,
+        }
+    }
+
+    async fn get_metadata_of(&self, public_key: XOnlyPublicKey) -> Result<Option<Metadata>, Error> {
+        let filter = SubscriptionFilter::new()
+            .authors(vec![public_key])
+            .kind(Kind::Metadata)
+            .limit(1);
+        let events: Vec<Event> = self.get_events_of(vec![filter]).await?;
+        match events.into_iter().max_by_key(|e| e.created_at) {
+            Some(event) => Ok(Some(Metadata::from_json(event.content)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get latest metadata of `public_keys`, requested in batches of `batch_size` authors
+    ///
+    /// Public keys without metadata (or with invalid metadata) are not included in the map.
+    #[cfg(feature = "nip05")]
+    pub(crate) async fn get_metadata_batched(
+        &self,
+        mut public_keys: Vec<XOnlyPublicKey>,
+        batch_size: usize,
+    ) -> Result<HashMap<XOnlyPublicKey, Metadata>, Error> {
+        public_keys.sort();
+        public_keys.dedup();
+
+        let mut latest: HashMap<XOnlyPublicKey, Event> = HashMap::new();
+        for chunk in public_keys.chunks(batch_size.max(1)) {
+            let filter = SubscriptionFilter::new()
+                .authors(chunk.to_vec())
+                .kind(Kind::Metadata);
+            let events: Vec<Event> = self.get_events_of(vec![filter]).await?;
+            for event in events.into_iter() {
+                if !chunk.contains(&event.pubkey) {
+                    continue;
+                }
+                match latest.get(&event.pubkey) {
+                    Some(current) if current.created_at >= event.created_at => {}
+                    _ => {
+                        latest.insert(event.pubkey, event);
+                    }
+                }
+            }
+        }
+
+        Ok(latest
+            .into_iter()
+            .filter_map(|(pk, event)| Some((pk, Metadata::from_json(event.content).ok()?)))
+            .collect())
+    }
+
+    /// Publish text note
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .publish_text_note("My first text note from Nostr SDK!", &[])
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn publish_text_note<S>(&self, content: S, tags: &[Tag]) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let builder = EventBuilder::new_text_note(content, tags);
+        self.send_event_builder(builder).await
+    }
+
+    /// Publish POW text note
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .publish_pow_text_note("My first POW text note from Nostr SDK!", &[], 16)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "nip13")]
+    pub async fn publish_pow_text_note<S>(
+        &self,
+        content: S,
+        tags: &[Tag],
+        difficulty: u8,
+    ) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let event: Event = self.sign(EventBuilder::new_text_note(content, tags), difficulty)?;
+        self.audit(&event, AuditAction::Signed);
+        Ok(self.send_event(event).await?.value)
+    }
+
+    /// Add recommended relay
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .add_recommended_relay("wss://relay.damus.io")
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn add_recommended_relay<S>(&self, url: S) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let url = relay_url::parse(url.into())?;
         let builder = EventBuilder::add_recommended_relay(&url);
         self.send_event_builder(builder).await
     }
@@ -499,10 +1661,39 @@ impl Client {
         self.send_event_builder(builder).await
     }
 
-    /// Get contact list
+    /// Get contact list
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/02.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let _list = client.get_contact_list().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn get_contact_list(&self) -> Result<Vec<Contact>, Error> {
+        self.get_contact_list_of(self.keys.public_key()).await
+    }
+
+    async fn get_contact_list_of(&self, public_key: XOnlyPublicKey) -> Result<Vec<Contact>, Error> {
+        let mut lists = self.get_contact_lists(vec![public_key]).await?;
+        Ok(lists
+            .remove(&public_key)
+            .map(|list| list.contacts)
+            .unwrap_or_default())
+    }
+
+    /// Get latest contact lists of `public_keys`
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/02.md>
     ///
+    /// Authors are requested in batches of [`CONTACT_LISTS_BATCH_SIZE`]. Public keys without a contact list are not included in the map.
+    ///
     /// # Example
     /// ```rust,no_run
     /// use nostr_sdk::prelude::*;
@@ -511,38 +1702,142 @@ impl Client {
     /// # async fn main() {
     /// #   let my_keys = Keys::generate();
     /// #   let client = Client::new(&my_keys);
-    /// let _list = client.get_contact_list().await.unwrap();
+    /// let follows = client.get_contact_list().await.unwrap();
+    /// let pubkeys = follows.into_iter().map(|c| c.pk).collect();
+    /// let _lists = client.get_contact_lists(pubkeys).await.unwrap();
     /// # }
     /// ```
-    pub async fn get_contact_list(&self) -> Result<Vec<Contact>, Error> {
-        let mut contact_list: Vec<Contact> = Vec::new();
+    pub async fn get_contact_lists(
+        &self,
+        public_keys: Vec<XOnlyPublicKey>,
+    ) -> Result<HashMap<XOnlyPublicKey, ContactList>, Error> {
+        self.get_contact_lists_batched(public_keys, CONTACT_LISTS_BATCH_SIZE)
+            .await
+    }
 
-        let filter = SubscriptionFilter::new()
-            .authors(vec![self.keys.public_key()])
-            .kind(Kind::ContactList)
-            .limit(1);
-        let events: Vec<Event> = self.get_events_of(vec![filter]).await?;
+    pub(crate) async fn get_contact_lists_batched(
+        &self,
+        mut public_keys: Vec<XOnlyPublicKey>,
+        batch_size: usize,
+    ) -> Result<HashMap<XOnlyPublicKey, ContactList>, Error> {
+        public_keys.sort();
+        public_keys.dedup();
 
-        for event in events.into_iter() {
-            for tag in event.tags.into_iter() {
-                if let Tag::ContactList {
-                    pk,
-                    relay_url,
-                    alias,
-                } = tag
-                {
-                    contact_list.push(Contact::new(pk, relay_url, alias));
+        let mut lists: HashMap<XOnlyPublicKey, ContactList> = HashMap::new();
+        for chunk in public_keys.chunks(batch_size.max(1)) {
+            let filter = SubscriptionFilter::new()
+                .authors(chunk.to_vec())
+                .kind(Kind::ContactList);
+            let events: Vec<Event> = self.get_events_of(vec![filter]).await?;
+            for event in events.iter() {
+                if !chunk.contains(&event.pubkey) {
+                    continue;
+                }
+                if let Some(list) = ContactList::from_event(event) {
+                    match lists.get(&event.pubkey) {
+                        Some(current) if current.created_at >= list.created_at => {}
+                        _ => {
+                            lists.insert(event.pubkey, list);
+                        }
+                    }
                 }
             }
         }
 
-        Ok(contact_list)
+        Ok(lists)
+    }
+
+    /// Migrate identity from `old_keys` to `new_keys`
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    ///
+    /// Steps:
+    /// 1. publish the profile of `old_keys` with `new_keys`, including a delegation tag signed by `old_keys`
+    /// 2. copy the contact list of `old_keys` to `new_keys`
+    /// 3. publish a final profile with `old_keys`, pointing to the new public key
+    ///
+    /// The result of each step is reported in the returned [`MigrationReport`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let old_keys = Keys::generate();
+    /// #   let client = Client::new(&old_keys);
+    /// let new_keys = Keys::generate();
+    /// let report = client.migrate_identity(&old_keys, &new_keys).await.unwrap();
+    /// if !report.is_complete() {
+    ///     println!("Migration incomplete: {:?}", report.steps());
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "nip26")]
+    pub async fn migrate_identity(
+        &self,
+        old_keys: &Keys,
+        new_keys: &Keys,
+    ) -> Result<MigrationReport, Error> {
+        // Events of the new identity must be created after the delegation start
+        let since = nostr::Timestamp::now() - Duration::from_secs(1);
+        let delegation: Tag = nip26::create_delegation_tag(old_keys, new_keys.public_key(), since)?;
+        let metadata: Metadata = self
+            .get_metadata_of(old_keys.public_key())
+            .await?
+            .unwrap_or_default();
+        let contacts: Vec<Contact> = self.get_contact_list_of(old_keys.public_key()).await?;
+
+        let mut report = MigrationReport::default();
+
+        let res: Result<Option<EventId>, Error> = async {
+            let event: Event =
+                EventBuilder::new(Kind::Metadata, metadata.as_json()?, &[delegation])
+                    .to_event(new_keys)?;
+            self.audit(&event, AuditAction::Signed);
+            Ok(Some(self.send_event(event).await?.value))
+        }
+        .await;
+        if !report.record(MigrationStep::Profile, res) {
+            return Ok(report);
+        }
+
+        let res: Result<Option<EventId>, Error> = async {
+            if contacts.is_empty() {
+                return Ok(None);
+            }
+            let event: Event = EventBuilder::set_contact_list(contacts).to_event(new_keys)?;
+            self.audit(&event, AuditAction::Signed);
+            Ok(Some(self.send_event(event).await?.value))
+        }
+        .await;
+        if !report.record(MigrationStep::ContactList, res) {
+            return Ok(report);
+        }
+
+        let res: Result<Option<EventId>, Error> = async {
+            let about: String = format!("This account has moved to {}", moved_to(new_keys));
+            let event: Event = EventBuilder::new(
+                Kind::Metadata,
+                metadata.about(about).as_json()?,
+                &[Tag::PubKey(new_keys.public_key(), None)],
+            )
+            .to_event(old_keys)?;
+            self.audit(&event, AuditAction::Signed);
+            Ok(Some(self.send_event(event).await?.value))
+        }
+        .await;
+        report.record(MigrationStep::Announcement, res);
+
+        Ok(report)
     }
 
     /// Send encrypted direct message
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/04.md>
     ///
+    /// The message is sent only to relays allowed by the trust policy.
+    ///
     /// # Example
     /// ```rust,no_run
     /// use nostr_sdk::prelude::*;
@@ -572,7 +1867,41 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::new_encrypted_direct_msg(&self.keys, receiver, msg)?;
-        self.send_event_builder(builder).await
+        let event: Event = self.build_event(builder)?;
+        self.send_event_with_action(event, RelayAction::SendDirectMessage)
+            .await
+    }
+
+    /// Send attachment as encrypted direct messages, split in chunks
+    ///
+    /// Return the [`EventId`] of every chunk. The receiver reassembles them with an
+    /// [`AttachmentAssembler`](nostr::attachment::AttachmentAssembler).
+    #[cfg(feature = "attachment")]
+    pub async fn send_attachment<S>(
+        &self,
+        receiver: XOnlyPublicKey,
+        data: &[u8],
+        mime_type: Option<S>,
+    ) -> Result<Vec<EventId>, Error>
+    where
+        S: Into<String>,
+    {
+        let builders = EventBuilder::encrypted_attachment(
+            &self.keys,
+            receiver,
+            data,
+            mime_type,
+            nostr::attachment::DEFAULT_CHUNK_SIZE,
+        )?;
+        let mut ids: Vec<EventId> = Vec::with_capacity(builders.len());
+        for builder in builders.into_iter() {
+            let event: Event = self.build_event(builder)?;
+            ids.push(
+                self.send_event_with_action(event, RelayAction::SendDirectMessage)
+                    .await?,
+            );
+        }
+        Ok(ids)
     }
 
     /// Repost event
@@ -588,6 +1917,8 @@ impl Client {
     /// Delete event
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/09.md>
+    ///
+    /// The deletion is published only to relays allowed by the trust policy.
     pub async fn delete_event<S>(
         &self,
         event_id: EventId,
@@ -597,7 +1928,9 @@ impl Client {
         S: Into<String>,
     {
         let builder = EventBuilder::delete(vec![event_id], reason);
-        self.send_event_builder(builder).await
+        let event: Event = self.build_event(builder)?;
+        self.send_event_with_action(event, RelayAction::PublishDeletion)
+            .await
     }
 
     /// Like event
@@ -779,30 +2112,68 @@ impl Client {
             .await
     }
 
-    /// Get entity of hex string
+    /// Get entity of hex string (event id or public key) or coordinate (`<kind>:<pubkey>:<d-identifier>`)
+    ///
+    /// The underlying event is attached when found: the event itself, the latest metadata of an account
+    /// or the latest version of a parameterized replaceable event (see [`Entity::from_event`]).
     pub async fn get_entity_of<S>(&self, entity: S) -> Result<Entity, Error>
     where
         S: Into<String>,
     {
         let entity: String = entity.into();
-        let events: Vec<Event> = self
-            .get_events_of(vec![SubscriptionFilter::new()
-                .id(&entity)
-                .kind(Kind::ChannelCreation)
-                .limit(1)])
-            .await?;
-        if events.is_empty() {
-            let pubkey = XOnlyPublicKey::from_str(&entity)?;
-            let events: Vec<Event> = self
-                .get_events_of(vec![SubscriptionFilter::new().author(pubkey).limit(1)])
-                .await?;
-            if events.is_empty() {
-                Ok(Entity::Unknown)
-            } else {
-                Ok(Entity::Account)
+
+        if let Ok(coordinate) = Coordinate::from_str(&entity) {
+            let filter = SubscriptionFilter::new()
+                .kind(coordinate.kind)
+                .author(coordinate.pubkey);
+            let event: Option<Event> = self
+                .get_events_of(vec![filter])
+                .await?
+                .into_iter()
+                .filter(|e| {
+                    e.tags.iter().any(|t| match t.as_vec().as_slice() {
+                        [tag, identifier, ..] => tag == "d" && identifier == &coordinate.identifier,
+                        _ => false,
+                    })
+                })
+                .max_by_key(|e| e.created_at);
+            return Ok(match event {
+                Some(event) => Entity::from_event(event),
+                None => Entity::Coordinate {
+                    coordinate,
+                    event: None,
+                },
+            });
+        }
+
+        let id = EventId::from_hex(&entity)?;
+        let public_key: Option<XOnlyPublicKey> = XOnlyPublicKey::from_str(&entity).ok();
+        let mut filters: Vec<SubscriptionFilter> = vec![SubscriptionFilter::new().id(id).limit(1)];
+        if let Some(public_key) = public_key {
+            filters.push(
+                SubscriptionFilter::new()
+                    .author(public_key)
+                    .kind(Kind::Metadata)
+                    .limit(1),
+            );
+            filters.push(SubscriptionFilter::new().author(public_key).limit(1));
+        }
+        let events: Vec<Event> = self.get_events_of(filters).await?;
+
+        if let Some(event) = events.iter().find(|e| e.id == id) {
+            return Ok(Entity::from_event(event.clone()));
+        }
+        match public_key {
+            Some(public_key) if events.iter().any(|e| e.pubkey == public_key) => {
+                Ok(Entity::Account {
+                    public_key,
+                    metadata: events
+                        .into_iter()
+                        .filter(|e| e.pubkey == public_key && e.kind == Kind::Metadata)
+                        .max_by_key(|e| e.created_at),
+                })
             }
-        } else {
-            Ok(Entity::Channel)
+            _ => Ok(Entity::Unknown),
         }
     }
 
@@ -819,4 +2190,590 @@ impl Client {
             }
         }
     }
+
+    /// Upload file to a NIP-96 file storage server
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/96.md>
+    ///
+    /// Return the download URL and the NIP-94 file metadata tags.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let server = Url::parse("https://nostr.build").unwrap();
+    /// let data: Vec<u8> = std::fs::read("image.png").unwrap();
+    /// let (url, _tags) = client
+    ///     .upload_file(server, data, Some("image/png"))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "nip96")]
+    pub async fn upload_file(
+        &self,
+        server: Url,
+        data: Vec<u8>,
+        mime_type: Option<&str>,
+    ) -> Result<(Url, Vec<Tag>), Error> {
+        use nostr::nips::nip96::{self, ServerConfig};
+
+        let proxy: Option<SocketAddr> = self.opts.proxy;
+        let config = ServerConfig::get(server, proxy).await?;
+        let response = nip96::upload(&self.keys, &config, data, mime_type, proxy).await?;
+        let url: Url = response.download_url().ok_or(Error::DownloadUrlNotFound)?;
+        Ok((url, response.tags()))
+    }
+
+    /// Upload blob to Blossom media servers
+    ///
+    /// The blob is uploaded to the first available server and mirrored to the others
+    /// (falling back to a direct upload if the server doesn't support mirroring).
+    ///
+    /// Return the [`BlobDescriptor`](nostr::blossom::BlobDescriptor) of every server that stored the blob.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let servers = vec![
+    ///     Url::parse("https://cdn.satellite.earth").unwrap(),
+    ///     Url::parse("https://blossom.primal.net").unwrap(),
+    /// ];
+    /// let data: Vec<u8> = std::fs::read("image.png").unwrap();
+    /// let blobs = client.blossom_upload(servers, data).await.unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "blossom")]
+    pub async fn blossom_upload(
+        &self,
+        servers: Vec<Url>,
+        data: Vec<u8>,
+    ) -> Result<Vec<nostr::blossom::BlobDescriptor>, Error> {
+        use nostr::blossom::{BlobDescriptor, BlossomClient};
+
+        let mut blobs: Vec<BlobDescriptor> = Vec::new();
+        let mut last_error: Option<Error> = None;
+
+        for server in servers.into_iter() {
+            let blossom = BlossomClient::new(server.clone(), self.opts.proxy)?;
+
+            // Mirror from the first server that stored the blob
+            if let Some(blob) = blobs.first() {
+                match blossom.mirror(&self.keys, blob).await {
+                    Ok(blob) => {
+                        blobs.push(blob);
+                        continue;
+                    }
+                    Err(e) => log::warn!("Impossible to mirror blob to {}: {}", server, e),
+                }
+            }
+
+            match blossom.upload(&self.keys, data.clone(), None).await {
+                Ok(blob) => blobs.push(blob),
+                Err(e) => {
+                    log::error!("Impossible to upload blob to {}: {}", server, e);
+                    last_error = Some(e.into());
+                }
+            }
+        }
+
+        if blobs.is_empty() {
+            Err(last_error.unwrap_or(Error::NoBlossomServer))
+        } else {
+            Ok(blobs)
+        }
+    }
+
+    /// Verify NIP-05 identifier of public key
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+    ///
+    /// The lookup goes through the proxy or the DNS-over-HTTPS resolver set in [`Options`].
+    #[cfg(feature = "nip05")]
+    pub async fn verify_nip05(&self, public_key: XOnlyPublicKey, nip05: &str) -> Result<(), Error> {
+        Ok(nostr::nips::nip05::verify_with_client(self.http_client()?, public_key, nip05).await?)
+    }
+
+    /// Follow the user of NIP05 identifier (ex. `name@domain.com`)
+    ///
+    /// The identifier is resolved like in [`Client::verify_nip05`] and the user is added to the contact list,
+    /// with the first relay of the NIP05 profile as relay hint and the identifier as petname. An existing
+    /// contact with the same public key is replaced.
+    ///
+    /// If `relay_set` is set, the write relays of the user (NIP-65 relay list, or the NIP05 relays if no
+    /// relay list is found) are added to the relay set, created if missing.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .follow_nip05("bob@example.com", Some("discovery"))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "nip05")]
+    pub async fn follow_nip05(
+        &self,
+        nip05: &str,
+        relay_set: Option<&str>,
+    ) -> Result<EventId, Error> {
+        let profile =
+            nostr::nips::nip05::get_profile_with_client(self.http_client()?, nip05).await?;
+        let public_key: XOnlyPublicKey = profile.public_key;
+
+        if let Some(name) = relay_set {
+            let filter = SubscriptionFilter::new()
+                .author(public_key)
+                .kind(Kind::RelayList)
+                .limit(1);
+            let relay_list: Option<Event> = self
+                .get_events_of(vec![filter])
+                .await?
+                .into_iter()
+                .max_by_key(|e| e.created_at);
+            let write_relays: Vec<Url> = match relay_list {
+                Some(event) => write_relays(&event),
+                None => profile.relays.clone(),
+            };
+            let (mut urls, strategy) = match self.relay_set(name).await {
+                Some(set) => (set.urls, set.strategy),
+                None => (Vec::new(), ConnectionStrategy::Persistent),
+            };
+            urls.extend(write_relays);
+            let urls: Vec<String> = urls.iter().map(relay_url::to_string).collect();
+            self.create_relay_set_with_strategy(name, urls, strategy)
+                .await?;
+        }
+
+        let relay_hint: Option<String> = profile.relays.first().map(relay_url::to_string);
+        let mut contacts: Vec<Contact> = self.get_contact_list().await?;
+        contacts.retain(|c| c.pk != public_key);
+        contacts.push(Contact::new(
+            public_key,
+            relay_hint,
+            Some(nip05.to_string()),
+        ));
+        self.set_contact_list(contacts).await
+    }
+
+    /// Get the community reviews of relay `url` (NIP-32 labels) and aggregate them
+    ///
+    /// Only the latest review of each author counts.
+    #[cfg(feature = "nip32")]
+    pub async fn get_relay_reviews<S>(&self, url: S) -> Result<RelayRating, Error>
+    where
+        S: Into<String>,
+    {
+        let url: Url = relay_url::parse(url.into())?;
+        let mut references: Vec<String> = vec![relay_url::to_string(&url)];
+        if url.as_str() != references[0] {
+            references.push(url.to_string());
+        }
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Label)
+            .references(references);
+        let mut rating = RelayRating::new(url);
+        for event in self.get_events_of(vec![filter]).await?.iter() {
+            rating.ingest(event);
+        }
+        Ok(rating)
+    }
+
+    /// Decode a NIP-19 entity and fetch its data
+    ///
+    /// Profile metadata for `npub` and `nprofile`, the event for `note`, `nevent` and `naddr`.
+    /// Up to 3 relay hints of the entity are temporarily added to the pool for the lookup.
+    #[cfg(feature = "nip19")]
+    pub async fn resolve<S>(&self, entity: S) -> Result<Resolved, Error>
+    where
+        S: AsRef<str>,
+    {
+        let (filter, hints): (SubscriptionFilter, Vec<Url>) =
+            match Nip19::from_bech32(entity.as_ref())? {
+                Nip19::Pubkey(public_key) => (metadata_filter(public_key), Vec::new()),
+                Nip19::Profile(profile) => {
+                    (metadata_filter(profile.public_key), profile.relay_urls())
+                }
+                Nip19::EventId(id) => (SubscriptionFilter::new().id(id), Vec::new()),
+                Nip19::Event(event) => (
+                    SubscriptionFilter::new().id(event.event_id()),
+                    event.relay_urls(),
+                ),
+                Nip19::Coordinate(coordinate) => {
+                    let hints: Vec<Url> = coordinate
+                        .relays
+                        .iter()
+                        .filter_map(|r| r.parse().ok())
+                        .collect();
+                    (
+                        SubscriptionFilter::new().coordinate(coordinate).limit(1),
+                        hints,
+                    )
+                }
+                Nip19::Relay(url) => return Ok(Resolved::Relay(url)),
+                Nip19::Secret(..) => return Err(Error::SecretKeyEntity),
+            };
+
+        let events: Vec<Event> = self.get_events_with_hints(vec![filter], hints).await?;
+        let event: Event = events
+            .into_iter()
+            .max_by_key(|e| e.created_at)
+            .ok_or(Error::EntityNotFound)?;
+        if event.kind == Kind::Metadata {
+            Ok(Resolved::Profile {
+                public_key: event.pubkey,
+                metadata: Box::new(Metadata::from_json(&event.content)?),
+            })
+        } else {
+            Ok(Resolved::Event(Box::new(event)))
+        }
+    }
+
+    /// Request to join group, with an optional invite code
+    ///
+    /// The group relay is added to the pool (and connected) if missing.
+    #[cfg(feature = "nip29")]
+    pub async fn join_group(
+        &self,
+        group: &GroupId,
+        invite_code: Option<&str>,
+    ) -> Result<EventId, Error> {
+        let builder = EventBuilder::group_join_request(&group.id, invite_code, "");
+        self.send_group_event(group, builder).await
+    }
+
+    /// Request to leave group
+    #[cfg(feature = "nip29")]
+    pub async fn leave_group(&self, group: &GroupId) -> Result<EventId, Error> {
+        let builder = EventBuilder::group_leave_request(&group.id, "");
+        self.send_group_event(group, builder).await
+    }
+
+    /// Send chat message to group
+    #[cfg(feature = "nip29")]
+    pub async fn send_group_message<S>(&self, group: &GroupId, content: S) -> Result<EventId, Error>
+    where
+        S: Into<String>,
+    {
+        let builder = EventBuilder::group_chat_message(&group.id, content);
+        self.send_group_event(group, builder).await
+    }
+
+    /// Add user to group, or change its roles (admins only)
+    #[cfg(feature = "nip29")]
+    pub async fn put_group_user(
+        &self,
+        group: &GroupId,
+        public_key: XOnlyPublicKey,
+        roles: &[&str],
+    ) -> Result<EventId, Error> {
+        let builder = EventBuilder::group_put_user(&group.id, public_key, roles);
+        self.send_group_event(group, builder).await
+    }
+
+    /// Remove user from group (admins only)
+    #[cfg(feature = "nip29")]
+    pub async fn remove_group_user(
+        &self,
+        group: &GroupId,
+        public_key: XOnlyPublicKey,
+    ) -> Result<EventId, Error> {
+        let builder = EventBuilder::group_remove_user(&group.id, public_key);
+        self.send_group_event(group, builder).await
+    }
+
+    /// Get group metadata from the group relay
+    #[cfg(feature = "nip29")]
+    pub async fn get_group_metadata(&self, group: &GroupId) -> Result<GroupMetadata, Error> {
+        let event: Event = self.get_group_state(group, Kind::GroupMetadata).await?;
+        Ok(GroupMetadata::from_event(&event)?)
+    }
+
+    /// Get group admins from the group relay
+    #[cfg(feature = "nip29")]
+    pub async fn get_group_admins(&self, group: &GroupId) -> Result<Vec<GroupAdmin>, Error> {
+        let event: Event = self.get_group_state(group, Kind::GroupAdmins).await?;
+        Ok(nip29::group_admins(&event)?)
+    }
+
+    /// Get group members from the group relay
+    #[cfg(feature = "nip29")]
+    pub async fn get_group_members(&self, group: &GroupId) -> Result<Vec<XOnlyPublicKey>, Error> {
+        let event: Event = self.get_group_state(group, Kind::GroupMembers).await?;
+        Ok(nip29::group_members(&event)?)
+    }
+
+    /// Add group relay to the pool and connect it, if missing
+    #[cfg(feature = "nip29")]
+    async fn add_group_relay(&self, group: &GroupId) {
+        if !self.pool.relays().await.contains_key(&group.relay_url) {
+            self.pool
+                .add_relay(group.relay_url.clone(), self.opts.proxy)
+                .await;
+            if let Some(relay) = self.pool.relays().await.get(&group.relay_url) {
+                self.pool.connect_relay(relay, true).await;
+            }
+        }
+    }
+
+    #[cfg(feature = "nip29")]
+    async fn send_group_event(
+        &self,
+        group: &GroupId,
+        builder: EventBuilder,
+    ) -> Result<EventId, Error> {
+        self.add_group_relay(group).await;
+        let event: Event = self.build_event(builder)?;
+        self.send_event_to(group.relay_url.to_string(), event).await
+    }
+
+    /// Get latest relay-generated event of `kind` of group
+    #[cfg(feature = "nip29")]
+    async fn get_group_state(&self, group: &GroupId, kind: Kind) -> Result<Event, Error> {
+        self.add_group_relay(group).await;
+        let filter = SubscriptionFilter::new().kind(kind);
+        self.pool
+            .get_events_from(std::slice::from_ref(&group.relay_url), vec![filter])
+            .await?
+            .into_iter()
+            .filter(|e| nip29::group_id_of(e).as_deref() == Some(group.id.as_str()))
+            .max_by_key(|e| e.created_at)
+            .ok_or(Error::GroupNotFound)
+    }
+
+    /// Get events of filters, adding the relay hints missing from the pool until done
+    #[cfg(feature = "nip19")]
+    async fn get_events_with_hints(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        hints: Vec<Url>,
+    ) -> Result<Vec<Event>, Error> {
+        let relays: HashMap<Url, Relay> = self.pool.relays().await;
+        let mut temporary: Vec<Url> = Vec::new();
+        for url in hints.into_iter().take(resolve::MAX_RELAY_HINTS) {
+            if !relays.contains_key(&url) && !temporary.contains(&url) {
+                self.pool.add_relay(url.clone(), self.opts.proxy).await;
+                temporary.push(url);
+            }
+        }
+        for (url, relay) in self.pool.relays().await.iter() {
+            if temporary.contains(url) {
+                self.pool.connect_relay(relay, true).await;
+            }
+        }
+
+        let res = self.get_events_of(filters).await;
+
+        for url in temporary.into_iter() {
+            self.pool.remove_relay(url).await;
+        }
+
+        res
+    }
+
+    /// Get LNURL pay request of LNURL (`lud06`) or lightning address (`lud16`)
+    ///
+    /// The lookup goes through the proxy or the DNS-over-HTTPS resolver set in [`Options`].
+    pub async fn get_lnurl_pay_request(&self, lud: &str) -> Result<PayRequest, Error> {
+        Ok(lnurl::get_pay_request(self.http_client()?, lud).await?)
+    }
+
+    /// Get HTTP client, never falling back to a client that ignores the proxy options
+    fn http_client(&self) -> Result<&reqwest::Client, Error> {
+        self.http.as_ref().map_err(|e| Error::HttpClient(e.clone()))
+    }
+}
+
+#[cfg(feature = "nip19")]
+fn metadata_filter(public_key: XOnlyPublicKey) -> SubscriptionFilter {
+    SubscriptionFilter::new()
+        .authors(vec![public_key])
+        .kind(Kind::Metadata)
+        .limit(1)
+}
+
+/// Write relays of relay list (NIP-65): `r` tags without marker or with the `write` marker
+#[cfg(feature = "nip05")]
+fn write_relays(event: &Event) -> Vec<Url> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_vec().as_slice() {
+            [kind, url] if kind == "r" => relay_url::parse(url).ok(),
+            [kind, url, marker, ..] if kind == "r" && marker == "write" => {
+                relay_url::parse(url).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn send_action(relay: Url, result: Result<(), String>) -> AuditAction {
+    match result {
+        Ok(()) => AuditAction::Sent { relay },
+        Err(error) => AuditAction::SendFailed { relay, error },
+    }
+}
+
+#[cfg(feature = "nip26")]
+fn moved_to(keys: &Keys) -> String {
+    #[cfg(feature = "nip19")]
+    {
+        use nostr::prelude::ToBech32;
+        if let Ok(npub) = keys.public_key().to_bech32() {
+            return npub;
+        }
+    }
+    keys.public_key().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::trust::{RelayTrust, TrustLevel};
+
+    const DENIED: &str = "wss://denied.example.com";
+    const NORMAL: &str = "wss://normal.example.com";
+
+    fn event_id() -> EventId {
+        EventId::from_slice(&[1; 32]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_event_with_action() {
+        let keys = Keys::generate();
+        let client = Client::builder(&keys)
+            .trust_policy(RelayTrust::new().deny(Url::parse(DENIED).unwrap()))
+            .build();
+
+        // No relays
+        assert!(matches!(
+            client.delete_event(event_id(), None::<String>).await,
+            Err(Error::NoTrustedRelay)
+        ));
+
+        // Only denied relays
+        client.add_relay(DENIED, None).await.unwrap();
+        assert!(matches!(
+            client.delete_event(event_id(), None::<String>).await,
+            Err(Error::NoTrustedRelay)
+        ));
+        #[cfg(feature = "nip04")]
+        assert!(matches!(
+            client.send_direct_msg(keys.public_key(), "hi").await,
+            Err(Error::NoTrustedRelay)
+        ));
+
+        // A normal relay allows deletions and direct messages, even if the relay is not connected
+        client.add_relay(NORMAL, None).await.unwrap();
+        assert!(client
+            .delete_event(event_id(), None::<String>)
+            .await
+            .is_ok());
+        #[cfg(feature = "nip04")]
+        assert!(client
+            .send_direct_msg(keys.public_key(), "hi")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_builder_proxy_and_relay_sets() {
+        let proxy = SocketAddr::from(([127, 0, 0, 1], 9050));
+        let own_proxy = SocketAddr::from(([127, 0, 0, 1], 9051));
+        let set = RelaySet::new(
+            "dm",
+            vec![Url::parse(NORMAL).unwrap(), Url::parse(DENIED).unwrap()],
+            ConnectionStrategy::OnDemand,
+        );
+        let client = Client::builder(&Keys::generate())
+            .proxy(proxy)
+            .relay(Url::parse(NORMAL).unwrap(), Some(own_proxy))
+            .relay_set(RelaySet::new(
+                "dm",
+                Vec::new(),
+                ConnectionStrategy::Persistent,
+            ))
+            .relay_set(set.clone())
+            .build();
+
+        assert_eq!(client.opts.proxy, Some(proxy));
+        assert_eq!(client.relay_sets().await, vec![set]);
+
+        // Relays of the sets are in the pool, with the proxy of the options if not already added
+        let relays = client.relays().await;
+        assert_eq!(relays.len(), 2);
+        assert_eq!(
+            relays[&Url::parse(NORMAL).unwrap()].proxy(),
+            Some(own_proxy)
+        );
+        assert_eq!(relays[&Url::parse(DENIED).unwrap()].proxy(), Some(proxy));
+    }
+
+    #[tokio::test]
+    async fn test_signer() {
+        let keys = Keys::generate();
+        let public_only = Keys::from_public_key(keys.public_key());
+        let builder = || EventBuilder::new_text_note("GM", &[]);
+
+        // Without signer, the secret key is needed
+        let client = Client::new(&public_only);
+        assert!(client.build_event(builder()).is_err());
+
+        let client = Client::builder(&public_only).signer(keys.clone()).build();
+        let event: Event = client.build_event(builder()).unwrap();
+        assert_eq!(event.pubkey, keys.public_key());
+        event.verify().unwrap();
+
+        let event: Event = client.sign(builder(), 8).unwrap();
+        assert!(nostr::nips::nip13::get_leading_zero_bits(event.id.inner()) >= 8);
+        event.verify().unwrap();
+
+        // Signer of other keys
+        let client = Client::builder(&public_only)
+            .signer(Keys::generate())
+            .build();
+        assert!(matches!(
+            client.build_event(builder()),
+            Err(Error::Signer(nostr::signer::Error::PublicKeyMismatch))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_event_with_action_untrusted_by_default() {
+        let keys = Keys::generate();
+        let client = Client::builder(&keys)
+            .trust_policy(
+                RelayTrust::new()
+                    .default_level(TrustLevel::Untrusted)
+                    .level(Url::parse(NORMAL).unwrap(), TrustLevel::Normal),
+            )
+            .build();
+        client.add_relay(DENIED, None).await.unwrap();
+        assert!(matches!(
+            client.delete_event(event_id(), None::<String>).await,
+            Err(Error::NoTrustedRelay)
+        ));
+
+        client.add_relay(NORMAL, None).await.unwrap();
+        assert!(client
+            .delete_event(event_id(), None::<String>)
+            .await
+            .is_ok());
+    }
 }
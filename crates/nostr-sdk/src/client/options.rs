@@ -1,8 +1,51 @@
 // Copyright (c) 2022-2023 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use nostr::{ImageUrlPolicy, SubscriptionIdGenerator, Url};
+
+use crate::middleware::OutgoingHook;
+use crate::policy::{AcceptAll, EventPolicy, ImageProxy};
+use crate::relay::cache::QueryCacheOptions;
+use crate::relay::queue::MessagePriority;
+use crate::relay::stats::Timeouts;
+use crate::relay::trust::{RelayTrust, TrustPolicy};
+
+/// Client identification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAgent {
+    /// Client name
+    pub name: String,
+    /// Client version
+    pub version: Option<String>,
+}
+
+impl UserAgent {
+    /// New [`UserAgent`]
+    pub fn new<S>(name: S, version: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            version: version.map(|v| v.into()),
+        }
+    }
+}
+
+impl fmt::Display for UserAgent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}/{}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
 
 /// Options
 #[derive(Debug, Clone)]
@@ -13,6 +56,42 @@ pub struct Options {
     pub wait_for_send: Arc<AtomicBool>,
     /// POW difficulty (for all events)
     pub difficulty: Arc<AtomicU8>,
+    /// Relay trust policy
+    pub trust_policy: Arc<RwLock<Arc<dyn TrustPolicy>>>,
+    /// Event policy
+    pub event_policy: Arc<RwLock<Arc<dyn EventPolicy>>>,
+    /// Relay operations timeouts
+    pub timeouts: Timeouts,
+    /// Proxy for relays added without one and for HTTP lookups (applied when the client is built)
+    pub proxy: Option<SocketAddr>,
+    /// DNS-over-HTTPS endpoint for HTTP lookups (applied when the client is built)
+    pub dns_over_https: Option<Url>,
+    /// Client identification sent in the WebSocket handshake (applied when the client is built)
+    pub user_agent: Option<UserAgent>,
+    /// Add `client` tag to published events
+    pub client_tag: Arc<AtomicBool>,
+    /// Record signed and published events in the store audit log
+    pub audit_log: Arc<AtomicBool>,
+    /// Priority of `REQ` messages in the relay outbound queue (applied when the client is built)
+    pub req_priority: MessagePriority,
+    /// Relay query cache (applied when the client is built)
+    pub query_cache: Option<QueryCacheOptions>,
+    /// Max follow-up `REQ`s per filter truncated by its `limit` (applied when the client is built)
+    pub auto_narrowing: Option<usize>,
+    /// Validation of the picture and banner urls of metadata
+    pub image_url_policy: Option<ImageUrlPolicy>,
+    /// Rewrite of the picture and banner urls of metadata
+    pub image_proxy: Option<Arc<dyn ImageProxy>>,
+    /// Generation of subscription ids
+    pub subscription_ids: SubscriptionIdGenerator,
+    /// Max random delay between relays when publishing and connecting, in random order
+    pub jitter: Option<Duration>,
+    /// Refresh interval of the relay information documents (NIP-11)
+    pub document_refresh: Option<Duration>,
+    /// Honor the proxy environment variables
+    pub system_proxy: bool,
+    /// Hooks run on events built by the client, before signing
+    pub outgoing_hooks: Vec<Arc<dyn OutgoingHook>>,
 }
 
 impl Default for Options {
@@ -21,6 +100,24 @@ impl Default for Options {
             wait_for_connection: Arc::new(AtomicBool::new(false)),
             wait_for_send: Arc::new(AtomicBool::new(false)),
             difficulty: Arc::new(AtomicU8::new(0)),
+            trust_policy: Arc::new(RwLock::new(Arc::new(RelayTrust::default()))),
+            event_policy: Arc::new(RwLock::new(Arc::new(AcceptAll))),
+            timeouts: Timeouts::default(),
+            proxy: None,
+            dns_over_https: None,
+            user_agent: None,
+            client_tag: Arc::new(AtomicBool::new(false)),
+            audit_log: Arc::new(AtomicBool::new(false)),
+            req_priority: MessagePriority::Low,
+            query_cache: None,
+            auto_narrowing: None,
+            image_url_policy: None,
+            image_proxy: None,
+            subscription_ids: SubscriptionIdGenerator::default(),
+            jitter: None,
+            document_refresh: None,
+            system_proxy: true,
+            outgoing_hooks: Vec::new(),
         }
     }
 }
@@ -73,7 +170,266 @@ impl Options {
             .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(difficulty));
     }
 
+    /// Set relay [`TrustPolicy`] (default: [`RelayTrust`] with all relays at `TrustLevel::Normal`)
+    pub fn trust_policy<T>(self, policy: T) -> Self
+    where
+        T: TrustPolicy + 'static,
+    {
+        Self {
+            trust_policy: Arc::new(RwLock::new(Arc::new(policy))),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_trust_policy(&self) -> Arc<dyn TrustPolicy> {
+        match self.trust_policy.read() {
+            Ok(policy) => policy.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub(crate) fn update_trust_policy(&self, policy: Arc<dyn TrustPolicy>) {
+        match self.trust_policy.write() {
+            Ok(mut p) => *p = policy,
+            Err(e) => *e.into_inner() = policy,
+        }
+    }
+
+    /// Set [`EventPolicy`] (default: [`AcceptAll`])
+    pub fn event_policy<T>(self, policy: T) -> Self
+    where
+        T: EventPolicy + 'static,
+    {
+        Self {
+            event_policy: Arc::new(RwLock::new(Arc::new(policy))),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_event_policy(&self) -> Arc<dyn EventPolicy> {
+        match self.event_policy.read() {
+            Ok(policy) => policy.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    pub(crate) fn update_event_policy(&self, policy: Arc<dyn EventPolicy>) {
+        match self.event_policy.write() {
+            Ok(mut p) => *p = policy,
+            Err(e) => *e.into_inner() = policy,
+        }
+    }
+
+    /// Adapt timeouts of relay operations (`EOSE` and `OK` wait) to the measured latency of each relay (default: `true`)
+    ///
+    /// If set to `false`, the fixed timeout is used for all relays.
+    pub fn adaptive_timeouts(self, adaptive: bool) -> Self {
+        Self {
+            timeouts: Timeouts::new(adaptive, self.timeouts.timeout())
+                .stale_subscription(self.timeouts.stale_subscription_timeout()),
+            ..self
+        }
+    }
+
+    /// Set fixed timeout of relay operations, upper bound of adaptive timeouts (default: 10 secs)
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeouts: Timeouts::new(self.timeouts.is_adaptive(), timeout)
+                .stale_subscription(self.timeouts.stale_subscription_timeout()),
+            ..self
+        }
+    }
+
+    /// Re-issue the subscription `REQ` of relays silent for `timeout` while connected (default: disabled)
+    ///
+    /// A relay may silently drop a subscription (ex. after a restart or a `CLOSE` not sent to the client).
+    /// When no event or `EOSE` is received for the subscription within `timeout`, the `REQ` is sent again
+    /// and [`RelayPoolNotification::StaleSubscription`](crate::RelayPoolNotification::StaleSubscription)
+    /// is emitted. Set `timeout` above the expected silence of the subscribed filters.
+    pub fn stale_subscription_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeouts: Timeouts::new(self.timeouts.is_adaptive(), self.timeouts.timeout())
+                .stale_subscription(Some(timeout)),
+            ..self
+        }
+    }
+
+    /// Set proxy (ex. Tor `127.0.0.1:9050`)
+    ///
+    /// Used for NIP-05 and LNURL lookups and for relays added without a proxy.
+    pub fn proxy(self, proxy: SocketAddr) -> Self {
+        Self {
+            proxy: Some(proxy),
+            ..self
+        }
+    }
+
+    /// Resolve names of NIP-05 and LNURL lookups with DNS-over-HTTPS (ex. `https://1.1.1.1/dns-query`)
+    ///
+    /// Ignored if a proxy is set, since names are resolved by the proxy.
+    pub fn dns_over_https(self, endpoint: Url) -> Self {
+        Self {
+            dns_over_https: Some(endpoint),
+            ..self
+        }
+    }
+
+    /// Set client identification
+    ///
+    /// Sent as `User-Agent` header in the WebSocket handshake with relays (ex. `my-client/1.0.0`).
+    pub fn user_agent(self, user_agent: UserAgent) -> Self {
+        Self {
+            user_agent: Some(user_agent),
+            ..self
+        }
+    }
+
+    /// If set to `true`, add a `client` tag with the [`UserAgent`] name to published events (default: `false`)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    ///
+    /// Ignored if [`UserAgent`] is not set.
+    pub fn client_tag(self, enable: bool) -> Self {
+        Self {
+            client_tag: Arc::new(AtomicBool::new(enable)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_client_tag(&self) -> bool {
+        self.client_tag.load(Ordering::SeqCst)
+    }
+
+    /// If set to `true`, record every signed event and every publish attempt, with the `OK` of each relay,
+    /// in the audit log of the store (default: `false`)
+    ///
+    /// Ignored if the client has no store. See `Client::audit_log`.
+    pub fn audit_log(self, enable: bool) -> Self {
+        Self {
+            audit_log: Arc::new(AtomicBool::new(enable)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_audit_log(&self) -> bool {
+        self.audit_log.load(Ordering::SeqCst)
+    }
+
+    /// Set priority of `REQ` messages in the relay outbound queue (default: [`MessagePriority::Low`])
+    ///
+    /// `AUTH` and `CLOSE` are always sent first, `EVENT` with [`MessagePriority::Normal`].
+    pub fn req_priority(self, priority: MessagePriority) -> Self {
+        Self {
+            req_priority: priority,
+            ..self
+        }
+    }
+
+    /// Cache the complete results of `REQ`s sent with `get_events_of` for `ttl`, up to `capacity` queries per relay
+    ///
+    /// Repeated identical queries are served locally until expired or until a new event matching
+    /// their filters is received from or sent to the relay.
+    pub fn query_cache(self, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            query_cache: Some(QueryCacheOptions::new(ttl, capacity)),
+            ..self
+        }
+    }
+
+    /// Complete the results of `get_events_of` truncated by the `limit` of a filter (default: disabled)
+    ///
+    /// When a relay returns exactly `limit` events for a filter with `since`, the filter is queried
+    /// again with a tightened `until`, up to `max_requests` times, until the time range is covered.
+    /// The additional events are merged in the result. Filters without `since` are never narrowed.
+    pub fn auto_narrowing(self, max_requests: usize) -> Self {
+        Self {
+            auto_narrowing: Some(max_requests),
+            ..self
+        }
+    }
+
+    /// Validate picture and banner urls of metadata with `policy` (default: disabled)
+    ///
+    /// See `Client::sanitize_metadata`.
+    pub fn image_url_policy(self, policy: ImageUrlPolicy) -> Self {
+        Self {
+            image_url_policy: Some(policy),
+            ..self
+        }
+    }
+
+    /// Rewrite picture and banner urls of metadata with `proxy` (default: disabled)
+    ///
+    /// `data:` URIs are not rewritten. See `Client::sanitize_metadata`.
+    pub fn image_proxy<T>(self, proxy: T) -> Self
+    where
+        T: ImageProxy + 'static,
+    {
+        Self {
+            image_proxy: Some(Arc::new(proxy)),
+            ..self
+        }
+    }
+
+    /// Add [`OutgoingHook`], run after the ones already added
+    pub fn outgoing_hook<T>(mut self, hook: T) -> Self
+    where
+        T: OutgoingHook + 'static,
+    {
+        self.outgoing_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Set how subscription ids are generated (default: [`SubscriptionIdGenerator::Uuid`])
+    ///
+    /// Use [`SubscriptionIdGenerator::Random`] or [`SubscriptionIdGenerator::Prefix`] for relays limiting
+    /// the id length, [`SubscriptionIdGenerator::Deterministic`] for idempotent re-subscriptions.
+    pub fn subscription_ids(self, generator: SubscriptionIdGenerator) -> Self {
+        Self {
+            subscription_ids: generator,
+            ..self
+        }
+    }
+
+    /// Send events to relays and connect to them in random order, waiting a random delay
+    /// up to `max` between relays (default: disabled)
+    ///
+    /// Makes harder for colluding relays to fingerprint the client by its precise publish pattern,
+    /// at the cost of slower publishing (up to `max` per relay).
+    pub fn jitter(self, max: Duration) -> Self {
+        Self {
+            jitter: Some(max),
+            ..self
+        }
+    }
+
+    /// Refresh the relay information documents (NIP-11) every `interval` (default: disabled)
+    ///
+    /// Listen for [`RelayPoolNotification::RelayDocumentChanged`](crate::RelayPoolNotification::RelayDocumentChanged)
+    /// to adapt to new limitations (ex. auth newly required) without restart.
+    pub fn document_refresh(self, interval: Duration) -> Self {
+        Self {
+            document_refresh: Some(interval),
+            ..self
+        }
+    }
+
+    /// Honor the proxy environment variables (`HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY`, `NO_PROXY`) when no
+    /// proxy is set (default: `true`)
+    ///
+    /// Used for relays (`http`, `socks5` and `socks5h` proxies) and HTTP requests (NIP-05, NIP-11, LNURL).
+    /// Relays added with their own proxy always use it.
+    pub fn system_proxy(self, enabled: bool) -> Self {
+        Self {
+            system_proxy: enabled,
+            ..self
+        }
+    }
+
     /// Update [`Options`]
+    ///
+    /// Proxy, DNS-over-HTTPS, user agent, `REQ` priority, query cache, auto-narrowing and image options are not updated,
+    /// nor the system proxy of HTTP requests.
     pub fn update_opts(&self, new_opts: Options) {
         let _ = self
             .wait_for_connection
@@ -86,5 +442,18 @@ impl Options {
                 Some(new_opts.get_wait_for_send())
             });
         self.update_difficulty(new_opts.get_difficulty());
+        let _ = self
+            .client_tag
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(new_opts.get_client_tag())
+            });
+        let _ = self
+            .audit_log
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(new_opts.get_audit_log())
+            });
+        self.update_trust_policy(new_opts.get_trust_policy());
+        self.update_event_policy(new_opts.get_event_policy());
+        self.timeouts.update(&new_opts.timeouts);
     }
 }
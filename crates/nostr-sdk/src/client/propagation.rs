@@ -0,0 +1,33 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Event propagation
+
+use nostr::Url;
+
+/// Event propagation report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropagationReport {
+    /// Relays having the event
+    pub found: Vec<Url>,
+    /// Relays answering without the event
+    pub missing: Vec<Url>,
+    /// Relays that couldn't be queried, with the error
+    pub failed: Vec<(Url, String)>,
+}
+
+impl PropagationReport {
+    /// Check if all the checked relays have the event
+    pub fn is_fully_propagated(&self) -> bool {
+        self.missing.is_empty() && self.failed.is_empty()
+    }
+
+    /// Share of the checked relays having the event, from `0.0` to `1.0`
+    pub fn coverage(&self) -> f64 {
+        let total: usize = self.found.len() + self.missing.len() + self.failed.len();
+        if total == 0 {
+            return 0.0;
+        }
+        self.found.len() as f64 / total as f64
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay sets
+
+use nostr::Url;
+
+/// Connection strategy of a [`RelaySet`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConnectionStrategy {
+    /// Connect the relays when the set is created and keep the connections alive
+    #[default]
+    Persistent,
+    /// Connect the relays the first time the set is used (publish or query), waiting for the connections
+    OnDemand,
+}
+
+/// Named group of relays (ex. "dm", "search" or "nwc" relays)
+///
+/// Relays of a set are added to the pool of the client and can be shared between sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelaySet {
+    /// Name
+    pub name: String,
+    /// Relays
+    pub urls: Vec<Url>,
+    /// Connection strategy
+    pub strategy: ConnectionStrategy,
+}
+
+impl RelaySet {
+    /// New [`RelaySet`]
+    pub fn new<S>(name: S, urls: Vec<Url>, strategy: ConnectionStrategy) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut set = Self {
+            name: name.into(),
+            urls: Vec::new(),
+            strategy,
+        };
+        for url in urls.into_iter() {
+            if !set.urls.contains(&url) {
+                set.urls.push(url);
+            }
+        }
+        set
+    }
+
+    /// Check if the set contains `url`
+    pub fn contains(&self, url: &Url) -> bool {
+        self.urls.contains(url)
+    }
+}
@@ -0,0 +1,27 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP-19 resolution
+
+use nostr::key::XOnlyPublicKey;
+use nostr::url::Url;
+use nostr::{Event, Metadata};
+
+/// Max number of relay hints connected to resolve an entity
+pub(crate) const MAX_RELAY_HINTS: usize = 3;
+
+/// Data of a NIP-19 entity, fetched by [`Client::resolve`](super::Client::resolve)
+#[derive(Debug, Clone)]
+pub enum Resolved {
+    /// Profile metadata (`npub` and `nprofile`)
+    Profile {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// Latest metadata
+        metadata: Box<Metadata>,
+    },
+    /// Event (`note`, `nevent` and `naddr`)
+    Event(Box<Event>),
+    /// Relay (`nrelay`), nothing to fetch
+    Relay(Url),
+}
@@ -0,0 +1,315 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Crawler
+//!
+//! Walk relays with paged filters and reduce events as they are received, without keeping them in memory.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::url::Url;
+use nostr::{Event, EventId, Kind, SubscriptionFilter, Timestamp};
+
+use crate::client::Client;
+use crate::relay::Relay;
+
+const SECS_PER_DAY: u64 = 86400;
+
+/// Event reducer
+///
+/// Called for every event found by the [`Crawler`]. Events already seen (on the same or on another relay) are skipped.
+pub trait Reducer {
+    /// Reduce event
+    fn reduce(&mut self, relay_url: &Url, event: &Event);
+}
+
+/// Crawl summary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrawlSummary {
+    /// Number of requested pages
+    pub pages: usize,
+    /// Number of events passed to the reducer
+    pub events: u64,
+    /// Number of duplicated events skipped
+    pub duplicates: u64,
+    /// Relays that failed (timeout, disconnected, ...)
+    pub failed_relays: Vec<Url>,
+}
+
+/// Crawler
+///
+/// Each relay is walked backward in time: every page is requested with `until` set to the oldest event of the previous page.
+#[derive(Debug, Clone)]
+pub struct Crawler {
+    client: Client,
+    filter: SubscriptionFilter,
+    page_size: usize,
+    max_pages: Option<usize>,
+    timeout: Duration,
+    dedup_capacity: usize,
+}
+
+impl Crawler {
+    /// New [`Crawler`]
+    ///
+    /// The crawler walks the relays of the [`Client`]. `limit` and `until` of `filter` are overwritten at every page.
+    pub fn new(client: &Client, filter: SubscriptionFilter) -> Self {
+        Self {
+            client: client.clone(),
+            filter,
+            page_size: 500,
+            max_pages: None,
+            timeout: Duration::from_secs(30),
+            dedup_capacity: 100_000,
+        }
+    }
+
+    /// Set max number of events per page (default: 500)
+    pub fn page_size(self, page_size: usize) -> Self {
+        Self {
+            page_size: page_size.max(1),
+            ..self
+        }
+    }
+
+    /// Set max number of pages per relay (default: unlimited)
+    pub fn max_pages(self, max_pages: usize) -> Self {
+        Self {
+            max_pages: Some(max_pages),
+            ..self
+        }
+    }
+
+    /// Set page timeout (default: 30 secs)
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Set number of recent [`EventId`] kept to skip duplicates (default: 100000)
+    ///
+    /// Duplicates older than the last `capacity` events may be reduced again.
+    pub fn dedup_capacity(self, capacity: usize) -> Self {
+        Self {
+            dedup_capacity: capacity,
+            ..self
+        }
+    }
+
+    /// Crawl relays and pass every event to `reducer`
+    ///
+    /// Relays that fail are skipped and reported in [`CrawlSummary`].
+    pub async fn run<R>(&self, reducer: &mut R) -> CrawlSummary
+    where
+        R: Reducer,
+    {
+        let mut summary = CrawlSummary::default();
+        let mut seen = RecentIds::new(self.dedup_capacity);
+        let relays: HashMap<Url, Relay> = self.client.relays().await;
+        for (url, relay) in relays.into_iter() {
+            if let Err(e) = self
+                .crawl_relay(&url, &relay, reducer, &mut seen, &mut summary)
+                .await
+            {
+                log::error!("Impossible to crawl {}: {}", url, e);
+                summary.failed_relays.push(url);
+            }
+        }
+        summary
+    }
+
+    async fn crawl_relay<R>(
+        &self,
+        url: &Url,
+        relay: &Relay,
+        reducer: &mut R,
+        seen: &mut RecentIds,
+        summary: &mut CrawlSummary,
+    ) -> Result<(), crate::relay::Error>
+    where
+        R: Reducer,
+    {
+        let mut until: Option<Timestamp> = self.filter.until;
+        let mut pages: usize = 0;
+        while self.max_pages.map_or(true, |max| pages < max) {
+            let mut filter = self.filter.clone().limit(self.page_size);
+            if let Some(until) = until {
+                filter = filter.until(until);
+            }
+            let events: Vec<Event> = relay.get_events_of(vec![filter], self.timeout).await?;
+            pages += 1;
+            summary.pages += 1;
+
+            let oldest: Timestamp = match events.iter().map(|e| e.created_at).min() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+
+            let mut new_events: usize = 0;
+            for event in events.iter() {
+                if seen.insert(event.id) {
+                    reducer.reduce(url, event);
+                    summary.events += 1;
+                    new_events += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+
+            // Keep `until` inclusive to not miss events with the same timestamp,
+            // move past it when the page didn't contain anything new.
+            let next = if new_events == 0 {
+                if oldest.as_u64() == 0 {
+                    break;
+                }
+                oldest - Duration::from_secs(1)
+            } else {
+                oldest
+            };
+            if until == Some(next) {
+                break;
+            }
+            until = Some(next);
+        }
+        Ok(())
+    }
+
+    /// Crawl relays and compute [`CrawlStats`]
+    ///
+    /// `max_authors` is the number of authors tracked (see [`CrawlStats::new`]).
+    pub async fn stats(&self, max_authors: usize) -> (CrawlStats, CrawlSummary) {
+        let mut stats = CrawlStats::new(max_authors);
+        let summary = self.run(&mut stats).await;
+        (stats, summary)
+    }
+}
+
+/// Bounded set of the most recent [`EventId`]
+struct RecentIds {
+    capacity: usize,
+    ids: HashSet<EventId>,
+    order: VecDeque<EventId>,
+}
+
+impl RecentIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Return `false` if the id was already present
+    fn insert(&mut self, id: EventId) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        while self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        true
+    }
+}
+
+/// Aggregate statistics
+///
+/// Events per kind and per day are counted exactly. Events per author are counted with the
+/// space-saving algorithm: at most `max_authors` authors are tracked and counts of the most
+/// active ones are overestimated by at most [`CrawlStats::author_error`].
+#[derive(Debug, Clone, Default)]
+pub struct CrawlStats {
+    total: u64,
+    kinds: BTreeMap<Kind, u64>,
+    days: BTreeMap<u64, u64>,
+    max_authors: usize,
+    authors: HashMap<XOnlyPublicKey, (u64, u64)>,
+}
+
+impl CrawlStats {
+    /// New [`CrawlStats`] tracking at most `max_authors` authors
+    pub fn new(max_authors: usize) -> Self {
+        Self {
+            max_authors,
+            ..Default::default()
+        }
+    }
+
+    /// Total number of events
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Number of events per kind
+    pub fn kinds(&self) -> &BTreeMap<Kind, u64> {
+        &self.kinds
+    }
+
+    /// Number of events per day
+    ///
+    /// Days are counted since UNIX epoch (UTC), according to `created_at`.
+    pub fn days(&self) -> &BTreeMap<u64, u64> {
+        &self.days
+    }
+
+    /// Most active authors with their (estimated) number of events, sorted by count
+    pub fn top_authors(&self, n: usize) -> Vec<(XOnlyPublicKey, u64)> {
+        let mut authors: Vec<(XOnlyPublicKey, u64)> = self
+            .authors
+            .iter()
+            .map(|(pk, (count, _))| (*pk, *count))
+            .collect();
+        authors.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        authors.truncate(n);
+        authors
+    }
+
+    /// Max overestimation of the events count of `public_key`
+    ///
+    /// Return `None` if the author is not tracked.
+    pub fn author_error(&self, public_key: &XOnlyPublicKey) -> Option<u64> {
+        self.authors.get(public_key).map(|(_, error)| *error)
+    }
+
+    fn count_author(&mut self, public_key: XOnlyPublicKey) {
+        if let Some((count, _)) = self.authors.get_mut(&public_key) {
+            *count += 1;
+            return;
+        }
+
+        if self.authors.len() < self.max_authors {
+            self.authors.insert(public_key, (1, 0));
+            return;
+        }
+
+        // Replace the least active author
+        let min = self
+            .authors
+            .iter()
+            .min_by_key(|(_, (count, _))| *count)
+            .map(|(pk, (count, _))| (*pk, *count));
+        if let Some((pk, min)) = min {
+            self.authors.remove(&pk);
+            self.authors.insert(public_key, (min + 1, min));
+        }
+    }
+}
+
+impl Reducer for CrawlStats {
+    fn reduce(&mut self, _relay_url: &Url, event: &Event) {
+        self.total += 1;
+        *self.kinds.entry(event.kind).or_default() += 1;
+        *self
+            .days
+            .entry(event.created_at.as_u64() / SECS_PER_DAY)
+            .or_default() += 1;
+        self.count_author(event.pubkey);
+    }
+}
@@ -0,0 +1,429 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay discovery
+//!
+//! Discover relays from the relay lists of the users (NIP-65, kind 10002) and from the
+//! announcements of relay monitors (NIP-66, kind 30166), starting from a set of bootstrap relays.
+//! With the `nip32` feature, the community reviews of the relays (kind 1985) are part of the score.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use nostr::key::XOnlyPublicKey;
+#[cfg(feature = "nip32")]
+use nostr::nips::nip32::{RelayRating, RelayReview};
+use nostr::types::relay_url;
+use nostr::url::Url;
+use nostr::{Event, Kind, SubscriptionFilter, Timestamp};
+
+use crate::client::{Client, Error};
+
+/// Default bootstrap relays
+pub const DEFAULT_BOOTSTRAP_RELAYS: [&str; 4] = [
+    "wss://relay.damus.io",
+    "wss://nos.lol",
+    "wss://relay.nostr.band",
+    "wss://purplepag.es",
+];
+
+/// Number of geohash characters identifying a region (~1250 km x 625 km)
+const REGION_PRECISION: usize = 2;
+
+/// Round trip time above which a relay gets no latency bonus
+const MAX_RTT: Duration = Duration::from_secs(2);
+
+/// Number of reviewers giving half weight to the rating
+const REVIEWS_CONFIDENCE: f64 = 3.0;
+
+/// Network of a relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Network {
+    /// Clearnet
+    Clearnet,
+    /// Tor
+    Tor,
+    /// I2P
+    I2p,
+    /// Other network (as announced by the monitors)
+    Other(String),
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clearnet => write!(f, "clearnet"),
+            Self::Tor => write!(f, "tor"),
+            Self::I2p => write!(f, "i2p"),
+            Self::Other(network) => write!(f, "{network}"),
+        }
+    }
+}
+
+impl Network {
+    fn from_url(url: &Url) -> Self {
+        match url.host_str() {
+            Some(host) if host.ends_with(".onion") => Self::Tor,
+            Some(host) if host.ends_with(".i2p") => Self::I2p,
+            _ => Self::Clearnet,
+        }
+    }
+
+    fn from_tag(network: &str) -> Self {
+        match network {
+            "clearnet" => Self::Clearnet,
+            "tor" => Self::Tor,
+            "i2p" => Self::I2p,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+/// Discovered relay
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayCandidate {
+    /// Url
+    pub url: Url,
+    /// Number of users listing the relay in their relay list
+    pub mentions: usize,
+    /// Number of monitors announcing the relay
+    pub monitors: usize,
+    /// Lowest round trip time to open a connection, announced by the monitors
+    pub rtt: Option<Duration>,
+    /// Network
+    pub network: Network,
+    /// Geohash announced by the monitors
+    pub geohash: Option<String>,
+    /// Timestamp of the latest monitor announcement
+    pub last_seen: Option<Timestamp>,
+    /// Number of reviewers (NIP-32)
+    pub reviews: usize,
+    /// Average review quality, from 0 to 1
+    pub rating: Option<f64>,
+}
+
+impl RelayCandidate {
+    fn new(url: Url) -> Self {
+        Self {
+            network: Network::from_url(&url),
+            url,
+            mentions: 0,
+            monitors: 0,
+            rtt: None,
+            geohash: None,
+            last_seen: None,
+            reviews: 0,
+            rating: None,
+        }
+    }
+
+    /// Score of the relay (higher is better)
+    ///
+    /// Popularity grows logarithmically with the number of mentions, every monitor adds 1,
+    /// a fast relay gets up to 1 more and the reviews add up to 1 (best rating) or remove up
+    /// to 1 (worst rating), weighted by the number of reviewers.
+    pub fn score(&self) -> f64 {
+        let popularity: f64 = (self.mentions as f64).ln_1p();
+        let latency: f64 = self.rtt.map_or(0.0, |rtt| {
+            1.0 - rtt.as_secs_f64().min(MAX_RTT.as_secs_f64()) / MAX_RTT.as_secs_f64()
+        });
+        let reviews: f64 = self.rating.map_or(0.0, |rating| {
+            let confidence: f64 = self.reviews as f64 / (self.reviews as f64 + REVIEWS_CONFIDENCE);
+            (rating - 0.5) * 2.0 * confidence
+        });
+        popularity + self.monitors as f64 + latency + reviews
+    }
+
+    /// Geographic region of the relay (geohash prefix)
+    pub fn region(&self) -> Option<&str> {
+        self.geohash
+            .as_deref()
+            .and_then(|geohash| geohash.get(..REGION_PRECISION))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Sources {
+    authors: HashSet<XOnlyPublicKey>,
+    monitors: HashSet<XOnlyPublicKey>,
+}
+
+/// Relay discovery
+///
+/// Relays are suggested by [`RelayCandidate::score`], within the constraints on the total number of
+/// relays and on the number of relays per network and per region.
+#[derive(Debug)]
+pub struct RelayDiscovery {
+    client: Client,
+    bootstrap_relays: Vec<Url>,
+    limit: usize,
+    max_relays: usize,
+    max_per_network: Option<usize>,
+    max_per_region: Option<usize>,
+    candidates: HashMap<Url, RelayCandidate>,
+    sources: HashMap<Url, Sources>,
+    #[cfg(feature = "nip32")]
+    ratings: HashMap<Url, RelayRating>,
+}
+
+impl RelayDiscovery {
+    /// New [`RelayDiscovery`] with the [`DEFAULT_BOOTSTRAP_RELAYS`]
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            bootstrap_relays: DEFAULT_BOOTSTRAP_RELAYS
+                .iter()
+                .filter_map(|url| relay_url::parse(url).ok())
+                .collect(),
+            limit: 500,
+            max_relays: 10,
+            max_per_network: None,
+            max_per_region: None,
+            candidates: HashMap::new(),
+            sources: HashMap::new(),
+            #[cfg(feature = "nip32")]
+            ratings: HashMap::new(),
+        }
+    }
+
+    /// Set bootstrap relays
+    pub fn bootstrap_relays(self, relays: Vec<Url>) -> Self {
+        Self {
+            bootstrap_relays: relays,
+            ..self
+        }
+    }
+
+    /// Set max number of events requested per kind (default: 500)
+    pub fn limit(self, limit: usize) -> Self {
+        Self { limit, ..self }
+    }
+
+    /// Set max number of relays in the pool, including the ones already added (default: 10)
+    pub fn max_relays(self, max_relays: usize) -> Self {
+        Self { max_relays, ..self }
+    }
+
+    /// Set max number of relays per [`Network`] (default: unlimited)
+    pub fn max_per_network(self, max: usize) -> Self {
+        Self {
+            max_per_network: Some(max),
+            ..self
+        }
+    }
+
+    /// Set max number of relays per region (default: unlimited)
+    ///
+    /// Relays without announced geohash are not limited.
+    pub fn max_per_region(self, max: usize) -> Self {
+        Self {
+            max_per_region: Some(max),
+            ..self
+        }
+    }
+
+    /// Ingest relay list (kind 10002), relay discovery (kind 30166) or relay review (kind 1985, `nip32` feature) event
+    ///
+    /// Other kinds and invalid relay URLs are ignored. URLs are normalized (see [`relay_url::parse`]).
+    pub fn ingest(&mut self, event: &Event) {
+        match event.kind {
+            Kind::RelayList => {
+                for tag in event.tags.iter().map(|t| t.as_vec()) {
+                    if let [kind, url, ..] = tag.as_slice() {
+                        if kind == "r" {
+                            if let Ok(url) = relay_url::parse(url) {
+                                let sources = self.sources.entry(url.clone()).or_default();
+                                if sources.authors.insert(event.pubkey) {
+                                    self.candidate(url).mentions += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Kind::RelayDiscovery => self.ingest_announcement(event),
+            #[cfg(feature = "nip32")]
+            Kind::Label => self.ingest_review(event),
+            _ => (),
+        }
+    }
+
+    #[cfg(feature = "nip32")]
+    fn ingest_review(&mut self, event: &Event) {
+        if let Ok(review) = RelayReview::from_event(event) {
+            let url: Url = review.relay_url;
+            let rating = self
+                .ratings
+                .entry(url.clone())
+                .or_insert_with(|| RelayRating::new(url.clone()));
+            if rating.ingest(event) {
+                let (reviews, average) = (rating.reviews(), rating.average());
+                let candidate: &mut RelayCandidate = self.candidate(url);
+                candidate.reviews = reviews;
+                candidate.rating = average;
+            }
+        }
+    }
+
+    fn ingest_announcement(&mut self, event: &Event) {
+        let tags: Vec<Vec<String>> = event.tags.iter().map(|t| t.as_vec()).collect();
+        let url: Option<Url> = tags.iter().find_map(|tag| match tag.as_slice() {
+            [kind, url, ..] if kind == "d" => relay_url::parse(url).ok(),
+            _ => None,
+        });
+        let url: Url = match url {
+            Some(url) => url,
+            None => return,
+        };
+
+        let sources = self.sources.entry(url.clone()).or_default();
+        let new_monitor: bool = sources.monitors.insert(event.pubkey);
+        let candidate: &mut RelayCandidate = self.candidate(url);
+        if new_monitor {
+            candidate.monitors += 1;
+        }
+        candidate.last_seen = candidate.last_seen.max(Some(event.created_at));
+        for tag in tags.iter() {
+            match tag.as_slice() {
+                [kind, rtt, ..] if kind == "rtt-open" => {
+                    if let Ok(ms) = rtt.parse::<u64>() {
+                        let rtt = Duration::from_millis(ms);
+                        candidate.rtt = Some(candidate.rtt.map_or(rtt, |r| r.min(rtt)));
+                    }
+                }
+                [kind, network, ..] if kind == "n" => {
+                    candidate.network = Network::from_tag(network);
+                }
+                // Keep the most precise geohash
+                [kind, geohash, ..]
+                    if kind == "g"
+                        && candidate
+                            .geohash
+                            .as_ref()
+                            .map_or(true, |g| g.len() < geohash.len()) =>
+                {
+                    candidate.geohash = Some(geohash.to_lowercase());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn candidate(&mut self, url: Url) -> &mut RelayCandidate {
+        self.candidates
+            .entry(url.clone())
+            .or_insert_with(|| RelayCandidate::new(url))
+    }
+
+    /// Discovered relays, sorted by score
+    pub fn candidates(&self) -> Vec<RelayCandidate> {
+        let mut candidates: Vec<RelayCandidate> = self.candidates.values().cloned().collect();
+        candidates.sort_by(compare);
+        candidates
+    }
+
+    /// Add and connect the bootstrap relays, then fetch and ingest relay lists and relay discovery events
+    ///
+    /// Return the number of discovered relays.
+    pub async fn discover(&mut self) -> Result<usize, Error> {
+        let relays: HashMap<Url, crate::Relay> = self.client.relays().await;
+        for url in self.bootstrap_relays.iter() {
+            if !relays.contains_key(url) {
+                self.client.add_relay(url.as_str(), None).await?;
+                self.client.connect_relay(url.as_str(), true).await?;
+            }
+        }
+
+        #[allow(unused_mut)]
+        let mut filters = vec![
+            SubscriptionFilter::new()
+                .kind(Kind::RelayList)
+                .limit(self.limit),
+            SubscriptionFilter::new()
+                .kind(Kind::RelayDiscovery)
+                .limit(self.limit),
+        ];
+        #[cfg(feature = "nip32")]
+        filters.push(
+            SubscriptionFilter::new()
+                .kind(Kind::Label)
+                .limit(self.limit),
+        );
+        let events: Vec<Event> = self.client.get_events_of(filters).await?;
+        for event in events.iter() {
+            self.ingest(event);
+        }
+        Ok(self.candidates.len())
+    }
+
+    /// Suggest relays to add to the pool
+    ///
+    /// Relays already in the pool are skipped but count toward the constraints.
+    pub async fn suggest(&self) -> Vec<RelayCandidate> {
+        let pool: HashMap<Url, crate::Relay> = self.client.relays().await;
+        let mut networks: HashMap<Network, usize> = HashMap::new();
+        let mut regions: HashMap<String, usize> = HashMap::new();
+        for url in pool.keys() {
+            let candidate: RelayCandidate = self
+                .candidates
+                .get(url)
+                .cloned()
+                .unwrap_or_else(|| RelayCandidate::new(url.clone()));
+            *networks.entry(candidate.network.clone()).or_default() += 1;
+            if let Some(region) = candidate.region() {
+                *regions.entry(region.to_string()).or_default() += 1;
+            }
+        }
+
+        let mut available: usize = self.max_relays.saturating_sub(pool.len());
+        let mut suggestions: Vec<RelayCandidate> = Vec::new();
+        for candidate in self.candidates().into_iter() {
+            if available == 0 {
+                break;
+            }
+            if pool.contains_key(&candidate.url) {
+                continue;
+            }
+
+            let network = networks.entry(candidate.network.clone()).or_default();
+            if self.max_per_network.map_or(false, |max| *network >= max) {
+                continue;
+            }
+            if let (Some(region), Some(max)) = (candidate.region(), self.max_per_region) {
+                if regions.get(region).copied().unwrap_or_default() >= max {
+                    continue;
+                }
+                *regions.entry(region.to_string()).or_default() += 1;
+            }
+            *network += 1;
+            available -= 1;
+            suggestions.push(candidate);
+        }
+        suggestions
+    }
+
+    /// Add and connect the suggested relays
+    ///
+    /// Return the URLs of the added relays.
+    pub async fn add_suggested(&self) -> Result<Vec<Url>, Error> {
+        let mut added: Vec<Url> = Vec::new();
+        for candidate in self.suggest().await.into_iter() {
+            self.client.add_relay(candidate.url.as_str(), None).await?;
+            self.client
+                .connect_relay(candidate.url.as_str(), false)
+                .await?;
+            added.push(candidate.url);
+        }
+        Ok(added)
+    }
+}
+
+/// Sort by score (descending), then by URL
+fn compare(a: &RelayCandidate, b: &RelayCandidate) -> Ordering {
+    b.score()
+        .partial_cmp(&a.score())
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| a.url.cmp(&b.url))
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Display names
+//!
+//! Resolve the names to display for public keys from the metadata and contact list in the [`Store`].
+
+use std::collections::HashMap;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::types::display_name;
+use nostr::{ContactList, Event, Kind, Metadata, SubscriptionFilter};
+
+use crate::store::Store;
+
+/// Display name resolver
+///
+/// Names are resolved as petname > `display_name` > `name` > `nip05` > shortened `npub`
+/// (see [`display_name::display_name`]), where petnames are the aliases in the contact list of the user.
+#[derive(Debug, Clone)]
+pub struct DisplayName {
+    store: Store,
+    user: XOnlyPublicKey,
+}
+
+impl DisplayName {
+    /// New [`DisplayName`] resolver, with the petnames of the contact list of `user`
+    pub fn new(store: Store, user: XOnlyPublicKey) -> Self {
+        Self { store, user }
+    }
+
+    /// Resolve the name to display for `public_key`
+    pub fn resolve(&self, public_key: &XOnlyPublicKey) -> String {
+        let contacts: Option<ContactList> = self.contact_list();
+        self.resolve_with(public_key, contacts.as_ref())
+    }
+
+    /// Resolve the names to display for `public_keys`
+    pub fn resolve_many(&self, public_keys: &[XOnlyPublicKey]) -> HashMap<XOnlyPublicKey, String> {
+        let contacts: Option<ContactList> = self.contact_list();
+        public_keys
+            .iter()
+            .map(|pk| (*pk, self.resolve_with(pk, contacts.as_ref())))
+            .collect()
+    }
+
+    fn resolve_with(&self, public_key: &XOnlyPublicKey, contacts: Option<&ContactList>) -> String {
+        let petname: Option<&str> = contacts.and_then(|list| {
+            list.contacts
+                .iter()
+                .find(|c| &c.pk == public_key)
+                .and_then(|c| c.alias.as_deref())
+        });
+        let metadata: Option<Metadata> = self
+            .latest(*public_key, Kind::Metadata)
+            .and_then(|event| Metadata::from_json(event.content).ok());
+        display_name::display_name(public_key, metadata.as_ref(), petname)
+    }
+
+    fn contact_list(&self) -> Option<ContactList> {
+        self.latest(self.user, Kind::ContactList)
+            .and_then(|event| ContactList::from_event(&event))
+    }
+
+    fn latest(&self, author: XOnlyPublicKey, kind: Kind) -> Option<Event> {
+        let filter = SubscriptionFilter::new().author(author).kind(kind);
+        self.store
+            .query(vec![filter])
+            .into_iter()
+            .max_by_key(|e| e.created_at)
+    }
+}
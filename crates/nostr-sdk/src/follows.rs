@@ -0,0 +1,215 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Follow notifications
+//!
+//! Diff the contact lists (NIP-02) saved in the store to detect follows and unfollows.
+
+use std::collections::HashSet;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::{ContactList, Event, Kind, SubscriptionFilter, Timestamp};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::client::{Client, Error};
+use crate::store::{Store, StoreNotification};
+use crate::thread;
+
+/// Follow notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FollowNotification {
+    /// A followed user changed the contact list
+    ContactsChanged {
+        /// Followed user
+        public_key: XOnlyPublicKey,
+        /// Public keys added to the contact list
+        added: Vec<XOnlyPublicKey>,
+        /// Public keys removed from the contact list
+        removed: Vec<XOnlyPublicKey>,
+        /// Creation time of the new contact list
+        created_at: Timestamp,
+    },
+    /// New follower
+    Followed {
+        /// Follower
+        follower: XOnlyPublicKey,
+        /// Creation time of the contact list
+        created_at: Timestamp,
+    },
+    /// Lost follower
+    Unfollowed {
+        /// Former follower
+        follower: XOnlyPublicKey,
+        /// Creation time of the contact list
+        created_at: Timestamp,
+    },
+}
+
+/// Follow watcher
+///
+/// Listen for new contact lists saved in the client [`Store`], so a store is required, and compare them with
+/// the superseded versions. Changes are reported only for the users in the client contact list (the watchlist),
+/// follows and unfollows for every contact list that adds or removes the client public key.
+///
+/// The first contact list seen of a user has nothing to be compared with: it's reported as
+/// [`FollowNotification::Followed`] if it contains the client public key, even if the follow is older.
+#[derive(Debug, Clone)]
+pub struct FollowWatcher {
+    public_key: XOnlyPublicKey,
+    store: Store,
+    notification_sender: broadcast::Sender<FollowNotification>,
+}
+
+impl FollowWatcher {
+    /// New [`FollowWatcher`] for the client public key
+    pub fn new(client: &Client) -> Result<Self, Error> {
+        let store: Store = client.store().ok_or(Error::StoreNotConfigured)?;
+        let public_key: XOnlyPublicKey = client.keys().public_key();
+        let (notification_sender, _) = broadcast::channel(1024);
+        let watcher = Self {
+            public_key,
+            store,
+            notification_sender,
+        };
+        watcher.handle_notifications();
+        Ok(watcher)
+    }
+
+    fn handle_notifications(&self) {
+        let public_key: XOnlyPublicKey = self.public_key;
+        let mut watchlist: HashSet<XOnlyPublicKey> = self.watchlist();
+        let mut notifications = self.store.notifications();
+        let sender = self.notification_sender.clone();
+        thread::spawn(async move {
+            loop {
+                let (previous, event) = match notifications.recv().await {
+                    Ok(StoreNotification::Updated { previous, event }) => (previous, event),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                let list: ContactList = match ContactList::from_event(&event) {
+                    Some(list) => list,
+                    None => continue,
+                };
+
+                if event.pubkey == public_key {
+                    watchlist = list.public_keys().into_iter().collect();
+                    continue;
+                }
+
+                for notification in diff(
+                    public_key,
+                    &watchlist,
+                    event.pubkey,
+                    previous.as_deref().and_then(ContactList::from_event),
+                    list,
+                ) {
+                    let _ = sender.send(notification);
+                }
+            }
+            log::debug!("Exited from follow watcher");
+        });
+    }
+
+    /// Get new notification listener
+    pub fn notifications(&self) -> broadcast::Receiver<FollowNotification> {
+        self.notification_sender.subscribe()
+    }
+
+    /// Public keys in the client contact list
+    pub fn watchlist(&self) -> HashSet<XOnlyPublicKey> {
+        self.contact_lists(SubscriptionFilter::new().author(self.public_key))
+            .into_iter()
+            .next()
+            .and_then(|event| ContactList::from_event(&event))
+            .map(|list| list.public_keys().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Known followers: authors of the stored contact lists containing the client public key
+    pub fn followers(&self) -> HashSet<XOnlyPublicKey> {
+        self.contact_lists(SubscriptionFilter::new().pubkey(self.public_key))
+            .into_iter()
+            .map(|event| event.pubkey)
+            .filter(|pk| pk != &self.public_key)
+            .collect()
+    }
+
+    fn contact_lists(&self, filter: SubscriptionFilter) -> Vec<Event> {
+        self.store.query(vec![filter.kind(Kind::ContactList)])
+    }
+
+    /// Filters of the contact lists to watch
+    ///
+    /// Contact lists containing the client public key (new followers) and contact lists of the
+    /// watchlist and of the known followers (changes and unfollows).
+    pub fn filters(&self, since: Option<Timestamp>) -> Vec<SubscriptionFilter> {
+        let mut filters = vec![SubscriptionFilter::new().pubkey(self.public_key)];
+        let mut authors: Vec<XOnlyPublicKey> =
+            self.watchlist().union(&self.followers()).copied().collect();
+        authors.sort();
+        if !authors.is_empty() {
+            filters.push(SubscriptionFilter::new().authors(authors));
+        }
+        filters
+            .into_iter()
+            .map(|filter| {
+                let filter = filter.kind(Kind::ContactList);
+                match since {
+                    Some(since) => filter.since(since),
+                    None => filter,
+                }
+            })
+            .collect()
+    }
+}
+
+fn diff(
+    public_key: XOnlyPublicKey,
+    watchlist: &HashSet<XOnlyPublicKey>,
+    author: XOnlyPublicKey,
+    previous: Option<ContactList>,
+    current: ContactList,
+) -> Vec<FollowNotification> {
+    let mut notifications: Vec<FollowNotification> = Vec::new();
+    let created_at: Timestamp = current.created_at;
+
+    if let Some(previous) = &previous {
+        if watchlist.contains(&author) {
+            let added: Vec<XOnlyPublicKey> = current
+                .public_keys()
+                .into_iter()
+                .filter(|pk| !previous.contains(pk))
+                .collect();
+            let removed: Vec<XOnlyPublicKey> = previous
+                .public_keys()
+                .into_iter()
+                .filter(|pk| !current.contains(pk))
+                .collect();
+            if !added.is_empty() || !removed.is_empty() {
+                notifications.push(FollowNotification::ContactsChanged {
+                    public_key: author,
+                    added,
+                    removed,
+                    created_at,
+                });
+            }
+        }
+    }
+
+    let was_following: bool = previous.map_or(false, |list| list.contains(&public_key));
+    match (was_following, current.contains(&public_key)) {
+        (false, true) => notifications.push(FollowNotification::Followed {
+            follower: author,
+            created_at,
+        }),
+        (true, false) => notifications.push(FollowNotification::Unfollowed {
+            follower: author,
+            created_at,
+        }),
+        _ => (),
+    }
+
+    notifications
+}
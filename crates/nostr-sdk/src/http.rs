@@ -0,0 +1,133 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! HTTP client
+//!
+//! Used for NIP-05 and LNURL lookups.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use futures_util::future;
+use hyper::client::connect::dns::Name;
+use nostr::url::Url;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::Proxy;
+use serde::Deserialize;
+
+/// DNS record types
+const A: u16 = 1;
+const AAAA: u16 = 28;
+
+/// HTTP error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reqwest error
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// No address found for name
+    #[error("no address found for {0}")]
+    NoAddress(String),
+}
+
+/// Build HTTP client
+///
+/// When a proxy is set, names are resolved by the proxy (`socks5h`) and `dns_over_https` is ignored.
+/// Otherwise the proxy environment variables (`HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY`, `NO_PROXY`)
+/// are honored if `system_proxy` is enabled.
+pub(crate) fn build_client(
+    proxy: Option<SocketAddr>,
+    dns_over_https: Option<Url>,
+    system_proxy: bool,
+) -> Result<reqwest::Client, Error> {
+    let mut builder = reqwest::Client::builder();
+    if !system_proxy {
+        builder = builder.no_proxy();
+    }
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{proxy}");
+        builder = builder.proxy(Proxy::all(proxy)?);
+    } else if let Some(endpoint) = dns_over_https {
+        builder = builder.dns_resolver(Arc::new(DnsOverHttps::new(endpoint)?));
+    }
+    Ok(builder.build()?)
+}
+
+#[derive(Deserialize)]
+struct DnsResponse {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DnsAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+/// DNS-over-HTTPS resolver
+///
+/// Query the `application/dns-json` API of the endpoint (ex. `https://1.1.1.1/dns-query`).
+#[derive(Debug, Clone)]
+pub struct DnsOverHttps {
+    endpoint: Url,
+    client: reqwest::Client,
+}
+
+impl DnsOverHttps {
+    /// New [`DnsOverHttps`] resolver
+    ///
+    /// Use an IP address as endpoint host to avoid resolving it with the system resolver.
+    pub fn new(endpoint: Url) -> Result<Self, Error> {
+        Ok(Self {
+            endpoint,
+            client: reqwest::Client::builder().build()?,
+        })
+    }
+
+    async fn query(&self, name: &str, record_type: u16) -> Result<Vec<IpAddr>, Error> {
+        let res: DnsResponse = self
+            .client
+            .get(self.endpoint.clone())
+            .query(&[("name", name), ("type", &record_type.to_string())])
+            .header("Accept", "application/dns-json")
+            .send()
+            .await?
+            .json()
+            .await?;
+        Ok(res
+            .answer
+            .into_iter()
+            .filter(|answer| answer.record_type == record_type)
+            .filter_map(|answer| answer.data.parse().ok())
+            .collect())
+    }
+
+    /// Lookup IPv4 and IPv6 addresses of `name`
+    pub async fn lookup(&self, name: &str) -> Result<Vec<IpAddr>, Error> {
+        let (ipv4, ipv6) = future::join(self.query(name, A), self.query(name, AAAA)).await;
+        let mut addresses: Vec<IpAddr> = ipv4.unwrap_or_default();
+        addresses.extend(ipv6.unwrap_or_default());
+        if addresses.is_empty() {
+            return Err(Error::NoAddress(name.to_string()));
+        }
+        Ok(addresses)
+    }
+}
+
+impl Resolve for DnsOverHttps {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addresses = resolver.lookup(name.as_str()).await?;
+            // Port is set by the connector
+            let addrs: Addrs = Box::new(
+                addresses
+                    .into_iter()
+                    .map(|address| SocketAddr::new(address, 0)),
+            );
+            Ok(addrs)
+        })
+    }
+}
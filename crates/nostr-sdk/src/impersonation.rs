@@ -0,0 +1,114 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Impersonation checks
+//!
+//! Flag metadata events whose NIP-05 identifier doesn't verify or whose names collide with a verified contact.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+use futures_util::future;
+use nostr::key::XOnlyPublicKey;
+use nostr::types::impersonation;
+use nostr::{Event, ImpersonationWarning, Kind, Metadata};
+
+use crate::client::{Client, Error, CONTACT_LISTS_BATCH_SIZE};
+
+/// Impersonation checker
+///
+/// Verified contacts are the contacts of the [`Client`] keys whose NIP-05 identifier verifies.
+/// They are loaded with [`ImpersonationChecker::load_contacts`]; clones share the same contacts.
+#[derive(Debug, Clone)]
+pub struct ImpersonationChecker {
+    client: Client,
+    verified_contacts: Arc<RwLock<Vec<(XOnlyPublicKey, Metadata)>>>,
+}
+
+impl ImpersonationChecker {
+    /// New [`ImpersonationChecker`]
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            verified_contacts: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Fetch contacts metadata and verify their NIP-05 identifiers
+    ///
+    /// Return the number of verified contacts.
+    pub async fn load_contacts(&self) -> Result<usize, Error> {
+        let public_keys: Vec<XOnlyPublicKey> = self
+            .client
+            .get_contact_list()
+            .await?
+            .into_iter()
+            .map(|contact| contact.pk)
+            .collect();
+        let metadata = self
+            .client
+            .get_metadata_batched(public_keys, CONTACT_LISTS_BATCH_SIZE)
+            .await?;
+
+        let candidates: Vec<(XOnlyPublicKey, Metadata)> = metadata
+            .into_iter()
+            .filter(|(_, metadata)| metadata.nip05.is_some())
+            .collect();
+        let results = future::join_all(candidates.iter().map(|(public_key, metadata)| {
+            let nip05: &str = metadata.nip05.as_deref().unwrap_or_default();
+            self.client.verify_nip05(*public_key, nip05)
+        }))
+        .await;
+
+        let verified: Vec<(XOnlyPublicKey, Metadata)> = candidates
+            .into_iter()
+            .zip(results)
+            .filter_map(|(contact, result)| result.is_ok().then_some(contact))
+            .collect();
+        let len: usize = verified.len();
+
+        let mut verified_contacts = self
+            .verified_contacts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        *verified_contacts = verified;
+        Ok(len)
+    }
+
+    /// Get verified contacts with their metadata
+    pub fn verified_contacts(&self) -> Vec<(XOnlyPublicKey, Metadata)> {
+        self.verified_contacts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Check metadata [`Event`]
+    ///
+    /// Events of other kinds don't produce warnings.
+    pub async fn check(&self, event: &Event) -> Result<Vec<ImpersonationWarning>, Error> {
+        if event.kind != Kind::Metadata {
+            return Ok(Vec::new());
+        }
+
+        let metadata = Metadata::from_json(event.content.clone())?;
+        let mut warnings: Vec<ImpersonationWarning> = Vec::new();
+
+        if let Some(nip05) = &metadata.nip05 {
+            if let Err(e) = self.client.verify_nip05(event.pubkey, nip05).await {
+                warnings.push(ImpersonationWarning::Nip05Unverified {
+                    nip05: nip05.clone(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+
+        let verified_contacts = self.verified_contacts();
+        warnings.extend(impersonation::name_collisions(
+            &event.pubkey,
+            &metadata,
+            &verified_contacts,
+        ));
+
+        Ok(warnings)
+    }
+}
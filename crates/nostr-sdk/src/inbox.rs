@@ -0,0 +1,566 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Inbox
+//!
+//! Direct messages of multiple identities aggregated in a single conversation list,
+//! with unread tracking.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+#[cfg(feature = "nip59")]
+use std::collections::HashSet;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use nostr::event::TagKind;
+use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip04;
+#[cfg(feature = "nip59")]
+use nostr::nips::nip59;
+use nostr::{Event, EventId, Keys, Kind, SubscriptionFilter, Tag, Timestamp};
+
+use crate::client::{Client, Error};
+use crate::relay::pool::Output;
+
+/// Identifier of the read markers event (NIP-78)
+const READ_MARKERS_ID: &str = "nostr-sdk/inbox/read";
+/// Max random shift in the past of gift wraps `created_at` (NIP-59)
+const GIFT_WRAP_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+/// Read marker tag: `["read", <counterparty>, <timestamp>]`
+const READ_MARKER: &str = "read";
+
+/// Direct message protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirectMessageKind {
+    /// Encrypted direct message (NIP-04, kind 4)
+    Nip04,
+    /// Private direct message gift wrapped (NIP-17 and NIP-59, kind 1059)
+    GiftWrap,
+}
+
+/// Decrypted direct message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectMessage {
+    /// Event id (rumor id for gift wraps)
+    pub id: EventId,
+    /// Identity that sent or received the message
+    pub identity: XOnlyPublicKey,
+    /// Other side of the conversation
+    pub counterparty: XOnlyPublicKey,
+    /// Sent by `identity`
+    pub outgoing: bool,
+    /// Timestamp
+    pub created_at: Timestamp,
+    /// Plaintext
+    pub content: String,
+    /// Protocol
+    pub kind: DirectMessageKind,
+}
+
+/// Conversation between an identity and a counterparty
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversation {
+    /// Identity
+    pub identity: XOnlyPublicKey,
+    /// Other side of the conversation
+    pub counterparty: XOnlyPublicKey,
+    /// Latest message
+    pub last_message: DirectMessage,
+    /// Number of incoming messages not marked as read
+    pub unread: usize,
+}
+
+type ConversationKey = (XOnlyPublicKey, XOnlyPublicKey);
+
+#[derive(Debug, Default)]
+struct InboxState {
+    identities: HashMap<XOnlyPublicKey, Keys>,
+    messages: HashMap<EventId, DirectMessage>,
+    /// Read markers: incoming messages created before or at the marker are read
+    read: HashMap<ConversationKey, Timestamp>,
+}
+
+/// Direct messages inbox
+///
+/// Aggregate the NIP-04 direct messages and, with the `nip59` feature, the gift wrapped private
+/// messages of all the identities. Read markers are persisted in the client [`Store`](crate::Store)
+/// as NIP-78 events signed by every identity, never sent to relays.
+///
+/// Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    client: Client,
+    state: Arc<RwLock<InboxState>>,
+}
+
+impl Inbox {
+    /// New [`Inbox`]
+    ///
+    /// The [`Client`] keys are added as first identity if they have a secret key.
+    pub fn new(client: &Client) -> Self {
+        let inbox = Self {
+            client: client.clone(),
+            state: Arc::new(RwLock::new(InboxState::default())),
+        };
+        let _ = inbox.add_identity(client.keys());
+        inbox
+    }
+
+    /// Add identity
+    ///
+    /// The secret key is required to decrypt messages.
+    pub fn add_identity(&self, keys: Keys) -> Result<(), Error> {
+        keys.secret_key()?;
+        let public_key: XOnlyPublicKey = keys.public_key();
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        state.identities.insert(public_key, keys);
+        let saved: Option<Event> = self
+            .client
+            .store()
+            .and_then(|store| store.app_data(public_key, READ_MARKERS_ID));
+        if let Some(event) = saved {
+            for tag in event.tags.iter().map(|t| t.as_vec()) {
+                if let [kind, counterparty, timestamp] = tag.as_slice() {
+                    if kind.as_str() != READ_MARKER {
+                        continue;
+                    }
+                    if let (Ok(counterparty), Ok(timestamp)) = (
+                        counterparty.parse::<XOnlyPublicKey>(),
+                        timestamp.parse::<u64>(),
+                    ) {
+                        state
+                            .read
+                            .insert((public_key, counterparty), Timestamp::from(timestamp));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove identity and its messages
+    pub fn remove_identity(&self, public_key: &XOnlyPublicKey) {
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        state.identities.remove(public_key);
+        state.messages.retain(|_, msg| &msg.identity != public_key);
+        state.read.retain(|(identity, _), _| identity != public_key);
+    }
+
+    /// Get identities
+    pub fn identities(&self) -> Vec<XOnlyPublicKey> {
+        self.state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .identities
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    /// Filters of the direct messages sent and received by all the identities
+    pub fn filters(&self, since: Option<Timestamp>) -> Vec<SubscriptionFilter> {
+        let identities: Vec<XOnlyPublicKey> = self.identities();
+        let mut filters = vec![
+            SubscriptionFilter::new()
+                .kind(Kind::EncryptedDirectMessage)
+                .pubkeys(identities.clone()),
+            SubscriptionFilter::new()
+                .kind(Kind::EncryptedDirectMessage)
+                .authors(identities.clone()),
+        ];
+        if let Some(since) = since {
+            filters = filters.into_iter().map(|f| f.since(since)).collect();
+        }
+        if cfg!(feature = "nip59") {
+            let mut filter = SubscriptionFilter::new()
+                .kind(Kind::GiftWrap)
+                .pubkeys(identities);
+            if let Some(since) = since {
+                // Gift wraps `created_at` is randomized up to 2 days in the past
+                filter = filter.since(Timestamp::from(
+                    since.as_u64().saturating_sub(GIFT_WRAP_TIMESTAMP_TWEAK),
+                ));
+            }
+            filters.push(filter);
+        }
+        filters
+    }
+
+    /// Fetch and decrypt the direct messages of all the identities
+    ///
+    /// Return the number of new messages.
+    pub async fn sync(&self) -> Result<usize, Error> {
+        let events: Vec<Event> = self.client.get_events_of(self.filters(None)).await?;
+        Ok(events
+            .iter()
+            .filter_map(|event| self.handle_event(event))
+            .count())
+    }
+
+    /// Subscribe to new direct messages of all the identities
+    ///
+    /// Replace the current [`Client`] subscription: merge [`Inbox::filters`] with your filters
+    /// and call [`Client::subscribe`] to keep both. Received events must be passed to [`Inbox::handle_event`].
+    pub async fn subscribe(&self) -> Result<Output<()>, Error> {
+        self.client
+            .subscribe(self.filters(Some(Timestamp::now())))
+            .await
+    }
+
+    /// Decrypt and add direct message [`Event`]
+    ///
+    /// Return `None` if the event is not a direct message of an identity, can't be decrypted or was already added.
+    pub fn handle_event(&self, event: &Event) -> Option<DirectMessage> {
+        let identities: Vec<Keys> = self
+            .state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .identities
+            .values()
+            .cloned()
+            .collect();
+
+        let msg: DirectMessage = match event.kind {
+            Kind::EncryptedDirectMessage => decrypt_nip04(&identities, event)?,
+            #[cfg(feature = "nip59")]
+            Kind::GiftWrap => unwrap_private_msg(&identities, event)?,
+            _ => return None,
+        };
+
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        if !state.identities.contains_key(&msg.identity) || state.messages.contains_key(&msg.id) {
+            return None;
+        }
+        state.messages.insert(msg.id, msg.clone());
+        Some(msg)
+    }
+
+    /// Get conversations, from the most recent
+    pub fn conversations(&self) -> Vec<Conversation> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let mut conversations: HashMap<ConversationKey, Conversation> = HashMap::new();
+        for msg in state.messages.values() {
+            let key: ConversationKey = (msg.identity, msg.counterparty);
+            let is_unread: bool = !msg.outgoing
+                && state
+                    .read
+                    .get(&key)
+                    .map_or(true, |read| msg.created_at > *read);
+            let conversation = conversations.entry(key).or_insert_with(|| Conversation {
+                identity: msg.identity,
+                counterparty: msg.counterparty,
+                last_message: msg.clone(),
+                unread: 0,
+            });
+            if msg.created_at > conversation.last_message.created_at {
+                conversation.last_message = msg.clone();
+            }
+            if is_unread {
+                conversation.unread += 1;
+            }
+        }
+        let mut conversations: Vec<Conversation> = conversations.into_values().collect();
+        conversations.sort_by_key(|c| Reverse(c.last_message.created_at));
+        conversations
+    }
+
+    /// Get messages of a conversation, from the oldest
+    pub fn messages(
+        &self,
+        identity: &XOnlyPublicKey,
+        counterparty: &XOnlyPublicKey,
+    ) -> Vec<DirectMessage> {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let mut messages: Vec<DirectMessage> = state
+            .messages
+            .values()
+            .filter(|msg| &msg.identity == identity && &msg.counterparty == counterparty)
+            .cloned()
+            .collect();
+        messages.sort_by_key(|msg| msg.created_at);
+        messages
+    }
+
+    /// Total number of unread messages
+    pub fn unread_count(&self) -> usize {
+        self.conversations().iter().map(|c| c.unread).sum()
+    }
+
+    /// Mark all the messages of a conversation as read
+    ///
+    /// The read markers of `identity` are saved in the client store, if any.
+    pub fn mark_read(
+        &self,
+        identity: &XOnlyPublicKey,
+        counterparty: &XOnlyPublicKey,
+    ) -> Result<(), Error> {
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        let latest: Timestamp = state
+            .messages
+            .values()
+            .filter(|msg| &msg.identity == identity && &msg.counterparty == counterparty)
+            .map(|msg| msg.created_at)
+            .max()
+            .unwrap_or_else(Timestamp::now);
+        state.read.insert((*identity, *counterparty), latest);
+        self.save_read_markers(&state, identity)
+    }
+
+    fn save_read_markers(
+        &self,
+        state: &InboxState,
+        identity: &XOnlyPublicKey,
+    ) -> Result<(), Error> {
+        let store = match self.client.store() {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        let keys: &Keys = match state.identities.get(identity) {
+            Some(keys) => keys,
+            None => return Ok(()),
+        };
+
+        let tags: Vec<Tag> = state
+            .read
+            .iter()
+            .filter(|((i, _), _)| i == identity)
+            .map(|((_, counterparty), timestamp)| {
+                Tag::Generic(
+                    TagKind::Custom(READ_MARKER.to_string()),
+                    vec![counterparty.to_string(), timestamp.to_string()],
+                )
+            })
+            .collect();
+        store.save_app_data(keys, READ_MARKERS_ID, tags)?;
+        Ok(())
+    }
+}
+
+fn first_tagged_public_key(tags: &[Tag]) -> Option<XOnlyPublicKey> {
+    tags.iter().find_map(|tag| match tag {
+        Tag::PubKey(pk, ..) => Some(*pk),
+        Tag::ContactList { pk, .. } => Some(*pk),
+        _ => None,
+    })
+}
+
+fn decrypt_nip04(identities: &[Keys], event: &Event) -> Option<DirectMessage> {
+    let receiver: XOnlyPublicKey = first_tagged_public_key(&event.tags)?;
+    let (keys, counterparty, outgoing) = match identities
+        .iter()
+        .find(|keys| keys.public_key() == event.pubkey)
+    {
+        Some(keys) => (keys, receiver, true),
+        None => (
+            identities
+                .iter()
+                .find(|keys| keys.public_key() == receiver)?,
+            event.pubkey,
+            false,
+        ),
+    };
+    let content: String =
+        nip04::decrypt(&keys.secret_key().ok()?, &counterparty, &event.content).ok()?;
+    Some(DirectMessage {
+        id: event.id,
+        identity: keys.public_key(),
+        counterparty,
+        outgoing,
+        created_at: event.created_at,
+        content,
+        kind: DirectMessageKind::Nip04,
+    })
+}
+
+#[cfg(feature = "nip59")]
+fn unwrap_private_msg(identities: &[Keys], event: &Event) -> Option<DirectMessage> {
+    let receivers: HashSet<XOnlyPublicKey> = event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::PubKey(pk, ..) => Some(*pk),
+            _ => None,
+        })
+        .collect();
+    identities
+        .iter()
+        .filter(|keys| receivers.contains(&keys.public_key()))
+        .find_map(|keys| {
+            let unwrapped = nip59::unwrap_gift_wrap(keys, event).ok()?;
+            if unwrapped.rumor.kind != Kind::PrivateDirectMessage {
+                return None;
+            }
+            let identity: XOnlyPublicKey = keys.public_key();
+            let outgoing: bool = unwrapped.sender == identity;
+            let counterparty: XOnlyPublicKey = if outgoing {
+                // Copy of a sent message
+                first_tagged_public_key(&unwrapped.rumor.tags)?
+            } else {
+                unwrapped.sender
+            };
+            Some(DirectMessage {
+                id: unwrapped.rumor.id,
+                identity,
+                counterparty,
+                outgoing,
+                created_at: unwrapped.rumor.created_at,
+                content: unwrapped.rumor.content,
+                kind: DirectMessageKind::GiftWrap,
+            })
+        })
+}
+
+#[cfg(all(test, feature = "nip59"))]
+mod tests {
+    use nostr::{EventBuilder, UnsignedEvent};
+
+    use super::*;
+
+    /// Private direct message (NIP-17) from `sender` to `receiver`
+    fn rumor(sender: &Keys, receiver: &Keys, content: &str) -> UnsignedEvent {
+        EventBuilder::new(
+            Kind::PrivateDirectMessage,
+            content,
+            &[Tag::PubKey(receiver.public_key(), None)],
+        )
+        .to_unsigned_event(sender.public_key())
+    }
+
+    fn wrap(sender: &Keys, receiver: &Keys, rumor: &UnsignedEvent) -> Event {
+        nip59::gift_wrap(sender, &receiver.public_key(), rumor).unwrap()
+    }
+
+    struct Wraps {
+        alice: Keys,
+        bob: Keys,
+        carol: Keys,
+        /// Valid wraps: bob to alice, bob to carol and the copy of alice to bob
+        valid: Vec<Event>,
+        /// Wraps addressed to alice that must be dropped
+        invalid: Vec<Event>,
+    }
+
+    fn wraps() -> Wraps {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+        let mallory = Keys::generate();
+
+        let to_alice = rumor(&bob, &alice, "hi alice");
+        let valid = vec![
+            wrap(&bob, &alice, &to_alice),
+            wrap(&bob, &carol, &rumor(&bob, &carol, "hi carol")),
+            wrap(&alice, &alice, &rumor(&alice, &bob, "hi bob")),
+        ];
+
+        let mut tampered = rumor(&bob, &alice, "original");
+        tampered.content = String::from("tampered");
+        let note = EventBuilder::new_text_note("not a private message", &[])
+            .to_unsigned_event(bob.public_key());
+        let invalid = vec![
+            // Sealed by mallory, impersonating bob
+            wrap(&mallory, &alice, &rumor(&bob, &alice, "I'm bob")),
+            // Rumor id doesn't match its content
+            wrap(&bob, &alice, &tampered),
+            // Not a private direct message
+            wrap(&bob, &alice, &note),
+            // Same rumor wrapped again
+            wrap(&bob, &alice, &to_alice),
+        ];
+
+        Wraps {
+            alice,
+            bob,
+            carol,
+            valid,
+            invalid,
+        }
+    }
+
+    fn assert_inbox(inbox: &Inbox, wraps: &Wraps) {
+        let Wraps {
+            alice, bob, carol, ..
+        } = wraps;
+        let conversations = inbox.conversations();
+        assert_eq!(conversations.len(), 2);
+        assert_eq!(inbox.unread_count(), 2);
+
+        let messages = inbox.messages(&alice.public_key(), &bob.public_key());
+        assert_eq!(messages.len(), 2);
+        assert!(messages
+            .iter()
+            .all(|m| m.kind == DirectMessageKind::GiftWrap));
+        let incoming: Vec<&DirectMessage> = messages.iter().filter(|m| !m.outgoing).collect();
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].content, "hi alice");
+        let outgoing: Vec<&DirectMessage> = messages.iter().filter(|m| m.outgoing).collect();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].content, "hi bob");
+
+        let messages = inbox.messages(&carol.public_key(), &bob.public_key());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "hi carol");
+    }
+
+    #[tokio::test]
+    async fn test_handle_gift_wraps() {
+        let wraps = wraps();
+        let inbox = Inbox::new(&Client::new(&wraps.alice));
+        inbox.add_identity(wraps.carol.clone()).unwrap();
+
+        for event in wraps.valid.iter() {
+            assert!(inbox.handle_event(event).is_some());
+        }
+        for event in wraps.invalid.iter() {
+            assert_eq!(inbox.handle_event(event), None);
+        }
+        // Already added
+        assert_eq!(inbox.handle_event(&wraps.valid[0]), None);
+        // Not addressed to an identity
+        let dave = Keys::generate();
+        let to_dave = wrap(&wraps.bob, &dave, &rumor(&wraps.bob, &dave, "hi dave"));
+        assert_eq!(inbox.handle_event(&to_dave), None);
+
+        assert_inbox(&inbox, &wraps);
+
+        // Messages of removed identities are dropped
+        inbox.remove_identity(&wraps.carol.public_key());
+        assert_eq!(inbox.handle_event(&wraps.valid[1]), None);
+        assert_eq!(inbox.conversations().len(), 1);
+    }
+
+    #[cfg(feature = "scenario")]
+    #[tokio::test]
+    async fn test_sync_gift_wraps() {
+        use crate::scenario::{Scenario, ScenarioRelay, Step};
+        use crate::Options;
+
+        let wraps = wraps();
+        // Wrap signature not valid: dropped by the pool
+        let mut forged = wrap(
+            &wraps.bob,
+            &wraps.alice,
+            &rumor(&wraps.bob, &wraps.alice, "forged"),
+        );
+        forged.sig = wraps.valid[0].sig;
+
+        let mut events = wraps.valid.clone();
+        events.extend(wraps.invalid.iter().cloned());
+        events.push(forged);
+        let scenario = Scenario::new()
+            // Subscription sent on connection
+            .step(Step::Receive)
+            .respond_to_req(events);
+        let relay = ScenarioRelay::start(scenario).unwrap();
+
+        let client = Client::new_with_opts(&wraps.alice, Options::new().wait_for_connection(true));
+        client.add_relay(relay.url(), None).await.unwrap();
+        client.connect().await;
+        let inbox = Inbox::new(&client);
+        inbox.add_identity(wraps.carol.clone()).unwrap();
+
+        assert_eq!(inbox.sync().await.unwrap(), wraps.valid.len());
+        assert_inbox(&inbox, &wraps);
+        relay.stop();
+    }
+}
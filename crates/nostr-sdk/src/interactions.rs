@@ -0,0 +1,234 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Interactions
+//!
+//! Aggregate the reactions (NIP-25) and zaps (NIP-57) of an event. Multiple reactions or zaps
+//! from the same author are collapsed according to an [`AggregationPolicy`], since raw counting
+//! overstates engagement.
+
+use std::collections::HashMap;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::{Event, EventId, Kind, SubscriptionFilter, Timestamp};
+
+use crate::client::{Client, Error};
+use crate::store::Store;
+
+/// Reactions of the same author
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReactionDedup {
+    /// Count every reaction
+    All,
+    /// Count only the latest reaction of each author
+    #[default]
+    LatestWins,
+}
+
+/// Zaps of the same sender
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZapDedup {
+    /// Count every zap
+    All,
+    /// Count only the latest zap of each sender
+    LatestWins,
+    /// Count one zap per sender, with the sum of the amounts
+    #[default]
+    Sum,
+}
+
+/// Deduplication policy of the interactions of the same author
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AggregationPolicy {
+    /// Reactions
+    pub reactions: ReactionDedup,
+    /// Zaps
+    pub zaps: ZapDedup,
+}
+
+impl AggregationPolicy {
+    /// New default [`AggregationPolicy`] (latest reaction wins, zaps summed)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set reactions deduplication
+    pub fn reactions(self, reactions: ReactionDedup) -> Self {
+        Self { reactions, ..self }
+    }
+
+    /// Set zaps deduplication
+    pub fn zaps(self, zaps: ZapDedup) -> Self {
+        Self { zaps, ..self }
+    }
+}
+
+/// Aggregated interactions of an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interactions {
+    /// Event id
+    pub event_id: EventId,
+    /// Number of reactions by content (`+` for likes, `-` for dislikes, emojis)
+    pub reactions: HashMap<String, usize>,
+    /// Number of zaps
+    pub zaps: usize,
+    /// Zapped amount, in millisats
+    pub zap_amount: u64,
+    /// Zapped amount by sender, in millisats
+    pub zappers: HashMap<XOnlyPublicKey, u64>,
+}
+
+impl Interactions {
+    /// Aggregate reactions (kind 7) and zap receipts (kind 9735) of `event_id`
+    ///
+    /// Events of other kinds or referencing other events are ignored. Zap amounts are taken
+    /// from the `amount` tag of the zap request: zaps without amount count for 0 millisats.
+    pub fn aggregate(event_id: EventId, events: &[Event], policy: AggregationPolicy) -> Self {
+        let mut interactions = Self {
+            event_id,
+            reactions: HashMap::new(),
+            zaps: 0,
+            zap_amount: 0,
+            zappers: HashMap::new(),
+        };
+
+        let mut reactions: Vec<&Event> = Vec::new();
+        let mut zaps: Vec<(XOnlyPublicKey, Timestamp, u64)> = Vec::new();
+        for event in events.iter() {
+            match event.kind {
+                Kind::Reaction if reaction_target(event) == Some(event_id) => reactions.push(event),
+                Kind::ZapReceipt if event_ids(event).contains(&event_id) => {
+                    if let Some(request) = zap_request(event) {
+                        zaps.push((request.pubkey, event.created_at, zap_amount(&request)));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if policy.reactions == ReactionDedup::LatestWins {
+            let mut latest: HashMap<XOnlyPublicKey, &Event> = HashMap::new();
+            for event in reactions.into_iter() {
+                let entry = latest.entry(event.pubkey).or_insert(event);
+                if event.created_at > entry.created_at {
+                    *entry = event;
+                }
+            }
+            reactions = latest.into_values().collect();
+        }
+        for event in reactions.into_iter() {
+            let content: &str = match event.content.as_str() {
+                "" => "+",
+                content => content,
+            };
+            *interactions
+                .reactions
+                .entry(content.to_string())
+                .or_default() += 1;
+        }
+
+        if policy.zaps == ZapDedup::LatestWins {
+            let mut latest: HashMap<XOnlyPublicKey, (Timestamp, u64)> = HashMap::new();
+            for (sender, created_at, amount) in zaps.into_iter() {
+                let entry = latest.entry(sender).or_insert((created_at, amount));
+                if created_at > entry.0 {
+                    *entry = (created_at, amount);
+                }
+            }
+            zaps = latest
+                .into_iter()
+                .map(|(sender, (created_at, amount))| (sender, created_at, amount))
+                .collect();
+        }
+        for (sender, _, amount) in zaps.iter() {
+            *interactions.zappers.entry(*sender).or_default() += amount;
+            interactions.zap_amount += amount;
+        }
+        interactions.zaps = match policy.zaps {
+            ZapDedup::Sum => interactions.zappers.len(),
+            ZapDedup::All | ZapDedup::LatestWins => zaps.len(),
+        };
+
+        interactions
+    }
+
+    /// Number of reactions with `content` (ex. `+`)
+    pub fn reactions_of(&self, content: &str) -> usize {
+        self.reactions.get(content).copied().unwrap_or_default()
+    }
+
+    /// Total number of reactions
+    pub fn total_reactions(&self) -> usize {
+        self.reactions.values().sum()
+    }
+}
+
+/// Filter of the reactions and zap receipts of `event_id`
+pub fn filter(event_id: EventId) -> SubscriptionFilter {
+    SubscriptionFilter::new()
+        .event(event_id)
+        .kinds(vec![Kind::Reaction, Kind::ZapReceipt])
+}
+
+impl Store {
+    /// Aggregate the reactions and zaps of `event_id` saved in the store
+    pub fn interactions(&self, event_id: EventId, policy: AggregationPolicy) -> Interactions {
+        let events: Vec<Event> = self.query(vec![filter(event_id)]);
+        Interactions::aggregate(event_id, &events, policy)
+    }
+}
+
+impl Client {
+    /// Get the reactions and zaps of `event_id` from relays and aggregate them
+    pub async fn get_interactions(
+        &self,
+        event_id: EventId,
+        policy: AggregationPolicy,
+    ) -> Result<Interactions, Error> {
+        let events: Vec<Event> = self.get_events_of(vec![filter(event_id)]).await?;
+        Ok(Interactions::aggregate(event_id, &events, policy))
+    }
+}
+
+pub(crate) fn event_ids(event: &Event) -> Vec<EventId> {
+    event
+        .tags
+        .iter()
+        .map(|t| t.as_vec())
+        .filter(|t| t.first().map(|k| k.as_str()) == Some("e"))
+        .filter_map(|t| EventId::from_hex(t.get(1)?).ok())
+        .collect()
+}
+
+/// Reacted event: the last `e` tag (NIP-25)
+fn reaction_target(event: &Event) -> Option<EventId> {
+    event_ids(event).pop()
+}
+
+/// Zap request in the `description` tag of zap receipt (NIP-57)
+pub(crate) fn zap_request(event: &Event) -> Option<Event> {
+    let description: String =
+        event
+            .tags
+            .iter()
+            .map(|t| t.as_vec())
+            .find_map(|t| match t.as_slice() {
+                [kind, description] if kind == "description" => Some(description.clone()),
+                _ => None,
+            })?;
+    let request: Event = Event::from_json(description).ok()?;
+    (request.kind == Kind::ZapRequest).then_some(request)
+}
+
+/// Amount in millisats of zap request
+fn zap_amount(request: &Event) -> u64 {
+    request
+        .tags
+        .iter()
+        .map(|t| t.as_vec())
+        .find_map(|t| match t.as_slice() {
+            [kind, amount, ..] if kind == "amount" => amount.parse().ok(),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
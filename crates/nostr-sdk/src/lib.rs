@@ -12,20 +12,77 @@ use tokio::runtime::Runtime;
 
 pub use nostr;
 pub use nostr::Result;
+pub use tokio_util::sync::CancellationToken;
 
+#[cfg(feature = "nip46")]
+pub mod bunker;
 pub mod client;
+pub mod crawler;
+pub mod discovery;
+pub mod display_name;
 #[cfg(feature = "all-nips")]
 mod doctest;
+pub mod follows;
+pub mod http;
+#[cfg(feature = "nip05")]
+pub mod impersonation;
+#[cfg(feature = "nip04")]
+pub mod inbox;
+pub mod interactions;
+pub mod lnurl;
+pub mod middleware;
+pub mod notification_center;
+pub mod policy;
 pub mod prelude;
 pub mod relay;
+#[cfg(feature = "scenario")]
+pub mod scenario;
+pub mod social_graph;
+pub mod store;
 pub mod subscription;
 mod thread;
+pub mod web_of_trust;
 
+#[cfg(feature = "nip46")]
+pub use self::bunker::RemoteSigner;
 #[cfg(feature = "blocking")]
 pub use self::client::blocking;
-pub use self::client::{Client, Options};
-pub use self::relay::pool::{RelayPool, RelayPoolNotification};
+#[cfg(feature = "encrypted-store")]
+pub use self::client::AccountImport;
+#[cfg(feature = "nip19")]
+pub use self::client::Resolved;
+pub use self::client::{
+    with_cancellation, Client, ClientBuilder, ConnectionStrategy, ImportReport, Options,
+    PropagationReport, RelaySet, Thread, ThreadNode, UserAgent,
+};
+pub use self::discovery::{RelayCandidate, RelayDiscovery};
+pub use self::display_name::DisplayName;
+pub use self::follows::{FollowNotification, FollowWatcher};
+#[cfg(feature = "nip05")]
+pub use self::impersonation::ImpersonationChecker;
+#[cfg(feature = "nip04")]
+pub use self::inbox::{Conversation, DirectMessage, DirectMessageKind, Inbox};
+pub use self::interactions::{AggregationPolicy, Interactions, ReactionDedup, ZapDedup};
+pub use self::middleware::{BoxFuture, Middleware, OutgoingHook};
+pub use self::notification_center::{Notification, NotificationCenter, NotificationKind};
+pub use self::policy::{AcceptAll, EventAction, EventPolicy, ImageProxy, QueryImageProxy};
+pub use self::relay::cache::QueryCacheOptions;
+pub use self::relay::pool::{Output, PoolStatus, RelayPool, RelayPoolNotification};
+pub use self::relay::probe::RelayProbeReport;
+pub use self::relay::queue::MessagePriority;
+pub use self::relay::recorder::{Direction, RecordedMessage, Recording};
+pub use self::relay::stats::{BandwidthBudget, BudgetPeriod, RelayStats, SentReq, Timeouts};
+pub use self::relay::trust::{RelayAction, RelayTrust, TrustLevel, TrustPolicy};
+pub use self::relay::verifier::VerificationStats;
 pub use self::relay::{Relay, RelayStatus};
+#[cfg(feature = "scenario")]
+pub use self::scenario::{Scenario, ScenarioRelay};
+pub use self::social_graph::SocialGraph;
+pub use self::store::audit::{AuditAction, AuditEntry};
+#[cfg(feature = "encrypted-store")]
+pub use self::store::{AccountBundle, StoreSecret};
+pub use self::store::{Store, StoreNotification};
+pub use self::web_of_trust::{TrustScore, TrustScorePolicy, TrustScorer, WebOfTrust};
 
 #[cfg(feature = "blocking")]
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().expect("Can't start Tokio runtime"));
@@ -0,0 +1,76 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! LNURL
+//!
+//! <https://github.com/lnurl/luds>
+
+use nostr::bech32::{self, FromBase32};
+use nostr::key::XOnlyPublicKey;
+use nostr::url::{self, Url};
+use serde::Deserialize;
+
+/// LNURL error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reqwest error
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// Bech32 error
+    #[error(transparent)]
+    Bech32(#[from] bech32::Error),
+    /// Url parse error
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    /// Invalid LNURL or lightning address
+    #[error("invalid LNURL")]
+    InvalidLnurl,
+}
+
+/// LNURL pay request (LUD-06)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PayRequest {
+    /// Callback URL
+    pub callback: Url,
+    /// Min amount in millisatoshis
+    #[serde(rename = "minSendable")]
+    pub min_sendable: u64,
+    /// Max amount in millisatoshis
+    #[serde(rename = "maxSendable")]
+    pub max_sendable: u64,
+    /// Metadata JSON string
+    pub metadata: String,
+    /// Zaps support (NIP-57)
+    #[serde(rename = "allowsNostr", default)]
+    pub allows_nostr: bool,
+    /// Public key used to sign zap receipts (NIP-57)
+    #[serde(rename = "nostrPubkey")]
+    pub nostr_pubkey: Option<XOnlyPublicKey>,
+}
+
+/// Get pay request URL from LNURL (`lud06`) or lightning address (`lud16`)
+pub fn pay_request_url(lud: &str) -> Result<Url, Error> {
+    let lud: &str = lud.trim();
+    if let Some((name, domain)) = lud.split_once('@') {
+        if name.is_empty() || domain.is_empty() {
+            return Err(Error::InvalidLnurl);
+        }
+        return Ok(Url::parse(&format!(
+            "https://{domain}/.well-known/lnurlp/{name}"
+        ))?);
+    }
+
+    let (hrp, data, _) = bech32::decode(lud)?;
+    if hrp != "lnurl" {
+        return Err(Error::InvalidLnurl);
+    }
+    let data: Vec<u8> = Vec::<u8>::from_base32(&data)?;
+    let url: String = String::from_utf8(data).map_err(|_| Error::InvalidLnurl)?;
+    Ok(Url::parse(&url)?)
+}
+
+/// Get LNURL pay request
+pub async fn get_pay_request(client: &reqwest::Client, lud: &str) -> Result<PayRequest, Error> {
+    let url: Url = pay_request_url(lud)?;
+    Ok(client.get(url).send().await?.json().await?)
+}
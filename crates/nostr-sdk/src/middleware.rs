@@ -0,0 +1,206 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Ingestion middlewares and outgoing hooks
+//!
+//! New events received from relays go through an ordered pipeline:
+//!
+//! 1. dedup: events already verified skip the verification and the stages after the policy,
+//!    only the relay is recorded (see [`RelayPoolNotification::SeenOn`](crate::RelayPoolNotification::SeenOn))
+//! 2. verify: signature check, once per event even if received from many relays
+//! 3. policy: [`EventPolicy`](crate::policy::EventPolicy) check of the verified event
+//! 4. custom middlewares, in insertion order
+//! 5. store: saved in the [`Store`](crate::Store), if any
+//! 6. notify: sent to listeners as [`RelayPoolNotification::Event`](crate::RelayPoolNotification::Event)
+//!
+//! The raw [`RelayPoolNotification::Message`](crate::RelayPoolNotification::Message) of an event is sent
+//! after the policy, before the middlewares: it's the message of the relay, not the processed event.
+//! Invalid or rejected events are never notified. The other messages of a relay (ex. `EOSE`) are
+//! notified after the events received before them.
+//!
+//! Events built by the client go through the [`OutgoingHook`]s, in insertion order, before signing.
+
+use std::fmt;
+
+pub use futures_util::future::BoxFuture;
+use nostr::url::Url;
+use nostr::{Event, UnsignedEvent};
+
+/// Ingestion middleware
+///
+/// Run on every new verified [`Event`], after the built-in checks and before the store.
+/// Middlewares run one event at a time, in a dedicated task: a slow middleware delays the next events.
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr_sdk::prelude::*;
+///
+/// #[derive(Debug)]
+/// struct DropLongNotes;
+///
+/// impl Middleware for DropLongNotes {
+///     fn process<'a>(&'a self, _relay_url: &'a Url, event: Event) -> BoxFuture<'a, Option<Event>> {
+///         Box::pin(async move { (event.content.len() <= 1000).then_some(event) })
+///     }
+/// }
+///
+/// let my_keys = Keys::generate();
+/// let client = ClientBuilder::new(&my_keys).middleware(DropLongNotes).build();
+/// ```
+pub trait Middleware: fmt::Debug + Send + Sync {
+    /// Process [`Event`] received from relay
+    ///
+    /// Return the event, optionally modified (ex. translated content), or `None` to drop it.
+    /// Modified events aren't verified again.
+    fn process<'a>(&'a self, relay_url: &'a Url, event: Event) -> BoxFuture<'a, Option<Event>>;
+}
+
+/// Outgoing event hook
+///
+/// Run on every event built and signed by the client (ex. text notes, reactions, contact list),
+/// to enforce organizational policies. Events already signed (ex. `Client::send_event`) don't go
+/// through the hooks.
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr_sdk::prelude::*;
+///
+/// /// Require a content warning on notes mentioning `spoiler`
+/// #[derive(Debug)]
+/// struct SpoilerWarning;
+///
+/// impl OutgoingHook for SpoilerWarning {
+///     fn process(&self, event: &mut UnsignedEvent) -> Result<(), String> {
+///         if event.kind == Kind::TextNote && event.content.contains("spoiler") {
+///             event.tags.push(Tag::ContentWarning { reason: None });
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let my_keys = Keys::generate();
+/// let client = ClientBuilder::new(&my_keys).outgoing_hook(SpoilerWarning).build();
+/// ```
+pub trait OutgoingHook: fmt::Debug + Send + Sync {
+    /// Check or modify [`UnsignedEvent`] before signing
+    ///
+    /// Kind, content and tags can be modified: the id, `created_at` and the public key are
+    /// set when signing. Return `Err` with the reason to veto the event.
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use nostr::{EventBuilder, Keys, RelayMessage, SubscriptionFilter, SubscriptionId};
+
+    use super::*;
+    use crate::relay::recorder::{Direction, RecordedMessage, Recording};
+    use crate::{RelayPool, RelayPoolNotification, Store};
+
+    /// Drop events with `drop` content and translate the ones with `translate` content
+    #[derive(Debug)]
+    struct Translate;
+
+    impl Middleware for Translate {
+        fn process<'a>(
+            &'a self,
+            _relay_url: &'a Url,
+            event: Event,
+        ) -> BoxFuture<'a, Option<Event>> {
+            Box::pin(async move {
+                match event.content.as_str() {
+                    "drop" => None,
+                    "translate" => Some(Event {
+                        content: String::from("translated"),
+                        ..event
+                    }),
+                    _ => Some(event),
+                }
+            })
+        }
+    }
+
+    /// Record the content of the processed events
+    #[derive(Debug, Default)]
+    struct Seen(Mutex<Vec<String>>);
+
+    impl Middleware for Seen {
+        fn process<'a>(
+            &'a self,
+            _relay_url: &'a Url,
+            event: Event,
+        ) -> BoxFuture<'a, Option<Event>> {
+            self.0.lock().unwrap().push(event.content.clone());
+            Box::pin(async move { Some(event) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_middlewares() {
+        let relay_url = Url::parse("wss://relay.example.com").unwrap();
+        let keys = Keys::generate();
+        let events: Vec<Event> = ["keep", "drop", "translate"]
+            .iter()
+            .map(|content| {
+                EventBuilder::new_text_note(*content, &[])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+        let id = SubscriptionId::new("sub");
+        let recording = Recording {
+            messages: events
+                .iter()
+                .map(|event| RecordedMessage {
+                    elapsed: Duration::ZERO,
+                    direction: Direction::Inbound,
+                    relay_url: relay_url.clone(),
+                    message: RelayMessage::new_event(id.clone(), event.clone()).as_json(),
+                })
+                .collect(),
+        };
+
+        let pool = RelayPool::new();
+        let store = Store::new();
+        let before = Arc::new(Seen::default());
+        let after = Arc::new(Seen::default());
+        pool.add_middleware(before.clone());
+        pool.add_middleware(Arc::new(Translate));
+        pool.add_middleware(after.clone());
+        pool.set_store(Some(store.clone()));
+        let mut notifications = pool.notifications();
+        assert_eq!(pool.replay(&recording).await, 3);
+
+        let mut notified: Vec<Event> = Vec::new();
+        while notified.len() < 2 {
+            let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let RelayPoolNotification::Event(url, event) = notification {
+                assert_eq!(url, relay_url);
+                notified.push(event);
+            }
+        }
+
+        // Run in insertion order: the dropped event doesn't reach the next middlewares
+        assert_eq!(*before.0.lock().unwrap(), vec!["keep", "drop", "translate"]);
+        assert_eq!(*after.0.lock().unwrap(), vec!["keep", "translated"]);
+
+        // Modified events are stored and notified, dropped events aren't
+        let contents = |events: Vec<Event>| -> Vec<String> {
+            let mut contents: Vec<String> = events.into_iter().map(|e| e.content).collect();
+            contents.sort();
+            contents
+        };
+        assert_eq!(contents(notified), vec!["keep", "translated"]);
+        assert_eq!(
+            contents(store.query(vec![SubscriptionFilter::new()])),
+            vec!["keep", "translated"]
+        );
+        assert_eq!(store.event_by_id(&events[1].id), None);
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Notification center
+//!
+//! Classify the events addressed to the client public key (mentions, replies, reactions,
+//! zaps and new followers), with read state persisted in the store.
+
+use std::collections::HashSet;
+
+use nostr::event::TagKind;
+use nostr::key::XOnlyPublicKey;
+use nostr::{Event, EventId, Keys, Kind, SubscriptionFilter, Tag, Timestamp};
+
+use crate::client::{Client, Error};
+use crate::interactions::{event_ids, zap_request};
+use crate::relay::pool::Output;
+use crate::store::Store;
+
+/// Identifier of the read state event (NIP-78)
+const READ_STATE_ID: &str = "nostr-sdk/notifications/read";
+/// Read state tag: `["read_until", <timestamp>]`
+const READ_UNTIL: &str = "read_until";
+/// Read state tag: `["read", <event-id>]`
+const READ: &str = "read";
+
+/// Notification kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    /// Text note mentioning me
+    Mention,
+    /// Reply to one of my notes
+    Reply,
+    /// Reaction to one of my notes (NIP-25)
+    Reaction,
+    /// Zap to me (NIP-57)
+    Zap,
+    /// Contact list following me
+    NewFollower,
+}
+
+/// Notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Event id
+    pub id: EventId,
+    /// Kind
+    pub kind: NotificationKind,
+    /// Author (the zap sender for zaps)
+    pub author: XOnlyPublicKey,
+    /// My event replied, reacted or zapped, if any
+    pub target: Option<EventId>,
+    /// Timestamp
+    pub created_at: Timestamp,
+    /// Read
+    pub read: bool,
+    /// Event
+    pub event: Event,
+}
+
+#[derive(Debug, Default)]
+struct ReadState {
+    until: Option<Timestamp>,
+    ids: HashSet<EventId>,
+}
+
+impl ReadState {
+    fn is_read(&self, event: &Event) -> bool {
+        self.until.map_or(false, |until| event.created_at <= until) || self.ids.contains(&event.id)
+    }
+}
+
+/// Notification center
+///
+/// Notifications are computed from the events saved in the client [`Store`], so a store is required.
+/// The read state is persisted in the store as a NIP-78 event signed with the client keys, never sent to relays.
+///
+/// Replies are detected only if the replied note is in the store: otherwise they are reported as mentions.
+/// Relays keep only the latest contact list of every user, so the time of a follow is the time of the
+/// latest contact list update of the follower.
+#[derive(Debug, Clone)]
+pub struct NotificationCenter {
+    client: Client,
+}
+
+impl NotificationCenter {
+    /// New [`NotificationCenter`] for the client public key
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+        }
+    }
+
+    fn public_key(&self) -> XOnlyPublicKey {
+        self.client.keys().public_key()
+    }
+
+    fn store(&self) -> Result<Store, Error> {
+        self.client.store().ok_or(Error::StoreNotConfigured)
+    }
+
+    /// Filters of the events addressed to the client public key
+    pub fn filters(&self, since: Option<Timestamp>) -> Vec<SubscriptionFilter> {
+        let mut filter = SubscriptionFilter::new()
+            .kinds(vec![
+                Kind::TextNote,
+                Kind::Reaction,
+                Kind::ZapReceipt,
+                Kind::ContactList,
+            ])
+            .pubkey(self.public_key());
+        if let Some(since) = since {
+            filter = filter.since(since);
+        }
+        vec![filter]
+    }
+
+    /// Fetch the events addressed to the client public key and save them in the store
+    ///
+    /// Return the number of new events.
+    pub async fn sync(&self) -> Result<usize, Error> {
+        let store: Store = self.store()?;
+        let events: Vec<Event> = self.client.get_events_of(self.filters(None)).await?;
+        Ok(events
+            .into_iter()
+            .filter(|event| store.save_event(event.clone()))
+            .count())
+    }
+
+    /// Subscribe to new events addressed to the client public key
+    ///
+    /// Replace the current [`Client`] subscription: merge [`NotificationCenter::filters`] with your
+    /// filters and call [`Client::subscribe`] to keep both. Received events are saved in the store by the client.
+    pub async fn subscribe(&self) -> Result<Output<()>, Error> {
+        self.client
+            .subscribe(self.filters(Some(Timestamp::now())))
+            .await
+    }
+
+    /// Classify [`Event`]
+    ///
+    /// Return `None` if the event is not a notification for the client public key.
+    pub fn classify(&self, event: &Event) -> Result<Option<Notification>, Error> {
+        let store: Store = self.store()?;
+        let read_state: ReadState = self.read_state(&store);
+        Ok(self.notification(&store, &read_state, event))
+    }
+
+    /// Get notifications, from the newest
+    pub fn feed(&self) -> Result<Vec<Notification>, Error> {
+        let store: Store = self.store()?;
+        let read_state: ReadState = self.read_state(&store);
+        Ok(store
+            .query(self.filters(None))
+            .iter()
+            .filter_map(|event| self.notification(&store, &read_state, event))
+            .collect())
+    }
+
+    /// Number of unread notifications
+    pub fn unread_count(&self) -> Result<usize, Error> {
+        Ok(self.feed()?.iter().filter(|n| !n.read).count())
+    }
+
+    /// Mark notification as read
+    pub fn mark_read(&self, id: EventId) -> Result<(), Error> {
+        let store: Store = self.store()?;
+        let mut read_state: ReadState = self.read_state(&store);
+        read_state.ids.insert(id);
+        self.save_read_state(&store, &read_state)
+    }
+
+    /// Mark all the notifications as read
+    pub fn mark_all_read(&self) -> Result<(), Error> {
+        let store: Store = self.store()?;
+        let newest: Option<Timestamp> = store
+            .query(self.filters(None))
+            .iter()
+            .map(|event| event.created_at)
+            .max();
+        let read_state = ReadState {
+            until: Some(newest.map_or_else(Timestamp::now, |newest| newest.max(Timestamp::now()))),
+            ids: HashSet::new(),
+        };
+        self.save_read_state(&store, &read_state)
+    }
+
+    fn notification(
+        &self,
+        store: &Store,
+        read_state: &ReadState,
+        event: &Event,
+    ) -> Option<Notification> {
+        let public_key: XOnlyPublicKey = self.public_key();
+        if event.pubkey == public_key || !event.mentioned_pubkeys().contains(&public_key) {
+            return None;
+        }
+
+        let mut author: XOnlyPublicKey = event.pubkey;
+        let (kind, target) = match event.kind {
+            Kind::TextNote => {
+                let parent: Option<EventId> = event.reply_to_id().filter(|id| {
+                    store
+                        .event_by_id(id)
+                        .map_or(false, |parent| parent.pubkey == public_key)
+                });
+                match parent {
+                    Some(parent) => (NotificationKind::Reply, Some(parent)),
+                    None => (NotificationKind::Mention, None),
+                }
+            }
+            // The reacted event is the last `e` tag
+            Kind::Reaction => (NotificationKind::Reaction, event_ids(event).pop()),
+            Kind::ZapReceipt => {
+                if let Some(sender) = zap_request(event).map(|request| request.pubkey) {
+                    author = sender;
+                }
+                (NotificationKind::Zap, event_ids(event).first().copied())
+            }
+            Kind::ContactList => (NotificationKind::NewFollower, None),
+            _ => return None,
+        };
+
+        Some(Notification {
+            id: event.id,
+            kind,
+            author,
+            target,
+            created_at: event.created_at,
+            read: read_state.is_read(event),
+            event: event.clone(),
+        })
+    }
+
+    fn read_state(&self, store: &Store) -> ReadState {
+        let mut read_state = ReadState::default();
+        if let Some(event) = store.app_data(self.public_key(), READ_STATE_ID) {
+            for tag in event.tags.iter().map(|t| t.as_vec()) {
+                match tag.as_slice() {
+                    [kind, timestamp] if kind == READ_UNTIL => {
+                        read_state.until = timestamp.parse::<u64>().ok().map(Timestamp::from);
+                    }
+                    [kind, id] if kind == READ => {
+                        if let Ok(id) = EventId::from_hex(id) {
+                            read_state.ids.insert(id);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        read_state
+    }
+
+    fn save_read_state(&self, store: &Store, read_state: &ReadState) -> Result<(), Error> {
+        let keys: Keys = self.client.keys();
+        let mut tags: Vec<Tag> = Vec::new();
+        if let Some(until) = read_state.until {
+            tags.push(Tag::Generic(
+                TagKind::Custom(READ_UNTIL.to_string()),
+                vec![until.to_string()],
+            ));
+        }
+        tags.extend(
+            read_state
+                .ids
+                .iter()
+                .map(|id| Tag::Generic(TagKind::Custom(READ.to_string()), vec![id.to_hex()])),
+        );
+        store.save_app_data(&keys, READ_STATE_ID, tags)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Event policy
+
+use std::fmt;
+
+use nostr::url::Url;
+use nostr::Event;
+
+/// Action to take on a received [`Event`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventAction {
+    /// Deliver the event
+    #[default]
+    Accept,
+    /// Deliver the event, after the accepted ones when events are returned in bulk
+    Downrank,
+    /// Drop the event
+    Reject,
+}
+
+/// Event policy
+///
+/// Checked for every event received from relays, before it's sent to notification listeners or returned by queries.
+pub trait EventPolicy: fmt::Debug + Send + Sync {
+    /// Check [`Event`] received from relay
+    fn check(&self, relay_url: &Url, event: &Event) -> EventAction;
+}
+
+/// Rewrite of image urls (ex. through an image proxy), applied by `Client::sanitize_metadata`
+pub trait ImageProxy: fmt::Debug + Send + Sync {
+    /// Rewrite image url
+    fn rewrite(&self, url: &Url) -> Url;
+}
+
+/// [`ImageProxy`] passing the original url as query parameter of `endpoint`
+/// (ex. `https://proxy.example.com/image?url=<original url>`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryImageProxy {
+    endpoint: Url,
+    param: String,
+}
+
+impl QueryImageProxy {
+    /// New [`QueryImageProxy`]
+    pub fn new<S>(endpoint: Url, param: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            endpoint,
+            param: param.into(),
+        }
+    }
+}
+
+impl ImageProxy for QueryImageProxy {
+    fn rewrite(&self, url: &Url) -> Url {
+        let mut proxied: Url = self.endpoint.clone();
+        proxied
+            .query_pairs_mut()
+            .append_pair(&self.param, url.as_str());
+        proxied
+    }
+}
+
+/// Policy accepting all events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptAll;
+
+impl EventPolicy for AcceptAll {
+    fn check(&self, _relay_url: &Url, _event: &Event) -> EventAction {
+        EventAction::Accept
+    }
+}
@@ -3,12 +3,15 @@
 
 //! Prelude
 
+#![allow(unknown_lints, ambiguous_glob_reexports)]
+
 // External crates
 pub use nostr::prelude::*;
 
 // Internal modules
 pub use crate::client::*;
 pub use crate::relay::pool::*;
+pub use crate::relay::trust::*;
 pub use crate::relay::*;
 pub use crate::subscription::*;
 pub use crate::*;
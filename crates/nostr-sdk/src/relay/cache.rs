@@ -0,0 +1,91 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay query cache
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nostr::{Event, SubscriptionFilter};
+use tokio::sync::Mutex;
+
+/// Query cache options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryCacheOptions {
+    /// Time to live of the cached results
+    pub ttl: Duration,
+    /// Max number of cached queries per relay
+    pub capacity: usize,
+}
+
+impl QueryCacheOptions {
+    /// New [`QueryCacheOptions`]
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    filters: Vec<SubscriptionFilter>,
+    events: Vec<Event>,
+    cached_at: Instant,
+}
+
+/// Cache of the complete (`EOSE` received) results of the queries sent to a relay
+///
+/// Results are dropped when expired or when an event matching their filters is received or sent.
+#[derive(Debug, Clone)]
+pub(crate) struct QueryCache {
+    opts: QueryCacheOptions,
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+}
+
+impl QueryCache {
+    pub fn new(opts: QueryCacheOptions) -> Self {
+        Self {
+            opts,
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Get cached events of `filters`, if not expired
+    pub async fn get(&self, filters: &[SubscriptionFilter]) -> Option<Vec<Event>> {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|entry| entry.cached_at.elapsed() < self.opts.ttl);
+        entries
+            .iter()
+            .find(|entry| entry.filters == filters)
+            .map(|entry| entry.events.clone())
+    }
+
+    /// Cache events of `filters`, evicting the oldest query if the cache is full
+    pub async fn insert(&self, filters: Vec<SubscriptionFilter>, events: Vec<Event>) {
+        if self.opts.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().await;
+        entries.retain(|entry| entry.filters != filters);
+        while entries.len() >= self.opts.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            filters,
+            events,
+            cached_at: Instant::now(),
+        });
+    }
+
+    /// Drop the results of the queries matching `event`
+    pub async fn invalidate(&self, event: &Event) {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|entry| !entry.filters.iter().any(|f| f.match_event(event)));
+    }
+
+    /// Drop all results
+    pub async fn clear(&self) {
+        let mut entries = self.entries.lock().await;
+        entries.clear();
+    }
+}
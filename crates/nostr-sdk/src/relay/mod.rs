@@ -3,32 +3,54 @@
 
 //! Relay
 
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 
 use futures_util::{SinkExt, StreamExt};
-use nostr::nips::nip11::RelayInformationDocument;
-use nostr::{ClientMessage, Event, RelayMessage, SubscriptionFilter, SubscriptionId, Url};
+use nostr::nips::nip11::{CacheValidators, RelayInformationDocument};
+use nostr::{
+    ClientMessage, Event, EventId, RelayMessage, SubscriptionFilter, SubscriptionId,
+    SubscriptionIdGenerator, Timestamp, Url,
+};
 use tokio::sync::broadcast;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
+pub mod cache;
 mod net;
 pub mod pool;
+pub mod probe;
+pub mod queue;
+pub mod recorder;
+pub mod stats;
+pub mod trust;
+pub mod verifier;
 
+use self::cache::{QueryCache, QueryCacheOptions};
 use self::net::Message as WsMessage;
 use self::pool::RelayPoolMessage;
 use self::pool::SUBSCRIPTION;
-use crate::thread;
+use self::queue::{MessagePriority, Queue, QueueReceiver};
+use self::recorder::SharedRecorder;
+use self::stats::{Bandwidth, BandwidthBudget, Latencies, RelayStats, ReqLog, SentReq, Timeouts};
 use crate::RelayPoolNotification;
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
+use crate::{http, thread};
 
 type Message = (RelayEvent, Option<oneshot::Sender<bool>>);
 
+/// Relays may never send `OK` messages (NIP-20), so events pending for longer than the `OK` timeout are dropped
+const MAX_PENDING_OK: usize = 1000;
+/// Max interval between stale subscription checks
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
 /// [`Relay`] error
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -81,9 +103,11 @@ impl fmt::Display for RelayStatus {
 pub enum RelayEvent {
     /// Send [`ClientMessage`]
     SendMsg(Box<ClientMessage>),
+    /// Notify when all the previous queued events are processed
+    Flush,
     // Ping,
-    /// Close
-    Close,
+    /// Close connection, with the reason
+    Close(Option<String>),
     /// Completly disconnect
     Terminate,
 }
@@ -95,13 +119,32 @@ pub struct Relay {
     proxy: Option<SocketAddr>,
     status: Arc<Mutex<RelayStatus>>,
     document: Arc<Mutex<RelayInformationDocument>>,
+    /// `None` until the first document is fetched
+    document_validators: Arc<Mutex<Option<CacheValidators>>>,
+    /// Refresh interval of the document, in seconds (0 = disabled)
+    document_refresh: Arc<AtomicU64>,
+    /// Honor the proxy environment variables when no proxy is set
+    system_proxy: Arc<AtomicBool>,
     scheduled_for_termination: Arc<Mutex<bool>>,
     pool_sender: Sender<RelayPoolMessage>,
-    relay_sender: Sender<Message>,
-    relay_receiver: Arc<Mutex<Receiver<Message>>>,
+    relay_sender: Queue,
+    relay_receiver: Arc<Mutex<QueueReceiver>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
+    pending_ok: Arc<Mutex<HashMap<EventId, Instant>>>,
+    latencies: Arc<Mutex<Latencies>>,
+    bandwidth: Arc<Mutex<Bandwidth>>,
+    req_log: Arc<Mutex<ReqLog>>,
+    subscription_activity: Arc<Mutex<HashMap<SubscriptionId, Instant>>>,
+    timeouts: Timeouts,
+    user_agent: Option<String>,
+    query_cache: Option<QueryCache>,
+    auto_narrowing: Option<usize>,
+    subscription_ids: SharedSubscriptionIds,
+    recorder: SharedRecorder,
 }
 
+pub(crate) type SharedSubscriptionIds = Arc<RwLock<SubscriptionIdGenerator>>;
+
 impl Relay {
     /// Create new `Relay`
     pub fn new(
@@ -110,21 +153,101 @@ impl Relay {
         notification_sender: broadcast::Sender<RelayPoolNotification>,
         proxy: Option<SocketAddr>,
     ) -> Self {
-        let (relay_sender, relay_receiver) = mpsc::channel::<Message>(1024);
+        Self::with_opts(
+            url,
+            pool_sender,
+            notification_sender,
+            proxy,
+            Timeouts::default(),
+            None,
+            MessagePriority::Low,
+        )
+    }
+
+    pub(crate) fn with_opts(
+        url: Url,
+        pool_sender: Sender<RelayPoolMessage>,
+        notification_sender: broadcast::Sender<RelayPoolNotification>,
+        proxy: Option<SocketAddr>,
+        timeouts: Timeouts,
+        user_agent: Option<String>,
+        req_priority: MessagePriority,
+    ) -> Self {
+        let (relay_sender, relay_receiver) = queue::channel(req_priority);
 
         Self {
             url,
             proxy,
             status: Arc::new(Mutex::new(RelayStatus::Initialized)),
             document: Arc::new(Mutex::new(RelayInformationDocument::new())),
+            document_validators: Arc::new(Mutex::new(None)),
+            document_refresh: Arc::new(AtomicU64::new(0)),
+            system_proxy: Arc::new(AtomicBool::new(true)),
             scheduled_for_termination: Arc::new(Mutex::new(false)),
             pool_sender,
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
             notification_sender,
+            pending_ok: Arc::new(Mutex::new(HashMap::new())),
+            latencies: Arc::new(Mutex::new(Latencies::default())),
+            bandwidth: Arc::new(Mutex::new(Bandwidth::default())),
+            req_log: Arc::new(Mutex::new(ReqLog::default())),
+            subscription_activity: Arc::new(Mutex::new(HashMap::new())),
+            timeouts,
+            user_agent,
+            query_cache: None,
+            auto_narrowing: None,
+            subscription_ids: Arc::new(RwLock::new(SubscriptionIdGenerator::default())),
+            recorder: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn with_query_cache(self, opts: Option<QueryCacheOptions>) -> Self {
+        Self {
+            query_cache: opts.map(QueryCache::new),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_auto_narrowing(self, max_requests: Option<usize>) -> Self {
+        Self {
+            auto_narrowing: max_requests,
+            ..self
         }
     }
 
+    pub(crate) fn with_subscription_ids(self, subscription_ids: SharedSubscriptionIds) -> Self {
+        Self {
+            subscription_ids,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_recorder(self, recorder: SharedRecorder) -> Self {
+        Self { recorder, ..self }
+    }
+
+    pub(crate) fn with_document_refresh(self, document_refresh: Arc<AtomicU64>) -> Self {
+        Self {
+            document_refresh,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_system_proxy(self, system_proxy: Arc<AtomicBool>) -> Self {
+        Self {
+            system_proxy,
+            ..self
+        }
+    }
+
+    fn subscription_id_generator(&self) -> SubscriptionIdGenerator {
+        self.subscription_ids
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
     /// Get relay url
     pub fn url(&self) -> Url {
         self.url.clone()
@@ -147,9 +270,19 @@ impl Relay {
         RUNTIME.block_on(async { self.status().await })
     }
 
-    async fn set_status(&self, status: RelayStatus) {
+    /// Update [`RelayStatus`] and notify the change
+    async fn set_status(&self, status: RelayStatus, reason: Option<String>) {
         let mut s = self.status.lock().await;
-        *s = status;
+        if *s != status {
+            *s = status.clone();
+            let _ = self
+                .notification_sender
+                .send(RelayPoolNotification::RelayStatus {
+                    url: self.url(),
+                    status,
+                    reason,
+                });
+        }
     }
 
     /// Get [`RelayInformationDocument`]
@@ -164,9 +297,144 @@ impl Relay {
         RUNTIME.block_on(async { self.document().await })
     }
 
-    async fn set_document(&self, document: RelayInformationDocument) {
-        let mut d = self.document.lock().await;
-        *d = document;
+    /// Fetch [`RelayInformationDocument`], if modified since the last fetch
+    ///
+    /// Notify [`RelayPoolNotification::RelayDocumentChanged`] if supported NIPs, limitations or fees changed.
+    pub async fn refresh_document(&self) {
+        let system_proxy: bool = self.system_proxy.load(Ordering::SeqCst);
+        let client = match http::build_client(self.proxy(), None, system_proxy) {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("Impossible to build HTTP client: {}", e);
+                return;
+            }
+        };
+        let validators: Option<CacheValidators> = self.document_validators.lock().await.clone();
+        let result = RelayInformationDocument::get_if_modified_with_client(
+            &client,
+            self.url(),
+            &validators.clone().unwrap_or_default(),
+        )
+        .await;
+        match result {
+            Ok(Some((document, new_validators))) => {
+                *self.document_validators.lock().await = Some(new_validators);
+                let old: RelayInformationDocument = {
+                    let mut d = self.document.lock().await;
+                    std::mem::replace(&mut *d, document.clone())
+                };
+                if validators.is_some() && old.capabilities_changed(&document) {
+                    log::info!("Information document of {} changed", self.url);
+                    let _ = self.notification_sender.send(
+                        RelayPoolNotification::RelayDocumentChanged {
+                            url: self.url(),
+                            old: Box::new(old),
+                            new: Box::new(document),
+                        },
+                    );
+                }
+            }
+            Ok(None) => log::debug!("Information document of {} not modified", self.url),
+            Err(e) => log::error!(
+                "Impossible to get information document from {}: {}",
+                self.url,
+                e
+            ),
+        }
+    }
+
+    fn document_refresh_interval(&self) -> Option<Duration> {
+        match self.document_refresh.load(Ordering::SeqCst) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Drop the cached query results, if the query cache is enabled
+    pub async fn clear_query_cache(&self) {
+        if let Some(cache) = &self.query_cache {
+            cache.clear().await;
+        }
+    }
+
+    async fn invalidate_query_cache(&self, event: &Event) {
+        if let Some(cache) = &self.query_cache {
+            cache.invalidate(event).await;
+        }
+    }
+
+    /// Get number of messages waiting in the outbound queue
+    pub fn queue(&self) -> usize {
+        self.relay_sender.len()
+    }
+
+    /// Get sent events still waiting for an `OK` message from relay, with the time they were sent
+    pub async fn pending_ok(&self) -> HashMap<EventId, Instant> {
+        let pending_ok = self.pending_ok.lock().await;
+        pending_ok.clone()
+    }
+
+    async fn add_pending_ok(&self, event_id: EventId) {
+        let ok_timeout: Duration = self.stats().await.ok_timeout;
+        let mut pending_ok = self.pending_ok.lock().await;
+        if pending_ok.len() >= MAX_PENDING_OK {
+            pending_ok.retain(|_, sent_at| sent_at.elapsed() < ok_timeout);
+        }
+        pending_ok.insert(event_id, Instant::now());
+    }
+
+    async fn remove_pending_ok(&self, event_id: &EventId) {
+        let mut pending_ok = self.pending_ok.lock().await;
+        if let Some(sent_at) = pending_ok.remove(event_id) {
+            let mut latencies = self.latencies.lock().await;
+            latencies.ok.record(sent_at.elapsed());
+        }
+    }
+
+    /// Get [`RelayStats`]: measured latencies, current timeouts and bandwidth usage
+    pub async fn stats(&self) -> RelayStats {
+        let bandwidth = *self.bandwidth.lock().await;
+        let latencies = self.latencies.lock().await;
+        latencies.stats(&self.timeouts, &bandwidth)
+    }
+
+    /// Get [`RelayStats`]: measured latencies, current timeouts and bandwidth usage
+    #[cfg(feature = "blocking")]
+    pub fn stats_blocking(&self) -> RelayStats {
+        RUNTIME.block_on(async { self.stats().await })
+    }
+
+    /// Set [`BandwidthBudget`] (`None` to remove it)
+    ///
+    /// Bytes transferred before this call are not counted in the budget.
+    pub async fn set_bandwidth_budget(&self, budget: Option<BandwidthBudget>) {
+        let mut bandwidth = self.bandwidth.lock().await;
+        bandwidth.set_budget(budget);
+    }
+
+    /// Check if the [`BandwidthBudget`] of the current period is exhausted
+    pub async fn is_over_budget(&self) -> bool {
+        let bandwidth = self.bandwidth.lock().await;
+        bandwidth.is_over_budget()
+    }
+
+    /// Get the latest REQs sent to the relay (oldest first)
+    ///
+    /// Only the REQs actually written to the WebSocket are logged.
+    pub async fn sent_reqs(&self) -> Vec<SentReq> {
+        let req_log = self.req_log.lock().await;
+        req_log.entries()
+    }
+
+    /// Get the latest REQs sent to the relay (oldest first)
+    #[cfg(feature = "blocking")]
+    pub fn sent_reqs_blocking(&self) -> Vec<SentReq> {
+        RUNTIME.block_on(async { self.sent_reqs().await })
+    }
+
+    async fn record_eose_latency(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().await;
+        latencies.eose.record(latency);
     }
 
     async fn is_scheduled_for_termination(&self) -> bool {
@@ -186,14 +454,14 @@ impl Relay {
                 self.try_connect().await
             } else {
                 // Update relay status
-                self.set_status(RelayStatus::Disconnected).await;
+                self.set_status(RelayStatus::Disconnected, None).await;
             }
 
             let relay = self.clone();
             thread::spawn(async move {
                 loop {
                     log::debug!(
-                        "{} channel capacity: {}",
+                        "{} channel capacity (high, normal, low): {:?}",
                         relay.url(),
                         relay.relay_sender.capacity()
                     );
@@ -201,7 +469,7 @@ impl Relay {
                     // Schedule relay for termination
                     // Needed to terminate the auto reconnect loop, also if the relay is not connected yet.
                     if relay.is_scheduled_for_termination().await {
-                        relay.set_status(RelayStatus::Terminated).await;
+                        relay.set_status(RelayStatus::Terminated, None).await;
                         relay.schedule_for_termination(false).await;
                         log::debug!("Auto connect loop terminated for {}", relay.url);
                         break;
@@ -220,6 +488,21 @@ impl Relay {
                     tokio::time::sleep(Duration::from_secs(20)).await;
                 }
             });
+
+            if self.document_refresh_interval().is_some() {
+                let relay = self.clone();
+                thread::spawn(async move {
+                    while let Some(interval) = relay.document_refresh_interval() {
+                        tokio::time::sleep(interval).await;
+                        match relay.status().await {
+                            RelayStatus::Connected => relay.refresh_document().await,
+                            RelayStatus::Terminated => break,
+                            _ => (),
+                        }
+                    }
+                    log::debug!("Document refresh loop terminated for {}", relay.url);
+                });
+            }
         }
     }
 
@@ -227,26 +510,26 @@ impl Relay {
         let url: String = self.url.to_string();
 
         // Set RelayStatus to `Connecting`
-        self.set_status(RelayStatus::Connecting).await;
+        self.set_status(RelayStatus::Connecting, None).await;
         log::debug!("Connecting to {}", url);
 
         // Request `RelayInformationDocument`
         let relay = self.clone();
-        thread::spawn(async move {
-            match RelayInformationDocument::get(relay.url(), relay.proxy()).await {
-                Ok(document) => relay.set_document(document).await,
-                Err(e) => log::error!(
-                    "Impossible to get information document from {}: {}",
-                    relay.url,
-                    e
-                ),
-            };
-        });
+        thread::spawn(async move { relay.refresh_document().await });
 
         // Connect
-        match net::get_connection(&self.url, self.proxy, None).await {
+        let system_proxy: bool = self.system_proxy.load(Ordering::SeqCst);
+        let connection = net::get_connection(
+            &self.url,
+            self.proxy,
+            None,
+            self.user_agent.as_deref(),
+            system_proxy,
+        )
+        .await;
+        match connection {
             Ok((mut ws_tx, mut ws_rx)) => {
-                self.set_status(RelayStatus::Connected).await;
+                self.set_status(RelayStatus::Connected, None).await;
                 log::info!("Connected to {}", url);
 
                 let relay = self.clone();
@@ -256,13 +539,11 @@ impl Relay {
                     while let Some((relay_event, oneshot_sender)) = rx.recv().await {
                         match relay_event {
                             RelayEvent::SendMsg(msg) => {
-                                log::trace!("Sending message {}", msg.as_json());
-                                if let Err(e) = ws_tx.send(WsMessage::Text(msg.as_json())).await {
-                                    log::error!(
-                                        "Impossible to send msg to {}: {}",
-                                        relay.url(),
-                                        e.to_string()
-                                    );
+                                let json: String = msg.as_json();
+                                log::trace!("Sending message {}", json);
+                                let len: usize = json.len();
+                                if let Err(e) = ws_tx.send(WsMessage::Text(json)).await {
+                                    log::error!("Impossible to send msg to {}: {}", relay.url(), e);
                                     if let Some(sender) = oneshot_sender {
                                         if let Err(e) = sender.send(false) {
                                             log::error!("Impossible to send oneshot msg: {}", e);
@@ -270,6 +551,36 @@ impl Relay {
                                     }
                                     break;
                                 };
+                                relay.bandwidth.lock().await.record_sent(len);
+                                recorder::record_outbound(&relay.recorder, &relay.url, &msg);
+                                match *msg {
+                                    ClientMessage::Event(ref event) => {
+                                        relay.add_pending_ok(event.id).await;
+                                        relay.invalidate_query_cache(event).await;
+                                    }
+                                    ClientMessage::Req {
+                                        ref subscription_id,
+                                        ref filters,
+                                    } => {
+                                        let req =
+                                            SentReq::new(subscription_id.clone(), filters.clone());
+                                        log::debug!(
+                                            "Sent REQ {} to {}: {}",
+                                            subscription_id,
+                                            relay.url,
+                                            req.explain()
+                                        );
+                                        relay.req_log.lock().await.record(req);
+                                    }
+                                    _ => (),
+                                }
+                                if let Some(sender) = oneshot_sender {
+                                    if let Err(e) = sender.send(true) {
+                                        log::error!("Impossible to send oneshot msg: {}", e);
+                                    }
+                                }
+                            }
+                            RelayEvent::Flush => {
                                 if let Some(sender) = oneshot_sender {
                                     if let Err(e) = sender.send(true) {
                                         log::error!("Impossible to send oneshot msg: {}", e);
@@ -282,9 +593,9 @@ impl Relay {
                                     break;
                                 }
                             } */
-                            RelayEvent::Close => {
+                            RelayEvent::Close(reason) => {
                                 let _ = ws_tx.close().await;
-                                relay.set_status(RelayStatus::Disconnected).await;
+                                relay.set_status(RelayStatus::Disconnected, reason).await;
                                 log::info!("Disconnected from {}", url);
                                 break;
                             }
@@ -294,12 +605,12 @@ impl Relay {
                                     log::error!(
                                         "Impossible to unsubscribe from {}: {}",
                                         relay.url(),
-                                        e.to_string()
+                                        e
                                     )
                                 }
                                 // Close stream
                                 let _ = ws_tx.close().await;
-                                relay.set_status(RelayStatus::Terminated).await;
+                                relay.set_status(RelayStatus::Terminated, None).await;
                                 relay.schedule_for_termination(false).await;
                                 log::info!("Completely disconnected from {}", url);
                                 break;
@@ -308,49 +619,110 @@ impl Relay {
                     }
                 });
 
+                // Stop the stale subscription checks when the message thread exits
+                let connection = CancellationToken::new();
+                let connection_guard = connection.clone().drop_guard();
+
                 let relay = self.clone();
                 thread::spawn(async move {
                     log::debug!("Relay Message Thread Started");
+                    let _connection_guard = connection_guard;
+                    let mut reason = String::from("connection closed by relay");
                     while let Some(msg_res) = ws_rx.next().await {
-                        if let Ok(msg) = msg_res {
-                            let data: Vec<u8> = msg.into_data();
-
-                            match String::from_utf8(data) {
-                                Ok(data) => match RelayMessage::from_json(&data) {
-                                    Ok(msg) => {
-                                        log::trace!("Received message to {}: {:?}", relay.url, msg);
-                                        if let Err(err) = relay
-                                            .pool_sender
-                                            .send(RelayPoolMessage::ReceivedMsg {
-                                                relay_url: relay.url(),
-                                                msg,
-                                            })
-                                            .await
-                                        {
-                                            log::error!(
-                                                "Impossible to send ReceivedMsg to pool: {}",
-                                                &err
+                        match msg_res {
+                            Ok(msg) => {
+                                let data: Vec<u8> = msg.into_data();
+                                relay.bandwidth.lock().await.record_received(data.len());
+
+                                match String::from_utf8(data) {
+                                    Ok(data) => match RelayMessage::from_json(&data) {
+                                        Ok(msg) => {
+                                            log::trace!(
+                                                "Received message to {}: {:?}",
+                                                relay.url,
+                                                msg
                                             );
-                                        };
-                                    }
-                                    Err(err) => {
-                                        log::error!("{}: {}", err, data);
-                                    }
-                                },
-                                Err(err) => log::error!("{}", err),
+                                            recorder::record_inbound(
+                                                &relay.recorder,
+                                                &relay.url,
+                                                &msg,
+                                            );
+                                            match &msg {
+                                                RelayMessage::Ok { event_id, .. } => {
+                                                    relay.remove_pending_ok(event_id).await;
+                                                }
+                                                RelayMessage::Event {
+                                                    subscription_id,
+                                                    event,
+                                                } => {
+                                                    relay.invalidate_query_cache(event).await;
+                                                    relay
+                                                        .record_subscription_activity(
+                                                            subscription_id,
+                                                        )
+                                                        .await;
+                                                }
+                                                RelayMessage::EndOfStoredEvents(
+                                                    subscription_id,
+                                                ) => {
+                                                    relay
+                                                        .record_subscription_activity(
+                                                            subscription_id,
+                                                        )
+                                                        .await;
+                                                }
+                                                _ => (),
+                                            }
+                                            if let Err(err) = relay
+                                                .pool_sender
+                                                .send(RelayPoolMessage::ReceivedMsg {
+                                                    relay_url: relay.url(),
+                                                    msg,
+                                                })
+                                                .await
+                                            {
+                                                log::error!(
+                                                    "Impossible to send ReceivedMsg to pool: {}",
+                                                    &err
+                                                );
+                                            };
+                                        }
+                                        Err(err) => {
+                                            log::error!("{}: {}", err, data);
+                                        }
+                                    },
+                                    Err(err) => log::error!("{}", err),
+                                }
                             }
+                            Err(e) => reason = e.to_string(),
                         }
                     }
 
                     log::debug!("Exited from Message Thread of {}", relay.url);
 
                     if relay.status().await != RelayStatus::Terminated {
-                        if let Err(err) = relay.disconnect().await {
+                        if let Err(err) = relay.disconnect(Some(reason)).await {
                             log::error!("Impossible to disconnect {}: {}", relay.url, err);
                         }
                     }
                 });
 
+                let relay = self.clone();
+                thread::spawn(async move {
+                    log::debug!("Relay Stale Subscriptions Thread Started");
+                    loop {
+                        let interval: Duration = relay
+                            .timeouts
+                            .stale_subscription_timeout()
+                            .map_or(STALE_CHECK_INTERVAL, |t| (t / 2).min(STALE_CHECK_INTERVAL));
+                        tokio::select! {
+                            _ = connection.cancelled() => break,
+                            _ = tokio::time::sleep(interval) => relay.refresh_stale_subscriptions().await,
+                        }
+                    }
+                    log::debug!("Exited from Stale Subscriptions Thread of {}", relay.url);
+                });
+
                 // Ping thread
                 /* let relay = self.clone();
                 thread::spawn(async move {
@@ -382,16 +754,13 @@ impl Relay {
 
                 // Subscribe to relay
                 if let Err(e) = self.subscribe(false).await {
-                    log::error!(
-                        "Impossible to subscribe to {}: {}",
-                        self.url(),
-                        e.to_string()
-                    )
+                    log::error!("Impossible to subscribe to {}: {}", self.url(), e)
                 }
             }
             Err(err) => {
-                self.set_status(RelayStatus::Disconnected).await;
                 log::error!("Impossible to connect to {}: {}", url, err);
+                self.set_status(RelayStatus::Disconnected, Some(err.to_string()))
+                    .await;
             }
         };
     }
@@ -407,16 +776,17 @@ impl Relay {
             .map_err(|_| Error::ChannelTimeout)
     }
 
-    /// Ping relay
+    // Ping relay
     /* async fn ping(&self) -> Result<(), Error> {
         self.send_relay_event(RelayEvent::Ping).await
     } */
 
     /// Disconnect from relay and set status to 'Disconnected'
-    async fn disconnect(&self) -> Result<(), Error> {
+    async fn disconnect(&self, reason: Option<String>) -> Result<(), Error> {
         let status = self.status().await;
         if status.ne(&RelayStatus::Disconnected) && status.ne(&RelayStatus::Terminated) {
-            self.send_relay_event(RelayEvent::Close, None).await?;
+            self.send_relay_event(RelayEvent::Close(reason), None)
+                .await?;
         }
         Ok(())
     }
@@ -431,6 +801,22 @@ impl Relay {
         Ok(())
     }
 
+    /// Wait until all the messages queued before this call are transmitted to relay
+    ///
+    /// Messages stay queued while the relay is disconnected, so this will return [`Error::Timeout`]
+    /// if the relay doesn't reconnect within `timeout`.
+    pub async fn flush(&self, timeout: Duration) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel::<bool>();
+        let flush = async {
+            self.send_relay_event(RelayEvent::Flush, Some(tx)).await?;
+            rx.await.map_err(|_| Error::OneShotRecvError)
+        };
+        match tokio::time::timeout(timeout, flush).await {
+            Ok(result) => result.map(|_| ()),
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+
     /// Send msg to relay
     ///
     /// if `wait` arg is true, this method will wait for the msg to be sent
@@ -461,13 +847,17 @@ impl Relay {
     /// Subscribe
     pub async fn subscribe(&self, wait: bool) -> Result<SubscriptionId, Error> {
         let mut subscription = SUBSCRIPTION.lock().await;
-        let channel = subscription.get_channel(&self.url());
+        let channel = subscription.get_channel(&self.url(), &self.subscription_id_generator());
         let channel_id = channel.id();
-        self.send_msg(
-            ClientMessage::new_req(channel_id.clone(), subscription.get_filters()),
-            wait,
-        )
-        .await?;
+        let filters: Vec<SubscriptionFilter> = subscription.get_filters();
+        let empty: bool = filters.is_empty();
+        self.send_msg(ClientMessage::new_req(channel_id.clone(), filters), wait)
+            .await?;
+        let mut activity = self.subscription_activity.lock().await;
+        activity.clear();
+        if !empty {
+            activity.insert(channel_id.clone(), Instant::now());
+        }
         Ok(channel_id)
     }
 
@@ -475,26 +865,134 @@ impl Relay {
     pub async fn unsubscribe(&self, wait: bool) -> Result<(), Error> {
         let mut subscription = SUBSCRIPTION.lock().await;
         if let Some(channel) = subscription.remove_channel(&self.url()) {
+            self.subscription_activity
+                .lock()
+                .await
+                .remove(&channel.id());
             self.send_msg(ClientMessage::close(channel.id()), wait)
                 .await?;
         }
         Ok(())
     }
 
+    pub(crate) async fn has_active_subscription(&self) -> bool {
+        !self.subscription_activity.lock().await.is_empty()
+    }
+
+    async fn record_subscription_activity(&self, subscription_id: &SubscriptionId) {
+        let mut activity = self.subscription_activity.lock().await;
+        if let Some(last) = activity.get_mut(subscription_id) {
+            *last = Instant::now();
+        }
+    }
+
+    /// Re-issue the `REQ` of the subscriptions silent for longer than the stale subscription timeout
+    async fn refresh_stale_subscriptions(&self) {
+        let timeout: Duration = match self.timeouts.stale_subscription_timeout() {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        if self.status().await != RelayStatus::Connected {
+            return;
+        }
+
+        let stale: Vec<(SubscriptionId, Duration)> = {
+            let activity = self.subscription_activity.lock().await;
+            activity
+                .iter()
+                .map(|(id, last)| (id.clone(), last.elapsed()))
+                .filter(|(_, silent_for)| *silent_for >= timeout)
+                .collect()
+        };
+        for (subscription_id, silent_for) in stale.into_iter() {
+            log::warn!(
+                "Subscription {} silent on {} for {:?}: re-issuing REQ",
+                subscription_id,
+                self.url,
+                silent_for
+            );
+            let _ = self
+                .notification_sender
+                .send(RelayPoolNotification::StaleSubscription {
+                    url: self.url(),
+                    subscription_id,
+                    silent_for,
+                });
+            if let Err(e) = self.subscribe(false).await {
+                log::error!("Impossible to re-subscribe to {}: {}", self.url, e);
+            }
+        }
+    }
+
     /// Get events of filters
     pub async fn get_events_of(
         &self,
         filters: Vec<SubscriptionFilter>,
         timeout: Duration,
     ) -> Result<Vec<Event>, Error> {
+        let (mut events, mut eose) = self.fetch_events(filters.clone(), timeout).await?;
+        if eose {
+            eose = self
+                .narrow_truncated(&filters, &mut events, timeout)
+                .await?;
+        }
+        if eose {
+            Ok(events)
+        } else {
+            Err(Error::Timeout)
+        }
+    }
+
+    /// Get events of filters, waiting for `EOSE` up to the relay `EOSE` timeout
+    ///
+    /// Return the events received before the timeout.
+    pub(crate) async fn get_events_of_with_eose_timeout(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<Vec<Event>, Error> {
+        let timeout: Duration = self.stats().await.eose_timeout;
+        let (mut events, mut eose) = self.fetch_events(filters.clone(), timeout).await?;
+        if eose {
+            eose = self
+                .narrow_truncated(&filters, &mut events, timeout)
+                .await?;
+        }
+        if !eose {
+            log::warn!(
+                "Timeout while waiting EOSE from {} ({} events received)",
+                self.url,
+                events.len()
+            );
+        }
+        Ok(events)
+    }
+
+    /// Get events of filters, return `true` if `EOSE` was received before `timeout`
+    ///
+    /// Complete results are served from the query cache, if enabled.
+    async fn fetch_events(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Duration,
+    ) -> Result<(Vec<Event>, bool), Error> {
+        if let Some(cache) = &self.query_cache {
+            if let Some(events) = cache.get(&filters).await {
+                log::debug!("Query served from cache of {}", self.url);
+                return Ok((events, true));
+            }
+        }
+
         let mut events: Vec<Event> = Vec::new();
 
-        let id = SubscriptionId::generate();
+        let id = self.subscription_id_generator().generate(&filters);
 
+        let mut notifications = self.notification_sender.subscribe();
+        let start = Instant::now();
+        // Closed also if cancelled while waiting
+        let subscription = CloseOnDrop::new(self.clone(), id.clone());
         self.send_msg(ClientMessage::new_req(id.clone(), filters.clone()), false)
             .await?;
 
-        let mut notifications = self.notification_sender.subscribe();
         let recv = async {
             while let Ok(notification) = notifications.recv().await {
                 if let RelayPoolNotification::Message(_, msg) = notification {
@@ -502,15 +1000,13 @@ impl Relay {
                         RelayMessage::Event {
                             subscription_id,
                             event,
-                        } => {
-                            if subscription_id == id {
-                                events.push(event.as_ref().clone());
-                            }
+                        } if subscription_id == id => {
+                            events.push(event.as_ref().clone());
                         }
-                        RelayMessage::EndOfStoredEvents(subscription_id) => {
-                            if subscription_id == id {
-                                break;
-                            }
+                        RelayMessage::EndOfStoredEvents(subscription_id)
+                            if subscription_id == id =>
+                        {
+                            break;
                         }
                         _ => (),
                     };
@@ -518,35 +1014,124 @@ impl Relay {
             }
         };
 
-        if tokio::time::timeout(timeout, recv).await.is_err() {
-            return Err(Error::Timeout);
+        let eose: bool = tokio::time::timeout(timeout, recv).await.is_ok();
+        if eose {
+            self.record_eose_latency(start.elapsed()).await;
         }
 
         // Unsubscribe
-        self.send_msg(ClientMessage::close(id), false).await?;
+        subscription.close().await?;
 
-        Ok(events)
+        if eose {
+            if let Some(cache) = &self.query_cache {
+                cache.insert(filters, events.clone()).await;
+            }
+        }
+
+        Ok((events, eose))
+    }
+
+    /// Complete the results of the filters truncated by their `limit`, if auto-narrowing is enabled
+    ///
+    /// A filter with `since` and `limit` is truncated when the relay returns `limit` events: it's
+    /// queried again with `until` tightened to the oldest event received, until `since` is reached,
+    /// the relay returns less than `limit` events or the max number of follow-up `REQ`s is reached.
+    ///
+    /// Return `false` if a follow-up `REQ` timed out.
+    async fn narrow_truncated(
+        &self,
+        filters: &[SubscriptionFilter],
+        events: &mut Vec<Event>,
+        timeout: Duration,
+    ) -> Result<bool, Error> {
+        let max_requests: usize = match self.auto_narrowing {
+            Some(max_requests) => max_requests,
+            None => return Ok(true),
+        };
+
+        let mut ids: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+        for filter in filters.iter() {
+            let (limit, since) = match (filter.limit, filter.since) {
+                (Some(limit), Some(since)) if limit > 0 => (limit, since),
+                _ => continue,
+            };
+
+            let mut received: usize = 0;
+            let mut oldest: Option<Timestamp> = None;
+            for event in events.iter().filter(|e| filter.match_event(e)) {
+                received += 1;
+                oldest = Some(oldest.map_or(event.created_at, |o| o.min(event.created_at)));
+            }
+
+            let mut requests: usize = 0;
+            while received >= limit {
+                // Events at `until` are requested again, since others may share the same timestamp
+                let until: Timestamp = match oldest {
+                    Some(oldest) if oldest > since => oldest,
+                    _ => break,
+                };
+
+                if requests >= max_requests {
+                    log::warn!(
+                        "Results of {} still truncated after {} follow-up requests",
+                        self.url,
+                        requests
+                    );
+                    break;
+                }
+                requests += 1;
+
+                let (batch, eose) = self
+                    .fetch_events(vec![filter.clone().until(until)], timeout)
+                    .await?;
+                if !eose {
+                    return Ok(false);
+                }
+
+                received = batch.len();
+                let mut new_events: usize = 0;
+                for event in batch.into_iter() {
+                    oldest = Some(oldest.map_or(event.created_at, |o| o.min(event.created_at)));
+                    if ids.insert(event.id) {
+                        events.push(event);
+                        new_events += 1;
+                    }
+                }
+
+                // More than `limit` events with the same timestamp: impossible to narrow further
+                if new_events == 0 {
+                    break;
+                }
+            }
+
+            if requests > 0 {
+                log::debug!(
+                    "Truncated results of {} completed with {} follow-up requests",
+                    self.url,
+                    requests
+                );
+            }
+        }
+
+        Ok(true)
     }
 
     /// Request events of filter. All events will be sent to notification listener
     pub fn req_events_of(&self, filters: Vec<SubscriptionFilter>, timeout: Duration) {
         let relay = self.clone();
         thread::spawn(async move {
-            let id = SubscriptionId::generate();
+            let id = relay.subscription_id_generator().generate(&filters);
 
             // Subscribe
+            let mut notifications = relay.notification_sender.subscribe();
+            let start = Instant::now();
             if let Err(e) = relay
                 .send_msg(ClientMessage::new_req(id.clone(), filters.clone()), false)
                 .await
             {
-                log::error!(
-                    "Impossible to send REQ to {}: {}",
-                    relay.url(),
-                    e.to_string()
-                );
+                log::error!("Impossible to send REQ to {}: {}", relay.url(), e);
             };
 
-            let mut notifications = relay.notification_sender.subscribe();
             let recv = async {
                 while let Ok(notification) = notifications.recv().await {
                     if let RelayPoolNotification::Message(
@@ -561,8 +1146,9 @@ impl Relay {
                 }
             };
 
-            if let Err(e) = tokio::time::timeout(timeout, recv).await {
-                log::error!("{e}");
+            match tokio::time::timeout(timeout, recv).await {
+                Ok(_) => relay.record_eose_latency(start.elapsed()).await,
+                Err(e) => log::error!("{e}"),
             }
 
             // Unsubscribe
@@ -570,9 +1156,44 @@ impl Relay {
                 log::error!(
                     "Impossible to close subscription with {}: {}",
                     relay.url(),
-                    e.to_string()
+                    e
                 );
             }
         });
     }
 }
+
+/// Subscription closed on relay when dropped (ex. when the request is cancelled)
+struct CloseOnDrop {
+    relay: Relay,
+    id: Option<SubscriptionId>,
+}
+
+impl CloseOnDrop {
+    fn new(relay: Relay, id: SubscriptionId) -> Self {
+        Self {
+            relay,
+            id: Some(id),
+        }
+    }
+
+    async fn close(mut self) -> Result<(), Error> {
+        match self.id.take() {
+            Some(id) => self.relay.send_msg(ClientMessage::close(id), false).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let relay = self.relay.clone();
+            thread::spawn(async move {
+                if let Err(e) = relay.send_msg(ClientMessage::close(id), false).await {
+                    log::error!("Impossible to close subscription on {}: {}", relay.url(), e);
+                }
+            });
+        }
+    }
+}
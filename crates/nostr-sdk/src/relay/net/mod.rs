@@ -14,6 +14,9 @@ use tokio::net::TcpStream;
 use tokio_rustls::client::TlsStream;
 use tokio_rustls::rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
 use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, InvalidHeaderValue, USER_AGENT};
 use tokio_tungstenite::tungstenite::Error as WsError;
 pub use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
@@ -23,6 +26,7 @@ type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 type Stream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
 mod socks;
+mod system_proxy;
 
 use self::socks::TpcSocks5Stream;
 
@@ -45,41 +49,107 @@ pub enum Error {
     /// Url parse error
     #[error("impossible to parse URL: {0}")]
     Url(#[from] nostr::url::ParseError),
+    /// Invalid header value
+    #[error("invalid header value: {0}")]
+    InvalidHeaderValue(#[from] InvalidHeaderValue),
+    /// Proxy error
+    #[error("proxy error: {0}")]
+    Proxy(String),
 }
 
+/// Connect to relay
+///
+/// Without `proxy`, the proxy of the environment variables is used if `system_proxy` is enabled
+/// (see [`system_proxy`]).
 pub(crate) async fn get_connection(
     url: &Url,
     proxy: Option<SocketAddr>,
     timeout: Option<Duration>,
+    user_agent: Option<&str>,
+    system_proxy: bool,
 ) -> Result<(Sink, Stream), Error> {
-    let stream = match proxy {
-        Some(proxy) => connect_proxy(url, proxy, timeout).await?,
-        None => connect_direct(url, timeout).await?,
+    let mut request: Request = url.as_str().into_client_request()?;
+    if let Some(user_agent) = user_agent {
+        request
+            .headers_mut()
+            .insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+    }
+    let system_proxy: Option<Url> = match proxy {
+        None if system_proxy => system_proxy::from_env(url),
+        _ => None,
+    };
+    let stream = match (proxy, system_proxy) {
+        (Some(proxy), _) => connect_proxy(url, request, proxy, timeout).await?,
+        (None, Some(proxy)) => connect_system_proxy(url, request, &proxy, timeout).await?,
+        (None, None) => connect_direct(request, timeout).await?,
     };
     Ok(stream.split())
 }
 
-async fn connect_direct(url: &Url, timeout: Option<Duration>) -> Result<WebSocket, Error> {
+async fn connect_direct(request: Request, timeout: Option<Duration>) -> Result<WebSocket, Error> {
     let timeout = timeout.unwrap_or(Duration::from_secs(60));
-    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(url))
+    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(request))
         .await
         .map_err(|_| Error::Timeout)??;
     Ok(stream)
 }
 
+/// Get `host:port` of relay
+fn target_addr(url: &Url) -> Result<String, ParseError> {
+    match url.host_str() {
+        Some(host) => match url.port_or_known_default() {
+            Some(port) => Ok(format!("{host}:{port}")),
+            None => Err(ParseError::EmptyHost),
+        },
+        None => Err(ParseError::InvalidPort),
+    }
+}
+
+async fn connect_system_proxy(
+    url: &Url,
+    request: Request,
+    proxy: &Url,
+    timeout: Option<Duration>,
+) -> Result<WebSocket, Error> {
+    log::debug!("Connecting to {} through system proxy", url);
+    match proxy.scheme() {
+        "socks5" | "socks5h" => {
+            let host: &str = proxy
+                .host_str()
+                .ok_or_else(|| Error::Proxy(String::from("missing proxy host")))?;
+            let port: u16 = proxy.port_or_known_default().unwrap_or(1080);
+            let addr: SocketAddr = tokio::net::lookup_host((host, port))
+                .await?
+                .next()
+                .ok_or_else(|| Error::Proxy(format!("impossible to resolve {host}")))?;
+            connect_proxy(url, request, addr, timeout).await
+        }
+        _ => {
+            let timeout = timeout.unwrap_or(Duration::from_secs(60));
+            let connect = async {
+                let conn = system_proxy::http_connect(proxy, &target_addr(url)?).await?;
+                let conn = match url.scheme() {
+                    "wss" => MaybeTlsStream::Rustls(connect_with_tls(conn, url).await?),
+                    _ => MaybeTlsStream::Plain(conn),
+                };
+                let (stream, _) = tokio_tungstenite::client_async(request, conn).await?;
+                Ok(stream)
+            };
+            tokio::time::timeout(timeout, connect)
+                .await
+                .map_err(|_| Error::Timeout)?
+        }
+    }
+}
+
 async fn connect_proxy(
     url: &Url,
+    request: Request,
     proxy: SocketAddr,
     timeout: Option<Duration>,
 ) -> Result<WebSocket, Error> {
     let timeout = timeout.unwrap_or(Duration::from_secs(60));
-    let addr: String = match url.host_str() {
-        Some(host) => match url.port_or_known_default() {
-            Some(port) => format!("{host}:{port}"),
-            None => return Err(Error::Url(ParseError::EmptyHost)),
-        },
-        None => return Err(Error::Url(ParseError::InvalidPort)),
-    };
+    let addr: String = target_addr(url)?;
 
     let conn = TpcSocks5Stream::connect(proxy, addr.clone()).await?;
     let conn = match connect_with_tls(conn, url).await {
@@ -90,7 +160,7 @@ async fn connect_proxy(
         }
     };
 
-    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::client_async(url, conn))
+    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::client_async(request, conn))
         .await
         .map_err(|_| Error::Timeout)??;
     Ok(stream)
@@ -0,0 +1,104 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! System proxy
+//!
+//! Proxy of the standard environment variables (uppercase or lowercase): `HTTPS_PROXY` for `wss` relays,
+//! `HTTP_PROXY` for `ws` relays, `ALL_PROXY` as fallback and `NO_PROXY` for the excluded hosts.
+//! Supported proxy schemes: `http` (`CONNECT` tunnel), `socks5` and `socks5h`.
+
+use std::env;
+
+use nostr::url::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::Error;
+
+/// Max size of the `CONNECT` response head
+const MAX_RESPONSE_HEAD: usize = 8192;
+
+fn var(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        env::var(name)
+            .ok()
+            .or_else(|| env::var(name.to_lowercase()).ok())
+            .filter(|value| !value.trim().is_empty())
+    })
+}
+
+/// Check if `host` is excluded by `NO_PROXY` (`*`, exact hosts or domain suffixes, comma separated)
+fn is_excluded(host: &str) -> bool {
+    match var(&["NO_PROXY"]) {
+        Some(no_proxy) => no_proxy.split(',').map(str::trim).any(|entry| {
+            let entry = entry.trim_start_matches('.');
+            entry == "*"
+                || (!entry.is_empty() && (host == entry || host.ends_with(&format!(".{entry}"))))
+        }),
+        None => false,
+    }
+}
+
+/// Proxy of relay `url` from the environment, if any
+pub(crate) fn from_env(url: &Url) -> Option<Url> {
+    let host: &str = url.host_str()?;
+    if is_excluded(host) {
+        return None;
+    }
+    let proxy: String = match url.scheme() {
+        "wss" => var(&["HTTPS_PROXY", "ALL_PROXY"])?,
+        _ => var(&["HTTP_PROXY", "ALL_PROXY"])?,
+    };
+    let proxy: String = if proxy.contains("://") {
+        proxy
+    } else {
+        format!("http://{proxy}")
+    };
+    match Url::parse(&proxy) {
+        Ok(proxy) => match proxy.scheme() {
+            "http" | "socks5" | "socks5h" => Some(proxy),
+            scheme => {
+                log::warn!("Unsupported proxy scheme for {}: {}", url, scheme);
+                None
+            }
+        },
+        Err(e) => {
+            log::warn!("Invalid proxy for {}: {}", url, e);
+            None
+        }
+    }
+}
+
+/// Open a tunnel to `addr` (`host:port`) with a HTTP `CONNECT` request to `proxy`
+pub(crate) async fn http_connect(proxy: &Url, addr: &str) -> Result<TcpStream, Error> {
+    if !proxy.username().is_empty() || proxy.password().is_some() {
+        return Err(Error::Proxy(String::from(
+            "proxy credentials are not supported",
+        )));
+    }
+    let host: &str = proxy
+        .host_str()
+        .ok_or_else(|| Error::Proxy(String::from("missing proxy host")))?;
+    let port: u16 = proxy.port_or_known_default().unwrap_or(80);
+
+    let mut stream = TcpStream::connect((host, port)).await?;
+    let request = format!("CONNECT {addr} HTTP/1.1\r\nHost: {addr}\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response head byte by byte, to not consume the data of the tunnel
+    let mut head: Vec<u8> = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD {
+            return Err(Error::Proxy(String::from("response head too large")));
+        }
+        let byte: u8 = stream.read_u8().await?;
+        head.push(byte);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status_line: &str = head.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(stream),
+        _ => Err(Error::Proxy(format!("tunnel refused: {status_line}"))),
+    }
+}
@@ -3,19 +3,38 @@
 
 //! Relay Pool
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 use std::time::Duration;
 
+use futures_util::future;
+use nostr::nips::nip11::RelayInformationDocument;
+use nostr::secp256k1::rand::seq::SliceRandom;
+use nostr::secp256k1::rand::{self, Rng};
+use nostr::types::relay_url;
 use nostr::url::Url;
-use nostr::{ClientMessage, Event, EventId, RelayMessage, SubscriptionFilter, SubscriptionId};
+use nostr::{
+    ClientMessage, Event, EventId, RelayMessage, SubscriptionFilter, SubscriptionId,
+    SubscriptionIdGenerator,
+};
 use once_cell::sync::Lazy;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::{broadcast, Mutex};
 use tokio::time;
 
-use super::{Error as RelayError, Relay};
+use super::cache::QueryCacheOptions;
+use super::queue::MessagePriority;
+use super::recorder::{self, Recorder, Recording, SharedRecorder};
+use super::stats::{EventRate, SentReq, Timeouts};
+use super::verifier::{Counters, VerificationStats, Verified, Verifier};
+use super::{Error as RelayError, Relay, RelayStatus, SharedSubscriptionIds};
+use crate::middleware::Middleware;
+use crate::policy::{AcceptAll, EventAction, EventPolicy};
+use crate::store::Store;
 use crate::subscription::Subscription;
 use crate::thread;
 
@@ -34,6 +53,9 @@ pub enum Error {
     /// Relay not found
     #[error("relay not found")]
     RelayNotFound,
+    /// Recorder error
+    #[error("recorder error: {0}")]
+    Recorder(#[from] recorder::Error),
 }
 
 /// Relay Pool Message
@@ -46,6 +68,13 @@ pub enum RelayPoolMessage {
         /// Relay message
         msg: RelayMessage,
     },
+    /// Message replayed from a [`Recording`]
+    ReplayedMsg {
+        /// Relay url
+        relay_url: Url,
+        /// Relay message
+        msg: RelayMessage,
+    },
     /// Event sent
     EventSent(Box<Event>),
     /// Shutdown
@@ -59,84 +88,552 @@ pub enum RelayPoolNotification {
     Event(Url, Event),
     /// Received a [`RelayMessage`]
     Message(Url, RelayMessage),
+    /// Already received [`Event`] seen on another relay
+    SeenOn(Url, EventId),
+    /// [`RelayStatus`] changed
+    RelayStatus {
+        /// Relay url
+        url: Url,
+        /// New status
+        status: RelayStatus,
+        /// Reason of the change, if known (ex. why the relay disconnected)
+        reason: Option<String>,
+    },
+    /// Supported NIPs, limitations or fees of the relay information document (NIP-11) changed
+    RelayDocumentChanged {
+        /// Relay url
+        url: Url,
+        /// Previous document
+        old: Box<RelayInformationDocument>,
+        /// New document
+        new: Box<RelayInformationDocument>,
+    },
+    /// Subscription silent while the relay is connected, `REQ` re-issued
+    StaleSubscription {
+        /// Relay url
+        url: Url,
+        /// Subscription id
+        subscription_id: SubscriptionId,
+        /// Time since the last event or `EOSE`
+        silent_for: Duration,
+    },
     /// Shutdown
     Shutdown,
 }
 
+type SeenOn = Arc<Mutex<HashMap<EventId, HashSet<Url>>>>;
+type SharedEventPolicy = Arc<RwLock<Arc<dyn EventPolicy>>>;
+type SharedMiddlewares = Arc<RwLock<Vec<Arc<dyn Middleware>>>>;
+type SharedStore = Arc<RwLock<Option<Store>>>;
+
+fn get_event_policy(policy: &SharedEventPolicy) -> Arc<dyn EventPolicy> {
+    policy
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Event received from a relay, waiting for the verification
+struct Received {
+    relay_url: Url,
+    subscription_id: SubscriptionId,
+    event: Box<Event>,
+    /// Position in the messages of the relay
+    seq: u64,
+}
+
+/// Event being verified
+struct Verifying {
+    subscription_id: SubscriptionId,
+    seq: u64,
+    /// Copies of the same event received while verifying (ex. from other relays)
+    waiting: Vec<Received>,
+}
+
+/// Messages of a relay waiting for the events received before them
+///
+/// Events are notified once verified, so the other messages (ex. `EOSE`) are delayed
+/// to keep them after the events they refer to.
+#[derive(Default)]
+struct RelayQueue {
+    next_seq: u64,
+    /// Events not verified yet
+    verifying: BTreeSet<u64>,
+    deferred: VecDeque<(u64, RelayMessage)>,
+}
+
+impl RelayQueue {
+    fn next_seq(&mut self) -> u64 {
+        let seq: u64 = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    /// Remove the messages no longer waiting for an event
+    fn pop_ready(&mut self) -> Vec<RelayMessage> {
+        let first_verifying: Option<u64> = self.verifying.iter().next().copied();
+        let mut ready: Vec<RelayMessage> = Vec::new();
+        while let Some((seq, _)) = self.deferred.front() {
+            if first_verifying.map_or(false, |first| first < *seq) {
+                break;
+            }
+            if let Some((_, msg)) = self.deferred.pop_front() {
+                ready.push(msg);
+            }
+        }
+        ready
+    }
+}
+
 struct RelayPoolTask {
     receiver: Receiver<RelayPoolMessage>,
+    verifier: Verifier,
+    verified_receiver: Receiver<Verified>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
     events: VecDeque<EventId>,
+    verifying: HashMap<EventId, Verifying>,
+    queues: HashMap<Url, RelayQueue>,
+    seen_on: SeenOn,
+    event_policy: SharedEventPolicy,
+    event_rate: Arc<Mutex<EventRate>>,
+    ingestion_sender: Sender<(Url, Box<Event>)>,
+}
+
+/// Last stages of the ingestion pipeline (middlewares, store and notify), see [`crate::middleware`]
+struct IngestionTask {
+    receiver: Receiver<(Url, Box<Event>)>,
+    middlewares: SharedMiddlewares,
+    store: SharedStore,
+    notification_sender: broadcast::Sender<RelayPoolNotification>,
+}
+
+impl IngestionTask {
+    async fn run(&mut self) {
+        log::debug!("IngestionTask Thread Started");
+        while let Some((relay_url, event)) = self.receiver.recv().await {
+            if let Some(event) = self.apply_middlewares(&relay_url, *event).await {
+                let store: Option<Store> = self
+                    .store
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone();
+                if let Some(store) = store {
+                    store.save_event(event.clone());
+                }
+                let _ = self
+                    .notification_sender
+                    .send(RelayPoolNotification::Event(relay_url, event));
+            }
+        }
+        log::debug!("Exited from IngestionTask thread");
+    }
+
+    async fn apply_middlewares(&self, relay_url: &Url, mut event: Event) -> Option<Event> {
+        let middlewares: Vec<Arc<dyn Middleware>> = self
+            .middlewares
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for middleware in middlewares.iter() {
+            let id: EventId = event.id;
+            match middleware.process(relay_url, event).await {
+                Some(processed) => event = processed,
+                None => {
+                    log::debug!(
+                        "Event {} from {} dropped by {:?}",
+                        id,
+                        relay_url,
+                        middleware
+                    );
+                    return None;
+                }
+            }
+        }
+        Some(event)
+    }
 }
 
 const MAX_EVENTS: usize = 100000;
+/// Capacity of the notification channel
+const NOTIFICATION_CAPACITY: usize = 1024;
 
 impl RelayPoolTask {
     pub fn new(
         pool_task_receiver: Receiver<RelayPoolMessage>,
         notification_sender: broadcast::Sender<RelayPoolNotification>,
+        seen_on: SeenOn,
+        event_policy: SharedEventPolicy,
+        verification: Arc<Counters>,
+        event_rate: Arc<Mutex<EventRate>>,
+        ingestion_sender: Sender<(Url, Box<Event>)>,
     ) -> Self {
+        let (verified_sender, verified_receiver) = mpsc::channel(1024);
         Self {
             receiver: pool_task_receiver,
+            verifier: Verifier::new(verified_sender, verification),
+            verified_receiver,
             events: VecDeque::new(),
+            verifying: HashMap::new(),
+            queues: HashMap::new(),
             notification_sender,
+            seen_on,
+            event_policy,
+            event_rate,
+            ingestion_sender,
         }
     }
 
     pub async fn run(&mut self) {
         log::debug!("RelayPoolTask Thread Started");
-        while let Some(msg) = self.receiver.recv().await {
-            match msg {
-                RelayPoolMessage::ReceivedMsg { relay_url, msg } => {
-                    let _ = self
-                        .notification_sender
-                        .send(RelayPoolNotification::Message(
-                            relay_url.clone(),
-                            msg.clone(),
-                        ));
-
-                    if let RelayMessage::Event {
-                        subscription_id: _,
-                        event,
-                    } = msg
-                    {
-                        //Verifies if the event is valid
-                        if event.verify().is_ok() {
-                            //Adds only new events
-                            if !self.events.contains(&event.id) {
-                                self.add_event(event.id);
-                                let notification =
-                                    RelayPoolNotification::Event(relay_url, event.as_ref().clone());
-
-                                let _ = self.notification_sender.send(notification);
-                            }
+        loop {
+            tokio::select! {
+                msg = self.receiver.recv() => match msg {
+                    Some(msg) => {
+                        if !self.handle_message(msg).await {
+                            break;
                         }
                     }
+                    None => break,
+                },
+                Some((relay_url, event, valid)) = self.verified_receiver.recv() => {
+                    self.handle_verification(relay_url, event, valid).await;
                 }
-                RelayPoolMessage::EventSent(event) => {
-                    self.add_event(event.id);
+            }
+        }
+    }
+
+    /// Return `false` on shutdown
+    async fn handle_message(&mut self, msg: RelayPoolMessage) -> bool {
+        match msg {
+            RelayPoolMessage::ReceivedMsg { relay_url, msg } => {
+                self.handle_received_msg(relay_url, msg, false).await;
+            }
+            RelayPoolMessage::ReplayedMsg { relay_url, msg } => {
+                self.handle_received_msg(relay_url, msg, true).await;
+            }
+            RelayPoolMessage::EventSent(event) => {
+                self.add_event(event.id).await;
+            }
+            RelayPoolMessage::Shutdown => {
+                if let Err(e) = self
+                    .notification_sender
+                    .send(RelayPoolNotification::Shutdown)
+                {
+                    log::error!("Impossible to send shutdown notification: {}", e);
                 }
-                RelayPoolMessage::Shutdown => {
-                    if let Err(e) = self
+                log::debug!("Exited from RelayPoolTask thread");
+                self.receiver.close();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Ingestion of relay message: dedup, verify, policy and notify of events, see [`crate::middleware`]
+    ///
+    /// The other messages are notified after the events received before them.
+    /// Replayed events are verified inline, to keep the recording order.
+    async fn handle_received_msg(&mut self, relay_url: Url, msg: RelayMessage, replay: bool) {
+        let queue: &mut RelayQueue = self.queues.entry(relay_url.clone()).or_default();
+        let seq: u64 = queue.next_seq();
+        match msg {
+            RelayMessage::Event {
+                subscription_id,
+                event,
+            } => {
+                queue.verifying.insert(seq);
+                let received = Received {
+                    relay_url,
+                    subscription_id,
+                    event,
+                    seq,
+                };
+                self.handle_event(received, replay).await;
+            }
+            msg => {
+                if queue.verifying.is_empty() {
+                    let _ = self
                         .notification_sender
-                        .send(RelayPoolNotification::Shutdown)
-                    {
-                        log::error!("Impossible to send shutdown notification: {}", e);
-                    }
-                    log::debug!("Exited from RelayPoolTask thread");
-                    self.receiver.close();
-                    break;
+                        .send(RelayPoolNotification::Message(relay_url, msg));
+                } else {
+                    queue.deferred.push_back((seq, msg));
                 }
             }
         }
     }
 
-    fn add_event(&mut self, event_id: EventId) {
+    async fn handle_event(&mut self, received: Received, replay: bool) {
+        let Received {
+            relay_url,
+            subscription_id,
+            event,
+            seq,
+        } = received;
+
+        if self.events.contains(&event.id) {
+            self.handle_duplicate(&relay_url, subscription_id, event)
+                .await;
+            self.done(&relay_url, seq);
+            return;
+        }
+
+        // Another copy is being verified: wait for its result
+        if let Some(verifying) = self.verifying.get_mut(&event.id) {
+            verifying.waiting.push(Received {
+                relay_url,
+                subscription_id,
+                event,
+                seq,
+            });
+            return;
+        }
+
+        if replay {
+            if self.verifier.verify_now(&relay_url, &event) {
+                self.handle_verified_event(&relay_url, subscription_id, event)
+                    .await;
+            }
+            self.done(&relay_url, seq);
+            return;
+        }
+
+        // Verification is done by the workers, the events come back through `verified_receiver`
+        let event_id: EventId = event.id;
+        if self.verifier.verify(relay_url.clone(), event) {
+            let verifying = Verifying {
+                subscription_id,
+                seq,
+                waiting: Vec::new(),
+            };
+            self.verifying.insert(event_id, verifying);
+        } else {
+            self.done(&relay_url, seq);
+        }
+    }
+
+    async fn handle_verification(&mut self, relay_url: Url, event: Box<Event>, valid: bool) {
+        let verifying: Verifying = match self.verifying.remove(&event.id) {
+            Some(verifying) => verifying,
+            None => return,
+        };
+        if valid {
+            self.handle_verified_event(&relay_url, verifying.subscription_id, event)
+                .await;
+        }
+        self.done(&relay_url, verifying.seq);
+
+        // Duplicates if valid, otherwise the next copy is verified
+        for received in verifying.waiting.into_iter() {
+            self.handle_event(received, false).await;
+        }
+    }
+
+    async fn handle_verified_event(
+        &mut self,
+        relay_url: &Url,
+        subscription_id: SubscriptionId,
+        event: Box<Event>,
+    ) {
+        // Sent by the pool while verifying
+        if self.events.contains(&event.id) {
+            self.handle_duplicate(relay_url, subscription_id, event)
+                .await;
+            return;
+        }
+        if !self.check_policy(relay_url, &event) {
+            return;
+        }
+        self.add_seen_on(event.id, relay_url).await;
+        self.add_event(event.id).await;
+        self.event_rate.lock().await.record();
+        let _ = self
+            .notification_sender
+            .send(RelayPoolNotification::Message(
+                relay_url.clone(),
+                RelayMessage::Event {
+                    subscription_id,
+                    event: event.clone(),
+                },
+            ));
+        if self
+            .ingestion_sender
+            .send((relay_url.clone(), event))
+            .await
+            .is_err()
+        {
+            log::error!("Impossible to send event to ingestion task");
+        }
+    }
+
+    /// Event already verified: only the relay is recorded
+    async fn handle_duplicate(
+        &mut self,
+        relay_url: &Url,
+        subscription_id: SubscriptionId,
+        event: Box<Event>,
+    ) {
+        if !self.check_policy(relay_url, &event) {
+            return;
+        }
+        let event_id: EventId = event.id;
+        let _ = self
+            .notification_sender
+            .send(RelayPoolNotification::Message(
+                relay_url.clone(),
+                RelayMessage::Event {
+                    subscription_id,
+                    event,
+                },
+            ));
+        if self.add_seen_on(event_id, relay_url).await {
+            let notification = RelayPoolNotification::SeenOn(relay_url.clone(), event_id);
+            let _ = self.notification_sender.send(notification);
+        }
+    }
+
+    fn check_policy(&self, relay_url: &Url, event: &Event) -> bool {
+        let policy = get_event_policy(&self.event_policy);
+        if policy.check(relay_url, event) == EventAction::Reject {
+            log::debug!("Event {} from {} rejected by policy", event.id, relay_url);
+            return false;
+        }
+        true
+    }
+
+    /// Event at `seq` handled: notify the messages that were waiting for it
+    fn done(&mut self, relay_url: &Url, seq: u64) {
+        if let Some(queue) = self.queues.get_mut(relay_url) {
+            queue.verifying.remove(&seq);
+            for msg in queue.pop_ready().into_iter() {
+                let _ = self
+                    .notification_sender
+                    .send(RelayPoolNotification::Message(relay_url.clone(), msg));
+            }
+        }
+    }
+
+    async fn add_event(&mut self, event_id: EventId) {
         while self.events.len() >= MAX_EVENTS {
-            self.events.pop_front();
+            if let Some(old_event_id) = self.events.pop_front() {
+                let mut seen_on = self.seen_on.lock().await;
+                seen_on.remove(&old_event_id);
+            }
         }
         self.events.push_back(event_id);
     }
+
+    /// Return `true` if the event wasn't already seen on this relay
+    async fn add_seen_on(&mut self, event_id: EventId, relay_url: &Url) -> bool {
+        let mut seen_on = self.seen_on.lock().await;
+        seen_on
+            .entry(event_id)
+            .or_default()
+            .insert(relay_url.clone())
+    }
+}
+
+/// Result of an operation on multiple relays
+///
+/// A failure on some relays doesn't hide the success on the others, and vice versa.
+#[derive(Debug)]
+pub struct Output<T> {
+    /// Value (ex. the id of the sent event)
+    pub value: T,
+    /// Relays where the operation succeeded
+    pub success: HashSet<Url>,
+    /// Relays where the operation failed
+    pub failed: HashMap<Url, RelayError>,
+}
+
+impl<T> Output<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            value,
+            success: HashSet::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, url: Url, result: Result<(), RelayError>) {
+        match result {
+            Ok(()) => {
+                self.success.insert(url);
+            }
+            Err(e) => {
+                self.failed.insert(url, e);
+            }
+        }
+    }
+
+    /// Check if the operation succeeded on at least one relay
+    pub fn is_success(&self) -> bool {
+        !self.success.is_empty()
+    }
+
+    /// Map value
+    pub fn map<U, F>(self, f: F) -> Output<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Output {
+            value: f(self.value),
+            success: self.success,
+            failed: self.failed,
+        }
+    }
+}
+
+/// Relay pool status, for status bars and healthchecks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolStatus {
+    /// Connected relays
+    pub relays_connected: usize,
+    /// Relays in the pool
+    pub relays_total: usize,
+    /// Relays with an active subscription
+    pub subscriptions_active: usize,
+    /// Published events waiting for an `OK`
+    pub publishes_pending: usize,
+    /// Messages waiting in the outbound queues
+    pub queued_messages: usize,
+    /// New events received per second in the last minute
+    pub events_per_second: f64,
+    /// Notification listeners
+    pub notification_listeners: usize,
+    /// Notifications not yet received by the slowest listener
+    pub notification_backlog: usize,
+    /// Whether a listener is close to skip notifications
+    pub lagging_consumers: bool,
+}
+
+impl PoolStatus {
+    /// Check if no relay is connected
+    pub fn is_down(&self) -> bool {
+        self.relays_connected == 0
+    }
+
+    /// Check if some relays are not connected or some listeners are lagging
+    pub fn is_degraded(&self) -> bool {
+        self.relays_connected < self.relays_total || self.lagging_consumers
+    }
+}
+
+impl fmt::Display for PoolStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} relays, {} subscriptions, {} pending, {:.1} events/s",
+            self.relays_connected,
+            self.relays_total,
+            self.subscriptions_active,
+            self.publishes_pending,
+            self.events_per_second
+        )?;
+        if self.lagging_consumers {
+            write!(f, ", lagging ({} behind)", self.notification_backlog)?;
+        }
+        Ok(())
+    }
 }
 
 /// Relay Pool
@@ -145,6 +642,24 @@ pub struct RelayPool {
     relays: Arc<Mutex<HashMap<Url, Relay>>>,
     pool_task_sender: Sender<RelayPoolMessage>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
+    seen_on: SeenOn,
+    timeouts: Timeouts,
+    event_policy: SharedEventPolicy,
+    middlewares: SharedMiddlewares,
+    store: SharedStore,
+    user_agent: Option<String>,
+    req_priority: MessagePriority,
+    query_cache: Option<QueryCacheOptions>,
+    auto_narrowing: Option<usize>,
+    subscription_ids: SharedSubscriptionIds,
+    recorder: SharedRecorder,
+    verification: Arc<Counters>,
+    event_rate: Arc<Mutex<EventRate>>,
+    /// Max jitter in milliseconds (0 = disabled)
+    jitter: Arc<AtomicU64>,
+    /// Document refresh interval in seconds (0 = disabled)
+    document_refresh: Arc<AtomicU64>,
+    system_proxy: Arc<AtomicBool>,
 }
 
 impl Default for RelayPool {
@@ -156,32 +671,283 @@ impl Default for RelayPool {
 impl RelayPool {
     /// Create new `RelayPool`
     pub fn new() -> Self {
-        let (notification_sender, _) = broadcast::channel(1024);
+        Self::with_relays(
+            Vec::new(),
+            Timeouts::default(),
+            Arc::new(RwLock::new(Arc::new(AcceptAll))),
+            None,
+            MessagePriority::Low,
+            None,
+            None,
+        )
+    }
+
+    /// Create new `RelayPool` with initial relays
+    pub(crate) fn with_relays(
+        initial_relays: Vec<(Url, Option<SocketAddr>)>,
+        timeouts: Timeouts,
+        event_policy: SharedEventPolicy,
+        user_agent: Option<String>,
+        req_priority: MessagePriority,
+        query_cache: Option<QueryCacheOptions>,
+        auto_narrowing: Option<usize>,
+    ) -> Self {
+        let (notification_sender, _) = broadcast::channel(NOTIFICATION_CAPACITY);
         let (pool_task_sender, pool_task_receiver) = mpsc::channel(1024);
 
-        let mut relay_pool_task =
-            RelayPoolTask::new(pool_task_receiver, notification_sender.clone());
+        let seen_on: SeenOn = Arc::new(Mutex::new(HashMap::new()));
+        let verification: Arc<Counters> = Arc::new(Counters::default());
+        let event_rate: Arc<Mutex<EventRate>> = Arc::new(Mutex::new(EventRate::default()));
+        let middlewares: SharedMiddlewares = Arc::new(RwLock::new(Vec::new()));
+        let store: SharedStore = Arc::new(RwLock::new(None));
+        let (ingestion_sender, ingestion_receiver) = mpsc::channel(1024);
+        let mut relay_pool_task = RelayPoolTask::new(
+            pool_task_receiver,
+            notification_sender.clone(),
+            seen_on.clone(),
+            event_policy.clone(),
+            verification.clone(),
+            event_rate.clone(),
+            ingestion_sender,
+        );
+        let mut ingestion_task = IngestionTask {
+            receiver: ingestion_receiver,
+            middlewares: middlewares.clone(),
+            store: store.clone(),
+            notification_sender: notification_sender.clone(),
+        };
 
         thread::spawn(async move { relay_pool_task.run().await });
+        thread::spawn(async move { ingestion_task.run().await });
+
+        let subscription_ids: SharedSubscriptionIds =
+            Arc::new(RwLock::new(SubscriptionIdGenerator::default()));
+        let recorder: SharedRecorder = Arc::new(std::sync::Mutex::new(None));
+        let document_refresh = Arc::new(AtomicU64::new(0));
+        let system_proxy = Arc::new(AtomicBool::new(true));
+        let mut relays: HashMap<Url, Relay> = HashMap::new();
+        for (url, proxy) in initial_relays.into_iter() {
+            if !relays.contains_key(&url) {
+                let relay = Relay::with_opts(
+                    url,
+                    pool_task_sender.clone(),
+                    notification_sender.clone(),
+                    proxy,
+                    timeouts.clone(),
+                    user_agent.clone(),
+                    req_priority,
+                )
+                .with_query_cache(query_cache)
+                .with_auto_narrowing(auto_narrowing)
+                .with_subscription_ids(subscription_ids.clone())
+                .with_recorder(recorder.clone())
+                .with_document_refresh(document_refresh.clone())
+                .with_system_proxy(system_proxy.clone());
+                relays.insert(relay.url(), relay);
+            }
+        }
 
         Self {
-            relays: Arc::new(Mutex::new(HashMap::new())),
+            relays: Arc::new(Mutex::new(relays)),
             pool_task_sender,
             notification_sender,
+            seen_on,
+            timeouts,
+            event_policy,
+            middlewares,
+            store,
+            user_agent,
+            req_priority,
+            query_cache,
+            auto_narrowing,
+            subscription_ids,
+            recorder,
+            verification,
+            event_rate,
+            jitter: Arc::new(AtomicU64::new(0)),
+            document_refresh,
+            system_proxy,
+        }
+    }
+
+    /// Add ingestion [`Middleware`], run after the ones already added
+    ///
+    /// See [`crate::middleware`] for the stages of the pipeline.
+    pub fn add_middleware(&self, middleware: Arc<dyn Middleware>) {
+        self.middlewares
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(middleware);
+    }
+
+    /// Set [`Store`] where new events are saved, before being notified (`None` to disable)
+    pub fn set_store(&self, store: Option<Store>) {
+        *self.store.write().unwrap_or_else(PoisonError::into_inner) = store;
+    }
+
+    /// Record the traffic of all relays in `path`, replacing the current recording (if any)
+    ///
+    /// See [`recorder`](super::recorder) for the format and what is sanitized.
+    pub fn start_recording<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let recorder = Recorder::create(path)?;
+        *self.recorder.lock().unwrap_or_else(PoisonError::into_inner) = Some(recorder);
+        Ok(())
+    }
+
+    /// Stop recording
+    pub fn stop_recording(&self) {
+        *self.recorder.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Feed the messages received in [`Recording`] through the pool, in recording order
+    ///
+    /// Messages go through the same stages of live traffic (dedup, verify, policy, middlewares, store)
+    /// and are notified as if received from the recorded relays, without waiting the recorded delays.
+    /// Return the number of replayed messages.
+    pub async fn replay(&self, recording: &Recording) -> usize {
+        let mut replayed: usize = 0;
+        for (relay_url, msg) in recording.inbound().into_iter() {
+            let msg = RelayPoolMessage::ReplayedMsg { relay_url, msg };
+            if let Err(e) = self.pool_task_sender.send(msg).await {
+                log::error!("Impossible to replay message: {}", e);
+                break;
+            }
+            replayed += 1;
+        }
+        replayed
+    }
+
+    /// Set [`SubscriptionIdGenerator`] of all relays (default: [`SubscriptionIdGenerator::Uuid`])
+    ///
+    /// Subscriptions already sent keep their id.
+    pub fn set_subscription_id_generator(&self, generator: SubscriptionIdGenerator) {
+        *self
+            .subscription_ids
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = generator;
+    }
+
+    /// Randomize the order of relays when publishing and connecting, waiting a random delay
+    /// up to `max` between relays (`None` to disable)
+    ///
+    /// Makes harder for colluding relays to fingerprint the client by its publish pattern.
+    pub fn set_jitter(&self, max: Option<Duration>) {
+        let millis: u64 = max.map_or(0, |max| max.as_millis().max(1) as u64);
+        self.jitter.store(millis, Ordering::SeqCst);
+    }
+
+    /// Periodically refresh the relay information documents (NIP-11) of the connected relays (`None` to disable)
+    ///
+    /// Documents are fetched with `ETag`/`If-Modified-Since` validators: unchanged documents aren't downloaded again.
+    /// [`RelayPoolNotification::RelayDocumentChanged`] is sent when supported NIPs, limitations or fees change.
+    /// Applied to the relays connected after the change.
+    pub fn set_document_refresh(&self, interval: Option<Duration>) {
+        let secs: u64 = interval.map_or(0, |interval| interval.as_secs().max(1));
+        self.document_refresh.store(secs, Ordering::SeqCst);
+    }
+
+    /// Honor the proxy environment variables (`HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY`, `NO_PROXY`)
+    /// for relays without a proxy (default: `true`)
+    ///
+    /// Relays added with a proxy always use it. Applied to the next connections.
+    pub fn set_system_proxy(&self, enabled: bool) {
+        self.system_proxy.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Check if the proxy environment variables are honored
+    pub fn system_proxy(&self) -> bool {
+        self.system_proxy.load(Ordering::SeqCst)
+    }
+
+    /// Get max jitter, if enabled
+    pub fn jitter(&self) -> Option<Duration> {
+        match self.jitter.load(Ordering::SeqCst) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    /// Shuffle `items` if jitter is enabled
+    pub(crate) fn shuffle<T>(&self, items: &mut [T]) {
+        if self.jitter().is_some() {
+            items.shuffle(&mut rand::thread_rng());
         }
     }
 
+    /// Wait a random delay, if jitter is enabled
+    pub(crate) async fn wait_jitter(&self) {
+        if let Some(max) = self.jitter() {
+            let delay =
+                Duration::from_millis(rand::thread_rng().gen_range(0..=max.as_millis() as u64));
+            time::sleep(delay).await;
+        }
+    }
+
+    /// Get [`PoolStatus`]
+    pub async fn status(&self) -> PoolStatus {
+        let relays = self.relays().await;
+        let mut status = PoolStatus {
+            relays_connected: 0,
+            relays_total: relays.len(),
+            subscriptions_active: 0,
+            publishes_pending: 0,
+            queued_messages: 0,
+            events_per_second: self.event_rate.lock().await.per_second(),
+            notification_listeners: self.notification_sender.receiver_count(),
+            notification_backlog: self.notification_sender.len(),
+            lagging_consumers: false,
+        };
+        for relay in relays.values() {
+            if relay.status().await == RelayStatus::Connected {
+                status.relays_connected += 1;
+            }
+            if relay.has_active_subscription().await {
+                status.subscriptions_active += 1;
+            }
+            status.publishes_pending += relay.pending_ok().await.len();
+            status.queued_messages += relay.queue();
+        }
+        status.lagging_consumers = status.notification_backlog >= NOTIFICATION_CAPACITY * 3 / 4;
+        status
+    }
+
     /// Get new notification listener
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.notification_sender.subscribe()
     }
 
+    /// Get relays where the [`Event`] was seen on
+    pub async fn seen_on(&self, event_id: &EventId) -> Vec<Url> {
+        let seen_on = self.seen_on.lock().await;
+        match seen_on.get(event_id) {
+            Some(relays) => relays.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get [`VerificationStats`] (signature verification queue and cache)
+    pub fn verification_stats(&self) -> VerificationStats {
+        self.verification.stats()
+    }
+
     /// Get relays
     pub async fn relays(&self) -> HashMap<Url, Relay> {
         let relays = self.relays.lock().await;
         relays.clone()
     }
 
+    /// Get the latest REQs sent to each relay (see [`Relay::sent_reqs`])
+    pub async fn sent_reqs(&self) -> HashMap<Url, Vec<SentReq>> {
+        let mut sent_reqs: HashMap<Url, Vec<SentReq>> = HashMap::new();
+        for (url, relay) in self.relays().await.into_iter() {
+            sent_reqs.insert(url, relay.sent_reqs().await);
+        }
+        sent_reqs
+    }
+
     /// Get subscriptions
     pub async fn subscription(&self) -> Subscription {
         let subscription = SUBSCRIPTION.lock().await;
@@ -189,21 +955,34 @@ impl RelayPool {
     }
 
     /// Add new relay
+    ///
+    /// The url is normalized (see [`relay_url::normalize`]).
     pub async fn add_relay(&self, url: Url, proxy: Option<SocketAddr>) {
+        let url: Url = relay_url::normalize(url);
         let mut relays = self.relays.lock().await;
         if !relays.contains_key(&url) {
-            let relay = Relay::new(
+            let relay = Relay::with_opts(
                 url,
                 self.pool_task_sender.clone(),
                 self.notification_sender.clone(),
                 proxy,
-            );
+                self.timeouts.clone(),
+                self.user_agent.clone(),
+                self.req_priority,
+            )
+            .with_query_cache(self.query_cache)
+            .with_auto_narrowing(self.auto_narrowing)
+            .with_subscription_ids(self.subscription_ids.clone())
+            .with_recorder(self.recorder.clone())
+            .with_document_refresh(self.document_refresh.clone())
+            .with_system_proxy(self.system_proxy.clone());
             relays.insert(relay.url(), relay);
         }
     }
 
     /// Disconnect and remove relay
     pub async fn remove_relay(&self, url: Url) {
+        let url: Url = relay_url::normalize(url);
         let mut relays = self.relays.lock().await;
         if let Some(relay) = relays.remove(&url) {
             if self.disconnect_relay(&relay).await.is_err() {
@@ -215,11 +994,12 @@ impl RelayPool {
     /// Send client message
     #[deprecated]
     pub async fn send_client_msg(&self, msg: ClientMessage, wait: bool) -> Result<(), Error> {
-        self.send_msg(msg, wait).await
+        self.send_msg(msg, wait).await?;
+        Ok(())
     }
 
-    /// Send client message
-    pub async fn send_msg(&self, msg: ClientMessage, wait: bool) -> Result<(), Error> {
+    /// Send client message to all relays
+    pub async fn send_msg(&self, msg: ClientMessage, wait: bool) -> Result<Output<()>, Error> {
         let relays = self.relays().await;
 
         if relays.is_empty() {
@@ -232,21 +1012,34 @@ impl RelayPool {
                 .send(RelayPoolMessage::EventSent(event.clone()))
                 .await
             {
-                log::error!("{}", err.to_string());
+                log::error!("{}", err);
             };
         }
 
-        for (url, relay) in relays.into_iter() {
-            if let Err(e) = relay.send_msg(msg.clone(), wait).await {
-                log::error!("Impossible to send msg to {}: {}", url, e.to_string());
+        let is_event: bool = matches!(msg, ClientMessage::Event(_));
+        let mut relays: Vec<(Url, Relay)> = relays.into_iter().collect();
+        if is_event {
+            self.shuffle(&mut relays);
+        }
+
+        let mut output = Output::new(());
+        for (i, (url, relay)) in relays.into_iter().enumerate() {
+            if is_event && i > 0 {
+                self.wait_jitter().await;
+            }
+            let result = relay.send_msg(msg.clone(), wait).await;
+            if let Err(e) = &result {
+                log::error!("Impossible to send msg to {}: {}", url, e);
             }
+            output.insert(url, result);
         }
 
-        Ok(())
+        Ok(output)
     }
 
     /// Send client message
     pub async fn send_msg_to(&self, url: Url, msg: ClientMessage, wait: bool) -> Result<(), Error> {
+        let url: Url = relay_url::normalize(url);
         let relays = self.relays().await;
         if let Some(relay) = relays.get(&url) {
             relay.send_msg(msg, wait).await?;
@@ -261,7 +1054,7 @@ impl RelayPool {
         &self,
         filters: Vec<SubscriptionFilter>,
         wait: bool,
-    ) -> Result<(), Error> {
+    ) -> Result<Output<()>, Error> {
         let relays = self.relays().await;
 
         {
@@ -269,11 +1062,16 @@ impl RelayPool {
             subscription.update_filters(filters.clone());
         }
 
-        for relay in relays.values() {
-            relay.subscribe(wait).await?;
+        let mut output = Output::new(());
+        for (url, relay) in relays.into_iter() {
+            let result = relay.subscribe(wait).await.map(|_| ());
+            if let Err(e) = &result {
+                log::error!("Impossible to subscribe to {}: {}", url, e);
+            }
+            output.insert(url, result);
         }
 
-        Ok(())
+        Ok(output)
     }
 
     /// Unsubscribe from filters
@@ -287,52 +1085,80 @@ impl RelayPool {
     }
 
     /// Get events of filters
+    ///
+    /// Each relay is queried until `EOSE` or until its `EOSE` timeout expires (see [`Relay::stats`]).
+    /// Events rejected by the [`EventPolicy`] are dropped and downranked ones are moved after the others.
+    /// Relays over their [`BandwidthBudget`](crate::relay::stats::BandwidthBudget) are skipped, unless all of them are.
     pub async fn get_events_of(
         &self,
         filters: Vec<SubscriptionFilter>,
     ) -> Result<Vec<Event>, Error> {
-        let mut events: Vec<Event> = Vec::new();
+        let relays = self.relays().await;
+        self.fetch_events(relays, filters).await
+    }
 
-        let id = SubscriptionId::generate();
+    /// Get events of filters from the relays in `urls`
+    ///
+    /// Same as [`RelayPool::get_events_of`], relays not in the pool are ignored.
+    pub async fn get_events_from(
+        &self,
+        urls: &[Url],
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<Vec<Event>, Error> {
+        let urls: Vec<Url> = urls.iter().cloned().map(relay_url::normalize).collect();
+        let mut relays = self.relays().await;
+        relays.retain(|url, _| urls.contains(url));
+        self.fetch_events(relays, filters).await
+    }
 
-        let relays = self.relays().await;
+    async fn fetch_events(
+        &self,
+        mut relays: HashMap<Url, Relay>,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<Vec<Event>, Error> {
+        // Skip the relays over budget, unless all of them are
+        let mut over_budget: HashSet<Url> = HashSet::new();
+        for (url, relay) in relays.iter() {
+            if relay.is_over_budget().await {
+                over_budget.insert(url.clone());
+            }
+        }
+        if over_budget.len() < relays.len() {
+            relays.retain(|url, _| !over_budget.contains(url));
+        }
 
-        // Subscribe
-        for relay in relays.values() {
-            relay
-                .send_msg(ClientMessage::new_req(id.clone(), filters.clone()), false)
-                .await?;
-        }
-
-        let mut notifications = self.notifications();
-        while let Ok(notification) = notifications.recv().await {
-            if let RelayPoolNotification::Message(_, msg) = notification {
-                match msg {
-                    RelayMessage::Event {
-                        subscription_id,
-                        event,
-                    } => {
-                        if subscription_id == id {
-                            events.push(event.as_ref().clone());
+        let results = future::join_all(
+            relays
+                .values()
+                .map(|relay| relay.get_events_of_with_eose_timeout(filters.clone())),
+        )
+        .await;
+
+        let policy = get_event_policy(&self.event_policy);
+        let mut ids: HashSet<EventId> = HashSet::new();
+        let mut events: Vec<Event> = Vec::new();
+        let mut downranked: Vec<Event> = Vec::new();
+        for (url, result) in relays.keys().zip(results) {
+            match result {
+                Ok(relay_events) => {
+                    for event in relay_events.into_iter() {
+                        if !ids.insert(event.id) {
+                            continue;
                         }
-                    }
-                    RelayMessage::EndOfStoredEvents(subscription_id) => {
-                        if subscription_id == id {
-                            break;
+                        match policy.check(url, &event) {
+                            EventAction::Accept => events.push(event),
+                            EventAction::Downrank => downranked.push(event),
+                            EventAction::Reject => {
+                                log::debug!("Event {} from {} rejected by policy", event.id, url)
+                            }
                         }
                     }
-                    _ => (),
-                };
+                }
+                Err(e) => log::error!("Impossible to get events from {}: {}", url, e),
             }
         }
 
-        // Unsubscribe
-        for relay in relays.values() {
-            relay
-                .send_msg(ClientMessage::close(id.clone()), false)
-                .await?;
-        }
-
+        events.extend(downranked);
         Ok(events)
     }
 
@@ -345,9 +1171,15 @@ impl RelayPool {
     }
 
     /// Connect to all added relays and keep connection alive
+    ///
+    /// With jitter enabled (see [`RelayPool::set_jitter`]), relays are connected in random order.
     pub async fn connect(&self, wait_for_connection: bool) {
-        let relays = self.relays().await;
-        for relay in relays.values() {
+        let mut relays: Vec<Relay> = self.relays().await.into_values().collect();
+        self.shuffle(&mut relays);
+        for (i, relay) in relays.iter().enumerate() {
+            if i > 0 {
+                self.wait_jitter().await;
+            }
             self.connect_relay(relay, wait_for_connection).await;
         }
     }
@@ -373,13 +1205,193 @@ impl RelayPool {
         Ok(())
     }
 
+    /// Wait until all the queued messages are transmitted to relays
+    pub async fn flush(&self, timeout: Duration) -> Result<(), Error> {
+        let relays = self.relays().await;
+        let results = future::join_all(relays.values().map(|relay| relay.flush(timeout))).await;
+        for result in results.into_iter() {
+            result?;
+        }
+        Ok(())
+    }
+
     /// Completly shutdown pool
     pub async fn shutdown(self) -> Result<(), Error> {
         self.disconnect().await?;
         time::sleep(Duration::from_secs(3)).await;
         if let Err(e) = self.pool_task_sender.send(RelayPoolMessage::Shutdown).await {
-            log::error!("Impossible to shutdown pool: {}", e.to_string());
+            log::error!("Impossible to shutdown pool: {}", e);
         };
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    /// Reject the events of `rejected` relay, recording the checked events
+    #[derive(Debug)]
+    struct RejectRelay {
+        rejected: Url,
+        checked: std::sync::Mutex<Vec<EventId>>,
+    }
+
+    impl EventPolicy for RejectRelay {
+        fn check(&self, relay_url: &Url, event: &Event) -> EventAction {
+            self.checked.lock().unwrap().push(event.id);
+            if relay_url == &self.rejected {
+                EventAction::Reject
+            } else {
+                EventAction::Accept
+            }
+        }
+    }
+
+    async fn receive(pool: &RelayPool, relay_url: &Url, msg: RelayMessage) {
+        let msg = RelayPoolMessage::ReceivedMsg {
+            relay_url: relay_url.clone(),
+            msg,
+        };
+        pool.pool_task_sender.send(msg).await.unwrap();
+    }
+
+    /// Raw messages notified until the `EOSE` of `relay_url`
+    async fn messages_until_eose(
+        notifications: &mut broadcast::Receiver<RelayPoolNotification>,
+        relay_url: &Url,
+    ) -> Vec<RelayMessage> {
+        let mut messages = Vec::new();
+        loop {
+            let notification = time::timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let RelayPoolNotification::Message(url, msg) = notification {
+                if &url == relay_url {
+                    let eose: bool = matches!(msg, RelayMessage::EndOfStoredEvents(_));
+                    messages.push(msg);
+                    if eose {
+                        return messages;
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_order() {
+        let pool = RelayPool::new();
+        let relay_a = Url::parse("wss://relay-a.example.com").unwrap();
+        let relay_b = Url::parse("wss://relay-b.example.com").unwrap();
+        let policy = Arc::new(RejectRelay {
+            rejected: relay_a.clone(),
+            checked: std::sync::Mutex::new(Vec::new()),
+        });
+        *pool.event_policy.write().unwrap() = policy.clone();
+        let mut notifications = pool.notifications();
+
+        let keys = Keys::generate();
+        let event: Event = EventBuilder::new_text_note("GM", &[])
+            .to_event(&keys)
+            .unwrap();
+        let mut forged: Event = EventBuilder::new_text_note("GN", &[])
+            .to_event(&keys)
+            .unwrap();
+        forged.sig = event.sig;
+        let id = SubscriptionId::new("sub");
+
+        // Verified before the policy: the forged event is never checked nor notified
+        receive(
+            &pool,
+            &relay_b,
+            RelayMessage::new_event(id.clone(), forged.clone()),
+        )
+        .await;
+        receive(
+            &pool,
+            &relay_b,
+            RelayMessage::new_event(id.clone(), event.clone()),
+        )
+        .await;
+        receive(&pool, &relay_b, RelayMessage::new_eose(id.clone())).await;
+        assert_eq!(
+            messages_until_eose(&mut notifications, &relay_b).await,
+            vec![
+                RelayMessage::new_event(id.clone(), event.clone()),
+                RelayMessage::new_eose(id.clone()),
+            ]
+        );
+
+        // Duplicate: still checked by the policy, rejected for relay A
+        receive(
+            &pool,
+            &relay_a,
+            RelayMessage::new_event(id.clone(), event.clone()),
+        )
+        .await;
+        receive(&pool, &relay_a, RelayMessage::new_eose(id.clone())).await;
+        assert_eq!(
+            messages_until_eose(&mut notifications, &relay_a).await,
+            vec![RelayMessage::new_eose(id)]
+        );
+
+        assert_eq!(*policy.checked.lock().unwrap(), vec![event.id, event.id]);
+        assert_eq!(pool.seen_on(&event.id).await, vec![relay_b]);
+        let stats = pool.verification_stats();
+        assert_eq!((stats.verified, stats.invalid), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_cross_posted_event_verified_once() {
+        let pool = RelayPool::new();
+        let mut notifications = pool.notifications();
+        let relays: Vec<Url> = (0..5)
+            .map(|i| Url::parse(&format!("wss://relay-{}.example.com", i)).unwrap())
+            .collect();
+
+        let keys = Keys::generate();
+        let event: Event = EventBuilder::new_text_note("GM", &[])
+            .to_event(&keys)
+            .unwrap();
+        let other: Event = EventBuilder::new_text_note("GN", &[])
+            .to_event(&keys)
+            .unwrap();
+        let mut forged: Event = event.clone();
+        forged.sig = other.sig;
+        let id = SubscriptionId::new("sub");
+
+        // The first relay sends a forged signature, the others the valid event
+        for (i, relay_url) in relays.iter().enumerate() {
+            let event: Event = if i == 0 {
+                forged.clone()
+            } else {
+                event.clone()
+            };
+            receive(&pool, relay_url, RelayMessage::new_event(id.clone(), event)).await;
+            receive(&pool, relay_url, RelayMessage::new_eose(id.clone())).await;
+        }
+        let mut eose: usize = 0;
+        while eose < relays.len() {
+            let notification = time::timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let RelayPoolNotification::Message(_, RelayMessage::EndOfStoredEvents(_)) =
+                notification
+            {
+                eose += 1;
+            }
+        }
+
+        // One verification of the forged copy, one of the valid copy
+        let stats = pool.verification_stats();
+        assert_eq!((stats.cache_misses, stats.cache_hits), (2, 0));
+        assert_eq!((stats.verified, stats.invalid), (1, 1));
+        let mut seen_on: Vec<Url> = pool.seen_on(&event.id).await;
+        seen_on.sort();
+        assert_eq!(seen_on, relays[1..].to_vec());
+    }
+}
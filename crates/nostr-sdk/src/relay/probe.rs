@@ -0,0 +1,267 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay capability probing
+
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use nostr::nips::nip11::{CacheValidators, RelayInformationDocument};
+use nostr::{
+    ClientMessage, Event, EventBuilder, Keys, Kind, RelayMessage, SubscriptionFilter,
+    SubscriptionId, Url,
+};
+
+use super::net::{self, Message as WsMessage};
+use crate::http;
+
+/// Unassigned ephemeral kind used for the probe event
+const PROBE_KIND: u64 = 29_999;
+
+/// Relay probe report
+#[derive(Debug, Clone)]
+pub struct RelayProbeReport {
+    /// Relay url
+    pub url: Url,
+    /// Relay information document (NIP-11), if available
+    pub document: Option<RelayInformationDocument>,
+    /// Connection error, if the connection failed
+    pub connection_error: Option<String>,
+    /// Time to open the WebSocket connection
+    pub connect_latency: Option<Duration>,
+    /// Time from `REQ` to `EOSE`
+    pub eose_latency: Option<Duration>,
+    /// Time from `EVENT` to `OK`
+    pub ok_latency: Option<Duration>,
+    /// Time from `EVENT` to receiving it back on the subscription
+    pub round_trip_latency: Option<Duration>,
+    /// Status of the `OK` of the probe event
+    pub event_accepted: Option<bool>,
+    /// Message of the `OK` of the probe event
+    pub event_message: Option<String>,
+    /// Required by the relay information document, `AUTH` challenge received or messages prefixed by `auth-required:` (NIP-42)
+    pub auth_required: bool,
+    /// Required by the relay information document (limitations or fees) or probe event rejected with a message asking for a payment
+    pub payment_required: bool,
+    /// `NOTICE`s received
+    pub notices: Vec<String>,
+}
+
+impl RelayProbeReport {
+    fn new(url: Url) -> Self {
+        Self {
+            url,
+            document: None,
+            connection_error: None,
+            connect_latency: None,
+            eose_latency: None,
+            ok_latency: None,
+            round_trip_latency: None,
+            event_accepted: None,
+            event_message: None,
+            auth_required: false,
+            payment_required: false,
+            notices: Vec::new(),
+        }
+    }
+
+    /// Check if the WebSocket connection succeeded
+    pub fn is_reachable(&self) -> bool {
+        self.connect_latency.is_some()
+    }
+
+    /// Check if the relay answers to `REQ`s
+    pub fn is_readable(&self) -> bool {
+        self.eose_latency.is_some()
+    }
+
+    /// Check if the relay accepted the probe event
+    pub fn is_writable(&self) -> bool {
+        self.event_accepted == Some(true)
+    }
+
+    /// Check if `nip` is in the supported NIPs of the relay information document
+    pub fn supports_nip(&self, nip: u16) -> bool {
+        self.document
+            .as_ref()
+            .and_then(|d| d.supported_nips.as_ref())
+            .map_or(false, |nips| nips.contains(&nip))
+    }
+
+    fn check_message(&mut self, message: &str) {
+        if message.starts_with("auth-required:") {
+            self.auth_required = true;
+        }
+    }
+}
+
+/// Probe relay
+///
+/// Fetch the relay information document while connecting, then subscribe to the probe event author,
+/// wait for `EOSE`, publish an ephemeral event signed with random keys and wait for the `OK`
+/// and the event on the subscription. Every step waits up to `timeout`.
+pub(crate) async fn probe(
+    url: Url,
+    proxy: Option<SocketAddr>,
+    user_agent: Option<String>,
+    timeout: Duration,
+    system_proxy: bool,
+) -> RelayProbeReport {
+    let mut report = RelayProbeReport::new(url.clone());
+
+    let connect = async {
+        let start = Instant::now();
+        net::get_connection(
+            &url,
+            proxy,
+            Some(timeout),
+            user_agent.as_deref(),
+            system_proxy,
+        )
+        .await
+        .map(|connection| (connection, start.elapsed()))
+    };
+    let document = async {
+        let client = http::build_client(proxy, None, system_proxy).ok()?;
+        let validators = CacheValidators::default();
+        let (document, _) = RelayInformationDocument::get_if_modified_with_client(
+            &client,
+            url.clone(),
+            &validators,
+        )
+        .await
+        .ok()??;
+        Some(document)
+    };
+    let document = tokio::time::timeout(timeout, document);
+    let (document, connection) = tokio::join!(document, connect);
+    report.document = document.ok().flatten();
+    if let Some(document) = &report.document {
+        report.payment_required = document.is_paid();
+        report.auth_required = document
+            .limitation
+            .as_ref()
+            .and_then(|l| l.auth_required)
+            .unwrap_or(false);
+    }
+
+    let (mut ws_tx, mut ws_rx) = match connection {
+        Ok((connection, latency)) => {
+            report.connect_latency = Some(latency);
+            connection
+        }
+        Err(e) => {
+            report.connection_error = Some(e.to_string());
+            return report;
+        }
+    };
+
+    let keys = Keys::generate();
+    let kind = Kind::from(PROBE_KIND);
+    let event: Event = match EventBuilder::new(kind, "nostr-sdk relay probe", &[]).to_event(&keys) {
+        Ok(event) => event,
+        Err(e) => {
+            log::error!("Impossible to build probe event: {}", e);
+            return report;
+        }
+    };
+
+    let subscription_id = SubscriptionId::generate();
+    let filter = SubscriptionFilter::new()
+        .author(keys.public_key())
+        .kind(kind);
+    let req = ClientMessage::new_req(subscription_id.clone(), vec![filter]);
+    if let Err(e) = ws_tx.send(WsMessage::Text(req.as_json())).await {
+        report.connection_error = Some(e.to_string());
+        return report;
+    }
+    let req_sent = Instant::now();
+    let mut event_sent: Option<Instant> = None;
+
+    loop {
+        let msg = match tokio::time::timeout(timeout, ws_rx.next()).await {
+            Ok(Some(Ok(msg))) => msg,
+            Ok(Some(Err(e))) => {
+                report.connection_error = Some(e.to_string());
+                break;
+            }
+            Ok(None) => {
+                report.connection_error = Some(String::from("connection closed by relay"));
+                break;
+            }
+            Err(_) => {
+                // `EOSE` not received: try to publish anyway
+                if event_sent.is_none() {
+                    let msg = ClientMessage::new_event(event.clone());
+                    if ws_tx.send(WsMessage::Text(msg.as_json())).await.is_err() {
+                        break;
+                    }
+                    event_sent = Some(Instant::now());
+                    continue;
+                }
+                break;
+            }
+        };
+
+        let data: String = match String::from_utf8(msg.into_data()) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        match RelayMessage::from_json(&data) {
+            Ok(RelayMessage::EndOfStoredEvents(id)) if id == subscription_id => {
+                report.eose_latency = Some(req_sent.elapsed());
+                if event_sent.is_none() {
+                    let msg = ClientMessage::new_event(event.clone());
+                    if let Err(e) = ws_tx.send(WsMessage::Text(msg.as_json())).await {
+                        report.connection_error = Some(e.to_string());
+                        break;
+                    }
+                    event_sent = Some(Instant::now());
+                }
+            }
+            Ok(RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            }) if event_id == event.id => {
+                report.ok_latency = event_sent.map(|sent| sent.elapsed());
+                report.event_accepted = Some(status);
+                report.check_message(&message);
+                if !status && message.to_lowercase().contains("pay") {
+                    report.payment_required = true;
+                }
+                report.event_message = Some(message);
+            }
+            Ok(RelayMessage::Event {
+                subscription_id: id,
+                event: received,
+            }) if id == subscription_id && received.id == event.id => {
+                report.round_trip_latency = event_sent.map(|sent| sent.elapsed());
+            }
+            Ok(RelayMessage::Auth { .. }) => report.auth_required = true,
+            Ok(RelayMessage::Notice { message }) => {
+                report.check_message(&message);
+                report.notices.push(message);
+            }
+            Ok(_) => (),
+            Err(e) => log::debug!("Probe of {}: {}: {}", url, e, data),
+        }
+
+        let done: bool = match report.event_accepted {
+            Some(true) => report.round_trip_latency.is_some(),
+            Some(false) => true,
+            None => false,
+        };
+        if done {
+            break;
+        }
+    }
+
+    let close = ClientMessage::close(subscription_id);
+    let _ = ws_tx.send(WsMessage::Text(close.as_json())).await;
+    let _ = ws_tx.close().await;
+
+    report
+}
@@ -0,0 +1,384 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay outbound queue with priority lanes
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use nostr::{ClientMessage, SubscriptionId};
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use super::{Message, RelayEvent};
+
+/// Capacity of each lane
+const LANE_CAPACITY: usize = 1024;
+
+/// Priority of outgoing messages
+///
+/// Messages with higher priority are sent first, messages with the same priority in order.
+/// `AUTH` and `CLOSE` are always [`MessagePriority::High`], `EVENT` is [`MessagePriority::Normal`],
+/// `REQ` is configurable (default: [`MessagePriority::Low`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessagePriority {
+    /// Low
+    Low,
+    /// Normal
+    Normal,
+    /// High
+    High,
+}
+
+/// Queued `REQ`s by subscription id
+///
+/// A `CLOSE` can overtake the `REQ` of the same subscription: in this case the `REQ` is discarded.
+#[derive(Debug, Default)]
+struct PendingReqs {
+    queued: BTreeMap<SubscriptionId, usize>,
+    cancelled: BTreeMap<SubscriptionId, usize>,
+}
+
+impl PendingReqs {
+    fn dequeue(&mut self, subscription_id: &SubscriptionId) {
+        if let Some(queued) = self.queued.get_mut(subscription_id) {
+            *queued -= 1;
+            if *queued == 0 {
+                self.queued.remove(subscription_id);
+            }
+        }
+    }
+}
+
+type SharedPendingReqs = Arc<Mutex<PendingReqs>>;
+
+fn lock(pending: &SharedPendingReqs) -> std::sync::MutexGuard<'_, PendingReqs> {
+    pending.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Queue {
+    high: Sender<Message>,
+    normal: Sender<Message>,
+    low: Sender<Message>,
+    req_priority: MessagePriority,
+    pending: SharedPendingReqs,
+}
+
+#[derive(Debug)]
+pub(crate) struct QueueReceiver {
+    high: Receiver<Message>,
+    normal: Receiver<Message>,
+    low: Receiver<Message>,
+    pending: SharedPendingReqs,
+}
+
+pub(crate) fn channel(req_priority: MessagePriority) -> (Queue, QueueReceiver) {
+    let (high, high_rx) = mpsc::channel(LANE_CAPACITY);
+    let (normal, normal_rx) = mpsc::channel(LANE_CAPACITY);
+    let (low, low_rx) = mpsc::channel(LANE_CAPACITY);
+    let pending = SharedPendingReqs::default();
+    (
+        Queue {
+            high,
+            normal,
+            low,
+            req_priority,
+            pending: pending.clone(),
+        },
+        QueueReceiver {
+            high: high_rx,
+            normal: normal_rx,
+            low: low_rx,
+            pending,
+        },
+    )
+}
+
+impl Queue {
+    fn priority(&self, event: &RelayEvent) -> MessagePriority {
+        match event {
+            RelayEvent::SendMsg(msg) => match msg.as_ref() {
+                ClientMessage::Auth(_) | ClientMessage::Close(_) => MessagePriority::High,
+                ClientMessage::Event(_) => MessagePriority::Normal,
+                ClientMessage::Req { .. } => self.req_priority,
+            },
+            // Must be processed after all the messages queued before it
+            RelayEvent::Flush => MessagePriority::Low,
+            RelayEvent::Close(_) | RelayEvent::Terminate => MessagePriority::Normal,
+        }
+    }
+
+    fn lane(&self, priority: MessagePriority) -> &Sender<Message> {
+        match priority {
+            MessagePriority::High => &self.high,
+            MessagePriority::Normal => &self.normal,
+            MessagePriority::Low => &self.low,
+        }
+    }
+
+    /// Number of queued messages
+    pub fn len(&self) -> usize {
+        [&self.high, &self.normal, &self.low]
+            .iter()
+            .map(|lane| lane.max_capacity() - lane.capacity())
+            .sum()
+    }
+
+    /// Available capacity of each lane (high, normal, low)
+    pub fn capacity(&self) -> (usize, usize, usize) {
+        (
+            self.high.capacity(),
+            self.normal.capacity(),
+            self.low.capacity(),
+        )
+    }
+
+    pub async fn send_timeout(
+        &self,
+        message: Message,
+        timeout: Duration,
+    ) -> Result<(), SendTimeoutError<Message>> {
+        let priority: MessagePriority = self.priority(&message.0);
+        let req: Option<SubscriptionId> = match &message.0 {
+            RelayEvent::SendMsg(msg) => match msg.as_ref() {
+                ClientMessage::Req {
+                    subscription_id, ..
+                } => Some(subscription_id.clone()),
+                ClientMessage::Close(subscription_id) if priority > self.req_priority => {
+                    // Discard the queued `REQ`s of this subscription
+                    let mut pending = lock(&self.pending);
+                    if let Some(queued) = pending.queued.get(subscription_id).copied() {
+                        pending.cancelled.insert(subscription_id.clone(), queued);
+                    }
+                    None
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(subscription_id) = &req {
+            let mut pending = lock(&self.pending);
+            *pending.queued.entry(subscription_id.clone()).or_default() += 1;
+        }
+
+        let res = self.lane(priority).send_timeout(message, timeout).await;
+
+        if let (Some(subscription_id), Err(_)) = (&req, &res) {
+            lock(&self.pending).dequeue(subscription_id);
+        }
+
+        res
+    }
+}
+
+impl QueueReceiver {
+    /// Receive the next message, by priority
+    pub async fn recv(&mut self) -> Option<Message> {
+        loop {
+            let message: Message = tokio::select! {
+                biased;
+                Some(message) = self.high.recv() => message,
+                Some(message) = self.normal.recv() => message,
+                Some(message) = self.low.recv() => message,
+                else => return None,
+            };
+
+            if let RelayEvent::SendMsg(msg) = &message.0 {
+                if let ClientMessage::Req {
+                    subscription_id, ..
+                } = msg.as_ref()
+                {
+                    if self.is_cancelled(subscription_id) {
+                        log::debug!("REQ {} discarded: closed before sent", subscription_id);
+                        if let Some(sender) = message.1 {
+                            let _ = sender.send(true);
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            return Some(message);
+        }
+    }
+
+    /// Dequeue `REQ` and check if it was cancelled by a `CLOSE`
+    fn is_cancelled(&self, subscription_id: &SubscriptionId) -> bool {
+        let mut pending = lock(&self.pending);
+        pending.dequeue(subscription_id);
+        match pending.cancelled.get_mut(subscription_id) {
+            Some(cancelled) => {
+                *cancelled -= 1;
+                if *cancelled == 0 {
+                    pending.cancelled.remove(subscription_id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    fn msg(msg: ClientMessage) -> Message {
+        (RelayEvent::SendMsg(Box::new(msg)), None)
+    }
+
+    fn req(id: &str) -> ClientMessage {
+        ClientMessage::new_req(SubscriptionId::new(id), Vec::new())
+    }
+
+    fn close(id: &str) -> ClientMessage {
+        ClientMessage::close(SubscriptionId::new(id))
+    }
+
+    fn event(content: &str) -> ClientMessage {
+        let event = EventBuilder::new_text_note(content, &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        ClientMessage::new_event(event)
+    }
+
+    fn auth() -> ClientMessage {
+        let event = EventBuilder::new_text_note("auth", &[])
+            .to_event(&Keys::generate())
+            .unwrap();
+        ClientMessage::new_auth(event)
+    }
+
+    async fn send(queue: &Queue, message: ClientMessage) {
+        queue.send_timeout(msg(message), TIMEOUT).await.unwrap();
+    }
+
+    async fn recv(rx: &mut QueueReceiver) -> ClientMessage {
+        match rx.recv().await {
+            Some((RelayEvent::SendMsg(msg), _)) => *msg,
+            m => panic!("Unexpected message: {m:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_priority_ordering() {
+        let (queue, mut rx) = channel(MessagePriority::Low);
+        let (event1, event2) = (event("1"), event("2"));
+        let auth = auth();
+        send(&queue, req("a")).await;
+        send(&queue, event1.clone()).await;
+        send(&queue, req("b")).await;
+        send(&queue, event2.clone()).await;
+        send(&queue, close("c")).await;
+        send(&queue, auth.clone()).await;
+        assert_eq!(queue.len(), 6);
+
+        // By priority, in order within the same priority
+        assert_eq!(recv(&mut rx).await, close("c"));
+        assert_eq!(recv(&mut rx).await, auth);
+        assert_eq!(recv(&mut rx).await, event1);
+        assert_eq!(recv(&mut rx).await, event2);
+        assert_eq!(recv(&mut rx).await, req("a"));
+        assert_eq!(recv(&mut rx).await, req("b"));
+        assert_eq!(queue.len(), 0);
+
+        // Flush is processed after the messages queued before it
+        queue
+            .send_timeout((RelayEvent::Flush, None), TIMEOUT)
+            .await
+            .unwrap();
+        send(&queue, req("d")).await;
+        assert!(matches!(rx.recv().await, Some((RelayEvent::Flush, _))));
+        assert_eq!(recv(&mut rx).await, req("d"));
+    }
+
+    #[tokio::test]
+    async fn test_req_priority() {
+        let (queue, mut rx) = channel(MessagePriority::High);
+        let event = event("1");
+        send(&queue, event.clone()).await;
+        send(&queue, req("a")).await;
+        send(&queue, close("a")).await;
+
+        // Same priority of `CLOSE`: the `REQ` is sent before its `CLOSE`
+        assert_eq!(recv(&mut rx).await, req("a"));
+        assert_eq!(recv(&mut rx).await, close("a"));
+        assert_eq!(recv(&mut rx).await, event);
+    }
+
+    #[tokio::test]
+    async fn test_capacity() {
+        let (queue, mut rx) = channel(MessagePriority::Low);
+        let event = event("1");
+        assert_eq!(
+            queue.capacity(),
+            (LANE_CAPACITY, LANE_CAPACITY, LANE_CAPACITY)
+        );
+
+        send(&queue, close("a")).await;
+        send(&queue, req("b")).await;
+        assert_eq!(
+            queue.capacity(),
+            (LANE_CAPACITY - 1, LANE_CAPACITY, LANE_CAPACITY - 1)
+        );
+
+        // Full lane: timeout, without affecting the other lanes
+        for i in 1..LANE_CAPACITY {
+            send(&queue, req(&i.to_string())).await;
+        }
+        assert_eq!(queue.capacity().2, 0);
+        assert!(matches!(
+            queue.send_timeout(msg(req("full")), TIMEOUT).await,
+            Err(SendTimeoutError::Timeout(_))
+        ));
+        send(&queue, event.clone()).await;
+        assert_eq!(queue.len(), LANE_CAPACITY + 2);
+
+        // The `REQ` that timed out is not pending anymore: its `CLOSE` doesn't discard later `REQ`s
+        send(&queue, close("full")).await;
+        assert_eq!(recv(&mut rx).await, close("a"));
+        assert_eq!(recv(&mut rx).await, close("full"));
+        assert_eq!(recv(&mut rx).await, event);
+        assert_eq!(recv(&mut rx).await, req("b"));
+        send(&queue, req("full")).await;
+        for _ in 1..LANE_CAPACITY {
+            recv(&mut rx).await;
+        }
+        assert_eq!(recv(&mut rx).await, req("full"));
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drop_closed_req() {
+        let (queue, mut rx) = channel(MessagePriority::Low);
+        let (tx, discarded) = oneshot::channel();
+        queue
+            .send_timeout((RelayEvent::SendMsg(Box::new(req("a"))), Some(tx)), TIMEOUT)
+            .await
+            .unwrap();
+        send(&queue, req("b")).await;
+        send(&queue, req("a")).await;
+        send(&queue, close("a")).await;
+
+        // Both the queued `REQ`s of `a` are discarded, and the sender is notified
+        assert_eq!(recv(&mut rx).await, close("a"));
+        assert_eq!(recv(&mut rx).await, req("b"));
+        assert_eq!(discarded.await, Ok(true));
+
+        // `REQ`s queued after the `CLOSE` are sent
+        send(&queue, req("a")).await;
+        assert_eq!(recv(&mut rx).await, req("a"));
+
+        // Closed queue
+        drop(queue);
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,549 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay latency, timeouts, bandwidth, sent REQs and event rate
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nostr::{SubscriptionFilter, SubscriptionId, Timestamp};
+
+/// Weight of the latest sample in the moving average
+const EMA_ALPHA: f64 = 0.2;
+/// Adaptive timeout is a multiple of the average latency
+const ADAPTIVE_MULTIPLIER: f64 = 4.0;
+/// Min adaptive timeout
+const MIN_ADAPTIVE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Default timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Seconds per day
+const SECS_PER_DAY: u64 = 86400;
+/// Max number of REQs kept in the log of each relay
+const MAX_SENT_REQS: usize = 100;
+/// Window of the event rate (seconds)
+const EVENT_RATE_WINDOW: u64 = 60;
+
+/// Timeouts of relay operations (REQ `EOSE` wait, `OK` wait, silent subscriptions)
+///
+/// When adaptive, the timeout of each relay is derived from its measured latency and
+/// `timeout` is used as upper bound (and until the first measurement).
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    adaptive: Arc<AtomicBool>,
+    timeout: Arc<AtomicU64>,
+    /// Millis, `0` if disabled
+    stale_subscription: Arc<AtomicU64>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::new(true, DEFAULT_TIMEOUT)
+    }
+}
+
+impl Timeouts {
+    /// New [`Timeouts`]
+    pub fn new(adaptive: bool, timeout: Duration) -> Self {
+        Self {
+            adaptive: Arc::new(AtomicBool::new(adaptive)),
+            timeout: Arc::new(AtomicU64::new(timeout.as_millis() as u64)),
+            stale_subscription: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Set time without events after which a subscription is considered stale (`None` to disable)
+    pub fn stale_subscription(self, timeout: Option<Duration>) -> Self {
+        let millis: u64 = timeout.map_or(0, |t| (t.as_millis() as u64).max(1));
+        self.stale_subscription.store(millis, Ordering::SeqCst);
+        self
+    }
+
+    /// Check if timeouts are adaptive
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive.load(Ordering::SeqCst)
+    }
+
+    /// Get fixed timeout (upper bound of adaptive timeouts)
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout.load(Ordering::SeqCst))
+    }
+
+    /// Get time without events after which a subscription is considered stale
+    pub fn stale_subscription_timeout(&self) -> Option<Duration> {
+        match self.stale_subscription.load(Ordering::SeqCst) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    pub(crate) fn update(&self, other: &Timeouts) {
+        self.adaptive.store(other.is_adaptive(), Ordering::SeqCst);
+        self.timeout
+            .store(other.timeout().as_millis() as u64, Ordering::SeqCst);
+        self.stale_subscription.store(
+            other.stale_subscription.load(Ordering::SeqCst),
+            Ordering::SeqCst,
+        );
+    }
+
+    /// Get timeout for an operation with the average `latency`
+    fn get(&self, latency: Option<Duration>) -> Duration {
+        let timeout: Duration = self.timeout();
+        match latency {
+            Some(latency) if self.is_adaptive() => latency
+                .mul_f64(ADAPTIVE_MULTIPLIER)
+                .max(MIN_ADAPTIVE_TIMEOUT)
+                .min(timeout),
+            _ => timeout,
+        }
+    }
+}
+
+/// Exponential moving average of latency
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Latency {
+    average: Option<Duration>,
+    samples: u64,
+}
+
+impl Latency {
+    pub fn record(&mut self, sample: Duration) {
+        self.average = Some(match self.average {
+            Some(average) => average.mul_f64(1.0 - EMA_ALPHA) + sample.mul_f64(EMA_ALPHA),
+            None => sample,
+        });
+        self.samples += 1;
+    }
+}
+
+/// Relay latency tracker
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Latencies {
+    /// REQ to `EOSE`
+    pub eose: Latency,
+    /// EVENT to `OK`
+    pub ok: Latency,
+}
+
+impl Latencies {
+    pub fn stats(&self, timeouts: &Timeouts, bandwidth: &Bandwidth) -> RelayStats {
+        RelayStats {
+            eose_latency: self.eose.average,
+            eose_samples: self.eose.samples,
+            ok_latency: self.ok.average,
+            ok_samples: self.ok.samples,
+            eose_timeout: timeouts.get(self.eose.average),
+            ok_timeout: timeouts.get(self.ok.average),
+            adaptive: timeouts.is_adaptive(),
+            bytes_sent: bandwidth.sent,
+            bytes_received: bandwidth.received,
+            budget: bandwidth.budget,
+            budget_usage: bandwidth.usage(),
+            over_budget: bandwidth.is_over_budget(),
+        }
+    }
+}
+
+/// Period of a [`BandwidthBudget`] (UTC calendar days and months)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BudgetPeriod {
+    /// Daily
+    Daily,
+    /// Monthly
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// Index of the period containing `timestamp`
+    fn index(&self, timestamp: Timestamp) -> u64 {
+        let days: u64 = timestamp.as_u64() / SECS_PER_DAY;
+        match self {
+            Self::Daily => days,
+            Self::Monthly => {
+                let (year, month) = year_month(days);
+                year * 12 + month
+            }
+        }
+    }
+}
+
+/// Convert days since UNIX epoch to (year, month)
+///
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn year_month(days: u64) -> (u64, u64) {
+    let z: u64 = days + 719468;
+    let era: u64 = z / 146097;
+    let doe: u64 = z - era * 146097;
+    let yoe: u64 = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy: u64 = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp: u64 = (5 * doy + 2) / 153;
+    let month: u64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: u64 = yoe + era * 400 + u64::from(month <= 2);
+    (year, month)
+}
+
+/// Bytes allowed per period
+///
+/// Relays over budget are deprioritized for queries but still used for publishing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BandwidthBudget {
+    /// Max bytes (sent and received) per period
+    pub bytes: u64,
+    /// Period
+    pub period: BudgetPeriod,
+}
+
+impl BandwidthBudget {
+    /// New daily [`BandwidthBudget`]
+    pub fn daily(bytes: u64) -> Self {
+        Self {
+            bytes,
+            period: BudgetPeriod::Daily,
+        }
+    }
+
+    /// New monthly [`BandwidthBudget`]
+    pub fn monthly(bytes: u64) -> Self {
+        Self {
+            bytes,
+            period: BudgetPeriod::Monthly,
+        }
+    }
+}
+
+/// Relay bandwidth tracker
+///
+/// Counts the payload of the WebSocket messages (framing and TLS overhead excluded).
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Bandwidth {
+    sent: u64,
+    received: u64,
+    budget: Option<BandwidthBudget>,
+    /// Index of the current budget period and bytes transferred in it
+    period: (u64, u64),
+}
+
+impl Bandwidth {
+    pub fn record_sent(&mut self, bytes: usize) {
+        self.sent = self.sent.saturating_add(bytes as u64);
+        self.record_period(bytes);
+    }
+
+    pub fn record_received(&mut self, bytes: usize) {
+        self.received = self.received.saturating_add(bytes as u64);
+        self.record_period(bytes);
+    }
+
+    pub fn set_budget(&mut self, budget: Option<BandwidthBudget>) {
+        self.budget = budget;
+        self.period = (0, 0);
+    }
+
+    fn record_period(&mut self, bytes: usize) {
+        if let Some(budget) = self.budget {
+            let index: u64 = budget.period.index(Timestamp::now());
+            if self.period.0 != index {
+                self.period = (index, 0);
+            }
+            self.period.1 = self.period.1.saturating_add(bytes as u64);
+        }
+    }
+
+    /// Bytes transferred in the current budget period
+    fn usage(&self) -> u64 {
+        match self.budget {
+            Some(budget) if self.period.0 == budget.period.index(Timestamp::now()) => self.period.1,
+            _ => 0,
+        }
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        self.budget
+            .map_or(false, |budget| self.usage() >= budget.bytes)
+    }
+}
+
+/// REQ sent to a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentReq {
+    /// Subscription id
+    pub subscription_id: SubscriptionId,
+    /// Filters
+    pub filters: Vec<SubscriptionFilter>,
+    /// Timestamp of the write to the WebSocket
+    pub sent_at: Timestamp,
+}
+
+impl SentReq {
+    pub(crate) fn new(subscription_id: SubscriptionId, filters: Vec<SubscriptionFilter>) -> Self {
+        Self {
+            subscription_id,
+            filters,
+            sent_at: Timestamp::now(),
+        }
+    }
+
+    /// Human-readable description of the filters (see [`SubscriptionFilter::explain`])
+    pub fn explain(&self) -> String {
+        self.filters
+            .iter()
+            .map(|f| f.explain())
+            .collect::<Vec<String>>()
+            .join(" OR ")
+    }
+}
+
+/// Log of the latest REQs sent to a relay
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReqLog {
+    reqs: VecDeque<SentReq>,
+}
+
+impl ReqLog {
+    pub fn record(&mut self, req: SentReq) {
+        if self.reqs.len() >= MAX_SENT_REQS {
+            self.reqs.pop_front();
+        }
+        self.reqs.push_back(req);
+    }
+
+    pub fn entries(&self) -> Vec<SentReq> {
+        self.reqs.iter().cloned().collect()
+    }
+}
+
+/// Received events per second, counted in one second buckets over the last minute
+#[derive(Debug)]
+pub(crate) struct EventRate {
+    start: Instant,
+    buckets: VecDeque<(u64, u64)>,
+}
+
+impl Default for EventRate {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: VecDeque::new(),
+        }
+    }
+}
+
+impl EventRate {
+    pub fn record(&mut self) {
+        let now: u64 = self.start.elapsed().as_secs();
+        match self.buckets.back_mut() {
+            Some((second, count)) if *second == now => *count += 1,
+            _ => self.buckets.push_back((now, 1)),
+        }
+        while let Some((second, _)) = self.buckets.front() {
+            if now - second < EVENT_RATE_WINDOW {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Average over the last minute (or since the start, if more recent)
+    pub fn per_second(&self) -> f64 {
+        let now: u64 = self.start.elapsed().as_secs();
+        let events: u64 = self
+            .buckets
+            .iter()
+            .filter(|(second, _)| now - second < EVENT_RATE_WINDOW)
+            .map(|(_, count)| count)
+            .sum();
+        let window: u64 = (now + 1).min(EVENT_RATE_WINDOW);
+        events as f64 / window as f64
+    }
+}
+
+/// Relay statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayStats {
+    /// Average time between REQ and `EOSE` (exponential moving average)
+    pub eose_latency: Option<Duration>,
+    /// Number of `EOSE` measurements
+    pub eose_samples: u64,
+    /// Average time between EVENT and `OK` (exponential moving average)
+    pub ok_latency: Option<Duration>,
+    /// Number of `OK` measurements
+    pub ok_samples: u64,
+    /// Current timeout for `EOSE`
+    pub eose_timeout: Duration,
+    /// Current timeout for `OK`
+    pub ok_timeout: Duration,
+    /// Whether the timeouts are adaptive
+    pub adaptive: bool,
+    /// Bytes sent since the relay was added
+    pub bytes_sent: u64,
+    /// Bytes received since the relay was added
+    pub bytes_received: u64,
+    /// Bandwidth budget
+    pub budget: Option<BandwidthBudget>,
+    /// Bytes transferred in the current budget period
+    pub budget_usage: u64,
+    /// Whether the budget of the current period is exhausted
+    pub over_budget: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_and_timeouts() {
+        let mut latency = Latency::default();
+        latency.record(Duration::from_millis(1000));
+        assert_eq!(latency.average, Some(Duration::from_millis(1000)));
+        latency.record(Duration::from_millis(2000));
+        assert_eq!(latency.average, Some(Duration::from_millis(1200)));
+        assert_eq!(latency.samples, 2);
+
+        let timeouts = Timeouts::new(true, Duration::from_secs(10));
+        assert_eq!(timeouts.get(None), Duration::from_secs(10));
+        assert_eq!(
+            timeouts.get(Some(Duration::from_millis(1200))),
+            Duration::from_millis(4800)
+        );
+        assert_eq!(
+            timeouts.get(Some(Duration::from_millis(10))),
+            MIN_ADAPTIVE_TIMEOUT
+        );
+        assert_eq!(
+            timeouts.get(Some(Duration::from_secs(5))),
+            Duration::from_secs(10)
+        );
+
+        // Not adaptive
+        timeouts.update(&Timeouts::new(false, Duration::from_secs(3)));
+        assert_eq!(
+            timeouts.get(Some(Duration::from_millis(10))),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn test_bandwidth_counters() {
+        let mut bandwidth = Bandwidth::default();
+        bandwidth.record_sent(100);
+        bandwidth.record_received(250);
+        bandwidth.record_received(50);
+
+        let stats = Latencies::default().stats(&Timeouts::default(), &bandwidth);
+        assert_eq!(stats.bytes_sent, 100);
+        assert_eq!(stats.bytes_received, 300);
+        assert_eq!(stats.budget, None);
+        assert_eq!(stats.budget_usage, 0);
+        assert!(!stats.over_budget);
+
+        bandwidth.record_sent(usize::MAX);
+        bandwidth.record_sent(1);
+        assert_eq!(bandwidth.sent, u64::MAX);
+    }
+
+    #[test]
+    fn test_bandwidth_budget() {
+        let mut bandwidth = Bandwidth::default();
+        bandwidth.record_sent(1000);
+
+        // Bytes transferred before the budget are not counted
+        bandwidth.set_budget(Some(BandwidthBudget::daily(100)));
+        assert_eq!(bandwidth.usage(), 0);
+        assert!(!bandwidth.is_over_budget());
+
+        bandwidth.record_sent(60);
+        bandwidth.record_received(39);
+        assert_eq!(bandwidth.usage(), 99);
+        assert!(!bandwidth.is_over_budget());
+
+        // Exhausted when the usage reaches the budget
+        bandwidth.record_received(1);
+        assert_eq!(bandwidth.usage(), 100);
+        assert!(bandwidth.is_over_budget());
+        let stats = Latencies::default().stats(&Timeouts::default(), &bandwidth);
+        assert_eq!(stats.budget, Some(BandwidthBudget::daily(100)));
+        assert_eq!(stats.budget_usage, 100);
+        assert!(stats.over_budget);
+
+        // A new period starts from zero
+        bandwidth.period.0 -= 1;
+        assert_eq!(bandwidth.usage(), 0);
+        assert!(!bandwidth.is_over_budget());
+        bandwidth.record_sent(10);
+        assert_eq!(bandwidth.usage(), 10);
+
+        // Changing the budget resets the usage
+        bandwidth.record_sent(200);
+        assert!(bandwidth.is_over_budget());
+        bandwidth.set_budget(Some(BandwidthBudget::monthly(1000)));
+        assert!(!bandwidth.is_over_budget());
+        bandwidth.set_budget(None);
+        bandwidth.record_sent(2000);
+        assert!(!bandwidth.is_over_budget());
+        assert_eq!(bandwidth.sent, 3270);
+    }
+
+    #[test]
+    fn test_budget_period() {
+        // 2024-02-29T23:59:59Z and 2024-03-01T00:00:00Z
+        let end_of_february = Timestamp::from(1709251199);
+        let start_of_march = Timestamp::from(1709251200);
+        assert_eq!(
+            year_month(end_of_february.as_u64() / SECS_PER_DAY),
+            (2024, 2)
+        );
+        assert_eq!(
+            year_month(start_of_march.as_u64() / SECS_PER_DAY),
+            (2024, 3)
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.index(start_of_march),
+            BudgetPeriod::Monthly.index(end_of_february) + 1
+        );
+        assert_eq!(
+            BudgetPeriod::Daily.index(start_of_march),
+            BudgetPeriod::Daily.index(end_of_february) + 1
+        );
+        // 2023-12-31 and 2024-01-01
+        assert_eq!(
+            BudgetPeriod::Monthly.index(Timestamp::from(1704067200)),
+            BudgetPeriod::Monthly.index(Timestamp::from(1704067199)) + 1
+        );
+        assert_eq!(year_month(0), (1970, 1));
+    }
+
+    #[test]
+    fn test_req_log() {
+        let mut log = ReqLog::default();
+        for i in 0..MAX_SENT_REQS + 10 {
+            log.record(SentReq::new(SubscriptionId::new(i.to_string()), Vec::new()));
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), MAX_SENT_REQS);
+        assert_eq!(entries[0].subscription_id, SubscriptionId::new("10"));
+        assert_eq!(
+            entries[MAX_SENT_REQS - 1].subscription_id,
+            SubscriptionId::new((MAX_SENT_REQS + 9).to_string())
+        );
+    }
+
+    #[test]
+    fn test_event_rate() {
+        let mut rate = EventRate::default();
+        assert_eq!(rate.per_second(), 0.0);
+        for _ in 0..5 {
+            rate.record();
+        }
+        // Less than one second since the start
+        assert_eq!(rate.per_second(), 5.0);
+
+        // Buckets out of the window are ignored
+        rate.start -= Duration::from_secs(EVENT_RATE_WINDOW);
+        assert_eq!(rate.per_second(), 0.0);
+        rate.record();
+        assert_eq!(rate.buckets.len(), 1);
+        assert_eq!(rate.per_second(), 1.0 / EVENT_RATE_WINDOW as f64);
+    }
+}
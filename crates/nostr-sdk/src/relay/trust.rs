@@ -0,0 +1,282 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay trust
+
+use std::collections::HashMap;
+use std::fmt;
+
+use nostr::Url;
+
+/// Relay trust level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrustLevel {
+    /// Never authenticate, send direct messages or publish deletions
+    Untrusted,
+    /// Send direct messages and publish deletions but don't authenticate automatically
+    #[default]
+    Normal,
+    /// Everything is allowed, including automatic authentication (NIP-42)
+    Trusted,
+}
+
+impl TrustLevel {
+    /// Check if [`RelayAction`] is allowed by this level
+    pub fn allows(&self, action: RelayAction) -> bool {
+        match self {
+            Self::Untrusted => false,
+            Self::Normal => !matches!(action, RelayAction::Authenticate),
+            Self::Trusted => true,
+        }
+    }
+}
+
+impl fmt::Display for TrustLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Untrusted => write!(f, "Untrusted"),
+            Self::Normal => write!(f, "Normal"),
+            Self::Trusted => write!(f, "Trusted"),
+        }
+    }
+}
+
+/// Sensitive action that the client may perform with a relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelayAction {
+    /// Automatic authentication (NIP-42)
+    Authenticate,
+    /// Send direct message (NIP-04)
+    SendDirectMessage,
+    /// Publish event deletion (NIP-09)
+    PublishDeletion,
+}
+
+/// Trust policy
+///
+/// Implement this trait to enforce custom relay policies (ex. load them from a central configuration).
+pub trait TrustPolicy: fmt::Debug + Send + Sync {
+    /// Check if [`RelayAction`] is allowed for relay
+    fn is_allowed(&self, relay_url: &Url, action: RelayAction) -> bool;
+}
+
+/// Per-relay trust configuration (allowlist/denylist)
+#[derive(Debug, Clone, Default)]
+pub struct RelayTrust {
+    default_level: TrustLevel,
+    levels: HashMap<Url, TrustLevel>,
+}
+
+impl RelayTrust {
+    /// Create new [`RelayTrust`]
+    ///
+    /// All relays start with [`TrustLevel::Normal`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set [`TrustLevel`] of relays not present in allowlist or denylist
+    pub fn default_level(self, level: TrustLevel) -> Self {
+        Self {
+            default_level: level,
+            ..self
+        }
+    }
+
+    /// Set [`TrustLevel`] of relay
+    pub fn level(mut self, relay_url: Url, level: TrustLevel) -> Self {
+        self.levels.insert(relay_url, level);
+        self
+    }
+
+    /// Add relay to allowlist ([`TrustLevel::Trusted`])
+    pub fn allow(self, relay_url: Url) -> Self {
+        self.level(relay_url, TrustLevel::Trusted)
+    }
+
+    /// Add relay to denylist ([`TrustLevel::Untrusted`])
+    pub fn deny(self, relay_url: Url) -> Self {
+        self.level(relay_url, TrustLevel::Untrusted)
+    }
+
+    /// Get [`TrustLevel`] of relay
+    pub fn get_level(&self, relay_url: &Url) -> TrustLevel {
+        self.levels
+            .get(relay_url)
+            .copied()
+            .unwrap_or(self.default_level)
+    }
+}
+
+impl TrustPolicy for RelayTrust {
+    fn is_allowed(&self, relay_url: &Url, action: RelayAction) -> bool {
+        self.get_level(relay_url).allows(action)
+    }
+}
+
+/// Keep only the relays that allow [`RelayAction`]
+pub(crate) fn allowed_relays<I>(
+    policy: &dyn TrustPolicy,
+    relays: I,
+    action: RelayAction,
+) -> Vec<Url>
+where
+    I: IntoIterator<Item = Url>,
+{
+    relays
+        .into_iter()
+        .filter(|url| {
+            let allowed: bool = policy.is_allowed(url, action);
+            if !allowed {
+                log::debug!("Skipped {:?} for {}: relay not trusted", action, url);
+            }
+            allowed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACTIONS: [RelayAction; 3] = [
+        RelayAction::Authenticate,
+        RelayAction::SendDirectMessage,
+        RelayAction::PublishDeletion,
+    ];
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn test_trust_level_allows() {
+        // (level, [authenticate, send direct message, publish deletion])
+        let table = [
+            (TrustLevel::Untrusted, [false, false, false]),
+            (TrustLevel::Normal, [false, true, true]),
+            (TrustLevel::Trusted, [true, true, true]),
+        ];
+        for (level, expected) in table.into_iter() {
+            for (action, allowed) in ACTIONS.into_iter().zip(expected) {
+                assert_eq!(level.allows(action), allowed, "{level} {action:?}");
+            }
+        }
+        assert_eq!(TrustLevel::default(), TrustLevel::Normal);
+    }
+
+    #[test]
+    fn test_relay_trust() {
+        let allowed = url("wss://allowed.example.com");
+        let denied = url("wss://denied.example.com");
+        let other = url("wss://other.example.com");
+
+        // (policy, relay, level)
+        let table = [
+            (RelayTrust::new(), &other, TrustLevel::Normal),
+            (
+                RelayTrust::new().allow(allowed.clone()),
+                &allowed,
+                TrustLevel::Trusted,
+            ),
+            (
+                RelayTrust::new().deny(denied.clone()),
+                &denied,
+                TrustLevel::Untrusted,
+            ),
+            (
+                RelayTrust::new().allow(allowed.clone()),
+                &other,
+                TrustLevel::Normal,
+            ),
+            (
+                RelayTrust::new().default_level(TrustLevel::Untrusted),
+                &other,
+                TrustLevel::Untrusted,
+            ),
+            (
+                RelayTrust::new()
+                    .default_level(TrustLevel::Untrusted)
+                    .allow(allowed.clone()),
+                &allowed,
+                TrustLevel::Trusted,
+            ),
+            (
+                RelayTrust::new()
+                    .default_level(TrustLevel::Trusted)
+                    .deny(denied.clone()),
+                &denied,
+                TrustLevel::Untrusted,
+            ),
+            // Last level set wins
+            (
+                RelayTrust::new().deny(denied.clone()).allow(denied.clone()),
+                &denied,
+                TrustLevel::Trusted,
+            ),
+            (
+                RelayTrust::new().level(other.clone(), TrustLevel::Normal),
+                &other,
+                TrustLevel::Normal,
+            ),
+        ];
+        for (policy, relay, level) in table.into_iter() {
+            assert_eq!(policy.get_level(relay), level, "{relay}");
+            for action in ACTIONS.into_iter() {
+                assert_eq!(
+                    policy.is_allowed(relay, action),
+                    level.allows(action),
+                    "{relay} {action:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_allowed_relays() {
+        let allowed = url("wss://allowed.example.com");
+        let denied = url("wss://denied.example.com");
+        let other = url("wss://other.example.com");
+        let relays = vec![allowed.clone(), denied.clone(), other.clone()];
+        let policy = RelayTrust::new().allow(allowed.clone()).deny(denied);
+
+        assert_eq!(
+            allowed_relays(&policy, relays.clone(), RelayAction::Authenticate),
+            vec![allowed.clone()]
+        );
+        for action in [RelayAction::SendDirectMessage, RelayAction::PublishDeletion] {
+            assert_eq!(
+                allowed_relays(&policy, relays.clone(), action),
+                vec![allowed.clone(), other.clone()]
+            );
+        }
+
+        let policy = RelayTrust::new().default_level(TrustLevel::Untrusted);
+        assert!(allowed_relays(&policy, relays, RelayAction::PublishDeletion).is_empty());
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        /// Only direct messages, only to onion relays
+        #[derive(Debug)]
+        struct OnionOnly;
+
+        impl TrustPolicy for OnionOnly {
+            fn is_allowed(&self, relay_url: &Url, action: RelayAction) -> bool {
+                action == RelayAction::SendDirectMessage
+                    && relay_url
+                        .host_str()
+                        .map_or(false, |host| host.ends_with(".onion"))
+            }
+        }
+
+        let onion = url("ws://example.onion");
+        let clearnet = url("wss://example.com");
+        let relays = vec![onion.clone(), clearnet];
+        assert_eq!(
+            allowed_relays(&OnionOnly, relays.clone(), RelayAction::SendDirectMessage),
+            vec![onion]
+        );
+        assert!(allowed_relays(&OnionOnly, relays, RelayAction::PublishDeletion).is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Signature verification worker pool
+//!
+//! Incoming events are verified outside of the relay pool task, so a burst of events
+//! can't stall the relay readers (and their ping/pong handling).
+//!
+//! Valid events are remembered in an LRU cache, so the same event delivered by many relays
+//! (or fetched again later) is verified once.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError};
+
+use nostr::secp256k1::schnorr::Signature;
+use nostr::secp256k1::{Message, Secp256k1, VerifyOnly};
+use nostr::url::Url;
+use nostr::{Event, EventId};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::Mutex;
+
+use crate::thread;
+
+/// Max number of events waiting for verification
+const QUEUE_CAPACITY: usize = 4096;
+/// Max number of workers
+const MAX_WORKERS: usize = 4;
+/// Max number of valid events remembered by the cache
+const CACHE_CAPACITY: usize = 16384;
+
+type Job = (Url, Box<Event>);
+/// Verified event, with the result of the check
+pub(crate) type Verified = (Url, Box<Event>, bool);
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    queue_depth: AtomicUsize,
+    workers: AtomicUsize,
+    verified: AtomicU64,
+    invalid: AtomicU64,
+    dropped: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Counters {
+    pub fn stats(&self) -> VerificationStats {
+        VerificationStats {
+            queue_depth: self.queue_depth.load(Ordering::SeqCst),
+            queue_capacity: QUEUE_CAPACITY,
+            workers: self.workers.load(Ordering::SeqCst),
+            verified: self.verified.load(Ordering::SeqCst),
+            invalid: self.invalid.load(Ordering::SeqCst),
+            dropped: self.dropped.load(Ordering::SeqCst),
+            cache_hits: self.cache_hits.load(Ordering::SeqCst),
+            cache_misses: self.cache_misses.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Signature verification statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationStats {
+    /// Events waiting for verification
+    pub queue_depth: usize,
+    /// Max number of events waiting for verification
+    pub queue_capacity: usize,
+    /// Number of verification workers
+    pub workers: usize,
+    /// Events with valid signature
+    pub verified: u64,
+    /// Events with invalid signature
+    pub invalid: u64,
+    /// Events dropped because the queue was full
+    pub dropped: u64,
+    /// Events already verified, found in the cache
+    pub cache_hits: u64,
+    /// Events not in the cache, verified
+    pub cache_misses: u64,
+}
+
+/// Id computed from the event fields and signature: the same key always has the same
+/// verification result, whatever the claimed `id`.
+type CacheKey = (EventId, Signature);
+
+/// LRU cache of the valid events
+#[derive(Debug, Default)]
+struct VerifiedCache {
+    ticks: HashMap<CacheKey, u64>,
+    lru: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl VerifiedCache {
+    /// Check if `key` is cached, marking it as recently used
+    fn contains(&mut self, key: &CacheKey) -> bool {
+        self.tick += 1;
+        match self.ticks.get_mut(key) {
+            Some(tick) => {
+                self.lru.remove(tick);
+                *tick = self.tick;
+                self.lru.insert(self.tick, *key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: CacheKey) {
+        self.tick += 1;
+        if let Some(tick) = self.ticks.insert(key, self.tick) {
+            self.lru.remove(&tick);
+        }
+        self.lru.insert(self.tick, key);
+
+        while self.ticks.len() > CACHE_CAPACITY {
+            let oldest: u64 = match self.lru.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(key) = self.lru.remove(&oldest) {
+                self.ticks.remove(&key);
+            }
+        }
+    }
+}
+
+type SharedCache = Arc<std::sync::Mutex<VerifiedCache>>;
+
+fn lock(cache: &SharedCache) -> std::sync::MutexGuard<'_, VerifiedCache> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Verify `event` signature, skipping the check if `event` is in the cache
+fn verify(
+    secp: &Secp256k1<VerifyOnly>,
+    cache: &SharedCache,
+    counters: &Counters,
+    event: &Event,
+) -> bool {
+    let id = EventId::new(
+        &event.pubkey,
+        event.created_at,
+        &event.kind,
+        &event.tags,
+        &event.content,
+    );
+    let key: CacheKey = (id, event.sig);
+    if lock(cache).contains(&key) {
+        counters.cache_hits.fetch_add(1, Ordering::SeqCst);
+        return true;
+    }
+    counters.cache_misses.fetch_add(1, Ordering::SeqCst);
+
+    let valid: bool = match Message::from_slice(id.as_bytes()) {
+        Ok(message) => secp
+            .verify_schnorr(&event.sig, &message, &event.pubkey)
+            .is_ok(),
+        Err(_) => false,
+    };
+    if valid {
+        lock(cache).insert(key);
+    }
+    valid
+}
+
+/// Verify `event`, updating the counters
+fn check(
+    secp: &Secp256k1<VerifyOnly>,
+    cache: &SharedCache,
+    counters: &Counters,
+    relay_url: &Url,
+    event: &Event,
+) -> bool {
+    if verify(secp, cache, counters, event) {
+        counters.verified.fetch_add(1, Ordering::SeqCst);
+        true
+    } else {
+        counters.invalid.fetch_add(1, Ordering::SeqCst);
+        log::debug!(
+            "Invalid signature for event {} from {}",
+            event.id,
+            relay_url
+        );
+        false
+    }
+}
+
+/// Verification worker pool
+///
+/// Verified events (valid or not) are sent to the `verified` channel.
+/// The workers exit when the [`Verifier`] is dropped.
+#[derive(Debug)]
+pub(crate) struct Verifier {
+    sender: Sender<Job>,
+    counters: Arc<Counters>,
+    cache: SharedCache,
+    secp: Secp256k1<VerifyOnly>,
+}
+
+impl Verifier {
+    pub fn new(verified: Sender<Verified>, counters: Arc<Counters>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>(QUEUE_CAPACITY);
+        let receiver: Arc<Mutex<Receiver<Job>>> = Arc::new(Mutex::new(receiver));
+
+        let workers: usize = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_WORKERS);
+        counters.workers.store(workers, Ordering::SeqCst);
+        let cache: SharedCache = Arc::new(std::sync::Mutex::new(VerifiedCache::default()));
+
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            let verified = verified.clone();
+            let counters = counters.clone();
+            let cache = cache.clone();
+            thread::spawn(async move {
+                log::debug!("Verification Worker Started");
+                let secp = Secp256k1::verification_only();
+                loop {
+                    let job: Option<Job> = receiver.lock().await.recv().await;
+                    let (relay_url, event) = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    counters.queue_depth.fetch_sub(1, Ordering::SeqCst);
+
+                    let valid: bool = check(&secp, &cache, &counters, &relay_url, &event);
+                    if verified.send((relay_url, event, valid)).await.is_err() {
+                        break;
+                    }
+                }
+                log::debug!("Exited from Verification Worker");
+            });
+        }
+
+        Self {
+            sender,
+            counters,
+            cache,
+            secp: Secp256k1::verification_only(),
+        }
+    }
+
+    /// Queue event for verification, without waiting
+    ///
+    /// If the queue is full the event is dropped: return `false`.
+    pub fn verify(&self, relay_url: Url, event: Box<Event>) -> bool {
+        self.counters.queue_depth.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.try_send((relay_url, event)) {
+            self.counters.queue_depth.fetch_sub(1, Ordering::SeqCst);
+            match e {
+                TrySendError::Full((relay_url, event)) => {
+                    self.counters.dropped.fetch_add(1, Ordering::SeqCst);
+                    log::warn!(
+                        "Verification queue full: event {} from {} dropped",
+                        event.id,
+                        relay_url
+                    );
+                }
+                TrySendError::Closed(_) => log::error!("Verification workers exited"),
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Verify event in the current task, sharing the cache and the counters of the workers
+    pub fn verify_now(&self, relay_url: &Url, event: &Event) -> bool {
+        check(&self.secp, &self.cache, &self.counters, relay_url, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    fn key(i: usize) -> CacheKey {
+        let mut id = [0u8; 32];
+        id[..8].copy_from_slice(&(i as u64).to_be_bytes());
+        let sig = Signature::from_slice(&[1u8; 64]).unwrap();
+        (EventId::from_slice(&id).unwrap(), sig)
+    }
+
+    #[test]
+    fn test_cache_hits_and_misses() {
+        let secp = Secp256k1::verification_only();
+        let cache: SharedCache = SharedCache::default();
+        let counters = Counters::default();
+        let keys = Keys::generate();
+        let event: Event = EventBuilder::new_text_note("GM", &[])
+            .to_event(&keys)
+            .unwrap();
+
+        assert!(verify(&secp, &cache, &counters, &event));
+        assert!(verify(&secp, &cache, &counters, &event));
+        let stats = counters.stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (1, 1));
+
+        // Same id, another valid signature: verified again, then cached
+        let message = Message::from_slice(event.id.as_bytes()).unwrap();
+        let mut resigned: Event = event.clone();
+        resigned.sig = Secp256k1::new().sign_schnorr_with_aux_rand(
+            &message,
+            &keys.key_pair().unwrap(),
+            &[7; 32],
+        );
+        assert_ne!(resigned.sig, event.sig);
+        assert!(verify(&secp, &cache, &counters, &resigned));
+        assert!(verify(&secp, &cache, &counters, &resigned));
+        let stats = counters.stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (2, 2));
+
+        // Same id, invalid signature: never cached
+        let other: Event = EventBuilder::new_text_note("GN", &[])
+            .to_event(&keys)
+            .unwrap();
+        let mut forged: Event = event.clone();
+        forged.sig = other.sig;
+        assert!(!verify(&secp, &cache, &counters, &forged));
+        assert!(!verify(&secp, &cache, &counters, &forged));
+        let stats = counters.stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (2, 4));
+
+        // Claimed id doesn't matter, the key is computed from the event fields
+        let mut claimed: Event = event;
+        claimed.id = other.id;
+        assert!(verify(&secp, &cache, &counters, &claimed));
+        assert_eq!(counters.stats().cache_hits, 3);
+    }
+
+    #[test]
+    fn test_cache_lru_eviction() {
+        let mut cache = VerifiedCache::default();
+        for i in 0..CACHE_CAPACITY {
+            cache.insert(key(i));
+        }
+        assert_eq!(cache.ticks.len(), CACHE_CAPACITY);
+
+        // The oldest key is used again, so the second oldest is evicted
+        assert!(cache.contains(&key(0)));
+        cache.insert(key(CACHE_CAPACITY));
+        assert_eq!(cache.ticks.len(), CACHE_CAPACITY);
+        assert_eq!(cache.lru.len(), CACHE_CAPACITY);
+        assert!(cache.contains(&key(0)));
+        assert!(!cache.contains(&key(1)));
+        assert!(cache.contains(&key(2)));
+        assert!(cache.contains(&key(CACHE_CAPACITY)));
+
+        // Inserting a cached key again doesn't evict anything
+        cache.insert(key(3));
+        assert_eq!(cache.ticks.len(), CACHE_CAPACITY);
+        assert!(cache.contains(&key(4)));
+    }
+}
@@ -0,0 +1,510 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Scenario relay
+//!
+//! Local relay playing a scripted [`Scenario`], for reproducible tests of the pool logic and of apps.
+
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use nostr::url::{self, Url};
+use nostr::{ClientMessage, Event, RelayMessage};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::thread;
+
+/// [`ScenarioRelay`] error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+    /// Url parse error
+    #[error("impossible to parse URL: {0}")]
+    Url(#[from] url::ParseError),
+}
+
+/// Scenario step
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Wait for the next client message
+    Receive,
+    /// Wait for a `REQ` and answer with the events matching its filters, followed by `EOSE`
+    RespondToReq(Vec<Event>),
+    /// Wait for an `EVENT` and answer with `OK`
+    AckEvent {
+        /// Event accepted
+        accepted: bool,
+        /// Message
+        message: String,
+    },
+    /// Send [`RelayMessage`]
+    Send(RelayMessage),
+    /// Sleep before the next step
+    Sleep(Duration),
+    /// Close the connection
+    ///
+    /// The following steps are played on the next connection.
+    Disconnect,
+}
+
+/// Scenario
+///
+/// Steps are played in order, across connections. When all the steps are played,
+/// the relay keeps the connection open and records the client messages without answering.
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    steps: VecDeque<Step>,
+}
+
+impl Scenario {
+    /// New empty [`Scenario`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add [`Step`]
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push_back(step);
+        self
+    }
+
+    /// Add [`Step::RespondToReq`]
+    pub fn respond_to_req(self, events: Vec<Event>) -> Self {
+        self.step(Step::RespondToReq(events))
+    }
+
+    /// Add [`Step::AckEvent`] accepting the event
+    pub fn accept_event(self) -> Self {
+        self.step(Step::AckEvent {
+            accepted: true,
+            message: String::new(),
+        })
+    }
+
+    /// Add [`Step::AckEvent`] rejecting the event
+    pub fn reject_event<S>(self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.step(Step::AckEvent {
+            accepted: false,
+            message: message.into(),
+        })
+    }
+
+    /// Add [`Step::Send`]
+    pub fn send(self, msg: RelayMessage) -> Self {
+        self.step(Step::Send(msg))
+    }
+
+    /// Add [`Step::Sleep`]
+    pub fn sleep(self, duration: Duration) -> Self {
+        self.step(Step::Sleep(duration))
+    }
+
+    /// Add [`Step::Disconnect`]
+    pub fn disconnect(self) -> Self {
+        self.step(Step::Disconnect)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    received: Mutex<Vec<ClientMessage>>,
+    connections: AtomicUsize,
+    finished: AtomicBool,
+    finished_notify: Notify,
+    shutdown: Notify,
+}
+
+/// Relay playing a [`Scenario`]
+///
+/// Listen on `127.0.0.1` with a random port: add [`ScenarioRelay::url`] to the client or pool under test.
+#[derive(Debug, Clone)]
+pub struct ScenarioRelay {
+    url: Url,
+    state: Arc<State>,
+}
+
+impl ScenarioRelay {
+    /// Start relay playing `scenario`
+    pub fn start(scenario: Scenario) -> Result<Self, Error> {
+        let listener = std::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))?;
+        listener.set_nonblocking(true)?;
+        let url = Url::parse(&format!("ws://{}", listener.local_addr()?))?;
+        let state = Arc::new(State::default());
+
+        let relay = Self {
+            url,
+            state: state.clone(),
+        };
+        thread::spawn(async move {
+            let listener = match TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Impossible to start scenario relay: {}", e);
+                    return;
+                }
+            };
+            tokio::select! {
+                _ = play(listener, scenario, state.clone()) => {},
+                _ = state.shutdown.notified() => {},
+            }
+            log::debug!("Scenario relay stopped");
+        });
+
+        Ok(relay)
+    }
+
+    /// Get relay url
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Get the messages received from clients, in order
+    pub fn received(&self) -> Vec<ClientMessage> {
+        self.state
+            .received
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Number of accepted WebSocket connections
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::SeqCst)
+    }
+
+    /// Check if all the steps were played
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::SeqCst)
+    }
+
+    /// Wait until all the steps are played
+    ///
+    /// Return `false` if the timeout expires before.
+    pub async fn wait_finished(&self, timeout: Duration) -> bool {
+        let notified = self.state.finished_notify.notified();
+        if self.is_finished() {
+            return true;
+        }
+        tokio::time::timeout(timeout, notified).await.is_ok()
+    }
+
+    /// Stop relay
+    pub fn stop(&self) {
+        self.state.shutdown.notify_one();
+    }
+}
+
+async fn play(listener: TcpListener, mut scenario: Scenario, state: Arc<State>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                log::error!("Scenario relay impossible to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        // Non-WebSocket requests (ex. NIP-11 documents) are dropped
+        let mut ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                log::debug!("Scenario relay rejected connection: {}", e);
+                continue;
+            }
+        };
+        state.connections.fetch_add(1, Ordering::SeqCst);
+
+        if play_connection(&mut ws, &mut scenario, &state).await {
+            let _ = ws.close(None).await;
+        }
+    }
+}
+
+/// Play steps until [`Step::Disconnect`] (return `true`) or until the client disconnects (return `false`)
+///
+/// A step interrupted by the client disconnection is played again on the next connection.
+async fn play_connection(
+    ws: &mut WebSocketStream<TcpStream>,
+    scenario: &mut Scenario,
+    state: &State,
+) -> bool {
+    while let Some(step) = scenario.steps.pop_front() {
+        let open: bool = match &step {
+            Step::Receive => receive(ws, state).await.is_some(),
+            Step::RespondToReq(events) => loop {
+                match receive(ws, state).await {
+                    Some(ClientMessage::Req {
+                        subscription_id,
+                        filters,
+                    }) => {
+                        let mut msgs: Vec<RelayMessage> = Vec::new();
+                        for filter in filters.iter() {
+                            let matches = events.iter().filter(|e| filter.match_event(e));
+                            for event in matches.take(filter.limit.unwrap_or(usize::MAX)) {
+                                msgs.push(RelayMessage::new_event(
+                                    subscription_id.clone(),
+                                    event.clone(),
+                                ));
+                            }
+                        }
+                        msgs.push(RelayMessage::new_eose(subscription_id));
+                        break send_all(ws, msgs).await;
+                    }
+                    Some(_) => continue,
+                    None => break false,
+                }
+            },
+            Step::AckEvent { accepted, message } => loop {
+                match receive(ws, state).await {
+                    Some(ClientMessage::Event(event)) => {
+                        let msg = RelayMessage::new_ok(event.id, *accepted, message.clone());
+                        break send_all(ws, vec![msg]).await;
+                    }
+                    Some(_) => continue,
+                    None => break false,
+                }
+            },
+            Step::Send(msg) => send_all(ws, vec![msg.clone()]).await,
+            Step::Sleep(duration) => {
+                tokio::time::sleep(*duration).await;
+                true
+            }
+            Step::Disconnect => {
+                finish_if_empty(scenario, state);
+                return true;
+            }
+        };
+
+        if !open {
+            log::debug!("Scenario relay connection closed by client");
+            scenario.steps.push_front(step);
+            return false;
+        }
+    }
+
+    finish_if_empty(scenario, state);
+
+    // Record messages until the client disconnects
+    while receive(ws, state).await.is_some() {}
+    false
+}
+
+fn finish_if_empty(scenario: &Scenario, state: &State) {
+    if scenario.steps.is_empty() && !state.finished.swap(true, Ordering::SeqCst) {
+        state.finished_notify.notify_waiters();
+    }
+}
+
+/// Receive and record next client message
+///
+/// Return `None` if the connection is closed.
+async fn receive(ws: &mut WebSocketStream<TcpStream>, state: &State) -> Option<ClientMessage> {
+    while let Some(msg) = ws.next().await {
+        let text: String = match msg {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => continue,
+        };
+        match ClientMessage::from_json(&text) {
+            Ok(msg) => {
+                state
+                    .received
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(msg.clone());
+                return Some(msg);
+            }
+            Err(e) => log::warn!("Scenario relay received invalid message {}: {}", text, e),
+        }
+    }
+    None
+}
+
+/// Return `false` if the connection is closed
+async fn send_all(ws: &mut WebSocketStream<TcpStream>, msgs: Vec<RelayMessage>) -> bool {
+    for msg in msgs.into_iter() {
+        if ws.send(Message::Text(msg.as_json())).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use nostr::{EventBuilder, Keys, Kind, SubscriptionFilter};
+    use tokio::sync::broadcast::Receiver;
+
+    use super::*;
+    use crate::{RelayPool, RelayPoolNotification, RelayStatus};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    async fn connect(relay: &ScenarioRelay) -> (RelayPool, Receiver<RelayPoolNotification>) {
+        let pool = RelayPool::new();
+        let notifications = pool.notifications();
+        pool.add_relay(relay.url(), None).await;
+        pool.connect(true).await;
+        (pool, notifications)
+    }
+
+    async fn next_notification<F, T>(
+        notifications: &mut Receiver<RelayPoolNotification>,
+        mut f: F,
+    ) -> T
+    where
+        F: FnMut(RelayPoolNotification) -> Option<T>,
+    {
+        loop {
+            let notification = tokio::time::timeout(TIMEOUT, notifications.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let Some(value) = f(notification) {
+                return value;
+            }
+        }
+    }
+
+    async fn next_notice(notifications: &mut Receiver<RelayPoolNotification>) -> String {
+        next_notification(notifications, |notification| match notification {
+            RelayPoolNotification::Message(_, RelayMessage::Notice { message }) => Some(message),
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_scripted_messages() {
+        let keys = Keys::generate();
+        let note = EventBuilder::new_text_note("GM", &[])
+            .to_event(&keys)
+            .unwrap();
+        let metadata = EventBuilder::new(Kind::Metadata, "{}", &[])
+            .to_event(&keys)
+            .unwrap();
+        let scenario = Scenario::new()
+            // Subscription sent on connection
+            .step(Step::Receive)
+            .respond_to_req(vec![note.clone(), metadata])
+            .reject_event("blocked: scripted rejection")
+            .send(RelayMessage::new_notice("bye"));
+        let relay = ScenarioRelay::start(scenario).unwrap();
+        let (pool, mut notifications) = connect(&relay).await;
+
+        // Only the events matching the filter, then `EOSE`
+        let filter = SubscriptionFilter::new().kind(Kind::TextNote);
+        let events = pool.get_events_of(vec![filter]).await.unwrap();
+        assert_eq!(events, vec![note]);
+
+        let event = EventBuilder::new_text_note("Hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        pool.send_msg(ClientMessage::new_event(event.clone()), true)
+            .await
+            .unwrap();
+        let ok = next_notification(&mut notifications, |notification| match notification {
+            RelayPoolNotification::Message(_, RelayMessage::Ok { .. }) => Some(notification),
+            _ => None,
+        })
+        .await;
+        match ok {
+            RelayPoolNotification::Message(url, msg) => {
+                assert_eq!(url, relay.url());
+                assert_eq!(
+                    msg,
+                    RelayMessage::new_ok(event.id, false, "blocked: scripted rejection")
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(next_notice(&mut notifications).await, "bye");
+        assert!(relay.wait_finished(TIMEOUT).await);
+        assert_eq!(relay.connections(), 1);
+
+        // `CLOSE` of the `get_events_of` subscription may be received before or after the event
+        let received: Vec<ClientMessage> = relay
+            .received()
+            .into_iter()
+            .filter(|msg| !matches!(msg, ClientMessage::Close(_)))
+            .collect();
+        assert_eq!(received.len(), 3);
+        assert!(matches!(received[0], ClientMessage::Req { .. }));
+        assert!(matches!(received[1], ClientMessage::Req { .. }));
+        assert_eq!(received[2], ClientMessage::new_event(event));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_and_reconnect() {
+        let scenario = Scenario::new()
+            .step(Step::Receive)
+            .send(RelayMessage::new_notice("first"))
+            .disconnect()
+            .step(Step::Receive)
+            .send(RelayMessage::new_notice("second"));
+        let relay = ScenarioRelay::start(scenario).unwrap();
+
+        // The status is notified by the relay, the notice by the pool: any order
+        let (_pool, mut notifications) = connect(&relay).await;
+        let mut notice: Option<String> = None;
+        let mut disconnected: bool = false;
+        next_notification(&mut notifications, |notification| {
+            match notification {
+                RelayPoolNotification::Message(_, RelayMessage::Notice { message }) => {
+                    notice = Some(message)
+                }
+                RelayPoolNotification::RelayStatus {
+                    status: RelayStatus::Disconnected,
+                    ..
+                } => disconnected = true,
+                _ => (),
+            }
+            (notice.is_some() && disconnected).then_some(())
+        })
+        .await;
+        assert_eq!(notice.as_deref(), Some("first"));
+        assert!(!relay.is_finished());
+
+        // The next connection plays the remaining steps
+        let (_pool, mut notifications) = connect(&relay).await;
+        assert_eq!(next_notice(&mut notifications).await, "second");
+        assert!(relay.wait_finished(TIMEOUT).await);
+        assert_eq!(relay.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sleep() {
+        let delay = Duration::from_millis(300);
+        let scenario = Scenario::new()
+            .step(Step::Receive)
+            .send(RelayMessage::new_notice("before"))
+            .sleep(delay)
+            .send(RelayMessage::new_notice("after"));
+        let relay = ScenarioRelay::start(scenario).unwrap();
+
+        let (_pool, mut notifications) = connect(&relay).await;
+        assert_eq!(next_notice(&mut notifications).await, "before");
+        let start = Instant::now();
+        assert!(!relay.wait_finished(Duration::from_millis(50)).await);
+        assert_eq!(next_notice(&mut notifications).await, "after");
+        assert!(start.elapsed() >= delay - Duration::from_millis(50));
+        assert!(relay.wait_finished(TIMEOUT).await);
+
+        relay.stop();
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Social graph
+//!
+//! Walk contact lists (NIP-02) to discover follows of follows.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::ContactList;
+use tokio::sync::Mutex;
+
+use crate::client::{Client, Error, CONTACT_LISTS_BATCH_SIZE};
+
+/// Social graph
+///
+/// Contact lists are fetched in batches and memoized: every public key is requested at most once,
+/// until [`SocialGraph::clear_cache`] is called. Public keys without a contact list are memoized as empty.
+#[derive(Debug, Clone)]
+pub struct SocialGraph {
+    client: Client,
+    batch_size: usize,
+    cache: Arc<Mutex<HashMap<XOnlyPublicKey, ContactList>>>,
+}
+
+impl SocialGraph {
+    /// New [`SocialGraph`]
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            batch_size: CONTACT_LISTS_BATCH_SIZE,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set max number of authors per filter (default: [`CONTACT_LISTS_BATCH_SIZE`])
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Fetch the contact lists of `public_keys` that are not cached yet
+    pub async fn fetch(&self, public_keys: Vec<XOnlyPublicKey>) -> Result<(), Error> {
+        let missing: Vec<XOnlyPublicKey> = {
+            let cache = self.cache.lock().await;
+            public_keys
+                .into_iter()
+                .filter(|pk| !cache.contains_key(pk))
+                .collect()
+        };
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut lists = self
+            .client
+            .get_contact_lists_batched(missing.clone(), self.batch_size)
+            .await?;
+
+        let mut cache = self.cache.lock().await;
+        for public_key in missing.into_iter() {
+            let list: ContactList = lists.remove(&public_key).unwrap_or_default();
+            cache.insert(public_key, list);
+        }
+
+        Ok(())
+    }
+
+    /// Get contact list of `public_key`
+    pub async fn contact_list(&self, public_key: XOnlyPublicKey) -> Result<ContactList, Error> {
+        self.fetch(vec![public_key]).await?;
+        let cache = self.cache.lock().await;
+        Ok(cache.get(&public_key).cloned().unwrap_or_default())
+    }
+
+    /// Get public keys followed by `public_key`
+    pub async fn follows(&self, public_key: XOnlyPublicKey) -> Result<Vec<XOnlyPublicKey>, Error> {
+        Ok(self.contact_list(public_key).await?.public_keys())
+    }
+
+    /// Get public keys reachable from `root` following contact lists up to `depth` hops
+    ///
+    /// Return every reached public key with its distance from `root` (`root` included, with distance `0`).
+    /// Contact lists of each level are fetched with batched filters.
+    pub async fn network(
+        &self,
+        root: XOnlyPublicKey,
+        depth: usize,
+    ) -> Result<HashMap<XOnlyPublicKey, usize>, Error> {
+        let mut distances: HashMap<XOnlyPublicKey, usize> = HashMap::new();
+        distances.insert(root, 0);
+
+        let mut level: Vec<XOnlyPublicKey> = vec![root];
+        for distance in 1..=depth {
+            self.fetch(level.clone()).await?;
+
+            let cache = self.cache.lock().await;
+            let mut next: Vec<XOnlyPublicKey> = Vec::new();
+            for public_key in level.iter() {
+                if let Some(list) = cache.get(public_key) {
+                    for contact in list.contacts.iter() {
+                        if let Entry::Vacant(entry) = distances.entry(contact.pk) {
+                            entry.insert(distance);
+                            next.push(contact.pk);
+                        }
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                break;
+            }
+            level = next;
+        }
+
+        Ok(distances)
+    }
+
+    /// Get follows of follows of `root` that `root` doesn't follow yet
+    ///
+    /// Return every public key with the number of follows of `root` that follow it.
+    pub async fn friends_of_friends(
+        &self,
+        root: XOnlyPublicKey,
+    ) -> Result<HashMap<XOnlyPublicKey, usize>, Error> {
+        let follows: Vec<XOnlyPublicKey> = self.follows(root).await?;
+        self.fetch(follows.clone()).await?;
+
+        let excluded: HashSet<&XOnlyPublicKey> = follows.iter().collect();
+        let cache = self.cache.lock().await;
+        let mut counts: HashMap<XOnlyPublicKey, usize> = HashMap::new();
+        for public_key in follows.iter() {
+            if let Some(list) = cache.get(public_key) {
+                for contact in list.contacts.iter() {
+                    if contact.pk != root && !excluded.contains(&contact.pk) {
+                        *counts.entry(contact.pk).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Clear memoized contact lists
+    pub async fn clear_cache(&self) {
+        self.cache.lock().await.clear();
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Audit log
+//!
+//! Append-only trail of the events signed and published by the client (see `Options::audit_log`).
+
+use nostr::{Event, Timestamp, Url};
+
+/// Audited action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditAction {
+    /// Event signed
+    Signed,
+    /// Event sent to relay
+    Sent {
+        /// Relay url
+        relay: Url,
+    },
+    /// Event not sent to relay
+    SendFailed {
+        /// Relay url
+        relay: Url,
+        /// Error
+        error: String,
+    },
+    /// Event accepted by relay (`OK` with `true` status)
+    Accepted {
+        /// Relay url
+        relay: Url,
+        /// `OK` message
+        message: String,
+    },
+    /// Event rejected by relay (`OK` with `false` status)
+    Rejected {
+        /// Relay url
+        relay: Url,
+        /// `OK` message
+        message: String,
+    },
+}
+
+/// Audit log entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Time of the action
+    pub timestamp: Timestamp,
+    /// Event
+    pub event: Event,
+    /// Action
+    pub action: AuditAction,
+}
@@ -0,0 +1,378 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Account bundle
+//!
+//! Single encrypted file to move an account between apps: profile metadata, contact list,
+//! relay lists, mute list and app data events, optionally with the secret key.
+//!
+//! File layout: `[magic: "NSAB"][version: u8][secret kind: u8][salt: 16 bytes][nonce: 24 bytes][ciphertext]`.
+//! The plaintext is `[public key: 32 bytes][has secret key: u8][secret key: 32 bytes, if present]`
+//! followed by records `[length: u32 big endian][event json]`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use nostr::key::{SecretKey, XOnlyPublicKey};
+use nostr::secp256k1::rand::rngs::OsRng;
+use nostr::secp256k1::rand::RngCore;
+use nostr::{key, Event, Keys, Kind};
+
+use super::encrypted::{self, StoreSecret, NONCE_LEN, SALT_LEN};
+
+const MAGIC: &[u8; 4] = b"NSAB";
+const VERSION: u8 = 1;
+const KEY_LEN: usize = 32;
+
+/// Kinds exported in the bundle: metadata, contact list, mute list (NIP-51),
+/// relay list (NIP-65), DM relay list (NIP-17) and app data (NIP-78)
+pub const ACCOUNT_KINDS: [u64; 6] = [0, 3, 10000, 10002, 10050, 30078];
+
+/// Account bundle error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] nostr::secp256k1::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] nostr::event::Error),
+    /// Encryption error
+    #[error(transparent)]
+    Encryption(#[from] encrypted::Error),
+    /// Invalid bundle
+    #[error("invalid account bundle: {0}")]
+    InvalidBundle(String),
+    /// Secret key not matching the public key of the bundle
+    #[error("secret key mismatch")]
+    KeyMismatch,
+    /// Event not authored by the account
+    #[error("event not authored by the account")]
+    WrongAuthor,
+}
+
+/// Account bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountBundle {
+    /// Public key of the account
+    pub public_key: XOnlyPublicKey,
+    /// Secret key of the account, if exported
+    pub secret_key: Option<SecretKey>,
+    /// Events of the account (see [`ACCOUNT_KINDS`])
+    pub events: Vec<Event>,
+}
+
+impl AccountBundle {
+    /// New [`AccountBundle`] of `keys`
+    ///
+    /// Events not authored by `keys` or not of [`ACCOUNT_KINDS`] are skipped.
+    pub fn new(keys: &Keys, include_secret: bool, events: Vec<Event>) -> Result<Self, Error> {
+        let public_key: XOnlyPublicKey = keys.public_key();
+        let secret_key: Option<SecretKey> = if include_secret {
+            Some(keys.secret_key()?)
+        } else {
+            None
+        };
+        let kinds: Vec<Kind> = ACCOUNT_KINDS.into_iter().map(Kind::from).collect();
+        Ok(Self {
+            public_key,
+            secret_key,
+            events: events
+                .into_iter()
+                .filter(|e| e.pubkey == public_key && kinds.contains(&e.kind))
+                .collect(),
+        })
+    }
+
+    /// Get [`Keys`] of the account (with secret key, if exported)
+    pub fn keys(&self) -> Keys {
+        match self.secret_key {
+            Some(secret_key) => Keys::new(secret_key),
+            None => Keys::from_public_key(self.public_key),
+        }
+    }
+
+    /// Encrypt bundle with `secret`
+    ///
+    /// With [`StoreSecret::Keys`] the bundle can only be opened by the same keys:
+    /// use [`StoreSecret::Passphrase`] to move the account to another app.
+    pub fn encrypt(&self, secret: &StoreSecret) -> Result<Vec<u8>, Error> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let cipher: XChaCha20Poly1305 = secret.cipher(&salt)?;
+
+        // The plaintext holds the secret key: wipe it on success and on failure
+        let mut plaintext: Vec<u8> = Vec::new();
+        let ciphertext = self.write_plaintext(&mut plaintext).and_then(|()| {
+            cipher
+                .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+                .map_err(|_| Error::Encryption(encrypted::Error::Encryption))
+        });
+        encrypted::wipe(&mut plaintext);
+        let ciphertext: Vec<u8> = ciphertext?;
+
+        let mut bundle: Vec<u8> = Vec::with_capacity(6 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        bundle.extend_from_slice(MAGIC);
+        bundle.extend_from_slice(&[VERSION, secret.kind()]);
+        bundle.extend_from_slice(&salt);
+        bundle.extend_from_slice(&nonce);
+        bundle.extend_from_slice(&ciphertext);
+        Ok(bundle)
+    }
+
+    fn write_plaintext(&self, plaintext: &mut Vec<u8>) -> Result<(), Error> {
+        plaintext.extend_from_slice(&self.public_key.serialize());
+        match &self.secret_key {
+            Some(secret_key) => {
+                let mut secret: [u8; KEY_LEN] = secret_key.secret_bytes();
+                plaintext.push(1);
+                plaintext.extend_from_slice(&secret);
+                encrypted::wipe(&mut secret);
+            }
+            None => plaintext.push(0),
+        }
+        for event in self.events.iter() {
+            let json: String = event.as_json()?;
+            let len = u32::try_from(json.len())
+                .map_err(|_| Error::InvalidBundle(String::from("event too large")))?;
+            plaintext.extend_from_slice(&len.to_be_bytes());
+            plaintext.extend_from_slice(json.as_bytes());
+        }
+        Ok(())
+    }
+
+    /// Decrypt bundle with `secret`
+    ///
+    /// The secret key must match the public key and every event must be a valid event of the account.
+    pub fn decrypt(bundle: &[u8], secret: &StoreSecret) -> Result<Self, Error> {
+        let header_len: usize = 6 + SALT_LEN + NONCE_LEN;
+        if bundle.len() < header_len {
+            return Err(Error::InvalidBundle(String::from("truncated header")));
+        }
+        if &bundle[..4] != MAGIC {
+            return Err(Error::InvalidBundle(String::from("wrong magic")));
+        }
+        if bundle[4] != VERSION {
+            return Err(Error::InvalidBundle(format!(
+                "unsupported version {}",
+                bundle[4]
+            )));
+        }
+        if bundle[5] != secret.kind() {
+            return Err(encrypted::Error::SecretMismatch.into());
+        }
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&bundle[6..6 + SALT_LEN]);
+        let nonce = XNonce::from_slice(&bundle[6 + SALT_LEN..header_len]);
+        let mut plaintext: Vec<u8> = secret
+            .cipher(&salt)?
+            .decrypt(nonce, &bundle[header_len..])
+            .map_err(|_| encrypted::Error::Decryption)?;
+        let res = Self::read_plaintext(&plaintext);
+        encrypted::wipe(&mut plaintext);
+        res
+    }
+
+    fn read_plaintext(plaintext: &[u8]) -> Result<Self, Error> {
+        let truncated = || Error::InvalidBundle(String::from("truncated payload"));
+        let public_key =
+            XOnlyPublicKey::from_slice(plaintext.get(..KEY_LEN).ok_or_else(truncated)?)?;
+        let mut pos: usize = KEY_LEN + 1;
+        let secret_key: Option<SecretKey> = match plaintext.get(KEY_LEN) {
+            Some(0) => None,
+            Some(1) => {
+                let bytes: &[u8] = plaintext.get(pos..pos + KEY_LEN).ok_or_else(truncated)?;
+                pos += KEY_LEN;
+                let secret_key = SecretKey::from_slice(bytes)?;
+                if Keys::new(secret_key).public_key() != public_key {
+                    return Err(Error::KeyMismatch);
+                }
+                Some(secret_key)
+            }
+            _ => return Err(truncated()),
+        };
+
+        let mut events: Vec<Event> = Vec::new();
+        while pos < plaintext.len() {
+            let len: &[u8] = plaintext.get(pos..pos + 4).ok_or_else(truncated)?;
+            let len: usize = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            pos += 4;
+            let json: &[u8] = plaintext.get(pos..pos + len).ok_or_else(truncated)?;
+            pos += len;
+            let json = std::str::from_utf8(json)
+                .map_err(|_| Error::InvalidBundle(String::from("invalid UTF-8 event")))?;
+            let event: Event = Event::from_json(json)?;
+            if event.pubkey != public_key {
+                return Err(Error::WrongAuthor);
+            }
+            events.push(event);
+        }
+
+        Ok(Self {
+            public_key,
+            secret_key,
+            events,
+        })
+    }
+
+    /// Save encrypted bundle to file, readable and writable only by the owner
+    pub fn save<P>(&self, path: P, secret: &StoreSecret) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bundle: Vec<u8> = self.encrypt(secret)?;
+        let mut file = encrypted::create_private_file(path.as_ref())?;
+        file.write_all(&bundle)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Load encrypted bundle from file
+    pub fn load<P>(path: P, secret: &StoreSecret) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let bundle: Vec<u8> = fs::read(path)?;
+        Self::decrypt(&bundle, secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::EventBuilder;
+
+    use super::*;
+
+    fn account_events(keys: &Keys) -> Vec<Event> {
+        vec![
+            EventBuilder::new(Kind::Metadata, r#"{"name":"alice"}"#, &[])
+                .to_event(keys)
+                .unwrap(),
+            EventBuilder::new(Kind::ContactList, "", &[])
+                .to_event(keys)
+                .unwrap(),
+            EventBuilder::new_text_note("not exported", &[])
+                .to_event(keys)
+                .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let keys = Keys::generate();
+        let bundle = AccountBundle::new(&keys, true, account_events(&keys)).unwrap();
+        assert_eq!(bundle.events.len(), 2);
+
+        let secret = StoreSecret::Keys(keys.clone());
+        let decrypted = AccountBundle::decrypt(&bundle.encrypt(&secret).unwrap(), &secret).unwrap();
+        assert_eq!(decrypted, bundle);
+        assert_eq!(decrypted.keys(), keys);
+
+        // Without secret key
+        let bundle = AccountBundle::new(&keys, false, account_events(&keys)).unwrap();
+        let decrypted = AccountBundle::decrypt(&bundle.encrypt(&secret).unwrap(), &secret).unwrap();
+        assert_eq!(decrypted.secret_key, None);
+        assert!(decrypted.keys().secret_key().is_err());
+    }
+
+    #[test]
+    fn test_wrong_secret() {
+        let keys = Keys::generate();
+        let bundle = AccountBundle::new(&keys, true, account_events(&keys)).unwrap();
+        let passphrase = StoreSecret::Passphrase(String::from("correct horse"));
+        let encrypted: Vec<u8> = bundle.encrypt(&passphrase).unwrap();
+
+        assert!(matches!(
+            AccountBundle::decrypt(&encrypted, &StoreSecret::Keys(keys.clone())),
+            Err(Error::Encryption(encrypted::Error::SecretMismatch))
+        ));
+        assert!(matches!(
+            AccountBundle::decrypt(
+                &encrypted,
+                &StoreSecret::Passphrase(String::from("wrong horse"))
+            ),
+            Err(Error::Encryption(encrypted::Error::Decryption))
+        ));
+
+        let secret = StoreSecret::Keys(keys);
+        let mut encrypted: Vec<u8> = bundle.encrypt(&secret).unwrap();
+        assert!(matches!(
+            AccountBundle::decrypt(&encrypted, &StoreSecret::Keys(Keys::generate())),
+            Err(Error::Encryption(encrypted::Error::Decryption))
+        ));
+        let last: usize = encrypted.len() - 1;
+        encrypted[last] ^= 0x01;
+        assert!(matches!(
+            AccountBundle::decrypt(&encrypted, &secret),
+            Err(Error::Encryption(encrypted::Error::Decryption))
+        ));
+        assert!(matches!(
+            AccountBundle::decrypt(&encrypted[..20], &secret),
+            Err(Error::InvalidBundle(..))
+        ));
+    }
+
+    #[test]
+    fn test_key_mismatch() {
+        let keys = Keys::generate();
+        let other = Keys::generate();
+        let secret = StoreSecret::Keys(keys.clone());
+        let bundle = AccountBundle {
+            public_key: keys.public_key(),
+            secret_key: Some(other.secret_key().unwrap()),
+            events: Vec::new(),
+        };
+        assert!(matches!(
+            AccountBundle::decrypt(&bundle.encrypt(&secret).unwrap(), &secret),
+            Err(Error::KeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_wrong_author() {
+        let keys = Keys::generate();
+        let other = Keys::generate();
+        let secret = StoreSecret::Keys(keys.clone());
+        let bundle = AccountBundle {
+            public_key: keys.public_key(),
+            secret_key: None,
+            events: account_events(&other),
+        };
+        assert!(matches!(
+            AccountBundle::decrypt(&bundle.encrypt(&secret).unwrap(), &secret),
+            Err(Error::WrongAuthor)
+        ));
+
+        // Events of other authors are not exported
+        let bundle = AccountBundle::new(&keys, false, account_events(&other)).unwrap();
+        assert!(bundle.events.is_empty());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let keys = Keys::generate();
+        let bundle = AccountBundle::new(&keys, true, account_events(&keys)).unwrap();
+        let secret = StoreSecret::Keys(keys);
+        let path = std::env::temp_dir().join(format!("nostr-sdk-bundle-{}", std::process::id()));
+        bundle.save(&path, &secret).unwrap();
+        assert_eq!(AccountBundle::load(&path, &secret).unwrap(), bundle);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode: u32 = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
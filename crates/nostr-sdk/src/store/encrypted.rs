@@ -0,0 +1,521 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Encrypted store file
+//!
+//! Persist the [`Store`] to a file, optionally encrypting every record with XChaCha20-Poly1305.
+//!
+//! File layout: `[magic: "NSDB"][version: u8][secret kind: u8][salt: 16 bytes]` followed by records
+//! `[length: u32 big endian][record]` and by the end of file record `[0xFFFFFFFF][record count: u64 big endian]`.
+//! An encrypted record is `[nonce: 24 bytes][ciphertext]`, a plain record is the event JSON.
+//!
+//! Encrypted records are authenticated together with the header and their index, and the end of file
+//! record with a `[nonce: 24 bytes][tag: 16 bytes]` over the header and the record count: records can't be
+//! removed, reordered, duplicated or moved to another file.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use nostr::hashes::hmac::{Hmac, HmacEngine};
+use nostr::hashes::sha256::Hash as Sha256Hash;
+use nostr::hashes::{Hash, HashEngine};
+use nostr::secp256k1::rand::rngs::OsRng;
+use nostr::secp256k1::rand::RngCore;
+use nostr::{key, Event, Keys, SubscriptionFilter};
+
+use super::Store;
+
+const MAGIC: &[u8; 4] = b"NSDB";
+const VERSION: u8 = 1;
+pub(super) const SALT_LEN: usize = 16;
+pub(super) const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = 6 + SALT_LEN;
+const TAG_LEN: usize = 16;
+/// Length of the end of file record
+const END: u32 = u32::MAX;
+/// Scrypt cost (`log2(N)`) for passphrase derived keys
+const SCRYPT_LOG_N: u8 = 15;
+
+const PLAIN: u8 = 0x00;
+const KEYS: u8 = 0x01;
+const PASSPHRASE: u8 = 0x02;
+
+/// Encrypted store error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] nostr::event::Error),
+    /// Invalid file
+    #[error("invalid store file: {0}")]
+    InvalidFile(String),
+    /// The file is encrypted but no secret was provided, or vice versa
+    #[error("store secret mismatch")]
+    SecretMismatch,
+    /// Key derivation error
+    #[error("key derivation failed")]
+    KeyDerivation,
+    /// Encryption error
+    #[error("encryption failed")]
+    Encryption,
+    /// Decryption error (wrong secret or corrupted record)
+    #[error("decryption failed")]
+    Decryption,
+}
+
+/// Secret used to derive the store encryption key
+///
+/// The passphrase is wiped on drop.
+#[derive(Debug, Clone)]
+pub enum StoreSecret {
+    /// Key derived from the secret key of [`Keys`]
+    Keys(Keys),
+    /// Key derived from a passphrase (scrypt)
+    Passphrase(String),
+}
+
+impl Drop for StoreSecret {
+    fn drop(&mut self) {
+        if let Self::Passphrase(passphrase) = self {
+            zeroize::Zeroize::zeroize(passphrase);
+        }
+    }
+}
+
+impl StoreSecret {
+    pub(super) fn kind(&self) -> u8 {
+        match self {
+            Self::Keys(..) => KEYS,
+            Self::Passphrase(..) => PASSPHRASE,
+        }
+    }
+
+    pub(super) fn cipher(&self, salt: &[u8; SALT_LEN]) -> Result<XChaCha20Poly1305, Error> {
+        let mut key: [u8; 32] = match self {
+            Self::Keys(keys) => {
+                let mut secret: [u8; 32] = keys.secret_key()?.secret_bytes();
+                let mut engine: HmacEngine<Sha256Hash> = HmacEngine::new(b"nostr-sdk/store");
+                engine.input(salt);
+                engine.input(&secret);
+                wipe(&mut secret);
+                Hmac::<Sha256Hash>::from_engine(engine).into_inner()
+            }
+            Self::Passphrase(passphrase) => {
+                let params = scrypt::Params::new(SCRYPT_LOG_N, 8, 1, 32)
+                    .map_err(|_| Error::KeyDerivation)?;
+                let mut key = [0u8; 32];
+                scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+                    .map_err(|_| Error::KeyDerivation)?;
+                key
+            }
+        };
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+        wipe(&mut key);
+        Ok(cipher)
+    }
+}
+
+#[inline]
+pub(super) fn wipe(bytes: &mut [u8]) {
+    zeroize::Zeroize::zeroize(bytes);
+}
+
+/// Create (or truncate) file readable and writable only by the owner
+pub(super) fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // The mode is applied only to new files
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+/// Additional data of record `index`
+fn record_aad(header: &[u8; HEADER_LEN], index: u64) -> Vec<u8> {
+    [header.as_slice(), &index.to_be_bytes()].concat()
+}
+
+/// Additional data of the end of file record
+fn end_aad(header: &[u8; HEADER_LEN], count: u64) -> Vec<u8> {
+    [header.as_slice(), &count.to_be_bytes(), b"end"].concat()
+}
+
+fn encrypt_record(cipher: &XChaCha20Poly1305, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext: Vec<u8> = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg, aad })
+        .map_err(|_| Error::Encryption)?;
+    Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+}
+
+fn decrypt_record(cipher: &XChaCha20Poly1305, record: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+    if record.len() < NONCE_LEN + TAG_LEN {
+        return Err(Error::InvalidFile(String::from("truncated record")));
+    }
+    let (nonce, msg) = record.split_at(NONCE_LEN);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg, aad })
+        .map_err(|_| Error::Decryption)
+}
+
+impl Store {
+    /// Save current events to file
+    ///
+    /// If `secret` is set, every record is encrypted. Superseded versions and expired events are not saved.
+    /// Return the number of saved events.
+    pub fn save_to_file<P>(&self, path: P, secret: Option<&StoreSecret>) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let cipher: Option<XChaCha20Poly1305> = secret.map(|s| s.cipher(&salt)).transpose()?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        header[5] = secret.map_or(PLAIN, StoreSecret::kind);
+        header[6..].copy_from_slice(&salt);
+
+        // Write to temporary file first, to not lose the previous snapshot on failure
+        let path: &Path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(create_private_file(&tmp)?);
+        writer.write_all(&header)?;
+
+        let events: Vec<Event> = self.query(vec![SubscriptionFilter::new()]);
+        let mut count: u64 = 0;
+        for event in events.iter() {
+            let json: String = event.as_json()?;
+            let record: Vec<u8> = match &cipher {
+                Some(cipher) => {
+                    encrypt_record(cipher, json.as_bytes(), &record_aad(&header, count))?
+                }
+                None => json.into_bytes(),
+            };
+            let len = u32::try_from(record.len())
+                .ok()
+                .filter(|len| *len != END)
+                .ok_or_else(|| Error::InvalidFile(String::from("record too large")))?;
+            writer.write_all(&len.to_be_bytes())?;
+            writer.write_all(&record)?;
+            count += 1;
+        }
+
+        writer.write_all(&END.to_be_bytes())?;
+        writer.write_all(&count.to_be_bytes())?;
+        if let Some(cipher) = &cipher {
+            writer.write_all(&encrypt_record(cipher, &[], &end_aad(&header, count))?)?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp, path)?;
+        Ok(events.len())
+    }
+
+    /// Load events from file saved with [`Store::save_to_file`]
+    ///
+    /// Records are decrypted with `secret` and every event is verified. Nothing is saved if the file
+    /// is corrupted, truncated or was tampered with. Return the number of stored events.
+    pub fn load_from_file<P>(&self, path: P, secret: Option<&StoreSecret>) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(Error::InvalidFile(String::from("wrong magic")));
+        }
+        if header[4] != VERSION {
+            return Err(Error::InvalidFile(format!(
+                "unsupported version {}",
+                header[4]
+            )));
+        }
+        if header[5] != secret.map_or(PLAIN, StoreSecret::kind) {
+            return Err(Error::SecretMismatch);
+        }
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&header[6..]);
+        let cipher: Option<XChaCha20Poly1305> = secret.map(|s| s.cipher(&salt)).transpose()?;
+
+        let truncated = |e: io::Error| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::InvalidFile(String::from("truncated file")),
+            _ => Error::IO(e),
+        };
+
+        let mut events: Vec<Event> = Vec::new();
+        loop {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len).map_err(truncated)?;
+            let len: u32 = u32::from_be_bytes(len);
+            if len == END {
+                break;
+            }
+            let mut record = vec![0u8; len as usize];
+            reader.read_exact(&mut record).map_err(truncated)?;
+
+            let index = events.len() as u64;
+            let json: Vec<u8> = match &cipher {
+                Some(cipher) => decrypt_record(cipher, &record, &record_aad(&header, index))?,
+                None => record,
+            };
+            let json = String::from_utf8(json)
+                .map_err(|_| Error::InvalidFile(String::from("invalid UTF-8 record")))?;
+            events.push(Event::from_json(json)?);
+        }
+
+        let mut count = [0u8; 8];
+        reader.read_exact(&mut count).map_err(truncated)?;
+        let count: u64 = u64::from_be_bytes(count);
+        if let Some(cipher) = &cipher {
+            let mut tag = [0u8; NONCE_LEN + TAG_LEN];
+            reader.read_exact(&mut tag).map_err(truncated)?;
+            decrypt_record(cipher, &tag, &end_aad(&header, count))?;
+        }
+        if count != events.len() as u64 {
+            return Err(Error::InvalidFile(String::from("record count mismatch")));
+        }
+        if reader.read(&mut [0u8; 1])? != 0 {
+            return Err(Error::InvalidFile(String::from("trailing data")));
+        }
+
+        Ok(events
+            .into_iter()
+            .map(|event| self.save_event(event))
+            .filter(|saved| *saved)
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use nostr::{EventBuilder, EventId};
+
+    use super::*;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("nostr-sdk-{}-{}.db", name, std::process::id()))
+    }
+
+    fn store_with_notes(keys: &Keys) -> Store {
+        let store = Store::new();
+        for content in ["one", "two", "three"].into_iter() {
+            let event = EventBuilder::new_text_note(content, &[])
+                .to_event(keys)
+                .unwrap();
+            assert!(store.save_event(event));
+        }
+        store
+    }
+
+    /// Byte ranges of the records of a saved file (without the end of file record)
+    fn records(bytes: &[u8]) -> Vec<std::ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut pos: usize = HEADER_LEN;
+        loop {
+            let len =
+                u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]);
+            if len == END {
+                return ranges;
+            }
+            ranges.push(pos..pos + 4 + len as usize);
+            pos += 4 + len as usize;
+        }
+    }
+
+    /// Rewrite the file with the records in `order`, keeping the end of file record
+    fn rewrite(path: &Path, order: &[usize]) {
+        let bytes: Vec<u8> = fs::read(path).unwrap();
+        let ranges = records(&bytes);
+        let end: usize = ranges.last().unwrap().end;
+        let mut tampered: Vec<u8> = bytes[..HEADER_LEN].to_vec();
+        for i in order.iter() {
+            tampered.extend_from_slice(&bytes[ranges[*i].clone()]);
+        }
+        tampered.extend_from_slice(&bytes[end..]);
+        fs::write(path, tampered).unwrap();
+    }
+
+    fn assert_rejected(path: &Path, secret: Option<&StoreSecret>) -> Error {
+        let store = Store::new();
+        let err = store.load_from_file(path, secret).unwrap_err();
+        assert!(store.is_empty());
+        err
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let keys = Keys::generate();
+        let store = store_with_notes(&keys);
+        let secrets = [
+            None,
+            Some(StoreSecret::Keys(keys.clone())),
+            Some(StoreSecret::Passphrase(String::from("correct horse"))),
+        ];
+        for (i, secret) in secrets.iter().enumerate() {
+            let path = tmp_path(&format!("round-trip-{i}"));
+            assert_eq!(store.save_to_file(&path, secret.as_ref()).unwrap(), 3);
+
+            let loaded = Store::new();
+            assert_eq!(loaded.load_from_file(&path, secret.as_ref()).unwrap(), 3);
+            let ids = |store: &Store| -> BTreeSet<EventId> {
+                store
+                    .query(vec![SubscriptionFilter::new()])
+                    .into_iter()
+                    .map(|e| e.id)
+                    .collect()
+            };
+            assert_eq!(ids(&loaded), ids(&store));
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode: u32 = fs::metadata(&path).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_wrong_secret() {
+        let keys = Keys::generate();
+        let path = tmp_path("wrong-secret");
+        let secret = StoreSecret::Keys(keys.clone());
+        store_with_notes(&keys)
+            .save_to_file(&path, Some(&secret))
+            .unwrap();
+
+        let other = StoreSecret::Keys(Keys::generate());
+        assert!(matches!(
+            assert_rejected(&path, Some(&other)),
+            Error::Decryption
+        ));
+        assert!(matches!(
+            assert_rejected(&path, None),
+            Error::SecretMismatch
+        ));
+        let passphrase = StoreSecret::Passphrase(String::from("passphrase"));
+        assert!(matches!(
+            assert_rejected(&path, Some(&passphrase)),
+            Error::SecretMismatch
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tampered_records() {
+        let keys = Keys::generate();
+        let store = store_with_notes(&keys);
+        let secret = StoreSecret::Keys(keys);
+        let path = tmp_path("tampered");
+
+        // Modified ciphertext
+        store.save_to_file(&path, Some(&secret)).unwrap();
+        let mut bytes: Vec<u8> = fs::read(&path).unwrap();
+        let last: usize = records(&bytes)[0].end - 1;
+        bytes[last] ^= 0x01;
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            assert_rejected(&path, Some(&secret)),
+            Error::Decryption
+        ));
+
+        // Removed, reordered and duplicated records
+        for order in [&[0, 2][..], &[1, 0, 2], &[0, 0, 1, 2], &[0, 1]].into_iter() {
+            store.save_to_file(&path, Some(&secret)).unwrap();
+            rewrite(&path, order);
+            let err = assert_rejected(&path, Some(&secret));
+            assert!(
+                matches!(err, Error::Decryption | Error::InvalidFile(..)),
+                "{order:?}: {err}"
+            );
+        }
+
+        // Tampered record count
+        store.save_to_file(&path, Some(&secret)).unwrap();
+        let mut bytes: Vec<u8> = fs::read(&path).unwrap();
+        let count_pos: usize = records(&bytes).last().unwrap().end + 4;
+        bytes[count_pos + 7] = 2;
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            assert_rejected(&path, Some(&secret)),
+            Error::Decryption
+        ));
+
+        // Record moved from another file of the same secret
+        let other_path = tmp_path("tampered-other");
+        store.save_to_file(&path, Some(&secret)).unwrap();
+        store.save_to_file(&other_path, Some(&secret)).unwrap();
+        let mut bytes: Vec<u8> = fs::read(&path).unwrap();
+        let other: Vec<u8> = fs::read(&other_path).unwrap();
+        let range = records(&bytes)[1].clone();
+        bytes[range.clone()].copy_from_slice(&other[range]);
+        fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            assert_rejected(&path, Some(&secret)),
+            Error::Decryption
+        ));
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&other_path).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let keys = Keys::generate();
+        let store = store_with_notes(&keys);
+        let secrets = [None, Some(StoreSecret::Keys(keys))];
+        for (i, secret) in secrets.iter().enumerate() {
+            let path = tmp_path(&format!("truncated-{i}"));
+            store.save_to_file(&path, secret.as_ref()).unwrap();
+            let bytes: Vec<u8> = fs::read(&path).unwrap();
+            let ranges = records(&bytes);
+
+            // At record boundaries, in the middle of a record and in the end of file record
+            let cuts = [
+                ranges[0].end,
+                ranges[2].end,
+                ranges[1].start + 10,
+                bytes.len() - 1,
+            ];
+            for cut in cuts.into_iter() {
+                fs::write(&path, &bytes[..cut]).unwrap();
+                assert!(matches!(
+                    assert_rejected(&path, secret.as_ref()),
+                    Error::InvalidFile(..)
+                ));
+            }
+
+            // Trailing data
+            fs::write(&path, [bytes.as_slice(), &[0]].concat()).unwrap();
+            assert!(matches!(
+                assert_rejected(&path, secret.as_ref()),
+                Error::InvalidFile(..)
+            ));
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
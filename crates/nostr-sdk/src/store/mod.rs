@@ -0,0 +1,752 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Local event store
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+use std::time::Duration;
+
+use nostr::event::unsigned;
+use nostr::event::TagKind;
+use nostr::key::XOnlyPublicKey;
+use nostr::{
+    Event, EventBuilder, EventId, Keys, Kind, SubscriptionFilter, Tag, Timestamp, UnsignedEvent,
+};
+use tokio::sync::broadcast;
+
+use crate::thread;
+
+pub mod audit;
+#[cfg(feature = "encrypted-store")]
+pub mod bundle;
+#[cfg(feature = "encrypted-store")]
+pub mod encrypted;
+
+use self::audit::{AuditAction, AuditEntry};
+
+#[cfg(feature = "encrypted-store")]
+pub use self::bundle::AccountBundle;
+#[cfg(feature = "encrypted-store")]
+pub use self::encrypted::StoreSecret;
+
+/// Application-specific data kind (NIP-78)
+const APP_DATA: u16 = 30078;
+
+/// Address of replaceable events: author, kind and `d` tag
+type Address = (XOnlyPublicKey, u64, String);
+
+#[derive(Debug, Default)]
+struct InnerStore {
+    keep_history: bool,
+    events: HashMap<EventId, Event>,
+    /// Versions of replaceable events, from the oldest to the current one
+    versions: HashMap<Address, Vec<EventId>>,
+    /// Expiration index (NIP-40)
+    expirations: BTreeSet<(Timestamp, EventId)>,
+    /// Audit log, in chronological order
+    audit_log: Vec<AuditEntry>,
+    /// Audited events
+    audited: HashSet<EventId>,
+}
+
+/// [`Store`] notification
+#[derive(Debug, Clone)]
+pub enum StoreNotification {
+    /// [`Event`] expired and removed from the store (NIP-40)
+    Expired(Event),
+    /// New current version of a replaceable event
+    Updated {
+        /// Superseded version, if it was stored
+        previous: Option<Box<Event>>,
+        /// New current version
+        event: Event,
+    },
+}
+
+/// In-memory event store
+///
+/// Replaceable and parameterized replaceable events are resolved to their latest version
+/// (NIP-01 and NIP-33). Ephemeral events are not stored.
+///
+/// Events with an `expiration` tag (NIP-40) are not returned by queries once expired
+/// and are removed by [`Store::remove_expired`] or by the sweeper (see [`Store::start_sweeper`]).
+#[derive(Debug, Clone)]
+pub struct Store {
+    inner: Arc<RwLock<InnerStore>>,
+    notification_sender: broadcast::Sender<StoreNotification>,
+    sweeper_started: Arc<AtomicBool>,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::with_inner(InnerStore::default())
+    }
+}
+
+impl Store {
+    /// New [`Store`]
+    ///
+    /// Superseded versions of replaceable events are discarded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New [`Store`] keeping superseded versions of replaceable events
+    ///
+    /// Required to reconstruct historical states with [`Store::query_at`].
+    pub fn with_history() -> Self {
+        Self::with_inner(InnerStore {
+            keep_history: true,
+            ..Default::default()
+        })
+    }
+
+    fn with_inner(inner: InnerStore) -> Self {
+        let (notification_sender, _) = broadcast::channel(1024);
+        Self {
+            inner: Arc::new(RwLock::new(inner)),
+            notification_sender,
+            sweeper_started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Get new notification listener
+    pub fn notifications(&self) -> broadcast::Receiver<StoreNotification> {
+        self.notification_sender.subscribe()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, InnerStore> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, InnerStore> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Save [`Event`]
+    ///
+    /// Return `false` if the event was not stored (ephemeral, expired, duplicated or superseded by a newer version).
+    pub fn save_event(&self, event: Event) -> bool {
+        if event.kind.is_ephemeral() || event.is_expired() {
+            return false;
+        }
+
+        let mut inner = self.write();
+        if inner.events.contains_key(&event.id) {
+            return false;
+        }
+
+        let mut updated: Option<StoreNotification> = None;
+        if let Some(address) = address(&event) {
+            let keep_history: bool = inner.keep_history;
+            let versions: Vec<EventId> = inner.versions.remove(&address).unwrap_or_default();
+            let mut versions: Vec<Event> = versions
+                .into_iter()
+                .filter_map(|id| inner.events.get(&id).cloned())
+                .collect();
+            let previous: Option<Event> = versions.last().cloned();
+
+            if !keep_history {
+                if let Some(current) = versions.last() {
+                    if cmp_versions(current, &event) == Ordering::Greater {
+                        inner.versions.insert(address, vec![current.id]);
+                        return false;
+                    }
+                    inner.remove(&current.id);
+                }
+                versions.clear();
+            }
+
+            versions.push(event.clone());
+            versions.sort_by(cmp_versions);
+            if versions.last().map(|e| e.id) == Some(event.id) {
+                updated = Some(StoreNotification::Updated {
+                    previous: previous.map(Box::new),
+                    event: event.clone(),
+                });
+            }
+            inner
+                .versions
+                .insert(address, versions.into_iter().map(|e| e.id).collect());
+        }
+
+        if let Some(expiration) = event.expiration() {
+            inner.expirations.insert((expiration, event.id));
+        }
+        inner.events.insert(event.id, event);
+        drop(inner);
+
+        if let Some(notification) = updated {
+            let _ = self.notification_sender.send(notification);
+        }
+        true
+    }
+
+    /// Remove events expired at `now` (NIP-40)
+    ///
+    /// An [`StoreNotification::Expired`] is emitted for every removed event.
+    pub fn remove_expired(&self, now: Timestamp) -> Vec<Event> {
+        let expired: Vec<Event> = {
+            let mut inner = self.write();
+            let ids: Vec<EventId> = inner
+                .expirations
+                .iter()
+                .take_while(|(expiration, _)| *expiration <= now)
+                .map(|(_, id)| *id)
+                .collect();
+            ids.iter().filter_map(|id| inner.remove(id)).collect()
+        };
+
+        for event in expired.iter() {
+            let _ = self
+                .notification_sender
+                .send(StoreNotification::Expired(event.clone()));
+        }
+        expired
+    }
+
+    /// Start background sweeper removing expired events every `interval`
+    ///
+    /// The sweeper is started only once and stops when all the clones of the store are dropped.
+    pub fn start_sweeper(&self, interval: Duration) {
+        if self.sweeper_started.swap(true, AtomicOrdering::SeqCst) {
+            return;
+        }
+
+        let inner: Weak<RwLock<InnerStore>> = Arc::downgrade(&self.inner);
+        let notification_sender = self.notification_sender.clone();
+        let sweeper_started = self.sweeper_started.clone();
+        thread::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let store = match inner.upgrade() {
+                    Some(inner) => Self {
+                        inner,
+                        notification_sender: notification_sender.clone(),
+                        sweeper_started: sweeper_started.clone(),
+                    },
+                    None => break,
+                };
+                let expired = store.remove_expired(Timestamp::now());
+                if !expired.is_empty() {
+                    log::debug!("Removed {} expired events from store", expired.len());
+                }
+            }
+            log::debug!("Exited from store sweeper");
+        });
+    }
+
+    /// Get [`Event`] by id
+    pub fn event_by_id(&self, id: &EventId) -> Option<Event> {
+        self.read().events.get(id).cloned()
+    }
+
+    /// Get the current application-specific data event (NIP-78) of `author` with `identifier`
+    pub(crate) fn app_data(&self, author: XOnlyPublicKey, identifier: &str) -> Option<Event> {
+        let inner = self.read();
+        let address: Address = (author, u64::from(APP_DATA), identifier.to_string());
+        let id: &EventId = inner.versions.get(&address)?.last()?;
+        inner.events.get(id).cloned()
+    }
+
+    /// Sign and save application-specific data event (NIP-78) with `identifier` as `d` tag
+    ///
+    /// Used to persist local state, never sent to relays. `created_at` is bumped if needed, so the
+    /// new event always replaces the current one.
+    pub(crate) fn save_app_data(
+        &self,
+        keys: &Keys,
+        identifier: &str,
+        tags: Vec<Tag>,
+    ) -> Result<Event, unsigned::Error> {
+        let mut tags: Vec<Tag> = tags;
+        tags.insert(0, Tag::Generic(TagKind::D, vec![identifier.to_string()]));
+        let mut unsigned: UnsignedEvent =
+            EventBuilder::new(Kind::ParameterizedReplaceable(APP_DATA), "", &tags)
+                .to_unsigned_event(keys.public_key());
+        if let Some(current) = self.app_data(keys.public_key(), identifier) {
+            if unsigned.created_at <= current.created_at {
+                unsigned.created_at = Timestamp::from(current.created_at.as_u64() + 1);
+                unsigned.id = EventId::new(
+                    &unsigned.pubkey,
+                    unsigned.created_at,
+                    &unsigned.kind,
+                    &unsigned.tags,
+                    &unsigned.content,
+                );
+            }
+        }
+        let event: Event = unsigned.sign(keys)?;
+        self.save_event(event.clone());
+        Ok(event)
+    }
+
+    /// Append action on `event` to the audit log
+    pub(crate) fn append_audit(&self, event: &Event, action: AuditAction) {
+        let mut inner = self.write();
+        inner.audited.insert(event.id);
+        inner.audit_log.push(AuditEntry {
+            timestamp: Timestamp::now(),
+            event: event.clone(),
+            action,
+        });
+    }
+
+    /// Get audited [`Event`] by id
+    pub(crate) fn audited_event(&self, id: &EventId) -> Option<Event> {
+        let inner = self.read();
+        if !inner.audited.contains(id) {
+            return None;
+        }
+        inner
+            .audit_log
+            .iter()
+            .rev()
+            .find(|entry| &entry.event.id == id)
+            .map(|entry| entry.event.clone())
+    }
+
+    /// Get audit log entries of the events matching any of `filters` (all if `filters` is empty)
+    ///
+    /// Entries are in chronological order. Expired and superseded events are included.
+    pub fn audit_log(&self, filters: Vec<SubscriptionFilter>) -> Vec<AuditEntry> {
+        let inner = self.read();
+        inner
+            .audit_log
+            .iter()
+            .filter(|entry| {
+                filters.is_empty() || filters.iter().any(|f| f.match_event(&entry.event))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Number of stored events, superseded versions included
+    pub fn len(&self) -> usize {
+        self.read().events.len()
+    }
+
+    /// Check if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.read().events.is_empty()
+    }
+
+    /// Query events matching any of `filters`
+    ///
+    /// Only the current version of replaceable events is returned. Events are sorted from the newest.
+    pub fn query(&self, filters: Vec<SubscriptionFilter>) -> Vec<Event> {
+        self.query_inner(filters, None)
+    }
+
+    /// Query events matching any of `filters` as they were at `as_of`
+    ///
+    /// Events created after `as_of` are ignored and replaceable events are resolved to the
+    /// version that was current at `as_of`. Superseded versions are available only if the
+    /// store was created with [`Store::with_history`].
+    pub fn query_at(&self, filters: Vec<SubscriptionFilter>, as_of: Timestamp) -> Vec<Event> {
+        self.query_inner(filters, Some(as_of))
+    }
+
+    fn query_inner(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        as_of: Option<Timestamp>,
+    ) -> Vec<Event> {
+        let now: Timestamp = as_of.unwrap_or_else(Timestamp::now);
+        let inner = self.read();
+        let candidates: Vec<&Event> = inner
+            .events
+            .values()
+            .filter(|event| !event.is_expired_at(now) && inner.is_visible(event, as_of))
+            .collect();
+
+        let mut ids: HashSet<EventId> = HashSet::new();
+        let mut events: Vec<Event> = Vec::new();
+        for filter in filters.iter() {
+            let mut matches: Vec<&Event> = candidates
+                .iter()
+                .copied()
+                .filter(|event| filter.match_event(event))
+                .collect();
+            matches.sort_by_key(|event| Reverse(event.created_at));
+            if let Some(limit) = filter.limit {
+                matches.truncate(limit);
+            }
+            for event in matches.into_iter() {
+                if ids.insert(event.id) {
+                    events.push(event.clone());
+                }
+            }
+        }
+        events.sort_by_key(|event| Reverse(event.created_at));
+        events
+    }
+}
+
+impl InnerStore {
+    /// Remove event and its indexes
+    fn remove(&mut self, id: &EventId) -> Option<Event> {
+        let event: Event = self.events.remove(id)?;
+        if let Some(expiration) = event.expiration() {
+            self.expirations.remove(&(expiration, event.id));
+        }
+        if let Some(address) = address(&event) {
+            if let Some(versions) = self.versions.get_mut(&address) {
+                versions.retain(|v| v != id);
+                if versions.is_empty() {
+                    self.versions.remove(&address);
+                }
+            }
+        }
+        Some(event)
+    }
+
+    /// Check if `event` was created and was the current version at `as_of` (now if `None`)
+    fn is_visible(&self, event: &Event, as_of: Option<Timestamp>) -> bool {
+        if let Some(as_of) = as_of {
+            if event.created_at > as_of {
+                return false;
+            }
+        }
+
+        let address = match address(event) {
+            Some(address) => address,
+            None => return true,
+        };
+
+        let versions = match self.versions.get(&address) {
+            Some(versions) => versions,
+            None => return true,
+        };
+
+        let current: Option<&EventId> = match as_of {
+            Some(as_of) => versions
+                .iter()
+                .rev()
+                .find(|id| self.events.get(id).map_or(false, |e| e.created_at <= as_of)),
+            None => versions.last(),
+        };
+        current == Some(&event.id)
+    }
+}
+
+fn address(event: &Event) -> Option<Address> {
+    if event.kind.is_replaceable() {
+        Some((event.pubkey, event.kind.as_u64(), String::new()))
+    } else if event.kind.is_parameterized_replaceable() {
+        let identifier: String = event
+            .tags
+            .iter()
+            .map(|t| t.as_vec())
+            .find(|t| t.first().map(|k| k.as_str()) == Some("d"))
+            .and_then(|t| t.get(1).cloned())
+            .unwrap_or_default();
+        Some((event.pubkey, event.kind.as_u64(), identifier))
+    } else {
+        None
+    }
+}
+
+/// Order versions of replaceable event from the oldest to the current one
+///
+/// With the same `created_at`, the event with the lowest id is the current one.
+fn cmp_versions(a: &Event, b: &Event) -> Ordering {
+    a.created_at
+        .cmp(&b.created_at)
+        .then_with(|| b.id.cmp(&a.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAMETERIZED: u16 = 30000;
+
+    fn event(keys: &Keys, kind: Kind, tags: &[Tag], created_at: u64) -> Event {
+        let mut unsigned: UnsignedEvent =
+            EventBuilder::new(kind, "", tags).to_unsigned_event(keys.public_key());
+        unsigned.created_at = Timestamp::from(created_at);
+        unsigned.id = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &unsigned.tags,
+            &unsigned.content,
+        );
+        unsigned.sign(keys).unwrap()
+    }
+
+    fn metadata(keys: &Keys, created_at: u64) -> Event {
+        event(keys, Kind::Metadata, &[], created_at)
+    }
+
+    fn identified(keys: &Keys, identifier: &str, created_at: u64) -> Event {
+        let tags = [Tag::Generic(TagKind::D, vec![identifier.to_string()])];
+        event(
+            keys,
+            Kind::ParameterizedReplaceable(PARAMETERIZED),
+            &tags,
+            created_at,
+        )
+    }
+
+    fn ids(events: Vec<Event>) -> Vec<EventId> {
+        events.into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn test_replaceable_supersession() {
+        let keys = Keys::generate();
+        let store = Store::new();
+        let mut notifications = store.notifications();
+
+        let v1 = metadata(&keys, 10);
+        let v2 = metadata(&keys, 20);
+        assert!(store.save_event(v1.clone()));
+        assert!(store.save_event(v2.clone()));
+        assert!(!store.save_event(v2.clone()));
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.event_by_id(&v1.id), None);
+        assert_eq!(
+            ids(store.query(vec![SubscriptionFilter::new()])),
+            vec![v2.id]
+        );
+
+        match notifications.try_recv().unwrap() {
+            StoreNotification::Updated { previous, event } => {
+                assert_eq!(previous, None);
+                assert_eq!(event, v1);
+            }
+            n => panic!("Unexpected notification: {n:?}"),
+        }
+        match notifications.try_recv().unwrap() {
+            StoreNotification::Updated { previous, event } => {
+                assert_eq!(previous.map(|e| e.id), Some(v1.id));
+                assert_eq!(event, v2);
+            }
+            n => panic!("Unexpected notification: {n:?}"),
+        }
+
+        // Older versions are rejected
+        assert!(!store.save_event(metadata(&keys, 15)));
+        assert_eq!(
+            ids(store.query(vec![SubscriptionFilter::new()])),
+            vec![v2.id]
+        );
+        assert!(notifications.try_recv().is_err());
+
+        // Same `created_at`: the lowest id wins
+        let a = metadata(&keys, 30);
+        let tags = [Tag::Generic(TagKind::D, vec![String::from("ignored")])];
+        let b = event(&keys, Kind::Metadata, &tags, 30);
+        let (low, high) = if a.id < b.id { (a, b) } else { (b, a) };
+        assert!(store.save_event(low.clone()));
+        assert!(!store.save_event(high));
+        assert_eq!(
+            ids(store.query(vec![SubscriptionFilter::new()])),
+            vec![low.id]
+        );
+
+        // Replaceable events of other authors and regular events are not superseded
+        let other = metadata(&Keys::generate(), 5);
+        let note = event(&keys, Kind::TextNote, &[], 5);
+        assert!(store.save_event(other.clone()));
+        assert!(store.save_event(note.clone()));
+        assert_eq!(store.len(), 3);
+
+        // Ephemeral events are not stored
+        assert!(!store.save_event(event(&keys, Kind::Ephemeral(20000), &[], 40)));
+        assert_eq!(store.len(), 3);
+    }
+
+    #[test]
+    fn test_parameterized_replaceable_supersession() {
+        let keys = Keys::generate();
+        let store = Store::new();
+
+        let a1 = identified(&keys, "a", 10);
+        let b1 = identified(&keys, "b", 20);
+        let a2 = identified(&keys, "a", 30);
+        assert!(store.save_event(a1.clone()));
+        assert!(store.save_event(b1.clone()));
+        assert!(store.save_event(a2.clone()));
+        assert!(!store.save_event(identified(&keys, "a", 20)));
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.event_by_id(&a1.id), None);
+        assert_eq!(
+            ids(store.query(vec![SubscriptionFilter::new()])),
+            vec![a2.id, b1.id]
+        );
+
+        // Missing `d` tag is the same as empty identifier
+        let empty = event(
+            &keys,
+            Kind::ParameterizedReplaceable(PARAMETERIZED),
+            &[],
+            10,
+        );
+        let empty_d = identified(&keys, "", 20);
+        assert!(store.save_event(empty));
+        assert!(store.save_event(empty_d.clone()));
+        assert_eq!(store.len(), 3);
+        assert!(store.event_by_id(&empty_d.id).is_some());
+    }
+
+    #[test]
+    fn test_remove_expired() {
+        let keys = Keys::generate();
+        let store = Store::new();
+        let mut notifications = store.notifications();
+        let now = Timestamp::now();
+        let expiration = now + Duration::from_secs(100);
+
+        // Already expired events are not stored
+        let expired = event(
+            &keys,
+            Kind::TextNote,
+            &[Tag::Expiration(now - Duration::from_secs(1))],
+            10,
+        );
+        assert!(!store.save_event(expired));
+
+        let expiring = event(&keys, Kind::TextNote, &[Tag::Expiration(expiration)], 10);
+        let note = event(&keys, Kind::TextNote, &[], 10);
+        assert!(store.save_event(expiring.clone()));
+        assert!(store.save_event(note.clone()));
+
+        assert!(store.remove_expired(now).is_empty());
+        assert!(store
+            .remove_expired(expiration - Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(store.len(), 2);
+
+        // Expired at `expiration`, but still stored until removed
+        assert_eq!(
+            ids(store.query_at(vec![SubscriptionFilter::new()], expiration)),
+            vec![note.id]
+        );
+        assert_eq!(store.len(), 2);
+
+        assert_eq!(ids(store.remove_expired(expiration)), vec![expiring.id]);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.event_by_id(&expiring.id), None);
+        assert!(store.remove_expired(expiration).is_empty());
+        match notifications.try_recv().unwrap() {
+            StoreNotification::Expired(event) => assert_eq!(event, expiring),
+            n => panic!("Unexpected notification: {n:?}"),
+        }
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sweeper() {
+        let keys = Keys::generate();
+        let store = Store::new();
+        let mut notifications = store.notifications();
+        let expiring = event(
+            &keys,
+            Kind::TextNote,
+            &[Tag::Expiration(Timestamp::now() + Duration::from_secs(1))],
+            10,
+        );
+        assert!(store.save_event(expiring.clone()));
+
+        store.start_sweeper(Duration::from_millis(100));
+        store.start_sweeper(Duration::from_millis(100));
+        let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match notification {
+            StoreNotification::Expired(event) => assert_eq!(event, expiring),
+            n => panic!("Unexpected notification: {n:?}"),
+        }
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_query_at() {
+        let keys = Keys::generate();
+        let store = Store::with_history();
+        let v1 = metadata(&keys, 10);
+        let v2 = metadata(&keys, 20);
+        let v3 = metadata(&keys, 30);
+        let note = event(&keys, Kind::TextNote, &[], 25);
+        // Out of order: older versions are kept
+        for event in [v3.clone(), note.clone(), v1.clone(), v2.clone()] {
+            assert!(store.save_event(event));
+        }
+        assert_eq!(store.len(), 4);
+
+        let filters = || vec![SubscriptionFilter::new()];
+        assert!(store.query_at(filters(), Timestamp::from(9)).is_empty());
+        assert_eq!(
+            ids(store.query_at(filters(), Timestamp::from(10))),
+            vec![v1.id]
+        );
+        assert_eq!(
+            ids(store.query_at(filters(), Timestamp::from(19))),
+            vec![v1.id]
+        );
+        assert_eq!(
+            ids(store.query_at(filters(), Timestamp::from(20))),
+            vec![v2.id]
+        );
+        assert_eq!(
+            ids(store.query_at(filters(), Timestamp::from(25))),
+            vec![note.id, v2.id]
+        );
+        assert_eq!(
+            ids(store.query_at(filters(), Timestamp::from(30))),
+            vec![v3.id, note.id]
+        );
+        assert_eq!(ids(store.query(filters())), vec![v3.id, note.id]);
+
+        // Limit applies to the events visible at `as_of`
+        let filters = vec![SubscriptionFilter::new().limit(1)];
+        assert_eq!(
+            ids(store.query_at(filters, Timestamp::from(25))),
+            vec![note.id]
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let keys = Keys::generate();
+        let v1 = metadata(&keys, 10);
+        let v2 = metadata(&keys, 20);
+
+        // Without history, superseded versions are discarded
+        let store = Store::new();
+        assert!(store.save_event(v1.clone()));
+        assert!(store.save_event(v2.clone()));
+        assert!(store
+            .query_at(vec![SubscriptionFilter::new()], Timestamp::from(15))
+            .is_empty());
+
+        // With history, older versions are stored without replacing the current one
+        let store = Store::with_history();
+        let mut notifications = store.notifications();
+        assert!(store.save_event(v2.clone()));
+        assert!(store.save_event(v1.clone()));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.event_by_id(&v1.id), Some(v1.clone()));
+        assert_eq!(
+            ids(store.query(vec![SubscriptionFilter::new()])),
+            vec![v2.id]
+        );
+        assert_eq!(
+            ids(store.query_at(vec![SubscriptionFilter::new()], Timestamp::from(15))),
+            vec![v1.id]
+        );
+        assert!(matches!(
+            notifications.try_recv(),
+            Ok(StoreNotification::Updated { previous: None, .. })
+        ));
+        assert!(notifications.try_recv().is_err());
+    }
+}
@@ -7,7 +7,7 @@
 
 use std::collections::HashMap;
 
-use nostr::{SubscriptionFilter, SubscriptionId, Url};
+use nostr::{SubscriptionFilter, SubscriptionId, SubscriptionIdGenerator, Url};
 
 #[derive(Debug, Clone)]
 pub struct Subscription {
@@ -49,11 +49,12 @@ impl Subscription {
         self.channels.remove(relay_url)
     }
 
-    /// Get subscription channels
-    pub fn get_channel(&mut self, relay_url: &Url) -> Channel {
+    /// Get subscription channel, with id generated from the filters if new
+    pub fn get_channel(&mut self, relay_url: &Url, generator: &SubscriptionIdGenerator) -> Channel {
+        let filters: &[SubscriptionFilter] = &self.filters;
         self.channels
             .entry(relay_url.clone())
-            .or_insert_with(|| Channel::new(relay_url.clone()))
+            .or_insert_with(|| Channel::with_id(relay_url.clone(), generator.generate(filters)))
             .clone()
     }
 }
@@ -73,6 +74,11 @@ impl Channel {
         }
     }
 
+    /// Create new subscription channel with `id`
+    pub fn with_id(relay_url: Url, id: SubscriptionId) -> Self {
+        Self { id, relay_url }
+    }
+
     /// Get channel id
     pub fn id(&self) -> SubscriptionId {
         self.id.clone()
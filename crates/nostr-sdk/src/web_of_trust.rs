@@ -0,0 +1,275 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Web of trust
+//!
+//! Score public keys by follow distance from a root public key and by the mute lists (NIP-51)
+//! and reports (NIP-56) published by the root and its follows.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use nostr::key::XOnlyPublicKey;
+use nostr::url::Url;
+use nostr::{Event, Kind, SubscriptionFilter, Tag};
+
+use crate::client::{Client, Error, CONTACT_LISTS_BATCH_SIZE};
+use crate::policy::{EventAction, EventPolicy};
+use crate::social_graph::SocialGraph;
+
+/// Mute list kind (NIP-51)
+const MUTE_LIST: u64 = 10000;
+/// Reporting kind (NIP-56)
+const REPORTING: u64 = 1984;
+
+/// Trust scorer
+///
+/// Implement this trait to plug custom scoring into [`TrustScorePolicy`].
+pub trait TrustScorer: fmt::Debug + Send + Sync {
+    /// Get trust score of `public_key`, from `0.0` (untrusted) to `1.0` (fully trusted)
+    fn score(&self, public_key: &XOnlyPublicKey) -> f64;
+}
+
+/// Trust score of a public key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrustScore {
+    /// Follow distance from the root (`0` for the root itself)
+    pub distance: Option<usize>,
+    /// Number of signal sources muting the public key
+    pub mutes: usize,
+    /// Number of signal sources reporting the public key
+    pub reports: usize,
+    /// Muted by the root
+    pub muted_by_root: bool,
+    /// Score, from `0.0` to `1.0`
+    pub score: f64,
+}
+
+/// Web of trust scorer
+///
+/// The base score is `1.0` for the root and its follows and `1 / distance` for farther public keys,
+/// `0.0` for public keys outside the network. Mutes and reports published by the root and by its follows
+/// (the signal sources) lower the score. Public keys muted by the root always score `0.0`.
+///
+/// Scores are computed by [`WebOfTrust::refresh`]; clones share the same scores.
+#[derive(Debug, Clone)]
+pub struct WebOfTrust {
+    client: Client,
+    graph: SocialGraph,
+    root: XOnlyPublicKey,
+    depth: usize,
+    mute_weight: f64,
+    report_weight: f64,
+    scores: Arc<RwLock<HashMap<XOnlyPublicKey, TrustScore>>>,
+}
+
+impl WebOfTrust {
+    /// New [`WebOfTrust`] rooted at `root`
+    pub fn new(client: &Client, root: XOnlyPublicKey) -> Self {
+        Self {
+            client: client.clone(),
+            graph: SocialGraph::new(client),
+            root,
+            depth: 2,
+            mute_weight: 0.25,
+            report_weight: 0.5,
+            scores: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Set max follow distance (default: 2)
+    pub fn depth(self, depth: usize) -> Self {
+        Self { depth, ..self }
+    }
+
+    /// Set score penalty for every mute (default: 0.25)
+    pub fn mute_weight(self, weight: f64) -> Self {
+        Self {
+            mute_weight: weight,
+            ..self
+        }
+    }
+
+    /// Set score penalty for every report (default: 0.5)
+    pub fn report_weight(self, weight: f64) -> Self {
+        Self {
+            report_weight: weight,
+            ..self
+        }
+    }
+
+    /// Fetch contact lists, mute lists and reports and recompute scores
+    pub async fn refresh(&self) -> Result<(), Error> {
+        self.graph.clear_cache().await;
+        let network: HashMap<XOnlyPublicKey, usize> =
+            self.graph.network(self.root, self.depth).await?;
+
+        let sources: Vec<XOnlyPublicKey> = network
+            .iter()
+            .filter(|(_, distance)| **distance <= 1)
+            .map(|(pk, _)| *pk)
+            .collect();
+        let mut muted_by_root: HashSet<XOnlyPublicKey> = HashSet::new();
+        let mut mutes: HashSet<(XOnlyPublicKey, XOnlyPublicKey)> = HashSet::new();
+        let mut reports: HashSet<(XOnlyPublicKey, XOnlyPublicKey)> = HashSet::new();
+        for chunk in sources.chunks(CONTACT_LISTS_BATCH_SIZE) {
+            let filter = SubscriptionFilter::new()
+                .authors(chunk.to_vec())
+                .kinds(vec![Kind::from(MUTE_LIST), Kind::from(REPORTING)]);
+            let events: Vec<Event> = self.client.get_events_of(vec![filter]).await?;
+            for event in events.iter().filter(|e| chunk.contains(&e.pubkey)) {
+                for public_key in tagged_public_keys(event) {
+                    if event.kind.as_u64() == MUTE_LIST {
+                        if event.pubkey == self.root {
+                            muted_by_root.insert(public_key);
+                        }
+                        mutes.insert((event.pubkey, public_key));
+                    } else {
+                        reports.insert((event.pubkey, public_key));
+                    }
+                }
+            }
+        }
+
+        let mut scores: HashMap<XOnlyPublicKey, TrustScore> = HashMap::new();
+        for (public_key, distance) in network.into_iter() {
+            scores.insert(public_key, self.empty_score(Some(distance)));
+        }
+        for (_, public_key) in mutes.into_iter() {
+            scores
+                .entry(public_key)
+                .or_insert_with(|| self.empty_score(None))
+                .mutes += 1;
+        }
+        for (_, public_key) in reports.into_iter() {
+            scores
+                .entry(public_key)
+                .or_insert_with(|| self.empty_score(None))
+                .reports += 1;
+        }
+        for (public_key, score) in scores.iter_mut() {
+            score.muted_by_root = muted_by_root.contains(public_key);
+            score.score = self.compute(score);
+        }
+
+        let mut current = self.scores.write().unwrap_or_else(PoisonError::into_inner);
+        *current = scores;
+        Ok(())
+    }
+
+    /// Get [`TrustScore`] of `public_key`
+    ///
+    /// Return `None` if the public key is outside the network and has no mutes or reports.
+    pub fn get(&self, public_key: &XOnlyPublicKey) -> Option<TrustScore> {
+        self.scores
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(public_key)
+            .copied()
+    }
+
+    fn empty_score(&self, distance: Option<usize>) -> TrustScore {
+        TrustScore {
+            distance,
+            mutes: 0,
+            reports: 0,
+            muted_by_root: false,
+            score: 0.0,
+        }
+    }
+
+    fn compute(&self, score: &TrustScore) -> f64 {
+        if score.muted_by_root {
+            return 0.0;
+        }
+        let base: f64 = match score.distance {
+            Some(0) | Some(1) => 1.0,
+            Some(distance) => 1.0 / distance as f64,
+            None => 0.0,
+        };
+        let penalty: f64 =
+            score.mutes as f64 * self.mute_weight + score.reports as f64 * self.report_weight;
+        (base - penalty).clamp(0.0, 1.0)
+    }
+}
+
+impl TrustScorer for WebOfTrust {
+    fn score(&self, public_key: &XOnlyPublicKey) -> f64 {
+        self.get(public_key).map_or(0.0, |score| score.score)
+    }
+}
+
+/// Public keys of the `p` tags
+fn tagged_public_keys(event: &Event) -> Vec<XOnlyPublicKey> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::PubKey(pk, ..) => Some(*pk),
+            Tag::ContactList { pk, .. } => Some(*pk),
+            _ => None,
+        })
+        .collect()
+}
+
+/// [`EventPolicy`] based on the author [`TrustScorer`] score
+///
+/// Events of authors scoring below `reject_below` are rejected, below `downrank_below` are downranked.
+#[derive(Debug, Clone)]
+pub struct TrustScorePolicy<S>
+where
+    S: TrustScorer,
+{
+    scorer: S,
+    downrank_below: f64,
+    reject_below: f64,
+}
+
+impl<S> TrustScorePolicy<S>
+where
+    S: TrustScorer,
+{
+    /// New [`TrustScorePolicy`]
+    ///
+    /// By default events are never rejected and events of authors scoring below `0.5` are downranked.
+    pub fn new(scorer: S) -> Self {
+        Self {
+            scorer,
+            downrank_below: 0.5,
+            reject_below: 0.0,
+        }
+    }
+
+    /// Downrank events of authors scoring below `score`
+    pub fn downrank_below(self, score: f64) -> Self {
+        Self {
+            downrank_below: score,
+            ..self
+        }
+    }
+
+    /// Reject events of authors scoring below `score`
+    pub fn reject_below(self, score: f64) -> Self {
+        Self {
+            reject_below: score,
+            ..self
+        }
+    }
+}
+
+impl<S> EventPolicy for TrustScorePolicy<S>
+where
+    S: TrustScorer,
+{
+    fn check(&self, _relay_url: &Url, event: &Event) -> EventAction {
+        let score: f64 = self.scorer.score(&event.pubkey);
+        if score < self.reject_below {
+            EventAction::Reject
+        } else if score < self.downrank_below {
+            EventAction::Downrank
+        } else {
+            EventAction::Accept
+        }
+    }
+}
@@ -13,10 +13,7 @@ fn main() -> Result<()> {
 
     println!("Public key: {}", public_key);
     println!("Public key bech32: {}", public_key.to_bech32()?);
-    println!(
-        "Secret key: {}",
-        keys.secret_key()?.display_secret().to_string()
-    );
+    println!("Secret key: {}", keys.secret_key()?.display_secret());
     println!("Secret key bech32: {}", secret_key.to_bech32()?);
 
     // Bech32 keys
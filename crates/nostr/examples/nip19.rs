@@ -11,7 +11,7 @@ fn main() -> Result<()> {
     let pubkey = XOnlyPublicKey::from_str(
         "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
     )?;
-    let profile = Profile::new(pubkey, vec!["wss://r.x.com", "wss://djbas.sadkb.com"]);
+    let profile = Profile::try_new(pubkey, vec!["wss://r.x.com", "wss://djbas.sadkb.com"])?;
     println!("{}", profile.to_bech32()?);
 
     Ok(())
@@ -0,0 +1,377 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Attachments
+//!
+//! Send small binary attachments over encrypted direct messages (kind 4), when no media server
+//! is available. The blob is split in chunks, each encrypted (NIP-04) in its own event with the
+//! tags:
+//!
+//! * `["attachment", <id>, <index>, <total>]`: chunk position
+//! * `["x", <sha256 of the blob>]`: integrity hash, checked after the reassembly
+//! * `["m", <mime type>]`: optional mime type
+//!
+//! The chunks can be received in any order: [`AttachmentAssembler`] reassembles them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use base64::engine::{general_purpose, Engine};
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::Hash;
+use secp256k1::XOnlyPublicKey;
+
+use crate::event::{Event, EventBuilder, Kind, Tag, TagKind};
+use crate::key::{self, Keys};
+use crate::nips::nip04;
+
+/// Default chunk size (bytes of the blob in each event)
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+/// Max number of chunks of an attachment
+pub const MAX_CHUNKS: usize = 1024;
+
+const ATTACHMENT_TAG: &str = "attachment";
+const HASH_TAG: &str = "x";
+const MIME_TYPE_TAG: &str = "m";
+
+/// Attachment error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// NIP04 error
+    #[error(transparent)]
+    NIP04(#[from] nip04::Error),
+    /// Empty attachment or zero chunk size
+    #[error("empty attachment or zero chunk size")]
+    Empty,
+    /// Too many chunks
+    #[error("too many chunks: {0} (max {MAX_CHUNKS})")]
+    TooManyChunks(usize),
+    /// Not an attachment chunk
+    #[error("not an attachment chunk")]
+    NotAttachment,
+    /// Malformed attachment tags
+    #[error("malformed attachment tags")]
+    MalformedTags,
+    /// Chunk not consistent with the other chunks of the attachment
+    #[error("chunk not consistent with the attachment")]
+    InconsistentChunk,
+    /// Invalid chunk encoding
+    #[error("invalid chunk encoding")]
+    InvalidEncoding,
+    /// Hash of the reassembled attachment doesn't match
+    #[error("attachment hash mismatch")]
+    HashMismatch,
+}
+
+/// Reassembled attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Attachment id
+    pub id: String,
+    /// Sender
+    pub sender: XOnlyPublicKey,
+    /// Mime type
+    pub mime_type: Option<String>,
+    /// Sha256 of the data
+    pub hash: Sha256Hash,
+    /// Data
+    pub data: Vec<u8>,
+}
+
+struct ChunkHeader {
+    id: String,
+    index: usize,
+    total: usize,
+    hash: Sha256Hash,
+    mime_type: Option<String>,
+}
+
+impl ChunkHeader {
+    fn from_tags(tags: &[Tag]) -> Result<Self, Error> {
+        let mut position: Option<(String, usize, usize)> = None;
+        let mut hash: Option<Sha256Hash> = None;
+        let mut mime_type: Option<String> = None;
+        for tag in tags.iter() {
+            match tag.as_vec().as_slice() {
+                [kind, id, index, total] if kind == ATTACHMENT_TAG => {
+                    let index: usize = index.parse().map_err(|_| Error::MalformedTags)?;
+                    let total: usize = total.parse().map_err(|_| Error::MalformedTags)?;
+                    position = Some((id.clone(), index, total));
+                }
+                [kind, ..] if kind == ATTACHMENT_TAG => return Err(Error::MalformedTags),
+                [kind, x] if kind == HASH_TAG => {
+                    hash = Some(Sha256Hash::from_str(x).map_err(|_| Error::MalformedTags)?);
+                }
+                [kind, m] if kind == MIME_TYPE_TAG => mime_type = Some(m.clone()),
+                _ => (),
+            }
+        }
+
+        let (id, index, total) = position.ok_or(Error::NotAttachment)?;
+        if total == 0 || total > MAX_CHUNKS || index >= total {
+            return Err(Error::MalformedTags);
+        }
+        Ok(Self {
+            id,
+            index,
+            total,
+            hash: hash.ok_or(Error::MalformedTags)?,
+            mime_type,
+        })
+    }
+}
+
+impl EventBuilder {
+    /// Split `data` in encrypted direct messages of `chunk_size` bytes each
+    ///
+    /// Return the [`EventBuilder`] of every chunk: all of them must be sent.
+    pub fn encrypted_attachment<S>(
+        sender_keys: &Keys,
+        receiver_pubkey: XOnlyPublicKey,
+        data: &[u8],
+        mime_type: Option<S>,
+        chunk_size: usize,
+    ) -> Result<Vec<Self>, Error>
+    where
+        S: Into<String>,
+    {
+        if data.is_empty() || chunk_size == 0 {
+            return Err(Error::Empty);
+        }
+
+        let total: usize = (data.len() + chunk_size - 1) / chunk_size;
+        if total > MAX_CHUNKS {
+            return Err(Error::TooManyChunks(total));
+        }
+
+        let id: String = uuid::Uuid::new_v4().simple().to_string();
+        let hash = Sha256Hash::hash(data);
+        let mime_type: Option<String> = mime_type.map(|m| m.into());
+        let secret_key = sender_keys.secret_key()?;
+
+        let mut builders: Vec<Self> = Vec::with_capacity(total);
+        for (index, chunk) in data.chunks(chunk_size).enumerate() {
+            let content: String = nip04::encrypt(
+                &secret_key,
+                &receiver_pubkey,
+                general_purpose::STANDARD.encode(chunk),
+            )?;
+            let mut tags: Vec<Tag> = vec![
+                Tag::PubKey(receiver_pubkey, None),
+                Tag::Generic(
+                    TagKind::Custom(String::from(ATTACHMENT_TAG)),
+                    vec![id.clone(), index.to_string(), total.to_string()],
+                ),
+                Tag::Generic(
+                    TagKind::Custom(String::from(HASH_TAG)),
+                    vec![hash.to_string()],
+                ),
+            ];
+            if let Some(mime_type) = &mime_type {
+                tags.push(Tag::Generic(
+                    TagKind::Custom(String::from(MIME_TYPE_TAG)),
+                    vec![mime_type.clone()],
+                ));
+            }
+            builders.push(Self::new(Kind::EncryptedDirectMessage, content, &tags));
+        }
+        Ok(builders)
+    }
+}
+
+struct PartialAttachment {
+    total: usize,
+    hash: Sha256Hash,
+    mime_type: Option<String>,
+    chunks: BTreeMap<usize, Vec<u8>>,
+}
+
+/// Reassemble the attachments received (or sent) by `keys`
+pub struct AttachmentAssembler {
+    keys: Keys,
+    partials: HashMap<(XOnlyPublicKey, String), PartialAttachment>,
+}
+
+impl AttachmentAssembler {
+    /// New [`AttachmentAssembler`]
+    pub fn new(keys: Keys) -> Self {
+        Self {
+            keys,
+            partials: HashMap::new(),
+        }
+    }
+
+    /// Add chunk [`Event`]
+    ///
+    /// Return the [`Attachment`] when its last missing chunk is received.
+    /// Duplicated chunks are ignored.
+    pub fn ingest(&mut self, event: &Event) -> Result<Option<Attachment>, Error> {
+        if event.kind != Kind::EncryptedDirectMessage {
+            return Err(Error::NotAttachment);
+        }
+
+        let header = ChunkHeader::from_tags(&event.tags)?;
+
+        let key = (event.pubkey, header.id.clone());
+        if let Some(partial) = self.partials.get(&key) {
+            if partial.total != header.total || partial.hash != header.hash {
+                return Err(Error::InconsistentChunk);
+            }
+            if partial.chunks.contains_key(&header.index) {
+                return Ok(None);
+            }
+        }
+
+        // Chunks sent by us are encrypted with the key of the receiver
+        let counterparty: XOnlyPublicKey = if event.pubkey == self.keys.public_key() {
+            event
+                .tags
+                .iter()
+                .find_map(|tag| match tag {
+                    Tag::PubKey(public_key, ..) => Some(*public_key),
+                    _ => None,
+                })
+                .ok_or(Error::MalformedTags)?
+        } else {
+            event.pubkey
+        };
+        let content: String =
+            nip04::decrypt(&self.keys.secret_key()?, &counterparty, &event.content)?;
+        let chunk: Vec<u8> = general_purpose::STANDARD
+            .decode(content)
+            .map_err(|_| Error::InvalidEncoding)?;
+
+        let partial = self
+            .partials
+            .entry(key.clone())
+            .or_insert_with(|| PartialAttachment {
+                total: header.total,
+                hash: header.hash,
+                mime_type: header.mime_type,
+                chunks: BTreeMap::new(),
+            });
+        partial.chunks.insert(header.index, chunk);
+
+        if partial.chunks.len() < partial.total {
+            return Ok(None);
+        }
+
+        let partial = self.partials.remove(&key).ok_or(Error::NotAttachment)?;
+        let data: Vec<u8> = partial.chunks.into_values().flatten().collect();
+        if Sha256Hash::hash(&data) != partial.hash {
+            return Err(Error::HashMismatch);
+        }
+        Ok(Some(Attachment {
+            id: header.id,
+            sender: event.pubkey,
+            mime_type: partial.mime_type,
+            hash: partial.hash,
+            data,
+        }))
+    }
+
+    /// Number of attachments with missing chunks
+    pub fn pending(&self) -> usize {
+        self.partials.len()
+    }
+
+    /// Discard the chunks received of attachment `id` of `sender`
+    pub fn discard(&mut self, sender: XOnlyPublicKey, id: &str) -> bool {
+        self.partials.remove(&(sender, id.to_string())).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+
+        let mut events: Vec<Event> = EventBuilder::encrypted_attachment(
+            &alice,
+            bob.public_key(),
+            &data,
+            Some("image/png"),
+            300,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|builder| builder.to_event(&alice).unwrap())
+        .collect();
+        assert_eq!(events.len(), 4);
+
+        // Out of order and duplicated chunks
+        events.reverse();
+        let mut assembler = AttachmentAssembler::new(bob.clone());
+        assert_eq!(assembler.ingest(&events[0]).unwrap(), None);
+        assert_eq!(assembler.ingest(&events[0]).unwrap(), None);
+        assert_eq!(assembler.ingest(&events[2]).unwrap(), None);
+        assert_eq!(assembler.ingest(&events[1]).unwrap(), None);
+        assert_eq!(assembler.pending(), 1);
+        let attachment = assembler.ingest(&events[3]).unwrap().unwrap();
+        assert_eq!(attachment.data, data);
+        assert_eq!(attachment.sender, alice.public_key());
+        assert_eq!(attachment.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(assembler.pending(), 0);
+
+        // The sender can reassemble the attachment too
+        let mut assembler = AttachmentAssembler::new(alice.clone());
+        let attachment = events
+            .iter()
+            .find_map(|event| assembler.ingest(event).unwrap())
+            .unwrap();
+        assert_eq!(attachment.data, data);
+    }
+
+    #[test]
+    fn test_attachment_errors() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+
+        assert!(matches!(
+            EventBuilder::encrypted_attachment(&alice, bob.public_key(), &[], None::<String>, 10),
+            Err(Error::Empty)
+        ));
+        assert!(matches!(
+            EventBuilder::encrypted_attachment(
+                &alice,
+                bob.public_key(),
+                &[0; 2000],
+                None::<String>,
+                1
+            ),
+            Err(Error::TooManyChunks(2000))
+        ));
+
+        let mut assembler = AttachmentAssembler::new(bob.clone());
+        let note = EventBuilder::new_encrypted_direct_msg(&alice, bob.public_key(), "hello")
+            .unwrap()
+            .to_event(&alice)
+            .unwrap();
+        assert!(matches!(assembler.ingest(&note), Err(Error::NotAttachment)));
+
+        // Tampered hash
+        let builder = EventBuilder::encrypted_attachment(
+            &alice,
+            bob.public_key(),
+            b"data",
+            None::<String>,
+            10,
+        )
+        .unwrap()
+        .remove(0);
+        let mut event = builder.to_event(&alice).unwrap();
+        event.tags[2] = Tag::Generic(
+            TagKind::Custom(String::from(HASH_TAG)),
+            vec![Sha256Hash::hash(b"other").to_string()],
+        );
+        assert!(matches!(assembler.ingest(&event), Err(Error::HashMismatch)));
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Binary serialization
+//!
+//! Compact serialization of [`Event`], [`SubscriptionFilter`], [`ClientMessage`] and [`RelayMessage`]
+//! to CBOR (`cbor` feature) or MessagePack (`msgpack` feature), for local storage, IPC or caches.
+//! Not meant to be exchanged with relays, that only speak JSON.
+
+use std::str::FromStr;
+
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::event::{self, Event, EventId, Kind, Tag};
+use crate::message::MessageHandleError;
+use crate::{ClientMessage, RelayMessage, SubscriptionFilter, Timestamp};
+
+/// Binary serialization error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// CBOR error
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    Cbor(#[from] serde_cbor::Error),
+    /// MessagePack encode error
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+    /// MessagePack decode error
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Event id error
+    #[error(transparent)]
+    EventId(#[from] event::id::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] event::Error),
+    /// Tag error
+    #[error(transparent)]
+    Tag(#[from] event::tag::Error),
+    /// Message error
+    #[error(transparent)]
+    Message(#[from] MessageHandleError),
+}
+
+/// Binary format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// CBOR (RFC 8949)
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// MessagePack
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+fn encode<T>(value: &T, format: Format) -> Result<Vec<u8>, Error>
+where
+    T: Serialize,
+{
+    match format {
+        #[cfg(feature = "cbor")]
+        Format::Cbor => Ok(serde_cbor::to_vec(value)?),
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack => Ok(rmp_serde::to_vec(value)?),
+    }
+}
+
+fn decode<T>(bytes: &[u8], format: Format) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    match format {
+        #[cfg(feature = "cbor")]
+        Format::Cbor => Ok(serde_cbor::from_slice(bytes)?),
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack => Ok(rmp_serde::from_slice(bytes)?),
+    }
+}
+
+/// [`Event`] with keys, id and signature as raw bytes
+#[derive(Serialize, Deserialize)]
+struct BinaryEvent {
+    #[serde(with = "serde_bytes")]
+    id: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pubkey: Vec<u8>,
+    created_at: u64,
+    kind: u64,
+    tags: Vec<Vec<String>>,
+    content: String,
+    #[serde(with = "serde_bytes")]
+    sig: Vec<u8>,
+    ots: Option<String>,
+}
+
+impl Event {
+    /// Serialize [`Event`] to binary `format`
+    ///
+    /// Keys, id and signature are stored as raw bytes.
+    pub fn to_binary(&self, format: Format) -> Result<Vec<u8>, Error> {
+        let event = BinaryEvent {
+            id: self.id.as_bytes().to_vec(),
+            pubkey: self.pubkey.serialize().to_vec(),
+            created_at: self.created_at.as_u64(),
+            kind: self.kind.as_u64(),
+            tags: self.tags.iter().map(|t| t.as_vec()).collect(),
+            content: self.content.clone(),
+            sig: self.sig.as_ref().to_vec(),
+            ots: self.ots.clone(),
+        };
+        encode(&event, format)
+    }
+
+    /// Deserialize [`Event`] from binary `format`
+    ///
+    /// The event is verified, as in [`Event::from_json`].
+    pub fn from_binary(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        let event: BinaryEvent = decode(bytes, format)?;
+        let event = Self {
+            id: EventId::from_slice(&event.id)?,
+            pubkey: XOnlyPublicKey::from_slice(&event.pubkey)?,
+            created_at: Timestamp::from(event.created_at),
+            kind: Kind::from(event.kind),
+            tags: event
+                .tags
+                .into_iter()
+                .map(Tag::parse)
+                .collect::<Result<Vec<Tag>, _>>()?,
+            content: event.content,
+            sig: Signature::from_slice(&event.sig)?,
+            ots: event.ots,
+        };
+        event.verify()?;
+        Ok(event)
+    }
+}
+
+impl SubscriptionFilter {
+    /// Serialize [`SubscriptionFilter`] to binary `format`
+    ///
+    /// The filter keeps the structure of its JSON representation.
+    pub fn to_binary(&self, format: Format) -> Result<Vec<u8>, Error> {
+        encode(&serde_json::to_value(self)?, format)
+    }
+
+    /// Deserialize [`SubscriptionFilter`] from binary `format`
+    pub fn from_binary(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        let value: Value = decode(bytes, format)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl ClientMessage {
+    /// Serialize [`ClientMessage`] to binary `format`
+    ///
+    /// The message keeps the structure of its JSON representation.
+    pub fn to_binary(&self, format: Format) -> Result<Vec<u8>, Error> {
+        encode(&json_value(&self.as_json())?, format)
+    }
+
+    /// Deserialize [`ClientMessage`] from binary `format`
+    pub fn from_binary(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        let value: Value = decode(bytes, format)?;
+        Ok(Self::from_json(value.to_string())?)
+    }
+}
+
+impl RelayMessage {
+    /// Serialize [`RelayMessage`] to binary `format`
+    ///
+    /// The message keeps the structure of its JSON representation.
+    pub fn to_binary(&self, format: Format) -> Result<Vec<u8>, Error> {
+        encode(&json_value(&self.as_json())?, format)
+    }
+
+    /// Deserialize [`RelayMessage`] from binary `format`
+    pub fn from_binary(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        let value: Value = decode(bytes, format)?;
+        Ok(Self::from_json(value.to_string())?)
+    }
+}
+
+fn json_value(json: &str) -> Result<Value, Error> {
+    Ok(Value::from_str(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, SubscriptionId};
+
+    #[test]
+    fn test_binary() {
+        let keys = Keys::generate();
+        let event = EventBuilder::new_text_note("Hello", &[Tag::Subject(String::from("nostr"))])
+            .to_event(&keys)
+            .unwrap();
+        let filter = SubscriptionFilter::new()
+            .author(keys.public_key())
+            .kind(Kind::TextNote)
+            .event(event.id)
+            .limit(10);
+        let req = ClientMessage::new_req(SubscriptionId::generate(), vec![filter.clone()]);
+        let msg = RelayMessage::new_event(SubscriptionId::generate(), event.clone());
+
+        let formats = [
+            #[cfg(feature = "cbor")]
+            Format::Cbor,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack,
+        ];
+        for format in formats.into_iter() {
+            let bytes = event.to_binary(format).unwrap();
+            assert!(bytes.len() < event.as_json().unwrap().len());
+            assert_eq!(Event::from_binary(&bytes, format).unwrap(), event);
+
+            let bytes = filter.to_binary(format).unwrap();
+            assert_eq!(
+                SubscriptionFilter::from_binary(&bytes, format).unwrap(),
+                filter
+            );
+
+            let bytes = req.to_binary(format).unwrap();
+            assert_eq!(ClientMessage::from_binary(&bytes, format).unwrap(), req);
+
+            let bytes = msg.to_binary(format).unwrap();
+            assert_eq!(RelayMessage::from_binary(&bytes, format).unwrap(), msg);
+
+            // Tampered content
+            let mut tampered = event.clone();
+            tampered.content = String::from("Bye");
+            let bytes = tampered.to_binary(format).unwrap();
+            assert!(Event::from_binary(&bytes, format).is_err());
+        }
+    }
+}
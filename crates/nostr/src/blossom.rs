@@ -0,0 +1,313 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Blossom
+//!
+//! Blobs stored simply on media servers: <https://github.com/hzrd149/blossom>
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base64::engine::{general_purpose, Engine};
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::Hash;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Proxy, RequestBuilder, Response};
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use url::Url;
+
+use crate::event::{self, builder, Event, EventBuilder, Kind, Tag, TagKind};
+use crate::{Keys, Timestamp};
+
+/// Validity of authorization events
+const AUTH_EXPIRATION: Duration = Duration::from_secs(300);
+
+/// Blossom error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reqwest error
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// Url parse error
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] event::Error),
+    /// Event builder error
+    #[error(transparent)]
+    EventBuilder(#[from] builder::Error),
+    /// Server error
+    #[error("server error {status}: {reason}")]
+    Server {
+        /// HTTP status code
+        status: u16,
+        /// Reason
+        reason: String,
+    },
+    /// Downloaded blob doesn't match the requested hash
+    #[error("hash mismatch")]
+    HashMismatch,
+}
+
+/// Authorized action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Download blob
+    Get,
+    /// Upload (or mirror) blob
+    Upload,
+    /// List blobs
+    List,
+    /// Delete blob
+    Delete,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Get => write!(f, "get"),
+            Self::Upload => write!(f, "upload"),
+            Self::List => write!(f, "list"),
+            Self::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// Blob descriptor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobDescriptor {
+    /// Blob URL
+    pub url: Url,
+    /// SHA256 hash of the blob
+    pub sha256: Sha256Hash,
+    /// Size in bytes
+    pub size: u64,
+    /// MIME type
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Upload timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded: Option<Timestamp>,
+}
+
+impl EventBuilder {
+    /// Blossom authorization
+    ///
+    /// <https://github.com/hzrd149/blossom/blob/master/buds/01.md>
+    pub fn blossom_auth<S>(
+        action: Action,
+        content: S,
+        hashes: Vec<Sha256Hash>,
+        expiration: Timestamp,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut tags: Vec<Tag> = vec![Tag::Generic(TagKind::T, vec![action.to_string()])];
+        for hash in hashes.into_iter() {
+            tags.push(Tag::Generic(TagKind::X, vec![hash.to_string()]));
+        }
+        tags.push(Tag::Expiration(expiration));
+        Self::new(Kind::BlossomAuth, content, &tags)
+    }
+}
+
+/// Blossom media server client
+#[derive(Debug, Clone)]
+pub struct BlossomClient {
+    server: Url,
+    client: reqwest::Client,
+}
+
+impl BlossomClient {
+    /// New [`BlossomClient`]
+    pub fn new(server: Url, proxy: Option<SocketAddr>) -> Result<Self, Error> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = proxy {
+            let proxy = format!("socks5h://{proxy}");
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        Ok(Self {
+            server,
+            client: builder.build()?,
+        })
+    }
+
+    /// Get server URL
+    pub fn server(&self) -> Url {
+        self.server.clone()
+    }
+
+    /// Upload blob
+    pub async fn upload(
+        &self,
+        keys: &Keys,
+        data: Vec<u8>,
+        mime_type: Option<&str>,
+    ) -> Result<BlobDescriptor, Error> {
+        let hash = Sha256Hash::hash(&data);
+        let mut req = self
+            .client
+            .put(self.server.join("/upload")?)
+            .header(
+                CONTENT_TYPE,
+                mime_type.unwrap_or("application/octet-stream"),
+            )
+            .body(data);
+        req = authorize(req, keys, Action::Upload, "Upload blob", vec![hash])?;
+        let res = check_status(req.send().await?).await?;
+        Ok(res.json().await?)
+    }
+
+    /// Mirror blob already stored on another server
+    ///
+    /// <https://github.com/hzrd149/blossom/blob/master/buds/04.md>
+    pub async fn mirror(
+        &self,
+        keys: &Keys,
+        blob: &BlobDescriptor,
+    ) -> Result<BlobDescriptor, Error> {
+        let mut req = self
+            .client
+            .put(self.server.join("/mirror")?)
+            .json(&json!({ "url": blob.url }));
+        req = authorize(req, keys, Action::Upload, "Mirror blob", vec![blob.sha256])?;
+        let res = check_status(req.send().await?).await?;
+        Ok(res.json().await?)
+    }
+
+    /// Download blob
+    ///
+    /// The content is checked against `sha256`.
+    pub async fn download(
+        &self,
+        sha256: &Sha256Hash,
+        keys: Option<&Keys>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut req = self.client.get(self.server.join(&format!("/{sha256}"))?);
+        if let Some(keys) = keys {
+            req = authorize(req, keys, Action::Get, "Get blob", vec![*sha256])?;
+        }
+        let res = check_status(req.send().await?).await?;
+        let data: Vec<u8> = res.bytes().await?.to_vec();
+        if &Sha256Hash::hash(&data) != sha256 {
+            return Err(Error::HashMismatch);
+        }
+        Ok(data)
+    }
+
+    /// List blobs uploaded by public key
+    pub async fn list(
+        &self,
+        public_key: XOnlyPublicKey,
+        keys: Option<&Keys>,
+    ) -> Result<Vec<BlobDescriptor>, Error> {
+        let mut req = self
+            .client
+            .get(self.server.join(&format!("/list/{public_key}"))?);
+        if let Some(keys) = keys {
+            req = authorize(req, keys, Action::List, "List blobs", Vec::new())?;
+        }
+        let res = check_status(req.send().await?).await?;
+        Ok(res.json().await?)
+    }
+
+    /// Delete blob
+    pub async fn delete(&self, keys: &Keys, sha256: &Sha256Hash) -> Result<(), Error> {
+        let mut req = self.client.delete(self.server.join(&format!("/{sha256}"))?);
+        req = authorize(req, keys, Action::Delete, "Delete blob", vec![*sha256])?;
+        check_status(req.send().await?).await?;
+        Ok(())
+    }
+}
+
+fn authorize(
+    req: RequestBuilder,
+    keys: &Keys,
+    action: Action,
+    content: &str,
+    hashes: Vec<Sha256Hash>,
+) -> Result<RequestBuilder, Error> {
+    let expiration = Timestamp::now() + AUTH_EXPIRATION;
+    let event: Event =
+        EventBuilder::blossom_auth(action, content, hashes, expiration).to_event(keys)?;
+    let header = format!(
+        "Nostr {}",
+        general_purpose::STANDARD.encode(event.as_json()?)
+    );
+    Ok(req.header(AUTHORIZATION, header))
+}
+
+/// Blossom servers return the failure reason in the `X-Reason` header
+async fn check_status(res: Response) -> Result<Response, Error> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let reason: String = match res.headers().get("X-Reason") {
+        Some(reason) => reason.to_str().unwrap_or_default().to_string(),
+        None => res.text().await.unwrap_or_default(),
+    };
+    Err(Error::Server {
+        status: status.as_u16(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_blossom_auth_event() {
+        let keys = Keys::generate();
+        let hash = Sha256Hash::hash(b"hello");
+        let event = EventBuilder::blossom_auth(
+            Action::Upload,
+            "Upload blob",
+            vec![hash],
+            Timestamp::from(1708774469),
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        assert_eq!(event.kind, Kind::BlossomAuth);
+        assert_eq!(
+            event.tags,
+            vec![
+                Tag::Generic(TagKind::T, vec!["upload".to_string()]),
+                Tag::Generic(
+                    TagKind::X,
+                    vec![
+                        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                            .to_string()
+                    ]
+                ),
+                Tag::Expiration(Timestamp::from(1708774469)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_blob_descriptor() {
+        let json = r#"{"url":"https://cdn.example.com/b1674191a88ec5cdd733e4240a81803105dc412d6c6708d53ab94fc248f4f553.pdf","sha256":"b1674191a88ec5cdd733e4240a81803105dc412d6c6708d53ab94fc248f4f553","size":184292,"type":"application/pdf","uploaded":1725105921}"#;
+        let blob: BlobDescriptor = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            blob.sha256,
+            Sha256Hash::from_str(
+                "b1674191a88ec5cdd733e4240a81803105dc412d6c6708d53ab94fc248f4f553"
+            )
+            .unwrap()
+        );
+        assert_eq!(blob.size, 184292);
+        assert_eq!(blob.mime_type, Some(String::from("application/pdf")));
+        assert_eq!(blob.uploaded, Some(Timestamp::from(1725105921)));
+    }
+}
@@ -3,15 +3,15 @@
 
 //! Event builder
 
-use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
 use serde_json::{json, Value};
 use url::Url;
 
 pub use super::kind::Kind;
 pub use super::tag::{Marker, Tag, TagKind};
-use super::{Event, EventId};
+use super::{Event, EventId, UnsignedEvent};
 use crate::key::{self, Keys};
 #[cfg(feature = "nip04")]
 use crate::nips::nip04;
@@ -30,7 +30,7 @@ pub enum Error {
     Key(#[from] key::Error),
     #[error(transparent)]
     /// Secp256k1 error
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
     /// JSON error
     #[error(transparent)]
     Json(#[from] serde_json::Error),
@@ -64,6 +64,12 @@ impl EventBuilder {
         }
     }
 
+    /// Add tags
+    pub fn add_tags(mut self, tags: &[Tag]) -> Self {
+        self.tags.extend_from_slice(tags);
+        self
+    }
+
     /// Build [`Event`]
     pub fn to_event(self, keys: &Keys) -> Result<Event, Error> {
         let secp = Secp256k1::new();
@@ -86,9 +92,48 @@ impl EventBuilder {
         })
     }
 
+    /// Build [`UnsignedEvent`] for `pubkey`
+    ///
+    /// Use it when the secret key is held by an external signer.
+    pub fn to_unsigned_event(self, pubkey: XOnlyPublicKey) -> UnsignedEvent {
+        let created_at: Timestamp = Timestamp::now();
+        let id = EventId::new(&pubkey, created_at, &self.kind, &self.tags, &self.content);
+        UnsignedEvent {
+            id,
+            pubkey,
+            created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+        }
+    }
+
     /// Build POW [`Event`]
     #[cfg(feature = "nip13")]
     pub fn to_pow_event(self, keys: &Keys, difficulty: u8) -> Result<Event, Error> {
+        let unsigned: UnsignedEvent = self.to_unsigned_pow_event(keys.public_key(), difficulty);
+
+        let secp = Secp256k1::new();
+        let keypair: &KeyPair = &keys.key_pair()?;
+        let message = Message::from_slice(unsigned.id.as_bytes())?;
+
+        Ok(Event {
+            id: unsigned.id,
+            pubkey: unsigned.pubkey,
+            created_at: unsigned.created_at,
+            kind: unsigned.kind,
+            tags: unsigned.tags,
+            content: unsigned.content,
+            sig: secp.sign_schnorr(&message, keypair),
+            ots: None,
+        })
+    }
+
+    /// Build POW [`UnsignedEvent`] for `pubkey`
+    ///
+    /// Use it when the secret key is held by an external signer.
+    #[cfg(feature = "nip13")]
+    pub fn to_unsigned_pow_event(self, pubkey: XOnlyPublicKey, difficulty: u8) -> UnsignedEvent {
         #[cfg(target_arch = "wasm32")]
         use instant::Instant;
         #[cfg(not(target_arch = "wasm32"))]
@@ -97,8 +142,6 @@ impl EventBuilder {
         let mut nonce: u128 = 0;
         let mut tags: Vec<Tag> = self.tags;
 
-        let pubkey = keys.public_key();
-
         let now = Instant::now();
 
         loop {
@@ -117,20 +160,14 @@ impl EventBuilder {
                     nonce * 1000 / std::cmp::max(1, now.elapsed().as_millis())
                 );
 
-                let secp = Secp256k1::new();
-                let keypair: &KeyPair = &keys.key_pair()?;
-                let message = Message::from_slice(id.as_bytes())?;
-
-                return Ok(Event {
+                return UnsignedEvent {
                     id,
                     pubkey,
                     created_at,
                     kind: self.kind,
                     tags,
                     content: self.content,
-                    sig: secp.sign_schnorr(&message, keypair),
-                    ots: None,
-                });
+                };
             }
 
             tags.pop();
@@ -416,7 +453,7 @@ impl EventBuilder {
 mod tests {
     use std::str::FromStr;
 
-    use bitcoin::secp256k1::SecretKey;
+    use secp256k1::SecretKey;
 
     use crate::{Event, EventBuilder, Keys, Result};
 
@@ -426,7 +463,7 @@ mod tests {
             "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e",
         )?);
 
-        let event = EventBuilder::new_text_note("hello", &vec![]).to_event(&keys)?;
+        let event = EventBuilder::new_text_note("hello", &[]).to_event(&keys)?;
 
         let serialized = event.as_json().unwrap();
         let deserialized = Event::from_json(serialized)?;
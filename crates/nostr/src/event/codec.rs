@@ -0,0 +1,360 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Content codecs
+//!
+//! Structured content of events, decoded by kind: implement [`ContentCodec`] to support a new kind
+//! and access the content with [`TypedEvent`].
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{Event, EventBuilder, Kind, Tag};
+use crate::Metadata;
+
+/// [`ContentCodec`] error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Kind not supported by the codec
+    #[error("unexpected kind: {0}")]
+    UnexpectedKind(u64),
+    /// Invalid content
+    #[error("invalid content: {0}")]
+    InvalidContent(String),
+    /// Error serializing or deserializing JSON data
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Compression error
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[error(transparent)]
+    Compression(#[from] super::compression::Error),
+}
+
+/// Content codec
+pub trait ContentCodec: Sized {
+    /// Kinds with this content format (the first one is used to build new events)
+    fn kinds() -> Vec<Kind>;
+
+    /// Decode content
+    fn decode(content: &str) -> Result<Self, Error>;
+
+    /// Encode content
+    fn encode(&self) -> Result<String, Error>;
+}
+
+/// [`Event`] with structured content
+///
+/// The content is decoded on access with the [`ContentCodec`] `T`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedEvent<T> {
+    event: Event,
+    codec: PhantomData<T>,
+}
+
+impl<T> TypedEvent<T>
+where
+    T: ContentCodec,
+{
+    /// New [`TypedEvent`]
+    ///
+    /// Return [`Error::UnexpectedKind`] if the kind is not supported by the codec.
+    pub fn new(event: Event) -> Result<Self, Error> {
+        if !T::kinds().contains(&event.kind) {
+            return Err(Error::UnexpectedKind(event.kind.as_u64()));
+        }
+        Ok(Self {
+            event,
+            codec: PhantomData,
+        })
+    }
+
+    /// [`EventBuilder`] of a new event with `content`
+    pub fn builder(content: &T, tags: &[Tag]) -> Result<EventBuilder, Error> {
+        let kind: Kind = T::kinds()
+            .first()
+            .copied()
+            .ok_or_else(|| Error::InvalidContent(String::from("codec without kinds")))?;
+        Ok(EventBuilder::new(kind, content.encode()?, tags))
+    }
+
+    /// [`EventBuilder`] of a new event with `content` compressed with `encoding`
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn compressed_builder(
+        content: &T,
+        tags: &[Tag],
+        encoding: super::compression::Encoding,
+    ) -> Result<EventBuilder, Error> {
+        let kind: Kind = T::kinds()
+            .first()
+            .copied()
+            .ok_or_else(|| Error::InvalidContent(String::from("codec without kinds")))?;
+        Ok(EventBuilder::new_compressed(
+            kind,
+            content.encode()?,
+            tags,
+            encoding,
+        )?)
+    }
+
+    /// Get [`Event`]
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Get [`Event`]
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+
+    /// Decode content, decompressed if compressed
+    pub fn content(&self) -> Result<T, Error> {
+        T::decode(&content_of(&self.event)?)
+    }
+}
+
+/// Get content of `event`, decompressed if marked with a `content-encoding` tag
+fn content_of(event: &Event) -> Result<Cow<'_, str>, Error> {
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    return Ok(super::compression::decompressed_content(event)?);
+    #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+    Ok(Cow::Borrowed(&event.content))
+}
+
+type Validator = fn(&str) -> Result<(), Error>;
+
+/// Registry of the [`ContentCodec`]s by kind
+///
+/// Useful to validate events of any kind, ex. before publishing or storing them.
+#[derive(Clone)]
+pub struct CodecRegistry {
+    validators: BTreeMap<Kind, Validator>,
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodecRegistry")
+            .field("kinds", &self.validators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Registry with the built-in codecs: [`Metadata`], [`Pgn`] and [`Markdown`]
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .register::<Metadata>()
+            .register::<Pgn>()
+            .register::<Markdown>()
+    }
+}
+
+impl CodecRegistry {
+    /// New [`CodecRegistry`] with the built-in codecs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New [`CodecRegistry`] without codecs
+    pub fn empty() -> Self {
+        Self {
+            validators: BTreeMap::new(),
+        }
+    }
+
+    /// Register codec `T` for its kinds, replacing the codecs already registered for them
+    pub fn register<T>(mut self) -> Self
+    where
+        T: ContentCodec,
+    {
+        for kind in T::kinds().into_iter() {
+            self.validators
+                .insert(kind, |content| T::decode(content).map(|_| ()));
+        }
+        self
+    }
+
+    /// Check if a codec is registered for `kind`
+    pub fn is_registered(&self, kind: &Kind) -> bool {
+        self.validators.contains_key(kind)
+    }
+
+    /// Validate [`Event`] content, decompressed if compressed
+    ///
+    /// Events of kinds without codec are always valid.
+    pub fn validate(&self, event: &Event) -> Result<(), Error> {
+        match self.validators.get(&event.kind) {
+            Some(validator) => validator(&content_of(event)?),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Metadata (NIP-01): JSON
+impl ContentCodec for Metadata {
+    fn kinds() -> Vec<Kind> {
+        vec![Kind::Metadata]
+    }
+
+    fn decode(content: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    fn encode(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Chess game in Portable Game Notation (NIP-64)
+///
+/// Only the structure is parsed (tag pairs and movetext): moves are not validated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pgn {
+    /// Tag pairs (ex. `Event`, `White`, `Result`)
+    pub tags: Vec<(String, String)>,
+    /// Movetext
+    pub movetext: String,
+}
+
+impl Pgn {
+    /// Get value of tag pair `name`
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl ContentCodec for Pgn {
+    fn kinds() -> Vec<Kind> {
+        vec![Kind::Chess]
+    }
+
+    fn decode(content: &str) -> Result<Self, Error> {
+        let mut pgn = Self::default();
+        let mut movetext: Vec<&str> = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') && movetext.is_empty() {
+                let pair: &str = line
+                    .strip_prefix('[')
+                    .and_then(|l| l.strip_suffix(']'))
+                    .ok_or_else(|| Error::InvalidContent(format!("invalid tag pair: {line}")))?;
+                let (name, value) = pair
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| Error::InvalidContent(format!("invalid tag pair: {line}")))?;
+                let value: &str = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .ok_or_else(|| Error::InvalidContent(format!("invalid tag pair: {line}")))?;
+                pgn.tags
+                    .push((name.to_string(), value.replace("\\\"", "\"")));
+            } else if !line.is_empty() {
+                movetext.push(line);
+            }
+        }
+        pgn.movetext = movetext.join("\n");
+
+        if pgn.tags.is_empty() && pgn.movetext.is_empty() {
+            return Err(Error::InvalidContent(String::from("empty PGN")));
+        }
+
+        Ok(pgn)
+    }
+
+    fn encode(&self) -> Result<String, Error> {
+        let mut lines: Vec<String> = self
+            .tags
+            .iter()
+            .map(|(name, value)| format!("[{name} \"{}\"]", value.replace('"', "\\\"")))
+            .collect();
+        if !self.movetext.is_empty() {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(self.movetext.clone());
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Long-form content in Markdown (NIP-23), articles and drafts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Markdown(pub String);
+
+impl ContentCodec for Markdown {
+    fn kinds() -> Vec<Kind> {
+        vec![Kind::from(30023), Kind::from(30024)]
+    }
+
+    fn decode(content: &str) -> Result<Self, Error> {
+        Ok(Self(content.to_string()))
+    }
+
+    fn encode(&self) -> Result<String, Error> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    const GAME: &str = "[Event \"Casual Game\"]\n[White \"Alice\"]\n[Black \"Bob\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0";
+
+    #[test]
+    fn test_pgn() {
+        let pgn = Pgn::decode(GAME).unwrap();
+        assert_eq!(pgn.tag("White"), Some("Alice"));
+        assert_eq!(pgn.tag("Result"), Some("1-0"));
+        assert_eq!(pgn.movetext, "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0");
+        assert_eq!(pgn.encode().unwrap(), GAME);
+
+        // Movetext only
+        assert!(Pgn::decode("1. e4 *").unwrap().tags.is_empty());
+
+        assert!(Pgn::decode("").is_err());
+        assert!(Pgn::decode("[White Alice]").is_err());
+    }
+
+    #[test]
+    fn test_typed_event() {
+        let keys = Keys::generate();
+        let pgn = Pgn::decode(GAME).unwrap();
+        let event = TypedEvent::builder(&pgn, &[])
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::Chess);
+
+        let typed: TypedEvent<Pgn> = TypedEvent::new(event.clone()).unwrap();
+        assert_eq!(typed.content().unwrap(), pgn);
+
+        assert!(matches!(
+            TypedEvent::<Markdown>::new(event),
+            Err(Error::UnexpectedKind(64))
+        ));
+    }
+
+    #[test]
+    fn test_registry() {
+        let keys = Keys::generate();
+        let registry = CodecRegistry::new();
+        assert!(registry.is_registered(&Kind::from(30023)));
+        assert!(!registry.is_registered(&Kind::TextNote));
+
+        let invalid = EventBuilder::new(Kind::Chess, "", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(registry.validate(&invalid).is_err());
+        assert!(CodecRegistry::empty().validate(&invalid).is_ok());
+
+        let metadata = EventBuilder::new(Kind::Metadata, "{\"name\":\"alice\"}", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(registry.validate(&metadata).is_ok());
+    }
+}
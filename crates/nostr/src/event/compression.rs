@@ -0,0 +1,210 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Content compression
+//!
+//! Opt-in compression of large content (ex. app data or file lists): the content is compressed,
+//! base64 encoded and marked with a `content-encoding` tag (ex. `["content-encoding", "gzip"]`).
+//! Content of the kinds registered in a [`CodecRegistry`](super::CodecRegistry) is decompressed on read.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use base64::engine::{general_purpose, Engine};
+
+use super::{Event, EventBuilder, Kind, Tag, TagKind};
+
+/// Tag marking the encoding of the content
+pub const CONTENT_ENCODING_TAG: &str = "content-encoding";
+
+/// Max size of decompressed content (16 MiB)
+pub const MAX_DECOMPRESSED_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Compression error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// Error while decoding from base64
+    #[error("Error while decoding from base64")]
+    Base64Decode,
+    /// Error while encoding to UTF-8
+    #[error("Error while encoding to UTF-8")]
+    Utf8Encode,
+    /// Unknown encoding
+    #[error("unknown encoding: {0}")]
+    UnknownEncoding(String),
+    /// Encoding not enabled in this build
+    #[error("unsupported encoding: {0}")]
+    UnsupportedEncoding(Encoding),
+    /// Decompressed content larger than [`MAX_DECOMPRESSED_SIZE`]
+    #[error("decompressed content too large")]
+    TooLarge,
+}
+
+/// Content encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Gzip (`gzip` feature)
+    Gzip,
+    /// Zstandard (`zstd` feature)
+    Zstd,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(Error::UnknownEncoding(s.to_string())),
+        }
+    }
+}
+
+/// Compress `content` and encode it to base64
+pub fn compress(content: &str, encoding: Encoding) -> Result<String, Error> {
+    let compressed: Vec<u8> = match encoding {
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip => {
+            use std::io::Write;
+
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(content.as_bytes())?;
+            encoder.finish()?
+        }
+        #[cfg(feature = "zstd")]
+        Encoding::Zstd => zstd::stream::encode_all(content.as_bytes(), 0)?,
+        #[allow(unreachable_patterns)]
+        encoding => return Err(Error::UnsupportedEncoding(encoding)),
+    };
+    Ok(general_purpose::STANDARD.encode(compressed))
+}
+
+/// Decode base64 `content` and decompress it
+///
+/// Fails with [`Error::TooLarge`] if the decompressed content exceeds [`MAX_DECOMPRESSED_SIZE`].
+pub fn decompress(content: &str, encoding: Encoding) -> Result<String, Error> {
+    let compressed: Vec<u8> = general_purpose::STANDARD
+        .decode(content)
+        .map_err(|_| Error::Base64Decode)?;
+    let decoder: Box<dyn Read> = match encoding {
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip => Box::new(flate2::read::GzDecoder::new(compressed.as_slice())),
+        #[cfg(feature = "zstd")]
+        Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(compressed.as_slice())?),
+        #[allow(unreachable_patterns)]
+        encoding => return Err(Error::UnsupportedEncoding(encoding)),
+    };
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(Error::TooLarge);
+    }
+    String::from_utf8(decompressed).map_err(|_| Error::Utf8Encode)
+}
+
+/// Get the [`Encoding`] of the content of `event`, if compressed
+pub fn content_encoding(event: &Event) -> Result<Option<Encoding>, Error> {
+    for tag in event.tags.iter().map(|t| t.as_vec()) {
+        if let [kind, encoding, ..] = tag.as_slice() {
+            if kind == CONTENT_ENCODING_TAG {
+                return Encoding::from_str(encoding).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Get the content of `event`, decompressed if marked with a `content-encoding` tag
+pub fn decompressed_content(event: &Event) -> Result<Cow<'_, str>, Error> {
+    match content_encoding(event)? {
+        Some(encoding) => Ok(Cow::Owned(decompress(&event.content, encoding)?)),
+        None => Ok(Cow::Borrowed(&event.content)),
+    }
+}
+
+impl EventBuilder {
+    /// New [`EventBuilder`] with compressed `content`, marked with a `content-encoding` tag
+    pub fn new_compressed<S>(
+        kind: Kind,
+        content: S,
+        tags: &[Tag],
+        encoding: Encoding,
+    ) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let content: String = compress(content.as_ref(), encoding)?;
+        let mut tags: Vec<Tag> = tags.to_vec();
+        tags.push(Tag::Generic(
+            TagKind::Custom(CONTENT_ENCODING_TAG.to_string()),
+            vec![encoding.to_string()],
+        ));
+        Ok(Self::new(kind, content, &tags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::codec::{CodecRegistry, Markdown, TypedEvent};
+    use crate::Keys;
+
+    #[test]
+    fn test_compression() {
+        let keys = Keys::generate();
+        let content: String = "{\"files\":[\"a.txt\",\"b.txt\"]}".repeat(100);
+        let encodings = [
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip,
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd,
+        ];
+        for encoding in encodings.into_iter() {
+            let event = EventBuilder::new_compressed(Kind::from(30078), &content, &[], encoding)
+                .unwrap()
+                .to_event(&keys)
+                .unwrap();
+            assert!(event.content.len() < content.len());
+            assert_eq!(content_encoding(&event).unwrap(), Some(encoding));
+            assert_eq!(decompressed_content(&event).unwrap(), content);
+
+            // Decompressed on read for registered kinds
+            let article = Markdown(content.clone());
+            let event = TypedEvent::compressed_builder(&article, &[], encoding)
+                .unwrap()
+                .to_event(&keys)
+                .unwrap();
+            assert!(CodecRegistry::new().validate(&event).is_ok());
+            let typed: TypedEvent<Markdown> = TypedEvent::new(event).unwrap();
+            assert_eq!(typed.content().unwrap(), article);
+        }
+
+        let plain = EventBuilder::new(Kind::TextNote, "hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(decompressed_content(&plain).unwrap(), "hello");
+        assert!(matches!(
+            Encoding::from_str("br"),
+            Err(Error::UnknownEncoding(_))
+        ));
+    }
+}
@@ -19,16 +19,88 @@ pub enum Kind {
     EventDeletion,
     Repost,
     Reaction,
+    /// Chat Message (NIP-29)
+    ChatMessage,
+    /// Seal (NIP-59)
+    Seal,
+    /// Private Direct Message (NIP-17)
+    PrivateDirectMessage,
     ChannelCreation,
     ChannelMetadata,
     ChannelMessage,
     ChannelHideMessage,
     ChannelMuteUser,
+    /// Chess (PGN) (NIP-64)
+    Chess,
+    /// Poll Response (NIP-88)
+    PollResponse,
+    /// Gift Wrap (NIP-59)
+    GiftWrap,
+    /// Poll (NIP-88)
+    Poll,
+    /// File Metadata (NIP-94)
+    FileMetadata,
+    /// Label (NIP-32)
+    Label,
+    /// Cashu Token (NIP-60)
+    CashuToken,
+    /// Cashu Wallet History (NIP-60)
+    CashuWalletHistory,
+    /// Group Put User (NIP-29)
+    GroupPutUser,
+    /// Group Remove User (NIP-29)
+    GroupRemoveUser,
+    /// Group Edit Metadata (NIP-29)
+    GroupEditMetadata,
+    /// Group Delete Event (NIP-29)
+    GroupDeleteEvent,
+    /// Group Create (NIP-29)
+    GroupCreate,
+    /// Group Delete (NIP-29)
+    GroupDelete,
+    /// Group Create Invite (NIP-29)
+    GroupCreateInvite,
+    /// Group Join Request (NIP-29)
+    GroupJoinRequest,
+    /// Group Leave Request (NIP-29)
+    GroupLeaveRequest,
+    /// Nutzap (NIP-61)
+    Nutzap,
+    /// Highlight (NIP-84)
+    Highlight,
+    /// Zap Request (NIP-57)
+    ZapRequest,
+    /// Zap Receipt (NIP-57)
+    ZapReceipt,
+    /// Relay List Metadata (NIP-65)
+    RelayList,
+    /// Nutzap Info (NIP-61)
+    NutzapInfo,
+    /// Cashu Wallet (NIP-60)
+    CashuWallet,
     Authentication,
+    /// Nostr Connect (NIP-46)
+    NostrConnect,
+    /// Blossom authorization
+    BlossomAuth,
+    /// HTTP Auth (NIP-98)
+    HttpAuth,
     /// Replacabe event (must be between 10000 and <20000)
     Replaceable(u16),
     /// Ephemeral event (must be between 20000 and <30000)
     Ephemeral(u16),
+    /// Relay Discovery (NIP-66)
+    RelayDiscovery,
+    /// Community Definition (NIP-72)
+    CommunityDefinition,
+    /// Group Metadata (NIP-29)
+    GroupMetadata,
+    /// Group Admins (NIP-29)
+    GroupAdmins,
+    /// Group Members (NIP-29)
+    GroupMembers,
+    /// Group Roles (NIP-29)
+    GroupRoles,
     /// Parameterized Replacabe event (must be between 30000 and <40000)
     ParameterizedReplaceable(u16),
     Custom(u64),
@@ -39,6 +111,22 @@ impl Kind {
     pub fn as_u64(&self) -> u64 {
         (*self).into()
     }
+
+    /// Check if [`Kind`] is replaceable (only the latest event per author is kept)
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self, Self::Metadata | Self::ContactList)
+            || (10_000..20_000).contains(&self.as_u64())
+    }
+
+    /// Check if [`Kind`] is ephemeral (not stored by relays)
+    pub fn is_ephemeral(&self) -> bool {
+        (20_000..30_000).contains(&self.as_u64())
+    }
+
+    /// Check if [`Kind`] is parameterized replaceable (only the latest event per author and `d` tag is kept)
+    pub fn is_parameterized_replaceable(&self) -> bool {
+        (30_000..40_000).contains(&self.as_u64())
+    }
 }
 
 impl From<u64> for Kind {
@@ -52,12 +140,48 @@ impl From<u64> for Kind {
             5 => Self::EventDeletion,
             6 => Self::Repost,
             7 => Self::Reaction,
+            9 => Self::ChatMessage,
+            13 => Self::Seal,
+            14 => Self::PrivateDirectMessage,
             40 => Self::ChannelCreation,
             41 => Self::ChannelMetadata,
             42 => Self::ChannelMessage,
             43 => Self::ChannelHideMessage,
             44 => Self::ChannelMuteUser,
+            64 => Self::Chess,
+            1018 => Self::PollResponse,
+            1059 => Self::GiftWrap,
+            1068 => Self::Poll,
+            1063 => Self::FileMetadata,
+            1985 => Self::Label,
+            7375 => Self::CashuToken,
+            7376 => Self::CashuWalletHistory,
+            9000 => Self::GroupPutUser,
+            9001 => Self::GroupRemoveUser,
+            9002 => Self::GroupEditMetadata,
+            9005 => Self::GroupDeleteEvent,
+            9007 => Self::GroupCreate,
+            9008 => Self::GroupDelete,
+            9009 => Self::GroupCreateInvite,
+            9021 => Self::GroupJoinRequest,
+            9022 => Self::GroupLeaveRequest,
+            9321 => Self::Nutzap,
+            9802 => Self::Highlight,
+            9734 => Self::ZapRequest,
+            9735 => Self::ZapReceipt,
+            10002 => Self::RelayList,
+            10019 => Self::NutzapInfo,
+            17375 => Self::CashuWallet,
             22242 => Self::Authentication,
+            24133 => Self::NostrConnect,
+            24242 => Self::BlossomAuth,
+            27235 => Self::HttpAuth,
+            30166 => Self::RelayDiscovery,
+            34550 => Self::CommunityDefinition,
+            39000 => Self::GroupMetadata,
+            39001 => Self::GroupAdmins,
+            39002 => Self::GroupMembers,
+            39003 => Self::GroupRoles,
             x if (10_000..20_000).contains(&x) => Self::Replaceable(x as u16),
             x if (20_000..30_000).contains(&x) => Self::Ephemeral(x as u16),
             x if (30_000..40_000).contains(&x) => Self::ParameterizedReplaceable(x as u16),
@@ -77,12 +201,48 @@ impl From<Kind> for u64 {
             Kind::EventDeletion => 5,
             Kind::Repost => 6,
             Kind::Reaction => 7,
+            Kind::ChatMessage => 9,
+            Kind::Seal => 13,
+            Kind::PrivateDirectMessage => 14,
             Kind::ChannelCreation => 40,
             Kind::ChannelMetadata => 41,
             Kind::ChannelMessage => 42,
             Kind::ChannelHideMessage => 43,
             Kind::ChannelMuteUser => 44,
+            Kind::Chess => 64,
+            Kind::PollResponse => 1018,
+            Kind::GiftWrap => 1059,
+            Kind::Poll => 1068,
+            Kind::FileMetadata => 1063,
+            Kind::Label => 1985,
+            Kind::CashuToken => 7375,
+            Kind::CashuWalletHistory => 7376,
+            Kind::GroupPutUser => 9000,
+            Kind::GroupRemoveUser => 9001,
+            Kind::GroupEditMetadata => 9002,
+            Kind::GroupDeleteEvent => 9005,
+            Kind::GroupCreate => 9007,
+            Kind::GroupDelete => 9008,
+            Kind::GroupCreateInvite => 9009,
+            Kind::GroupJoinRequest => 9021,
+            Kind::GroupLeaveRequest => 9022,
+            Kind::Nutzap => 9321,
+            Kind::Highlight => 9802,
+            Kind::ZapRequest => 9734,
+            Kind::ZapReceipt => 9735,
+            Kind::RelayList => 10002,
+            Kind::NutzapInfo => 10019,
+            Kind::CashuWallet => 17375,
             Kind::Authentication => 22242,
+            Kind::NostrConnect => 24133,
+            Kind::BlossomAuth => 24242,
+            Kind::HttpAuth => 27235,
+            Kind::RelayDiscovery => 30166,
+            Kind::CommunityDefinition => 34550,
+            Kind::GroupMetadata => 39000,
+            Kind::GroupAdmins => 39001,
+            Kind::GroupMembers => 39002,
+            Kind::GroupRoles => 39003,
             Kind::Replaceable(u) => u as u64,
             Kind::Ephemeral(u) => u as u64,
             Kind::ParameterizedReplaceable(u) => u as u64,
@@ -6,20 +6,26 @@
 
 use std::str::FromStr;
 
-use bitcoin::secp256k1::schnorr::Signature;
-use bitcoin::secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use secp256k1::schnorr::Signature;
+use secp256k1::{Message, Secp256k1, XOnlyPublicKey};
 use serde::{Deserialize, Deserializer, Serialize};
 
 pub mod builder;
+pub mod codec;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compression;
 pub mod id;
 pub mod kind;
 pub mod tag;
+pub mod unsigned;
 
 pub use self::builder::EventBuilder;
+pub use self::codec::{CodecRegistry, ContentCodec, TypedEvent};
 pub use self::id::EventId;
 pub use self::kind::Kind;
 pub use self::tag::{Marker, Tag, TagKind};
-use crate::Timestamp;
+pub use self::unsigned::UnsignedEvent;
+use crate::{Coordinate, Timestamp};
 
 /// [`Event`] error
 #[derive(Debug, thiserror::Error)]
@@ -32,10 +38,10 @@ pub enum Error {
     Json(#[from] serde_json::Error),
     /// Secp256k1 error
     #[error(transparent)]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
     /// Hex decoding error
     #[error(transparent)]
-    Hex(#[from] bitcoin::hashes::hex::Error),
+    Hex(#[from] bitcoin_hashes::hex::Error),
 }
 
 /// [`Event`] struct
@@ -61,6 +67,13 @@ pub struct Event {
     pub ots: Option<String>,
 }
 
+/// NIP-10 thread references
+struct ThreadRefs {
+    root: Option<EventId>,
+    reply_to: Option<EventId>,
+    mentions: Vec<EventId>,
+}
+
 fn sig_string<'de, D>(deserializer: D) -> Result<Signature, D::Error>
 where
     D: Deserializer<'de>,
@@ -100,6 +113,142 @@ impl Event {
     pub fn as_json(&self) -> Result<String, Error> {
         Ok(serde_json::to_string(&self)?)
     }
+
+    /// Get expiration timestamp (NIP-40)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn expiration(&self) -> Option<Timestamp> {
+        self.tags.iter().find_map(|tag| match tag {
+            Tag::Expiration(timestamp) => Some(*timestamp),
+            _ => None,
+        })
+    }
+
+    /// Check if the event is expired at `timestamp` (NIP-40)
+    pub fn is_expired_at(&self, timestamp: Timestamp) -> bool {
+        self.expiration()
+            .map_or(false, |expiration| expiration <= timestamp)
+    }
+
+    /// Check if the event is expired (NIP-40)
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Timestamp::now())
+    }
+
+    /// Check if the event is a reply (NIP-10)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn is_reply(&self) -> bool {
+        self.reply_to_id().is_some()
+    }
+
+    /// Check if the event is the root of a thread (NIP-10)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn is_root(&self) -> bool {
+        !self.is_reply()
+    }
+
+    /// Get id of the thread root (NIP-10)
+    ///
+    /// Both marked (`root`, `reply`, `mention`) and deprecated positional `e` tags are supported.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn root_id(&self) -> Option<EventId> {
+        self.thread_refs().root
+    }
+
+    /// Get id of the replied event (NIP-10)
+    ///
+    /// For direct replies to the root, this is the root id.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn reply_to_id(&self) -> Option<EventId> {
+        self.thread_refs().reply_to
+    }
+
+    /// Get ids of the mentioned events: `e` tags that are neither root nor replied event (NIP-10)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn mentioned_event_ids(&self) -> Vec<EventId> {
+        self.thread_refs().mentions
+    }
+
+    /// Get public keys of `p` tags, without duplicates
+    pub fn mentioned_pubkeys(&self) -> Vec<XOnlyPublicKey> {
+        let mut pubkeys: Vec<XOnlyPublicKey> = Vec::new();
+        for tag in self.tags.iter() {
+            let tag: Vec<String> = tag.as_vec();
+            if tag.first().map(|k| k.as_str()) == Some("p") {
+                if let Some(pubkey) = tag.get(1).and_then(|pk| XOnlyPublicKey::from_str(pk).ok()) {
+                    if !pubkeys.contains(&pubkey) {
+                        pubkeys.push(pubkey);
+                    }
+                }
+            }
+        }
+        pubkeys
+    }
+
+    fn thread_refs(&self) -> ThreadRefs {
+        // `e` tags: `["e", <id>, <relay>, <marker>, <pubkey>]`
+        let e_tags: Vec<(EventId, Option<Marker>)> = self
+            .tags
+            .iter()
+            .filter_map(|tag| {
+                let tag: Vec<String> = tag.as_vec();
+                if tag.first().map(|k| k.as_str()) != Some("e") {
+                    return None;
+                }
+                let id = EventId::from_hex(tag.get(1)?).ok()?;
+                let marker = tag.get(3).filter(|m| !m.is_empty()).map(Marker::from);
+                Some((id, marker))
+            })
+            .collect();
+
+        let is_marked: bool = e_tags
+            .iter()
+            .any(|(_, marker)| matches!(marker, Some(Marker::Root | Marker::Reply)));
+
+        let (root, reply_to) = if is_marked {
+            let find = |m: Marker| {
+                e_tags
+                    .iter()
+                    .find(|(_, marker)| marker.as_ref() == Some(&m))
+                    .map(|(id, _)| *id)
+            };
+            let root: Option<EventId> = find(Marker::Root);
+            (root, find(Marker::Reply).or(root))
+        } else {
+            // Positional: first is root, last is the replied event, the others are mentions
+            let positional: Vec<EventId> = e_tags
+                .iter()
+                .filter(|(_, marker)| !matches!(marker, Some(Marker::Custom(m)) if m == "mention"))
+                .map(|(id, _)| *id)
+                .collect();
+            (positional.first().copied(), positional.last().copied())
+        };
+
+        let mut mentions: Vec<EventId> = Vec::new();
+        for (id, _) in e_tags.into_iter() {
+            if Some(id) != root && Some(id) != reply_to && !mentions.contains(&id) {
+                mentions.push(id);
+            }
+        }
+
+        ThreadRefs {
+            root,
+            reply_to,
+            mentions,
+        }
+    }
+
+    /// Get [`Coordinate`]s of `a` tags, with relay hints
+    ///
+    /// Invalid coordinates are skipped.
+    pub fn coordinates(&self) -> Vec<Coordinate> {
+        self.tags.iter().filter_map(Coordinate::from_tag).collect()
+    }
 }
 
 impl Event {
@@ -164,4 +313,137 @@ mod tests {
         assert_eq!(Kind::Custom(123), e.kind);
         assert_eq!(Kind::Custom(123), deserialized.kind);
     }
+
+    #[test]
+    fn test_expiration() {
+        let keys = Keys::generate();
+        let e: Event = EventBuilder::new_text_note(
+            "disappearing",
+            &[Tag::Expiration(Timestamp::from(1600000000))],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        assert_eq!(e.expiration(), Some(Timestamp::from(1600000000)));
+        assert!(!e.is_expired_at(Timestamp::from(1599999999)));
+        assert!(e.is_expired_at(Timestamp::from(1600000000)));
+        assert!(e.is_expired());
+
+        let e: Event = EventBuilder::new_text_note("forever", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(e.expiration().is_none());
+        assert!(!e.is_expired());
+    }
+
+    fn event_with_tags(tags: &str) -> Event {
+        let tags: Vec<Tag> = serde_json::from_str(tags).unwrap();
+        EventBuilder::new_text_note("", &tags)
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    fn id(hex: &str) -> Option<EventId> {
+        Some(EventId::from_hex(hex).unwrap())
+    }
+
+    const ROOT: &str = "f5fa8c1c17b41d8a3b8de6d2b3aef4bb3ba0ab1ff5bd4b5e5e8b6ad1f60e4d3c";
+    const PARENT: &str = "23c4b7e9b1b5ca0c9dc8f4fbb7b8c4b8e0e4f0f5a2c4b2a3a1b3b4b5b6b7b8b9";
+    const QUOTED: &str = "8f1d2c3b4a5968778695a4b3c2d1e0f1f2e3d4c5b6a798877665544332211000";
+    const ALICE: &str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+    const BOB: &str = "82341f882b6eabcd2ba7f1ef90aad961cf074af15b9ef44a09f9d2a8fbfbe6a2";
+
+    #[test]
+    fn test_nip10_marked() {
+        // Reply in a thread
+        let event = event_with_tags(&format!(
+            r#"[["e","{ROOT}","wss://relay.damus.io","root"],["e","{PARENT}","","reply"],["p","{ALICE}"],["p","{BOB}"]]"#
+        ));
+        assert!(event.is_reply());
+        assert!(!event.is_root());
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(PARENT));
+        assert!(event.mentioned_event_ids().is_empty());
+
+        // Direct reply to root: only the `root` marker
+        let event = event_with_tags(&format!(
+            r#"[["e","{ROOT}","wss://nos.lol","root"],["p","{ALICE}"]]"#
+        ));
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(ROOT));
+
+        // With pubkey (5th element), mention and reply before root
+        let event = event_with_tags(&format!(
+            r#"[["e","{PARENT}","","reply","{BOB}"],["e","{QUOTED}","","mention"],["e","{ROOT}","wss://nos.lol","root","{ALICE}"]]"#
+        ));
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(PARENT));
+        assert_eq!(
+            event.mentioned_event_ids(),
+            vec![EventId::from_hex(QUOTED).unwrap()]
+        );
+
+        // Root quoting another note
+        let event = event_with_tags(&format!(
+            r#"[["e","{QUOTED}","","mention"],["p","{ALICE}"]]"#
+        ));
+        assert!(event.is_root());
+        assert_eq!(event.root_id(), None);
+        assert_eq!(
+            event.mentioned_event_ids(),
+            vec![EventId::from_hex(QUOTED).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_nip10_positional() {
+        let event = event_with_tags("[]");
+        assert!(event.is_root());
+        assert_eq!(event.root_id(), None);
+        assert_eq!(event.reply_to_id(), None);
+
+        // One `e` tag: reply to root
+        let event = event_with_tags(&format!(r#"[["e","{ROOT}"],["p","{ALICE}"]]"#));
+        assert!(event.is_reply());
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(ROOT));
+
+        // Two `e` tags: root and replied event
+        let event = event_with_tags(&format!(r#"[["e","{ROOT}"],["e","{PARENT}",""]]"#));
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(PARENT));
+        assert!(event.mentioned_event_ids().is_empty());
+
+        // Many `e` tags: the ones in the middle are mentions
+        let event = event_with_tags(&format!(
+            r#"[["e","{ROOT}",""],["e","{QUOTED}",""],["e","{PARENT}",""]]"#
+        ));
+        assert_eq!(event.root_id(), id(ROOT));
+        assert_eq!(event.reply_to_id(), id(PARENT));
+        assert_eq!(
+            event.mentioned_event_ids(),
+            vec![EventId::from_hex(QUOTED).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mentioned_pubkeys() {
+        let event = event_with_tags(&format!(
+            r#"[["p","{ALICE}"],["p","{BOB}","wss://relay.damus.io","bob"],["p","{ALICE}","","mention"],["e","{ROOT}"]]"#
+        ));
+        let alice = XOnlyPublicKey::from_str(ALICE).unwrap();
+        let bob = XOnlyPublicKey::from_str(BOB).unwrap();
+        assert_eq!(event.mentioned_pubkeys(), vec![alice, bob]);
+    }
+
+    #[test]
+    fn test_vectors() {
+        use crate::test_vectors::nip01;
+
+        let event = Event::from_json(nip01::EVENT_JSON).unwrap();
+        assert!(event.verify().is_ok());
+        assert_eq!(event.id.to_hex(), nip01::EVENT_ID);
+        assert_eq!(event.pubkey.to_string(), nip01::EVENT_PUBKEY);
+        assert_eq!(event.as_json().unwrap(), nip01::EVENT_JSON);
+    }
 }
@@ -7,8 +7,8 @@ use std::fmt;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
-use bitcoin::secp256k1::schnorr::Signature;
-use bitcoin::secp256k1::XOnlyPublicKey;
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
 use serde::de::Error as DeserializerError;
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -31,10 +31,10 @@ pub enum Error {
     ParseIntError(#[from] ParseIntError),
     /// Secp256k1
     #[error(transparent)]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
     /// Hex decoding error
     #[error(transparent)]
-    Hex(#[from] bitcoin::hashes::hex::Error),
+    Hex(#[from] bitcoin_hashes::hex::Error),
     /// Url parse error
     #[error("invalid url")]
     Url(#[from] url::ParseError),
@@ -87,6 +87,8 @@ pub enum TagKind {
     E,
     /// Identifier
     D,
+    /// Coordinate of a parameterized replaceable event (NIP-33)
+    A,
     /// Relay
     Relay,
     /// Nonce
@@ -101,6 +103,18 @@ pub enum TagKind {
     Subject,
     /// Auth challenge
     Challenge,
+    /// Absolute URL (NIP-98)
+    U,
+    /// HTTP method (NIP-98)
+    Method,
+    /// Payload hash (NIP-98)
+    Payload,
+    /// Hashtag
+    T,
+    /// SHA256 hash of a file
+    X,
+    /// Group id (NIP-29)
+    H,
     /// Custom tag kind
     Custom(String),
 }
@@ -111,6 +125,7 @@ impl fmt::Display for TagKind {
             Self::P => write!(f, "p"),
             Self::E => write!(f, "e"),
             Self::D => write!(f, "d"),
+            Self::A => write!(f, "a"),
             Self::Relay => write!(f, "relay"),
             Self::Nonce => write!(f, "nonce"),
             Self::Delegation => write!(f, "delegation"),
@@ -118,6 +133,12 @@ impl fmt::Display for TagKind {
             Self::Expiration => write!(f, "expiration"),
             Self::Subject => write!(f, "subject"),
             Self::Challenge => write!(f, "challenge"),
+            Self::U => write!(f, "u"),
+            Self::Method => write!(f, "method"),
+            Self::Payload => write!(f, "payload"),
+            Self::T => write!(f, "t"),
+            Self::X => write!(f, "x"),
+            Self::H => write!(f, "h"),
             Self::Custom(tag) => write!(f, "{tag}"),
         }
     }
@@ -133,6 +154,7 @@ where
             "p" => Self::P,
             "e" => Self::E,
             "d" => Self::D,
+            "a" => Self::A,
             "relay" => Self::Relay,
             "nonce" => Self::Nonce,
             "delegation" => Self::Delegation,
@@ -140,6 +162,12 @@ where
             "expiration" => Self::Expiration,
             "subject" => Self::Subject,
             "challenge" => Self::Challenge,
+            "u" => Self::U,
+            "method" => Self::Method,
+            "payload" => Self::Payload,
+            "t" => Self::T,
+            "x" => Self::X,
+            "h" => Self::H,
             tag => Self::Custom(tag.to_string()),
         }
     }
@@ -267,7 +295,7 @@ where
 impl From<Tag> for Vec<String> {
     fn from(data: Tag) -> Self {
         match data {
-            Tag::Generic(kind, data) => vec![vec![kind.to_string()], data].concat(),
+            Tag::Generic(kind, data) => [vec![kind.to_string()], data].concat(),
             Tag::Event(id, relay_url, marker) => {
                 let mut tag = vec![TagKind::E.to_string(), id.to_hex()];
                 if let Some(relay_url) = relay_url {
@@ -0,0 +1,229 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Unsigned Event
+//!
+//! Serialized as the event without `sig`, the format of NIP-07 `signEvent` and NIP-46 `sign_event`.
+
+use secp256k1::schnorr::Signature;
+use secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+
+use super::{Event, EventId, Kind, Tag};
+use crate::key::{self, Keys};
+use crate::Timestamp;
+
+/// [`UnsignedEvent`] error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] super::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Public key not found in JSON
+    #[error("missing public key")]
+    MissingPublicKey,
+    /// Public key not matching the expected one
+    #[error("public key mismatch")]
+    PublicKeyMismatch,
+    /// Id not matching the content of the event
+    #[error("invalid id")]
+    InvalidId,
+}
+
+/// [`UnsignedEvent`] struct
+///
+/// Event with computed id, waiting for the signature of `pubkey`.
+/// When deserialized, a missing `id` is computed and missing `tags` are empty.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UnsignedEventIntermediate")]
+pub struct UnsignedEvent {
+    /// Id
+    pub id: EventId,
+    /// Author
+    pub pubkey: XOnlyPublicKey,
+    /// Timestamp (seconds)
+    pub created_at: Timestamp,
+    /// Kind
+    pub kind: Kind,
+    /// Vector of [`Tag`]
+    pub tags: Vec<Tag>,
+    /// Content
+    pub content: String,
+}
+
+/// Event without `sig`, as received from NIP-07 and NIP-46 clients
+#[derive(Deserialize)]
+struct UnsignedEventIntermediate {
+    id: Option<EventId>,
+    pubkey: Option<XOnlyPublicKey>,
+    created_at: Timestamp,
+    kind: Kind,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    content: String,
+}
+
+impl UnsignedEventIntermediate {
+    fn into_unsigned(self, public_key: Option<XOnlyPublicKey>) -> Result<UnsignedEvent, Error> {
+        let pubkey: XOnlyPublicKey = match (self.pubkey, public_key) {
+            (Some(pubkey), Some(public_key)) if pubkey != public_key => {
+                return Err(Error::PublicKeyMismatch)
+            }
+            (Some(pubkey), _) | (None, Some(pubkey)) => pubkey,
+            (None, None) => return Err(Error::MissingPublicKey),
+        };
+        let id: EventId = self.id.unwrap_or_else(|| {
+            EventId::new(
+                &pubkey,
+                self.created_at,
+                &self.kind,
+                &self.tags,
+                &self.content,
+            )
+        });
+        Ok(UnsignedEvent {
+            id,
+            pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+        })
+    }
+}
+
+impl TryFrom<UnsignedEventIntermediate> for UnsignedEvent {
+    type Error = Error;
+
+    fn try_from(intermediate: UnsignedEventIntermediate) -> Result<Self, Self::Error> {
+        intermediate.into_unsigned(None)
+    }
+}
+
+impl UnsignedEvent {
+    /// New unsigned event from json string
+    ///
+    /// The `id`, when present, must match the content of the event.
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let intermediate: UnsignedEventIntermediate = serde_json::from_str(json.as_ref())?;
+        let unsigned: Self = intermediate.into_unsigned(None)?;
+        unsigned.verify_id()?;
+        Ok(unsigned)
+    }
+
+    /// New unsigned event from json string, authored by `public_key`
+    ///
+    /// For payloads without `pubkey` (ex. NIP-07 `signEvent` or NIP-46 `sign_event` requests).
+    /// The `pubkey`, when present, must be `public_key` and the `id` must match the content of the event.
+    pub fn from_json_with_public_key<S>(json: S, public_key: XOnlyPublicKey) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let intermediate: UnsignedEventIntermediate = serde_json::from_str(json.as_ref())?;
+        let unsigned: Self = intermediate.into_unsigned(Some(public_key))?;
+        unsigned.verify_id()?;
+        Ok(unsigned)
+    }
+
+    /// Get unsigned event as json string
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(&self)?)
+    }
+
+    /// Verify that the `id` matches the content of the event
+    pub fn verify_id(&self) -> Result<(), Error> {
+        let id = EventId::new(
+            &self.pubkey,
+            self.created_at,
+            &self.kind,
+            &self.tags,
+            &self.content,
+        );
+        if id == self.id {
+            Ok(())
+        } else {
+            Err(Error::InvalidId)
+        }
+    }
+
+    /// Sign [`UnsignedEvent`] with [`Keys`]
+    pub fn sign(self, keys: &Keys) -> Result<Event, Error> {
+        let secp = Secp256k1::new();
+        let keypair = keys.key_pair()?;
+        let message = Message::from_slice(self.id.as_bytes())?;
+        let sig = secp.sign_schnorr(&message, &keypair);
+        self.add_signature(sig)
+    }
+
+    /// Add [`Signature`] produced elsewhere (ex. by an external signer)
+    ///
+    /// The signature is verified against `pubkey`.
+    pub fn add_signature(self, sig: Signature) -> Result<Event, Error> {
+        let event = Event {
+            id: self.id,
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            sig,
+            ots: None,
+        };
+        event.verify()?;
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    // Event signed by another implementation, without `sig`
+    const UNSIGNED_EVENT: &str = r#"{"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","created_at":1640839235,"kind":4,"tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA=="}"#;
+
+    #[test]
+    fn test_unsigned_event_json() {
+        let unsigned = UnsignedEvent::from_json(UNSIGNED_EVENT).unwrap();
+        assert_eq!(unsigned.as_json().unwrap(), UNSIGNED_EVENT);
+
+        // NIP-07 `signEvent` / NIP-46 `sign_event` payload: no id and no pubkey
+        let pubkey = XOnlyPublicKey::from_str(
+            "f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785",
+        )
+        .unwrap();
+        let template = r#"{"kind":4,"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"created_at":1640839235}"#;
+        assert!(matches!(
+            UnsignedEvent::from_json(template),
+            Err(Error::MissingPublicKey)
+        ));
+        assert_eq!(
+            UnsignedEvent::from_json_with_public_key(template, pubkey).unwrap(),
+            unsigned
+        );
+        assert!(matches!(
+            UnsignedEvent::from_json_with_public_key(UNSIGNED_EVENT, Keys::generate().public_key()),
+            Err(Error::PublicKeyMismatch)
+        ));
+
+        // Tampered content
+        let tampered = UNSIGNED_EVENT.replace("uRuvYr585B80", "xRuvYr585B80");
+        assert!(matches!(
+            UnsignedEvent::from_json(tampered),
+            Err(Error::InvalidId)
+        ));
+    }
+}
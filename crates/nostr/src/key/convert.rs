@@ -0,0 +1,219 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Key conversions
+//!
+//! Representations of nostr keys used by bitcoin tooling: compressed public keys (`02`/`03` prefix),
+//! BIP-340 x-only bytes and WIF secret keys (`bitcoin` feature).
+
+#[cfg(feature = "nip19")]
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "bitcoin")]
+use bitcoin::{Network, PrivateKey};
+use secp256k1::{Parity, PublicKey};
+
+use super::{Error, Keys, Secp256k1, XOnlyPublicKey};
+#[cfg(feature = "nip19")]
+use crate::nips::nip19::{FromBech32, ToBech32};
+
+impl Keys {
+    /// Init [`Keys`] from WIF secret key (mainnet or testnet, compressed or not)
+    #[cfg(feature = "bitcoin")]
+    pub fn from_wif(wif: &str) -> Result<Self, Error> {
+        let private_key = PrivateKey::from_wif(wif).map_err(|_| Error::InvalidSecretKey)?;
+        Ok(Self::new(private_key.inner))
+    }
+
+    /// Get secret key as compressed mainnet WIF
+    #[cfg(feature = "bitcoin")]
+    pub fn to_wif(&self) -> Result<String, Error> {
+        Ok(PrivateKey::new(self.secret_key()?, Network::Bitcoin).to_wif())
+    }
+
+    /// Init [`Keys`] from hex public key: compressed (`02`/`03` prefix, 33 bytes) or x-only (32 bytes)
+    ///
+    /// The parity of compressed keys is dropped: nostr uses x-only keys.
+    pub fn from_compressed_hex(public_key: &str) -> Result<Self, Error> {
+        let public_key: XOnlyPublicKey = match public_key.len() {
+            66 => PublicKey::from_str(public_key)?.x_only_public_key().0,
+            _ => XOnlyPublicKey::from_str(public_key)?,
+        };
+        Ok(Self::from_public_key(public_key))
+    }
+
+    /// Get compressed public key
+    ///
+    /// The parity is known only with the secret key: public keys alone are assumed to have even
+    /// `y` (`02` prefix), as BIP-340 does.
+    pub fn compressed_public_key(&self) -> PublicKey {
+        match self.key_pair {
+            Some(key_pair) => PublicKey::from_keypair(&key_pair),
+            None => self.public_key.public_key(Parity::Even),
+        }
+    }
+
+    /// Get compressed public key as hex (with `02`/`03` prefix)
+    pub fn compressed_hex(&self) -> String {
+        self.compressed_public_key().to_string()
+    }
+
+    /// Init [`Keys`] from BIP-340 x-only public key bytes
+    pub fn from_x_only_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::from_public_key(XOnlyPublicKey::from_slice(bytes)?))
+    }
+
+    /// Get BIP-340 x-only public key bytes
+    pub fn x_only_bytes(&self) -> [u8; 32] {
+        self.public_key.serialize()
+    }
+
+    /// Check that the secret key, if any, matches `public_key` (any representation)
+    pub fn matches(&self, public_key: &PublicKey) -> bool {
+        let secp = Secp256k1::new();
+        match self.secret_key {
+            Some(secret_key) => secret_key.public_key(&secp) == *public_key,
+            None => public_key.x_only_public_key().0 == self.public_key,
+        }
+    }
+}
+
+/// Display [`XOnlyPublicKey`] as `npub`
+///
+/// # Example
+/// ```rust
+/// use nostr::prelude::*;
+///
+/// let keys = Keys::generate();
+/// println!("Public key: {}", Npub(keys.public_key()));
+/// ```
+#[cfg(feature = "nip19")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Npub(pub XOnlyPublicKey);
+
+#[cfg(feature = "nip19")]
+impl From<XOnlyPublicKey> for Npub {
+    fn from(public_key: XOnlyPublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+#[cfg(feature = "nip19")]
+impl FromStr for Npub {
+    type Err = Error;
+
+    /// Parse `hex` or `bech32` public key
+    fn from_str(public_key: &str) -> Result<Self, Self::Err> {
+        XOnlyPublicKey::from_str(public_key)
+            .or_else(|_| XOnlyPublicKey::from_bech32(public_key))
+            .map(Self)
+            .map_err(|_| Error::InvalidPublicKey)
+    }
+}
+
+#[cfg(feature = "nip19")]
+impl TryFrom<&str> for Npub {
+    type Error = Error;
+
+    fn try_from(public_key: &str) -> Result<Self, Self::Error> {
+        Self::from_str(public_key)
+    }
+}
+
+#[cfg(feature = "nip19")]
+impl fmt::Display for Npub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let npub: String = self.0.to_bech32().map_err(|_| fmt::Error)?;
+        write!(f, "{npub}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secp256k1::SecretKey;
+
+    use super::*;
+
+    const SECRET_KEY: &str = "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e";
+
+    fn keys() -> Keys {
+        Keys::new(SecretKey::from_str(SECRET_KEY).unwrap())
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn test_wif() {
+        let keys = keys();
+        let wif: String = keys.to_wif().unwrap();
+        assert!(wif.starts_with('K') || wif.starts_with('L'));
+        assert_eq!(Keys::from_wif(&wif).unwrap(), keys);
+        assert_eq!(
+            Keys::from_wif("invalid").unwrap_err(),
+            Error::InvalidSecretKey
+        );
+        assert_eq!(
+            Keys::from_public_key(keys.public_key())
+                .to_wif()
+                .unwrap_err(),
+            Error::SkMissing
+        );
+    }
+
+    #[test]
+    fn test_compressed_public_key() {
+        let keys = keys();
+        let compressed: String = keys.compressed_hex();
+        assert_eq!(compressed.len(), 66);
+        assert_eq!(&compressed[2..], keys.public_key().to_string());
+
+        let public_only = Keys::from_compressed_hex(&compressed).unwrap();
+        assert_eq!(public_only.public_key(), keys.public_key());
+        assert!(keys.matches(&keys.compressed_public_key()));
+        assert!(public_only.matches(&keys.compressed_public_key()));
+
+        let x_only = Keys::from_compressed_hex(&compressed[2..]).unwrap();
+        assert_eq!(x_only.public_key(), keys.public_key());
+        assert!(x_only.compressed_hex().starts_with("02"));
+    }
+
+    #[test]
+    fn test_x_only_bytes() {
+        let keys = keys();
+        let bytes: [u8; 32] = keys.x_only_bytes();
+        let keys2 = Keys::from_x_only_bytes(&bytes).unwrap();
+        assert_eq!(keys2.public_key(), keys.public_key());
+        assert!(Keys::from_x_only_bytes(&bytes[1..]).is_err());
+    }
+
+    #[cfg(feature = "nip19")]
+    #[test]
+    fn test_npub_display() {
+        let keys = keys();
+        assert_eq!(
+            Npub(keys.public_key()).to_string(),
+            keys.public_key().to_bech32().unwrap()
+        );
+    }
+
+    #[cfg(feature = "nip19")]
+    #[test]
+    fn test_from_str() {
+        let keys = keys();
+        let npub = Npub(keys.public_key());
+        assert_eq!(Npub::from_str(&npub.to_string()).unwrap(), npub);
+        assert_eq!(
+            Npub::try_from(keys.public_key().to_string().as_str()).unwrap(),
+            npub
+        );
+        assert_eq!(
+            Npub::from_str("npub1invalid").unwrap_err(),
+            Error::InvalidPublicKey
+        );
+
+        assert_eq!(Keys::try_from(SECRET_KEY).unwrap(), keys);
+        let nsec: String = keys.secret_key().unwrap().to_bech32().unwrap();
+        assert_eq!(Keys::try_from(nsec.as_str()).unwrap(), keys);
+        assert!(Keys::try_from("invalid").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! OS credential store
+//!
+//! Keychain on macOS and iOS, Secret Service on Linux and Windows Credential Manager.
+
+use std::str::FromStr;
+
+use secp256k1::SecretKey;
+
+use super::Keys;
+
+/// [`Keys`] keyring error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Keyring error
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+    /// Keys error
+    #[error(transparent)]
+    Keys(#[from] super::Error),
+    /// Invalid secret key stored
+    #[error("Invalid secret key stored")]
+    InvalidSecretKey,
+}
+
+impl Keys {
+    /// Load [`Keys`] from the OS credential store
+    ///
+    /// Return [`keyring::Error::NoEntry`] if no secret key is stored for `service` and `account`.
+    pub fn load_from_keyring(service: &str, account: &str) -> Result<Self, Error> {
+        let entry = keyring::Entry::new(service, account)?;
+        let secret_key: String = entry.get_password()?;
+        let secret_key =
+            SecretKey::from_str(secret_key.trim()).map_err(|_| Error::InvalidSecretKey)?;
+        Ok(Self::new(secret_key))
+    }
+
+    /// Save the secret key in the OS credential store, replacing the one already stored for `service` and `account`
+    pub fn save_to_keyring(&self, service: &str, account: &str) -> Result<(), Error> {
+        let secret_key: SecretKey = self.secret_key()?;
+        let entry = keyring::Entry::new(service, account)?;
+        entry.set_password(&secret_key.display_secret().to_string())?;
+        Ok(())
+    }
+
+    /// Delete the secret key stored in the OS credential store for `service` and `account`
+    pub fn delete_from_keyring(service: &str, account: &str) -> Result<(), Error> {
+        let entry = keyring::Entry::new(service, account)?;
+        entry.delete_password()?;
+        Ok(())
+    }
+}
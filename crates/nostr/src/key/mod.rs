@@ -9,12 +9,17 @@
 #[cfg(feature = "nip19")]
 use std::str::FromStr;
 
-use bitcoin::secp256k1::rand::rngs::OsRng;
-pub use bitcoin::secp256k1::{KeyPair, Secp256k1, SecretKey, XOnlyPublicKey};
+use secp256k1::rand::rngs::OsRng;
+pub use secp256k1::{KeyPair, Secp256k1, SecretKey, XOnlyPublicKey};
 
+pub mod convert;
+#[cfg(feature = "keyring")]
+pub mod keyring;
 #[cfg(feature = "vanity")]
 pub mod vanity;
 
+#[cfg(feature = "nip19")]
+pub use self::convert::Npub;
 #[cfg(feature = "nip19")]
 use crate::nips::nip19::FromBech32;
 
@@ -41,7 +46,7 @@ pub enum Error {
     InvalidChar(char),
     /// Secp256k1 error
     #[error(transparent)]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
 }
 
 /// Trait for [`Keys`]
@@ -94,7 +99,7 @@ impl Keys {
     /// Generate new random keys
     pub fn generate() -> Self {
         let secp = Secp256k1::new();
-        let mut rng = OsRng::default();
+        let mut rng = OsRng;
         let (secret_key, _) = secp.generate_keypair(&mut rng);
         Self::new(secret_key)
     }
@@ -123,6 +128,18 @@ impl Keys {
     }
 }
 
+/// Zeroize secret material (ex. ECDH shared secrets)
+#[cfg(any(
+    feature = "nip04",
+    feature = "nip19",
+    feature = "nip44",
+    feature = "nip49"
+))]
+#[inline]
+pub(crate) fn wipe(bytes: &mut [u8]) {
+    zeroize::Zeroize::zeroize(bytes);
+}
+
 #[cfg(feature = "nip19")]
 impl FromSkStr for Keys {
     type Err = Error;
@@ -154,3 +171,13 @@ impl FromPkStr for Keys {
         }
     }
 }
+
+#[cfg(feature = "nip19")]
+impl TryFrom<&str> for Keys {
+    type Error = Error;
+
+    /// Init [`Keys`] from `hex` or `bech32` secret key
+    fn try_from(secret_key: &str) -> Result<Self, Self::Error> {
+        Self::from_sk_str(secret_key)
+    }
+}
@@ -1,34 +1,69 @@
 // Copyright (c) 2022-2023 Yuki Kishimoto
 // Distributed under the MIT software license
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 
 //! Rust implementation of the Nostr protocol.
+//!
+//! Without the `std` feature (`default-features = false, features = ["no_std"]`),
+//! only the [`raw`] module is available, to build and sign events with `core` and `alloc`.
+//!
+//! Keys, events and filters only need `secp256k1` and `bitcoin_hashes`: the `bitcoin` crate is
+//! pulled by the `bitcoin` feature (WIF keys) and by `nip06`, so relays and other lightweight
+//! consumers can use `default-features = false, features = ["base"]`.
 
-pub use bitcoin::hashes;
-pub use bitcoin::secp256k1;
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub use bech32;
+pub use bitcoin_hashes as hashes;
+pub use secp256k1;
 #[cfg(feature = "base")]
 pub use url::{self, Url};
 
+#[cfg(feature = "attachment")]
+pub mod attachment;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub mod binary;
+#[cfg(feature = "blossom")]
+pub mod blossom;
 #[cfg(feature = "default")]
 mod doctest;
 #[cfg(feature = "base")]
 pub mod event;
+#[cfg(feature = "std")]
 pub mod key;
 #[cfg(feature = "base")]
 pub mod message;
 pub mod nips;
 pub mod prelude;
+pub mod raw;
+#[cfg(feature = "base")]
+pub mod signer;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
 #[cfg(feature = "base")]
 pub mod types;
 
 #[cfg(feature = "base")]
-pub use self::event::{Event, EventBuilder, EventId, Kind, Tag};
+pub use self::event::{Event, EventBuilder, EventId, Kind, Tag, UnsignedEvent};
+#[cfg(feature = "std")]
 pub use self::key::Keys;
 #[cfg(feature = "base")]
-pub use self::message::{ClientMessage, RelayMessage, SubscriptionFilter, SubscriptionId};
+pub use self::message::{
+    ClientMessage, RelayMessage, SubscriptionFilter, SubscriptionId, SubscriptionIdGenerator,
+};
+#[cfg(all(feature = "base", feature = "secure-memory"))]
+pub use self::signer::SecureKeys;
+#[cfg(feature = "base")]
+pub use self::signer::{Approval, HardwareSigner, PolicySigner, Signer};
 #[cfg(feature = "base")]
-pub use self::types::{Contact, Entity, Metadata, Profile, Timestamp};
+pub use self::types::{
+    Backup, Contact, ContactList, Coordinate, Entity, ImageUrlPolicy, ImpersonationWarning,
+    Metadata, Profile, Timestamp, UncheckedUrl,
+};
 
 /// Result
+#[cfg(feature = "std")]
 pub type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
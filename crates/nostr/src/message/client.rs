@@ -110,7 +110,7 @@ impl ClientMessage {
                 let subscription_id: SubscriptionId = serde_json::from_value(v[1].clone())
                     .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
                 return Ok(Self::new_req(subscription_id, Vec::new()));
-            } else if v_len == 3 {
+            } else if v_len >= 3 {
                 let subscription_id: SubscriptionId = serde_json::from_value(v[1].clone())
                     .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
                 let filters: Vec<SubscriptionFilter> =
@@ -156,7 +156,7 @@ mod tests {
 
     use std::str::FromStr;
 
-    use bitcoin::secp256k1::XOnlyPublicKey;
+    use secp256k1::XOnlyPublicKey;
 
     use crate::Kind;
 
@@ -176,6 +176,10 @@ mod tests {
             client_req.as_json(),
             r##"["REQ","test",{"kinds":[4]},{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}]"##
         );
+        assert_eq!(
+            ClientMessage::from_json(client_req.as_json()).unwrap(),
+            client_req
+        );
     }
 
     #[test]
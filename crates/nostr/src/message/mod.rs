@@ -9,7 +9,9 @@ pub mod subscription;
 
 pub use self::client::ClientMessage;
 pub use self::relay::RelayMessage;
-pub use self::subscription::{SubscriptionFilter, SubscriptionId};
+pub use self::subscription::{
+    EventIdPrefix, SubscriptionFilter, SubscriptionId, SubscriptionIdGenerator,
+};
 
 /// Messages error
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
@@ -139,7 +139,8 @@ impl RelayMessage {
 
             let subscription_id: SubscriptionId = serde_json::from_value(v[1].clone())
                 .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
-            let event = Event::from_json(v[2].to_string())
+            // Signature is not verified here: it's up to the reader (ex. once per event, not per relay)
+            let event: Event = serde_json::from_value(v[2].clone())
                 .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
 
             return Ok(Self::new_event(subscription_id, event));
@@ -238,6 +239,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_handle_event_invalid_signature() {
+        // Same event of `test_handle_valid_event`, with another content: the signature is no longer valid
+        let msg = r#"["EVENT", "random_string", {"id":"70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5","pubkey":"379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","created_at":1612809991,"kind":1,"tags":[],"content":"forged","sig":"273a9cd5d11455590f4359500bccb7a89428262b96b3ea87a756b770964472f8c3e87f5d5e64d8d2e859a71462a3f477b554565c4f2f326cb01dd7620db71502"}]"#;
+
+        match RelayMessage::from_json(msg).unwrap() {
+            RelayMessage::Event { event, .. } => {
+                assert_eq!(event.content, "forged");
+                assert!(event.verify().is_err());
+            }
+            msg => panic!("Unexpected message: {:?}", msg),
+        }
+    }
+
     #[test]
     fn test_handle_invalid_event() {
         //Mising Event field
@@ -333,7 +348,7 @@ mod tests {
     #[test]
     fn parse_message() -> Result<()> {
         // Got this fresh off the wire
-        pub const SAMPLE_EVENT: &'static str = r#"["EVENT", "random_string", {"id":"70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5","pubkey":"379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","created_at":1612809991,"kind":1,"tags":[],"content":"test","sig":"273a9cd5d11455590f4359500bccb7a89428262b96b3ea87a756b770964472f8c3e87f5d5e64d8d2e859a71462a3f477b554565c4f2f326cb01dd7620db71502"}]"#;
+        pub const SAMPLE_EVENT: &str = r#"["EVENT", "random_string", {"id":"70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5","pubkey":"379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","created_at":1612809991,"kind":1,"tags":[],"content":"test","sig":"273a9cd5d11455590f4359500bccb7a89428262b96b3ea87a756b770964472f8c3e87f5d5e64d8d2e859a71462a3f477b554565c4f2f326cb01dd7620db71502"}]"#;
 
         // Hand parsed version as a sanity check
         let id = "70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5";
@@ -6,14 +6,19 @@
 
 #![allow(missing_docs)]
 
-use bitcoin::hashes::sha256::Hash as Sha256Hash;
-use bitcoin::hashes::Hash;
-use bitcoin::secp256k1::XOnlyPublicKey;
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::Hash;
+use secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
 
-use crate::{EventId, Kind, Timestamp};
+#[cfg(feature = "nip19")]
+use crate::nips::nip19::FromBech32;
+use crate::{Coordinate, Event, EventId, Kind, Timestamp};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SubscriptionId(String);
 
 impl SubscriptionId {
@@ -30,16 +35,188 @@ impl SubscriptionId {
     }
 }
 
-impl ToString for SubscriptionId {
-    fn to_string(&self) -> String {
-        self.0.clone()
+/// Max length of a [`SubscriptionId`] accepted by most relays
+const MAX_SUBSCRIPTION_ID_LEN: usize = 64;
+/// Length of the random suffix of [`SubscriptionIdGenerator::Prefix`]
+const PREFIX_SUFFIX_LEN: usize = 16;
+
+/// Strategy to generate [`SubscriptionId`]s
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SubscriptionIdGenerator {
+    /// Random UUID v4 (36 chars)
+    #[default]
+    Uuid,
+    /// Random hex string of the given length (1 to 64 chars)
+    Random(usize),
+    /// Hash of the filters (32 hex chars)
+    ///
+    /// The same filters always get the same id, so re-subscribing after a reconnect or a restart
+    /// replaces the previous subscription on the relay. Concurrent requests with the same filters share the id.
+    Deterministic,
+    /// Caller-provided prefix followed by 16 random hex chars
+    ///
+    /// The prefix is truncated to 48 bytes, so the id fits 64 chars and keeps the random suffix.
+    Prefix(String),
+}
+
+impl SubscriptionIdGenerator {
+    /// [`SubscriptionIdGenerator::Prefix`] with the prefix truncated to 48 bytes
+    pub fn prefix<S>(prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut prefix: String = prefix.into();
+        let len: usize = truncated_prefix(&prefix).len();
+        prefix.truncate(len);
+        Self::Prefix(prefix)
+    }
+
+    /// Generate [`SubscriptionId`] for `filters`
+    pub fn generate(&self, filters: &[SubscriptionFilter]) -> SubscriptionId {
+        match self {
+            Self::Uuid => SubscriptionId::generate(),
+            Self::Random(len) => {
+                SubscriptionId(random_hex((*len).clamp(1, MAX_SUBSCRIPTION_ID_LEN)))
+            }
+            Self::Deterministic => {
+                let json: String = serde_json::to_string(filters).unwrap_or_default();
+                let hash = Sha256Hash::hash(json.as_bytes()).to_string();
+                SubscriptionId(hash[..32].to_string())
+            }
+            Self::Prefix(prefix) => SubscriptionId(format!(
+                "{}{}",
+                truncated_prefix(prefix),
+                random_hex(PREFIX_SUFFIX_LEN)
+            )),
+        }
+    }
+}
+
+/// Longest start of `prefix` leaving room for the random suffix, on a char boundary
+fn truncated_prefix(prefix: &str) -> &str {
+    let mut len: usize = prefix
+        .len()
+        .min(MAX_SUBSCRIPTION_ID_LEN - PREFIX_SUFFIX_LEN);
+    while !prefix.is_char_boundary(len) {
+        len -= 1;
+    }
+    &prefix[..len]
+}
+
+fn random_hex(len: usize) -> String {
+    let mut hex = String::with_capacity(len + 1);
+    while hex.len() < len {
+        let byte: u8 = secp256k1::rand::random();
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex.truncate(len);
+    hex
+}
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// [`SubscriptionFilter`] error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid hex prefix
+    #[error("invalid hex prefix: {0}")]
+    InvalidHexPrefix(String),
+    /// Unknown query parameter
+    #[error("unknown query parameter: {0}")]
+    UnknownParameter(String),
+    /// Invalid query parameter value
+    #[error("invalid value for `{name}`: {value}")]
+    InvalidValue {
+        /// Parameter name
+        name: String,
+        /// Invalid value
+        value: String,
+    },
+}
+
+/// Hex prefix of [`EventId`]
+///
+/// Lowercase hex string of 1 to 64 chars. A full [`EventId`] is a valid prefix.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub struct EventIdPrefix(String);
+
+impl EventIdPrefix {
+    /// New [`EventIdPrefix`]
+    ///
+    /// Uppercase hex chars are converted to lowercase.
+    pub fn new<S>(prefix: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let prefix: String = prefix.into();
+        if prefix.is_empty() || prefix.len() > 64 || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(Error::InvalidHexPrefix(prefix));
+        }
+        Ok(Self(prefix.to_lowercase()))
+    }
+
+    /// Get as `&str`
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check if `id` starts with this prefix
+    pub fn matches(&self, id: &EventId) -> bool {
+        id.to_hex().starts_with(&self.0)
+    }
+
+    /// Check if this prefix starts with `other` (every id matched by this prefix is also matched by `other`)
+    pub fn starts_with(&self, other: &EventIdPrefix) -> bool {
+        self.0.starts_with(&other.0)
+    }
+}
+
+impl From<EventId> for EventIdPrefix {
+    fn from(id: EventId) -> Self {
+        Self(id.to_hex())
+    }
+}
+
+impl TryFrom<String> for EventIdPrefix {
+    type Error = Error;
+
+    fn try_from(prefix: String) -> Result<Self, Self::Error> {
+        Self::new(prefix)
+    }
+}
+
+impl TryFrom<&str> for EventIdPrefix {
+    type Error = Error;
+
+    fn try_from(prefix: &str) -> Result<Self, Self::Error> {
+        Self::new(prefix)
+    }
+}
+
+impl FromStr for EventIdPrefix {
+    type Err = Error;
+
+    fn from_str(prefix: &str) -> Result<Self, Self::Err> {
+        Self::new(prefix)
+    }
+}
+
+impl fmt::Display for EventIdPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SubscriptionFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ids: Option<Vec<String>>,
+    pub ids: Option<Vec<EventIdPrefix>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authors: Option<Vec<XOnlyPublicKey>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -56,6 +233,12 @@ pub struct SubscriptionFilter {
     #[serde(rename = "#r")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub references: Option<Vec<String>>,
+    #[serde(rename = "#a")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<Vec<Coordinate>>,
+    #[serde(rename = "#h")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub search: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -81,6 +264,8 @@ impl SubscriptionFilter {
             pubkeys: None,
             hashtags: None,
             references: None,
+            coordinates: None,
+            groups: None,
             search: None,
             since: None,
             until: None,
@@ -89,18 +274,25 @@ impl SubscriptionFilter {
         }
     }
 
-    /// Set subscription id
-    pub fn id(self, id: impl Into<String>) -> Self {
+    /// Set event id (or prefix)
+    pub fn id<T>(self, id: T) -> Self
+    where
+        T: Into<EventIdPrefix>,
+    {
         Self {
             ids: Some(vec![id.into()]),
             ..self
         }
     }
 
-    /// Set subscription ids
-    pub fn ids(self, ids: impl Into<Vec<String>>) -> Self {
+    /// Set event ids (or prefixes)
+    pub fn ids<I, T>(self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<EventIdPrefix>,
+    {
         Self {
-            ids: Some(ids.into()),
+            ids: Some(ids.into_iter().map(|id| id.into()).collect()),
             ..self
         }
     }
@@ -209,6 +401,58 @@ impl SubscriptionFilter {
         }
     }
 
+    /// Set group id (NIP-29 `h` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group<S>(self, group_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            groups: Some(vec![group_id.into()]),
+            ..self
+        }
+    }
+
+    /// Set group ids (NIP-29 `h` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn groups<I, S>(self, group_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            groups: Some(group_ids.into_iter().map(|id| id.into()).collect()),
+            ..self
+        }
+    }
+
+    /// Set coordinate of a (parameterized) replaceable event (relay hints are ignored)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn coordinate(self, coordinate: Coordinate) -> Self {
+        self.coordinates(vec![coordinate])
+    }
+
+    /// Set coordinates of (parameterized) replaceable events (relay hints are ignored)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn coordinates(self, coordinates: Vec<Coordinate>) -> Self {
+        Self {
+            coordinates: Some(
+                coordinates
+                    .into_iter()
+                    .map(|c| Coordinate {
+                        relays: Vec::new(),
+                        ..c
+                    })
+                    .collect(),
+            ),
+            ..self
+        }
+    }
+
     /// Set search field
     pub fn search<S>(self, value: S) -> Self
     where
@@ -244,3 +488,878 @@ impl SubscriptionFilter {
         }
     }
 }
+
+impl SubscriptionFilter {
+    /// Parse URL query string
+    ///
+    /// Supported parameters: `ids`, `authors`, `kinds`, `#e`, `#p`, `#t`, `#r`, `#a`, `#h` (also without `#`),
+    /// `search`, `since`, `until` and `limit`. List values are comma separated and repeated parameters
+    /// are appended. Public keys and event ids can be hex or bech32 (`npub`, `note`).
+    ///
+    /// Ex. `authors=npub1..&kinds=1,6&since=1672531200&limit=20`
+    pub fn from_query_str(query: &str) -> Result<Self, Error> {
+        let mut filter = Self::new();
+        let query: &str = query.strip_prefix('?').unwrap_or(query);
+        for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            let invalid = |v: &str| Error::InvalidValue {
+                name: name.to_string(),
+                value: v.to_string(),
+            };
+            let values = value.split(',').map(str::trim).filter(|v| !v.is_empty());
+            match name.trim_start_matches('#') {
+                "ids" => {
+                    for v in values {
+                        let prefix: EventIdPrefix = match parse_event_id(v) {
+                            Some(id) => EventIdPrefix::from(id),
+                            None => EventIdPrefix::new(v).map_err(|_| invalid(v))?,
+                        };
+                        filter.ids.get_or_insert_with(Vec::new).push(prefix);
+                    }
+                }
+                "authors" => {
+                    for v in values {
+                        let pk = parse_public_key(v).ok_or_else(|| invalid(v))?;
+                        filter.authors.get_or_insert_with(Vec::new).push(pk);
+                    }
+                }
+                "kinds" => {
+                    for v in values {
+                        let kind: u64 = v.parse().map_err(|_| invalid(v))?;
+                        filter
+                            .kinds
+                            .get_or_insert_with(Vec::new)
+                            .push(Kind::from(kind));
+                    }
+                }
+                "e" => {
+                    for v in values {
+                        let id = parse_event_id(v).ok_or_else(|| invalid(v))?;
+                        filter.events.get_or_insert_with(Vec::new).push(id);
+                    }
+                }
+                "p" => {
+                    for v in values {
+                        let pk = parse_public_key(v).ok_or_else(|| invalid(v))?;
+                        filter.pubkeys.get_or_insert_with(Vec::new).push(pk);
+                    }
+                }
+                "t" => filter
+                    .hashtags
+                    .get_or_insert_with(Vec::new)
+                    .extend(values.map(String::from)),
+                "r" => filter
+                    .references
+                    .get_or_insert_with(Vec::new)
+                    .extend(values.map(String::from)),
+                "h" => filter
+                    .groups
+                    .get_or_insert_with(Vec::new)
+                    .extend(values.map(String::from)),
+                "a" => {
+                    for v in values {
+                        let coordinate = Coordinate::from_str(v).map_err(|_| invalid(v))?;
+                        filter
+                            .coordinates
+                            .get_or_insert_with(Vec::new)
+                            .push(coordinate);
+                    }
+                }
+                "search" => filter.search = Some(value.to_string()),
+                "since" => {
+                    filter.since = Some(Timestamp::from_str(&value).map_err(|_| invalid(&value))?)
+                }
+                "until" => {
+                    filter.until = Some(Timestamp::from_str(&value).map_err(|_| invalid(&value))?)
+                }
+                "limit" => filter.limit = Some(value.parse().map_err(|_| invalid(&value))?),
+                _ => return Err(Error::UnknownParameter(name.to_string())),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Parse hex or bech32 (`npub`) public key
+fn parse_public_key(s: &str) -> Option<XOnlyPublicKey> {
+    #[cfg(feature = "nip19")]
+    if let Ok(public_key) = XOnlyPublicKey::from_bech32(s) {
+        return Some(public_key);
+    }
+    XOnlyPublicKey::from_str(s).ok()
+}
+
+/// Parse hex or bech32 (`note`) event id
+fn parse_event_id(s: &str) -> Option<EventId> {
+    #[cfg(feature = "nip19")]
+    if let Ok(id) = EventId::from_bech32(s) {
+        return Some(id);
+    }
+    EventId::from_hex(s).ok()
+}
+
+/// Set algebra
+///
+/// `limit` doesn't change which events match a filter, so it's not considered when comparing filters.
+/// Event `ids` are treated as prefixes.
+impl SubscriptionFilter {
+    /// Check if every event matched by this filter is also matched by `other`
+    pub fn is_subset_of(&self, other: &SubscriptionFilter) -> bool {
+        prefixes_subset(&self.ids, &other.ids)
+            && list_subset(&self.authors, &other.authors)
+            && list_subset(&self.kinds, &other.kinds)
+            && list_subset(&self.events, &other.events)
+            && list_subset(&self.pubkeys, &other.pubkeys)
+            && list_subset(&self.hashtags, &other.hashtags)
+            && list_subset(&self.references, &other.references)
+            && list_subset(&self.coordinates, &other.coordinates)
+            && list_subset(&self.groups, &other.groups)
+            && match (&self.search, &other.search) {
+                (_, None) => true,
+                (Some(a), Some(b)) => a == b,
+                (None, Some(_)) => false,
+            }
+            && match (self.since, other.since) {
+                (_, None) => true,
+                (Some(a), Some(b)) => a >= b,
+                (None, Some(_)) => false,
+            }
+            && match (self.until, other.until) {
+                (_, None) => true,
+                (Some(a), Some(b)) => a <= b,
+                (None, Some(_)) => false,
+            }
+    }
+
+    /// Merge with `other` into a single filter matching exactly the events matched by either of them
+    ///
+    /// The merged `limit` is the larger of the two, or `None` if either filter has no limit:
+    /// the merged filter may return more events than each filter alone, never less.
+    ///
+    /// Return `None` if the union can't be expressed as a single filter.
+    pub fn merge(&self, other: &SubscriptionFilter) -> Option<SubscriptionFilter> {
+        let limit = match (self.limit, other.limit) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+
+        if self.is_subset_of(other) {
+            return Some(Self {
+                limit,
+                ..other.clone()
+            });
+        }
+
+        if other.is_subset_of(self) {
+            return Some(Self {
+                limit,
+                ..self.clone()
+            });
+        }
+
+        if self.search != other.search {
+            return None;
+        }
+
+        // The union is a single filter only if the filters differ in exactly one constraint
+        let mut merged = Self {
+            limit,
+            ..self.clone()
+        };
+        let mut diffs: usize = 0;
+
+        if self.ids != other.ids {
+            diffs += 1;
+            merged.ids = list_union(&self.ids, &other.ids);
+        }
+        if self.authors != other.authors {
+            diffs += 1;
+            merged.authors = list_union(&self.authors, &other.authors);
+        }
+        if self.kinds != other.kinds {
+            diffs += 1;
+            merged.kinds = list_union(&self.kinds, &other.kinds);
+        }
+        if self.events != other.events {
+            diffs += 1;
+            merged.events = list_union(&self.events, &other.events);
+        }
+        if self.pubkeys != other.pubkeys {
+            diffs += 1;
+            merged.pubkeys = list_union(&self.pubkeys, &other.pubkeys);
+        }
+        if self.hashtags != other.hashtags {
+            diffs += 1;
+            merged.hashtags = list_union(&self.hashtags, &other.hashtags);
+        }
+        if self.references != other.references {
+            diffs += 1;
+            merged.references = list_union(&self.references, &other.references);
+        }
+        if self.coordinates != other.coordinates {
+            diffs += 1;
+            merged.coordinates = list_union(&self.coordinates, &other.coordinates);
+        }
+        if self.groups != other.groups {
+            diffs += 1;
+            merged.groups = list_union(&self.groups, &other.groups);
+        }
+        if self.since != other.since || self.until != other.until {
+            diffs += 1;
+            // Time ranges must overlap
+            if let (Some(since), Some(until)) = (self.since, other.until) {
+                if since > until {
+                    return None;
+                }
+            }
+            if let (Some(since), Some(until)) = (other.since, self.until) {
+                if since > until {
+                    return None;
+                }
+            }
+            merged.since = self.since.zip(other.since).map(|(a, b)| a.min(b));
+            merged.until = self.until.zip(other.until).map(|(a, b)| a.max(b));
+        }
+
+        if diffs == 1 {
+            Some(merged)
+        } else {
+            None
+        }
+    }
+
+    /// Intersect with `other` into a filter matching only the events matched by both of them
+    ///
+    /// Return `None` if no event can match both filters (or if the intersection can't be expressed as a single filter).
+    pub fn intersect(&self, other: &SubscriptionFilter) -> Option<SubscriptionFilter> {
+        let search = match (&self.search, &other.search) {
+            (Some(a), Some(b)) if a != b => return None,
+            (a, b) => a.clone().or_else(|| b.clone()),
+        };
+
+        let since = self.since.max(other.since);
+        let until = match (self.until, other.until) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if let (Some(since), Some(until)) = (since, until) {
+            if since > until {
+                return None;
+            }
+        }
+
+        Some(Self {
+            ids: prefixes_intersection(&self.ids, &other.ids)?,
+            authors: list_intersection(&self.authors, &other.authors)?,
+            kinds: list_intersection(&self.kinds, &other.kinds)?,
+            events: list_intersection(&self.events, &other.events)?,
+            pubkeys: list_intersection(&self.pubkeys, &other.pubkeys)?,
+            hashtags: list_intersection(&self.hashtags, &other.hashtags)?,
+            references: list_intersection(&self.references, &other.references)?,
+            coordinates: list_intersection(&self.coordinates, &other.coordinates)?,
+            groups: list_intersection(&self.groups, &other.groups)?,
+            search,
+            since,
+            until,
+            limit: match (self.limit, other.limit) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        })
+    }
+
+    /// Check if `event` is matched by this filter
+    ///
+    /// `limit` is ignored and `search` is matched as a case-insensitive substring of the content.
+    pub fn match_event(&self, event: &Event) -> bool {
+        let tags: Vec<Vec<String>> = event.tags.iter().map(|t| t.as_vec()).collect();
+        let has_tag = |kind: &str, values: &[String]| {
+            tags.iter().any(|t| {
+                t.first().map(|k| k.as_str()) == Some(kind)
+                    && t.get(1).map_or(false, |v| values.contains(v))
+            })
+        };
+
+        self.ids.as_ref().map_or(true, |ids| {
+            ids.iter().any(|prefix| prefix.matches(&event.id))
+        }) && self
+            .authors
+            .as_ref()
+            .map_or(true, |authors| authors.contains(&event.pubkey))
+            && self
+                .kinds
+                .as_ref()
+                .map_or(true, |kinds| kinds.contains(&event.kind))
+            && self.events.as_ref().map_or(true, |ids| {
+                let ids: Vec<String> = ids.iter().map(|id| id.to_hex()).collect();
+                has_tag("e", &ids)
+            })
+            && self.pubkeys.as_ref().map_or(true, |pubkeys| {
+                let pubkeys: Vec<String> = pubkeys.iter().map(|pk| pk.to_string()).collect();
+                has_tag("p", &pubkeys)
+            })
+            && self
+                .hashtags
+                .as_ref()
+                .map_or(true, |hashtags| has_tag("t", hashtags))
+            && self
+                .references
+                .as_ref()
+                .map_or(true, |references| has_tag("r", references))
+            && self.coordinates.as_ref().map_or(true, |coordinates| {
+                let coordinates: Vec<String> = coordinates.iter().map(|c| c.to_string()).collect();
+                has_tag("a", &coordinates)
+            })
+            && self
+                .groups
+                .as_ref()
+                .map_or(true, |groups| has_tag("h", groups))
+            && self.search.as_ref().map_or(true, |search| {
+                event
+                    .content
+                    .to_lowercase()
+                    .contains(&search.to_lowercase())
+            })
+            && self.since.map_or(true, |since| event.created_at >= since)
+            && self.until.map_or(true, |until| event.created_at <= until)
+    }
+
+    /// Human-readable description of the filter
+    ///
+    /// Ex. `kinds 1,6 from 120 authors since 2h ago, limit 500`. Empty lists, that match nothing,
+    /// are reported as `no <field>`.
+    pub fn explain(&self) -> String {
+        self.explain_at(Timestamp::now())
+    }
+
+    fn explain_at(&self, now: Timestamp) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        parts.push(match &self.kinds {
+            Some(kinds) => describe(
+                "kind",
+                "kinds",
+                kinds.iter().map(|k| k.as_u64().to_string()).collect(),
+            ),
+            None => String::from("events"),
+        });
+        if let Some(ids) = &self.ids {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            parts.push(format!("with {}", describe("id", "ids", ids)));
+        }
+        if let Some(authors) = &self.authors {
+            parts.push(format!(
+                "from {}",
+                count("author", "authors", authors.len())
+            ));
+        }
+        if let Some(events) = &self.events {
+            parts.push(format!(
+                "referencing {}",
+                count("event", "events", events.len())
+            ));
+        }
+        if let Some(pubkeys) = &self.pubkeys {
+            parts.push(format!(
+                "mentioning {}",
+                count("pubkey", "pubkeys", pubkeys.len())
+            ));
+        }
+        if let Some(hashtags) = &self.hashtags {
+            parts.push(format!(
+                "with {}",
+                describe("hashtag", "hashtags", hashtags.clone())
+            ));
+        }
+        if let Some(references) = &self.references {
+            parts.push(format!(
+                "with {}",
+                describe("reference", "references", references.clone())
+            ));
+        }
+        if let Some(coordinates) = &self.coordinates {
+            parts.push(format!(
+                "addressing {}",
+                count("coordinate", "coordinates", coordinates.len())
+            ));
+        }
+        if let Some(groups) = &self.groups {
+            parts.push(format!(
+                "in {}",
+                describe("group", "groups", groups.clone())
+            ));
+        }
+        if let Some(search) = &self.search {
+            parts.push(format!("matching {search:?}"));
+        }
+        if let Some(since) = self.since {
+            parts.push(format!("since {}", relative_time(since, now)));
+        }
+        if let Some(until) = self.until {
+            parts.push(format!("until {}", relative_time(until, now)));
+        }
+
+        let mut explanation: String = parts.join(" ");
+        if let Some(limit) = self.limit {
+            explanation.push_str(&format!(", limit {limit}"));
+        }
+        explanation
+    }
+}
+
+/// Max number of values listed by [`SubscriptionFilter::explain`], longer lists are counted
+const MAX_EXPLAINED_VALUES: usize = 5;
+
+fn describe(singular: &str, plural: &str, values: Vec<String>) -> String {
+    match values.len() {
+        0 => format!("no {plural}"),
+        1 => format!("{singular} {}", values[0]),
+        len if len <= MAX_EXPLAINED_VALUES => format!("{plural} {}", values.join(",")),
+        len => format!("{len} {plural}"),
+    }
+}
+
+fn count(singular: &str, plural: &str, len: usize) -> String {
+    match len {
+        0 => format!("no {plural}"),
+        1 => format!("1 {singular}"),
+        len => format!("{len} {plural}"),
+    }
+}
+
+/// Ex. `2h ago` or `in 5m`
+fn relative_time(timestamp: Timestamp, now: Timestamp) -> String {
+    let (secs, past) = if timestamp <= now {
+        (now.as_u64() - timestamp.as_u64(), true)
+    } else {
+        (timestamp.as_u64() - now.as_u64(), false)
+    };
+    let duration: String = match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    };
+    if past {
+        format!("{duration} ago")
+    } else {
+        format!("in {duration}")
+    }
+}
+
+/// `None` means unconstrained
+fn list_subset<T>(a: &Option<Vec<T>>, b: &Option<Vec<T>>) -> bool
+where
+    T: PartialEq,
+{
+    match (a, b) {
+        (_, None) => true,
+        (Some(a), Some(b)) => a.iter().all(|v| b.contains(v)),
+        (None, Some(_)) => false,
+    }
+}
+
+fn prefixes_subset(a: &Option<Vec<EventIdPrefix>>, b: &Option<Vec<EventIdPrefix>>) -> bool {
+    match (a, b) {
+        (_, None) => true,
+        (Some(a), Some(b)) => a.iter().all(|v| b.iter().any(|p| v.starts_with(p))),
+        (None, Some(_)) => false,
+    }
+}
+
+fn list_union<T>(a: &Option<Vec<T>>, b: &Option<Vec<T>>) -> Option<Vec<T>>
+where
+    T: Clone + PartialEq,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let mut union: Vec<T> = a.clone();
+            for v in b.iter() {
+                if !union.contains(v) {
+                    union.push(v.clone());
+                }
+            }
+            Some(union)
+        }
+        _ => None,
+    }
+}
+
+/// Return `None` if the intersection is empty
+fn list_intersection<T>(a: &Option<Vec<T>>, b: &Option<Vec<T>>) -> Option<Option<Vec<T>>>
+where
+    T: Clone + PartialEq,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let intersection: Vec<T> = a.iter().filter(|v| b.contains(v)).cloned().collect();
+            if intersection.is_empty() {
+                None
+            } else {
+                Some(Some(intersection))
+            }
+        }
+        (Some(a), None) => Some(Some(a.clone())),
+        (None, b) => Some(b.clone()),
+    }
+}
+
+/// Return `None` if the intersection is empty
+fn prefixes_intersection(
+    a: &Option<Vec<EventIdPrefix>>,
+    b: &Option<Vec<EventIdPrefix>>,
+) -> Option<Option<Vec<EventIdPrefix>>> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let mut intersection: Vec<EventIdPrefix> = Vec::new();
+            for x in a.iter() {
+                for y in b.iter() {
+                    // The longer prefix is the most specific
+                    let prefix = if x.starts_with(y) {
+                        x
+                    } else if y.starts_with(x) {
+                        y
+                    } else {
+                        continue;
+                    };
+                    if !intersection.contains(prefix) {
+                        intersection.push(prefix.clone());
+                    }
+                }
+            }
+            if intersection.is_empty() {
+                None
+            } else {
+                Some(Some(intersection))
+            }
+        }
+        (Some(a), None) => Some(Some(a.clone())),
+        (None, b) => Some(b.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn pubkey(n: u8) -> XOnlyPublicKey {
+        let keys = [
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+            "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
+        ];
+        XOnlyPublicKey::from_str(keys[n as usize]).unwrap()
+    }
+
+    fn prefix(prefix: &str) -> EventIdPrefix {
+        EventIdPrefix::new(prefix).unwrap()
+    }
+
+    #[test]
+    fn test_subscription_id_generator() {
+        let filters = vec![SubscriptionFilter::new().kind(Kind::TextNote).limit(10)];
+
+        let id = SubscriptionIdGenerator::default().generate(&filters);
+        assert_eq!(id.to_string().len(), 36);
+
+        let id = SubscriptionIdGenerator::Random(7).generate(&filters);
+        assert_eq!(id.to_string().len(), 7);
+        assert!(id.to_string().chars().all(|c| c.is_ascii_hexdigit()));
+        let id = SubscriptionIdGenerator::Random(100).generate(&filters);
+        assert_eq!(id.to_string().len(), 64);
+
+        let generator = SubscriptionIdGenerator::Deterministic;
+        let id = generator.generate(&filters);
+        assert_eq!(id.to_string().len(), 32);
+        assert_eq!(generator.generate(&filters), id);
+        assert_ne!(
+            generator.generate(&[SubscriptionFilter::new().kind(Kind::Metadata)]),
+            id
+        );
+
+        let id = SubscriptionIdGenerator::Prefix(String::from("feed-")).generate(&filters);
+        assert!(id.to_string().starts_with("feed-"));
+        assert_eq!(id.to_string().len(), 21);
+
+        // Long prefix truncated, the random suffix is kept
+        let generator = SubscriptionIdGenerator::Prefix("x".repeat(70));
+        let id = generator.generate(&filters);
+        assert_eq!(id.to_string().len(), 64);
+        assert!(id.to_string().starts_with(&"x".repeat(48)));
+        assert_ne!(generator.generate(&filters), id);
+        let generator = SubscriptionIdGenerator::prefix("é".repeat(40));
+        assert_eq!(generator, SubscriptionIdGenerator::Prefix("é".repeat(24)));
+        let id = generator.generate(&filters);
+        assert_eq!(id.to_string().len(), 64);
+        assert_ne!(generator.generate(&filters), id);
+    }
+
+    #[test]
+    fn test_event_id_prefix() {
+        assert_eq!(prefix("ABcd").as_str(), "abcd");
+        assert!(EventIdPrefix::new("").is_err());
+        assert!(EventIdPrefix::new("xyz").is_err());
+        assert!(EventIdPrefix::new("a".repeat(65)).is_err());
+
+        let id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        assert!(prefix("70b1").matches(&id));
+        assert!(!prefix("70b2").matches(&id));
+        assert!(EventIdPrefix::from(id).matches(&id));
+
+        let filter: Result<SubscriptionFilter, _> = serde_json::from_str(r#"{"ids":["zz"]}"#);
+        assert!(filter.is_err());
+        let filter: SubscriptionFilter = serde_json::from_str(r#"{"ids":["70b1"]}"#).unwrap();
+        assert_eq!(filter, SubscriptionFilter::new().id(prefix("70b1")));
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let wide = SubscriptionFilter::new().kind(Kind::TextNote);
+        let narrow = SubscriptionFilter::new()
+            .kind(Kind::TextNote)
+            .author(pubkey(0))
+            .since(Timestamp::from(10));
+        assert!(narrow.is_subset_of(&wide));
+        assert!(!wide.is_subset_of(&narrow));
+        assert!(wide.is_subset_of(&SubscriptionFilter::new()));
+
+        let filter = SubscriptionFilter::new().id(prefix("abc"));
+        assert!(SubscriptionFilter::new()
+            .id(prefix("abcdef"))
+            .is_subset_of(&filter));
+        assert!(!SubscriptionFilter::new()
+            .id(prefix("abd"))
+            .is_subset_of(&filter));
+    }
+
+    #[test]
+    fn test_match_event() {
+        use crate::event::TagKind;
+        use crate::{EventBuilder, Keys, Tag};
+
+        let keys = Keys::generate();
+        let event = EventBuilder::new_text_note(
+            "Hello Nostr",
+            &[
+                Tag::PubKey(pubkey(1), None),
+                Tag::Generic(TagKind::T, vec![String::from("nostr")]),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        assert!(SubscriptionFilter::new().match_event(&event));
+        assert!(SubscriptionFilter::new()
+            .kind(Kind::TextNote)
+            .author(keys.public_key())
+            .pubkey(pubkey(1))
+            .hashtag("nostr")
+            .search("hello")
+            .id(prefix(&event.id.to_hex()[..8]))
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .kind(Kind::Metadata)
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .pubkey(pubkey(0))
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .since(event.created_at + std::time::Duration::from_secs(10))
+            .match_event(&event));
+    }
+
+    #[test]
+    fn test_coordinate_filter() {
+        use crate::{EventBuilder, Keys};
+
+        let keys = Keys::generate();
+        let coordinate = Coordinate::new(Kind::from(30023), keys.public_key())
+            .identifier("article")
+            .relay("wss://relay.damus.io");
+        let event = EventBuilder::new_text_note("comment", &[coordinate.clone().into()])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.coordinates(), vec![coordinate.clone()]);
+
+        let filter = SubscriptionFilter::new().coordinate(coordinate.clone());
+        assert!(filter.match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .coordinate(coordinate.clone().identifier("other"))
+            .match_event(&event));
+
+        let json = format!(r##"{{"#a":["30023:{}:article"]}}"##, keys.public_key());
+        assert_eq!(serde_json::to_string(&filter).unwrap(), json);
+        assert_eq!(
+            serde_json::from_str::<SubscriptionFilter>(&json).unwrap(),
+            filter
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = SubscriptionFilter::new()
+            .kind(Kind::TextNote)
+            .author(pubkey(0));
+        let b = SubscriptionFilter::new()
+            .kind(Kind::TextNote)
+            .author(pubkey(1));
+        assert_eq!(
+            a.merge(&b),
+            Some(
+                SubscriptionFilter::new()
+                    .kind(Kind::TextNote)
+                    .authors(vec![pubkey(0), pubkey(1)])
+            )
+        );
+
+        // Differ in two constraints
+        let c = SubscriptionFilter::new()
+            .kind(Kind::Metadata)
+            .author(pubkey(1));
+        assert_eq!(a.merge(&c), None);
+
+        // Subset
+        let d = SubscriptionFilter::new().kind(Kind::TextNote);
+        assert_eq!(a.merge(&d), Some(d.clone()));
+
+        // Time ranges
+        let e = d
+            .clone()
+            .since(Timestamp::from(10))
+            .until(Timestamp::from(20));
+        let f = d
+            .clone()
+            .since(Timestamp::from(15))
+            .until(Timestamp::from(30));
+        let g = d.clone().since(Timestamp::from(40));
+        assert_eq!(
+            e.merge(&f),
+            Some(d.since(Timestamp::from(10)).until(Timestamp::from(30)))
+        );
+        assert_eq!(e.merge(&g), None);
+
+        // Limits: the larger one, unlimited if either side is unlimited
+        let h = a.clone().limit(10);
+        let i = b.clone().limit(20);
+        assert_eq!(h.merge(&i).unwrap().limit, Some(20));
+        assert_eq!(i.merge(&h).unwrap().limit, Some(20));
+        assert_eq!(h.merge(&b).unwrap().limit, None);
+        assert_eq!(a.merge(&i).unwrap().limit, None);
+        assert_eq!(h.merge(&a.clone().limit(5)).unwrap().limit, Some(10));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = SubscriptionFilter::new()
+            .kinds(vec![Kind::TextNote, Kind::Metadata])
+            .since(Timestamp::from(10));
+        let b = SubscriptionFilter::new()
+            .kind(Kind::TextNote)
+            .author(pubkey(0))
+            .until(Timestamp::from(20));
+        let intersection = a.intersect(&b).unwrap();
+        assert_eq!(
+            intersection,
+            SubscriptionFilter::new()
+                .kind(Kind::TextNote)
+                .author(pubkey(0))
+                .since(Timestamp::from(10))
+                .until(Timestamp::from(20))
+        );
+        assert!(intersection.is_subset_of(&a));
+        assert!(intersection.is_subset_of(&b));
+
+        let c = SubscriptionFilter::new()
+            .kind(Kind::Metadata)
+            .author(pubkey(1));
+        assert_eq!(b.intersect(&c), None);
+
+        let d = SubscriptionFilter::new().until(Timestamp::from(5));
+        assert_eq!(a.intersect(&d), None);
+
+        let prefixes = SubscriptionFilter::new().ids(vec![prefix("ab"), prefix("cd")]);
+        assert_eq!(
+            prefixes.intersect(&SubscriptionFilter::new().id(prefix("abcd"))),
+            Some(SubscriptionFilter::new().id(prefix("abcd")))
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let now = Timestamp::from(1_700_000_000);
+        let authors: Vec<XOnlyPublicKey> = (0..120).map(|n| pubkey(n % 2)).collect();
+        let filter = SubscriptionFilter::new()
+            .kinds(vec![Kind::TextNote, Kind::Repost])
+            .authors(authors)
+            .since(now - Duration::from_secs(2 * 3600))
+            .limit(500);
+        assert_eq!(
+            filter.explain_at(now),
+            "kinds 1,6 from 120 authors since 2h ago, limit 500"
+        );
+
+        let filter = SubscriptionFilter::new()
+            .author(pubkey(0))
+            .hashtags(vec![String::from("nostr"), String::from("rust")])
+            .search("hello")
+            .until(now + Duration::from_secs(90));
+        assert_eq!(
+            filter.explain_at(now),
+            "events from 1 author with hashtags nostr,rust matching \"hello\" until in 1m"
+        );
+
+        let filter = SubscriptionFilter::new()
+            .kinds(Vec::new())
+            .id(prefix("70b1"));
+        assert_eq!(filter.explain_at(now), "no kinds with id 70b1");
+        assert_eq!(SubscriptionFilter::new().explain_at(now), "events");
+    }
+
+    #[test]
+    fn test_from_query_str() {
+        let filter = SubscriptionFilter::from_query_str(&format!(
+            "?authors={},{}&kinds=1,6&%23t=nostr&since=10&limit=20&search=hello+world",
+            pubkey(0),
+            pubkey(1)
+        ))
+        .unwrap();
+        assert_eq!(
+            filter,
+            SubscriptionFilter::new()
+                .authors(vec![pubkey(0), pubkey(1)])
+                .kinds(vec![Kind::TextNote, Kind::Repost])
+                .hashtag("nostr")
+                .since(Timestamp::from(10))
+                .limit(20)
+                .search("hello world")
+        );
+
+        #[cfg(feature = "nip19")]
+        {
+            use crate::test_vectors::nip19;
+            let filter =
+                SubscriptionFilter::from_query_str(&format!("p={}&ids=ab", nip19::NPUB)).unwrap();
+            assert_eq!(
+                filter,
+                SubscriptionFilter::new()
+                    .pubkey(XOnlyPublicKey::from_str(nip19::PUBLIC_KEY).unwrap())
+                    .id(prefix("ab"))
+            );
+        }
+
+        assert_eq!(
+            SubscriptionFilter::from_query_str("kinds=1,x"),
+            Err(Error::InvalidValue {
+                name: String::from("kinds"),
+                value: String::from("x")
+            })
+        );
+        assert_eq!(
+            SubscriptionFilter::from_query_str("foo=bar"),
+            Err(Error::UnknownParameter(String::from("foo")))
+        );
+        assert!(SubscriptionFilter::from_query_str("authors=npub1xyz").is_err());
+    }
+}
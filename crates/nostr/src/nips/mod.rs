@@ -17,5 +17,33 @@ pub mod nip11;
 pub mod nip13;
 #[cfg(feature = "nip19")]
 pub mod nip19;
+#[cfg(feature = "nip21")]
+pub mod nip21;
 #[cfg(feature = "nip26")]
 pub mod nip26;
+#[cfg(feature = "nip27")]
+pub mod nip27;
+#[cfg(feature = "nip29")]
+pub mod nip29;
+#[cfg(feature = "nip32")]
+pub mod nip32;
+#[cfg(feature = "nip44")]
+pub mod nip44;
+#[cfg(feature = "nip46")]
+pub mod nip46;
+#[cfg(feature = "nip49")]
+pub mod nip49;
+#[cfg(feature = "nip59")]
+pub mod nip59;
+#[cfg(feature = "nip60")]
+pub mod nip60;
+#[cfg(feature = "nip61")]
+pub mod nip61;
+#[cfg(feature = "nip84")]
+pub mod nip84;
+#[cfg(feature = "nip88")]
+pub mod nip88;
+#[cfg(feature = "nip96")]
+pub mod nip96;
+#[cfg(feature = "nip98")]
+pub mod nip98;
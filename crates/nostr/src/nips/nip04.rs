@@ -13,8 +13,10 @@ use aes::cipher::block_padding::Pkcs7;
 use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use aes::Aes256;
 use base64::engine::{general_purpose, Engine};
-use bitcoin::secp256k1::{ecdh, PublicKey, SecretKey, XOnlyPublicKey};
 use cbc::{Decryptor, Encryptor};
+use secp256k1::{ecdh, PublicKey, SecretKey, XOnlyPublicKey};
+
+use crate::key;
 
 type Aes256CbcEnc = Encryptor<Aes256>;
 type Aes256CbcDec = Decryptor<Aes256>;
@@ -36,7 +38,7 @@ pub enum Error {
     WrongBlockMode,
     /// Secp256k1 error
     #[error("secp256k1 error: {0}")]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
 }
 
 /// Entrypt
@@ -44,10 +46,11 @@ pub fn encrypt<T>(sk: &SecretKey, pk: &XOnlyPublicKey, text: T) -> Result<String
 where
     T: AsRef<[u8]>,
 {
-    let key: Vec<u8> = generate_shared_key(sk, pk)?;
-    let iv: [u8; 16] = bitcoin::secp256k1::rand::random();
+    let mut key: [u8; 32] = generate_shared_key(sk, pk)?;
+    let iv: [u8; 16] = secp256k1::rand::random();
 
-    let cipher = Aes256CbcEnc::new(key.as_slice().into(), &iv.into());
+    let cipher = Aes256CbcEnc::new(&key.into(), &iv.into());
+    key::wipe(&mut key);
     let result: Vec<u8> = cipher.encrypt_padded_vec_mut::<Pkcs7>(text.as_ref());
 
     Ok(format!(
@@ -78,9 +81,10 @@ where
     let iv: Vec<u8> = general_purpose::STANDARD
         .decode(parsed_content[1])
         .map_err(|_| Error::Base64Decode)?;
-    let key: Vec<u8> = generate_shared_key(sk, pk)?;
+    let mut key: [u8; 32] = generate_shared_key(sk, pk)?;
 
-    let cipher = Aes256CbcDec::new(key.as_slice().into(), iv.as_slice().into());
+    let cipher = Aes256CbcDec::new(&key.into(), iv.as_slice().into());
+    key::wipe(&mut key);
     let result = cipher
         .decrypt_padded_vec_mut::<Pkcs7>(&encrypted_content)
         .map_err(|_| Error::WrongBlockMode)?;
@@ -88,13 +92,14 @@ where
     String::from_utf8(result).map_err(|_| Error::Utf8Encode)
 }
 
-fn generate_shared_key(sk: &SecretKey, pk: &XOnlyPublicKey) -> Result<Vec<u8>, Error> {
+fn generate_shared_key(sk: &SecretKey, pk: &XOnlyPublicKey) -> Result<[u8; 32], Error> {
     let pk_normalized: PublicKey = from_schnorr_pk(pk)?;
-    let ssp = ecdh::shared_secret_point(&pk_normalized, sk);
+    let mut ssp = ecdh::shared_secret_point(&pk_normalized, sk);
 
     let mut shared_key = [0u8; 32];
     shared_key.copy_from_slice(&ssp[..32]);
-    Ok(shared_key.to_vec())
+    key::wipe(&mut ssp);
+    Ok(shared_key)
 }
 
 fn from_schnorr_pk(schnorr_pk: &XOnlyPublicKey) -> Result<PublicKey, Error> {
@@ -107,7 +112,7 @@ fn from_schnorr_pk(schnorr_pk: &XOnlyPublicKey) -> Result<PublicKey, Error> {
 mod tests {
     use super::*;
 
-    use bitcoin::secp256k1::{KeyPair, Secp256k1};
+    use secp256k1::{KeyPair, Secp256k1};
 
     use crate::Result;
 
@@ -166,4 +171,60 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_vectors() -> Result<()> {
+        use crate::test_vectors::nip04;
+
+        let secp = Secp256k1::new();
+        let sender_sk = SecretKey::from_str(nip04::SENDER_SECRET_KEY)?;
+        let sender_pk =
+            XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(&secp, &sender_sk)).0;
+        let receiver_sk = SecretKey::from_str(nip04::RECEIVER_SECRET_KEY)?;
+        let receiver_pk =
+            XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(&secp, &receiver_sk)).0;
+
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, nip04::CIPHERTEXT)?,
+            nip04::PLAINTEXT
+        );
+        assert_eq!(
+            decrypt(&sender_sk, &receiver_pk, nip04::CIPHERTEXT)?,
+            nip04::PLAINTEXT
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "secure-memory")]
+    #[test]
+    fn test_wipe_shared_key() -> Result<()> {
+        use crate::test_vectors::nip04;
+
+        let secp = Secp256k1::new();
+        let sender_sk = SecretKey::from_str(nip04::SENDER_SECRET_KEY)?;
+        let sender_pk =
+            XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(&secp, &sender_sk)).0;
+        let receiver_sk = SecretKey::from_str(nip04::RECEIVER_SECRET_KEY)?;
+        let receiver_pk =
+            XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(&secp, &receiver_sk)).0;
+
+        let mut shared_key: [u8; 32] = generate_shared_key(&sender_sk, &receiver_pk)?;
+        assert_eq!(shared_key, generate_shared_key(&receiver_sk, &sender_pk)?);
+        assert_ne!(shared_key, [0u8; 32]);
+        key::wipe(&mut shared_key);
+        assert_eq!(shared_key, [0u8; 32]);
+
+        // The shared key is wiped only after the cipher is initialized
+        let encrypted: String = encrypt(&sender_sk, &receiver_pk, nip04::PLAINTEXT)?;
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, encrypted)?,
+            nip04::PLAINTEXT
+        );
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, nip04::CIPHERTEXT)?,
+            nip04::PLAINTEXT
+        );
+        assert!(decrypt(&receiver_sk, &receiver_pk, nip04::CIPHERTEXT).is_err());
+        Ok(())
+    }
 }
@@ -8,9 +8,12 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
 
-use bitcoin::secp256k1::XOnlyPublicKey;
 use reqwest::Proxy;
+use secp256k1::XOnlyPublicKey;
 use serde_json::Value;
+use url::Url;
+
+use crate::types::relay_url;
 
 /// `NIP05` error
 #[derive(Debug, thiserror::Error)]
@@ -29,7 +32,19 @@ pub enum Error {
     Json(#[from] serde_json::Error),
     /// Secp256k1 error
     #[error(transparent)]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
+    /// Name not found
+    #[error("name not found")]
+    NotFound,
+}
+
+/// Profile resolved from a NIP05 identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nip05Profile {
+    /// Public key
+    pub public_key: XOnlyPublicKey,
+    /// Relays where the user is likely to be found
+    pub relays: Vec<Url>,
 }
 
 fn compose_url(nip05: &str) -> Result<(String, &str), Error> {
@@ -57,6 +72,27 @@ fn verify_json(public_key: XOnlyPublicKey, json: Value, name: &str) -> Result<()
     Err(Error::ImpossibleToVerify)
 }
 
+fn profile_from_json(json: Value, name: &str) -> Result<Nip05Profile, Error> {
+    let public_key: &str = json
+        .get("names")
+        .and_then(|names| names.get(name))
+        .and_then(|value| value.as_str())
+        .ok_or(Error::NotFound)?;
+    let public_key = XOnlyPublicKey::from_str(public_key)?;
+    let relays: Vec<Url> = json
+        .get("relays")
+        .and_then(|relays| relays.get(public_key.to_string()))
+        .and_then(|value| value.as_array())
+        .map(|relays| {
+            relays
+                .iter()
+                .filter_map(|url| relay_url::parse(url.as_str()?).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Nip05Profile { public_key, relays })
+}
+
 /// Verify NIP05
 pub async fn verify(
     public_key: XOnlyPublicKey,
@@ -65,18 +101,53 @@ pub async fn verify(
 ) -> Result<(), Error> {
     use reqwest::Client;
 
-    let (url, name) = compose_url(nip05)?;
     let mut builder = Client::builder();
     if let Some(proxy) = proxy {
         let proxy = format!("socks5h://{proxy}");
         builder = builder.proxy(Proxy::all(proxy)?);
     }
     let client: Client = builder.build()?;
+    verify_with_client(&client, public_key, nip05).await
+}
+
+/// Verify NIP05 using a custom HTTP client (ex. with DNS-over-HTTPS or a proxy)
+pub async fn verify_with_client(
+    client: &reqwest::Client,
+    public_key: XOnlyPublicKey,
+    nip05: &str,
+) -> Result<(), Error> {
+    let (url, name) = compose_url(nip05)?;
     let res = client.get(url).send().await?;
     let json: Value = serde_json::from_str(&res.text().await?)?;
     verify_json(public_key, json, name)
 }
 
+/// Get [`Nip05Profile`] of NIP05 identifier (ex. `name@domain.com`)
+///
+/// Invalid relay URLs are skipped.
+pub async fn get_profile(nip05: &str, proxy: Option<SocketAddr>) -> Result<Nip05Profile, Error> {
+    use reqwest::Client;
+
+    let mut builder = Client::builder();
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{proxy}");
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    let client: Client = builder.build()?;
+    get_profile_with_client(&client, nip05).await
+}
+
+/// Get [`Nip05Profile`] using a custom HTTP client (ex. with DNS-over-HTTPS or a proxy)
+pub async fn get_profile_with_client(
+    client: &reqwest::Client,
+    nip05: &str,
+) -> Result<Nip05Profile, Error> {
+    let (url, name) = compose_url(nip05)?;
+    let res = client.get(url).send().await?;
+    let json: Value = serde_json::from_str(&res.text().await?)?;
+    profile_from_json(json, name)
+}
+
 /// Verify NIP05
 #[cfg(feature = "blocking")]
 pub fn verify_blocking(
@@ -97,3 +168,31 @@ pub fn verify_blocking(
     let json: Value = serde_json::from_str(&res.text()?)?;
     verify_json(public_key, json, name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_from_json() {
+        let public_key = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+        let json: Value = serde_json::from_str(&format!(
+            r#"{{"names":{{"bob":"{public_key}"}},"relays":{{"{public_key}":["wss://relay.example.com/","invalid"]}}}}"#
+        ))
+        .unwrap();
+        let profile = profile_from_json(json.clone(), "bob").unwrap();
+        assert_eq!(
+            profile.public_key,
+            XOnlyPublicKey::from_str(public_key).unwrap()
+        );
+        assert_eq!(
+            profile.relays,
+            vec![Url::parse("wss://relay.example.com").unwrap()]
+        );
+        assert!(verify_json(profile.public_key, json.clone(), "bob").is_ok());
+        assert!(matches!(
+            profile_from_json(json, "alice"),
+            Err(Error::NotFound)
+        ));
+    }
+}
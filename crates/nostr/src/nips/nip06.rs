@@ -3,18 +3,22 @@
 
 //! NIP06
 //!
+//! Keys derived from BIP-39 mnemonics with the BIP-32 path `m/44'/1237'/<account>'/0/0`.
+//!
 //! https://github.com/nostr-protocol/nips/blob/master/06.md
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
 use bip39::Mnemonic;
-use bitcoin::hashes::hmac::{Hmac, HmacEngine};
-use bitcoin::hashes::{sha512, Hash, HashEngine};
-use bitcoin::secp256k1::rand::rngs::OsRng;
-use bitcoin::secp256k1::rand::RngCore;
-use bitcoin::secp256k1::Secp256k1;
-use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
 use bitcoin::Network;
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
+use bitcoin_hashes::{sha512, Hash, HashEngine};
+use secp256k1::rand::rngs::OsRng;
+use secp256k1::rand::RngCore;
+use secp256k1::{Secp256k1, XOnlyPublicKey};
 
 use crate::key::Keys;
 
@@ -29,10 +33,27 @@ pub enum Error {
     BIP39(#[from] bip39::Error),
 }
 
+/// BIP-44 coin type of nostr (SLIP-44)
+pub const COIN_TYPE: u32 = 1237;
+
 #[allow(missing_docs)]
 pub trait FromMnemonic: Sized {
     type Err;
+
+    /// Derive from BIP-39 mnemonic, first account (`m/44'/1237'/0'/0/0`)
     fn from_mnemonic<S>(mnemonic: S, passphrase: Option<S>) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        Self::from_mnemonic_with_account(mnemonic, passphrase, 0)
+    }
+
+    /// Derive from BIP-39 mnemonic, `account` (`m/44'/1237'/<account>'/0/0`)
+    fn from_mnemonic_with_account<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        account: u32,
+    ) -> Result<Self, Self::Err>
     where
         S: Into<String>;
 }
@@ -47,17 +68,160 @@ impl FromMnemonic for Keys {
     type Err = Error;
 
     /// Derive keys from BIP-39 mnemonics (ENGLISH wordlist).
-    fn from_mnemonic<S>(mnemonic: S, passphrase: Option<S>) -> Result<Self, Self::Err>
+    fn from_mnemonic_with_account<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        account: u32,
+    ) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let root_key = root_key(mnemonic, passphrase)?;
+        derive(&root_key, account)
+    }
+}
+
+fn root_key<S>(mnemonic: S, passphrase: Option<S>) -> Result<ExtendedPrivKey, Error>
+where
+    S: Into<String>,
+{
+    let mnemonic = Mnemonic::from_str(&mnemonic.into())?;
+    let seed = mnemonic.to_seed(passphrase.map(|p| p.into()).unwrap_or_default());
+    Ok(ExtendedPrivKey::new_master(Network::Bitcoin, &seed)?)
+}
+
+/// Derive the keys of `account` (`m/44'/1237'/<account>'/0/0`)
+fn derive(root_key: &ExtendedPrivKey, account: u32) -> Result<Keys, Error> {
+    let path = DerivationPath::from(vec![
+        ChildNumber::from_hardened_idx(44)?,
+        ChildNumber::from_hardened_idx(COIN_TYPE)?,
+        ChildNumber::from_hardened_idx(account)?,
+        ChildNumber::from_normal_idx(0)?,
+        ChildNumber::from_normal_idx(0)?,
+    ]);
+    let secp = Secp256k1::new();
+    let child_xprv = root_key.derive_priv(&secp, &path)?;
+    Ok(Keys::new(child_xprv.private_key))
+}
+
+impl Keys {
+    /// Derive keys of `account` from BIP-39 mnemonic, without passphrase
+    ///
+    /// Use [`AccountManager`] to derive many accounts: the seed is computed once.
+    pub fn derive_account<S>(mnemonic: S, account: u32) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Self::from_mnemonic_with_account(mnemonic, None, account)
+    }
+}
+
+/// Account derived by [`AccountManager`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    /// Account index
+    pub index: u32,
+    /// Label
+    pub label: Option<String>,
+    /// Keys
+    pub keys: Keys,
+}
+
+/// Many identities (accounts) of a single BIP-39 seed
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr::prelude::*;
+///
+/// # fn main() -> Result<()> {
+/// let mnemonic = "leader monkey parrot ring guide accident before fence cannon height naive bean";
+/// let mut manager = AccountManager::new(mnemonic, None)?;
+/// manager.set_label(1, "work");
+/// for account in manager.accounts(3)?.into_iter() {
+///     println!("{}: {:?}", account.index, account.label);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AccountManager {
+    root_key: ExtendedPrivKey,
+    labels: BTreeMap<u32, String>,
+}
+
+impl fmt::Debug for AccountManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccountManager")
+            .field("labels", &self.labels)
+            .finish()
+    }
+}
+
+impl AccountManager {
+    /// New [`AccountManager`] from BIP-39 mnemonic (ENGLISH wordlist)
+    pub fn new<S>(mnemonic: S, passphrase: Option<S>) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self {
+            root_key: root_key(mnemonic, passphrase)?,
+            labels: BTreeMap::new(),
+        })
+    }
+
+    /// Derive keys of `account`
+    pub fn derive(&self, account: u32) -> Result<Keys, Error> {
+        derive(&self.root_key, account)
+    }
+
+    /// Derive [`Account`] at `index`, with its label
+    pub fn account(&self, index: u32) -> Result<Account, Error> {
+        Ok(Account {
+            index,
+            label: self.labels.get(&index).cloned(),
+            keys: self.derive(index)?,
+        })
+    }
+
+    /// Derive the first `count` accounts
+    pub fn accounts(&self, count: u32) -> Result<Vec<Account>, Error> {
+        (0..count).map(|index| self.account(index)).collect()
+    }
+
+    /// Derive the labeled accounts, ordered by index
+    pub fn labeled_accounts(&self) -> Result<Vec<Account>, Error> {
+        self.labels
+            .keys()
+            .map(|index| self.account(*index))
+            .collect()
+    }
+
+    /// Set label of `account`
+    pub fn set_label<S>(&mut self, account: u32, label: S)
     where
         S: Into<String>,
     {
-        let mnemonic = Mnemonic::from_str(&mnemonic.into())?;
-        let seed = mnemonic.to_seed(passphrase.map(|p| p.into()).unwrap_or_default());
-        let root_key = ExtendedPrivKey::new_master(Network::Bitcoin, &seed)?;
-        let path = DerivationPath::from_str("m/44'/1237'/0'/0/0")?;
-        let secp = Secp256k1::new();
-        let child_xprv = root_key.derive_priv(&secp, &path)?;
-        Ok(Self::new(child_xprv.private_key))
+        self.labels.insert(account, label.into());
+    }
+
+    /// Get label of `account`
+    pub fn label(&self, account: u32) -> Option<&str> {
+        self.labels.get(&account).map(|l| l.as_str())
+    }
+
+    /// Remove label of `account`
+    pub fn remove_label(&mut self, account: u32) -> Option<String> {
+        self.labels.remove(&account)
+    }
+
+    /// Find the account of `public_key` among the first `count` accounts
+    pub fn find(&self, public_key: &XOnlyPublicKey, count: u32) -> Result<Option<u32>, Error> {
+        for index in 0..count {
+            if self.derive(index)?.public_key() == *public_key {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
     }
 }
 
@@ -78,7 +242,7 @@ impl GenerateMnemonic for Keys {
 
 #[cfg(test)]
 mod tests {
-    use bitcoin::secp256k1::SecretKey;
+    use secp256k1::SecretKey;
 
     use super::*;
 
@@ -98,4 +262,84 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_nip06_spec_vectors() -> Result<()> {
+        let vectors = [
+            (
+                "leader monkey parrot ring guide accident before fence cannon height naive bean",
+                "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a",
+            ),
+            (
+                "what bleak badge arrange retreat wolf trade produce cricket blur garlic valid proud rude strong choose busy staff weather area salt hollow arm fade",
+                "c15d739894c81a2fcfd3a2df85a0d2c0dbc47a280d092799f144d73d7ae78add",
+            ),
+        ];
+        for (mnemonic, secret_key) in vectors.into_iter() {
+            let keys = Keys::from_mnemonic(mnemonic, None)?;
+            assert_eq!(keys.secret_key()?, SecretKey::from_str(secret_key)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_nip06_accounts() -> Result<()> {
+        let mnemonic: &str =
+            "leader monkey parrot ring guide accident before fence cannon height naive bean";
+        let first = Keys::from_mnemonic_with_account(mnemonic, None, 0)?;
+        assert_eq!(first, Keys::from_mnemonic(mnemonic, None)?);
+
+        let second = Keys::from_mnemonic_with_account(mnemonic, None, 1)?;
+        assert_ne!(second.public_key(), first.public_key());
+        assert_ne!(
+            Keys::from_mnemonic_with_account(mnemonic, Some("passphrase"), 1)?.public_key(),
+            second.public_key()
+        );
+
+        // Hardened index out of range
+        assert!(Keys::from_mnemonic_with_account(mnemonic, None, 1 << 31).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_account_manager() -> Result<()> {
+        let mnemonic: &str =
+            "leader monkey parrot ring guide accident before fence cannon height naive bean";
+        let mut manager = AccountManager::new(mnemonic, None)?;
+        assert_eq!(manager.derive(2)?, Keys::derive_account(mnemonic, 2)?);
+
+        manager.set_label(1, "work");
+        manager.set_label(5, "anon");
+        assert_eq!(manager.label(1), Some("work"));
+
+        let accounts: Vec<Account> = manager.accounts(3)?;
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(accounts[0].keys, Keys::from_mnemonic(mnemonic, None)?);
+        assert_eq!(accounts[0].label, None);
+        assert_eq!(accounts[1].label.as_deref(), Some("work"));
+
+        let labeled: Vec<u32> = manager
+            .labeled_accounts()?
+            .into_iter()
+            .map(|a| a.index)
+            .collect();
+        assert_eq!(labeled, vec![1, 5]);
+
+        let public_key = manager.derive(5)?.public_key();
+        assert_eq!(manager.find(&public_key, 10)?, Some(5));
+        assert_eq!(manager.find(&public_key, 5)?, None);
+
+        assert_eq!(manager.remove_label(5), Some(String::from("anon")));
+        assert!(!format!("{manager:?}").contains("xprv"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vectors() -> Result<()> {
+        use crate::test_vectors::nip06;
+
+        let keys = Keys::from_mnemonic(nip06::MNEMONIC, None)?;
+        assert_eq!(keys.secret_key()?, SecretKey::from_str(nip06::SECRET_KEY)?);
+        Ok(())
+    }
 }
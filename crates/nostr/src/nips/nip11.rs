@@ -8,7 +8,8 @@
 
 use std::net::SocketAddr;
 
-use reqwest::Proxy;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Proxy, StatusCode};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -30,7 +31,7 @@ pub enum Error {
 }
 
 /// Relay information document
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RelayInformationDocument {
     /// Name
     pub name: Option<String>,
@@ -46,6 +47,84 @@ pub struct RelayInformationDocument {
     pub software: Option<String>,
     /// Software version
     pub version: Option<String>,
+    /// Server limitations
+    pub limitation: Option<Limitation>,
+    /// Payments page
+    pub payments_url: Option<String>,
+    /// Fee schedules
+    pub fees: Option<FeeSchedules>,
+}
+
+/// Server limitations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limitation {
+    /// Max bytes of incoming WebSocket messages
+    pub max_message_length: Option<u64>,
+    /// Max active subscriptions per connection
+    pub max_subscriptions: Option<u64>,
+    /// Max filters per subscription
+    pub max_filters: Option<u64>,
+    /// Max value of the filters `limit`
+    pub max_limit: Option<u64>,
+    /// Max length of subscription ids
+    pub max_subid_length: Option<u64>,
+    /// Max number of tags per event
+    pub max_event_tags: Option<u64>,
+    /// Max characters of the event content
+    pub max_content_length: Option<u64>,
+    /// Min POW difficulty (NIP-13)
+    pub min_pow_difficulty: Option<u8>,
+    /// Authentication required (NIP-42)
+    pub auth_required: Option<bool>,
+    /// Payment required
+    pub payment_required: Option<bool>,
+    /// Writes restricted to some users
+    pub restricted_writes: Option<bool>,
+}
+
+/// HTTP cache validators of a [`RelayInformationDocument`] (`ETag` and `Last-Modified` headers)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    /// `ETag`
+    pub etag: Option<String>,
+    /// `Last-Modified`
+    pub last_modified: Option<String>,
+}
+
+/// Fee schedules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedules {
+    /// Admission fees
+    #[serde(default)]
+    pub admission: Vec<FeeSchedule>,
+    /// Subscription fees
+    #[serde(default)]
+    pub subscription: Vec<FeeSchedule>,
+    /// Publication fees
+    #[serde(default)]
+    pub publication: Vec<FeeSchedule>,
+}
+
+impl FeeSchedules {
+    /// Check if there are no fees
+    pub fn is_empty(&self) -> bool {
+        self.admission.is_empty() && self.subscription.is_empty() && self.publication.is_empty()
+    }
+}
+
+/// Fee schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Amount
+    pub amount: u64,
+    /// Unit (ex. `msats`)
+    pub unit: String,
+    /// Period, in seconds (subscription fees)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// Kinds (publication fees)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<Vec<u64>>,
 }
 
 impl RelayInformationDocument {
@@ -54,6 +133,84 @@ impl RelayInformationDocument {
         Self::default()
     }
 
+    /// Check if the relay requires a payment: flagged in the limitations or with fees
+    pub fn is_paid(&self) -> bool {
+        self.limitation
+            .as_ref()
+            .and_then(|l| l.payment_required)
+            .unwrap_or(false)
+            || self.fees.as_ref().map_or(false, |fees| !fees.is_empty())
+    }
+
+    /// Check if supported NIPs, limitations or fees differ from `other`
+    ///
+    /// Descriptive fields (ex. name, contact) are ignored.
+    pub fn capabilities_changed(&self, other: &Self) -> bool {
+        self.supported_nips != other.supported_nips
+            || self.limitation != other.limitation
+            || self.fees != other.fees
+    }
+
+    /// Get Relay Information Document if modified since the response with `validators`
+    ///
+    /// Return `None` if the relay replied `304 Not Modified`, otherwise the document with the
+    /// validators to use for the next request.
+    pub async fn get_if_modified(
+        url: Url,
+        proxy: Option<SocketAddr>,
+        validators: &CacheValidators,
+    ) -> Result<Option<(Self, CacheValidators)>, Error> {
+        use reqwest::Client;
+
+        let mut builder = Client::builder();
+        if let Some(proxy) = proxy {
+            let proxy = format!("socks5h://{proxy}");
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        let client: Client = builder.build()?;
+        Self::get_if_modified_with_client(&client, url, validators).await
+    }
+
+    /// Get Relay Information Document if modified since the response with `validators`, using `client`
+    ///
+    /// Useful to reuse the proxy settings of an existing client. See [`RelayInformationDocument::get_if_modified`].
+    pub async fn get_if_modified_with_client(
+        client: &reqwest::Client,
+        url: Url,
+        validators: &CacheValidators,
+    ) -> Result<Option<(Self, CacheValidators)>, Error> {
+        let url = Self::with_http_scheme(url)?;
+        let mut req = client.get(url).header("Accept", "application/nostr+json");
+        if let Some(etag) = &validators.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = req
+            .send()
+            .await
+            .map_err(|_| Error::InaccessibleInformationDocument)?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let validators = CacheValidators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        match response.json().await {
+            Ok(document) => Ok(Some((document, validators))),
+            Err(_) => Err(Error::InvalidInformationDocument),
+        }
+    }
+
     /// Get Relay Information Document
     pub async fn get(url: Url, proxy: Option<SocketAddr>) -> Result<Self, Error> {
         use reqwest::Client;
@@ -109,3 +266,50 @@ impl RelayInformationDocument {
         Ok(url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fees() {
+        let json = r#"{"name":"paid relay","supported_nips":[1,11],"limitation":{"payment_required":true,"auth_required":false,"max_limit":500},"payments_url":"https://relay.example.com/pay","fees":{"admission":[{"amount":21000,"unit":"msats"}],"subscription":[{"amount":5000,"unit":"msats","period":2592000}],"publication":[{"kinds":[4],"amount":100,"unit":"msats"}]}}"#;
+        let document: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert!(document.is_paid());
+
+        let limitation = document.limitation.unwrap();
+        assert_eq!(limitation.payment_required, Some(true));
+        assert_eq!(limitation.max_limit, Some(500));
+
+        let fees = document.fees.unwrap();
+        assert_eq!(fees.admission[0].amount, 21000);
+        assert_eq!(fees.subscription[0].period, Some(2592000));
+        assert_eq!(fees.publication[0].kinds, Some(vec![4]));
+
+        let document: RelayInformationDocument =
+            serde_json::from_str(r#"{"name":"free relay"}"#).unwrap();
+        assert!(!document.is_paid());
+    }
+
+    #[test]
+    fn test_capabilities_changed() {
+        let json =
+            r#"{"name":"relay","supported_nips":[1,11],"limitation":{"auth_required":false}}"#;
+        let document: RelayInformationDocument = serde_json::from_str(json).unwrap();
+
+        let mut renamed = document.clone();
+        renamed.name = Some(String::from("new name"));
+        assert!(!document.capabilities_changed(&renamed));
+
+        let mut auth = document.clone();
+        auth.limitation = Some(Limitation {
+            auth_required: Some(true),
+            ..Default::default()
+        });
+        assert!(document.capabilities_changed(&auth));
+
+        let mut nips = document.clone();
+        nips.supported_nips = Some(vec![1, 11, 42]);
+        assert!(document.capabilities_changed(&nips));
+    }
+}
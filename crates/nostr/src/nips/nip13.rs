@@ -6,7 +6,7 @@
 //!
 //! https://github.com/nostr-protocol/nips/blob/master/13.md
 
-use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
 
 /// Gets the number of leading zero bits of a hash. Result is between 0 and 255.
 pub fn get_leading_zero_bits(h: Sha256Hash) -> u8 {
@@ -52,9 +52,9 @@ pub fn get_prefixes_for_difficulty(leading_zero_bits: u8) -> Vec<String> {
 }
 
 #[cfg(test)]
-pub mod tests {
+mod tests {
     use super::*;
-    use bitcoin::hashes::hex::FromHex;
+    use bitcoin_hashes::hex::FromHex;
 
     #[test]
     fn check_get_leading_zeroes() {
@@ -380,7 +380,7 @@ pub mod tests {
 
     #[test]
     fn check_find_prefixes_for_pow() {
-        assert_eq!(get_prefixes_for_difficulty(0).is_empty(), true);
+        assert!(get_prefixes_for_difficulty(0).is_empty());
 
         assert_eq!(
             get_prefixes_for_difficulty(1),
@@ -426,4 +426,16 @@ pub mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_vectors() {
+        use crate::test_vectors::nip13;
+
+        for (hash, leading_zero_bits) in nip13::LEADING_ZERO_BITS.iter() {
+            assert_eq!(
+                get_leading_zero_bits(Sha256Hash::from_hex(hash).unwrap()),
+                *leading_zero_bits
+            );
+        }
+    }
 }
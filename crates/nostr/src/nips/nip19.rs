@@ -7,21 +7,42 @@
 
 #![allow(missing_docs)]
 
-use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
-use bitcoin::secp256k1::{SecretKey, XOnlyPublicKey};
+#[cfg(feature = "base")]
+use std::fmt;
+#[cfg(feature = "base")]
+use std::str::FromStr;
+
+use bech32::{self, u5, FromBase32, ToBase32, Variant};
+use secp256k1::{SecretKey, XOnlyPublicKey};
 #[cfg(feature = "base")]
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "base")]
 use crate::event::id::{self, EventId};
+use crate::key;
 #[cfg(feature = "base")]
-use crate::Profile;
+use crate::types::relay_url;
+#[cfg(feature = "base")]
+use crate::url::Url;
+#[cfg(feature = "base")]
+use crate::{Coordinate, Event, Kind, Profile, UncheckedUrl};
 
 pub const PREFIX_BECH32_SECRET_KEY: &str = "nsec";
 pub const PREFIX_BECH32_PUBLIC_KEY: &str = "npub";
 pub const PREFIX_BECH32_NOTE_ID: &str = "note";
 pub const PREFIX_BECH32_PROFILE: &str = "nprofile";
 pub const PREFIX_BECH32_EVENT: &str = "nevent";
+pub const PREFIX_BECH32_COORDINATE: &str = "naddr";
+pub const PREFIX_BECH32_RELAY: &str = "nrelay";
+
+#[cfg(feature = "base")]
+const SPECIAL: u8 = 0;
+#[cfg(feature = "base")]
+const RELAY: u8 = 1;
+#[cfg(feature = "base")]
+const AUTHOR: u8 = 2;
+#[cfg(feature = "base")]
+const KIND: u8 = 3;
 
 /// `NIP19` error
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
@@ -44,12 +65,31 @@ pub enum Error {
     /// Invalid bec32 event
     #[error("Invalid bech32 event")]
     Bech32EventParseError,
+    /// Invalid bec32 coordinate
+    #[error("Invalid bech32 coordinate")]
+    Bech32CoordinateParseError,
+    /// Invalid bec32 relay
+    #[error("Invalid bech32 relay")]
+    Bech32RelayParseError,
+    /// Unknown bech32 prefix
+    #[error("Unknown bech32 prefix: {0}")]
+    UnknownPrefix(String),
+    /// Kind not encodable as 32-bit integer
+    #[error("Kind out of range")]
+    KindOutOfRange,
+    /// Invalid relay url
+    #[cfg(feature = "base")]
+    #[error(transparent)]
+    RelayUrl(#[from] relay_url::Error),
+    /// TLV value longer than 255 bytes
+    #[error("TLV value too long: {0} bytes")]
+    TLVValueTooLong(usize),
     /// Secp256k1 error
     #[error(transparent)]
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
     /// Hash error
     #[error(transparent)]
-    Hash(#[from] bitcoin::hashes::Error),
+    Hash(#[from] bitcoin_hashes::Error),
     /// EventId error
     #[cfg(feature = "base")]
     #[error(transparent)]
@@ -60,24 +100,93 @@ pub trait FromBech32: Sized {
     type Err;
     fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>;
+        S: AsRef<str>;
+}
+
+/// Number of 5-bit groups of a 32-bytes secret key
+const SECRET_KEY_BASE32_LEN: usize = 52;
+
+/// Convert the 5-bit groups of a secret key to bytes, without branching on their value
+///
+/// Return `false` if the padding bits are not zero.
+fn secret_key_from_base32(data: &[u5; SECRET_KEY_BASE32_LEN], bytes: &mut [u8; 32]) -> bool {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut index: usize = 0;
+    for value in data.iter() {
+        acc = (acc << 5) | value.to_u8() as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes[index] = (acc >> bits) as u8;
+            acc &= (1 << bits) - 1;
+            index += 1;
+        }
+    }
+    acc == 0
+}
+
+/// Convert secret key bytes to 5-bit groups, without heap copies
+fn secret_key_to_base32(bytes: &[u8; 32]) -> Result<[u5; SECRET_KEY_BASE32_LEN], Error> {
+    let mut data = [u5::default(); SECRET_KEY_BASE32_LEN];
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut index: usize = 0;
+    for byte in bytes.iter() {
+        acc = (acc << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data[index] = u5::try_from_u8(((acc >> bits) & 31) as u8)?;
+            acc &= (1 << bits) - 1;
+            index += 1;
+        }
+    }
+    data[index] = u5::try_from_u8(((acc << (5 - bits)) & 31) as u8)?;
+    Ok(data)
+}
+
+/// Overwrite 5-bit groups of a secret key
+fn wipe_base32(data: &mut [u5]) {
+    data.fill(u5::default());
 }
 
 impl FromBech32 for SecretKey {
     type Err = Error;
+
+    /// Decode `nsec`
+    ///
+    /// The decoded secret is wiped after the parsing and all the failures return the same
+    /// error, whatever the content of the secret.
     fn from_bech32<S>(secret_key: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
-        let (hrp, data, checksum) =
-            bech32::decode(&secret_key.into()).map_err(|_| Error::Bech32SkParseError)?;
+        let (hrp, mut data, checksum) =
+            bech32::decode(secret_key.as_ref()).map_err(|_| Error::Bech32SkParseError)?;
 
-        if hrp != PREFIX_BECH32_SECRET_KEY || checksum != Variant::Bech32 {
-            return Err(Error::Bech32SkParseError);
+        let mut groups = [u5::default(); SECRET_KEY_BASE32_LEN];
+        let valid_len: bool = data.len() == SECRET_KEY_BASE32_LEN;
+        if valid_len {
+            groups.copy_from_slice(&data);
         }
-
-        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32SkParseError)?;
-        SecretKey::from_slice(data.as_slice()).map_err(|_| Error::Bech32SkParseError)
+        wipe_base32(&mut data);
+
+        let mut bytes = [0u8; 32];
+        let valid_padding: bool = secret_key_from_base32(&groups, &mut bytes);
+        wipe_base32(&mut groups);
+
+        let result = if hrp == PREFIX_BECH32_SECRET_KEY
+            && checksum == Variant::Bech32
+            && valid_len
+            && valid_padding
+        {
+            SecretKey::from_slice(&bytes).map_err(|_| Error::Bech32SkParseError)
+        } else {
+            Err(Error::Bech32SkParseError)
+        };
+        key::wipe(&mut bytes);
+        result
     }
 }
 
@@ -85,10 +194,10 @@ impl FromBech32 for XOnlyPublicKey {
     type Err = Error;
     fn from_bech32<S>(public_key: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
         let (hrp, data, checksum) =
-            bech32::decode(&public_key.into()).map_err(|_| Error::Bech32PkParseError)?;
+            bech32::decode(public_key.as_ref()).map_err(|_| Error::Bech32PkParseError)?;
 
         if hrp != PREFIX_BECH32_PUBLIC_KEY || checksum != Variant::Bech32 {
             return Err(Error::Bech32PkParseError);
@@ -104,10 +213,10 @@ impl FromBech32 for EventId {
     type Err = Error;
     fn from_bech32<S>(hash: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
         let (hrp, data, checksum) =
-            bech32::decode(&hash.into()).map_err(|_| Error::Bech32NoteParseError)?;
+            bech32::decode(hash.as_ref()).map_err(|_| Error::Bech32NoteParseError)?;
 
         if hrp != PREFIX_BECH32_NOTE_ID || checksum != Variant::Bech32 {
             return Err(Error::Bech32NoteParseError);
@@ -140,12 +249,13 @@ impl ToBech32 for SecretKey {
     type Err = Error;
 
     fn to_bech32(&self) -> Result<String, Self::Err> {
-        let data = self.secret_bytes().to_base32();
-        Ok(bech32::encode(
-            PREFIX_BECH32_SECRET_KEY,
-            data,
-            Variant::Bech32,
-        )?)
+        let mut bytes: [u8; 32] = self.secret_bytes();
+        let data = secret_key_to_base32(&bytes);
+        key::wipe(&mut bytes);
+        let mut data = data?;
+        let nsec = bech32::encode(PREFIX_BECH32_SECRET_KEY, data, Variant::Bech32);
+        wipe_base32(&mut data);
+        Ok(nsec?)
     }
 }
 
@@ -164,61 +274,90 @@ impl ToBech32 for EventId {
     }
 }
 
+/// Append TLV entry, rejecting values longer than 255 bytes instead of truncating their length
+#[cfg(feature = "base")]
+fn push_tlv(bytes: &mut Vec<u8>, t: u8, value: &[u8]) -> Result<(), Error> {
+    let l: u8 = u8::try_from(value.len()).map_err(|_| Error::TLVValueTooLong(value.len()))?;
+    bytes.push(t);
+    bytes.push(l);
+    bytes.extend_from_slice(value);
+    Ok(())
+}
+
+/// Split the next TLV entry of `data`, `None` if truncated
+#[cfg(feature = "base")]
+fn next_tlv<'a>(data: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    let t: u8 = *data.first()?;
+    let l: usize = *data.get(1)? as usize;
+    let value: &[u8] = data.get(2..l + 2)?;
+    *data = &data[l + 2..];
+    Some((t, value))
+}
+
+/// Append relay hints, skipping the ones longer than 255 bytes (not encodable)
+#[cfg(feature = "base")]
+fn push_relays(bytes: &mut Vec<u8>, relays: &[UncheckedUrl]) -> Result<(), Error> {
+    for relay in relays
+        .iter()
+        .filter(|relay| relay.as_str().len() <= u8::MAX as usize)
+    {
+        push_tlv(bytes, RELAY, relay.as_str().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Validate and normalize relay url of a TLV entry
+#[cfg(feature = "base")]
+fn relay_tlv_value(relay: &str) -> Result<String, Error> {
+    Ok(relay_url::to_string(&relay_url::parse(relay)?))
+}
+
+/// Relay hint of a TLV value, as given: `None` only if not UTF-8
+#[cfg(feature = "base")]
+fn parse_relay(bytes: &[u8]) -> Option<UncheckedUrl> {
+    let relay: &str = std::str::from_utf8(bytes).ok()?;
+    Some(UncheckedUrl::from(relay))
+}
+
 #[cfg(feature = "base")]
 impl FromBech32 for Profile {
     type Err = Error;
+
+    /// Decode `nprofile`
+    ///
+    /// Relay hints are kept as given (not validated), non UTF-8 ones and unknown TLVs are ignored.
     fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
         let (hrp, data, checksum) =
-            bech32::decode(&s.into()).map_err(|_| Error::Bech32ProfileParseError)?;
+            bech32::decode(s.as_ref()).map_err(|_| Error::Bech32ProfileParseError)?;
 
         if hrp != PREFIX_BECH32_PROFILE || checksum != Variant::Bech32 {
             return Err(Error::Bech32ProfileParseError);
         }
 
-        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32ProfileParseError)?;
+        let mut data: &[u8] =
+            &Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32ProfileParseError)?;
 
-        let t = data.first().ok_or(Error::Bech32ProfileParseError)?;
-        if *t != 0 {
-            return Err(Error::Bech32ProfileParseError);
-        }
-
-        let l = data.get(1).ok_or(Error::Bech32ProfileParseError)?;
-        if *l != 32 {
-            return Err(Error::Bech32ProfileParseError);
-        }
-
-        let public_key = data.get(2..34).ok_or(Error::Bech32ProfileParseError)?;
-        let public_key = XOnlyPublicKey::from_slice(public_key)?;
+        let mut public_key: Option<XOnlyPublicKey> = None;
+        let mut relays: Vec<UncheckedUrl> = Vec::new();
 
-        let mut relays: Vec<String> = Vec::new();
-        let mut relays_data: Vec<u8> = data
-            .get(34..)
-            .ok_or(Error::Bech32ProfileParseError)?
-            .to_vec();
+        while !data.is_empty() {
+            let (t, bytes) = next_tlv(&mut data).ok_or(Error::Bech32ProfileParseError)?;
 
-        while !relays_data.is_empty() {
-            let t = relays_data.first().ok_or(Error::Bech32ProfileParseError)?;
-            if *t != 1 {
-                return Err(Error::Bech32ProfileParseError);
+            match t {
+                SPECIAL => public_key = Some(XOnlyPublicKey::from_slice(bytes)?),
+                RELAY => relays.extend(parse_relay(bytes)),
+                // Unknown TLVs are ignored
+                _ => (),
             }
-
-            let l = relays_data.get(1).ok_or(Error::Bech32ProfileParseError)?;
-            let l = *l as usize;
-
-            let data = relays_data
-                .get(2..l + 2)
-                .ok_or(Error::Bech32ProfileParseError)?;
-
-            relays.push(
-                String::from_utf8(data.to_vec()).map_err(|_| Error::Bech32ProfileParseError)?,
-            );
-            relays_data.drain(..l + 2);
         }
 
-        Ok(Self { public_key, relays })
+        Ok(Self {
+            public_key: public_key.ok_or(Error::Bech32ProfileParseError)?,
+            relays,
+        })
     }
 }
 
@@ -226,14 +365,14 @@ impl FromBech32 for Profile {
 impl ToBech32 for Profile {
     type Err = Error;
 
+    /// Encode `nprofile`
+    ///
+    /// Never fails: relay hints longer than 255 bytes are skipped.
     fn to_bech32(&self) -> Result<String, Self::Err> {
-        let mut bytes: Vec<u8> = vec![0, 32];
-        bytes.extend(self.public_key.serialize());
+        let mut bytes: Vec<u8> = Vec::new();
+        push_tlv(&mut bytes, SPECIAL, &self.public_key.serialize())?;
 
-        for relay in self.relays.iter() {
-            bytes.extend([1, relay.len() as u8]);
-            bytes.extend(relay.as_bytes());
-        }
+        push_relays(&mut bytes, &self.relays)?;
 
         let data = bytes.to_base32();
         Ok(bech32::encode(
@@ -244,95 +383,496 @@ impl ToBech32 for Profile {
     }
 }
 
+/// Event pointer (`nevent`)
 #[cfg(feature = "base")]
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct Nip19Event {
     event_id: EventId,
-    relays: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<XOnlyPublicKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<Kind>,
+    relays: Vec<UncheckedUrl>,
 }
 
 #[cfg(feature = "base")]
 impl Nip19Event {
+    /// New [`Nip19Event`]
+    ///
+    /// Relay hints are kept as given, without validation.
     pub fn new<S>(event_id: EventId, relays: Vec<S>) -> Self
     where
-        S: Into<String>,
+        S: Into<UncheckedUrl>,
     {
         Self {
             event_id,
-            relays: relays.into_iter().map(|u| u.into()).collect(),
+            author: None,
+            kind: None,
+            relays: relays.into_iter().map(|relay| relay.into()).collect(),
+        }
+    }
+
+    /// New [`Nip19Event`] with relay urls
+    ///
+    /// Fail if any relay is not a `ws` or `wss` url.
+    pub fn with_relays(event_id: EventId, relays: Vec<Url>) -> Result<Self, relay_url::Error> {
+        Ok(Self {
+            event_id,
+            author: None,
+            kind: None,
+            relays: relays
+                .into_iter()
+                .map(|relay| relay_url::check(relay).map(UncheckedUrl::from))
+                .collect::<Result<Vec<UncheckedUrl>, _>>()?,
+        })
+    }
+
+    /// New [`Nip19Event`] from relay strings
+    ///
+    /// Fail if any relay is not a valid `ws` or `wss` url.
+    pub fn try_new<S>(event_id: EventId, relays: Vec<S>) -> Result<Self, relay_url::Error>
+    where
+        S: AsRef<str>,
+    {
+        Self::with_relays(
+            event_id,
+            relays
+                .into_iter()
+                .map(relay_url::parse)
+                .collect::<Result<Vec<Url>, _>>()?,
+        )
+    }
+
+    /// Get event id
+    pub fn event_id(&self) -> EventId {
+        self.event_id
+    }
+
+    /// New [`Nip19Event`] pointing to `event`, with its author, kind and `relays` hints
+    ///
+    /// Fail if any relay is not a `ws` or `wss` url.
+    pub fn from_event(event: &Event, relays: Vec<Url>) -> Result<Self, relay_url::Error> {
+        Ok(Self::with_relays(event.id, relays)?
+            .author(event.pubkey)
+            .kind(event.kind))
+    }
+
+    /// Get relay hints, not validated (see [`UncheckedUrl::parse`])
+    pub fn relays(&self) -> &[UncheckedUrl] {
+        &self.relays
+    }
+
+    /// Valid relay hints, validated and normalized (see [`UncheckedUrl::parse`])
+    pub fn relay_urls(&self) -> Vec<Url> {
+        self.relays
+            .iter()
+            .filter_map(|relay| relay.parse().ok())
+            .collect()
+    }
+
+    /// Get author, if any
+    pub fn event_author(&self) -> Option<XOnlyPublicKey> {
+        self.author
+    }
+
+    /// Get kind, if any
+    pub fn event_kind(&self) -> Option<Kind> {
+        self.kind
+    }
+
+    /// Set author
+    pub fn author(self, author: XOnlyPublicKey) -> Self {
+        Self {
+            author: Some(author),
+            ..self
+        }
+    }
+
+    /// Set kind
+    pub fn kind(self, kind: Kind) -> Self {
+        Self {
+            kind: Some(kind),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "base")]
+impl From<EventId> for Nip19Event {
+    fn from(event_id: EventId) -> Self {
+        Self {
+            event_id,
+            author: None,
+            kind: None,
+            relays: Vec::new(),
         }
     }
 }
 
+#[cfg(feature = "base")]
+impl From<&Event> for Nip19Event {
+    fn from(event: &Event) -> Self {
+        Self::from(event.id).author(event.pubkey).kind(event.kind)
+    }
+}
+
+#[cfg(feature = "base")]
+impl From<Event> for Nip19Event {
+    fn from(event: Event) -> Self {
+        Self::from(&event)
+    }
+}
+
+#[cfg(feature = "base")]
+impl From<Nip19Event> for EventId {
+    fn from(event: Nip19Event) -> Self {
+        event.event_id
+    }
+}
+
 #[cfg(feature = "base")]
 impl FromBech32 for Nip19Event {
     type Err = Error;
+
+    /// Decode `nevent`
+    ///
+    /// Relay hints are kept as given (not validated), non UTF-8 ones and unknown TLVs are ignored.
     fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
     where
-        S: Into<String>,
+        S: AsRef<str>,
     {
         let (hrp, data, checksum) =
-            bech32::decode(&s.into()).map_err(|_| Error::Bech32EventParseError)?;
+            bech32::decode(s.as_ref()).map_err(|_| Error::Bech32EventParseError)?;
 
         if hrp != PREFIX_BECH32_EVENT || checksum != Variant::Bech32 {
             return Err(Error::Bech32EventParseError);
         }
 
-        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32EventParseError)?;
+        let mut data: &[u8] =
+            &Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32EventParseError)?;
+
+        let mut event_id: Option<EventId> = None;
+        let mut author: Option<XOnlyPublicKey> = None;
+        let mut kind: Option<Kind> = None;
+        let mut relays: Vec<UncheckedUrl> = Vec::new();
+
+        while !data.is_empty() {
+            let (t, bytes) = next_tlv(&mut data).ok_or(Error::Bech32EventParseError)?;
+
+            match t {
+                SPECIAL => {
+                    if bytes.len() != 32 {
+                        return Err(Error::Bech32EventParseError);
+                    }
+                    event_id = Some(EventId::from_slice(bytes)?);
+                }
+                RELAY => relays.extend(parse_relay(bytes)),
+                AUTHOR => author = Some(XOnlyPublicKey::from_slice(bytes)?),
+                KIND => {
+                    let bytes: [u8; 4] =
+                        bytes.try_into().map_err(|_| Error::Bech32EventParseError)?;
+                    kind = Some(Kind::from(u32::from_be_bytes(bytes) as u64));
+                }
+                // Unknown TLVs are ignored
+                _ => (),
+            }
+        }
 
-        let t = data.first().ok_or(Error::Bech32EventParseError)?;
-        if *t != 0 {
-            return Err(Error::Bech32EventParseError);
+        Ok(Self {
+            event_id: event_id.ok_or(Error::Bech32EventParseError)?,
+            author,
+            kind,
+            relays,
+        })
+    }
+}
+
+#[cfg(feature = "base")]
+impl ToBech32 for Nip19Event {
+    type Err = Error;
+
+    /// Encode `nevent`
+    ///
+    /// Never fails: relay hints longer than 255 bytes and kinds above [`u32::MAX`] are skipped.
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let mut bytes: Vec<u8> = Vec::new();
+        push_tlv(&mut bytes, SPECIAL, &self.event_id.inner())?;
+
+        push_relays(&mut bytes, &self.relays)?;
+
+        if let Some(author) = self.author {
+            push_tlv(&mut bytes, AUTHOR, &author.serialize())?;
         }
 
-        let l = data.get(1).ok_or(Error::Bech32EventParseError)?;
-        if *l != 32 {
-            return Err(Error::Bech32EventParseError);
+        // Optional: skipped if not encodable as 32-bit integer
+        if let Some(kind) = self.kind.and_then(|kind| u32::try_from(kind.as_u64()).ok()) {
+            push_tlv(&mut bytes, KIND, &kind.to_be_bytes())?;
         }
 
-        let event_id = data.get(2..34).ok_or(Error::Bech32EventParseError)?;
-        let event_id = EventId::from_slice(event_id)?;
+        let data = bytes.to_base32();
+        Ok(bech32::encode(PREFIX_BECH32_EVENT, data, Variant::Bech32)?)
+    }
+}
 
-        let mut relays: Vec<String> = Vec::new();
-        let mut relays_data: Vec<u8> = data.get(34..).ok_or(Error::Bech32EventParseError)?.to_vec();
+#[cfg(feature = "base")]
+impl FromBech32 for Coordinate {
+    type Err = Error;
 
-        while !relays_data.is_empty() {
-            let t = relays_data.first().ok_or(Error::Bech32EventParseError)?;
-            if *t != 1 {
-                return Err(Error::Bech32EventParseError);
+    /// Decode `naddr`
+    ///
+    /// Relay hints are kept as given (not validated), non UTF-8 ones and unknown TLVs are ignored.
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: AsRef<str>,
+    {
+        let (hrp, data, checksum) =
+            bech32::decode(s.as_ref()).map_err(|_| Error::Bech32CoordinateParseError)?;
+
+        if hrp != PREFIX_BECH32_COORDINATE || checksum != Variant::Bech32 {
+            return Err(Error::Bech32CoordinateParseError);
+        }
+
+        let mut data: &[u8] =
+            &Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32CoordinateParseError)?;
+
+        let mut identifier: Option<String> = None;
+        let mut pubkey: Option<XOnlyPublicKey> = None;
+        let mut kind: Option<Kind> = None;
+        let mut relays: Vec<UncheckedUrl> = Vec::new();
+
+        while !data.is_empty() {
+            let (t, bytes) = next_tlv(&mut data).ok_or(Error::Bech32CoordinateParseError)?;
+
+            match t {
+                SPECIAL => {
+                    identifier = Some(
+                        String::from_utf8(bytes.to_vec())
+                            .map_err(|_| Error::Bech32CoordinateParseError)?,
+                    )
+                }
+                RELAY => relays.extend(parse_relay(bytes)),
+                AUTHOR => pubkey = Some(XOnlyPublicKey::from_slice(bytes)?),
+                KIND => {
+                    let bytes: [u8; 4] = bytes
+                        .try_into()
+                        .map_err(|_| Error::Bech32CoordinateParseError)?;
+                    kind = Some(Kind::from(u32::from_be_bytes(bytes) as u64));
+                }
+                // Unknown TLVs are ignored
+                _ => (),
             }
+        }
 
-            let l = relays_data.get(1).ok_or(Error::Bech32EventParseError)?;
-            let l = *l as usize;
+        Ok(Self {
+            kind: kind.ok_or(Error::Bech32CoordinateParseError)?,
+            pubkey: pubkey.ok_or(Error::Bech32CoordinateParseError)?,
+            identifier: identifier.ok_or(Error::Bech32CoordinateParseError)?,
+            relays,
+        })
+    }
+}
 
-            let data = relays_data
-                .get(2..l + 2)
-                .ok_or(Error::Bech32EventParseError)?;
+#[cfg(feature = "base")]
+impl ToBech32 for Coordinate {
+    type Err = Error;
 
-            relays
-                .push(String::from_utf8(data.to_vec()).map_err(|_| Error::Bech32EventParseError)?);
-            relays_data.drain(..l + 2);
+    /// Encode `naddr`
+    ///
+    /// Relay hints longer than 255 bytes are skipped. Fail if the identifier is longer than
+    /// 255 bytes or if the kind is above [`u32::MAX`].
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let kind: u32 = u32::try_from(self.kind.as_u64()).map_err(|_| Error::KindOutOfRange)?;
+
+        let mut bytes: Vec<u8> = Vec::new();
+        push_tlv(&mut bytes, SPECIAL, self.identifier.as_bytes())?;
+
+        push_relays(&mut bytes, &self.relays)?;
+
+        push_tlv(&mut bytes, AUTHOR, &self.pubkey.serialize())?;
+        push_tlv(&mut bytes, KIND, &kind.to_be_bytes())?;
+
+        let data = bytes.to_base32();
+        Ok(bech32::encode(
+            PREFIX_BECH32_COORDINATE,
+            data,
+            Variant::Bech32,
+        )?)
+    }
+}
+
+#[cfg(feature = "base")]
+impl FromBech32 for Url {
+    type Err = Error;
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: AsRef<str>,
+    {
+        let (hrp, data, checksum) =
+            bech32::decode(s.as_ref()).map_err(|_| Error::Bech32RelayParseError)?;
+
+        if hrp != PREFIX_BECH32_RELAY || checksum != Variant::Bech32 {
+            return Err(Error::Bech32RelayParseError);
+        }
+
+        let mut data: &[u8] =
+            &Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32RelayParseError)?;
+
+        let mut url: Option<Url> = None;
+        while !data.is_empty() {
+            let (t, bytes) = next_tlv(&mut data).ok_or(Error::Bech32RelayParseError)?;
+
+            // Unknown TLVs are ignored
+            if t == SPECIAL && url.is_none() {
+                let relay = std::str::from_utf8(bytes).map_err(|_| Error::Bech32RelayParseError)?;
+                url = Some(relay_url::parse(relay).map_err(|_| Error::Bech32RelayParseError)?);
+            }
         }
 
-        Ok(Self { event_id, relays })
+        url.ok_or(Error::Bech32RelayParseError)
     }
 }
 
 #[cfg(feature = "base")]
-impl ToBech32 for Nip19Event {
+impl ToBech32 for Url {
     type Err = Error;
 
     fn to_bech32(&self) -> Result<String, Self::Err> {
-        let mut bytes: Vec<u8> = vec![0, 32];
-        bytes.extend(self.event_id.inner().iter());
+        let relay: String = relay_tlv_value(self.as_str())?;
+        let mut bytes: Vec<u8> = Vec::new();
+        push_tlv(&mut bytes, SPECIAL, relay.as_bytes())?;
+
+        let data = bytes.to_base32();
+        Ok(bech32::encode(PREFIX_BECH32_RELAY, data, Variant::Bech32)?)
+    }
+}
+
+/// Any NIP-19 entity
+#[cfg(feature = "base")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip19 {
+    /// `npub`
+    Pubkey(XOnlyPublicKey),
+    /// `nsec`
+    Secret(SecretKey),
+    /// `note`
+    EventId(EventId),
+    /// `nprofile`
+    Profile(Profile),
+    /// `nevent`
+    Event(Nip19Event),
+    /// `naddr`
+    Coordinate(Coordinate),
+    /// `nrelay`
+    Relay(Url),
+}
 
-        for relay in self.relays.iter() {
-            bytes.extend([1, relay.len() as u8]);
-            bytes.extend(relay.as_bytes());
+#[cfg(feature = "base")]
+impl FromBech32 for Nip19 {
+    type Err = Error;
+
+    /// Decode any NIP-19 entity, detecting its type by the prefix
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: AsRef<str>,
+    {
+        let s: &str = s.as_ref();
+
+        // Secret keys are decoded only once, to not leave more copies of the secret around
+        if let Some((hrp, _)) = s.rsplit_once('1') {
+            if hrp.eq_ignore_ascii_case(PREFIX_BECH32_SECRET_KEY) {
+                return Ok(Self::Secret(SecretKey::from_bech32(s)?));
+            }
         }
 
-        let data = bytes.to_base32();
-        Ok(bech32::encode(PREFIX_BECH32_EVENT, data, Variant::Bech32)?)
+        let (hrp, ..) = bech32::decode(s)?;
+        match hrp.as_str() {
+            PREFIX_BECH32_PUBLIC_KEY => Ok(Self::Pubkey(XOnlyPublicKey::from_bech32(s)?)),
+            PREFIX_BECH32_SECRET_KEY => Ok(Self::Secret(SecretKey::from_bech32(s)?)),
+            PREFIX_BECH32_NOTE_ID => Ok(Self::EventId(EventId::from_bech32(s)?)),
+            PREFIX_BECH32_PROFILE => Ok(Self::Profile(Profile::from_bech32(s)?)),
+            PREFIX_BECH32_EVENT => Ok(Self::Event(Nip19Event::from_bech32(s)?)),
+            PREFIX_BECH32_COORDINATE => Ok(Self::Coordinate(Coordinate::from_bech32(s)?)),
+            PREFIX_BECH32_RELAY => Ok(Self::Relay(Url::from_bech32(s)?)),
+            _ => Err(Error::UnknownPrefix(hrp)),
+        }
+    }
+}
+
+#[cfg(feature = "base")]
+impl ToBech32 for Nip19 {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        match self {
+            Self::Pubkey(public_key) => public_key.to_bech32(),
+            Self::Secret(secret_key) => secret_key.to_bech32(),
+            Self::EventId(event_id) => event_id.to_bech32(),
+            Self::Profile(profile) => profile.to_bech32(),
+            Self::Event(event) => event.to_bech32(),
+            Self::Coordinate(coordinate) => coordinate.to_bech32(),
+            Self::Relay(url) => url.to_bech32(),
+        }
+    }
+}
+
+#[cfg(feature = "base")]
+impl FromStr for Profile {
+    type Err = Error;
+
+    /// Parse `nprofile`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(s)
+    }
+}
+
+#[cfg(feature = "base")]
+impl fmt::Display for Profile {
+    /// Display as `nprofile`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_bech32().map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(feature = "base")]
+impl FromStr for Nip19Event {
+    type Err = Error;
+
+    /// Parse `nevent`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(s)
+    }
+}
+
+#[cfg(feature = "base")]
+impl fmt::Display for Nip19Event {
+    /// Display as `nevent`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_bech32().map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(feature = "base")]
+impl FromStr for Nip19 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(s)
+    }
+}
+
+#[cfg(feature = "base")]
+impl fmt::Display for Nip19 {
+    /// Display as bech32
+    ///
+    /// Coordinates and relays that can't be encoded (see [`ToBech32`]) are displayed as
+    /// `<kind>:<pubkey>:<d-identifier>` and as url.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.to_bech32(), self) {
+            (Ok(bech32), _) => write!(f, "{bech32}"),
+            (Err(_), Self::Coordinate(coordinate)) => write!(f, "{coordinate}"),
+            (Err(_), Self::Relay(url)) => write!(f, "{url}"),
+            (Err(_), _) => Err(fmt::Error),
+        }
     }
 }
 
@@ -342,6 +882,8 @@ mod tests {
 
     use super::*;
     use crate::Result;
+    #[cfg(feature = "base")]
+    use crate::{EventBuilder, Keys};
 
     #[test]
     fn to_bech32_public_key() -> Result<()> {
@@ -367,6 +909,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn secret_key_bech32() -> Result<()> {
+        for _ in 0..32 {
+            let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+            let expected: String = bech32::encode(
+                PREFIX_BECH32_SECRET_KEY,
+                secret_key.secret_bytes().to_base32(),
+                Variant::Bech32,
+            )?;
+            assert_eq!(secret_key.to_bech32()?, expected);
+            assert_eq!(SecretKey::from_bech32(&expected)?, secret_key);
+            assert_eq!(SecretKey::from_bech32(expected.to_uppercase())?, secret_key);
+        }
+
+        let invalid = vec![
+            // Wrong prefix
+            "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy".to_string(),
+            // Wrong checksum
+            "nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk98".to_string(),
+            // Wrong length
+            bech32::encode(
+                PREFIX_BECH32_SECRET_KEY,
+                [1u8; 31].to_base32(),
+                Variant::Bech32,
+            )?,
+            // Non-zero padding
+            bech32::encode(
+                PREFIX_BECH32_SECRET_KEY,
+                vec![u5::try_from_u8(1)?; SECRET_KEY_BASE32_LEN],
+                Variant::Bech32,
+            )?,
+            // Out of range
+            bech32::encode(
+                PREFIX_BECH32_SECRET_KEY,
+                [0u8; 32].to_base32(),
+                Variant::Bech32,
+            )?,
+        ];
+        for nsec in invalid.into_iter() {
+            assert_eq!(SecretKey::from_bech32(nsec), Err(Error::Bech32SkParseError));
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "base")]
     #[test]
     fn to_bech32_note() -> Result<()> {
@@ -382,7 +968,7 @@ mod tests {
     #[cfg(feature = "base")]
     #[test]
     fn to_bech32_profile() -> Result<()> {
-        let profile = Profile::new(
+        let profile = Profile::try_new(
             XOnlyPublicKey::from_str(
                 "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
             )?,
@@ -390,7 +976,7 @@ mod tests {
                 String::from("wss://r.x.com"),
                 String::from("wss://djbas.sadkb.com"),
             ],
-        );
+        )?;
         assert_eq!("nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p".to_string(), profile.to_bech32()?);
         Ok(())
     }
@@ -409,8 +995,430 @@ mod tests {
                 "wss://r.x.com".to_string(),
                 "wss://djbas.sadkb.com".to_string()
             ],
-            profile.relays
+            profile.relay_strs()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn profile_relays_validation() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+        assert!(Profile::try_new(public_key, vec!["https://r.x.com"]).is_err());
+        assert!(Profile::try_new(public_key, vec!["r.x.com"]).is_err());
+
+        assert!(Profile::with_relays(public_key, vec![Url::parse("https://r.x.com")?]).is_err());
+
+        let profile = Profile::with_relays(public_key, vec![Url::parse("wss://R.x.com/#relay")?])?;
+        assert_eq!(profile.relay_strs(), vec!["wss://r.x.com"]);
+        assert_eq!(profile.relay_urls(), vec![Url::parse("wss://r.x.com")?]);
+        assert_eq!(Profile::from_bech32(profile.to_bech32()?)?, profile);
+
+        // Hints kept as given, only the valid ones are returned as urls
+        let profile = Profile::new(public_key, vec!["https://r.x.com", "wss://R.x.com/"]);
+        assert_eq!(
+            profile.relay_strs(),
+            vec!["https://r.x.com", "wss://R.x.com/"]
+        );
+        assert_eq!(profile.relay_urls(), vec![Url::parse("wss://r.x.com")?]);
+        assert_eq!(Profile::from_bech32(profile.to_bech32()?)?, profile);
+        let json = serde_json::to_string(&profile)?;
+        assert_eq!(serde_json::from_str::<Profile>(&json)?, profile);
+        assert_eq!(Profile::from(public_key).relays, Vec::<UncheckedUrl>::new());
+
+        let coordinate = Coordinate::new(Kind::from(30023), public_key).relay("not a url");
+        assert_eq!(
+            Coordinate::from_bech32(coordinate.to_bech32()?)?,
+            coordinate
+        );
+        assert!(matches!(
+            Url::parse("https://r.x.com")?.to_bech32(),
+            Err(Error::RelayUrl(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn invalid_relay_hints_round_trip() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+        let event_id =
+            EventId::from_hex("d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5")?;
+        let push_relays = |bytes: &mut Vec<u8>| -> Result<()> {
+            for relay in [
+                &b"https://r.x.com"[..],
+                b"not a url",
+                &[0xff, 0xfe],
+                b"wss://r.x.com",
+            ] {
+                push_tlv(bytes, RELAY, relay)?;
+            }
+            Ok(())
+        };
+
+        let mut nprofile: Vec<u8> = Vec::new();
+        push_tlv(&mut nprofile, SPECIAL, &public_key.serialize())?;
+        push_relays(&mut nprofile)?;
+
+        let mut nevent: Vec<u8> = Vec::new();
+        push_tlv(&mut nevent, SPECIAL, event_id.as_bytes())?;
+        push_relays(&mut nevent)?;
+
+        let mut naddr: Vec<u8> = Vec::new();
+        push_tlv(&mut naddr, SPECIAL, b"ipsum")?;
+        push_relays(&mut naddr)?;
+        push_tlv(&mut naddr, AUTHOR, &public_key.serialize())?;
+        push_tlv(&mut naddr, KIND, &30023u32.to_be_bytes())?;
+
+        // Invalid hints don't fail the decoding, non UTF-8 ones are dropped
+        let all: Vec<String> = vec![
+            String::from("https://r.x.com"),
+            String::from("not a url"),
+            String::from("wss://r.x.com"),
+        ];
+        for (prefix, bytes) in [
+            (PREFIX_BECH32_PROFILE, nprofile),
+            (PREFIX_BECH32_EVENT, nevent),
+            (PREFIX_BECH32_COORDINATE, naddr),
+        ] {
+            let entity =
+                Nip19::from_bech32(bech32::encode(prefix, bytes.to_base32(), Variant::Bech32)?)?;
+            let relays: Vec<String> = match &entity {
+                Nip19::Profile(profile) => profile.relay_strs(),
+                Nip19::Event(event) => event.relays().iter().map(|r| r.to_string()).collect(),
+                Nip19::Coordinate(coordinate) => {
+                    coordinate.relays.iter().map(|r| r.to_string()).collect()
+                }
+                _ => panic!("unexpected entity"),
+            };
+            assert_eq!(relays, all, "{prefix}");
+            assert_eq!(Nip19::from_bech32(entity.to_bech32()?)?, entity);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn tlv_value_too_long() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+        let long_relay = Url::parse(&format!("wss://r.x.com/{}", "a".repeat(255)))?;
+        let relay = Url::parse("wss://r.x.com")?;
+
+        // Relay hints that don't fit are skipped
+        let profile = Profile::with_relays(public_key, vec![long_relay.clone(), relay.clone()])?;
+        assert_eq!(
+            Profile::from_bech32(profile.to_bech32()?)?.relays,
+            vec![UncheckedUrl::from(relay.clone())]
+        );
+        let event_id =
+            EventId::from_hex("d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5")?;
+        let event = Nip19Event::with_relays(event_id, vec![long_relay.clone(), relay.clone()])?;
+        assert_eq!(
+            Nip19Event::from_bech32(event.to_bech32()?)?.relays(),
+            &[UncheckedUrl::from(relay.clone())]
+        );
+        let mut coordinate = Coordinate::new(Kind::from(30023), public_key);
+        coordinate.relays = vec![
+            UncheckedUrl::from(long_relay),
+            UncheckedUrl::from(relay.clone()),
+        ];
+        assert_eq!(
+            Coordinate::from_bech32(coordinate.to_bech32()?)?.relays,
+            vec![UncheckedUrl::from(relay)]
+        );
+
+        // Kinds that don't fit are skipped
+        let event = Nip19Event::from(event_id).kind(Kind::from(u64::from(u32::MAX) + 1));
+        assert_eq!(
+            Nip19Event::from_bech32(event.to_bech32()?)?.event_kind(),
+            None
+        );
+
+        let coordinate = Coordinate::new(Kind::from(30023), public_key).identifier("a".repeat(256));
+        assert_eq!(coordinate.to_bech32(), Err(Error::TLVValueTooLong(256)));
+
+        // Longest value
+        let coordinate = Coordinate::new(Kind::from(30023), public_key).identifier("a".repeat(255));
+        assert_eq!(
+            Coordinate::from_bech32(coordinate.to_bech32()?)?,
+            coordinate
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn tlv_fuzz() -> Result<()> {
+        use secp256k1::rand::{self, Rng};
+
+        use crate::Keys;
+
+        let mut rng = rand::thread_rng();
+        let prefixes = [
+            PREFIX_BECH32_PROFILE,
+            PREFIX_BECH32_EVENT,
+            PREFIX_BECH32_COORDINATE,
+            PREFIX_BECH32_RELAY,
+        ];
+
+        // Random TLV streams: never panic
+        for _ in 0..1000 {
+            let mut bytes: Vec<u8> = Vec::new();
+            for _ in 0..rng.gen_range(0..6) {
+                let t: u8 = rng.gen_range(0..5);
+                let l: usize = *[0, 4, 32, rng.gen_range(0..64)]
+                    .get(rng.gen_range(0..4))
+                    .unwrap_or(&0);
+                let value: Vec<u8> = (0..l).map(|_| rng.gen()).collect();
+                bytes.push(t);
+                // Sometimes declare a wrong length
+                bytes.push(if rng.gen_ratio(1, 10) {
+                    rng.gen()
+                } else {
+                    l as u8
+                });
+                bytes.extend(value);
+            }
+            for prefix in prefixes.iter() {
+                let s = bech32::encode(prefix, bytes.to_base32(), Variant::Bech32)?;
+                let _ = Nip19::from_bech32(s);
+            }
+        }
+
+        // Random entities: lossless round-trip
+        for _ in 0..100 {
+            let public_key = Keys::generate().public_key();
+            let event_id = EventId::from_slice(&rng.gen::<[u8; 32]>())?;
+            let relays: Vec<Url> = (0..rng.gen_range(0..4))
+                .map(|i| Url::parse(&format!("wss://relay{i}.example.com/{}", rng.gen::<u16>())))
+                .collect::<std::result::Result<_, _>>()?;
+            let kind = Kind::from(rng.gen::<u32>() as u64);
+            let identifier: String = (0..rng.gen_range(0..=255))
+                .map(|_| rng.gen_range(b'a'..=b'z') as char)
+                .collect();
+
+            let mut event = Nip19Event::with_relays(event_id, relays.clone())?;
+            if rng.gen() {
+                event = event.author(public_key).kind(kind);
+            }
+            let mut coordinate = Coordinate::new(kind, public_key).identifier(identifier);
+            for relay in relays.iter() {
+                coordinate = coordinate.relay(relay.to_string());
+            }
+            let entities = vec![
+                Nip19::Profile(Profile::with_relays(public_key, relays)?),
+                Nip19::Event(event),
+                Nip19::Coordinate(coordinate),
+            ];
+            for entity in entities.into_iter() {
+                assert_eq!(Nip19::from_bech32(entity.to_bech32()?)?, entity);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn coordinate_bech32() -> Result<()> {
+        let coordinate = Coordinate::new(
+            Kind::from(30023),
+            XOnlyPublicKey::from_str(
+                "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+            )?,
+        )
+        .identifier("ipsum")
+        .relay("wss://relay.damus.io");
+        let naddr: String = coordinate.to_bech32()?;
+        assert!(naddr.starts_with("naddr1"));
+        assert_eq!(Coordinate::from_bech32(naddr)?, coordinate);
+
+        assert_eq!(
+            Coordinate::from_bech32(coordinate.pubkey.to_bech32()?),
+            Err(Error::Bech32CoordinateParseError)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn relay_bech32() -> Result<()> {
+        let url = Url::parse("wss://relay.nostr.example")?;
+        let nrelay: String = url.to_bech32()?;
+        assert_eq!(
+            nrelay,
+            "nrelay1qqvhwumn8ghj7un9d3shjtnwdaehgu3wv4uxzmtsd3jsh6r089"
+        );
+        assert_eq!(Url::from_bech32(nrelay)?, url);
+
+        // Unknown TLV (type 7) is ignored
+        let mut bytes: Vec<u8> = vec![7, 2, 0xAB, 0xCD, SPECIAL, 25];
+        bytes.extend(b"wss://relay.nostr.example");
+        let nrelay = bech32::encode(PREFIX_BECH32_RELAY, bytes.to_base32(), Variant::Bech32)?;
+        assert_eq!(Url::from_bech32(nrelay)?, url);
+
+        assert_eq!(
+            Url::from_bech32(url.as_str()),
+            Err(Error::Bech32RelayParseError)
         );
         Ok(())
     }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn nip19_enum() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+        let coordinate = Coordinate::new(Kind::from(30023), public_key).identifier("ipsum");
+        let entities = vec![
+            Nip19::Pubkey(public_key),
+            Nip19::Secret(SecretKey::from_str(
+                "9571a568a42b9e05646a349c783159b906b498119390df9a5a02667155128028",
+            )?),
+            Nip19::EventId(EventId::from_hex(
+                "d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5",
+            )?),
+            Nip19::Profile(Profile::try_new(public_key, vec!["wss://r.x.com"])?),
+            Nip19::Event(Nip19Event::with_relays(
+                EventId::from_hex(
+                    "d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5",
+                )?,
+                vec![Url::parse("wss://r.x.com")?],
+            )?),
+            Nip19::Coordinate(coordinate),
+            Nip19::Relay(Url::parse("wss://relay.nostr.example")?),
+        ];
+        for entity in entities.into_iter() {
+            assert_eq!(Nip19::from_bech32(entity.to_bech32()?)?, entity);
+        }
+
+        let bech32 = bech32::encode("nfoo", [0u8; 32].to_base32(), Variant::Bech32)?;
+        assert_eq!(
+            Nip19::from_bech32(bech32),
+            Err(Error::UnknownPrefix(String::from("nfoo")))
+        );
+        assert!(matches!(
+            Nip19::from_bech32("not bech32"),
+            Err(Error::Bech32(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn test_from_str_display() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+        let profile = Profile::try_new(public_key, vec!["wss://r.x.com"])?;
+        let nprofile: String = profile.to_bech32()?;
+        assert_eq!(profile.to_string(), nprofile);
+        assert_eq!(Profile::from_str(&nprofile)?, profile);
+
+        let event = Nip19Event::try_new(
+            EventId::from_hex("d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5")?,
+            vec!["wss://r.x.com"],
+        )?;
+        assert_eq!(event.to_string(), event.to_bech32()?);
+        assert_eq!(event.to_bech32()?.parse::<Nip19Event>()?, event);
+
+        let entity: Nip19 = nprofile.parse()?;
+        assert_eq!(entity, Nip19::Profile(profile));
+        assert_eq!(entity.to_bech32()?, nprofile);
+        assert_eq!(entity.to_string(), nprofile);
+        assert!(Nip19Event::from_str(&nprofile).is_err());
+
+        // Not encodable: plain form
+        let coordinate = Coordinate::new(Kind::from(30023), public_key).identifier("a".repeat(256));
+        assert_eq!(
+            Nip19::Coordinate(coordinate.clone()).to_string(),
+            coordinate.to_string()
+        );
+        let url = Url::parse("https://r.x.com")?;
+        assert_eq!(Nip19::Relay(url.clone()).to_string(), url.to_string());
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn test_nevent_author_and_kind() -> Result<()> {
+        let event_id =
+            EventId::from_hex("d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5")?;
+        let author = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )?;
+
+        let event = Nip19Event::try_new(event_id, vec!["wss://r.x.com"])?
+            .author(author)
+            .kind(Kind::TextNote);
+        assert_eq!(Nip19Event::from_bech32(event.to_bech32()?)?, event);
+
+        // Without the optional fields
+        let event = Nip19Event::from(event_id);
+        assert_eq!(Nip19Event::from_bech32(event.to_bech32()?)?, event);
+
+        // Unknown TLVs are ignored
+        let mut bytes: Vec<u8> = vec![SPECIAL, 32];
+        bytes.extend(event_id.inner().iter());
+        bytes.extend([42, 3, 1, 2, 3]);
+        let bech32 = bech32::encode(PREFIX_BECH32_EVENT, bytes.to_base32(), Variant::Bech32)?;
+        assert_eq!(Nip19Event::from_bech32(bech32)?, event);
+
+        // From event
+        let keys = Keys::generate();
+        let event: Event = EventBuilder::new_text_note("GM", &[]).to_event(&keys)?;
+        let pointer = Nip19Event::from_event(&event, vec![Url::parse("wss://r.x.com")?])?;
+        assert_eq!(pointer.event_id(), event.id);
+        assert_eq!(pointer.event_author(), Some(keys.public_key()));
+        assert_eq!(pointer.event_kind(), Some(Kind::TextNote));
+        assert_eq!(pointer.relays(), [UncheckedUrl::from("wss://r.x.com")]);
+        assert_eq!(pointer.relay_urls(), [Url::parse("wss://r.x.com")?]);
+        assert_eq!(Nip19Event::from_bech32(pointer.to_bech32()?)?, pointer);
+        assert!(Nip19Event::from_event(&event, vec![Url::parse("https://r.x.com")?]).is_err());
+        assert!(Nip19Event::from(event).relays().is_empty());
+
+        // Missing event id
+        let bech32 = bech32::encode(
+            PREFIX_BECH32_EVENT,
+            [KIND, 4, 0, 0, 0, 1].to_base32(),
+            Variant::Bech32,
+        )?;
+        assert_eq!(
+            Nip19Event::from_bech32(bech32),
+            Err(Error::Bech32EventParseError)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "base")]
+    #[test]
+    fn test_vectors() -> Result<()> {
+        use crate::test_vectors::nip19;
+
+        let public_key = XOnlyPublicKey::from_str(nip19::PUBLIC_KEY)?;
+        assert_eq!(public_key.to_bech32()?, nip19::NPUB);
+        assert_eq!(XOnlyPublicKey::from_bech32(nip19::NPUB)?, public_key);
+
+        let secret_key = SecretKey::from_str(nip19::SECRET_KEY)?;
+        assert_eq!(secret_key.to_bech32()?, nip19::NSEC);
+        assert_eq!(SecretKey::from_bech32(nip19::NSEC)?, secret_key);
+
+        let event_id = EventId::from_hex(nip19::EVENT_ID)?;
+        assert_eq!(event_id.to_bech32()?, nip19::NOTE);
+        assert_eq!(EventId::from_bech32(nip19::NOTE)?, event_id);
+
+        let profile = Profile::try_new(
+            XOnlyPublicKey::from_str(nip19::PROFILE_PUBLIC_KEY)?,
+            nip19::PROFILE_RELAYS.to_vec(),
+        )?;
+        assert_eq!(profile.to_bech32()?, nip19::NPROFILE);
+        assert_eq!(Profile::from_bech32(nip19::NPROFILE)?, profile);
+        Ok(())
+    }
 }
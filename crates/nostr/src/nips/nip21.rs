@@ -0,0 +1,168 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP21
+//!
+//! `nostr:` URI scheme: a NIP-19 entity prefixed by `nostr:`. Secret keys (`nsec`) are not allowed.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/21.md
+
+use secp256k1::XOnlyPublicKey;
+
+use super::nip19::{self, FromBech32, Nip19, Nip19Event, ToBech32};
+use crate::event::id::EventId;
+use crate::{Coordinate, Profile};
+
+/// URI scheme
+pub const SCHEME: &str = "nostr";
+
+/// `NIP21` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Missing or invalid scheme
+    #[error("invalid nostr URI scheme")]
+    InvalidScheme,
+    /// Secret keys are not allowed in URIs
+    #[error("secret keys are not allowed in nostr URIs")]
+    SecretKey,
+    /// NIP19 error
+    #[error(transparent)]
+    NIP19(#[from] nip19::Error),
+}
+
+fn split_uri(uri: &str) -> Result<&str, Error> {
+    match uri.split_once(':') {
+        Some((scheme, data)) if scheme.eq_ignore_ascii_case(SCHEME) => Ok(data),
+        _ => Err(Error::InvalidScheme),
+    }
+}
+
+/// Parse and generate `nostr:` URIs
+pub trait NostrURI: Sized + FromBech32<Err = nip19::Error> + ToBech32<Err = nip19::Error> {
+    /// Get `nostr:` URI
+    fn to_nostr_uri(&self) -> Result<String, Error> {
+        Ok(format!("{SCHEME}:{}", self.to_bech32()?))
+    }
+
+    /// Parse `nostr:` URI
+    fn from_nostr_uri<S>(uri: S) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        Ok(Self::from_bech32(split_uri(uri.as_ref())?)?)
+    }
+}
+
+impl NostrURI for XOnlyPublicKey {}
+impl NostrURI for EventId {}
+impl NostrURI for Profile {}
+impl NostrURI for Nip19Event {}
+impl NostrURI for Coordinate {}
+
+impl NostrURI for Nip19 {
+    fn to_nostr_uri(&self) -> Result<String, Error> {
+        if let Self::Secret(..) = self {
+            return Err(Error::SecretKey);
+        }
+        Ok(format!("{SCHEME}:{}", self.to_bech32()?))
+    }
+
+    fn from_nostr_uri<S>(uri: S) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        match Self::from_bech32(split_uri(uri.as_ref())?)? {
+            Self::Secret(..) => Err(Error::SecretKey),
+            entity => Ok(entity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use secp256k1::SecretKey;
+
+    use super::*;
+    use crate::Kind;
+
+    #[test]
+    fn test_nostr_uri() {
+        let public_key = XOnlyPublicKey::from_str(
+            "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e",
+        )
+        .unwrap();
+        let uri = "nostr:npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        assert_eq!(public_key.to_nostr_uri().unwrap(), uri);
+        assert_eq!(XOnlyPublicKey::from_nostr_uri(uri).unwrap(), public_key);
+        assert_eq!(
+            Nip19::from_nostr_uri(uri).unwrap(),
+            Nip19::Pubkey(public_key)
+        );
+
+        let event_id =
+            EventId::from_hex("d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5")
+                .unwrap();
+        let entities = vec![
+            Nip19::EventId(event_id),
+            Nip19::Profile(Profile::try_new(public_key, vec!["wss://r.x.com"]).unwrap()),
+            Nip19::Event(Nip19Event::try_new(event_id, vec!["wss://r.x.com"]).unwrap()),
+            Nip19::Coordinate(Coordinate::new(Kind::from(30023), public_key).identifier("ipsum")),
+        ];
+        for entity in entities.into_iter() {
+            let uri: String = entity.to_nostr_uri().unwrap();
+            assert!(uri.starts_with("nostr:"));
+            assert_eq!(Nip19::from_nostr_uri(uri).unwrap(), entity);
+        }
+    }
+
+    #[test]
+    fn test_vectors() {
+        use crate::test_vectors::{nip19, nip21};
+
+        let public_key = XOnlyPublicKey::from_str(nip21::PUBLIC_KEY).unwrap();
+        assert_eq!(public_key.to_nostr_uri().unwrap(), nip21::NPUB_URI);
+        assert_eq!(
+            XOnlyPublicKey::from_nostr_uri(nip21::NPUB_URI).unwrap(),
+            public_key
+        );
+
+        let profile = Profile::try_new(
+            XOnlyPublicKey::from_str(nip19::PROFILE_PUBLIC_KEY).unwrap(),
+            nip19::PROFILE_RELAYS.to_vec(),
+        )
+        .unwrap();
+        assert_eq!(profile.to_nostr_uri().unwrap(), nip21::NPROFILE_URI);
+        assert_eq!(
+            Profile::from_nostr_uri(nip21::NPROFILE_URI).unwrap(),
+            profile
+        );
+    }
+
+    #[test]
+    fn test_invalid_nostr_uri() {
+        let secret_key =
+            SecretKey::from_str("9571a568a42b9e05646a349c783159b906b498119390df9a5a02667155128028")
+                .unwrap();
+        assert_eq!(
+            Nip19::Secret(secret_key).to_nostr_uri(),
+            Err(Error::SecretKey)
+        );
+        let uri = format!("nostr:{}", secret_key.to_bech32().unwrap());
+        assert_eq!(Nip19::from_nostr_uri(uri), Err(Error::SecretKey));
+
+        assert_eq!(
+            XOnlyPublicKey::from_nostr_uri(
+                "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg"
+            ),
+            Err(Error::InvalidScheme)
+        );
+        assert_eq!(
+            EventId::from_nostr_uri(
+                "nostr:npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg"
+            ),
+            Err(Error::NIP19(nip19::Error::Bech32NoteParseError))
+        );
+    }
+}
@@ -5,12 +5,16 @@
 //!
 //! https://github.com/nostr-protocol/nips/blob/master/26.md
 
-use bitcoin::hashes::sha256::Hash as Sha256Hash;
-use bitcoin::hashes::Hash;
-use bitcoin::secp256k1::schnorr::Signature;
-use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::Hash;
+use secp256k1::schnorr::Signature;
+use secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
 
+#[cfg(feature = "base")]
+use crate::event::Tag;
 use crate::key::{self, Keys};
+#[cfg(feature = "base")]
+use crate::Timestamp;
 
 /// `NIP26` error
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
@@ -20,7 +24,7 @@ pub enum Error {
     Key(#[from] key::Error),
     #[error(transparent)]
     /// Secp256k1 error
-    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    Secp256k1(#[from] secp256k1::Error),
 }
 
 /// Sign delegation
@@ -36,3 +40,113 @@ pub fn sign_delegation(
     let message = Message::from_slice(&hashed_token)?;
     Ok(secp.sign_schnorr(&message, keypair))
 }
+
+/// Verify delegation signature
+pub fn verify_delegation_signature(
+    delegator_pk: XOnlyPublicKey,
+    signature: &Signature,
+    delegatee_pk: XOnlyPublicKey,
+    conditions: String,
+) -> Result<(), Error> {
+    let secp = Secp256k1::verification_only();
+    let unhashed_token: String = format!("nostr:delegation:{delegatee_pk}:{conditions}");
+    let hashed_token = Sha256Hash::hash(unhashed_token.as_bytes());
+    let message = Message::from_slice(&hashed_token)?;
+    Ok(secp.verify_schnorr(signature, &message, &delegator_pk)?)
+}
+
+/// Create delegation tag
+///
+/// The delegatee is allowed to publish events on behalf of the delegator from `since`.
+#[cfg(feature = "base")]
+pub fn create_delegation_tag(
+    delegator_keys: &Keys,
+    delegatee_pk: XOnlyPublicKey,
+    since: Timestamp,
+) -> Result<Tag, Error> {
+    let conditions: String = format!("created_at>{since}");
+    let sig: Signature = sign_delegation(delegator_keys, delegatee_pk, conditions.clone())?;
+    Ok(Tag::Delegation {
+        delegator_pk: delegator_keys.public_key(),
+        conditions,
+        sig,
+    })
+}
+
+#[cfg(all(test, feature = "base"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_delegation_tag() {
+        let delegator_keys = Keys::generate();
+        let delegatee_keys = Keys::generate();
+        let tag = create_delegation_tag(
+            &delegator_keys,
+            delegatee_keys.public_key(),
+            Timestamp::from(1676067553),
+        )
+        .unwrap();
+
+        match tag {
+            Tag::Delegation {
+                delegator_pk,
+                conditions,
+                sig,
+            } => {
+                assert_eq!(delegator_pk, delegator_keys.public_key());
+                assert_eq!(conditions, "created_at>1676067553");
+                assert!(verify_delegation_signature(
+                    delegator_pk,
+                    &sig,
+                    delegatee_keys.public_key(),
+                    conditions.clone()
+                )
+                .is_ok());
+                assert!(verify_delegation_signature(
+                    delegator_pk,
+                    &sig,
+                    delegator_keys.public_key(),
+                    conditions
+                )
+                .is_err());
+            }
+            _ => panic!("Expected delegation tag"),
+        }
+    }
+
+    #[test]
+    fn test_vectors() {
+        use std::str::FromStr;
+
+        use secp256k1::SecretKey;
+
+        use crate::test_vectors::nip26;
+
+        let delegator_keys = Keys::new(SecretKey::from_str(nip26::DELEGATOR_SECRET_KEY).unwrap());
+        let delegator_pk = XOnlyPublicKey::from_str(nip26::DELEGATOR_PUBLIC_KEY).unwrap();
+        assert_eq!(delegator_keys.public_key(), delegator_pk);
+        let delegatee_pk = XOnlyPublicKey::from_str(nip26::DELEGATEE_PUBLIC_KEY).unwrap();
+        let token = Signature::from_str(nip26::TOKEN).unwrap();
+        let conditions = String::from(nip26::CONDITIONS);
+
+        assert!(verify_delegation_signature(
+            delegator_pk,
+            &token,
+            delegatee_pk,
+            conditions.clone()
+        )
+        .is_ok());
+        assert!(verify_delegation_signature(
+            delegator_pk,
+            &token,
+            delegatee_pk,
+            String::from("kind=1")
+        )
+        .is_err());
+
+        // Schnorr signatures are randomized: check that a new token verifies too
+        let sig = sign_delegation(&delegator_keys, delegatee_pk, conditions.clone()).unwrap();
+        assert!(verify_delegation_signature(delegator_pk, &sig, delegatee_pk, conditions).is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP27
+//!
+//! Mentions of NIP-19 entities in the event content, as `nostr:` URIs (NIP-21) or bare bech32.
+//! Secret keys (`nsec`) are never extracted.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/27.md
+
+use std::ops::Range;
+
+use super::nip19::{
+    FromBech32, Nip19, PREFIX_BECH32_COORDINATE, PREFIX_BECH32_EVENT, PREFIX_BECH32_NOTE_ID,
+    PREFIX_BECH32_PROFILE, PREFIX_BECH32_PUBLIC_KEY, PREFIX_BECH32_RELAY,
+};
+use super::nip21::SCHEME;
+use crate::Event;
+
+const PREFIXES: [&str; 6] = [
+    PREFIX_BECH32_PUBLIC_KEY,
+    PREFIX_BECH32_NOTE_ID,
+    PREFIX_BECH32_PROFILE,
+    PREFIX_BECH32_EVENT,
+    PREFIX_BECH32_COORDINATE,
+    PREFIX_BECH32_RELAY,
+];
+
+/// Mention of a NIP-19 entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    /// Byte range in the content, including the `nostr:` scheme if any
+    pub range: Range<usize>,
+    /// Entity
+    pub entity: Nip19,
+    /// `true` for `nostr:` URIs, `false` for bare bech32
+    pub uri: bool,
+}
+
+/// Check if a mention can start at `index`: not glued to a previous word
+fn is_boundary(bytes: &[u8], index: usize) -> bool {
+    index == 0 || !bytes[index - 1].is_ascii_alphanumeric()
+}
+
+fn has_known_prefix(token: &str) -> bool {
+    match token.rsplit_once('1') {
+        Some((hrp, _)) => PREFIXES
+            .iter()
+            .any(|prefix| hrp.eq_ignore_ascii_case(prefix)),
+        None => false,
+    }
+}
+
+/// Extract the mentions of `content`, in order of appearance
+pub fn extract_mentions(content: &str) -> Vec<Mention> {
+    let bytes: &[u8] = content.as_bytes();
+    let scheme: &[u8] = SCHEME.as_bytes();
+    let mut mentions: Vec<Mention> = Vec::new();
+
+    let mut index: usize = 0;
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_alphabetic() || !is_boundary(bytes, index) {
+            index += 1;
+            continue;
+        }
+
+        let uri: bool = bytes.len() > index + scheme.len()
+            && bytes[index..index + scheme.len()].eq_ignore_ascii_case(scheme)
+            && bytes[index + scheme.len()] == b':';
+        let start: usize = if uri { index + scheme.len() + 1 } else { index };
+        let end: usize = start
+            + bytes[start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+
+        // Bech32 is ASCII only: `start` and `end` are char boundaries
+        let token: &str = &content[start..end];
+        if has_known_prefix(token) {
+            if let Ok(entity) = Nip19::from_bech32(token) {
+                mentions.push(Mention {
+                    range: index..end,
+                    entity,
+                    uri,
+                });
+                index = end;
+                continue;
+            }
+        }
+
+        index = if uri { start } else { end.max(index + 1) };
+    }
+
+    mentions
+}
+
+impl Event {
+    /// Mentions of NIP-19 entities in the content
+    pub fn mentions(&self) -> Vec<Mention> {
+        extract_mentions(&self.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use secp256k1::{SecretKey, XOnlyPublicKey};
+
+    use super::*;
+    use crate::nips::nip19::{Nip19Event, ToBech32};
+    use crate::EventId;
+
+    const NPUB: &str = "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy";
+    const NOTE: &str = "note1m99r7nwc0wdrkzldrqan96gklg5usqspq7z9696j6unf0ljnpxjspqfw99";
+
+    #[test]
+    fn test_extract_mentions() {
+        let public_key = XOnlyPublicKey::from_bech32(NPUB).unwrap();
+        let event_id = EventId::from_bech32(NOTE).unwrap();
+        let nevent: String = Nip19Event::try_new(event_id, vec!["wss://r.x.com"])
+            .unwrap()
+            .to_bech32()
+            .unwrap();
+
+        let content =
+            format!("nostr:{NPUB} said (nostr:{NOTE}), see {nevent}.\nGM ☕ nostr:{NPUB}");
+        let mentions = extract_mentions(&content);
+        assert_eq!(mentions.len(), 4);
+
+        assert_eq!(mentions[0].range, 0..6 + NPUB.len());
+        assert_eq!(mentions[0].entity, Nip19::Pubkey(public_key));
+        assert!(mentions[0].uri);
+
+        assert_eq!(&content[mentions[1].range.clone()], format!("nostr:{NOTE}"));
+        assert_eq!(mentions[1].entity, Nip19::EventId(event_id));
+
+        assert_eq!(&content[mentions[2].range.clone()], nevent);
+        assert!(!mentions[2].uri);
+
+        assert_eq!(mentions[3].range.end, content.len());
+        assert_eq!(mentions[3].entity, Nip19::Pubkey(public_key));
+    }
+
+    #[test]
+    fn test_ignored_mentions() {
+        let nsec: String =
+            SecretKey::from_str("9571a568a42b9e05646a349c783159b906b498119390df9a5a02667155128028")
+                .unwrap()
+                .to_bech32()
+                .unwrap();
+        let content = format!("nostr:{nsec} {nsec} x{NPUB} {NPUB}x nostr:npub1invalid nostr: npub");
+        assert!(extract_mentions(&content).is_empty());
+        assert!(extract_mentions("").is_empty());
+    }
+}
@@ -0,0 +1,418 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP29
+//!
+//! Relay-based groups: events of a group carry its id in the `h` tag and are sent to the group relay.
+//! Group state (metadata, admins and members) is published by the relay in the 39000s kinds.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/29.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use crate::event::{Event, EventBuilder, EventId, Kind, Tag, TagKind};
+use crate::types::relay_url;
+
+/// `NIP29` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Missing group id (`d` tag)
+    #[error("missing group id")]
+    MissingGroupId,
+    /// Invalid group identifier
+    #[error("invalid group identifier")]
+    InvalidGroupId,
+}
+
+/// Group identifier: group relay and group id (`<host>'<id>`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupId {
+    /// Group relay
+    pub relay_url: Url,
+    /// Group id (`h` tag)
+    pub id: String,
+}
+
+impl GroupId {
+    /// New [`GroupId`]
+    pub fn new<S>(relay_url: Url, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            relay_url: relay_url::normalize(relay_url),
+            id: id.into(),
+        }
+    }
+}
+
+impl FromStr for GroupId {
+    type Err = Error;
+
+    /// Parse `<host>'<id>` (the host is a `wss` relay) or `<relay url>'<id>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, id) = s.rsplit_once('\'').ok_or(Error::InvalidGroupId)?;
+        if id.is_empty() {
+            return Err(Error::InvalidGroupId);
+        }
+        let relay_url: Url = if host.contains("://") {
+            relay_url::parse(host)
+        } else {
+            relay_url::parse(format!("wss://{host}"))
+        }
+        .map_err(|_| Error::InvalidGroupId)?;
+        Ok(Self::new(relay_url, id))
+    }
+}
+
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let host: &str = self.relay_url.host_str().unwrap_or_default();
+        match self.relay_url.port() {
+            Some(port) => write!(f, "{host}:{port}'{}", self.id),
+            None => write!(f, "{host}'{}", self.id),
+        }
+    }
+}
+
+/// Group metadata (kind 39000, published by the relay)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMetadata {
+    /// Group id
+    pub id: String,
+    /// Name
+    pub name: Option<String>,
+    /// Picture url
+    pub picture: Option<String>,
+    /// Description
+    pub about: Option<String>,
+    /// Only members can read the group messages
+    pub private: bool,
+    /// Join requests are ignored (invite only)
+    pub closed: bool,
+}
+
+impl GroupMetadata {
+    /// New [`GroupMetadata`] of public and open group `id`
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse group metadata [`Event`]
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::GroupMetadata {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut metadata = Self::new(group_id_of(event).ok_or(Error::MissingGroupId)?);
+        for tag in event.tags.iter() {
+            match tag.as_vec().as_slice() {
+                [kind, value, ..] if kind == "name" => metadata.name = Some(value.clone()),
+                [kind, value, ..] if kind == "picture" => metadata.picture = Some(value.clone()),
+                [kind, value, ..] if kind == "about" => metadata.about = Some(value.clone()),
+                [kind] if kind == "private" => metadata.private = true,
+                [kind] if kind == "closed" => metadata.closed = true,
+                _ => (),
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+/// Group admin, with its roles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupAdmin {
+    /// Public key
+    pub public_key: XOnlyPublicKey,
+    /// Roles (ex. `moderator`)
+    pub roles: Vec<String>,
+}
+
+/// Parse group admins [`Event`] (kind 39001)
+pub fn group_admins(event: &Event) -> Result<Vec<GroupAdmin>, Error> {
+    if event.kind != Kind::GroupAdmins {
+        return Err(Error::UnexpectedKind);
+    }
+    Ok(event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_vec().as_slice() {
+            [kind, public_key, roles @ ..] if kind == "p" => Some(GroupAdmin {
+                public_key: XOnlyPublicKey::from_str(public_key).ok()?,
+                roles: roles.to_vec(),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Parse group members [`Event`] (kind 39002)
+pub fn group_members(event: &Event) -> Result<Vec<XOnlyPublicKey>, Error> {
+    if event.kind != Kind::GroupMembers {
+        return Err(Error::UnexpectedKind);
+    }
+    Ok(event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_vec().as_slice() {
+            [kind, public_key, ..] if kind == "p" => XOnlyPublicKey::from_str(public_key).ok(),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Get group id of `event`: `h` tag of group events, `d` tag of the relay-generated ones
+pub fn group_id_of(event: &Event) -> Option<String> {
+    let name: &str = if event.kind.is_parameterized_replaceable() {
+        "d"
+    } else {
+        "h"
+    };
+    event
+        .tags
+        .iter()
+        .find_map(|tag| match tag.as_vec().as_slice() {
+            [kind, id, ..] if kind == name => Some(id.clone()),
+            _ => None,
+        })
+}
+
+fn group_tag(group_id: &str) -> Tag {
+    Tag::Generic(TagKind::H, vec![group_id.to_string()])
+}
+
+fn flag_tag(flag: &str) -> Tag {
+    Tag::Generic(TagKind::Custom(flag.to_string()), Vec::new())
+}
+
+impl EventBuilder {
+    /// Group chat message
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_chat_message<S>(group_id: &str, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Kind::ChatMessage, content, &[group_tag(group_id)])
+    }
+
+    /// Request to join a group, with an optional invite code
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_join_request<S>(group_id: &str, invite_code: Option<&str>, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut tags: Vec<Tag> = vec![group_tag(group_id)];
+        if let Some(code) = invite_code {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("code")),
+                vec![code.to_string()],
+            ));
+        }
+        Self::new(Kind::GroupJoinRequest, reason, &tags)
+    }
+
+    /// Request to leave a group
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_leave_request<S>(group_id: &str, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Kind::GroupLeaveRequest, reason, &[group_tag(group_id)])
+    }
+
+    /// Add a user to a group, or change its roles (admins only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_put_user(group_id: &str, public_key: XOnlyPublicKey, roles: &[&str]) -> Self {
+        let mut values: Vec<String> = vec![public_key.to_string()];
+        values.extend(roles.iter().map(|role| role.to_string()));
+        let tags = vec![group_tag(group_id), Tag::Generic(TagKind::P, values)];
+        Self::new(Kind::GroupPutUser, "", &tags)
+    }
+
+    /// Remove a user from a group (admins only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_remove_user(group_id: &str, public_key: XOnlyPublicKey) -> Self {
+        let tags = vec![
+            group_tag(group_id),
+            Tag::Generic(TagKind::P, vec![public_key.to_string()]),
+        ];
+        Self::new(Kind::GroupRemoveUser, "", &tags)
+    }
+
+    /// Edit group metadata (admins only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_edit_metadata(metadata: &GroupMetadata) -> Self {
+        let mut tags: Vec<Tag> = vec![group_tag(&metadata.id)];
+        let fields = [
+            ("name", &metadata.name),
+            ("picture", &metadata.picture),
+            ("about", &metadata.about),
+        ];
+        for (name, value) in fields.into_iter() {
+            if let Some(value) = value {
+                tags.push(Tag::Generic(
+                    TagKind::Custom(name.to_string()),
+                    vec![value.clone()],
+                ));
+            }
+        }
+        tags.push(flag_tag(if metadata.private {
+            "private"
+        } else {
+            "public"
+        }));
+        tags.push(flag_tag(if metadata.closed { "closed" } else { "open" }));
+        Self::new(Kind::GroupEditMetadata, "", &tags)
+    }
+
+    /// Delete an event of a group (admins only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_delete_event(group_id: &str, event_id: EventId) -> Self {
+        let tags = vec![group_tag(group_id), Tag::Event(event_id, None, None)];
+        Self::new(Kind::GroupDeleteEvent, "", &tags)
+    }
+
+    /// Create a group
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_create(group_id: &str) -> Self {
+        Self::new(Kind::GroupCreate, "", &[group_tag(group_id)])
+    }
+
+    /// Delete a group (admins only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_delete(group_id: &str) -> Self {
+        Self::new(Kind::GroupDelete, "", &[group_tag(group_id)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keys, SubscriptionFilter};
+
+    #[test]
+    fn test_group_id() {
+        let group = GroupId::from_str("groups.nostr.com'abcdef").unwrap();
+        assert_eq!(group.relay_url.as_str(), "wss://groups.nostr.com/");
+        assert_eq!(group.id, "abcdef");
+        assert_eq!(group.to_string(), "groups.nostr.com'abcdef");
+
+        let group = GroupId::from_str("ws://localhost:7777'x").unwrap();
+        assert_eq!(group.relay_url.as_str(), "ws://localhost:7777/");
+        assert_eq!(group.to_string(), "localhost:7777'x");
+
+        assert_eq!(GroupId::from_str("abcdef"), Err(Error::InvalidGroupId));
+        assert_eq!(
+            GroupId::from_str("groups.nostr.com'"),
+            Err(Error::InvalidGroupId)
+        );
+    }
+
+    #[test]
+    fn test_group_events() {
+        let keys = Keys::generate();
+        let event = EventBuilder::group_chat_message("abcdef", "GM")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::ChatMessage);
+        assert_eq!(group_id_of(&event).as_deref(), Some("abcdef"));
+        assert!(SubscriptionFilter::new()
+            .group("abcdef")
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new().group("other").match_event(&event));
+
+        let event = EventBuilder::group_join_request("abcdef", Some("code123"), "hi")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::GroupJoinRequest);
+        assert!(event
+            .tags
+            .iter()
+            .any(|t| t.as_vec() == vec!["code", "code123"]));
+
+        let member = Keys::generate().public_key();
+        let event = EventBuilder::group_put_user("abcdef", member, &["moderator"])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind.as_u64(), 9000);
+        assert!(event.tags.iter().any(|t| t.as_vec()
+            == vec![
+                String::from("p"),
+                member.to_string(),
+                String::from("moderator")
+            ]));
+    }
+
+    #[test]
+    fn test_group_state() {
+        let keys = Keys::generate();
+        let metadata = GroupMetadata {
+            name: Some(String::from("Nostr devs")),
+            about: Some(String::from("Protocol talk")),
+            private: true,
+            ..GroupMetadata::new("abcdef")
+        };
+
+        // Relay-generated metadata: same tags of the edit, with the `d` tag
+        let edit = EventBuilder::group_edit_metadata(&metadata)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(edit.kind, Kind::GroupEditMetadata);
+        let mut tags: Vec<Tag> = vec![Tag::Generic(TagKind::D, vec![String::from("abcdef")])];
+        tags.extend(edit.tags.into_iter().skip(1));
+        let event = EventBuilder::new(Kind::GroupMetadata, "", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(GroupMetadata::from_event(&event).unwrap(), metadata);
+        assert_eq!(group_id_of(&event).as_deref(), Some("abcdef"));
+
+        let admin = Keys::generate().public_key();
+        let event = EventBuilder::new(
+            Kind::GroupAdmins,
+            "",
+            &[
+                Tag::Generic(TagKind::D, vec![String::from("abcdef")]),
+                Tag::Generic(
+                    TagKind::P,
+                    vec![admin.to_string(), String::from("moderator")],
+                ),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(
+            group_admins(&event).unwrap(),
+            vec![GroupAdmin {
+                public_key: admin,
+                roles: vec![String::from("moderator")]
+            }]
+        );
+        assert_eq!(group_members(&event), Err(Error::UnexpectedKind));
+        assert_eq!(
+            GroupMetadata::from_event(&event),
+            Err(Error::UnexpectedKind)
+        );
+    }
+}
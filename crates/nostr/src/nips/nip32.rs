@@ -0,0 +1,262 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP32
+//!
+//! Relay reviews: labels (kind 1985) of relays in the `review` namespace, with a quality
+//! between 0 and 1 in the label metadata.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/32.md
+
+use std::collections::HashMap;
+
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::event::{Event, EventBuilder, Kind, Tag, TagKind};
+use crate::types::relay_url;
+use crate::Timestamp;
+
+/// Label namespace of reviews
+pub const REVIEW_NAMESPACE: &str = "review";
+/// Label of relay reviews
+pub const RELAY_LABEL: &str = "relay";
+
+/// `NIP32` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Label event that isn't a relay review
+    #[error("not a relay review")]
+    NotRelayReview,
+    /// Missing or invalid relay url
+    #[error("invalid relay url")]
+    InvalidRelayUrl,
+    /// Missing quality or not between 0 and 1
+    #[error("invalid quality")]
+    InvalidQuality,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReviewMetadata {
+    quality: f64,
+}
+
+/// Relay review
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayReview {
+    /// Relay url
+    pub relay_url: Url,
+    /// Quality, from 0 (worst) to 1 (best)
+    pub quality: f64,
+    /// Review text
+    pub content: String,
+}
+
+impl RelayReview {
+    /// New [`RelayReview`], with `quality` clamped between 0 and 1
+    pub fn new(relay_url: Url, quality: f64) -> Self {
+        Self {
+            relay_url: relay_url::normalize(relay_url),
+            quality: quality.clamp(0.0, 1.0),
+            content: String::new(),
+        }
+    }
+
+    /// Set review text
+    pub fn content<S>(self, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            content: content.into(),
+            ..self
+        }
+    }
+
+    /// Parse relay review [`Event`]
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Label {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut is_review: bool = false;
+        let mut quality: Option<f64> = None;
+        let mut relay: Option<Url> = None;
+        for tag in event.tags.iter() {
+            match tag.as_vec().as_slice() {
+                [kind, label, namespace, rest @ ..]
+                    if kind == "l" && label == RELAY_LABEL && namespace == REVIEW_NAMESPACE =>
+                {
+                    is_review = true;
+                    quality = rest
+                        .first()
+                        .and_then(|metadata| serde_json::from_str::<ReviewMetadata>(metadata).ok())
+                        .map(|m| m.quality);
+                }
+                [kind, url, ..] if kind == "r" => relay = relay_url::parse(url).ok(),
+                _ => (),
+            }
+        }
+
+        if !is_review {
+            return Err(Error::NotRelayReview);
+        }
+
+        let quality: f64 = quality
+            .filter(|q| (0.0..=1.0).contains(q))
+            .ok_or(Error::InvalidQuality)?;
+        Ok(Self {
+            relay_url: relay.ok_or(Error::InvalidRelayUrl)?,
+            quality,
+            content: event.content.clone(),
+        })
+    }
+}
+
+impl EventBuilder {
+    /// Relay review
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/32.md>
+    pub fn relay_review(review: RelayReview) -> Self {
+        let metadata = ReviewMetadata {
+            quality: review.quality,
+        };
+        let tags = vec![
+            Tag::Generic(
+                TagKind::Custom(String::from("L")),
+                vec![String::from(REVIEW_NAMESPACE)],
+            ),
+            Tag::Generic(
+                TagKind::Custom(String::from("l")),
+                vec![
+                    String::from(RELAY_LABEL),
+                    String::from(REVIEW_NAMESPACE),
+                    serde_json::json!(metadata).to_string(),
+                ],
+            ),
+            Tag::Generic(
+                TagKind::Custom(String::from("r")),
+                vec![relay_url::to_string(&review.relay_url)],
+            ),
+        ];
+        Self::new(Kind::Label, review.content, &tags)
+    }
+}
+
+/// Community rating of a relay
+///
+/// Only the latest review of each author counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayRating {
+    /// Relay url
+    pub relay_url: Url,
+    reviews: HashMap<XOnlyPublicKey, (Timestamp, f64)>,
+}
+
+impl RelayRating {
+    /// New empty [`RelayRating`]
+    pub fn new(relay_url: Url) -> Self {
+        Self {
+            relay_url: relay_url::normalize(relay_url),
+            reviews: HashMap::new(),
+        }
+    }
+
+    /// Add review [`Event`]
+    ///
+    /// Return `false` if the event is not a valid review of the relay or if the author
+    /// already has a newer review.
+    pub fn ingest(&mut self, event: &Event) -> bool {
+        let review: RelayReview = match RelayReview::from_event(event) {
+            Ok(review) if review.relay_url == self.relay_url => review,
+            _ => return false,
+        };
+        match self.reviews.get(&event.pubkey) {
+            Some((created_at, _)) if *created_at >= event.created_at => false,
+            _ => {
+                self.reviews
+                    .insert(event.pubkey, (event.created_at, review.quality));
+                true
+            }
+        }
+    }
+
+    /// Number of reviewers
+    pub fn reviews(&self) -> usize {
+        self.reviews.len()
+    }
+
+    /// Average quality, if reviewed
+    pub fn average(&self) -> Option<f64> {
+        if self.reviews.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.reviews.values().map(|(_, quality)| quality).sum();
+        Some(sum / self.reviews.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_relay_review() {
+        let keys = Keys::generate();
+        let url = Url::parse("wss://relay.damus.io").unwrap();
+        let review = RelayReview::new(url.clone(), 0.8).content("Fast and reliable");
+        let event = EventBuilder::relay_review(review.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::Label);
+        assert_eq!(
+            event.tags[1].as_vec(),
+            vec!["l", "relay", "review", r#"{"quality":0.8}"#]
+        );
+        assert_eq!(RelayReview::from_event(&event).unwrap(), review);
+
+        assert_eq!(RelayReview::new(url, 1.5).quality, 1.0);
+
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(matches!(
+            RelayReview::from_event(&note),
+            Err(Error::UnexpectedKind)
+        ));
+    }
+
+    #[test]
+    fn test_relay_rating() {
+        let url = Url::parse("wss://relay.damus.io").unwrap();
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let review = |keys: &Keys, quality: f64, created_at: u64| {
+            let mut event = EventBuilder::relay_review(RelayReview::new(url.clone(), quality))
+                .to_event(keys)
+                .unwrap();
+            event.created_at = Timestamp::from(created_at);
+            event
+        };
+
+        let mut rating = RelayRating::new(url.clone());
+        assert_eq!(rating.average(), None);
+        assert!(rating.ingest(&review(&alice, 0.2, 10)));
+        assert!(rating.ingest(&review(&alice, 0.6, 20)));
+        assert!(!rating.ingest(&review(&alice, 0.0, 15)));
+        assert!(rating.ingest(&review(&bob, 1.0, 10)));
+        assert_eq!(rating.reviews(), 2);
+        assert_eq!(rating.average(), Some(0.8));
+
+        let other =
+            EventBuilder::relay_review(RelayReview::new(Url::parse("wss://nos.lol").unwrap(), 1.0))
+                .to_event(&bob)
+                .unwrap();
+        assert!(!rating.ingest(&other));
+    }
+}
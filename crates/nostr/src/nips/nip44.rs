@@ -0,0 +1,401 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP44
+//!
+//! Encrypted payloads (version 2)
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/44.md
+
+use base64::engine::{general_purpose, Engine};
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::{Hash, HashEngine};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use secp256k1::{ecdh, Parity, PublicKey, SecretKey, XOnlyPublicKey};
+
+use crate::key;
+
+const VERSION: u8 = 2;
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+
+/// `NIP44` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid plaintext length
+    #[error("Invalid plaintext length: {0}")]
+    InvalidPlaintextLength(usize),
+    /// Invalid payload
+    #[error("Invalid payload")]
+    InvalidPayload,
+    /// Unknown version
+    #[error("Unknown version: {0}")]
+    UnknownVersion(u8),
+    /// Error while decoding from base64
+    #[error("Error while decoding from base64")]
+    Base64Decode,
+    /// Invalid MAC
+    #[error("Invalid MAC")]
+    InvalidMac,
+    /// Invalid padding
+    #[error("Invalid padding")]
+    InvalidPadding,
+    /// Error while encoding to UTF-8
+    #[error("Error while encoding to UTF-8")]
+    Utf8Encode,
+}
+
+/// Conversation key shared by `sk` and `pk`
+///
+/// Zeroized on drop with the `secure-memory` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversationKey([u8; 32]);
+
+impl ConversationKey {
+    /// Derive conversation key
+    pub fn derive(sk: &SecretKey, pk: &XOnlyPublicKey) -> Self {
+        let pk = PublicKey::from_x_only_public_key(*pk, Parity::Even);
+        let mut ssp = ecdh::shared_secret_point(&pk, sk);
+        let conversation_key = Self(hmac(SALT, &[&ssp[..32]]));
+        key::wipe(&mut ssp);
+        conversation_key
+    }
+
+    /// Get conversation key as bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Derive ChaCha20 key, ChaCha20 nonce and HMAC key for `nonce`
+    fn message_keys(&self, nonce: &[u8; 32]) -> ([u8; 32], [u8; 12], [u8; 32]) {
+        // HKDF-expand to 76 bytes
+        let mut t1 = hmac(&self.0, &[nonce, &[1]]);
+        let mut t2 = hmac(&self.0, &[&t1, nonce, &[2]]);
+        let mut t3 = hmac(&self.0, &[&t2, nonce, &[3]]);
+        let mut okm: Vec<u8> = [t1, t2, t3].concat();
+
+        let mut chacha_key = [0u8; 32];
+        let mut chacha_nonce = [0u8; 12];
+        let mut hmac_key = [0u8; 32];
+        chacha_key.copy_from_slice(&okm[..32]);
+        chacha_nonce.copy_from_slice(&okm[32..44]);
+        hmac_key.copy_from_slice(&okm[44..76]);
+        for buf in [&mut t1, &mut t2, &mut t3] {
+            key::wipe(buf);
+        }
+        key::wipe(&mut okm);
+        (chacha_key, chacha_nonce, hmac_key)
+    }
+}
+
+#[cfg(feature = "secure-memory")]
+impl zeroize::Zeroize for ConversationKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "secure-memory")]
+impl Drop for ConversationKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "secure-memory")]
+impl zeroize::ZeroizeOnDrop for ConversationKey {}
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine: HmacEngine<Sha256Hash> = HmacEngine::new(key);
+    for d in data.iter() {
+        engine.input(d);
+    }
+    Hmac::<Sha256Hash>::from_engine(engine).into_inner()
+}
+
+/// Padded length of a plaintext of `len` bytes
+pub fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power: usize = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk: usize = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+/// Encrypt
+pub fn encrypt<T>(sk: &SecretKey, pk: &XOnlyPublicKey, plaintext: T) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let nonce: [u8; 32] = secp256k1::rand::random();
+    let conversation_key = ConversationKey::derive(sk, pk);
+    encrypt_with_nonce(&conversation_key, plaintext, nonce)
+}
+
+/// Encrypt with `nonce`
+///
+/// The nonce must be random and never reused: use [`encrypt`] unless you need deterministic output.
+pub fn encrypt_with_nonce<T>(
+    conversation_key: &ConversationKey,
+    plaintext: T,
+    nonce: [u8; 32],
+) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let plaintext: &[u8] = plaintext.as_ref();
+    let len: usize = plaintext.len();
+    if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+        return Err(Error::InvalidPlaintextLength(len));
+    }
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(2 + calc_padded_len(len));
+    buffer.extend_from_slice(&(len as u16).to_be_bytes());
+    buffer.extend_from_slice(plaintext);
+    buffer.resize(2 + calc_padded_len(len), 0);
+
+    let (mut chacha_key, chacha_nonce, mut hmac_key) = conversation_key.message_keys(&nonce);
+    let mut cipher = ChaCha20::new(&chacha_key.into(), &chacha_nonce.into());
+    cipher.apply_keystream(&mut buffer);
+    let mac: [u8; 32] = hmac(&hmac_key, &[&nonce, &buffer]);
+    key::wipe(&mut chacha_key);
+    key::wipe(&mut hmac_key);
+
+    let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + buffer.len() + 32);
+    payload.push(VERSION);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&buffer);
+    payload.extend_from_slice(&mac);
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+/// Decrypt
+pub fn decrypt<S>(sk: &SecretKey, pk: &XOnlyPublicKey, payload: S) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let conversation_key = ConversationKey::derive(sk, pk);
+    decrypt_with_conversation_key(&conversation_key, payload)
+}
+
+/// Decrypt with [`ConversationKey`]
+pub fn decrypt_with_conversation_key<S>(
+    conversation_key: &ConversationKey,
+    payload: S,
+) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let payload: &str = payload.as_ref();
+    if payload.starts_with('#') {
+        return Err(Error::UnknownVersion(0));
+    }
+    let payload: Vec<u8> = general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|_| Error::Base64Decode)?;
+    // version + nonce + min padded plaintext (2 + 32) + mac
+    if payload.len() < 1 + 32 + 34 + 32 {
+        return Err(Error::InvalidPayload);
+    }
+    if payload[0] != VERSION {
+        return Err(Error::UnknownVersion(payload[0]));
+    }
+
+    let mut nonce = [0u8; 32];
+    nonce.copy_from_slice(&payload[1..33]);
+    let (ciphertext, mac) = payload[33..].split_at(payload.len() - 33 - 32);
+
+    let (mut chacha_key, chacha_nonce, mut hmac_key) = conversation_key.message_keys(&nonce);
+    let expected: [u8; 32] = hmac(&hmac_key, &[&nonce, ciphertext]);
+    key::wipe(&mut hmac_key);
+    // Constant time comparison
+    if expected
+        .iter()
+        .zip(mac)
+        .fold(0, |acc, (a, b)| acc | (a ^ b))
+        != 0
+    {
+        key::wipe(&mut chacha_key);
+        return Err(Error::InvalidMac);
+    }
+
+    let mut buffer: Vec<u8> = ciphertext.to_vec();
+    let mut cipher = ChaCha20::new(&chacha_key.into(), &chacha_nonce.into());
+    key::wipe(&mut chacha_key);
+    cipher.apply_keystream(&mut buffer);
+
+    let len: usize = u16::from_be_bytes([buffer[0], buffer[1]]) as usize;
+    if len < MIN_PLAINTEXT_LEN || buffer.len() != 2 + calc_padded_len(len) {
+        return Err(Error::InvalidPadding);
+    }
+    buffer.truncate(2 + len);
+    String::from_utf8(buffer.split_off(2)).map_err(|_| Error::Utf8Encode)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin_hashes::hex::ToHex;
+    use secp256k1::{KeyPair, Secp256k1};
+
+    use super::*;
+
+    fn keys(sk: &str) -> (SecretKey, XOnlyPublicKey) {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_str(sk).unwrap();
+        let key_pair = KeyPair::from_secret_key(&secp, &sk);
+        (sk, XOnlyPublicKey::from_keypair(&key_pair).0)
+    }
+
+    #[test]
+    fn test_calc_padded_len() {
+        assert_eq!(calc_padded_len(1), 32);
+        assert_eq!(calc_padded_len(32), 32);
+        assert_eq!(calc_padded_len(33), 64);
+        assert_eq!(calc_padded_len(37), 64);
+        assert_eq!(calc_padded_len(45), 64);
+        assert_eq!(calc_padded_len(49), 64);
+        assert_eq!(calc_padded_len(64), 64);
+        assert_eq!(calc_padded_len(65), 96);
+        assert_eq!(calc_padded_len(100), 128);
+        assert_eq!(calc_padded_len(111), 128);
+        assert_eq!(calc_padded_len(200), 224);
+        assert_eq!(calc_padded_len(250), 256);
+        assert_eq!(calc_padded_len(320), 320);
+        assert_eq!(calc_padded_len(383), 384);
+        assert_eq!(calc_padded_len(384), 384);
+        assert_eq!(calc_padded_len(400), 448);
+        assert_eq!(calc_padded_len(500), 512);
+        assert_eq!(calc_padded_len(512), 512);
+        assert_eq!(calc_padded_len(515), 640);
+        assert_eq!(calc_padded_len(700), 768);
+        assert_eq!(calc_padded_len(800), 896);
+        assert_eq!(calc_padded_len(900), 1024);
+        assert_eq!(calc_padded_len(1020), 1024);
+        assert_eq!(calc_padded_len(65536), 65536);
+    }
+
+    #[test]
+    fn test_vector() {
+        let (sk1, _) = keys("0000000000000000000000000000000000000000000000000000000000000001");
+        let (_, pk2) = keys("0000000000000000000000000000000000000000000000000000000000000002");
+        let conversation_key = ConversationKey::derive(&sk1, &pk2);
+        assert_eq!(
+            conversation_key.as_bytes().to_hex(),
+            "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d"
+        );
+
+        let mut nonce = [0u8; 32];
+        nonce[31] = 1;
+        let payload = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb";
+        assert_eq!(
+            encrypt_with_nonce(&conversation_key, "a", nonce).unwrap(),
+            payload
+        );
+        assert_eq!(
+            decrypt_with_conversation_key(&conversation_key, payload).unwrap(),
+            "a"
+        );
+    }
+
+    #[test]
+    fn test_encryption_decryption() {
+        let (sender_sk, sender_pk) =
+            keys("6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e");
+        let (receiver_sk, receiver_pk) =
+            keys("7b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e");
+
+        let content = "Saturn, bringer of old age";
+        let payload = encrypt(&sender_sk, &receiver_pk, content).unwrap();
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, &payload).unwrap(),
+            content
+        );
+
+        // Tampered payload
+        let mut bytes = general_purpose::STANDARD.decode(&payload).unwrap();
+        bytes[40] ^= 1;
+        let tampered = general_purpose::STANDARD.encode(bytes);
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, tampered).unwrap_err(),
+            Error::InvalidMac
+        );
+
+        assert_eq!(
+            encrypt(&sender_sk, &receiver_pk, "").unwrap_err(),
+            Error::InvalidPlaintextLength(0)
+        );
+    }
+
+    #[test]
+    fn test_vectors() {
+        use crate::test_vectors::nip44;
+
+        let (sk1, _) = keys(nip44::SECRET_KEY_1);
+        let (_, pk2) = keys(nip44::SECRET_KEY_2);
+        let conversation_key = ConversationKey::derive(&sk1, &pk2);
+        assert_eq!(
+            conversation_key.as_bytes().to_hex(),
+            nip44::CONVERSATION_KEY
+        );
+        assert_eq!(
+            encrypt_with_nonce(&conversation_key, nip44::PLAINTEXT, nip44::NONCE).unwrap(),
+            nip44::PAYLOAD
+        );
+        assert_eq!(
+            decrypt_with_conversation_key(&conversation_key, nip44::PAYLOAD).unwrap(),
+            nip44::PLAINTEXT
+        );
+    }
+
+    #[cfg(feature = "secure-memory")]
+    #[test]
+    fn test_conversation_key_zeroized() {
+        use std::mem::{self, MaybeUninit};
+
+        use zeroize::Zeroize;
+
+        use crate::test_vectors::nip44;
+
+        let (sk1, pk1) = keys(nip44::SECRET_KEY_1);
+        let (sk2, pk2) = keys(nip44::SECRET_KEY_2);
+
+        let mut conversation_key = ConversationKey::derive(&sk1, &pk2);
+        conversation_key.zeroize();
+        assert_eq!(conversation_key.as_bytes(), &[0u8; 32]);
+
+        // Dropped in place, so that the memory can be inspected after the drop
+        let mut slot = MaybeUninit::new(ConversationKey::derive(&sk1, &pk2));
+        let bytes = |slot: &MaybeUninit<ConversationKey>| -> Vec<u8> {
+            let ptr = slot.as_ptr() as *const u8;
+            // SAFETY: reading the bytes of a live stack slot
+            unsafe { std::slice::from_raw_parts(ptr, mem::size_of::<ConversationKey>()) }.to_vec()
+        };
+        assert_eq!(bytes(&slot).to_hex(), nip44::CONVERSATION_KEY);
+        // SAFETY: the slot is initialized and not used as a `ConversationKey` after the drop
+        unsafe { slot.as_mut_ptr().drop_in_place() };
+        assert!(bytes(&slot).iter().all(|b| *b == 0));
+
+        // Keys are wiped only after use, also on errors
+        let payload: String = encrypt(&sk1, &pk2, nip44::PLAINTEXT).unwrap();
+        assert_eq!(decrypt(&sk2, &pk1, &payload).unwrap(), nip44::PLAINTEXT);
+        assert_eq!(
+            decrypt(&sk1, &pk1, &payload).unwrap_err(),
+            Error::InvalidMac
+        );
+        assert_eq!(
+            decrypt_with_conversation_key(&ConversationKey::derive(&sk2, &pk1), nip44::PAYLOAD)
+                .unwrap(),
+            nip44::PLAINTEXT
+        );
+    }
+}
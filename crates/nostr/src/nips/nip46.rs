@@ -0,0 +1,415 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP46
+//!
+//! Remote signing (Nostr Connect): `bunker://` URIs and request/response messages,
+//! exchanged as NIP-04 encrypted kind 24133 events.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/46.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::nip04;
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::event::{self, Event, EventBuilder, Kind, Tag};
+use crate::key::{self, Keys};
+use crate::types::relay_url;
+
+const SCHEME: &str = "bunker";
+
+/// `NIP46` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Url error
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    /// Relay url error
+    #[error(transparent)]
+    RelayUrl(#[from] relay_url::Error),
+    /// NIP04 error
+    #[error(transparent)]
+    NIP04(#[from] nip04::Error),
+    /// Event builder error
+    #[error(transparent)]
+    EventBuilder(#[from] event::builder::Error),
+    /// Unsigned event error
+    #[error(transparent)]
+    UnsignedEvent(#[from] unsigned::Error),
+    /// Invalid `bunker://` URI
+    #[error("invalid bunker uri: {0}")]
+    InvalidURI(String),
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Error returned by the remote signer
+    #[error("remote signer error: {0}")]
+    Response(String),
+    /// Response without result
+    #[error("empty response")]
+    EmptyResponse,
+}
+
+/// `bunker://<remote signer pubkey>?relay=<relay>&secret=<secret>` connection string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunkerURI {
+    /// Remote signer public key
+    pub remote_signer: XOnlyPublicKey,
+    /// Relays of the remote signer
+    pub relays: Vec<Url>,
+    /// Connection secret
+    pub secret: Option<String>,
+}
+
+impl BunkerURI {
+    /// New [`BunkerURI`]
+    pub fn new(remote_signer: XOnlyPublicKey, relays: Vec<Url>) -> Self {
+        Self {
+            remote_signer,
+            relays,
+            secret: None,
+        }
+    }
+
+    /// Set connection secret
+    pub fn secret<S>(self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            secret: Some(secret.into()),
+            ..self
+        }
+    }
+}
+
+impl FromStr for BunkerURI {
+    type Err = Error;
+
+    /// Relay urls are normalized (see [`relay_url::parse`]), unknown parameters are ignored
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let url: Url = Url::parse(uri.trim())?;
+        if url.scheme() != SCHEME {
+            return Err(Error::InvalidURI(format!(
+                "unsupported scheme: {}",
+                url.scheme()
+            )));
+        }
+        let remote_signer: &str = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| Error::InvalidURI(String::from("missing remote signer public key")))?;
+        let mut bunker = Self::new(XOnlyPublicKey::from_str(remote_signer)?, Vec::new());
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => {
+                    let relay: Url = relay_url::parse(value)?;
+                    if !bunker.relays.contains(&relay) {
+                        bunker.relays.push(relay);
+                    }
+                }
+                "secret" if !value.is_empty() => bunker.secret = Some(value.to_string()),
+                _ => (),
+            }
+        }
+        if bunker.relays.is_empty() {
+            return Err(Error::InvalidURI(String::from("missing relay")));
+        }
+        Ok(bunker)
+    }
+}
+
+impl fmt::Display for BunkerURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for relay in self.relays.iter() {
+            query.append_pair("relay", &relay_url::to_string(relay));
+        }
+        if let Some(secret) = &self.secret {
+            query.append_pair("secret", secret);
+        }
+        write!(f, "{SCHEME}://{}?{}", self.remote_signer, query.finish())
+    }
+}
+
+/// Request method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// Connect
+    Connect,
+    /// Get the public key of the account
+    GetPublicKey,
+    /// Sign event
+    SignEvent,
+    /// Ping
+    Ping,
+    /// Other method
+    Custom(String),
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect => write!(f, "connect"),
+            Self::GetPublicKey => write!(f, "get_public_key"),
+            Self::SignEvent => write!(f, "sign_event"),
+            Self::Ping => write!(f, "ping"),
+            Self::Custom(method) => write!(f, "{method}"),
+        }
+    }
+}
+
+impl From<&str> for Method {
+    fn from(method: &str) -> Self {
+        match method {
+            "connect" => Self::Connect,
+            "get_public_key" => Self::GetPublicKey,
+            "sign_event" => Self::SignEvent,
+            "ping" => Self::Ping,
+            method => Self::Custom(method.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawRequest {
+    id: String,
+    method: String,
+    params: Vec<String>,
+}
+
+/// Request sent to the remote signer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Id, to match the response
+    pub id: String,
+    /// Method
+    pub method: Method,
+    /// Params
+    pub params: Vec<String>,
+}
+
+impl Request {
+    /// New [`Request`] with random id
+    pub fn new(method: Method, params: Vec<String>) -> Self {
+        let id: [u8; 8] = secp256k1::rand::random();
+        Self {
+            id: id.iter().map(|b| format!("{b:02x}")).collect(),
+            method,
+            params,
+        }
+    }
+
+    /// Connect to `remote_signer`, with the secret of the [`BunkerURI`], if any
+    pub fn connect(remote_signer: XOnlyPublicKey, secret: Option<String>) -> Self {
+        let mut params: Vec<String> = vec![remote_signer.to_string()];
+        params.extend(secret);
+        Self::new(Method::Connect, params)
+    }
+
+    /// Get the public key of the account
+    pub fn get_public_key() -> Self {
+        Self::new(Method::GetPublicKey, Vec::new())
+    }
+
+    /// Sign [`UnsignedEvent`]
+    pub fn sign_event(unsigned: &UnsignedEvent) -> Result<Self, Error> {
+        Ok(Self::new(Method::SignEvent, vec![unsigned.as_json()?]))
+    }
+
+    /// Ping
+    pub fn ping() -> Self {
+        Self::new(Method::Ping, Vec::new())
+    }
+
+    /// Serialize [`Request`] to JSON string
+    pub fn as_json(&self) -> Result<String, Error> {
+        let raw = RawRequest {
+            id: self.id.clone(),
+            method: self.method.to_string(),
+            params: self.params.clone(),
+        };
+        Ok(serde_json::to_string(&raw)?)
+    }
+
+    /// Deserialize [`Request`] from JSON string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let raw: RawRequest = serde_json::from_str(json.as_ref())?;
+        Ok(Self {
+            id: raw.id,
+            method: Method::from(raw.method.as_str()),
+            params: raw.params,
+        })
+    }
+
+    /// Build request [`Event`] for `remote_signer`, signed by the client `keys`
+    pub fn to_event(&self, keys: &Keys, remote_signer: &XOnlyPublicKey) -> Result<Event, Error> {
+        to_event(keys, remote_signer, self.as_json()?)
+    }
+
+    /// Decrypt request [`Event`] received by the remote signer `keys`
+    pub fn from_event(keys: &Keys, event: &Event) -> Result<Self, Error> {
+        Self::from_json(decrypt(keys, event)?)
+    }
+}
+
+/// Response of the remote signer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    /// Id of the request
+    pub id: String,
+    /// Result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    /// Error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    /// Successful [`Response`] to request `id`
+    pub fn ok<S>(id: S, result: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            result: Some(result.into()),
+            error: None,
+        }
+    }
+
+    /// Serialize [`Response`] to JSON string
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Deserialize [`Response`] from JSON string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        Ok(serde_json::from_str(json.as_ref())?)
+    }
+
+    /// Build response [`Event`] for `client`, signed by the remote signer `keys`
+    pub fn to_event(&self, keys: &Keys, client: &XOnlyPublicKey) -> Result<Event, Error> {
+        to_event(keys, client, self.as_json()?)
+    }
+
+    /// Decrypt response [`Event`] received by the client `keys`
+    pub fn from_event(keys: &Keys, event: &Event) -> Result<Self, Error> {
+        Self::from_json(decrypt(keys, event)?)
+    }
+
+    /// Get result, or the error returned by the remote signer
+    pub fn into_result(self) -> Result<String, Error> {
+        match (self.error, self.result) {
+            (Some(error), _) if !error.is_empty() => Err(Error::Response(error)),
+            (_, Some(result)) => Ok(result),
+            _ => Err(Error::EmptyResponse),
+        }
+    }
+}
+
+fn to_event(keys: &Keys, receiver: &XOnlyPublicKey, json: String) -> Result<Event, Error> {
+    let content: String = nip04::encrypt(&keys.secret_key()?, receiver, json)?;
+    let tags = [Tag::PubKey(*receiver, None)];
+    Ok(EventBuilder::new(Kind::NostrConnect, content, &tags).to_event(keys)?)
+}
+
+fn decrypt(keys: &Keys, event: &Event) -> Result<String, Error> {
+    if event.kind != Kind::NostrConnect {
+        return Err(Error::UnexpectedKind);
+    }
+    Ok(nip04::decrypt(
+        &keys.secret_key()?,
+        &event.pubkey,
+        &event.content,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a";
+
+    #[test]
+    fn test_bunker_uri() {
+        let uri = format!(
+            "bunker://{PUBKEY}?relay=wss%3A%2F%2Frelay.nsec.app%2F&relay=wss://relay.damus.io&secret=abc123"
+        );
+        let bunker = BunkerURI::from_str(&uri).unwrap();
+        assert_eq!(
+            bunker,
+            BunkerURI::new(
+                XOnlyPublicKey::from_str(PUBKEY).unwrap(),
+                vec![
+                    Url::parse("wss://relay.nsec.app").unwrap(),
+                    Url::parse("wss://relay.damus.io").unwrap(),
+                ]
+            )
+            .secret("abc123")
+        );
+        assert_eq!(
+            bunker.to_string(),
+            format!("bunker://{PUBKEY}?relay=wss%3A%2F%2Frelay.nsec.app&relay=wss%3A%2F%2Frelay.damus.io&secret=abc123")
+        );
+        assert_eq!(BunkerURI::from_str(&bunker.to_string()).unwrap(), bunker);
+
+        assert!(BunkerURI::from_str(&format!("bunker://{PUBKEY}")).is_err());
+        assert!(BunkerURI::from_str(&format!(
+            "nostrconnect://{PUBKEY}?relay=wss://relay.damus.io"
+        ))
+        .is_err());
+        assert!(BunkerURI::from_str("bunker://abc?relay=wss://relay.damus.io").is_err());
+    }
+
+    #[test]
+    fn test_request_response() {
+        let client = Keys::generate();
+        let signer = Keys::generate();
+        let account = Keys::generate();
+
+        let unsigned =
+            EventBuilder::new_text_note("Hello", &[]).to_unsigned_event(account.public_key());
+        let request = Request::sign_event(&unsigned).unwrap();
+        let event = request.to_event(&client, &signer.public_key()).unwrap();
+        assert_eq!(event.kind, Kind::NostrConnect);
+        assert_eq!(Request::from_event(&signer, &event).unwrap(), request);
+
+        let signed = unsigned.sign(&account).unwrap();
+        let response = Response::ok(request.id.clone(), signed.as_json().unwrap());
+        let event = response.to_event(&signer, &client.public_key()).unwrap();
+        let response = Response::from_event(&client, &event).unwrap();
+        assert_eq!(response.id, request.id);
+        assert_eq!(
+            Event::from_json(response.into_result().unwrap()).unwrap(),
+            signed
+        );
+
+        let response = Response::from_json(r#"{"id":"1","result":"","error":"denied"}"#).unwrap();
+        assert!(matches!(response.into_result(), Err(Error::Response(e)) if e == "denied"));
+
+        let request = Request::from_json(r#"{"id":"2","method":"describe","params":[]}"#).unwrap();
+        assert_eq!(request.method, Method::Custom(String::from("describe")));
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP49
+//!
+//! Private key encryption (`ncryptsec`): scrypt key derivation and XChaCha20-Poly1305.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/49.md
+
+use std::fmt;
+
+use bech32::{self, FromBase32, ToBase32, Variant};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use scrypt::Params;
+use secp256k1::{self, SecretKey};
+use unicode_normalization::UnicodeNormalization;
+
+use super::nip19::{FromBech32, ToBech32};
+use crate::key;
+
+/// Bech32 prefix of encrypted secret keys
+pub const PREFIX_BECH32_ENCRYPTED_SECRET_KEY: &str = "ncryptsec";
+
+/// Default scrypt `LOG_N` (64 MiB of memory)
+pub const DEFAULT_LOG_N: u8 = 16;
+
+const VERSION: u8 = 0x02;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+const CIPHERTEXT_SIZE: usize = 48;
+const ENCRYPTED_SECRET_KEY_SIZE: usize = 1 + 1 + SALT_SIZE + NONCE_SIZE + 1 + CIPHERTEXT_SIZE;
+
+/// `NIP49` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Bech32 error
+    #[error("Invalid ncryptsec bech32")]
+    Bech32,
+    /// Invalid length
+    #[error("Invalid length: {0}")]
+    InvalidLength(usize),
+    /// Unknown version
+    #[error("Unknown version: {0}")]
+    UnknownVersion(u8),
+    /// Unknown key security
+    #[error("Unknown key security: {0}")]
+    UnknownKeySecurity(u8),
+    /// Invalid scrypt params
+    #[error("Invalid scrypt LOG_N: {0}")]
+    InvalidLogN(u8),
+    /// Encryption failed
+    #[error("Encryption failed")]
+    Encryption,
+    /// Wrong password or corrupted data
+    #[error("Decryption failed")]
+    Decryption,
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+}
+
+/// How the secret key was handled before being encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeySecurity {
+    /// The key has been known to be handled insecurely (stored unencrypted, cut and paste unencrypted, etc.)
+    Weak = 0x00,
+    /// The key has NOT been known to be handled insecurely
+    Medium = 0x01,
+    /// The client does not track this data
+    Unknown = 0x02,
+}
+
+impl TryFrom<u8> for KeySecurity {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x00 => Ok(Self::Weak),
+            0x01 => Ok(Self::Medium),
+            0x02 => Ok(Self::Unknown),
+            b => Err(Error::UnknownKeySecurity(b)),
+        }
+    }
+}
+
+/// Encrypted secret key
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptedSecretKey {
+    log_n: u8,
+    salt: [u8; SALT_SIZE],
+    nonce: [u8; NONCE_SIZE],
+    key_security: KeySecurity,
+    encrypted_key: [u8; CIPHERTEXT_SIZE],
+}
+
+impl fmt::Debug for EncryptedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedSecretKey")
+            .field("log_n", &self.log_n)
+            .field("key_security", &self.key_security)
+            .finish()
+    }
+}
+
+impl EncryptedSecretKey {
+    /// Encrypt `secret_key` with `password`
+    ///
+    /// `log_n` is the scrypt work factor: memory and time double at each step (see [`DEFAULT_LOG_N`]).
+    pub fn new<S>(
+        secret_key: &SecretKey,
+        password: S,
+        log_n: u8,
+        key_security: KeySecurity,
+    ) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let salt: [u8; SALT_SIZE] = secp256k1::rand::random();
+        let nonce: [u8; NONCE_SIZE] = secp256k1::rand::random();
+
+        let mut key: [u8; 32] = derive_key(password.as_ref(), &salt, log_n)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        key::wipe(&mut key);
+
+        let mut secret: [u8; 32] = secret_key.secret_bytes();
+        let res = cipher.encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &secret,
+                aad: &[key_security as u8],
+            },
+        );
+        key::wipe(&mut secret);
+
+        let ciphertext: Vec<u8> = res.map_err(|_| Error::Encryption)?;
+        let mut encrypted_key = [0u8; CIPHERTEXT_SIZE];
+        encrypted_key.copy_from_slice(&ciphertext);
+
+        Ok(Self {
+            log_n,
+            salt,
+            nonce,
+            key_security,
+            encrypted_key,
+        })
+    }
+
+    /// Parse from bytes
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != ENCRYPTED_SECRET_KEY_SIZE {
+            return Err(Error::InvalidLength(bytes.len()));
+        }
+        if bytes[0] != VERSION {
+            return Err(Error::UnknownVersion(bytes[0]));
+        }
+
+        let (salt_bytes, rest) = bytes[2..].split_at(SALT_SIZE);
+        let (nonce_bytes, rest) = rest.split_at(NONCE_SIZE);
+        let mut salt = [0u8; SALT_SIZE];
+        salt.copy_from_slice(salt_bytes);
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(nonce_bytes);
+        let mut encrypted_key = [0u8; CIPHERTEXT_SIZE];
+        encrypted_key.copy_from_slice(&rest[1..]);
+
+        Ok(Self {
+            log_n: bytes[1],
+            salt,
+            nonce,
+            key_security: KeySecurity::try_from(rest[0])?,
+            encrypted_key,
+        })
+    }
+
+    /// Serialize to bytes
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(ENCRYPTED_SECRET_KEY_SIZE);
+        bytes.push(VERSION);
+        bytes.push(self.log_n);
+        bytes.extend(self.salt);
+        bytes.extend(self.nonce);
+        bytes.push(self.key_security as u8);
+        bytes.extend(self.encrypted_key);
+        bytes
+    }
+
+    /// Get scrypt `LOG_N`
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// Get [`KeySecurity`]
+    pub fn key_security(&self) -> KeySecurity {
+        self.key_security
+    }
+
+    /// Decrypt the secret key with `password`
+    ///
+    /// Memory and time of the key derivation depend on [`EncryptedSecretKey::log_n`]:
+    /// check it before decrypting keys from untrusted sources.
+    pub fn to_secret_key<S>(&self, password: S) -> Result<SecretKey, Error>
+    where
+        S: AsRef<str>,
+    {
+        let mut key: [u8; 32] = derive_key(password.as_ref(), &self.salt, self.log_n)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        key::wipe(&mut key);
+
+        let mut secret: Vec<u8> = cipher
+            .decrypt(
+                XNonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.encrypted_key,
+                    aad: &[self.key_security as u8],
+                },
+            )
+            .map_err(|_| Error::Decryption)?;
+        let secret_key = SecretKey::from_slice(&secret);
+        key::wipe(&mut secret);
+        Ok(secret_key?)
+    }
+}
+
+/// Derive the symmetric key: scrypt of the NFKC normalized password
+fn derive_key(password: &str, salt: &[u8], log_n: u8) -> Result<[u8; 32], Error> {
+    let params = Params::new(log_n, 8, 1, 32).map_err(|_| Error::InvalidLogN(log_n))?;
+    let mut password: Vec<u8> = password.nfkc().collect::<String>().into_bytes();
+    let mut key = [0u8; 32];
+    let res = scrypt::scrypt(&password, salt, &params, &mut key);
+    key::wipe(&mut password);
+    res.map_err(|_| Error::InvalidLogN(log_n))?;
+    Ok(key)
+}
+
+impl FromBech32 for EncryptedSecretKey {
+    type Err = Error;
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: AsRef<str>,
+    {
+        let (hrp, data, checksum) = bech32::decode(s.as_ref()).map_err(|_| Error::Bech32)?;
+
+        if hrp != PREFIX_BECH32_ENCRYPTED_SECRET_KEY || checksum != Variant::Bech32 {
+            return Err(Error::Bech32);
+        }
+
+        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32)?;
+        Self::from_slice(&data)
+    }
+}
+
+impl ToBech32 for EncryptedSecretKey {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let data = self.to_vec().to_base32();
+        bech32::encode(PREFIX_BECH32_ENCRYPTED_SECRET_KEY, data, Variant::Bech32)
+            .map_err(|_| Error::Bech32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// Angstrom sign, ohm sign and long s with dots: NFKC normalized to `ÅΩṩ`
+    const PASSWORD: &str = "\u{212B}\u{2126}\u{1E9B}\u{0323}";
+    const SECRET_KEY: &str = "3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683";
+
+    #[test]
+    fn test_vectors() {
+        use crate::test_vectors::nip49;
+
+        let encrypted = EncryptedSecretKey::from_bech32(nip49::NCRYPTSEC).unwrap();
+        assert_eq!(encrypted.log_n(), 16);
+        assert_eq!(encrypted.key_security(), KeySecurity::Weak);
+        assert_eq!(encrypted.to_bech32().unwrap(), nip49::NCRYPTSEC);
+        assert_eq!(
+            encrypted.to_secret_key(nip49::PASSWORD).unwrap(),
+            SecretKey::from_str(nip49::SECRET_KEY).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let secret_key = SecretKey::from_str(SECRET_KEY).unwrap();
+        let encrypted =
+            EncryptedSecretKey::new(&secret_key, PASSWORD, 4, KeySecurity::Unknown).unwrap();
+        let ncryptsec: String = encrypted.to_bech32().unwrap();
+        assert!(ncryptsec.starts_with("ncryptsec1"));
+
+        let decoded = EncryptedSecretKey::from_bech32(&ncryptsec).unwrap();
+        assert_eq!(decoded, encrypted);
+        // Same password, NFKC normalized
+        assert_eq!(
+            decoded.to_secret_key("\u{00C5}\u{03A9}\u{1E69}").unwrap(),
+            secret_key
+        );
+        assert_eq!(decoded.to_secret_key("nostr"), Err(Error::Decryption));
+
+        // Tampered key security (associated data)
+        let mut bytes: Vec<u8> = encrypted.to_vec();
+        bytes[42] = KeySecurity::Weak as u8;
+        let tampered = EncryptedSecretKey::from_slice(&bytes).unwrap();
+        assert_eq!(tampered.to_secret_key(PASSWORD), Err(Error::Decryption));
+    }
+
+    #[test]
+    fn test_invalid_encrypted_secret_key() {
+        let secret_key = SecretKey::from_str(SECRET_KEY).unwrap();
+        let mut bytes: Vec<u8> =
+            EncryptedSecretKey::new(&secret_key, "nostr", 1, KeySecurity::Weak)
+                .unwrap()
+                .to_vec();
+        assert_eq!(
+            EncryptedSecretKey::from_slice(&bytes[1..]),
+            Err(Error::InvalidLength(90))
+        );
+        bytes[42] = 3;
+        assert_eq!(
+            EncryptedSecretKey::from_slice(&bytes),
+            Err(Error::UnknownKeySecurity(3))
+        );
+        bytes[0] = 1;
+        assert_eq!(
+            EncryptedSecretKey::from_slice(&bytes),
+            Err(Error::UnknownVersion(1))
+        );
+        assert_eq!(
+            EncryptedSecretKey::new(&secret_key, "nostr", 64, KeySecurity::Weak),
+            Err(Error::InvalidLogN(64))
+        );
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP59
+//!
+//! Gift wrap
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/59.md
+
+use secp256k1::XOnlyPublicKey;
+
+use super::nip44;
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::event::{self, builder};
+use crate::key::{self, Keys};
+use crate::{Event, EventBuilder, EventId, Kind, Tag, Timestamp};
+
+/// Max random shift in the past of `created_at` of seals and gift wraps (2 days)
+const RANGE_RANDOM_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+/// `NIP59` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] event::Error),
+    /// Event builder error
+    #[error(transparent)]
+    EventBuilder(#[from] builder::Error),
+    /// Unsigned event error
+    #[error(transparent)]
+    UnsignedEvent(#[from] unsigned::Error),
+    /// NIP44 error
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Not a gift wrap
+    #[error("not a gift wrap")]
+    NotGiftWrap,
+    /// Not a seal
+    #[error("not a seal")]
+    NotSeal,
+    /// Seal with tags (seals must have no tags)
+    #[error("seal with tags")]
+    SealWithTags,
+    /// The rumor id doesn't match its fields
+    #[error("invalid rumor id")]
+    InvalidRumorId,
+    /// The rumor author doesn't match the seal author
+    #[error("sender mismatch")]
+    SenderMismatch,
+}
+
+/// Unwrapped gift
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwrappedGift {
+    /// Sender (author of the seal)
+    pub sender: XOnlyPublicKey,
+    /// Rumor
+    pub rumor: UnsignedEvent,
+}
+
+fn random_past_timestamp() -> Timestamp {
+    let tweak: u64 = secp256k1::rand::random::<u64>() % RANGE_RANDOM_TIMESTAMP_TWEAK;
+    Timestamp::from(Timestamp::now().as_u64().saturating_sub(tweak))
+}
+
+fn sign_with_timestamp(builder: EventBuilder, keys: &Keys) -> Result<Event, Error> {
+    let mut unsigned: UnsignedEvent = builder.to_unsigned_event(keys.public_key());
+    unsigned.created_at = random_past_timestamp();
+    unsigned.id = EventId::new(
+        &unsigned.pubkey,
+        unsigned.created_at,
+        &unsigned.kind,
+        &unsigned.tags,
+        &unsigned.content,
+    );
+    Ok(unsigned.sign(keys)?)
+}
+
+/// Seal `rumor` for `receiver` (kind 13)
+pub fn seal(
+    sender: &Keys,
+    receiver: &XOnlyPublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<Event, Error> {
+    let content: String = nip44::encrypt(
+        &sender.secret_key()?,
+        receiver,
+        serde_json::to_string(rumor)?,
+    )?;
+    sign_with_timestamp(EventBuilder::new(Kind::Seal, content, &[]), sender)
+}
+
+/// Gift wrap `rumor` for `receiver` (kind 1059)
+///
+/// The rumor is sealed by `sender` and wrapped with a random ephemeral key.
+pub fn gift_wrap(
+    sender: &Keys,
+    receiver: &XOnlyPublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<Event, Error> {
+    let seal: Event = seal(sender, receiver, rumor)?;
+    let ephemeral = Keys::generate();
+    let content: String = nip44::encrypt(&ephemeral.secret_key()?, receiver, seal.as_json()?)?;
+    sign_with_timestamp(
+        EventBuilder::new(Kind::GiftWrap, content, &[Tag::PubKey(*receiver, None)]),
+        &ephemeral,
+    )
+}
+
+/// Unwrap gift wrap received by `receiver`
+///
+/// The seal signature is verified and the seal must have no tags. The rumor id must match
+/// its fields and the rumor author must match the seal author.
+pub fn unwrap_gift_wrap(receiver: &Keys, gift_wrap: &Event) -> Result<UnwrappedGift, Error> {
+    if gift_wrap.kind != Kind::GiftWrap {
+        return Err(Error::NotGiftWrap);
+    }
+    let secret_key = receiver.secret_key()?;
+
+    let seal: String = nip44::decrypt(&secret_key, &gift_wrap.pubkey, &gift_wrap.content)?;
+    let seal: Event = Event::from_json(seal)?;
+    if seal.kind != Kind::Seal {
+        return Err(Error::NotSeal);
+    }
+    if !seal.tags.is_empty() {
+        return Err(Error::SealWithTags);
+    }
+
+    let rumor: String = nip44::decrypt(&secret_key, &seal.pubkey, &seal.content)?;
+    let rumor: UnsignedEvent = serde_json::from_str(&rumor)?;
+    let id = EventId::new(
+        &rumor.pubkey,
+        rumor.created_at,
+        &rumor.kind,
+        &rumor.tags,
+        &rumor.content,
+    );
+    if rumor.id != id {
+        return Err(Error::InvalidRumorId);
+    }
+    if rumor.pubkey != seal.pubkey {
+        return Err(Error::SenderMismatch);
+    }
+
+    Ok(UnwrappedGift {
+        sender: seal.pubkey,
+        rumor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gift_wrap() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+
+        let rumor: UnsignedEvent = EventBuilder::new(
+            Kind::PrivateDirectMessage,
+            "hi bob",
+            &[Tag::PubKey(bob.public_key(), None)],
+        )
+        .to_unsigned_event(alice.public_key());
+        let wrap: Event = gift_wrap(&alice, &bob.public_key(), &rumor).unwrap();
+
+        assert_eq!(wrap.kind, Kind::GiftWrap);
+        assert_ne!(wrap.pubkey, alice.public_key());
+        assert!(wrap.created_at <= Timestamp::now());
+
+        let unwrapped = unwrap_gift_wrap(&bob, &wrap).unwrap();
+        assert_eq!(unwrapped.sender, alice.public_key());
+        assert_eq!(unwrapped.rumor, rumor);
+
+        // Not the receiver
+        assert!(unwrap_gift_wrap(&Keys::generate(), &wrap).is_err());
+    }
+
+    /// Wrap `seal` for `receiver`, like [`gift_wrap`] does
+    fn wrap_seal(receiver: &Keys, seal: &Event) -> Event {
+        let ephemeral = Keys::generate();
+        let content: String = nip44::encrypt(
+            &ephemeral.secret_key().unwrap(),
+            &receiver.public_key(),
+            seal.as_json().unwrap(),
+        )
+        .unwrap();
+        EventBuilder::new(
+            Kind::GiftWrap,
+            content,
+            &[Tag::PubKey(receiver.public_key(), None)],
+        )
+        .to_event(&ephemeral)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_invalid_gift_wrap() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let rumor: UnsignedEvent = EventBuilder::new(Kind::PrivateDirectMessage, "hi bob", &[])
+            .to_unsigned_event(alice.public_key());
+
+        // Rumor id not matching its fields
+        let mut forged: UnsignedEvent = rumor.clone();
+        forged.content = String::from("hi eve");
+        let wrap: Event = gift_wrap(&alice, &bob.public_key(), &forged).unwrap();
+        assert!(matches!(
+            unwrap_gift_wrap(&bob, &wrap),
+            Err(Error::InvalidRumorId)
+        ));
+
+        // Seal with tags
+        let content: String = nip44::encrypt(
+            &alice.secret_key().unwrap(),
+            &bob.public_key(),
+            serde_json::to_string(&rumor).unwrap(),
+        )
+        .unwrap();
+        let seal: Event =
+            EventBuilder::new(Kind::Seal, content, &[Tag::PubKey(bob.public_key(), None)])
+                .to_event(&alice)
+                .unwrap();
+        assert!(matches!(
+            unwrap_gift_wrap(&bob, &wrap_seal(&bob, &seal)),
+            Err(Error::SealWithTags)
+        ));
+
+        // Rumor of another author
+        let eve = Keys::generate();
+        let rumor: UnsignedEvent = EventBuilder::new(Kind::PrivateDirectMessage, "hi bob", &[])
+            .to_unsigned_event(eve.public_key());
+        let wrap: Event = gift_wrap(&alice, &bob.public_key(), &rumor).unwrap();
+        assert!(matches!(
+            unwrap_gift_wrap(&bob, &wrap),
+            Err(Error::SenderMismatch)
+        ));
+
+        // Not a seal
+        let note: Event = EventBuilder::new_text_note("hi bob", &[])
+            .to_event(&alice)
+            .unwrap();
+        assert!(matches!(
+            unwrap_gift_wrap(&bob, &wrap_seal(&bob, &note)),
+            Err(Error::NotSeal)
+        ));
+    }
+}
@@ -0,0 +1,465 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP60
+//!
+//! Cashu wallet
+//!
+//! The content of wallet, token and spending history events is `NIP44` encrypted to the author.
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/60.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use secp256k1::SecretKey;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::nip44;
+use crate::event::{Event, EventBuilder, EventId, Kind, Marker, Tag, TagKind};
+use crate::key::{self, Keys};
+
+/// `NIP60` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// NIP44 error
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Invalid secret key
+    #[error("invalid secret key")]
+    InvalidSecretKey,
+    /// Wallet without private key
+    #[error("wallet without private key")]
+    NoPrivateKey,
+    /// Unknown direction
+    #[error("unknown direction")]
+    UnknownDirection,
+    /// Spending history without direction or amount
+    #[error("incomplete spending history")]
+    IncompleteHistory,
+}
+
+/// Cashu proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    /// Keyset id
+    pub id: String,
+    /// Amount
+    pub amount: u64,
+    /// Secret
+    pub secret: String,
+    /// Unblinded signature
+    #[serde(rename = "C")]
+    pub c: String,
+}
+
+fn encrypt_tags(keys: &Keys, tags: Vec<Tag>) -> Result<String, Error> {
+    let tags: Vec<Vec<String>> = tags.into_iter().map(|t| t.as_vec()).collect();
+    encrypt_content(keys, serde_json::to_string(&tags)?)
+}
+
+fn decrypt_tags(keys: &Keys, event: &Event) -> Result<Vec<Vec<String>>, Error> {
+    Ok(serde_json::from_str(&decrypt_content(keys, event)?)?)
+}
+
+fn encrypt_content(keys: &Keys, content: String) -> Result<String, Error> {
+    Ok(nip44::encrypt(
+        &keys.secret_key()?,
+        &keys.public_key(),
+        content,
+    )?)
+}
+
+fn decrypt_content(keys: &Keys, event: &Event) -> Result<String, Error> {
+    Ok(nip44::decrypt(
+        &keys.secret_key()?,
+        &keys.public_key(),
+        &event.content,
+    )?)
+}
+
+/// Cashu wallet (kind 17375)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wallet {
+    /// Private key used to unlock the P2PK locked proofs (ex. nutzaps), not the nostr key of the user
+    pub privkey: SecretKey,
+    /// Mints
+    pub mints: Vec<Url>,
+}
+
+impl Wallet {
+    /// New [`Wallet`]
+    pub fn new(privkey: SecretKey, mints: Vec<Url>) -> Self {
+        Self { privkey, mints }
+    }
+
+    /// Decrypt and parse wallet [`Event`]
+    ///
+    /// Malformed mints are skipped.
+    pub fn from_event(event: &Event, keys: &Keys) -> Result<Self, Error> {
+        if event.kind != Kind::CashuWallet {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut privkey: Option<SecretKey> = None;
+        let mut mints: Vec<Url> = Vec::new();
+        for tag in decrypt_tags(keys, event)?.into_iter() {
+            match tag.as_slice() {
+                [kind, key, ..] if kind == "privkey" => {
+                    privkey = Some(SecretKey::from_str(key).map_err(|_| Error::InvalidSecretKey)?);
+                }
+                [kind, mint, ..] if kind == "mint" => {
+                    if let Ok(mint) = Url::parse(mint) {
+                        mints.push(mint);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            privkey: privkey.ok_or(Error::NoPrivateKey)?,
+            mints,
+        })
+    }
+}
+
+/// Unspent proofs of a mint (kind 7375)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    /// Mint
+    pub mint: Url,
+    /// Unspent proofs
+    pub proofs: Vec<Proof>,
+    /// Token events destroyed when this one was created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub del: Vec<EventId>,
+}
+
+impl Token {
+    /// New [`Token`]
+    pub fn new(mint: Url, proofs: Vec<Proof>) -> Self {
+        Self {
+            mint,
+            proofs,
+            del: Vec::new(),
+        }
+    }
+
+    /// Set token events destroyed when this one was created
+    pub fn del(self, del: Vec<EventId>) -> Self {
+        Self { del, ..self }
+    }
+
+    /// Total amount of the proofs
+    pub fn amount(&self) -> u64 {
+        self.proofs.iter().map(|p| p.amount).sum()
+    }
+
+    /// Decrypt and parse token [`Event`]
+    pub fn from_event(event: &Event, keys: &Keys) -> Result<Self, Error> {
+        if event.kind != Kind::CashuToken {
+            return Err(Error::UnexpectedKind);
+        }
+        Ok(serde_json::from_str(&decrypt_content(keys, event)?)?)
+    }
+}
+
+/// Direction of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received
+    In,
+    /// Sent
+    Out,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::In => write!(f, "in"),
+            Self::Out => write!(f, "out"),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "in" => Ok(Self::In),
+            "out" => Ok(Self::Out),
+            _ => Err(Error::UnknownDirection),
+        }
+    }
+}
+
+/// Spending history (kind 7376)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendingHistory {
+    /// Direction
+    pub direction: Direction,
+    /// Amount
+    pub amount: u64,
+    /// Unit (default: `sat`)
+    pub unit: Option<String>,
+    /// Created token events
+    pub created: Vec<EventId>,
+    /// Destroyed token events
+    pub destroyed: Vec<EventId>,
+    /// Redeemed nutzap events (public)
+    pub redeemed: Vec<EventId>,
+}
+
+impl SpendingHistory {
+    /// New [`SpendingHistory`]
+    pub fn new(direction: Direction, amount: u64) -> Self {
+        Self {
+            direction,
+            amount,
+            unit: None,
+            created: Vec::new(),
+            destroyed: Vec::new(),
+            redeemed: Vec::new(),
+        }
+    }
+
+    /// Set unit
+    pub fn unit<S>(self, unit: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            unit: Some(unit.into()),
+            ..self
+        }
+    }
+
+    /// Add created token event
+    pub fn created(mut self, id: EventId) -> Self {
+        self.created.push(id);
+        self
+    }
+
+    /// Add destroyed token event
+    pub fn destroyed(mut self, id: EventId) -> Self {
+        self.destroyed.push(id);
+        self
+    }
+
+    /// Add redeemed nutzap event
+    pub fn redeemed(mut self, id: EventId) -> Self {
+        self.redeemed.push(id);
+        self
+    }
+
+    /// Decrypt and parse spending history [`Event`]
+    pub fn from_event(event: &Event, keys: &Keys) -> Result<Self, Error> {
+        if event.kind != Kind::CashuWalletHistory {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut direction: Option<Direction> = None;
+        let mut amount: Option<u64> = None;
+        let mut history = Self::new(Direction::In, 0);
+        let public = event.tags.iter().map(|t| t.as_vec());
+        for tag in decrypt_tags(keys, event)?.into_iter().chain(public) {
+            match tag.as_slice() {
+                [kind, value, ..] if kind == "direction" => {
+                    direction = Some(Direction::from_str(value)?);
+                }
+                [kind, value, ..] if kind == "amount" => amount = value.parse().ok(),
+                [kind, value, ..] if kind == "unit" => history.unit = Some(value.clone()),
+                [kind, id, _, marker, ..] if kind == "e" => {
+                    let id = match EventId::from_hex(id) {
+                        Ok(id) => id,
+                        Err(_) => continue,
+                    };
+                    match marker.as_str() {
+                        "created" => history.created.push(id),
+                        "destroyed" => history.destroyed.push(id),
+                        "redeemed" => history.redeemed.push(id),
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        match (direction, amount) {
+            (Some(direction), Some(amount)) => Ok(Self {
+                direction,
+                amount,
+                ..history
+            }),
+            _ => Err(Error::IncompleteHistory),
+        }
+    }
+}
+
+fn marked_event(id: EventId, marker: &str) -> Tag {
+    Tag::Event(id, None, Some(Marker::Custom(marker.to_string())))
+}
+
+impl EventBuilder {
+    /// Cashu wallet
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/60.md>
+    pub fn cashu_wallet(keys: &Keys, wallet: &Wallet) -> Result<Self, Error> {
+        let mut tags: Vec<Tag> = vec![Tag::Generic(
+            TagKind::Custom(String::from("privkey")),
+            vec![wallet.privkey.display_secret().to_string()],
+        )];
+        tags.extend(wallet.mints.iter().map(|mint| {
+            Tag::Generic(
+                TagKind::Custom(String::from("mint")),
+                vec![mint.to_string()],
+            )
+        }));
+        Ok(Self::new(Kind::CashuWallet, encrypt_tags(keys, tags)?, &[]))
+    }
+
+    /// Cashu token (unspent proofs)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/60.md>
+    pub fn cashu_token(keys: &Keys, token: &Token) -> Result<Self, Error> {
+        let content: String = encrypt_content(keys, serde_json::to_string(token)?)?;
+        Ok(Self::new(Kind::CashuToken, content, &[]))
+    }
+
+    /// Cashu spending history
+    ///
+    /// Redeemed nutzaps are public, everything else is encrypted.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/60.md>
+    pub fn cashu_spending_history(keys: &Keys, history: &SpendingHistory) -> Result<Self, Error> {
+        let mut tags: Vec<Tag> = vec![
+            Tag::Generic(
+                TagKind::Custom(String::from("direction")),
+                vec![history.direction.to_string()],
+            ),
+            Tag::Generic(
+                TagKind::Custom(String::from("amount")),
+                vec![history.amount.to_string()],
+            ),
+        ];
+        if let Some(unit) = &history.unit {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("unit")),
+                vec![unit.clone()],
+            ));
+        }
+        tags.extend(
+            history
+                .created
+                .iter()
+                .map(|id| marked_event(*id, "created")),
+        );
+        tags.extend(
+            history
+                .destroyed
+                .iter()
+                .map(|id| marked_event(*id, "destroyed")),
+        );
+
+        let public: Vec<Tag> = history
+            .redeemed
+            .iter()
+            .map(|id| marked_event(*id, "redeemed"))
+            .collect();
+        Ok(Self::new(
+            Kind::CashuWalletHistory,
+            encrypt_tags(keys, tags)?,
+            &public,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(amount: u64) -> Proof {
+        Proof {
+            id: String::from("009a1f293253e41e"),
+            amount,
+            secret: String::from(
+                "407915bc212be61a77e3e6d2aeb4c727980bda51cd06a6afc29e2861768a7837",
+            ),
+            c: String::from("02bc9097997d81afb2cc7346b5e4345a9346bd2a506eb7958598a72f0cf85163ea"),
+        }
+    }
+
+    #[test]
+    fn test_wallet() {
+        let keys = Keys::generate();
+        let wallet = Wallet::new(
+            Keys::generate().secret_key().unwrap(),
+            vec![Url::parse("https://mint.example.com").unwrap()],
+        );
+        let event = EventBuilder::cashu_wallet(&keys, &wallet)
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::CashuWallet);
+        assert!(!event.content.contains("mint.example.com"));
+        assert_eq!(Wallet::from_event(&event, &keys).unwrap(), wallet);
+        assert!(Wallet::from_event(&event, &Keys::generate()).is_err());
+    }
+
+    #[test]
+    fn test_token() {
+        let keys = Keys::generate();
+        let destroyed =
+            EventId::from_hex("d7a5a0b5d2bea2ec7ee3eb1bd8bd1a3a1ef2fc44dbde3b4b2bd1f6c0d8b9c0e1")
+                .unwrap();
+        let token = Token::new(
+            Url::parse("https://mint.example.com").unwrap(),
+            vec![proof(1), proof(4)],
+        )
+        .del(vec![destroyed]);
+        assert_eq!(token.amount(), 5);
+
+        let event = EventBuilder::cashu_token(&keys, &token)
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(Token::from_event(&event, &keys).unwrap(), token);
+        assert!(matches!(
+            Wallet::from_event(&event, &keys),
+            Err(Error::UnexpectedKind)
+        ));
+    }
+
+    #[test]
+    fn test_spending_history() {
+        let keys = Keys::generate();
+        let id =
+            EventId::from_hex("d7a5a0b5d2bea2ec7ee3eb1bd8bd1a3a1ef2fc44dbde3b4b2bd1f6c0d8b9c0e1")
+                .unwrap();
+        let history = SpendingHistory::new(Direction::In, 21)
+            .unit("sat")
+            .created(id)
+            .redeemed(id);
+        let event = EventBuilder::cashu_spending_history(&keys, &history)
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            event.tags[0].as_vec(),
+            vec!["e", &id.to_hex(), "", "redeemed"]
+        );
+        assert_eq!(SpendingHistory::from_event(&event, &keys).unwrap(), history);
+    }
+}
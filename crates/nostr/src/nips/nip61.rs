@@ -0,0 +1,336 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP61
+//!
+//! Nutzaps
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/61.md
+
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use super::nip60::Proof;
+use crate::event::{Event, EventBuilder, EventId, Kind, Tag, TagKind};
+
+/// `NIP61` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Nutzap info without P2PK public key
+    #[error("nutzap info without public key")]
+    NoPublicKey,
+    /// Nutzap without proofs
+    #[error("nutzap without proofs")]
+    NoProofs,
+    /// Nutzap without mint
+    #[error("nutzap without mint")]
+    NoMint,
+    /// Nutzap without recipient
+    #[error("nutzap without recipient")]
+    NoRecipient,
+}
+
+/// Mint accepted for nutzaps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NutzapMint {
+    /// Url
+    pub url: Url,
+    /// Accepted units (ex. `sat`)
+    pub units: Vec<String>,
+}
+
+impl NutzapMint {
+    /// New [`NutzapMint`]
+    pub fn new(url: Url, units: Vec<String>) -> Self {
+        Self { url, units }
+    }
+}
+
+/// Nutzap info (kind 10019)
+///
+/// Mints, relays and P2PK public key the user accepts nutzaps with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NutzapInfo {
+    /// Relays where nutzaps are expected
+    pub relays: Vec<Url>,
+    /// Mints
+    pub mints: Vec<NutzapMint>,
+    /// Public key the proofs must be P2PK locked to (not the nostr key of the user)
+    pub pubkey: XOnlyPublicKey,
+}
+
+impl NutzapInfo {
+    /// New [`NutzapInfo`]
+    pub fn new(pubkey: XOnlyPublicKey) -> Self {
+        Self {
+            relays: Vec::new(),
+            mints: Vec::new(),
+            pubkey,
+        }
+    }
+
+    /// Add relay
+    pub fn relay(mut self, relay: Url) -> Self {
+        self.relays.push(relay);
+        self
+    }
+
+    /// Add mint
+    pub fn mint(mut self, mint: NutzapMint) -> Self {
+        self.mints.push(mint);
+        self
+    }
+
+    /// Parse nutzap info [`Event`]
+    ///
+    /// Malformed relays and mints are skipped.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::NutzapInfo {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut pubkey: Option<XOnlyPublicKey> = None;
+        let mut relays: Vec<Url> = Vec::new();
+        let mut mints: Vec<NutzapMint> = Vec::new();
+        for tag in event.tags.iter().map(|t| t.as_vec()) {
+            match tag.as_slice() {
+                [kind, relay, ..] if kind == "relay" => {
+                    if let Ok(relay) = Url::parse(relay) {
+                        relays.push(relay);
+                    }
+                }
+                [kind, url, units @ ..] if kind == "mint" => {
+                    if let Ok(url) = Url::parse(url) {
+                        mints.push(NutzapMint::new(url, units.to_vec()));
+                    }
+                }
+                [kind, key, ..] if kind == "pubkey" => {
+                    // P2PK keys may be published in compressed form
+                    let key = if key.len() == 66 { &key[2..] } else { key };
+                    pubkey = XOnlyPublicKey::from_str(key).ok();
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            relays,
+            mints,
+            pubkey: pubkey.ok_or(Error::NoPublicKey)?,
+        })
+    }
+}
+
+/// Nutzap (kind 9321)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nutzap {
+    /// P2PK locked proofs
+    pub proofs: Vec<Proof>,
+    /// Mint of the proofs
+    pub mint: Url,
+    /// Recipient
+    pub recipient: XOnlyPublicKey,
+    /// Zapped event
+    pub event: Option<EventId>,
+    /// Kind of the zapped event
+    pub event_kind: Option<Kind>,
+    /// Comment
+    pub comment: String,
+}
+
+impl Nutzap {
+    /// New [`Nutzap`]
+    pub fn new(proofs: Vec<Proof>, mint: Url, recipient: XOnlyPublicKey) -> Self {
+        Self {
+            proofs,
+            mint,
+            recipient,
+            event: None,
+            event_kind: None,
+            comment: String::new(),
+        }
+    }
+
+    /// Set zapped event
+    pub fn event(self, id: EventId, kind: Kind) -> Self {
+        Self {
+            event: Some(id),
+            event_kind: Some(kind),
+            ..self
+        }
+    }
+
+    /// Set comment
+    pub fn comment<S>(self, comment: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            comment: comment.into(),
+            ..self
+        }
+    }
+
+    /// Total amount of the proofs
+    pub fn amount(&self) -> u64 {
+        self.proofs.iter().map(|p| p.amount).sum()
+    }
+
+    /// Parse nutzap [`Event`]
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Nutzap {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut proofs: Vec<Proof> = Vec::new();
+        let mut mint: Option<Url> = None;
+        let mut recipient: Option<XOnlyPublicKey> = None;
+        let mut zapped: Option<EventId> = None;
+        let mut event_kind: Option<Kind> = None;
+        for tag in event.tags.iter().map(|t| t.as_vec()) {
+            match tag.as_slice() {
+                [kind, proof, ..] if kind == "proof" => {
+                    proofs.push(serde_json::from_str(proof)?);
+                }
+                [kind, url, ..] if kind == "u" => mint = Url::parse(url).ok(),
+                [kind, pk, ..] if kind == "p" => recipient = XOnlyPublicKey::from_str(pk).ok(),
+                [kind, id, ..] if kind == "e" => zapped = EventId::from_hex(id).ok(),
+                [kind, k, ..] if kind == "k" => {
+                    event_kind = k.parse::<u64>().ok().map(Kind::from);
+                }
+                _ => (),
+            }
+        }
+
+        if proofs.is_empty() {
+            return Err(Error::NoProofs);
+        }
+
+        Ok(Self {
+            proofs,
+            mint: mint.ok_or(Error::NoMint)?,
+            recipient: recipient.ok_or(Error::NoRecipient)?,
+            event: zapped,
+            event_kind,
+            comment: event.content.clone(),
+        })
+    }
+}
+
+impl EventBuilder {
+    /// Nutzap info
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/61.md>
+    pub fn nutzap_info(info: NutzapInfo) -> Self {
+        let mut tags: Vec<Tag> = info
+            .relays
+            .into_iter()
+            .map(|relay| {
+                Tag::Generic(
+                    TagKind::Custom(String::from("relay")),
+                    vec![relay.to_string()],
+                )
+            })
+            .collect();
+        tags.extend(info.mints.into_iter().map(|mint| {
+            let mut values: Vec<String> = vec![mint.url.to_string()];
+            values.extend(mint.units);
+            Tag::Generic(TagKind::Custom(String::from("mint")), values)
+        }));
+        tags.push(Tag::Generic(
+            TagKind::Custom(String::from("pubkey")),
+            vec![info.pubkey.to_string()],
+        ));
+        Self::new(Kind::NutzapInfo, "", &tags)
+    }
+
+    /// Nutzap
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/61.md>
+    pub fn nutzap(nutzap: Nutzap) -> Result<Self, Error> {
+        let mut tags: Vec<Tag> = Vec::with_capacity(nutzap.proofs.len() + 4);
+        for proof in nutzap.proofs.iter() {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("proof")),
+                vec![serde_json::to_string(proof)?],
+            ));
+        }
+        tags.push(Tag::Generic(
+            TagKind::Custom(String::from("u")),
+            vec![nutzap.mint.to_string()],
+        ));
+        if let Some(id) = nutzap.event {
+            tags.push(Tag::Event(id, None, None));
+        }
+        if let Some(kind) = nutzap.event_kind {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("k")),
+                vec![kind.as_u64().to_string()],
+            ));
+        }
+        tags.push(Tag::PubKey(nutzap.recipient, None));
+        Ok(Self::new(Kind::Nutzap, nutzap.comment, &tags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_nutzap_info() {
+        let keys = Keys::generate();
+        let p2pk = Keys::generate().public_key();
+        let info = NutzapInfo::new(p2pk)
+            .relay(Url::parse("wss://relay.damus.io").unwrap())
+            .mint(NutzapMint::new(
+                Url::parse("https://mint.example.com").unwrap(),
+                vec![String::from("sat")],
+            ));
+        let event = EventBuilder::nutzap_info(info.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::NutzapInfo);
+        assert!(event.kind.is_replaceable());
+        assert_eq!(NutzapInfo::from_event(&event).unwrap(), info);
+    }
+
+    #[test]
+    fn test_nutzap() {
+        let keys = Keys::generate();
+        let recipient = Keys::generate().public_key();
+        let zapped =
+            EventId::from_hex("d7a5a0b5d2bea2ec7ee3eb1bd8bd1a3a1ef2fc44dbde3b4b2bd1f6c0d8b9c0e1")
+                .unwrap();
+        let proof = Proof {
+            id: String::from("009a1f293253e41e"),
+            amount: 21,
+            secret: String::from("[\"P2PK\",{\"nonce\":\"b00bdd0467b0090a25bdf2d2f0d45ac4e355c482c1418350f273a04fedaaee83\",\"data\":\"02eaee8939e3565e48cc62967e2fde9d8e2a4b3ec0081f29eceff5c64ef10ac1ed\"}]"),
+            c: String::from("02bc9097997d81afb2cc7346b5e4345a9346bd2a506eb7958598a72f0cf85163ea"),
+        };
+        let nutzap = Nutzap::new(
+            vec![proof],
+            Url::parse("https://mint.example.com").unwrap(),
+            recipient,
+        )
+        .event(zapped, Kind::TextNote)
+        .comment("Thanks");
+        assert_eq!(nutzap.amount(), 21);
+
+        let event = EventBuilder::nutzap(nutzap.clone())
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content, "Thanks");
+        assert_eq!(Nutzap::from_event(&event).unwrap(), nutzap);
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP84
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/84.md
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use crate::event::{Event, EventBuilder, EventId, Kind, Tag, TagKind};
+use crate::Coordinate;
+
+/// Long-form content kind (NIP-23)
+const ARTICLE_KIND: u64 = 30023;
+
+/// `NIP84` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Article without `d` tag
+    #[error("article without identifier")]
+    NoIdentifier,
+    /// Range out of the content or not on char boundaries
+    #[error("invalid range")]
+    InvalidRange,
+}
+
+/// Source of a highlight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HighlightSource {
+    /// Web page (`r` tag)
+    Url(Url),
+    /// Parameterized replaceable event, ex. an article (`a` tag)
+    Coordinate(Coordinate),
+    /// Event (`e` tag)
+    Event(EventId),
+}
+
+impl From<HighlightSource> for Tag {
+    fn from(source: HighlightSource) -> Self {
+        match source {
+            HighlightSource::Url(url) => {
+                Tag::Generic(TagKind::Custom(String::from("r")), vec![url.to_string()])
+            }
+            HighlightSource::Coordinate(coordinate) => coordinate.into(),
+            HighlightSource::Event(id) => Tag::Event(id, None, None),
+        }
+    }
+}
+
+/// Attribution of the highlighted content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribution {
+    /// Public key
+    pub public_key: XOnlyPublicKey,
+    /// Relay hint
+    pub relay_url: Option<String>,
+    /// Role (ex. `author` or `editor`)
+    pub role: Option<String>,
+}
+
+impl Attribution {
+    /// New [`Attribution`]
+    pub fn new(public_key: XOnlyPublicKey) -> Self {
+        Self {
+            public_key,
+            relay_url: None,
+            role: None,
+        }
+    }
+
+    /// Set relay hint
+    pub fn relay_url<S>(self, relay_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            relay_url: Some(relay_url.into()),
+            ..self
+        }
+    }
+
+    /// Set role
+    pub fn role<S>(self, role: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            role: Some(role.into()),
+            ..self
+        }
+    }
+}
+
+impl From<Attribution> for Tag {
+    fn from(attribution: Attribution) -> Self {
+        match attribution.role {
+            Some(role) => Tag::ContactList {
+                pk: attribution.public_key,
+                relay_url: attribution.relay_url,
+                alias: Some(role),
+            },
+            None => Tag::PubKey(attribution.public_key, attribution.relay_url),
+        }
+    }
+}
+
+/// Highlight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    /// Highlighted text
+    pub content: String,
+    /// Sources
+    pub sources: Vec<HighlightSource>,
+    /// Surrounding text, if the highlight is part of a paragraph
+    pub context: Option<String>,
+    /// Attributions
+    pub attributions: Vec<Attribution>,
+}
+
+impl Highlight {
+    /// New [`Highlight`]
+    pub fn new<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            content: content.into(),
+            sources: Vec::new(),
+            context: None,
+            attributions: Vec::new(),
+        }
+    }
+
+    /// Add source
+    pub fn source(mut self, source: HighlightSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Set context
+    pub fn context<S>(self, context: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            context: Some(context.into()),
+            ..self
+        }
+    }
+
+    /// Add attribution
+    pub fn attribution(mut self, attribution: Attribution) -> Self {
+        self.attributions.push(attribution);
+        self
+    }
+
+    /// Highlight the byte `range` of the content of an article (kind 30023)
+    ///
+    /// The article is referenced by coordinate and id, its author is attributed with the `author` role
+    /// and the paragraph including the range is set as context, if longer than the highlight.
+    pub fn from_article(article: &Event, range: Range<usize>) -> Result<Self, Error> {
+        if article.kind.as_u64() != ARTICLE_KIND {
+            return Err(Error::UnexpectedKind);
+        }
+        let identifier: String = article
+            .tags
+            .iter()
+            .find_map(|t| match t.as_vec().as_slice() {
+                [kind, identifier, ..] if kind == "d" => Some(identifier.clone()),
+                _ => None,
+            })
+            .ok_or(Error::NoIdentifier)?;
+        let text: &str = article
+            .content
+            .get(range.clone())
+            .ok_or(Error::InvalidRange)?;
+
+        let start: usize = article.content[..range.start]
+            .rfind("\n\n")
+            .map_or(0, |i| i + 2);
+        let end: usize = article.content[range.end..]
+            .find("\n\n")
+            .map_or(article.content.len(), |i| range.end + i);
+        let paragraph: &str = article.content[start..end].trim();
+
+        let mut highlight = Self::new(text)
+            .source(HighlightSource::Coordinate(
+                Coordinate::new(article.kind, article.pubkey).identifier(identifier),
+            ))
+            .source(HighlightSource::Event(article.id))
+            .attribution(Attribution::new(article.pubkey).role("author"));
+        if paragraph != text.trim() {
+            highlight = highlight.context(paragraph);
+        }
+        Ok(highlight)
+    }
+
+    /// Parse highlight [`Event`]
+    ///
+    /// Malformed sources and attributions are skipped.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Highlight {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut highlight = Self::new(event.content.clone());
+        for tag in event.tags.iter() {
+            if let Some(coordinate) = Coordinate::from_tag(tag) {
+                highlight
+                    .sources
+                    .push(HighlightSource::Coordinate(coordinate));
+                continue;
+            }
+            match tag.as_vec().as_slice() {
+                [kind, url, ..] if kind == "r" => {
+                    if let Ok(url) = Url::parse(url) {
+                        highlight.sources.push(HighlightSource::Url(url));
+                    }
+                }
+                [kind, id, ..] if kind == "e" => {
+                    if let Ok(id) = EventId::from_hex(id) {
+                        highlight.sources.push(HighlightSource::Event(id));
+                    }
+                }
+                [kind, context, ..] if kind == "context" => {
+                    highlight.context = Some(context.clone());
+                }
+                [kind, public_key, rest @ ..] if kind == "p" => {
+                    if let Ok(public_key) = XOnlyPublicKey::from_str(public_key) {
+                        let value = |i: usize| rest.get(i).filter(|v| !v.is_empty()).cloned();
+                        highlight.attributions.push(Attribution {
+                            public_key,
+                            relay_url: value(0),
+                            role: value(1),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(highlight)
+    }
+}
+
+impl EventBuilder {
+    /// Highlight
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/84.md>
+    pub fn highlight(highlight: Highlight) -> Self {
+        let mut tags: Vec<Tag> = highlight.sources.into_iter().map(Tag::from).collect();
+        if let Some(context) = highlight.context {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("context")),
+                vec![context],
+            ));
+        }
+        tags.extend(highlight.attributions.into_iter().map(Tag::from));
+        Self::new(Kind::Highlight, highlight.content, &tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_highlight() {
+        let keys = Keys::generate();
+        let author = Keys::generate();
+        let highlight = Highlight::new("Nostr is a protocol")
+            .source(HighlightSource::Url(
+                Url::parse("https://example.com/post").unwrap(),
+            ))
+            .context("Nostr is a protocol, not a platform.")
+            .attribution(Attribution::new(author.public_key()).role("author"))
+            .attribution(Attribution::new(keys.public_key()).relay_url("wss://relay.damus.io"));
+        let event = EventBuilder::highlight(highlight.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::Highlight);
+        assert_eq!(
+            event.tags[0].as_vec(),
+            vec!["r", "https://example.com/post"]
+        );
+        assert_eq!(Highlight::from_event(&event).unwrap(), highlight);
+
+        let event = Event::from_json(event.as_json().unwrap()).unwrap();
+        assert_eq!(Highlight::from_event(&event).unwrap(), highlight);
+    }
+
+    #[test]
+    fn test_highlight_from_article() {
+        let keys = Keys::generate();
+        let content = "# Title\n\nFirst paragraph. Highlighted sentence. End.\n\nLast paragraph.";
+        let article = EventBuilder::new(
+            Kind::from(ARTICLE_KIND),
+            content,
+            &[Tag::Generic(TagKind::D, vec![String::from("my-article")])],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        let start = content.find("Highlighted").unwrap();
+        let end = start + "Highlighted sentence.".len();
+        let highlight = Highlight::from_article(&article, start..end).unwrap();
+        assert_eq!(highlight.content, "Highlighted sentence.");
+        assert_eq!(
+            highlight.context.as_deref(),
+            Some("First paragraph. Highlighted sentence. End.")
+        );
+        assert_eq!(
+            highlight.sources,
+            vec![
+                HighlightSource::Coordinate(
+                    Coordinate::new(Kind::from(ARTICLE_KIND), keys.public_key())
+                        .identifier("my-article")
+                ),
+                HighlightSource::Event(article.id),
+            ]
+        );
+        assert_eq!(
+            highlight.attributions,
+            vec![Attribution::new(keys.public_key()).role("author")]
+        );
+
+        let start = content.find("Last").unwrap();
+        let highlight = Highlight::from_article(&article, start..content.len()).unwrap();
+        assert_eq!(highlight.context, None);
+
+        assert!(matches!(
+            Highlight::from_article(&article, 0..content.len() + 1),
+            Err(Error::InvalidRange)
+        ));
+        let note = EventBuilder::new_text_note("Hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(matches!(
+            Highlight::from_article(&note, 0..1),
+            Err(Error::UnexpectedKind)
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP88
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/88.md
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use crate::event::{Event, EventBuilder, EventId, Kind, Tag, TagKind};
+use crate::Timestamp;
+
+/// `NIP88` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Unexpected kind
+    #[error("unexpected kind")]
+    UnexpectedKind,
+    /// Unknown poll type
+    #[error("unknown poll type")]
+    UnknownPollType,
+    /// Poll without options
+    #[error("poll without options")]
+    NoOptions,
+    /// Poll response without poll
+    #[error("poll response without poll")]
+    NoPoll,
+}
+
+/// Poll type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PollType {
+    /// Single choice (only the first response of a vote is counted)
+    #[default]
+    SingleChoice,
+    /// Multiple choice
+    MultipleChoice,
+}
+
+impl fmt::Display for PollType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SingleChoice => write!(f, "singlechoice"),
+            Self::MultipleChoice => write!(f, "multiplechoice"),
+        }
+    }
+}
+
+impl FromStr for PollType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "singlechoice" => Ok(Self::SingleChoice),
+            "multiplechoice" => Ok(Self::MultipleChoice),
+            _ => Err(Error::UnknownPollType),
+        }
+    }
+}
+
+/// Poll option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollOption {
+    /// Id
+    pub id: String,
+    /// Label
+    pub label: String,
+}
+
+impl PollOption {
+    /// New [`PollOption`]
+    pub fn new<S>(id: S, label: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            label: label.into(),
+        }
+    }
+}
+
+/// Poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poll {
+    /// Question
+    pub question: String,
+    /// Options
+    pub options: Vec<PollOption>,
+    /// Poll type
+    pub poll_type: PollType,
+    /// Relays where responses are expected
+    pub relays: Vec<Url>,
+    /// Deadline of responses
+    pub ends_at: Option<Timestamp>,
+}
+
+impl Poll {
+    /// New single choice [`Poll`]
+    pub fn new<S>(question: S, options: Vec<PollOption>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            question: question.into(),
+            options,
+            poll_type: PollType::default(),
+            relays: Vec::new(),
+            ends_at: None,
+        }
+    }
+
+    /// Set [`PollType`]
+    pub fn poll_type(self, poll_type: PollType) -> Self {
+        Self { poll_type, ..self }
+    }
+
+    /// Add relay
+    pub fn relay(mut self, relay: Url) -> Self {
+        self.relays.push(relay);
+        self
+    }
+
+    /// Set deadline of responses
+    pub fn ends_at(self, ends_at: Timestamp) -> Self {
+        Self {
+            ends_at: Some(ends_at),
+            ..self
+        }
+    }
+
+    /// Parse poll [`Event`]
+    ///
+    /// Malformed options and relays are skipped.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Poll {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut poll = Self::new(event.content.clone(), Vec::new());
+        for tag in event.tags.iter().map(|t| t.as_vec()) {
+            match tag.as_slice() {
+                [kind, id, label, ..]
+                    if kind == "option" && !poll.options.iter().any(|o| &o.id == id) =>
+                {
+                    poll.options
+                        .push(PollOption::new(id.clone(), label.clone()));
+                }
+                [kind, relay, ..] if kind == "relay" => {
+                    if let Ok(relay) = Url::parse(relay) {
+                        poll.relays.push(relay);
+                    }
+                }
+                [kind, poll_type, ..] if kind == "polltype" => {
+                    poll.poll_type = PollType::from_str(poll_type)?;
+                }
+                [kind, ends_at, ..] if kind == "endsAt" => {
+                    poll.ends_at = ends_at.parse::<u64>().ok().map(Timestamp::from);
+                }
+                _ => (),
+            }
+        }
+
+        if poll.options.is_empty() {
+            return Err(Error::NoOptions);
+        }
+
+        Ok(poll)
+    }
+
+    /// Check if the poll is ended at `now`
+    pub fn is_ended(&self, now: Timestamp) -> bool {
+        self.ends_at.map_or(false, |ends_at| now > ends_at)
+    }
+
+    /// Count the votes of `responses` to the poll with id `poll_id`
+    ///
+    /// Only the latest response of every author is counted, responses created after the deadline,
+    /// to other polls or with unknown options are ignored.
+    pub fn tally(&self, poll_id: EventId, responses: &[Event]) -> PollResults {
+        let mut latest: HashMap<XOnlyPublicKey, PollResponse> = HashMap::new();
+        for event in responses.iter() {
+            let response = match PollResponse::from_event(event) {
+                Ok(response) if response.poll_id == poll_id => response,
+                _ => continue,
+            };
+            if self.is_ended(event.created_at) {
+                continue;
+            }
+            match latest.get(&event.pubkey) {
+                Some(current) if current.created_at >= event.created_at => (),
+                _ => {
+                    latest.insert(event.pubkey, response);
+                }
+            }
+        }
+
+        let mut results = PollResults {
+            votes: self.options.iter().map(|o| (o.id.clone(), 0)).collect(),
+            voters: 0,
+        };
+        for response in latest.into_values() {
+            let mut options: Vec<&String> = Vec::new();
+            for id in response.options.iter() {
+                if results.votes.contains_key(id) && !options.contains(&id) {
+                    options.push(id);
+                }
+            }
+            if self.poll_type == PollType::SingleChoice {
+                options.truncate(1);
+            }
+            if options.is_empty() {
+                continue;
+            }
+            for id in options.into_iter() {
+                if let Some(votes) = results.votes.get_mut(id) {
+                    *votes += 1;
+                }
+            }
+            results.voters += 1;
+        }
+        results
+    }
+}
+
+/// Poll response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollResponse {
+    /// Poll id
+    pub poll_id: EventId,
+    /// Chosen option ids
+    pub options: Vec<String>,
+    /// Timestamp
+    pub created_at: Timestamp,
+}
+
+impl PollResponse {
+    /// Parse poll response [`Event`]
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::PollResponse {
+            return Err(Error::UnexpectedKind);
+        }
+
+        let mut poll_id: Option<EventId> = None;
+        let mut options: Vec<String> = Vec::new();
+        for tag in event.tags.iter().map(|t| t.as_vec()) {
+            match tag.as_slice() {
+                [kind, id, ..] if kind == "e" && poll_id.is_none() => {
+                    poll_id = EventId::from_hex(id).ok();
+                }
+                [kind, option, ..] if kind == "response" => options.push(option.clone()),
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            poll_id: poll_id.ok_or(Error::NoPoll)?,
+            options,
+            created_at: event.created_at,
+        })
+    }
+}
+
+/// Poll results
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PollResults {
+    /// Votes by option id
+    pub votes: BTreeMap<String, usize>,
+    /// Number of voters
+    pub voters: usize,
+}
+
+impl EventBuilder {
+    /// Poll
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/88.md>
+    pub fn poll(poll: Poll) -> Self {
+        let mut tags: Vec<Tag> = poll
+            .options
+            .into_iter()
+            .map(|o| Tag::Generic(TagKind::Custom(String::from("option")), vec![o.id, o.label]))
+            .collect();
+        tags.extend(poll.relays.into_iter().map(Tag::Relay));
+        tags.push(Tag::Generic(
+            TagKind::Custom(String::from("polltype")),
+            vec![poll.poll_type.to_string()],
+        ));
+        if let Some(ends_at) = poll.ends_at {
+            tags.push(Tag::Generic(
+                TagKind::Custom(String::from("endsAt")),
+                vec![ends_at.to_string()],
+            ));
+        }
+        Self::new(Kind::Poll, poll.question, &tags)
+    }
+
+    /// Poll response
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/88.md>
+    pub fn poll_response<S>(poll_id: EventId, options: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut tags: Vec<Tag> = vec![Tag::Event(poll_id, None, None)];
+        tags.extend(options.into_iter().map(|option| {
+            Tag::Generic(
+                TagKind::Custom(String::from("response")),
+                vec![option.into()],
+            )
+        }));
+        Self::new(Kind::PollResponse, "", &tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    fn poll() -> Poll {
+        Poll::new(
+            "Best client?",
+            vec![
+                PollOption::new("a", "Damus"),
+                PollOption::new("b", "Amethyst"),
+            ],
+        )
+        .relay(Url::parse("wss://relay.damus.io").unwrap())
+        .ends_at(Timestamp::from(2_000))
+    }
+
+    fn response(keys: &Keys, poll_id: EventId, options: Vec<&str>, created_at: u64) -> Event {
+        let mut unsigned =
+            EventBuilder::poll_response(poll_id, options).to_unsigned_event(keys.public_key());
+        unsigned.created_at = Timestamp::from(created_at);
+        unsigned.id = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &unsigned.tags,
+            &unsigned.content,
+        );
+        unsigned.sign(keys).unwrap()
+    }
+
+    #[test]
+    fn test_poll() {
+        let keys = Keys::generate();
+        let event = EventBuilder::poll(poll()).to_event(&keys).unwrap();
+        assert_eq!(event.kind, Kind::Poll);
+        assert_eq!(event.tags[0].as_vec(), vec!["option", "a", "Damus"]);
+        assert_eq!(Poll::from_event(&event).unwrap(), poll());
+
+        let response = response(&keys, event.id, vec!["b"], 1_000);
+        let parsed = PollResponse::from_event(&response).unwrap();
+        assert_eq!(parsed.poll_id, event.id);
+        assert_eq!(parsed.options, vec!["b".to_string()]);
+
+        assert!(matches!(
+            Poll::from_event(&response),
+            Err(Error::UnexpectedKind)
+        ));
+    }
+
+    #[test]
+    fn test_tally() {
+        let poll = poll();
+        let poll_id =
+            EventId::from_hex("d7a5a0b5d2bea2ec7ee3eb1bd8bd1a3a1ef2fc44dbde3b4b2bd1f6c0d8b9c0e1")
+                .unwrap();
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+
+        let responses = vec![
+            // Changed vote: only the latest counts
+            response(&alice, poll_id, vec!["a"], 1_000),
+            response(&alice, poll_id, vec!["b"], 1_100),
+            // Single choice: only the first option counts
+            response(&bob, poll_id, vec!["a", "b"], 1_000),
+            // After the deadline
+            response(&carol, poll_id, vec!["a"], 3_000),
+        ];
+
+        let results = poll.tally(poll_id, &responses);
+        assert_eq!(results.voters, 2);
+        assert_eq!(results.votes.get("a"), Some(&1));
+        assert_eq!(results.votes.get("b"), Some(&1));
+
+        let results = poll
+            .poll_type(PollType::MultipleChoice)
+            .tally(poll_id, &responses);
+        assert_eq!(results.votes.get("a"), Some(&1));
+        assert_eq!(results.votes.get("b"), Some(&2));
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP96
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/96.md
+
+use std::net::SocketAddr;
+
+use reqwest::Proxy;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::nip98::{self, HttpData, HttpMethod};
+use crate::event::{builder, Event, EventBuilder, Tag};
+use crate::Keys;
+
+/// `NIP96` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reqwest error
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// Error serializing or deserializing JSON data
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Url parse error
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    /// Event builder error
+    #[error(transparent)]
+    EventBuilder(#[from] builder::Error),
+    /// NIP98 error
+    #[error(transparent)]
+    NIP98(#[from] nip98::Error),
+    /// The server configuration is invalid
+    #[error("invalid server configuration")]
+    InvalidServerConfig,
+    /// Upload failed
+    #[error("upload failed: {0}")]
+    UploadFailed(String),
+}
+
+/// Raw `/.well-known/nostr/nip96.json` document
+#[derive(Deserialize)]
+struct RawServerConfig {
+    api_url: String,
+    download_url: Option<String>,
+    delegated_to_url: Option<String>,
+    supported_nips: Option<Vec<u16>>,
+    tos_url: Option<String>,
+    content_types: Option<Vec<String>>,
+}
+
+/// File storage server configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    /// Upload API URL
+    pub api_url: Url,
+    /// Download URL (if different from `api_url`)
+    pub download_url: Option<Url>,
+    /// Supported NIPs
+    pub supported_nips: Option<Vec<u16>>,
+    /// Terms of service
+    pub tos_url: Option<Url>,
+    /// Accepted MIME types
+    pub content_types: Option<Vec<String>>,
+}
+
+/// Get `/.well-known/nostr/nip96.json` URL of server
+pub fn well_known_url(server_url: &Url) -> Result<Url, Error> {
+    Ok(server_url.join("/.well-known/nostr/nip96.json")?)
+}
+
+fn parse_url(url: Option<String>) -> Result<Option<Url>, Error> {
+    match url {
+        Some(url) if !url.is_empty() => Ok(Some(Url::parse(&url)?)),
+        _ => Ok(None),
+    }
+}
+
+impl RawServerConfig {
+    /// Return the server the configuration is delegated to, if any
+    fn delegated_to(&self) -> Result<Option<Url>, Error> {
+        if self.api_url.is_empty() {
+            match parse_url(self.delegated_to_url.clone())? {
+                Some(url) => Ok(Some(url)),
+                None => Err(Error::InvalidServerConfig),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl TryFrom<RawServerConfig> for ServerConfig {
+    type Error = Error;
+
+    fn try_from(raw: RawServerConfig) -> Result<Self, Self::Error> {
+        Ok(Self {
+            api_url: Url::parse(&raw.api_url)?,
+            download_url: parse_url(raw.download_url)?,
+            supported_nips: raw.supported_nips,
+            tos_url: parse_url(raw.tos_url)?,
+            content_types: raw.content_types,
+        })
+    }
+}
+
+impl ServerConfig {
+    /// Get server configuration
+    ///
+    /// If the server delegates uploads to another server, the configuration of the latter is returned.
+    pub async fn get(server_url: Url, proxy: Option<SocketAddr>) -> Result<Self, Error> {
+        let client = async_client(proxy)?;
+        let mut raw: RawServerConfig = client
+            .get(well_known_url(&server_url)?)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(delegated_to) = raw.delegated_to()? {
+            raw = client
+                .get(well_known_url(&delegated_to)?)
+                .send()
+                .await?
+                .json()
+                .await?;
+            if raw.delegated_to()?.is_some() {
+                return Err(Error::InvalidServerConfig);
+            }
+        }
+        Self::try_from(raw)
+    }
+
+    /// Get server configuration
+    ///
+    /// If the server delegates uploads to another server, the configuration of the latter is returned.
+    #[cfg(feature = "blocking")]
+    pub fn get_blocking(server_url: Url, proxy: Option<SocketAddr>) -> Result<Self, Error> {
+        let client = blocking_client(proxy)?;
+        let mut raw: RawServerConfig = client.get(well_known_url(&server_url)?).send()?.json()?;
+        if let Some(delegated_to) = raw.delegated_to()? {
+            raw = client.get(well_known_url(&delegated_to)?).send()?.json()?;
+            if raw.delegated_to()?.is_some() {
+                return Err(Error::InvalidServerConfig);
+            }
+        }
+        Self::try_from(raw)
+    }
+}
+
+/// Upload status
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Success,
+    Error,
+    Processing,
+}
+
+/// NIP-94 file metadata returned by server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Nip94Event {
+    /// File metadata tags (`url`, `ox`, `x`, `m`, ...)
+    pub tags: Vec<Tag>,
+    /// Content
+    #[serde(default)]
+    pub content: String,
+}
+
+/// Upload response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadResponse {
+    /// Status
+    pub status: UploadStatus,
+    /// Human readable message
+    #[serde(default)]
+    pub message: String,
+    /// URL to poll while the file is processed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_url: Option<String>,
+    /// File metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nip94_event: Option<Nip94Event>,
+}
+
+impl UploadResponse {
+    /// Deserialize [`UploadResponse`] from `JSON` string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Ok(serde_json::from_str(&json.into())?)
+    }
+
+    /// Get file metadata tags
+    pub fn tags(&self) -> Vec<Tag> {
+        self.nip94_event
+            .as_ref()
+            .map(|e| e.tags.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get download URL of the uploaded file
+    pub fn download_url(&self) -> Option<Url> {
+        self.tags().into_iter().find_map(|tag| {
+            let tag: Vec<String> = tag.as_vec();
+            match tag.first().map(|t| t.as_str()) {
+                Some("url") => tag.get(1).and_then(|url| Url::parse(url).ok()),
+                _ => None,
+            }
+        })
+    }
+
+    fn ensure_success(self) -> Result<Self, Error> {
+        match self.status {
+            UploadStatus::Error => Err(Error::UploadFailed(self.message)),
+            _ => Ok(self),
+        }
+    }
+}
+
+fn auth_header(keys: &Keys, config: &ServerConfig, data: &[u8]) -> Result<String, Error> {
+    let http_data = HttpData::new(config.api_url.clone(), HttpMethod::POST).body(data);
+    let event: Event = EventBuilder::http_auth(http_data).to_event(keys)?;
+    Ok(nip98::authorization_header(&event)?)
+}
+
+/// Upload file
+///
+/// The request is authenticated with [`Keys`] (NIP-98).
+pub async fn upload(
+    keys: &Keys,
+    config: &ServerConfig,
+    data: Vec<u8>,
+    mime_type: Option<&str>,
+    proxy: Option<SocketAddr>,
+) -> Result<UploadResponse, Error> {
+    use reqwest::multipart::{Form, Part};
+
+    let authorization: String = auth_header(keys, config, &data)?;
+    let size: usize = data.len();
+    let mut part = Part::bytes(data).file_name("file");
+    if let Some(mime_type) = mime_type {
+        part = part.mime_str(mime_type)?;
+    }
+    let mut form = Form::new()
+        .part("file", part)
+        .text("size", size.to_string());
+    if let Some(mime_type) = mime_type {
+        form = form.text("content_type", mime_type.to_string());
+    }
+
+    let client = async_client(proxy)?;
+    let res = client
+        .post(config.api_url.clone())
+        .header("Authorization", authorization)
+        .multipart(form)
+        .send()
+        .await?;
+    UploadResponse::from_json(res.text().await?)?.ensure_success()
+}
+
+/// Upload file
+///
+/// The request is authenticated with [`Keys`] (NIP-98).
+#[cfg(feature = "blocking")]
+pub fn upload_blocking(
+    keys: &Keys,
+    config: &ServerConfig,
+    data: Vec<u8>,
+    mime_type: Option<&str>,
+    proxy: Option<SocketAddr>,
+) -> Result<UploadResponse, Error> {
+    use reqwest::blocking::multipart::{Form, Part};
+
+    let authorization: String = auth_header(keys, config, &data)?;
+    let size: usize = data.len();
+    let mut part = Part::bytes(data).file_name("file");
+    if let Some(mime_type) = mime_type {
+        part = part.mime_str(mime_type)?;
+    }
+    let mut form = Form::new()
+        .part("file", part)
+        .text("size", size.to_string());
+    if let Some(mime_type) = mime_type {
+        form = form.text("content_type", mime_type.to_string());
+    }
+
+    let client = blocking_client(proxy)?;
+    let res = client
+        .post(config.api_url.clone())
+        .header("Authorization", authorization)
+        .multipart(form)
+        .send()?;
+    UploadResponse::from_json(res.text()?)?.ensure_success()
+}
+
+fn async_client(proxy: Option<SocketAddr>) -> Result<reqwest::Client, Error> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{proxy}");
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(feature = "blocking")]
+fn blocking_client(proxy: Option<SocketAddr>) -> Result<reqwest::blocking::Client, Error> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{proxy}");
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_config() {
+        let json = r#"{"api_url":"https://nostr.build/api/v2/nip96/upload","download_url":"https://media.nostr.build","supported_nips":[94,96,98],"tos_url":"https://nostr.build/tos/","content_types":["image/jpeg","video/webm"]}"#;
+        let raw: RawServerConfig = serde_json::from_str(json).unwrap();
+        assert!(raw.delegated_to().unwrap().is_none());
+        let config = ServerConfig::try_from(raw).unwrap();
+        assert_eq!(
+            config.api_url,
+            Url::parse("https://nostr.build/api/v2/nip96/upload").unwrap()
+        );
+        assert_eq!(
+            config.download_url,
+            Some(Url::parse("https://media.nostr.build").unwrap())
+        );
+        assert_eq!(config.supported_nips, Some(vec![94, 96, 98]));
+
+        let json = r#"{"api_url":"","delegated_to_url":"https://nostr.build"}"#;
+        let raw: RawServerConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            raw.delegated_to().unwrap(),
+            Some(Url::parse("https://nostr.build").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_upload_response() {
+        let json = r#"{"status":"success","message":"Upload successful.","nip94_event":{"tags":[["url","https://image.nostr.build/719cc85e.png"],["ox","719cc85e6d2b0f5c0b9d3e5b9aac5e2a4c3ba6f6c1d4fb1b3c7d4b2d5c9e0a1f"],["m","image/png"]],"content":""}}"#;
+        let response = UploadResponse::from_json(json).unwrap();
+        assert_eq!(response.status, UploadStatus::Success);
+        assert_eq!(
+            response.download_url(),
+            Some(Url::parse("https://image.nostr.build/719cc85e.png").unwrap())
+        );
+        assert_eq!(response.tags().len(), 3);
+
+        let json = r#"{"status":"error","message":"File too large"}"#;
+        let response = UploadResponse::from_json(json).unwrap();
+        assert!(matches!(
+            response.ensure_success(),
+            Err(Error::UploadFailed(msg)) if msg == "File too large"
+        ));
+    }
+}
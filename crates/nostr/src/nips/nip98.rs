@@ -0,0 +1,166 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP98
+//!
+//! https://github.com/nostr-protocol/nips/blob/master/98.md
+
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::{general_purpose, Engine};
+use bitcoin_hashes::sha256::Hash as Sha256Hash;
+use bitcoin_hashes::Hash;
+use url::Url;
+
+use crate::event::{self, Event, EventBuilder, Kind, Tag, TagKind};
+
+/// `NIP98` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] event::Error),
+    /// Unknown HTTP method
+    #[error("unknown HTTP method")]
+    UnknownMethod,
+}
+
+/// HTTP method
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    GET,
+    POST,
+    PUT,
+    PATCH,
+    DELETE,
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GET => write!(f, "GET"),
+            Self::POST => write!(f, "POST"),
+            Self::PUT => write!(f, "PUT"),
+            Self::PATCH => write!(f, "PATCH"),
+            Self::DELETE => write!(f, "DELETE"),
+        }
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Self::GET),
+            "POST" => Ok(Self::POST),
+            "PUT" => Ok(Self::PUT),
+            "PATCH" => Ok(Self::PATCH),
+            "DELETE" => Ok(Self::DELETE),
+            _ => Err(Error::UnknownMethod),
+        }
+    }
+}
+
+/// HTTP request data to authenticate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpData {
+    /// Absolute request URL
+    pub url: Url,
+    /// HTTP method
+    pub method: HttpMethod,
+    /// SHA256 hash of the request body
+    pub payload: Option<Sha256Hash>,
+}
+
+impl HttpData {
+    /// New [`HttpData`]
+    pub fn new(url: Url, method: HttpMethod) -> Self {
+        Self {
+            url,
+            method,
+            payload: None,
+        }
+    }
+
+    /// Set payload hash
+    pub fn payload(self, payload: Sha256Hash) -> Self {
+        Self {
+            payload: Some(payload),
+            ..self
+        }
+    }
+
+    /// Set payload hash from request body
+    pub fn body(self, body: &[u8]) -> Self {
+        self.payload(Sha256Hash::hash(body))
+    }
+}
+
+impl From<HttpData> for Vec<Tag> {
+    fn from(data: HttpData) -> Self {
+        let mut tags = vec![
+            Tag::Generic(TagKind::U, vec![data.url.to_string()]),
+            Tag::Generic(TagKind::Method, vec![data.method.to_string()]),
+        ];
+        if let Some(payload) = data.payload {
+            tags.push(Tag::Generic(TagKind::Payload, vec![payload.to_string()]));
+        }
+        tags
+    }
+}
+
+impl EventBuilder {
+    /// HTTP Auth
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/98.md>
+    pub fn http_auth(data: HttpData) -> Self {
+        let tags: Vec<Tag> = data.into();
+        Self::new(Kind::HttpAuth, "", &tags)
+    }
+}
+
+/// Compose the value of the `Authorization` HTTP header
+pub fn authorization_header(event: &Event) -> Result<String, Error> {
+    let json: String = event.as_json()?;
+    Ok(format!("Nostr {}", general_purpose::STANDARD.encode(json)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_http_auth_event() {
+        let keys = Keys::generate();
+        let url = Url::parse("https://nostr.build/api/v2/upload/files").unwrap();
+        let data = HttpData::new(url, HttpMethod::POST).body(b"hello");
+        let event = EventBuilder::http_auth(data).to_event(&keys).unwrap();
+
+        assert_eq!(event.kind, Kind::HttpAuth);
+        assert_eq!(
+            event.tags[0].as_vec(),
+            vec!["u", "https://nostr.build/api/v2/upload/files"]
+        );
+        assert_eq!(event.tags[1].as_vec(), vec!["method", "POST"]);
+        assert_eq!(
+            event.tags[2].as_vec(),
+            vec![
+                "payload",
+                "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+            ]
+        );
+
+        let header = authorization_header(&event).unwrap();
+        let json = general_purpose::STANDARD
+            .decode(header.strip_prefix("Nostr ").unwrap())
+            .unwrap();
+        assert_eq!(
+            Event::from_json(String::from_utf8(json).unwrap()).unwrap(),
+            event
+        );
+    }
+}
@@ -3,20 +3,33 @@
 
 //! Prelude
 
+#![allow(unknown_lints, ambiguous_glob_reexports)]
+
 // External crates
-pub use bitcoin::hashes::*;
-pub use bitcoin::secp256k1::*;
+pub use crate::hashes::*;
+pub use crate::secp256k1::*;
 #[cfg(feature = "base")]
 pub use url::*;
 
 // Internal modules
+#[cfg(feature = "attachment")]
+pub use crate::attachment::*;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub use crate::binary::*;
+#[cfg(feature = "blossom")]
+pub use crate::blossom::*;
 #[cfg(feature = "base")]
 pub use crate::event::*;
+#[cfg(feature = "std")]
 pub use crate::key::*;
 #[cfg(feature = "base")]
 pub use crate::message::*;
+pub use crate::raw::*;
+#[cfg(feature = "base")]
+pub use crate::signer::*;
 #[cfg(feature = "base")]
 pub use crate::types::*;
+#[cfg(feature = "std")]
 pub use crate::Result;
 
 // NIPs
@@ -32,5 +45,31 @@ pub use crate::nips::nip11::*;
 pub use crate::nips::nip13::*;
 #[cfg(feature = "nip19")]
 pub use crate::nips::nip19::*;
+#[cfg(feature = "nip21")]
+pub use crate::nips::nip21::*;
 #[cfg(feature = "nip26")]
 pub use crate::nips::nip26::*;
+#[cfg(feature = "nip27")]
+pub use crate::nips::nip27::*;
+#[cfg(feature = "nip29")]
+pub use crate::nips::nip29::*;
+#[cfg(feature = "nip32")]
+pub use crate::nips::nip32::*;
+#[cfg(feature = "nip46")]
+pub use crate::nips::nip46::*;
+#[cfg(feature = "nip49")]
+pub use crate::nips::nip49::*;
+#[cfg(feature = "nip59")]
+pub use crate::nips::nip59::*;
+#[cfg(feature = "nip60")]
+pub use crate::nips::nip60::*;
+#[cfg(feature = "nip61")]
+pub use crate::nips::nip61::*;
+#[cfg(feature = "nip84")]
+pub use crate::nips::nip84::*;
+#[cfg(feature = "nip88")]
+pub use crate::nips::nip88::*;
+#[cfg(feature = "nip96")]
+pub use crate::nips::nip96::*;
+#[cfg(feature = "nip98")]
+pub use crate::nips::nip98::*;
@@ -0,0 +1,212 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Raw event
+//!
+//! Event construction, id computation and schnorr signing with `core` and `alloc` only,
+//! available without the `std` feature (ex. signing devices and embedded gateways).
+//! Timestamps and auxiliary randomness are provided by the caller.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::hashes::sha256::Hash as Sha256Hash;
+use crate::hashes::Hash;
+use crate::secp256k1::schnorr::Signature;
+use crate::secp256k1::{KeyPair, Message, Secp256k1, Signing, XOnlyPublicKey};
+
+/// Compute the event id (NIP-01)
+///
+/// Sha256 of `[0, pubkey, created_at, kind, tags, content]`, serialized as compact JSON.
+pub fn event_id(
+    pubkey: &XOnlyPublicKey,
+    created_at: u64,
+    kind: u64,
+    tags: &[Vec<String>],
+    content: &str,
+) -> Sha256Hash {
+    let mut json = String::from("[0,\"");
+    json.push_str(&pubkey.to_string());
+    json.push_str("\",");
+    json.push_str(&created_at.to_string());
+    json.push(',');
+    json.push_str(&kind.to_string());
+    json.push(',');
+    push_json_tags(&mut json, tags);
+    json.push(',');
+    push_json_str(&mut json, content);
+    json.push(']');
+    Sha256Hash::hash(json.as_bytes())
+}
+
+/// Raw event builder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawEventBuilder {
+    kind: u64,
+    tags: Vec<Vec<String>>,
+    content: String,
+}
+
+impl RawEventBuilder {
+    /// New [`RawEventBuilder`]
+    pub fn new<S>(kind: u64, content: S, tags: Vec<Vec<String>>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind,
+            tags,
+            content: content.into(),
+        }
+    }
+
+    /// Build and sign [`RawEvent`]
+    ///
+    /// `aux_rand` should be 32 fresh random bytes (BIP-340), ex. from the hardware RNG of the device.
+    pub fn to_event<C>(
+        self,
+        secp: &Secp256k1<C>,
+        keypair: &KeyPair,
+        created_at: u64,
+        aux_rand: &[u8; 32],
+    ) -> RawEvent
+    where
+        C: Signing,
+    {
+        let (pubkey, _) = keypair.x_only_public_key();
+        let id: Sha256Hash = event_id(&pubkey, created_at, self.kind, &self.tags, &self.content);
+        let message = Message::from(id);
+        let sig: Signature = secp.sign_schnorr_with_aux_rand(&message, keypair, aux_rand);
+        RawEvent {
+            id,
+            pubkey,
+            created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            sig,
+        }
+    }
+}
+
+/// Raw signed event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawEvent {
+    /// Id
+    pub id: Sha256Hash,
+    /// Author
+    pub pubkey: XOnlyPublicKey,
+    /// Timestamp (seconds)
+    pub created_at: u64,
+    /// Kind
+    pub kind: u64,
+    /// Tags
+    pub tags: Vec<Vec<String>>,
+    /// Content
+    pub content: String,
+    /// Signature
+    pub sig: Signature,
+}
+
+impl RawEvent {
+    /// Get event as json string, ready to be sent to relays
+    pub fn as_json(&self) -> String {
+        let mut json = String::from("{\"id\":\"");
+        json.push_str(&self.id.to_string());
+        json.push_str("\",\"pubkey\":\"");
+        json.push_str(&self.pubkey.to_string());
+        json.push_str("\",\"created_at\":");
+        json.push_str(&self.created_at.to_string());
+        json.push_str(",\"kind\":");
+        json.push_str(&self.kind.to_string());
+        json.push_str(",\"tags\":");
+        push_json_tags(&mut json, &self.tags);
+        json.push_str(",\"content\":");
+        push_json_str(&mut json, &self.content);
+        json.push_str(",\"sig\":\"");
+        json.push_str(&self.sig.to_string());
+        json.push_str("\"}");
+        json
+    }
+}
+
+fn push_json_tags(json: &mut String, tags: &[Vec<String>]) {
+    json.push('[');
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push('[');
+        for (j, value) in tag.iter().enumerate() {
+            if j > 0 {
+                json.push(',');
+            }
+            push_json_str(json, value);
+        }
+        json.push(']');
+    }
+    json.push(']');
+}
+
+/// Push JSON string, escaped as `serde_json` does
+fn push_json_str(json: &mut String, s: &str) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\u{08}' => json.push_str("\\b"),
+            '\u{0c}' => json.push_str("\\f"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                json.push_str("\\u00");
+                json.push(HEX[(c as usize) >> 4] as char);
+                json.push(HEX[(c as usize) & 0xf] as char);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(all(test, feature = "base"))]
+mod tests {
+    use super::*;
+    use crate::{Event, EventId, Keys, Kind, Tag, Timestamp};
+
+    #[test]
+    fn test_raw_event() {
+        let secp = Secp256k1::new();
+        let keys = Keys::generate();
+        let keypair = keys.key_pair().unwrap();
+        let content = "Hello \"nostr\" \\ \n\t\u{1}\u{7f} é / 🦀";
+        let tags: Vec<Vec<String>> = vec![
+            vec![String::from("t"), String::from("nostr")],
+            vec![String::from("subject"), String::from("a\"b\r")],
+        ];
+
+        let raw = RawEventBuilder::new(1, content, tags.clone()).to_event(
+            &secp,
+            &keypair,
+            1_671_739_153,
+            &[7u8; 32],
+        );
+        let tags: Vec<Tag> = tags.into_iter().map(|t| Tag::parse(t).unwrap()).collect();
+        let id = EventId::new(
+            &keys.public_key(),
+            Timestamp::from(1_671_739_153),
+            &Kind::TextNote,
+            &tags,
+            content,
+        );
+        assert_eq!(raw.id, id.inner());
+
+        let event = Event::from_json(raw.as_json()).unwrap();
+        assert!(event.verify().is_ok());
+        assert_eq!(event.content, content);
+        assert_eq!(event.tags, tags);
+    }
+}
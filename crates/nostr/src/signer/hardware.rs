@@ -0,0 +1,280 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Hardware signer
+//!
+//! Sign with an external device (ex. over serial or HID) holding the secret key, so it's never exposed to the host.
+//!
+//! # Protocol
+//!
+//! Requests and responses are frames: `[type: u8][length: u16 big endian][payload]`.
+//!
+//! | Type   | Name         | Payload                                                         |
+//! |--------|--------------|-----------------------------------------------------------------|
+//! | `0x01` | `GET_PUBKEY` | `0x01` to ask the user to confirm on the device, `0x00` otherwise |
+//! | `0x02` | `SIGN_EVENT` | unsigned event JSON, shown to the user for confirmation          |
+//! | `0x80` | `OK`         | 32 bytes x-only public key or 64 bytes schnorr signature          |
+//! | `0x81` | `REJECTED`   | empty: the user rejected the request on the device               |
+//! | `0x82` | `ERROR`      | UTF-8 error message                                              |
+//!
+//! Signatures returned by the device are verified before building the [`Event`].
+//! Transports with fixed-size reports (ex. 64 bytes HID reports) can be adapted with a [`Read`] + [`Write`] wrapper.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::{Mutex, PoisonError};
+
+use secp256k1::schnorr::Signature;
+use secp256k1::XOnlyPublicKey;
+
+use super::{Approval, Error, Signer};
+use crate::event::UnsignedEvent;
+use crate::Event;
+
+/// `GET_PUBKEY` request
+pub const GET_PUBKEY: u8 = 0x01;
+/// `SIGN_EVENT` request
+pub const SIGN_EVENT: u8 = 0x02;
+/// `OK` response
+pub const OK: u8 = 0x80;
+/// `REJECTED` response
+pub const REJECTED: u8 = 0x81;
+/// `ERROR` response
+pub const ERROR: u8 = 0x82;
+
+/// Write frame to `writer`
+pub fn write_frame<W>(writer: &mut W, kind: u8, payload: &[u8]) -> Result<(), Error>
+where
+    W: Write,
+{
+    let len: u16 =
+        u16::try_from(payload.len()).map_err(|_| Error::PayloadTooLarge(payload.len()))?;
+    writer.write_all(&[kind])?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read frame from `reader`
+pub fn read_frame<R>(reader: &mut R) -> Result<(u8, Vec<u8>), Error>
+where
+    R: Read,
+{
+    let mut header = [0u8; 3];
+    reader.read_exact(&mut header)?;
+    let len: usize = u16::from_be_bytes([header[1], header[2]]) as usize;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+/// Hardware signer
+///
+/// Talk to a signing device over any [`Read`] + [`Write`] transport (ex. a serial port).
+/// The public key is cached after the first request.
+pub struct HardwareSigner<T>
+where
+    T: Read + Write + Send,
+{
+    transport: Mutex<T>,
+    public_key: Mutex<Option<XOnlyPublicKey>>,
+}
+
+impl<T> fmt::Debug for HardwareSigner<T>
+where
+    T: Read + Write + Send,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HardwareSigner")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> HardwareSigner<T>
+where
+    T: Read + Write + Send,
+{
+    /// New [`HardwareSigner`]
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport: Mutex::new(transport),
+            public_key: Mutex::new(None),
+        }
+    }
+
+    /// Get public key from the device
+    ///
+    /// If `confirm` is `true`, the device asks the user to confirm the public key.
+    pub fn get_public_key(&self, confirm: bool) -> Result<XOnlyPublicKey, Error> {
+        let payload: Vec<u8> = self.request(GET_PUBKEY, &[u8::from(confirm)])?;
+        let public_key = XOnlyPublicKey::from_slice(&payload)
+            .map_err(|e| Error::InvalidResponse(e.to_string()))?;
+        let mut cached = self
+            .public_key
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *cached = Some(public_key);
+        Ok(public_key)
+    }
+
+    fn request(&self, kind: u8, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut transport = self
+            .transport
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        write_frame(&mut *transport, kind, payload)?;
+        let (status, payload) = read_frame(&mut *transport)?;
+        match status {
+            OK => Ok(payload),
+            REJECTED => Err(Error::Rejected),
+            ERROR => Err(Error::Device(String::from_utf8_lossy(&payload).to_string())),
+            status => Err(Error::InvalidResponse(format!(
+                "unknown status {status:#04x}"
+            ))),
+        }
+    }
+}
+
+impl<T> Signer for HardwareSigner<T>
+where
+    T: Read + Write + Send,
+{
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        let cached: Option<XOnlyPublicKey> = *self
+            .public_key
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match cached {
+            Some(public_key) => Ok(public_key),
+            None => self.get_public_key(false),
+        }
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        if unsigned.pubkey != self.public_key()? {
+            return Err(Error::PublicKeyMismatch);
+        }
+        if let Approval::Reject(reason) = self.approve(&unsigned) {
+            return Err(Error::NotApproved(reason));
+        }
+        let json: Vec<u8> = serde_json::to_vec(&unsigned)?;
+        let payload: Vec<u8> = self.request(SIGN_EVENT, &json)?;
+        let sig =
+            Signature::from_slice(&payload).map_err(|e| Error::InvalidResponse(e.to_string()))?;
+        Ok(unsigned.add_signature(sig)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io;
+
+    use secp256k1::{Message, Secp256k1};
+
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    /// In-memory device holding [`Keys`]
+    struct MockDevice {
+        keys: Keys,
+        approve: bool,
+        input: Vec<u8>,
+        output: VecDeque<u8>,
+    }
+
+    impl MockDevice {
+        fn new(keys: Keys, approve: bool) -> Self {
+            Self {
+                keys,
+                approve,
+                input: Vec::new(),
+                output: VecDeque::new(),
+            }
+        }
+
+        fn respond(&mut self, kind: u8, payload: &[u8]) {
+            let mut frame: Vec<u8> = Vec::new();
+            write_frame(&mut frame, kind, payload).unwrap();
+            self.output.extend(frame);
+        }
+
+        fn handle(&mut self, kind: u8, payload: Vec<u8>) {
+            if !self.approve {
+                return self.respond(REJECTED, &[]);
+            }
+            match kind {
+                GET_PUBKEY => {
+                    let public_key = self.keys.public_key().serialize();
+                    self.respond(OK, &public_key);
+                }
+                SIGN_EVENT => {
+                    let unsigned: UnsignedEvent = serde_json::from_slice(&payload).unwrap();
+                    let secp = Secp256k1::new();
+                    let message = Message::from_slice(unsigned.id.as_bytes()).unwrap();
+                    let sig = secp.sign_schnorr(&message, &self.keys.key_pair().unwrap());
+                    self.respond(OK, sig.as_ref());
+                }
+                _ => self.respond(ERROR, b"unknown command"),
+            }
+        }
+    }
+
+    impl Write for MockDevice {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.input.extend_from_slice(buf);
+            while self.input.len() >= 3 {
+                let len = u16::from_be_bytes([self.input[1], self.input[2]]) as usize;
+                if self.input.len() < 3 + len {
+                    break;
+                }
+                let frame: Vec<u8> = self.input.drain(..3 + len).collect();
+                self.handle(frame[0], frame[3..].to_vec());
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for MockDevice {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.output.len());
+            for (i, byte) in self.output.drain(..len).enumerate() {
+                buf[i] = byte;
+            }
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_hardware_signer() {
+        let keys = Keys::generate();
+        let signer = HardwareSigner::new(MockDevice::new(keys.clone(), true));
+
+        assert_eq!(signer.get_public_key(true).unwrap(), keys.public_key());
+
+        let unsigned = EventBuilder::new_text_note("signed on device", &[])
+            .to_unsigned_event(keys.public_key());
+        let event = signer.sign_event(unsigned.clone()).unwrap();
+        assert_eq!(event.id, unsigned.id);
+        assert!(event.verify().is_ok());
+
+        let other = EventBuilder::new_text_note("other", &[])
+            .to_unsigned_event(Keys::generate().public_key());
+        assert!(matches!(
+            signer.sign_event(other),
+            Err(Error::PublicKeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_hardware_signer_rejected() {
+        let signer = HardwareSigner::new(MockDevice::new(Keys::generate(), false));
+        assert!(matches!(signer.public_key(), Err(Error::Rejected)));
+    }
+}
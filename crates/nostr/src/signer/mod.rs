@@ -0,0 +1,111 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Signer
+//!
+//! Abstraction over the holders of a secret key: local [`Keys`] or external devices.
+
+use std::fmt;
+
+use secp256k1::XOnlyPublicKey;
+
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::key::{self, Keys};
+use crate::Event;
+
+pub mod hardware;
+pub mod policy;
+#[cfg(feature = "secure-memory")]
+pub mod secure;
+
+pub use self::hardware::HardwareSigner;
+pub use self::policy::PolicySigner;
+#[cfg(feature = "secure-memory")]
+pub use self::secure::SecureKeys;
+
+/// [`Signer`] error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Unsigned event error
+    #[error(transparent)]
+    UnsignedEvent(#[from] unsigned::Error),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// I/O error
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+    /// Request rejected by the user
+    #[error("request rejected by the user")]
+    Rejected,
+    /// Error reported by the device
+    #[error("device error: {0}")]
+    Device(String),
+    /// Invalid response
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    /// Request payload too large for a frame
+    #[error("payload too large: {0} bytes")]
+    PayloadTooLarge(usize),
+    /// Unsigned event author doesn't match the signer public key
+    #[error("public key mismatch")]
+    PublicKeyMismatch,
+    /// Signing not approved by [`Signer::approve`]
+    #[error("signing not approved: {0}")]
+    NotApproved(String),
+    /// Impossible to lock memory
+    #[error("impossible to lock memory: {0}")]
+    MemoryLock(String),
+}
+
+/// Pre-sign approval
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    /// Sign
+    Approve,
+    /// Don't sign, with the reason
+    Reject(String),
+}
+
+impl Approval {
+    /// Check if approved
+    pub fn is_approved(&self) -> bool {
+        matches!(self, Self::Approve)
+    }
+}
+
+/// Signer
+pub trait Signer: fmt::Debug + Send + Sync {
+    /// Get public key
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error>;
+
+    /// Pre-sign hook (default: approve all)
+    ///
+    /// Called by [`Signer::sign_event`] before signing: a rejection is returned as [`Error::NotApproved`].
+    /// See [`PolicySigner`] to add rules to an existing signer.
+    fn approve(&self, _unsigned: &UnsignedEvent) -> Approval {
+        Approval::Approve
+    }
+
+    /// Sign [`UnsignedEvent`]
+    fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error>;
+}
+
+impl Signer for Keys {
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(Keys::public_key(self))
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        if unsigned.pubkey != Keys::public_key(self) {
+            return Err(Error::PublicKeyMismatch);
+        }
+        if let Approval::Reject(reason) = self.approve(&unsigned) {
+            return Err(Error::NotApproved(reason));
+        }
+        Ok(unsigned.sign(self)?)
+    }
+}
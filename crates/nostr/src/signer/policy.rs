@@ -0,0 +1,184 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Signing policy
+//!
+//! Guardrails at the signing boundary: wrap a [`Signer`] with rules approving or rejecting
+//! every [`UnsignedEvent`] before it's signed (ex. never sign deletions, rate-limit DMs or ask the user).
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+use secp256k1::XOnlyPublicKey;
+
+use super::{Approval, Error, Signer};
+use crate::event::unsigned::UnsignedEvent;
+use crate::{Event, Kind};
+
+type Rule = Box<dyn Fn(&UnsignedEvent) -> Approval + Send + Sync>;
+
+/// [`Signer`] with approval rules
+///
+/// Rules are evaluated in order, before the [`Signer::approve`] of the inner signer: the first rejection wins.
+pub struct PolicySigner<S> {
+    signer: S,
+    rules: Vec<Rule>,
+}
+
+impl<S> fmt::Debug for PolicySigner<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicySigner")
+            .field("signer", &self.signer)
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl<S> PolicySigner<S>
+where
+    S: Signer,
+{
+    /// New [`PolicySigner`] without rules
+    pub fn new(signer: S) -> Self {
+        Self {
+            signer,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Get inner [`Signer`]
+    pub fn inner(&self) -> &S {
+        &self.signer
+    }
+
+    /// Add rule (ex. interactive confirmation)
+    pub fn rule<F>(mut self, rule: F) -> Self
+    where
+        F: Fn(&UnsignedEvent) -> Approval + Send + Sync + 'static,
+    {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Never sign events of `kinds`
+    pub fn deny_kinds(self, kinds: Vec<Kind>) -> Self {
+        self.rule(move |unsigned| {
+            if kinds.contains(&unsigned.kind) {
+                Approval::Reject(format!("kind {} not allowed", unsigned.kind.as_u64()))
+            } else {
+                Approval::Approve
+            }
+        })
+    }
+
+    /// Sign at most `max` events of `kind` every `interval`
+    ///
+    /// Every event of `kind` reaching this rule is counted, so add it after the other rules.
+    pub fn rate_limit(self, kind: Kind, max: usize, interval: Duration) -> Self {
+        let signed: Mutex<VecDeque<Instant>> = Mutex::new(VecDeque::new());
+        self.rule(move |unsigned| {
+            if unsigned.kind != kind {
+                return Approval::Approve;
+            }
+            let mut signed = signed.lock().unwrap_or_else(PoisonError::into_inner);
+            while let Some(oldest) = signed.front() {
+                if oldest.elapsed() < interval {
+                    break;
+                }
+                signed.pop_front();
+            }
+            if signed.len() >= max {
+                return Approval::Reject(format!("rate limit of kind {} exceeded", kind.as_u64()));
+            }
+            signed.push_back(Instant::now());
+            Approval::Approve
+        })
+    }
+
+    fn check_rules(&self, unsigned: &UnsignedEvent) -> Approval {
+        for rule in self.rules.iter() {
+            if let Approval::Reject(reason) = rule(unsigned) {
+                return Approval::Reject(reason);
+            }
+        }
+        Approval::Approve
+    }
+}
+
+impl<S> Signer for PolicySigner<S>
+where
+    S: Signer,
+{
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        self.signer.public_key()
+    }
+
+    fn approve(&self, unsigned: &UnsignedEvent) -> Approval {
+        match self.check_rules(unsigned) {
+            Approval::Approve => self.signer.approve(unsigned),
+            rejected => rejected,
+        }
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        // The inner signer checks its own approval
+        if let Approval::Reject(reason) = self.check_rules(&unsigned) {
+            return Err(Error::NotApproved(reason));
+        }
+        self.signer.sign_event(unsigned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_policy_signer() {
+        let keys = Keys::generate();
+        let signer = PolicySigner::new(keys.clone())
+            .deny_kinds(vec![Kind::EventDeletion])
+            .rule(|unsigned| {
+                if unsigned.content.contains("secret") {
+                    Approval::Reject(String::from("confirmation denied"))
+                } else {
+                    Approval::Approve
+                }
+            })
+            .rate_limit(Kind::EncryptedDirectMessage, 2, Duration::from_secs(60));
+
+        let unsigned = |kind: Kind, content: &str| {
+            EventBuilder::new(kind, content, &[]).to_unsigned_event(keys.public_key())
+        };
+
+        assert!(signer.sign_event(unsigned(Kind::TextNote, "hello")).is_ok());
+        assert!(matches!(
+            signer.sign_event(unsigned(Kind::EventDeletion, "")),
+            Err(Error::NotApproved(_))
+        ));
+        assert!(matches!(
+            signer.sign_event(unsigned(Kind::TextNote, "my secret")),
+            Err(Error::NotApproved(reason)) if reason == "confirmation denied"
+        ));
+
+        for _ in 0..2 {
+            assert!(signer
+                .sign_event(unsigned(Kind::EncryptedDirectMessage, "dm"))
+                .is_ok());
+        }
+        assert!(signer
+            .sign_event(unsigned(Kind::EncryptedDirectMessage, "dm"))
+            .is_err());
+        assert!(signer.sign_event(unsigned(Kind::TextNote, "hi")).is_ok());
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Secure keys
+//!
+//! Long-lived secret key kept in a dedicated heap allocation, optionally locked in memory
+//! (`mlock`/`VirtualLock`, never swapped to disk) and zeroized on drop.
+
+use std::fmt;
+use std::mem;
+
+use region::LockGuard;
+use secp256k1::{KeyPair, Message, Secp256k1, SecretKey, XOnlyPublicKey};
+use zeroize::Zeroize;
+
+use super::{Approval, Error, Signer};
+use crate::event::unsigned::UnsignedEvent;
+use crate::Event;
+
+/// [`Signer`] with a secret key protected in memory
+///
+/// The secret key is zeroized when dropped. Clones are not supported: every copy of the secret
+/// would need the same protection.
+pub struct SecureKeys {
+    public_key: XOnlyPublicKey,
+    // Declared before the key pair: unlocked before the allocation is freed
+    lock: Option<LockGuard>,
+    key_pair: Box<KeyPair>,
+}
+
+impl fmt::Debug for SecureKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureKeys")
+            .field("public_key", &self.public_key)
+            .field("locked", &self.is_locked())
+            .finish()
+    }
+}
+
+impl SecureKeys {
+    /// New [`SecureKeys`], zeroized on drop
+    pub fn new(secret_key: SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let key_pair = Box::new(KeyPair::from_secret_key(&secp, &secret_key));
+        Self {
+            public_key: XOnlyPublicKey::from_keypair(&key_pair).0,
+            lock: None,
+            key_pair,
+        }
+    }
+
+    /// New [`SecureKeys`], zeroized on drop and with the memory pages of the secret key locked
+    ///
+    /// Fails if the pages can't be locked (ex. `RLIMIT_MEMLOCK` exceeded).
+    /// Pages are shared with other allocations: unlocking happens when this is dropped.
+    pub fn locked(secret_key: SecretKey) -> Result<Self, Error> {
+        let mut keys = Self::new(secret_key);
+        let lock = region::lock(&*keys.key_pair as *const KeyPair, mem::size_of::<KeyPair>())
+            .map_err(|e| Error::MemoryLock(e.to_string()))?;
+        keys.lock = Some(lock);
+        Ok(keys)
+    }
+
+    /// Check if the memory pages of the secret key are locked
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Call `f` with a temporary copy of the secret key (ex. for NIP-04 or NIP-44), zeroized after the call
+    pub fn with_secret_key<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&SecretKey) -> R,
+    {
+        let mut secret_key = SecretKey::from_keypair(&self.key_pair);
+        let result = f(&secret_key);
+        // SAFETY: `SecretKey` is a plain byte array, valid for any bit pattern
+        unsafe { wipe_value(&mut secret_key) };
+        result
+    }
+
+    fn wipe(&mut self) {
+        // SAFETY: `KeyPair` is a plain byte array, valid for any bit pattern
+        unsafe { wipe_value(&mut *self.key_pair) };
+    }
+
+    #[cfg(test)]
+    fn key_pair_bytes(&self) -> &[u8] {
+        let ptr = &*self.key_pair as *const KeyPair as *const u8;
+        // SAFETY: reading the bytes of a live allocation
+        unsafe { std::slice::from_raw_parts(ptr, mem::size_of::<KeyPair>()) }
+    }
+}
+
+impl Drop for SecureKeys {
+    fn drop(&mut self) {
+        self.wipe();
+    }
+}
+
+/// Zeroize the memory of `value`
+///
+/// # Safety
+///
+/// All zeros must be a valid bit pattern for `T`.
+unsafe fn wipe_value<T>(value: &mut T) {
+    let bytes = std::slice::from_raw_parts_mut(value as *mut T as *mut u8, mem::size_of::<T>());
+    bytes.zeroize();
+}
+
+impl Signer for SecureKeys {
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.public_key)
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        if unsigned.pubkey != self.public_key {
+            return Err(Error::PublicKeyMismatch);
+        }
+        if let Approval::Reject(reason) = self.approve(&unsigned) {
+            return Err(Error::NotApproved(reason));
+        }
+        let secp = Secp256k1::new();
+        let message =
+            Message::from_slice(unsigned.id.as_bytes()).map_err(|e| Error::Key(e.into()))?;
+        let sig = secp.sign_schnorr(&message, &self.key_pair);
+        Ok(unsigned.add_signature(sig)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Kind};
+
+    #[test]
+    fn test_secure_keys() {
+        let keys = Keys::generate();
+        let secret_key = keys.secret_key().unwrap();
+        let secret_bytes = secret_key.secret_bytes();
+
+        let mut secure = SecureKeys::new(secret_key);
+        assert_eq!(Signer::public_key(&secure).unwrap(), keys.public_key());
+
+        let unsigned =
+            EventBuilder::new(Kind::TextNote, "hello", &[]).to_unsigned_event(keys.public_key());
+        assert!(secure.sign_event(unsigned).unwrap().verify().is_ok());
+        assert_eq!(secure.with_secret_key(|sk| sk.secret_bytes()), secret_bytes);
+
+        // Heap inspection: the secret is in the allocation until wiped
+        let contains_secret =
+            |bytes: &[u8]| bytes.windows(32).any(|w| w == secret_bytes.as_slice());
+        assert!(contains_secret(secure.key_pair_bytes()));
+        secure.wipe();
+        assert!(secure.key_pair_bytes().iter().all(|b| *b == 0));
+
+        // Locking may be denied by the environment (ex. `RLIMIT_MEMLOCK`)
+        match SecureKeys::locked(secret_key) {
+            Ok(secure) => assert!(secure.is_locked()),
+            Err(e) => assert!(matches!(e, Error::MemoryLock(_))),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Test vectors
+//!
+//! Canonical fixtures used by the golden tests of this crate. Exposed with the `test-vectors`
+//! feature, so downstream crates and bindings can prove conformance with the same data.
+//!
+//! Only deterministic data is included: NIPs whose output depends on random keys or nonces
+//! (ex. NIP-59 gift wraps, NIP-46 and NIP-60 payloads), or that have no canonical fixtures
+//! (ex. NIP-05 and NIP-11 documents fetched from servers), are covered by the unit tests of their modules.
+
+/// NIP-01: events
+pub mod nip01 {
+    /// Signed event (kind 4)
+    pub const EVENT_JSON: &str = r#"{"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","created_at":1640839235,"kind":4,"tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd"}"#;
+    /// Id of [`EVENT_JSON`]
+    pub const EVENT_ID: &str = "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45";
+    /// Author of [`EVENT_JSON`]
+    pub const EVENT_PUBKEY: &str =
+        "f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785";
+}
+
+/// NIP-04: encrypted direct messages
+pub mod nip04 {
+    /// Sender secret key
+    pub const SENDER_SECRET_KEY: &str =
+        "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e";
+    /// Receiver secret key
+    pub const RECEIVER_SECRET_KEY: &str =
+        "7b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e";
+    /// Plaintext
+    pub const PLAINTEXT: &str = "Saturn, bringer of old age";
+    /// [`PLAINTEXT`] encrypted by the sender to the receiver
+    pub const CIPHERTEXT: &str =
+        "dJc+WbBgaFCD2/kfg1XCWJParplBDxnZIdJGZ6FCTOg=?iv=M6VxRPkMZu7aIdD+10xPuw==";
+}
+
+/// NIP-06: keys from mnemonic
+pub mod nip06 {
+    /// Mnemonic (BIP-39), without passphrase
+    pub const MNEMONIC: &str = "equal dragon fabric refuse stable cherry smoke allow alley easy never medal attend together lumber movie what sad siege weather matrix buffalo state shoot";
+    /// Secret key derived from [`MNEMONIC`]
+    pub const SECRET_KEY: &str = "06992419a8fe821dd8de03d4c300614e8feefb5ea936b76f89976dcace8aebee";
+}
+
+/// NIP-13: proof of work
+pub mod nip13 {
+    /// Hashes with their number of leading zero bits
+    pub const LEADING_ZERO_BITS: &[(&str, u8)] = &[
+        (
+            "0fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            4,
+        ),
+        (
+            "1fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            3,
+        ),
+        (
+            "000006d8c378af1779d2feebc7603a125d99eca0ccf1085959b307f64e5dd358",
+            21,
+        ),
+    ];
+}
+
+/// NIP-19: bech32 entities
+pub mod nip19 {
+    /// Public key (hex)
+    pub const PUBLIC_KEY: &str = "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4";
+    /// [`PUBLIC_KEY`] bech32 encoded
+    pub const NPUB: &str = "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy";
+    /// Secret key (hex)
+    pub const SECRET_KEY: &str = "9571a568a42b9e05646a349c783159b906b498119390df9a5a02667155128028";
+    /// [`SECRET_KEY`] bech32 encoded
+    pub const NSEC: &str = "nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99";
+    /// Event id (hex)
+    pub const EVENT_ID: &str = "d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5";
+    /// [`EVENT_ID`] bech32 encoded
+    pub const NOTE: &str = "note1m99r7nwc0wdrkzldrqan96gklg5usqspq7z9696j6unf0ljnpxjspqfw99";
+    /// Profile public key (hex)
+    pub const PROFILE_PUBLIC_KEY: &str =
+        "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+    /// Profile relays
+    pub const PROFILE_RELAYS: &[&str] = &["wss://r.x.com", "wss://djbas.sadkb.com"];
+    /// Profile with [`PROFILE_PUBLIC_KEY`] and [`PROFILE_RELAYS`] bech32 encoded
+    pub const NPROFILE: &str = "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+}
+
+/// NIP-21: `nostr:` URIs
+pub mod nip21 {
+    /// Public key (hex)
+    pub const PUBLIC_KEY: &str = "84dee6e676e5bb67b4ad4e042cf70cbd8681155db535942fcc6a0533858a7240";
+    /// [`PUBLIC_KEY`] as `nostr:` URI
+    pub const NPUB_URI: &str =
+        "nostr:npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+    /// Profile of [`super::nip19::NPROFILE`] as `nostr:` URI
+    pub const NPROFILE_URI: &str = "nostr:nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+}
+
+/// NIP-26: delegated event signing
+pub mod nip26 {
+    /// Delegator secret key
+    pub const DELEGATOR_SECRET_KEY: &str =
+        "ee35e8bb71131c02c1d7e73231daa48e9953d329a4b701f7133c8f46dd21139c";
+    /// Delegator public key
+    pub const DELEGATOR_PUBLIC_KEY: &str =
+        "8e0d3d3eb2881ec137a11debe736a9086715a8c8beeeda615780064d68bc25dd";
+    /// Delegatee public key
+    pub const DELEGATEE_PUBLIC_KEY: &str =
+        "477318cfb5427b9cfc66a9fa376150c1ddbc62115ae27cef72417eb959691396";
+    /// Conditions
+    pub const CONDITIONS: &str = "kind=1&created_at>1674834236&created_at<1677426236";
+    /// Delegation token: signature of [`CONDITIONS`] for [`DELEGATEE_PUBLIC_KEY`] by the delegator
+    pub const TOKEN: &str = "6f44d7fe4f1c09f3954640fb58bd12bae8bb8ff4120853c4693106c82e920e2b898f1f9ba9bd65449a987c39c0423426ab7b53910c0c6abfb41b30bc16e5f524";
+}
+
+/// NIP-44: versioned encryption
+pub mod nip44 {
+    /// First secret key
+    pub const SECRET_KEY_1: &str =
+        "0000000000000000000000000000000000000000000000000000000000000001";
+    /// Second secret key
+    pub const SECRET_KEY_2: &str =
+        "0000000000000000000000000000000000000000000000000000000000000002";
+    /// Conversation key of [`SECRET_KEY_1`] and the public key of [`SECRET_KEY_2`]
+    pub const CONVERSATION_KEY: &str =
+        "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d";
+    /// Nonce: 31 zero bytes followed by `0x01`
+    pub const NONCE: [u8; 32] = {
+        let mut nonce = [0u8; 32];
+        nonce[31] = 1;
+        nonce
+    };
+    /// Plaintext
+    pub const PLAINTEXT: &str = "a";
+    /// [`PLAINTEXT`] encrypted with [`CONVERSATION_KEY`] and [`NONCE`]
+    pub const PAYLOAD: &str = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb";
+}
+
+/// NIP-49: private key encryption
+pub mod nip49 {
+    /// Password
+    pub const PASSWORD: &str = "nostr";
+    /// Secret key
+    pub const SECRET_KEY: &str = "3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683";
+    /// [`SECRET_KEY`] encrypted with [`PASSWORD`] (`log_n` 16, weak key security)
+    pub const NCRYPTSEC: &str = "ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p";
+}
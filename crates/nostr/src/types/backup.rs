@@ -0,0 +1,157 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Backup
+//!
+//! Parse events exported by other clients and relays. Supported layouts, detected automatically:
+//!
+//! * JSON array of events
+//! * JSON object with an `events` array
+//! * one event per line (JSONL, ex. `strfry export` dumps)
+//! * relay messages (`["EVENT", <subscription_id>, <event>]`), as array or one per line (ex. websocket dumps)
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{Event, EventId};
+
+/// Parsed backup
+#[derive(Debug, Clone, Default)]
+pub struct Backup {
+    /// Valid events, in backup order
+    pub events: Vec<Event>,
+    /// Number of events present more than once
+    pub duplicates: usize,
+    /// Number of invalid entries (malformed JSON, wrong id or invalid signature)
+    pub invalid: usize,
+}
+
+impl Backup {
+    /// Parse backup
+    ///
+    /// Every event is strictly validated: the id must match the content and the signature must verify.
+    /// Invalid entries and duplicates are skipped and counted.
+    pub fn parse<S>(data: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let data: &str = data.as_ref();
+        let mut backup = Self::default();
+        let mut ids: HashSet<EventId> = HashSet::new();
+
+        match serde_json::from_str::<Value>(data) {
+            Ok(value) => backup.add_value(value, &mut ids),
+            Err(_) => {
+                // JSONL
+                for line in data.lines().filter(|line| !line.trim().is_empty()) {
+                    match serde_json::from_str::<Value>(line) {
+                        Ok(value) => backup.add_value(value, &mut ids),
+                        Err(_) => backup.invalid += 1,
+                    }
+                }
+            }
+        }
+
+        backup
+    }
+
+    fn add_value(&mut self, value: Value, ids: &mut HashSet<EventId>) {
+        match value {
+            Value::Array(values) => {
+                if is_relay_message(&values) {
+                    self.add_event(values.into_iter().last(), ids);
+                } else {
+                    for value in values.into_iter() {
+                        self.add_value(value, ids);
+                    }
+                }
+            }
+            Value::Object(mut object) => match object.remove("events") {
+                Some(Value::Array(events)) => {
+                    for value in events.into_iter() {
+                        self.add_value(value, ids);
+                    }
+                }
+                _ => self.add_event(Some(Value::Object(object)), ids),
+            },
+            _ => self.invalid += 1,
+        }
+    }
+
+    fn add_event(&mut self, value: Option<Value>, ids: &mut HashSet<EventId>) {
+        let event: Option<Event> = value
+            .and_then(|value| serde_json::from_value::<Event>(value).ok())
+            .filter(is_valid);
+        match event {
+            Some(event) => {
+                if ids.insert(event.id) {
+                    self.events.push(event);
+                } else {
+                    self.duplicates += 1;
+                }
+            }
+            None => self.invalid += 1,
+        }
+    }
+}
+
+/// Check if `values` is an `EVENT` relay message
+fn is_relay_message(values: &[Value]) -> bool {
+    matches!(values.first(), Some(Value::String(t)) if t == "EVENT")
+        && matches!(values.last(), Some(Value::Object(..)))
+}
+
+/// Check id and signature
+fn is_valid(event: &Event) -> bool {
+    let id = EventId::new(
+        &event.pubkey,
+        event.created_at,
+        &event.kind,
+        &event.tags,
+        &event.content,
+    );
+    id == event.id && event.verify().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_parse_backup() {
+        let keys = Keys::generate();
+        let a = EventBuilder::new_text_note("a", &[])
+            .to_event(&keys)
+            .unwrap();
+        let b = EventBuilder::new_text_note("b", &[])
+            .to_event(&keys)
+            .unwrap();
+        let a_json = a.as_json().unwrap();
+        let b_json = b.as_json().unwrap();
+
+        // JSON array
+        let backup = Backup::parse(format!("[{a_json},{b_json},{a_json}]"));
+        assert_eq!(backup.events, vec![a.clone(), b.clone()]);
+        assert_eq!(backup.duplicates, 1);
+
+        // Object with events
+        let backup = Backup::parse(format!(r#"{{"version":1,"events":[{a_json}]}}"#));
+        assert_eq!(backup.events, vec![a.clone()]);
+
+        // JSONL with relay messages and an invalid line
+        let backup = Backup::parse(format!(
+            "{a_json}\n[\"EVENT\",\"sub\",{b_json}]\n\nnot json\n"
+        ));
+        assert_eq!(backup.events, vec![a.clone(), b]);
+        assert_eq!(backup.invalid, 1);
+
+        // Tampered content
+        let mut tampered = a;
+        tampered.content = String::from("tampered");
+        let backup = Backup::parse(format!("[{}]", tampered.as_json().unwrap()));
+        assert!(backup.events.is_empty());
+        assert_eq!(backup.invalid, 1);
+    }
+}
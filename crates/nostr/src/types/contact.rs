@@ -3,9 +3,12 @@
 
 //! Contact
 
-use bitcoin::secp256k1::XOnlyPublicKey;
+use secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
 
+use crate::event::{Event, Kind, Tag};
+use crate::{SubscriptionFilter, Timestamp};
+
 /// Contact
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
 pub struct Contact {
@@ -30,3 +33,179 @@ impl Contact {
         }
     }
 }
+
+/// Contact list (NIP-02)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/02.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContactList {
+    /// Contacts
+    pub contacts: Vec<Contact>,
+    /// Creation time of the contact list event
+    pub created_at: Timestamp,
+}
+
+impl Default for ContactList {
+    fn default() -> Self {
+        Self::new(Vec::new(), Timestamp::from(0))
+    }
+}
+
+impl ContactList {
+    /// Create new [`ContactList`]
+    pub fn new(contacts: Vec<Contact>, created_at: Timestamp) -> Self {
+        Self {
+            contacts,
+            created_at,
+        }
+    }
+
+    /// Get [`ContactList`] from contact list [`Event`]
+    ///
+    /// Return `None` if the event is not a contact list.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if event.kind != Kind::ContactList {
+            return None;
+        }
+
+        let mut contacts: Vec<Contact> = Vec::new();
+        for tag in event.tags.iter() {
+            let contact = match tag {
+                Tag::PubKey(pk, relay_url) => Contact::new(*pk, relay_url.clone(), None),
+                Tag::ContactList {
+                    pk,
+                    relay_url,
+                    alias,
+                } => Contact::new(*pk, relay_url.clone(), alias.clone()),
+                _ => continue,
+            };
+            if !contacts.iter().any(|c| c.pk == contact.pk) {
+                contacts.push(contact);
+            }
+        }
+
+        Some(Self::new(contacts, event.created_at))
+    }
+
+    /// Get public keys of contacts
+    pub fn public_keys(&self) -> Vec<XOnlyPublicKey> {
+        self.contacts.iter().map(|c| c.pk).collect()
+    }
+
+    /// Check if `public_key` is in the contact list
+    pub fn contains(&self, public_key: &XOnlyPublicKey) -> bool {
+        self.contacts.iter().any(|c| &c.pk == public_key)
+    }
+
+    /// Number of contacts
+    pub fn len(&self) -> usize {
+        self.contacts.len()
+    }
+
+    /// Check if the contact list is empty
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+
+    /// Build home feed filters: events of `kinds` by the contacts, `since` the given time
+    ///
+    /// Relays cap the size of filters, so authors are split in filters of up to `chunk_size`
+    /// public keys (at least 1). Return no filters if the contact list is empty.
+    pub fn feed_filters(
+        &self,
+        kinds: Vec<Kind>,
+        since: Option<Timestamp>,
+        chunk_size: usize,
+    ) -> Vec<SubscriptionFilter> {
+        self.public_keys()
+            .chunks(chunk_size.max(1))
+            .map(|authors| {
+                let filter = SubscriptionFilter::new()
+                    .authors(authors.to_vec())
+                    .kinds(kinds.clone());
+                match since {
+                    Some(since) => filter.since(since),
+                    None => filter,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_contact_list_from_event() {
+        let pk = XOnlyPublicKey::from_str(
+            "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
+        )
+        .unwrap();
+        let alice = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let keys = Keys::generate();
+
+        let event = EventBuilder::new(
+            Kind::ContactList,
+            "",
+            &[
+                Tag::PubKey(pk, None),
+                Tag::ContactList {
+                    pk: alice,
+                    relay_url: Some(String::from("wss://relay.damus.io")),
+                    alias: Some(String::from("alice")),
+                },
+                Tag::PubKey(pk, None),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        let list = ContactList::from_event(&event).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.created_at, event.created_at);
+        assert_eq!(list.public_keys(), vec![pk, alice]);
+        assert_eq!(list.contacts[1].alias, Some(String::from("alice")));
+        assert!(list.contains(&alice));
+
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(ContactList::from_event(&note).is_none());
+    }
+
+    #[test]
+    fn test_feed_filters() {
+        let contacts: Vec<Contact> = (0..5)
+            .map(|_| Contact::new::<String>(Keys::generate().public_key(), None, None))
+            .collect();
+        let list = ContactList::new(contacts, Timestamp::now());
+        let since = Timestamp::from(1_672_531_200);
+
+        let filters = list.feed_filters(vec![Kind::TextNote, Kind::Repost], Some(since), 2);
+        assert_eq!(filters.len(), 3);
+        assert_eq!(filters[2].authors.as_ref().unwrap().len(), 1);
+        let authors: Vec<XOnlyPublicKey> = filters
+            .iter()
+            .flat_map(|f| f.authors.clone().unwrap())
+            .collect();
+        assert_eq!(authors, list.public_keys());
+        assert!(
+            filters
+                .iter()
+                .all(|f| f.since == Some(since)
+                    && f.kinds == Some(vec![Kind::TextNote, Kind::Repost]))
+        );
+
+        assert_eq!(list.feed_filters(vec![Kind::TextNote], None, 0).len(), 5);
+        assert!(ContactList::default()
+            .feed_filters(vec![Kind::TextNote], None, 100)
+            .is_empty());
+    }
+}
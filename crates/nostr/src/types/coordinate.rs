@@ -0,0 +1,195 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Coordinate
+//!
+//! Reference to a (parameterized) replaceable event: `<kind>:<pubkey>:<d-identifier>`
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/33.md>
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use secp256k1::XOnlyPublicKey;
+use serde::de::Error as DeserializerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::relay_url::UncheckedUrl;
+use crate::event::tag::TagKind;
+use crate::{Kind, Tag};
+
+/// [`Coordinate`] error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid format
+    #[error("invalid coordinate: expected `<kind>:<pubkey>:<d-identifier>`")]
+    InvalidFormat,
+    /// Impossible to parse kind
+    #[error(transparent)]
+    Kind(#[from] ParseIntError),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+}
+
+/// Coordinate of a (parameterized) replaceable event
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Coordinate {
+    /// Kind
+    pub kind: Kind,
+    /// Author
+    pub pubkey: XOnlyPublicKey,
+    /// `d` tag identifier (empty for replaceable events)
+    pub identifier: String,
+    /// Relay hints
+    pub relays: Vec<UncheckedUrl>,
+}
+
+impl Coordinate {
+    /// New [`Coordinate`], with empty identifier
+    pub fn new(kind: Kind, pubkey: XOnlyPublicKey) -> Self {
+        Self {
+            kind,
+            pubkey,
+            identifier: String::new(),
+            relays: Vec::new(),
+        }
+    }
+
+    /// Set `d` tag identifier
+    pub fn identifier<S>(self, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            ..self
+        }
+    }
+
+    /// Add relay hint
+    pub fn relay<S>(mut self, relay: S) -> Self
+    where
+        S: Into<UncheckedUrl>,
+    {
+        self.relays.push(relay.into());
+        self
+    }
+
+    /// Parse `a` tag
+    ///
+    /// Return `None` if it's not an `a` tag or if the coordinate is invalid.
+    pub fn from_tag(tag: &Tag) -> Option<Self> {
+        match tag {
+            Tag::Generic(TagKind::A, values) => {
+                let mut coordinate = Self::from_str(values.first()?).ok()?;
+                if let Some(relay) = values.get(1).filter(|relay| !relay.is_empty()) {
+                    coordinate.relays.push(UncheckedUrl::from(relay.as_str()));
+                }
+                Some(coordinate)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.kind.as_u64(),
+            self.pubkey,
+            self.identifier
+        )
+    }
+}
+
+impl FromStr for Coordinate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(kind), Some(pubkey), Some(identifier)) => Ok(Self {
+                kind: Kind::from(kind.parse::<u64>()?),
+                pubkey: XOnlyPublicKey::from_str(pubkey)?,
+                identifier: identifier.to_string(),
+                relays: Vec::new(),
+            }),
+            _ => Err(Error::InvalidFormat),
+        }
+    }
+}
+
+/// `a` tag, with the first relay hint
+impl From<Coordinate> for Tag {
+    fn from(coordinate: Coordinate) -> Self {
+        let mut values: Vec<String> = vec![coordinate.to_string()];
+        if let Some(relay) = coordinate.relays.into_iter().next() {
+            values.push(relay.into());
+        }
+        Tag::Generic(TagKind::A, values)
+    }
+}
+
+/// Serialized as string, without relay hints
+impl Serialize for Coordinate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Coordinate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(DeserializerError::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a";
+
+    #[test]
+    fn test_coordinate() {
+        let pubkey = XOnlyPublicKey::from_str(PUBKEY).unwrap();
+        let coordinate = Coordinate::from_str(&format!("30023:{PUBKEY}:my:article")).unwrap();
+        assert_eq!(
+            coordinate,
+            Coordinate::new(Kind::from(30023), pubkey).identifier("my:article")
+        );
+        assert_eq!(coordinate.to_string(), format!("30023:{PUBKEY}:my:article"));
+
+        // Replaceable event, without identifier
+        let coordinate = Coordinate::from_str(&format!("0:{PUBKEY}:")).unwrap();
+        assert_eq!(coordinate.kind, Kind::Metadata);
+        assert!(coordinate.identifier.is_empty());
+
+        assert!(Coordinate::from_str(&format!("30023:{PUBKEY}")).is_err());
+        assert!(Coordinate::from_str("30023:abc:id").is_err());
+
+        // Tag
+        let tag = Tag::parse(vec![
+            "a".to_string(),
+            format!("30023:{PUBKEY}:id"),
+            "wss://relay.damus.io".to_string(),
+        ])
+        .unwrap();
+        let coordinate = Coordinate::from_tag(&tag).unwrap();
+        assert_eq!(
+            coordinate.relays,
+            vec![UncheckedUrl::from("wss://relay.damus.io")]
+        );
+        assert_eq!(Tag::from(coordinate), tag);
+    }
+}
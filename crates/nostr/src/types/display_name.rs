@@ -0,0 +1,113 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Display name
+
+use secp256k1::XOnlyPublicKey;
+
+use super::Metadata;
+#[cfg(feature = "nip19")]
+use crate::nips::nip19::ToBech32;
+
+/// Max number of characters of a display name
+pub const MAX_DISPLAY_NAME_LEN: usize = 32;
+
+/// Number of characters kept at both ends of a shortened public key
+const SHORT_PUBLIC_KEY_CHARS: usize = 8;
+
+/// Resolve the name to display for `public_key`
+///
+/// Petname (alias in the contact list of the user) > `display_name` > `name` > `nip05` > shortened `npub`.
+/// Blank values are skipped and names longer than [`MAX_DISPLAY_NAME_LEN`] characters are truncated with `…`.
+pub fn display_name(
+    public_key: &XOnlyPublicKey,
+    metadata: Option<&Metadata>,
+    petname: Option<&str>,
+) -> String {
+    let nip05 = metadata
+        .and_then(|m| m.nip05.as_deref())
+        .map(|nip05| nip05.trim().trim_start_matches("_@"));
+    petname
+        .into_iter()
+        .chain(metadata.and_then(|m| m.display_name.as_deref()))
+        .chain(metadata.and_then(|m| m.name.as_deref()))
+        .chain(nip05)
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .map(truncate)
+        .unwrap_or_else(|| shorten_public_key(public_key))
+}
+
+/// Shorten `public_key` (ex. `npub1abc…xyz`, hex without the `nip19` feature)
+pub fn shorten_public_key(public_key: &XOnlyPublicKey) -> String {
+    #[cfg(feature = "nip19")]
+    let key: String = public_key
+        .to_bech32()
+        .unwrap_or_else(|_| public_key.to_string());
+    #[cfg(not(feature = "nip19"))]
+    let key: String = public_key.to_string();
+
+    // Bech32 and hex are ASCII
+    let len: usize = key.len();
+    if len <= SHORT_PUBLIC_KEY_CHARS * 2 {
+        return key;
+    }
+    format!(
+        "{}…{}",
+        &key[..SHORT_PUBLIC_KEY_CHARS],
+        &key[len - SHORT_PUBLIC_KEY_CHARS..]
+    )
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() <= MAX_DISPLAY_NAME_LEN {
+        return name.to_string();
+    }
+    let mut truncated: String = name.chars().take(MAX_DISPLAY_NAME_LEN - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_display_name() {
+        let public_key = XOnlyPublicKey::from_str(
+            "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+        )
+        .unwrap();
+        let metadata = Metadata::new()
+            .name("yuki")
+            .display_name("  ")
+            .nip05("_@yukikishimoto.com");
+
+        assert_eq!(
+            display_name(&public_key, Some(&metadata), Some("Yuki K.")),
+            "Yuki K."
+        );
+        assert_eq!(display_name(&public_key, Some(&metadata), None), "yuki");
+        assert_eq!(
+            display_name(
+                &public_key,
+                Some(&Metadata::new().nip05("_@yukikishimoto.com")),
+                None
+            ),
+            "yukikishimoto.com"
+        );
+        assert_eq!(
+            display_name(
+                &public_key,
+                Some(&Metadata::new().name("a".repeat(40))),
+                None
+            ),
+            format!("{}…", "a".repeat(31))
+        );
+
+        #[cfg(feature = "nip19")]
+        assert_eq!(display_name(&public_key, None, None), "npub14f8…6qqh9nsy");
+    }
+}
@@ -3,15 +3,136 @@
 
 //! Entity
 
+use secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
 
+use crate::{Coordinate, Event, Kind};
+
 /// Nostr [`Entity`]
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Entity {
-    /// Account
-    Account,
-    /// Channel
-    Channel,
+    /// Account, with its latest metadata event, if any
+    Account {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// Metadata event
+        metadata: Option<Event>,
+    },
+    /// Public chat channel (NIP-28)
+    Channel(Event),
+    /// Moderated community (NIP-72)
+    Community(Event),
+    /// Parameterized replaceable event, if found
+    Coordinate {
+        /// Coordinate
+        coordinate: Coordinate,
+        /// Latest event
+        event: Option<Event>,
+    },
+    /// Other event
+    Event(Event),
     /// Unknown
     Unknown,
 }
+
+impl Entity {
+    /// Classify [`Event`]
+    ///
+    /// Metadata events are classified as [`Entity::Account`] of their author.
+    pub fn from_event(event: Event) -> Self {
+        match event.kind {
+            Kind::Metadata => Self::Account {
+                public_key: event.pubkey,
+                metadata: Some(event),
+            },
+            Kind::ChannelCreation => Self::Channel(event),
+            Kind::CommunityDefinition => Self::Community(event),
+            kind if kind.is_parameterized_replaceable() => {
+                let identifier: String = event
+                    .tags
+                    .iter()
+                    .find_map(|t| match t.as_vec().as_slice() {
+                        [tag, identifier, ..] if tag == "d" => Some(identifier.clone()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                Self::Coordinate {
+                    coordinate: Coordinate::new(kind, event.pubkey).identifier(identifier),
+                    event: Some(event),
+                }
+            }
+            _ => Self::Event(event),
+        }
+    }
+
+    /// Get the underlying [`Event`], if resolved
+    pub fn event(&self) -> Option<&Event> {
+        match self {
+            Self::Account { metadata, .. } => metadata.as_ref(),
+            Self::Channel(event) | Self::Community(event) | Self::Event(event) => Some(event),
+            Self::Coordinate { event, .. } => event.as_ref(),
+            Self::Unknown => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TagKind;
+    use crate::{EventBuilder, Keys, Metadata, Tag};
+
+    #[test]
+    fn test_entity_from_event() {
+        let keys = Keys::generate();
+
+        let metadata = EventBuilder::set_metadata(Metadata::new().name("myname"))
+            .unwrap()
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            Entity::from_event(metadata.clone()),
+            Entity::Account {
+                public_key: keys.public_key(),
+                metadata: Some(metadata),
+            }
+        );
+
+        let channel = EventBuilder::new(Kind::ChannelCreation, "{}", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            Entity::from_event(channel.clone()),
+            Entity::Channel(channel)
+        );
+
+        let d = || Tag::Generic(TagKind::D, vec![String::from("rust")]);
+        let community = EventBuilder::new(Kind::from(34550), "", &[d()])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(
+            Entity::from_event(community.clone()),
+            Entity::Community(community)
+        );
+
+        let article = EventBuilder::new(Kind::from(30023), "Hello", &[d()])
+            .to_event(&keys)
+            .unwrap();
+        let entity = Entity::from_event(article.clone());
+        assert_eq!(
+            entity,
+            Entity::Coordinate {
+                coordinate: Coordinate::new(Kind::from(30023), keys.public_key())
+                    .identifier("rust"),
+                event: Some(article.clone()),
+            }
+        );
+        assert_eq!(entity.event(), Some(&article));
+
+        let note = EventBuilder::new_text_note("Hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(Entity::from_event(note.clone()), Entity::Event(note));
+        assert_eq!(Entity::Unknown.event(), None);
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Impersonation checks
+
+use std::fmt;
+
+use secp256k1::XOnlyPublicKey;
+
+use super::Metadata;
+
+/// Impersonation warning
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImpersonationWarning {
+    /// The `nip05` identifier doesn't verify for the author
+    Nip05Unverified {
+        /// NIP-05 identifier
+        nip05: String,
+        /// Verification error
+        reason: String,
+    },
+    /// The name or display name collides with the one of a verified contact
+    NameCollision {
+        /// Name of the author
+        name: String,
+        /// Public key of the contact
+        contact: XOnlyPublicKey,
+        /// Name of the contact
+        contact_name: String,
+    },
+}
+
+impl fmt::Display for ImpersonationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nip05Unverified { nip05, reason } => {
+                write!(f, "NIP-05 {nip05} not verified: {reason}")
+            }
+            Self::NameCollision {
+                name,
+                contact,
+                contact_name,
+            } => write!(f, "name {name} collides with {contact_name} ({contact})"),
+        }
+    }
+}
+
+/// Normalize name for comparison
+///
+/// Case, whitespaces, separators and common lookalike characters (ex. `0` and `o`, cyrillic `а` and latin `a`) are ignored.
+pub fn normalize_name(name: &str) -> String {
+    name.trim_start_matches('@')
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '_' | '-' | '.'))
+        .map(|c| match c {
+            'I' => 'l',
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' | 'о' => 'o',
+            '1' | '|' => 'l',
+            '3' | 'е' => 'e',
+            '5' | '$' => 's',
+            'а' => 'a',
+            'с' => 'c',
+            'р' => 'p',
+            'х' => 'x',
+            'у' => 'y',
+            'і' => 'i',
+            _ => c,
+        })
+        .collect()
+}
+
+fn names(metadata: &Metadata) -> impl Iterator<Item = &String> {
+    metadata
+        .name
+        .iter()
+        .chain(metadata.display_name.iter())
+        .filter(|name| !name.trim().is_empty())
+}
+
+/// Find names of `metadata` colliding with the names of `verified_contacts`
+///
+/// Contacts with the same public key of the author are ignored.
+pub fn name_collisions(
+    public_key: &XOnlyPublicKey,
+    metadata: &Metadata,
+    verified_contacts: &[(XOnlyPublicKey, Metadata)],
+) -> Vec<ImpersonationWarning> {
+    let mut warnings: Vec<ImpersonationWarning> = Vec::new();
+    for (contact, contact_metadata) in verified_contacts.iter() {
+        if contact == public_key {
+            continue;
+        }
+
+        let collision = names(metadata).find_map(|name| {
+            let normalized: String = normalize_name(name);
+            names(contact_metadata)
+                .find(|contact_name| normalize_name(contact_name) == normalized)
+                .map(|contact_name| (name, contact_name))
+        });
+
+        if let Some((name, contact_name)) = collision {
+            warnings.push(ImpersonationWarning::NameCollision {
+                name: name.clone(),
+                contact: *contact,
+                contact_name: contact_name.clone(),
+            });
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Alice"), normalize_name("aIice"));
+        assert_eq!(
+            normalize_name("@jack_dorsey"),
+            normalize_name("Jack Dorsey")
+        );
+        assert_eq!(normalize_name("b0b"), normalize_name("bob"));
+        assert_eq!(normalize_name("\u{0430}lice"), normalize_name("alice"));
+        assert_ne!(normalize_name("alice"), normalize_name("alicia"));
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let author = XOnlyPublicKey::from_str(
+            "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
+        )
+        .unwrap();
+        let alice = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let contacts = vec![(alice, Metadata::new().name("alice").display_name("Alice"))];
+
+        let metadata = Metadata::new().name("a1ice");
+        assert_eq!(
+            name_collisions(&author, &metadata, &contacts),
+            vec![ImpersonationWarning::NameCollision {
+                name: String::from("a1ice"),
+                contact: alice,
+                contact_name: String::from("alice"),
+            }]
+        );
+
+        // Same public key
+        assert!(name_collisions(&alice, &metadata, &contacts).is_empty());
+
+        let metadata = Metadata::new().name("bob").display_name("");
+        assert!(name_collisions(&author, &metadata, &contacts).is_empty());
+    }
+}
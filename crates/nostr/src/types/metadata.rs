@@ -4,7 +4,8 @@
 //! Metadata
 
 use serde::{Deserialize, Serialize};
-use url::Url;
+use serde_json::{Map, Value};
+use url::{ParseError, Url};
 
 /// [`Metadata`] error
 #[derive(Debug, thiserror::Error)]
@@ -12,10 +13,95 @@ pub enum Error {
     /// Error serializing or deserializing JSON data
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
+    /// Invalid image url
+    #[error("invalid image url: {0}")]
+    InvalidImageUrl(#[from] ParseError),
+    /// Image url scheme not allowed
+    #[error("image url scheme not allowed: {0}")]
+    SchemeNotAllowed(String),
+    /// Image url longer than the max length
+    #[error("image url too long: {0} chars")]
+    ImageUrlTooLong(usize),
+}
+
+/// Validation of the picture and banner urls of [`Metadata`]
+///
+/// `data:` URIs are rejected unless allowed, whatever the scheme allowlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageUrlPolicy {
+    allowed_schemes: Vec<String>,
+    max_length: usize,
+    allow_data_uri: bool,
+}
+
+impl Default for ImageUrlPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: vec![String::from("https")],
+            max_length: 2048,
+            allow_data_uri: false,
+        }
+    }
+}
+
+impl ImageUrlPolicy {
+    /// New [`ImageUrlPolicy`] (default: `https` only, max 2048 chars, no `data:` URIs)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set allowed schemes
+    pub fn allowed_schemes<I, S>(self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_schemes: schemes
+                .into_iter()
+                .map(|s| s.into().to_lowercase())
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Set max length of the url
+    pub fn max_length(self, max_length: usize) -> Self {
+        Self { max_length, ..self }
+    }
+
+    /// Allow `data:` URIs
+    pub fn allow_data_uri(self, allow: bool) -> Self {
+        Self {
+            allow_data_uri: allow,
+            ..self
+        }
+    }
+
+    /// Check image url
+    pub fn check<S>(&self, url: S) -> Result<Url, Error>
+    where
+        S: AsRef<str>,
+    {
+        let url: &str = url.as_ref().trim();
+        let len: usize = url.chars().count();
+        if len > self.max_length {
+            return Err(Error::ImageUrlTooLong(len));
+        }
+        let url: Url = Url::parse(url)?;
+        let allowed: bool = match url.scheme() {
+            "data" => self.allow_data_uri,
+            scheme => self.allowed_schemes.iter().any(|s| s == scheme),
+        };
+        if !allowed {
+            return Err(Error::SchemeNotAllowed(url.scheme().to_string()));
+        }
+        Ok(url)
+    }
 }
 
 /// Metadata
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
     /// Name
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -44,6 +130,9 @@ pub struct Metadata {
     /// Lightning Address
     #[serde(skip_serializing_if = "Option::is_none")]
     pub lud16: Option<String>,
+    /// Other fields, kept as published
+    #[serde(flatten)]
+    pub custom: Map<String, Value>,
 }
 
 impl Default for Metadata {
@@ -65,6 +154,7 @@ impl Metadata {
             nip05: None,
             lud06: None,
             lud16: None,
+            custom: Map::new(),
         }
     }
 
@@ -81,6 +171,45 @@ impl Metadata {
         Ok(serde_json::to_string(&self)?)
     }
 
+    /// Merge [`Metadata`]
+    ///
+    /// Fields set in `other` take precedence, fields not set in `other` are kept.
+    pub fn merge(self, other: Metadata) -> Self {
+        let mut custom: Map<String, Value> = self.custom;
+        custom.extend(other.custom);
+        Self {
+            name: other.name.or(self.name),
+            display_name: other.display_name.or(self.display_name),
+            about: other.about.or(self.about),
+            website: other.website.or(self.website),
+            picture: other.picture.or(self.picture),
+            banner: other.banner.or(self.banner),
+            nip05: other.nip05.or(self.nip05),
+            lud06: other.lud06.or(self.lud06),
+            lud16: other.lud16.or(self.lud16),
+            custom,
+        }
+    }
+
+    /// Check picture and banner urls with `policy`, removing the invalid ones
+    pub fn sanitize_images(self, policy: &ImageUrlPolicy) -> Self {
+        let check = |url: Option<String>| -> Option<String> {
+            let url: String = url?;
+            match policy.check(&url) {
+                Ok(url) => Some(url.to_string()),
+                Err(e) => {
+                    log::debug!("Removed image url {url}: {e}");
+                    None
+                }
+            }
+        };
+        Self {
+            picture: check(self.picture),
+            banner: check(self.banner),
+            ..self
+        }
+    }
+
     /// Set name
     pub fn name<S>(self, name: S) -> Self
     where
@@ -170,6 +299,15 @@ impl Metadata {
             ..self
         }
     }
+
+    /// Set custom field
+    pub fn custom_field<S>(mut self, key: S, value: Value) -> Self
+    where
+        S: Into<String>,
+    {
+        self.custom.insert(key.into(), value);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -188,4 +326,91 @@ mod tests {
                 .display_name("")
         );
     }
+
+    #[test]
+    fn test_sanitize_images() {
+        let metadata = Metadata {
+            picture: Some(String::from(" https://example.com/avatar.png ")),
+            banner: Some(String::from("http://example.com/banner.png")),
+            ..Metadata::new().name("myname")
+        };
+        let sanitized = metadata.clone().sanitize_images(&ImageUrlPolicy::new());
+        assert_eq!(
+            sanitized.picture.as_deref(),
+            Some("https://example.com/avatar.png")
+        );
+        assert_eq!(sanitized.banner, None);
+        assert_eq!(sanitized.name.as_deref(), Some("myname"));
+
+        let policy = ImageUrlPolicy::new().allowed_schemes(["https", "HTTP"]);
+        assert!(metadata.sanitize_images(&policy).banner.is_some());
+
+        let data_uri = "data:image/png;base64,iVBORw0KGgo=";
+        assert!(matches!(
+            ImageUrlPolicy::new().check(data_uri),
+            Err(Error::SchemeNotAllowed(_))
+        ));
+        assert!(ImageUrlPolicy::new()
+            .allowed_schemes(["data"])
+            .check(data_uri)
+            .is_err());
+        assert!(ImageUrlPolicy::new()
+            .allow_data_uri(true)
+            .check(data_uri)
+            .is_ok());
+        assert!(matches!(
+            ImageUrlPolicy::new()
+                .max_length(20)
+                .check("https://example.com/avatar.png"),
+            Err(Error::ImageUrlTooLong(30))
+        ));
+        assert!(matches!(
+            ImageUrlPolicy::new().check("not a url"),
+            Err(Error::InvalidImageUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let current = Metadata::new()
+            .name("myname")
+            .about("Description")
+            .lud16("me@example.com");
+        let update = Metadata::new()
+            .about("New description")
+            .nip05("me@example.com");
+        assert_eq!(
+            current.merge(update),
+            Metadata::new()
+                .name("myname")
+                .about("New description")
+                .nip05("me@example.com")
+                .lud16("me@example.com")
+        );
+    }
+
+    #[test]
+    fn test_custom_fields() {
+        let content = r#"{"name":"myname","bot":true,"pronouns":"they/them"}"#;
+        let metadata = Metadata::from_json(content).unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("myname"));
+        assert_eq!(metadata.custom.get("bot"), Some(&Value::Bool(true)));
+        assert_eq!(
+            Metadata::from_json(metadata.as_json().unwrap()).unwrap(),
+            metadata
+        );
+
+        // Unknown fields survive a merge, unless overwritten
+        let update = Metadata::new()
+            .about("Description")
+            .custom_field("bot", Value::Bool(false));
+        assert_eq!(
+            metadata.merge(update),
+            Metadata::new()
+                .name("myname")
+                .about("Description")
+                .custom_field("bot", Value::Bool(false))
+                .custom_field("pronouns", Value::from("they/them"))
+        );
+    }
 }
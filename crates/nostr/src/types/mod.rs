@@ -3,14 +3,23 @@
 
 //! Types
 
+pub mod backup;
 pub mod contact;
+pub mod coordinate;
+pub mod display_name;
 pub mod entity;
+pub mod impersonation;
 pub mod metadata;
 pub mod profile;
+pub mod relay_url;
 pub mod time;
 
-pub use self::contact::Contact;
+pub use self::backup::Backup;
+pub use self::contact::{Contact, ContactList};
+pub use self::coordinate::Coordinate;
 pub use self::entity::Entity;
-pub use self::metadata::Metadata;
+pub use self::impersonation::ImpersonationWarning;
+pub use self::metadata::{ImageUrlPolicy, Metadata};
 pub use self::profile::Profile;
+pub use self::relay_url::UncheckedUrl;
 pub use self::time::Timestamp;
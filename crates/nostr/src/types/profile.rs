@@ -3,27 +3,92 @@
 
 //! Profile
 
-use bitcoin::secp256k1::XOnlyPublicKey;
+use secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::relay_url::{self, UncheckedUrl};
 
 /// Profile
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct Profile {
     /// Public key
     pub public_key: XOnlyPublicKey,
-    /// Relays
-    pub relays: Vec<String>,
+    /// Relay hints, not validated (see [`UncheckedUrl::parse`])
+    pub relays: Vec<UncheckedUrl>,
 }
 
 impl Profile {
     /// New [`Profile`]
+    ///
+    /// Relay hints are kept as given, without validation.
     pub fn new<S>(public_key: XOnlyPublicKey, relays: Vec<S>) -> Self
     where
-        S: Into<String>,
+        S: Into<UncheckedUrl>,
+    {
+        Self {
+            public_key,
+            relays: relays.into_iter().map(|relay| relay.into()).collect(),
+        }
+    }
+
+    /// New [`Profile`] with relay urls
+    ///
+    /// Fail if any relay is not a `ws` or `wss` url.
+    pub fn with_relays(
+        public_key: XOnlyPublicKey,
+        relays: Vec<Url>,
+    ) -> Result<Self, relay_url::Error> {
+        Ok(Self {
+            public_key,
+            relays: relays
+                .into_iter()
+                .map(|relay| relay_url::check(relay).map(UncheckedUrl::from))
+                .collect::<Result<Vec<UncheckedUrl>, _>>()?,
+        })
+    }
+
+    /// New [`Profile`] from relay strings
+    ///
+    /// Fail if any relay is not a valid `ws` or `wss` url.
+    pub fn try_new<S>(public_key: XOnlyPublicKey, relays: Vec<S>) -> Result<Self, relay_url::Error>
+    where
+        S: AsRef<str>,
     {
+        Ok(Self {
+            public_key,
+            relays: relays
+                .into_iter()
+                .map(|relay| relay_url::parse(relay).map(UncheckedUrl::from))
+                .collect::<Result<Vec<UncheckedUrl>, _>>()?,
+        })
+    }
+
+    /// Relay hints as strings
+    pub fn relay_strs(&self) -> Vec<String> {
+        self.relays.iter().map(|relay| relay.to_string()).collect()
+    }
+
+    /// Valid relay hints, validated and normalized (see [`UncheckedUrl::parse`])
+    pub fn relay_urls(&self) -> Vec<Url> {
+        self.relays
+            .iter()
+            .filter_map(|relay| relay.parse().ok())
+            .collect()
+    }
+}
+
+impl From<XOnlyPublicKey> for Profile {
+    fn from(public_key: XOnlyPublicKey) -> Self {
         Self {
             public_key,
-            relays: relays.into_iter().map(|u| u.into()).collect(),
+            relays: Vec::new(),
         }
     }
 }
+
+impl From<Profile> for XOnlyPublicKey {
+    fn from(profile: Profile) -> Self {
+        profile.public_key
+    }
+}
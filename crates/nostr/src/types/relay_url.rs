@@ -0,0 +1,229 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay URL
+//!
+//! Normalization of relay URLs, so that the same relay has a single representation
+//! (ex. `wss://relay.damus.io`, `wss://Relay.Damus.io:443/` and `wss://relay.damus.io#x` are the same relay).
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::{ParseError, Url};
+
+/// Relay URL error
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    /// Url parse error
+    #[error(transparent)]
+    Url(#[from] ParseError),
+    /// Scheme not `ws` or `wss`
+    #[error("unsupported scheme: {0}")]
+    UnsupportedScheme(String),
+    /// Url without host
+    #[error("url without host")]
+    NoHost,
+}
+
+/// Parse and [`normalize`] relay URL
+///
+/// The URL must have the `ws` or `wss` scheme and a host.
+pub fn parse<S>(url: S) -> Result<Url, Error>
+where
+    S: AsRef<str>,
+{
+    check(Url::parse(url.as_ref().trim())?)
+}
+
+/// Check that an already parsed URL is a relay URL and [`normalize`] it
+///
+/// The URL must have the `ws` or `wss` scheme and a host.
+pub fn check(url: Url) -> Result<Url, Error> {
+    match url.scheme() {
+        "ws" | "wss" => (),
+        scheme => return Err(Error::UnsupportedScheme(scheme.to_string())),
+    }
+    if url.host_str().map_or(true, str::is_empty) {
+        return Err(Error::NoHost);
+    }
+    Ok(normalize(url))
+}
+
+/// Normalize relay URL
+///
+/// Parsing a [`Url`] already lowercases scheme and host, encodes international hosts with punycode
+/// and strips the default ports. On top of that, the fragment, an empty query and the trailing
+/// slashes of the path are removed (the root path stays `/`, as required by [`Url`]).
+pub fn normalize(mut url: Url) -> Url {
+    url.set_fragment(None);
+    if url.query() == Some("") {
+        url.set_query(None);
+    }
+    let path: &str = url.path();
+    if path.len() > 1 && path.ends_with('/') {
+        let path: String = path.trim_end_matches('/').to_string();
+        url.set_path(&path);
+    }
+    url
+}
+
+/// Normalize relay URL string, without trailing slash (ex. `wss://relay.damus.io`)
+///
+/// Used where relay URLs are shared as strings (ex. tags and `nprofile`).
+/// Invalid URLs are returned trimmed but otherwise unchanged.
+pub fn normalize_str<S>(url: S) -> String
+where
+    S: AsRef<str>,
+{
+    let url: &str = url.as_ref().trim();
+    match parse(url) {
+        Ok(url) => to_string(&url),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Relay URL as string, without the trailing slash of the root path
+pub fn to_string(url: &Url) -> String {
+    let url: String = url.to_string();
+    match url.strip_suffix('/') {
+        Some(stripped) if !stripped.ends_with('/') => stripped.to_string(),
+        _ => url,
+    }
+}
+
+/// Relay URL as received (ex. relay hint of a tag), not validated yet
+///
+/// Use [`UncheckedUrl::parse`] (or [`TryFrom`]) to get a validated and normalized relay [`Url`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UncheckedUrl(String);
+
+impl UncheckedUrl {
+    /// New [`UncheckedUrl`]
+    pub fn new<S>(url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(url.into())
+    }
+
+    /// Get as `&str`
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Validate and [`normalize`] relay URL
+    pub fn parse(&self) -> Result<Url, Error> {
+        parse(&self.0)
+    }
+}
+
+impl From<&str> for UncheckedUrl {
+    fn from(url: &str) -> Self {
+        Self::new(url)
+    }
+}
+
+impl From<String> for UncheckedUrl {
+    fn from(url: String) -> Self {
+        Self(url)
+    }
+}
+
+impl From<Url> for UncheckedUrl {
+    fn from(url: Url) -> Self {
+        Self(to_string(&url))
+    }
+}
+
+impl From<UncheckedUrl> for String {
+    fn from(url: UncheckedUrl) -> Self {
+        url.0
+    }
+}
+
+impl TryFrom<UncheckedUrl> for Url {
+    type Error = Error;
+
+    fn try_from(url: UncheckedUrl) -> Result<Self, Self::Error> {
+        url.parse()
+    }
+}
+
+impl fmt::Display for UncheckedUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let expected: Url = Url::parse("wss://relay.damus.io").unwrap();
+        for url in [
+            "wss://relay.damus.io",
+            "wss://relay.damus.io/",
+            " WSS://Relay.Damus.IO:443 ",
+            "wss://relay.damus.io/#fragment",
+            "wss://relay.damus.io?",
+        ] {
+            assert_eq!(parse(url).unwrap(), expected);
+        }
+
+        assert_eq!(
+            parse("ws://relay.example.com:80/nostr//").unwrap().as_str(),
+            "ws://relay.example.com/nostr"
+        );
+        assert_eq!(
+            parse("wss://relay.example.com:4848").unwrap().as_str(),
+            "wss://relay.example.com:4848/"
+        );
+        assert_eq!(
+            parse("wss://réseau.example").unwrap().as_str(),
+            "wss://xn--rseau-bsa.example/"
+        );
+        assert_eq!(
+            parse("https://relay.damus.io"),
+            Err(Error::UnsupportedScheme(String::from("https")))
+        );
+        assert_eq!(
+            check(Url::parse("wss://relay.damus.io/#fragment").unwrap()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            check(Url::parse("https://relay.damus.io").unwrap()),
+            Err(Error::UnsupportedScheme(String::from("https")))
+        );
+
+        assert_eq!(
+            normalize_str("wss://relay.damus.io/"),
+            "wss://relay.damus.io"
+        );
+        assert_eq!(
+            normalize_str("wss://relay.example.com/nostr/"),
+            "wss://relay.example.com/nostr"
+        );
+        assert_eq!(normalize_str(" not a url "), "not a url");
+    }
+    #[test]
+    fn test_unchecked_url() {
+        let url = UncheckedUrl::from("wss://relay.damus.io/");
+        assert_eq!(url.as_str(), "wss://relay.damus.io/");
+        assert_eq!(
+            Url::try_from(url).unwrap(),
+            Url::parse("wss://relay.damus.io").unwrap()
+        );
+        assert_eq!(
+            UncheckedUrl::from(Url::parse("wss://relay.damus.io").unwrap()).to_string(),
+            "wss://relay.damus.io"
+        );
+        assert!(UncheckedUrl::from("not a url").parse().is_err());
+        assert_eq!(
+            serde_json::to_string(&UncheckedUrl::from("wss://relay.damus.io")).unwrap(),
+            r#""wss://relay.damus.io""#
+        );
+    }
+}
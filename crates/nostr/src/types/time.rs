@@ -6,6 +6,7 @@
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{
+    fmt,
     ops::{Add, Sub},
     str::FromStr,
 };
@@ -55,9 +56,9 @@ impl FromStr for Timestamp {
     }
 }
 
-impl ToString for Timestamp {
-    fn to_string(&self) -> String {
-        self.0.to_string()
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 